@@ -0,0 +1,484 @@
+//! `--batch-summary`: one comparison row per model among many files, e.g. the quant
+//! variants of one model in a folder. Each model is loaded on its own and dropped before
+//! the next, so no tensors are shared between rows and memory stays at one model's headers.
+//! `--aggregate` walks a whole collection the same way for an inventory with grand totals.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::check::split_set_name;
+use crate::index::find_index;
+use crate::model::ModelDescription;
+use crate::report::Report;
+use crate::summary::{
+    declared_from_config, declared_from_gguf, infer_architecture, language_model_parts,
+    rope_from_config, rope_from_gguf,
+};
+use crate::utils::{
+    display_width, fit_to_width, format_parameters, format_size, format_thousands, model_extension,
+};
+
+/// The files of one model: a single file, the parts of a split GGUF or sharded safetensors
+/// checkpoint (`model-00001-of-00003`), or every safetensors file in a directory with a
+/// `*.safetensors.index.json`
+#[derive(Debug, Clone)]
+pub struct ModelGroup {
+    pub name: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// Group `files` into models, keeping the order of each group's first file
+pub fn group_models(files: &[PathBuf]) -> Vec<ModelGroup> {
+    let mut groups: BTreeMap<(PathBuf, String), Vec<PathBuf>> = BTreeMap::new();
+    let mut order = Vec::new();
+    for file in files {
+        let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        let extension = model_extension(file).unwrap_or_default();
+        let name = if extension == "safetensors" && find_index(&dir).is_some() {
+            String::new()
+        } else {
+            format!("{}.{extension}", split_set_name(file))
+        };
+        let key = (dir, name);
+        if !groups.contains_key(&key) {
+            order.push(key.clone());
+        }
+        groups.entry(key).or_default().push(file.clone());
+    }
+    order
+        .into_iter()
+        .map(|key| {
+            let files = groups.remove(&key).unwrap_or_default();
+            let name = match (&key.1, files.as_slice()) {
+                (_, [single]) => single.display().to_string(),
+                // An indexed checkpoint is named after its directory
+                (set, _) if set.is_empty() => format!("{}/", key.0.display()),
+                (set, _) => key.0.join(set).display().to_string(),
+            };
+            ModelGroup { name, files }
+        })
+        .collect()
+}
+
+/// One model of the comparison
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchRow {
+    pub name: String,
+    pub files: usize,
+    /// Size of the model's files on disk
+    pub file_size: u64,
+    pub parameters: usize,
+    /// Tensor data bits per parameter
+    pub bits_per_weight: f64,
+    /// Dtype or quant type holding the most parameters, e.g. `Q4_K`
+    pub dominant_type: Option<String>,
+    pub context_length: Option<usize>,
+    pub architecture: Option<String>,
+}
+
+/// The comparison row of a loaded model. The language model's metadata is read before the
+/// sidecar `config.json`, and tensor names are the fallback for the architecture.
+pub fn batch_row(name: &str, model: &ModelDescription) -> BatchRow {
+    let (tensors, metadata) = language_model_parts(model);
+    let tensor_bytes: usize = model.tensors.iter().map(|t| t.size_bytes).sum();
+    let architecture = declared_from_gguf(&metadata)
+        .and_then(|params| params.family)
+        .or_else(|| {
+            model
+                .config
+                .as_ref()
+                .and_then(|config| declared_from_config(config).family)
+        })
+        .or_else(|| infer_architecture(&tensors).family);
+    let context_length = rope_from_gguf(&metadata)
+        .and_then(|rope| rope.context_length)
+        .or_else(|| {
+            model
+                .config
+                .as_ref()
+                .and_then(|config| rope_from_config(config).context_length)
+        });
+    BatchRow {
+        name: name.to_string(),
+        files: model.files.len(),
+        file_size: model.files.iter().map(|f| f.size).sum(),
+        parameters: model.total_parameters,
+        bits_per_weight: tensor_bytes as f64 * 8.0 / model.total_parameters.max(1) as f64,
+        dominant_type: model
+            .parameters_by_dtype()
+            .into_iter()
+            .next()
+            .map(|(dtype, _)| dtype),
+        context_length,
+        architecture,
+    }
+}
+
+/// A model that could not be loaded
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFailure {
+    pub name: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchReport {
+    /// Smallest on disk first
+    pub models: Vec<BatchRow>,
+    pub failed: Vec<BatchFailure>,
+}
+
+impl BatchReport {
+    pub fn push(&mut self, row: BatchRow) {
+        let at = self
+            .models
+            .partition_point(|m| (m.file_size, &m.name) <= (row.file_size, &row.name));
+        self.models.insert(at, row);
+    }
+}
+
+impl Report for BatchReport {
+    fn text_lines(&self) -> Vec<String> {
+        let name_width = self
+            .models
+            .iter()
+            .map(|m| display_width(&m.name))
+            .chain([5])
+            .max()
+            .unwrap_or(5);
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        let mut lines = vec![format!(
+            "{}  {:>10}  {:>8}  {:>6}  {:>8}  {:>8}  Architecture",
+            fit_to_width("Model", name_width, false),
+            "Size",
+            "Params",
+            "BPW",
+            "Type",
+            "Context"
+        )];
+        for model in &self.models {
+            lines.push(format!(
+                "{}  {:>10}  {:>8}  {:>6.2}  {:>8}  {:>8}  {}",
+                fit_to_width(&model.name, name_width, false),
+                format_size(model.file_size as usize),
+                format_parameters(model.parameters),
+                model.bits_per_weight,
+                optional(&model.dominant_type),
+                optional(&model.context_length.map(format_thousands)),
+                optional(&model.architecture)
+            ));
+        }
+        if !self.failed.is_empty() {
+            lines.push(String::new());
+            for failure in &self.failed {
+                lines.push(format!("Failed: {}: {}", failure.name, failure.error));
+            }
+        }
+        lines
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &[
+            "model",
+            "files",
+            "file_size",
+            "parameters",
+            "bits_per_weight",
+            "dominant_type",
+            "context_length",
+            "architecture",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        self.models
+            .iter()
+            .map(|model| {
+                vec![
+                    model.name.clone(),
+                    model.files.to_string(),
+                    model.file_size.to_string(),
+                    model.parameters.to_string(),
+                    format!("{:.3}", model.bits_per_weight),
+                    optional(model.dominant_type.clone()),
+                    optional(model.context_length.map(|c| c.to_string())),
+                    optional(model.architecture.clone()),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// One model of the `--aggregate` inventory
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryRow {
+    pub name: String,
+    /// File format, or formats joined with `+` for a mixed checkpoint
+    pub format: String,
+    pub files: usize,
+    pub file_size: u64,
+    pub parameters: usize,
+    pub dominant_type: Option<String>,
+    pub architecture: Option<String>,
+    /// The first model inventoried with the same architecture and parameter count
+    pub duplicate_of: Option<String>,
+}
+
+/// Disk usage of the files of one format
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FormatTotal {
+    pub models: usize,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Models sharing an architecture and parameter count, such as copies or quantizations
+/// of one model
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub architecture: String,
+    pub parameters: usize,
+    pub models: Vec<String>,
+    /// Disk usage of the whole group
+    pub bytes: u64,
+}
+
+/// `--aggregate`: rows and grand totals built one model at a time, so only the rows stay
+/// in memory, never the models' headers
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InventoryReport {
+    pub models: Vec<InventoryRow>,
+    pub total_files: usize,
+    pub total_bytes: u64,
+    pub by_format: BTreeMap<String, FormatTotal>,
+    /// Tensor data bytes per dtype or quant type over every model
+    pub by_type: BTreeMap<String, u64>,
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Models that could not be read, and why
+    pub errors: Vec<BatchFailure>,
+    /// Files of the models that could not be read
+    pub error_files: usize,
+}
+
+impl InventoryReport {
+    /// Count a loaded model into the rows and totals
+    pub fn add(&mut self, name: &str, model: &ModelDescription) {
+        let architecture = batch_row(name, model).architecture;
+        let mut formats: Vec<&str> = Vec::new();
+        for file in &model.files {
+            if !formats.contains(&file.format) {
+                formats.push(file.format);
+            }
+            let total = self.by_format.entry(file.format.to_string()).or_default();
+            total.files += 1;
+            total.bytes += file.size;
+        }
+        for format in &formats {
+            if let Some(total) = self.by_format.get_mut(*format) {
+                total.models += 1;
+            }
+        }
+        for tensor in &model.tensors {
+            *self.by_type.entry(tensor.dtype.clone()).or_default() += tensor.size_bytes as u64;
+        }
+
+        let file_size = model.files.iter().map(|f| f.size).sum();
+        self.total_files += model.files.len();
+        self.total_bytes += file_size;
+        let duplicate_of = (model.total_parameters > 0)
+            .then(|| {
+                self.models.iter().find(|other| {
+                    other.duplicate_of.is_none()
+                        && other.parameters == model.total_parameters
+                        && other.architecture == architecture
+                })
+            })
+            .flatten()
+            .map(|original| original.name.clone());
+        self.models.push(InventoryRow {
+            name: name.to_string(),
+            format: formats.join("+"),
+            files: model.files.len(),
+            file_size,
+            parameters: model.total_parameters,
+            dominant_type: model
+                .parameters_by_dtype()
+                .into_iter()
+                .next()
+                .map(|(dtype, _)| dtype),
+            architecture,
+            duplicate_of,
+        });
+    }
+
+    /// Count a model that could not be read as an error
+    pub fn fail(&mut self, name: &str, files: usize, error: String) {
+        self.error_files += files;
+        self.errors.push(BatchFailure {
+            name: name.to_string(),
+            error,
+        });
+    }
+
+    /// Group the duplicates once every model is in
+    pub fn finish(&mut self) {
+        self.duplicates = self
+            .models
+            .iter()
+            .filter(|original| original.duplicate_of.is_none())
+            .filter_map(|original| {
+                let copies: Vec<&InventoryRow> = self
+                    .models
+                    .iter()
+                    .filter(|m| m.duplicate_of.as_ref() == Some(&original.name))
+                    .collect();
+                if copies.is_empty() {
+                    return None;
+                }
+                Some(DuplicateGroup {
+                    architecture: original
+                        .architecture
+                        .clone()
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    parameters: original.parameters,
+                    models: std::iter::once(original)
+                        .chain(copies.iter().copied())
+                        .map(|m| m.name.clone())
+                        .collect(),
+                    bytes: original.file_size + copies.iter().map(|m| m.file_size).sum::<u64>(),
+                })
+            })
+            .collect();
+    }
+}
+
+impl Report for InventoryReport {
+    fn text_lines(&self) -> Vec<String> {
+        let name_width = self
+            .models
+            .iter()
+            .map(|m| display_width(&m.name))
+            .chain([5])
+            .max()
+            .unwrap_or(5);
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        let mut lines = vec![format!(
+            "{}  {:<12}  {:>10}  {:>8}  {:>8}  Architecture",
+            fit_to_width("Model", name_width, false),
+            "Format",
+            "Size",
+            "Params",
+            "Type"
+        )];
+        for model in &self.models {
+            let duplicate = model
+                .duplicate_of
+                .as_ref()
+                .map(|original| format!("  (duplicate of {original})"))
+                .unwrap_or_default();
+            lines.push(format!(
+                "{}  {:<12}  {:>10}  {:>8}  {:>8}  {}{duplicate}",
+                fit_to_width(&model.name, name_width, false),
+                model.format,
+                format_size(model.file_size as usize),
+                format_parameters(model.parameters),
+                optional(&model.dominant_type),
+                optional(&model.architecture)
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push(format!(
+            "Total: {} models in {} files, {}{}",
+            format_thousands(self.models.len()),
+            format_thousands(self.total_files),
+            format_size(self.total_bytes as usize),
+            match self.errors.len() {
+                0 => String::new(),
+                errors => format!(
+                    "; {errors} model{} ({} file{}) could not be read",
+                    if errors == 1 { "" } else { "s" },
+                    self.error_files,
+                    if self.error_files == 1 { "" } else { "s" }
+                ),
+            }
+        ));
+        lines.push(String::new());
+        lines.push("By format:".to_string());
+        for (format, total) in &self.by_format {
+            lines.push(format!(
+                "  {format:<12}  {:>10}  {} models, {} files",
+                format_size(total.bytes as usize),
+                format_thousands(total.models),
+                format_thousands(total.files)
+            ));
+        }
+        let mut by_type: Vec<(&String, &u64)> = self.by_type.iter().collect();
+        by_type.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        if !by_type.is_empty() {
+            lines.push(String::new());
+            lines.push("By type (tensor data):".to_string());
+            for (dtype, bytes) in by_type {
+                lines.push(format!(
+                    "  {dtype:<12}  {:>10}",
+                    format_size(*bytes as usize)
+                ));
+            }
+        }
+        if !self.duplicates.is_empty() {
+            lines.push(String::new());
+            lines.push("Duplicates (same architecture and parameter count):".to_string());
+            for group in &self.duplicates {
+                lines.push(format!(
+                    "  {} {}, {} models, {}: {}",
+                    group.architecture,
+                    format_parameters(group.parameters),
+                    group.models.len(),
+                    format_size(group.bytes as usize),
+                    group.models.join(", ")
+                ));
+            }
+        }
+        if !self.errors.is_empty() {
+            lines.push(String::new());
+            for failure in &self.errors {
+                lines.push(format!("Failed: {}: {}", failure.name, failure.error));
+            }
+        }
+        lines
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &[
+            "model",
+            "format",
+            "files",
+            "file_size",
+            "parameters",
+            "dominant_type",
+            "architecture",
+            "duplicate_of",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        self.models
+            .iter()
+            .map(|model| {
+                vec![
+                    model.name.clone(),
+                    model.format.clone(),
+                    model.files.to_string(),
+                    model.file_size.to_string(),
+                    model.parameters.to_string(),
+                    optional(model.dominant_type.clone()),
+                    optional(model.architecture.clone()),
+                    optional(model.duplicate_of.clone()),
+                ]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,255 @@
+//! `--bench`: load the files several times without the TUI and report where the time
+//! goes, per file and in total.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::model::ModelDescription;
+use crate::report::Report;
+use crate::tree::{GroupBy, TensorInfo, TreeBuilder, TreeLimits};
+use crate::utils::{display_file_name, display_width, fit_to_width, format_thousands};
+
+/// Fastest and median of the runs of one measurement, in milliseconds
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Timing {
+    pub min_ms: f64,
+    pub median_ms: f64,
+}
+
+impl Timing {
+    fn from_samples(samples: &mut [Duration]) -> Self {
+        samples.sort();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let median = match samples.len() {
+            0 => 0.0,
+            n if n % 2 == 1 => ms(samples[n / 2]),
+            n => (ms(samples[n / 2 - 1]) + ms(samples[n / 2])) / 2.0,
+        };
+        Timing {
+            min_ms: samples.first().map_or(0.0, |&d| ms(d)),
+            median_ms: median,
+        }
+    }
+
+    fn label(&self) -> String {
+        format!("{:.3} / {:.3}", self.min_ms, self.median_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileBench {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Header bytes the loader took from the file; None for formats it doesn't count
+    pub bytes_read: Option<u64>,
+    /// Reading the header or metadata section
+    pub header: Option<Timing>,
+    /// Turning the tensor records into tensors
+    pub tensor_info: Option<Timing>,
+    /// The whole load of this file
+    pub parse: Timing,
+    /// Grouping this file's tensors into a tree on their own
+    pub tree: Timing,
+    /// Header bytes per second of median parse time, in MB/s
+    pub throughput_mb_s: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub iterations: usize,
+    pub files: Vec<FileBench>,
+    pub bytes_read: u64,
+    /// Loading every file, including sidecars and deduplication
+    pub load: Timing,
+    /// Building the tree of the merged model, as the TUI does on start
+    pub tree: Timing,
+}
+
+/// Per-file samples of one measurement across iterations
+#[derive(Default)]
+struct Samples {
+    header: Vec<Duration>,
+    tensor_info: Vec<Duration>,
+    parse: Vec<Duration>,
+    tree: Vec<Duration>,
+}
+
+/// Load `files` `iterations` times (at least once) and collect the timings
+pub fn run_bench(files: &[PathBuf], iterations: usize) -> Result<BenchReport> {
+    let iterations = iterations.max(1);
+    let mut samples: Vec<Samples> = files.iter().map(|_| Samples::default()).collect();
+    let (mut load, mut tree) = (Vec::new(), Vec::new());
+    let mut last = None;
+    for _ in 0..iterations {
+        let started = Instant::now();
+        let model = ModelDescription::load(files)?;
+        load.push(started.elapsed());
+
+        let started = Instant::now();
+        let _ = TreeBuilder::build_tree_mixed(
+            &model.tensors,
+            &model.metadata,
+            GroupBy::Prefix,
+            TreeLimits::default(),
+        );
+        tree.push(started.elapsed());
+
+        for (record, samples) in model.files.iter().zip(&mut samples) {
+            samples.parse.push(record.parse_time);
+            samples.header.extend(record.header_time);
+            samples.tensor_info.extend(record.tensor_info_time);
+            let own = file_tensors(&model.tensors, &record.path);
+            let started = Instant::now();
+            let _ = TreeBuilder::build_tree(&own, TreeLimits::default());
+            samples.tree.push(started.elapsed());
+        }
+        last = Some(model);
+    }
+
+    let model = last.expect("at least one iteration");
+    let files = model
+        .files
+        .iter()
+        .zip(&mut samples)
+        .map(|(record, samples)| {
+            let parse = Timing::from_samples(&mut samples.parse);
+            let phase = |samples: &mut Vec<Duration>| {
+                (!samples.is_empty()).then(|| Timing::from_samples(samples))
+            };
+            FileBench {
+                path: record.path.clone(),
+                size: record.size,
+                bytes_read: record.bytes_read,
+                header: phase(&mut samples.header),
+                tensor_info: phase(&mut samples.tensor_info),
+                parse,
+                tree: Timing::from_samples(&mut samples.tree),
+                throughput_mb_s: record
+                    .bytes_read
+                    .filter(|_| parse.median_ms > 0.0)
+                    .map(|bytes| bytes as f64 / 1e6 / (parse.median_ms / 1000.0)),
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(BenchReport {
+        iterations,
+        bytes_read: files.iter().filter_map(|f| f.bytes_read).sum(),
+        files,
+        load: Timing::from_samples(&mut load),
+        tree: Timing::from_samples(&mut tree),
+    })
+}
+
+/// Tensors read from `path`, in the merged model's order
+fn file_tensors(tensors: &[TensorInfo], path: &Path) -> Vec<TensorInfo> {
+    tensors
+        .iter()
+        .filter(|t| t.source_file == path)
+        .cloned()
+        .collect()
+}
+
+impl Report for BenchReport {
+    fn text_lines(&self) -> Vec<String> {
+        let name_width = self
+            .files
+            .iter()
+            .map(|f| display_width(&display_file_name(&f.path)))
+            .chain([5])
+            .max()
+            .unwrap_or(5);
+        let optional =
+            |timing: Option<Timing>| timing.map_or_else(|| "-".to_string(), |t| t.label());
+        let mut lines = vec![
+            format!(
+                "{} file{}, {} iteration{}; times in ms as min / median",
+                self.files.len(),
+                if self.files.len() == 1 { "" } else { "s" },
+                self.iterations,
+                if self.iterations == 1 { "" } else { "s" }
+            ),
+            String::new(),
+            format!(
+                "{}  {:>12}  {:>17}  {:>17}  {:>17}  {:>17}  {:>12}",
+                fit_to_width("File", name_width, false),
+                "Bytes read",
+                "Header",
+                "Tensor info",
+                "Parse",
+                "Tree",
+                "MB/s"
+            ),
+        ];
+        for file in &self.files {
+            lines.push(format!(
+                "{}  {:>12}  {:>17}  {:>17}  {:>17}  {:>17}  {:>12}",
+                fit_to_width(&display_file_name(&file.path), name_width, false),
+                file.bytes_read
+                    .map_or_else(|| "-".to_string(), |b| format_thousands(b as usize)),
+                optional(file.header),
+                optional(file.tensor_info),
+                file.parse.label(),
+                file.tree.label(),
+                file.throughput_mb_s
+                    .map_or_else(|| "-".to_string(), |t| format!("{t:.1}")),
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "Total: {} header bytes read | load {} ms | merged tree {} ms",
+            format_thousands(self.bytes_read as usize),
+            self.load.label(),
+            self.tree.label()
+        ));
+        lines
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &[
+            "file",
+            "size",
+            "bytes_read",
+            "header_min_ms",
+            "header_median_ms",
+            "tensor_info_min_ms",
+            "tensor_info_median_ms",
+            "parse_min_ms",
+            "parse_median_ms",
+            "tree_min_ms",
+            "tree_median_ms",
+            "throughput_mb_s",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let ms = |value: f64| format!("{value:.3}");
+        let pair = |timing: Option<Timing>| match timing {
+            Some(t) => [ms(t.min_ms), ms(t.median_ms)],
+            None => [String::new(), String::new()],
+        };
+        self.files
+            .iter()
+            .map(|file| {
+                let [header_min, header_median] = pair(file.header);
+                let [info_min, info_median] = pair(file.tensor_info);
+                vec![
+                    file.path.display().to_string(),
+                    file.size.to_string(),
+                    file.bytes_read.map_or_else(String::new, |b| b.to_string()),
+                    header_min,
+                    header_median,
+                    info_min,
+                    info_median,
+                    ms(file.parse.min_ms),
+                    ms(file.parse.median_ms),
+                    ms(file.tree.min_ms),
+                    ms(file.tree.median_ms),
+                    file.throughput_mb_s
+                        .map_or_else(String::new, |t| format!("{t:.1}")),
+                ]
+            })
+            .collect()
+    }
+}
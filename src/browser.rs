@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::tree::natural_sort_key;
+use crate::utils::model_extension;
+
+/// A row of the file browser: a directory to enter or a model file to open
+#[derive(Debug, Clone)]
+pub struct BrowserEntry {
+    pub path: PathBuf,
+    pub name: String,
+    /// None for directories
+    pub size: Option<u64>,
+}
+
+impl BrowserEntry {
+    pub fn is_dir(&self) -> bool {
+        self.size.is_none()
+    }
+}
+
+/// The directories and supported model files in `dir`, directories first, each in natural
+/// name order. Entries starting with a dot are left out unless `hidden`.
+pub fn list_dir(dir: &Path, hidden: bool) -> Result<Vec<BrowserEntry>> {
+    let mut entries: Vec<BrowserEntry> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && !hidden {
+                return None;
+            }
+            // Follows symlinks, so linked model directories can be entered
+            let metadata = fs::metadata(entry.path()).ok()?;
+            let size = if metadata.is_dir() {
+                None
+            } else {
+                model_extension(&entry.path())
+                    .filter(|ext| crate::SUPPORTED_EXTENSIONS.contains(ext))?;
+                Some(metadata.len())
+            };
+            Some(BrowserEntry {
+                path: entry.path(),
+                name,
+                size,
+            })
+        })
+        .collect();
+    entries.sort_by_cached_key(|entry| (!entry.is_dir(), natural_sort_key(&entry.name)));
+    Ok(entries)
+}
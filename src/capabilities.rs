@@ -0,0 +1,64 @@
+//! What the terminal can do, decided once at startup: colors (off under `NO_COLOR` or on a
+//! dumb terminal), emoji (off on dumb terminals and the Linux console), and whether the
+//! explorer can run at all (a terminal on stdin and stdout with raw mode and cursor
+//! addressing).
+
+use crossterm::terminal;
+use std::io::IsTerminal;
+
+use crate::ui::Theme;
+
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    pub color: bool,
+    pub emoji: bool,
+    /// Why the explorer can't take over the terminal; None when it can
+    pub not_interactive: Option<String>,
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let dumb = term == "dumb";
+        // https://no-color.org: set to anything but the empty string
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Capabilities {
+            color: !dumb && !no_color,
+            emoji: !dumb && term != "linux",
+            not_interactive: Self::interactive_problem(&term),
+        }
+    }
+
+    fn interactive_problem(term: &str) -> Option<String> {
+        if !std::io::stdout().is_terminal() {
+            return Some("stdout is not a terminal".to_string());
+        }
+        if !std::io::stdin().is_terminal() {
+            return Some("stdin is not a terminal".to_string());
+        }
+        if term == "dumb" {
+            return Some("TERM=dumb has no cursor addressing".to_string());
+        }
+        if !cfg!(windows) && term.is_empty() {
+            return Some("TERM is not set".to_string());
+        }
+        if let Err(e) = terminal::size() {
+            return Some(format!("the terminal size is unknown ({e})"));
+        }
+        match terminal::enable_raw_mode() {
+            Ok(()) => terminal::disable_raw_mode()
+                .err()
+                .map(|e| format!("raw mode failed ({e})")),
+            Err(e) => Some(format!("raw mode is not available ({e})")),
+        }
+    }
+
+    /// Theme when neither the command line nor the config file picks one
+    pub fn default_theme(&self) -> Theme {
+        if self.color {
+            Theme::default()
+        } else {
+            Theme::Mono
+        }
+    }
+}
@@ -0,0 +1,898 @@
+//! Consistency checks run by `--check`. Each one compares facts a checkpoint records in
+//! more than one place and reports whether they agree.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::companion;
+use crate::expected::{self, Gap};
+use crate::model::{FileRecord, GgufSplit, ModelDescription};
+use crate::report::Report;
+use crate::size_label;
+use crate::summary::{self, metadata_array_len, metadata_string, metadata_usize};
+use crate::tree::{MetadataInfo, TensorInfo, composition_label, dtype_composition};
+use crate::utils::{
+    DOWNLOAD_SUFFIXES, display_file_name, format_shape, format_size, format_thousands,
+};
+
+/// Embedding rows are usually padded up to a multiple of this (or of 128)
+const VOCAB_PADDING: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The check passed
+    Info,
+    /// Unusual but most likely harmless
+    Warning,
+    /// The checkpoint is probably broken
+    Error,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "ok",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// Which check produced the finding, e.g. `vocab`
+    pub check: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// Full name of the tensor the finding is about, which the report view can jump to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub related_node_path: Option<String>,
+    /// Counts and byte sizes behind the message, e.g. `nan_count`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<&'static str, u64>,
+}
+
+/// Outcome of `--check`, one finding per check that had something to compare
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    pub findings: Vec<Finding>,
+}
+
+impl CheckReport {
+    /// The most severe finding's severity; None when no check applied
+    pub fn worst(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+}
+
+impl Report for CheckReport {
+    fn text_lines(&self) -> Vec<String> {
+        if self.findings.is_empty() {
+            return vec!["Nothing to check: no check applies to these files".to_string()];
+        }
+        self.findings
+            .iter()
+            .map(|f| format!("{:<8}{}: {}", f.severity.label(), f.check, f.message))
+            .collect()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["check", "severity", "message"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.findings
+            .iter()
+            .map(|f| {
+                vec![
+                    f.check.to_string(),
+                    f.severity.label().to_string(),
+                    f.message.clone(),
+                ]
+            })
+            .collect()
+    }
+
+    fn findings(&self) -> Vec<Finding> {
+        self.findings.clone()
+    }
+}
+
+/// Write findings to `path` as a JSON array, as the report view's export does
+pub fn write_findings(findings: &[Finding], path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(findings)? + "\n";
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn run_checks(model: &ModelDescription) -> CheckReport {
+    let mut findings: Vec<Finding> = [vocab_check(model), size_label_check(model)]
+        .into_iter()
+        .flatten()
+        .collect();
+    findings.extend(shard_check(model));
+    findings.extend(download_check(model));
+    findings.extend(split_check(model));
+    findings.extend(companion_check(model));
+    findings.extend(expected_check(model));
+    // Layers of an incomplete file are audited by their headers, not reported missing;
+    // companions have layers of their own
+    let mut tensors: Vec<TensorInfo> = companion::base_tensors(model).cloned().collect();
+    tensors.extend(model.cut_off_tensors.iter().cloned());
+    findings.extend(layer_audit(&tensors));
+    CheckReport { findings }
+}
+
+/// Whether each draft model and adapter fits the base model loaded with it
+pub fn companion_check(model: &ModelDescription) -> Vec<Finding> {
+    if companion::base_tensors(model).next().is_none() {
+        return Vec::new();
+    }
+    model
+        .companions
+        .iter()
+        .filter_map(|c| {
+            let (fits, message) = companion::companion_fit(model, c)?;
+            Some(Finding {
+                check: "companion",
+                severity: if fits {
+                    Severity::Info
+                } else {
+                    Severity::Warning
+                },
+                message: format!("{} {message}", companion::describe(c)),
+                related_node_path: None,
+                values: BTreeMap::new(),
+            })
+        })
+        .collect()
+}
+
+/// Compare the base model's tensor names with the ones llama.cpp loads for its
+/// `general.architecture` and `block_count`: a missing tensor is an error, as llama.cpp
+/// refuses the file, and one it doesn't know a warning, as it is either misnamed or from a
+/// variant the table lacks. Expected tensors whose data the file cut off count as present
+/// but are errors of their own. One finding per missing, unexpected or truncated name, with
+/// the layers it is in, or one saying every expected tensor is there.
+pub fn expected_check(model: &ModelDescription) -> Vec<Finding> {
+    let metadata: Vec<MetadataInfo> = model
+        .metadata
+        .iter()
+        .filter(|m| !companion::metadata_in_companion(&model.companions, m))
+        .cloned()
+        .collect();
+    let Some(architecture) = metadata_string(&metadata, "general.architecture") else {
+        return Vec::new();
+    };
+    // Cut-off tensors are in the header, just not in the file yet
+    let names: Vec<&str> = companion::base_tensors(model)
+        .chain(&model.cut_off_tensors)
+        .map(|t| t.name.as_str())
+        .collect();
+    let layers = metadata_usize(&metadata, &format!("{architecture}.block_count"))
+        .unwrap_or_else(|| expected::block_count(&names));
+    let Some(checklist) = expected::checklist(&architecture, layers, &names) else {
+        return Vec::new();
+    };
+    let where_in = |gap: &Gap| match gap.layers.len() {
+        0 => String::new(),
+        n if n == layers && n > 1 => format!(" in all {n} layers"),
+        _ => format!(" in {}", format_layers(&gap.layers)),
+    };
+    let mut findings: Vec<Finding> = checklist
+        .missing
+        .iter()
+        .map(|gap| Finding {
+            check: "expected",
+            severity: Severity::Error,
+            message: format!(
+                "{} is missing{}; llama.cpp needs it for {architecture}",
+                gap.name,
+                where_in(gap)
+            ),
+            related_node_path: None,
+            values: BTreeMap::new(),
+        })
+        .collect();
+    findings.extend(checklist.unexpected.iter().map(|gap| Finding {
+        check: "expected",
+        severity: Severity::Warning,
+        message: format!(
+            "{}{} is not a tensor llama.cpp loads for {architecture}; misnamed, or from a variant it doesn't know",
+            gap.name,
+            where_in(gap)
+        ),
+        related_node_path: gap.example.clone(),
+        values: BTreeMap::new(),
+    }));
+    let cut_off: Vec<&str> = model
+        .cut_off_tensors
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect();
+    findings.extend(
+        checklist
+            .expected_among(&cut_off)
+            .iter()
+            .map(|gap| Finding {
+                check: "expected",
+                severity: Severity::Error,
+                message: format!(
+                    "{}{} is present but truncated; the file ends before its data",
+                    gap.name,
+                    where_in(gap)
+                ),
+                // Cut-off tensors are not in the tree
+                related_node_path: None,
+                values: BTreeMap::new(),
+            }),
+    );
+    if findings.is_empty() {
+        findings.push(Finding {
+            check: "expected",
+            severity: Severity::Info,
+            message: format!(
+                "All {} tensors llama.cpp loads for {architecture} with {layers} layers are present, and no others",
+                checklist.required
+            ),
+            related_node_path: None,
+            values: BTreeMap::new(),
+        });
+    }
+    findings
+}
+
+/// One error per file whose tensor data runs past its end, with the bytes missing
+pub fn download_check(model: &ModelDescription) -> Vec<Finding> {
+    model
+        .files
+        .iter()
+        .filter_map(|record| {
+            let incomplete = record.incomplete?;
+            Some(Finding {
+                check: "download",
+                severity: Severity::Error,
+                message: format!(
+                    "{} is truncated: {} bytes missing ({} of {} present); {} of {} tensors complete",
+                    display_file_name(&record.path),
+                    format_thousands(incomplete.missing_bytes as usize),
+                    format_size(record.size as usize),
+                    format_size(incomplete.expected_size as usize),
+                    format_thousands(incomplete.present_tensors),
+                    format_thousands(incomplete.present_tensors + incomplete.cut_tensors)
+                ),
+                related_node_path: None,
+                values: BTreeMap::new(),
+            })
+        })
+        .collect()
+}
+
+/// Compare the computed parameter count with the size in `general.size_label` and the
+/// file or directory name; None when neither names a size
+pub fn size_label_check(model: &ModelDescription) -> Option<Finding> {
+    let check = size_label::size_check(model)?;
+    let verdict = check.verdict()?;
+    let severity = if check.disagreements().next().is_some() {
+        Severity::Warning
+    } else {
+        Severity::Info
+    };
+    Some(Finding {
+        check: "size",
+        severity,
+        message: format!("{} computed; {verdict}", check.label()),
+        related_node_path: None,
+        values: BTreeMap::new(),
+    })
+}
+
+/// Compare the dtype mix of the shards in each directory, going by the file each tensor was
+/// read from: a shard re-exported in another dtype leaves layers 0-15 in BF16 and 16-31 in
+/// F16. One finding per directory of two or more shards; GGUF splits, whose quantization
+/// mixes vary on purpose, and companion files such as vision projectors are skipped.
+pub fn shard_check(model: &ModelDescription) -> Vec<Finding> {
+    let mut by_dir: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+    for record in &model.files {
+        if record.tensors == 0
+            || record.format == "GGUF"
+            || model.mmproj_files.contains(&record.path)
+            || model.companions.iter().any(|c| c.file == record.path)
+        {
+            continue;
+        }
+        let dir = record.path.parent().unwrap_or(Path::new(""));
+        by_dir.entry(dir).or_default().push(&record.path);
+    }
+
+    let mut findings = Vec::new();
+    for shards in by_dir.values().filter(|shards| shards.len() >= 2) {
+        let mixes: Vec<(&Path, Vec<_>)> = shards
+            .iter()
+            .map(|&path| {
+                let tensors = model.tensors.iter().filter(|t| t.source_file == path);
+                (path, dtype_composition(tensors))
+            })
+            .collect();
+        let dominant = |mix: &[crate::tree::DtypeShare]| mix.first().map(|s| s.dtype.clone());
+        let dominants: BTreeSet<String> =
+            mixes.iter().filter_map(|(_, mix)| dominant(mix)).collect();
+        let histogram = mixes
+            .iter()
+            .map(|(path, mix)| format!("{} {}", display_file_name(path), composition_label(mix, 3)))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let (severity, message) = if dominants.len() > 1 {
+            (
+                Severity::Warning,
+                format!(
+                    "shards disagree on the dominant dtype ({}): {histogram}",
+                    dominants.into_iter().collect::<Vec<_>>().join(" vs ")
+                ),
+            )
+        } else {
+            (
+                Severity::Info,
+                format!(
+                    "{} shards are mostly {}: {histogram}",
+                    shards.len(),
+                    dominants.into_iter().next().unwrap_or_default()
+                ),
+            )
+        };
+        // Point at the first tensor of the first shard that differs from the first shard
+        let related = mixes
+            .iter()
+            .find(|(_, mix)| dominant(mix) != dominant(&mixes[0].1))
+            .and_then(|(path, _)| model.tensors.iter().find(|t| t.source_file == *path))
+            .map(|t| t.name.clone());
+        findings.push(Finding {
+            check: "shards",
+            severity,
+            message,
+            related_node_path: related,
+            values: BTreeMap::new(),
+        });
+    }
+    findings
+}
+
+/// Name shared by the files of one split GGUF: `model` for `model-00001-of-00009.gguf`;
+/// the whole stem for files not named that way
+pub fn split_set_name(path: &Path) -> String {
+    // A part still being downloaded (`model-00002-of-00003.gguf.part`) belongs to the set
+    let path = match path.extension().and_then(|s| s.to_str()) {
+        Some(extension) if DOWNLOAD_SUFFIXES.contains(&extension) => {
+            Path::new(path.file_stem().unwrap_or_default())
+        }
+        _ => path,
+    };
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let mut parts = stem.rsplitn(4, '-');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(count), Some("of"), Some(no), Some(rest)) if digits(count) && digits(no) => {
+            rest.to_string()
+        }
+        _ => stem,
+    }
+}
+
+/// `3`, `3 and 7`, `2, 3 and 7`
+fn and_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [init @ .., last] => format!("{} and {last}", init.join(", ")),
+    }
+}
+
+/// Validate each set of split GGUF files (those with `split.*` keys): one `split.count`
+/// for all of them, every part from 1 to the count present exactly once, the tensors of
+/// all parts adding up to `split.tensors.count`, and no tensor name in two parts. Parts
+/// are numbered from 1 as in the file names, although `split.no` counts from 0.
+pub fn split_check(model: &ModelDescription) -> Vec<Finding> {
+    let mut sets: BTreeMap<(&Path, String), Vec<&FileRecord>> = BTreeMap::new();
+    for record in &model.files {
+        if record.split.is_some() {
+            let dir = record.path.parent().unwrap_or(Path::new(""));
+            sets.entry((dir, split_set_name(&record.path)))
+                .or_default()
+                .push(record);
+        }
+    }
+
+    let mut findings = Vec::new();
+    for ((_, name), records) in sets {
+        let mut problems: Vec<(String, Option<String>)> = Vec::new();
+        let splits: Vec<(&FileRecord, GgufSplit)> = records
+            .iter()
+            .filter_map(|&record| Some((record, record.split?)))
+            .collect();
+        let files_with = |matches: &dyn Fn(&GgufSplit) -> bool| {
+            splits
+                .iter()
+                .filter(|(_, split)| matches(split))
+                .map(|(record, _)| display_file_name(&record.path))
+                .collect::<Vec<_>>()
+        };
+
+        // The count most files agree on, the larger one on a tie
+        let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+        for (_, split) in &splits {
+            *counts.entry(split.count).or_default() += 1;
+        }
+        let count = counts
+            .iter()
+            .max_by_key(|&(count, files)| (files, count))
+            .map_or(0, |(&count, _)| count);
+        if counts.len() > 1 {
+            let claims: Vec<String> = counts
+                .keys()
+                .map(|&c| format!("{c} in {}", files_with(&|s| s.count == c).join(", ")))
+                .collect();
+            problems.push((
+                format!("split.count disagrees across parts: {}", claims.join("; ")),
+                None,
+            ));
+        }
+
+        let mut parts: BTreeMap<u64, usize> = BTreeMap::new();
+        for (_, split) in &splits {
+            *parts.entry(split.no).or_default() += 1;
+        }
+        for (&no, &files) in &parts {
+            if no >= count {
+                problems.push((
+                    format!(
+                        "{} claims part {} of {count}",
+                        files_with(&|s| s.no == no).join(", "),
+                        no + 1
+                    ),
+                    None,
+                ));
+            } else if files > 1 {
+                problems.push((
+                    format!(
+                        "part {} of {count} appears {files} times: {}",
+                        no + 1,
+                        files_with(&|s| s.no == no).join(", ")
+                    ),
+                    None,
+                ));
+            }
+        }
+        let missing: Vec<String> = (0..count)
+            .filter(|no| !parts.contains_key(no))
+            .map(|no| (no + 1).to_string())
+            .collect();
+        if !missing.is_empty() {
+            problems.push((
+                format!(
+                    "missing part{} {} of {count}",
+                    if missing.len() == 1 { "" } else { "s" },
+                    and_list(&missing)
+                ),
+                None,
+            ));
+        }
+
+        let declared: BTreeSet<u64> = splits.iter().filter_map(|(_, s)| s.tensors_count).collect();
+        let tensors: usize = records.iter().map(|record| record.tensors).sum();
+        if declared.len() > 1 {
+            let claims: Vec<String> = declared
+                .iter()
+                .map(|&n| {
+                    let files = files_with(&|s| s.tensors_count == Some(n));
+                    format!("{} in {}", format_thousands(n as usize), files.join(", "))
+                })
+                .collect();
+            problems.push((
+                format!(
+                    "split.tensors.count disagrees across parts: {}",
+                    claims.join("; ")
+                ),
+                None,
+            ));
+        } else if let Some(&expected) = declared.first()
+            && tensors as u64 != expected
+        {
+            problems.push((
+                format!(
+                    "the parts hold {} tensors but split.tensors.count is {}",
+                    format_thousands(tensors),
+                    format_thousands(expected as usize)
+                ),
+                None,
+            ));
+        }
+
+        let in_set = |path: &Path| records.iter().any(|record| record.path == path);
+        let repeated: Vec<(&str, String)> = model
+            .shadowed_tensors
+            .iter()
+            .filter(|(_, dropped)| in_set(dropped))
+            .filter_map(|(tensor, dropped)| {
+                let kept = model.tensors.iter().find(|t| t.name == *tensor)?;
+                in_set(&kept.source_file).then(|| {
+                    let files = format!(
+                        "{} and {}",
+                        display_file_name(&kept.source_file),
+                        display_file_name(dropped)
+                    );
+                    (tensor.as_str(), files)
+                })
+            })
+            .collect();
+        if !repeated.is_empty() {
+            let shown: Vec<String> = repeated
+                .iter()
+                .take(3)
+                .map(|(tensor, files)| format!("{tensor} ({files})"))
+                .collect();
+            let more = repeated.len().saturating_sub(shown.len());
+            problems.push((
+                format!(
+                    "{} tensor name{} in more than one part: {}{}",
+                    format_thousands(repeated.len()),
+                    if repeated.len() == 1 { "" } else { "s" },
+                    shown.join(", "),
+                    if more > 0 {
+                        format!(" and {more} more")
+                    } else {
+                        String::new()
+                    }
+                ),
+                Some(repeated[0].0.to_string()),
+            ));
+        }
+
+        if problems.is_empty() {
+            findings.push(Finding {
+                check: "split",
+                severity: Severity::Info,
+                message: format!(
+                    "{name}: all {count} parts present, {} tensors",
+                    format_thousands(tensors)
+                ),
+                related_node_path: None,
+                values: BTreeMap::new(),
+            });
+        }
+        for (message, related) in problems {
+            findings.push(Finding {
+                check: "split",
+                severity: Severity::Error,
+                message: format!("{name}: {message}"),
+                related_node_path: related,
+                values: BTreeMap::new(),
+            });
+        }
+    }
+    findings
+}
+
+/// A vocab size and where it was read from
+struct VocabSource {
+    label: String,
+    size: usize,
+    /// Rows of an embedding or output tensor rather than a metadata value
+    tensor: bool,
+}
+
+/// Cross-check the tokenizer's token count, any declared `vocab_size` and the rows of
+/// the embedding and output tensors; None when fewer than two of them are known
+pub fn vocab_check(model: &ModelDescription) -> Option<Finding> {
+    let (tensors, metadata) = summary::language_model_parts(model);
+    let mut sources = Vec::new();
+    let mut add = |label: String, size: Option<usize>, tensor: bool| {
+        if let Some(size) = size {
+            sources.push(VocabSource {
+                label,
+                size,
+                tensor,
+            });
+        }
+    };
+
+    add(
+        "tokenizer.ggml.tokens".to_string(),
+        metadata_array_len(&metadata, "tokenizer.ggml.tokens"),
+        false,
+    );
+    add(
+        "tokenizer.json".to_string(),
+        metadata_usize(&metadata, "tokenizer.vocab_size"),
+        false,
+    );
+    if let Some(arch) = metadata_string(&metadata, "general.architecture") {
+        let key = format!("{arch}.vocab_size");
+        let size = metadata_usize(&metadata, &key);
+        add(key, size, false);
+    }
+    if let Some(config) = &model.config {
+        add(
+            "config.json vocab_size".to_string(),
+            summary::declared_from_config(config).vocab_size,
+            false,
+        );
+    }
+    let rows = |tensor: Option<&TensorInfo>| {
+        tensor.and_then(|t| match t.shape.as_slice() {
+            [rows, _] => Some((format!("{} rows", t.name), *rows)),
+            _ => None,
+        })
+    };
+    let embedding = summary::token_embedding(&tensors);
+    if let Some((label, size)) = rows(embedding) {
+        add(label, Some(size), true);
+    }
+    let output = summary::output_head(&tensors);
+    if let Some((label, size)) = rows(output) {
+        add(label, Some(size), true);
+    }
+
+    if sources.len() < 2 {
+        return None;
+    }
+    let listing = sources
+        .iter()
+        .map(|s| format!("{} {}", s.label, format_thousands(s.size)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (severity, verdict) = vocab_verdict(&sources);
+    let message = match verdict {
+        Some(verdict) => format!("{listing}: {verdict}"),
+        None => format!("{listing} agree"),
+    };
+    Some(Finding {
+        check: "vocab",
+        severity,
+        message,
+        related_node_path: embedding.or(output).map(|t| t.name.clone()),
+        values: BTreeMap::new(),
+    })
+}
+
+/// Severity and explanation of a set of vocab sizes; no explanation when they all agree
+fn vocab_verdict(sources: &[VocabSource]) -> (Severity, Option<String>) {
+    let sizes = |tensor: bool| sources.iter().filter(move |s| s.tensor == tensor);
+    let Some(first) = sources.first() else {
+        return (Severity::Info, None);
+    };
+    if sources.iter().all(|s| s.size == first.size) {
+        return (Severity::Info, None);
+    }
+
+    let rows: Vec<usize> = sizes(true).map(|s| s.size).collect();
+    let declared_max = sizes(false).map(|s| s.size).max().unwrap_or(0);
+    let declared_min = sizes(false).map(|s| s.size).min().unwrap_or(0);
+    let Some(&table) = rows.iter().max() else {
+        return (
+            Severity::Warning,
+            Some("the metadata disagrees on the vocab size".to_string()),
+        );
+    };
+    if rows.iter().any(|&r| r != table) {
+        return (
+            Severity::Error,
+            Some("the embedding and output tensors have different row counts".to_string()),
+        );
+    }
+    if declared_max > table {
+        return (
+            Severity::Error,
+            Some(format!(
+                "{} token ids have no embedding row",
+                format_thousands(declared_max - table)
+            )),
+        );
+    }
+    let extra = table - declared_min;
+    if table.is_multiple_of(VOCAB_PADDING) {
+        let multiple = if table.is_multiple_of(VOCAB_PADDING * 2) {
+            VOCAB_PADDING * 2
+        } else {
+            VOCAB_PADDING
+        };
+        (
+            Severity::Warning,
+            Some(format!(
+                "{} extra row{}, likely padding to a multiple of {multiple}",
+                format_thousands(extra),
+                if extra == 1 { "" } else { "s" }
+            )),
+        )
+    } else {
+        (
+            Severity::Error,
+            Some(format!(
+                "{} rows more than the vocab, and not padding to a multiple of {VOCAB_PADDING}",
+                format_thousands(extra)
+            )),
+        )
+    }
+}
+
+/// The tensors of one stack of numbered layers, e.g. `model.layers` or `blk`
+#[derive(Default)]
+struct LayerStack<'a> {
+    layers: BTreeSet<usize>,
+    /// Name with the stack and layer index removed, e.g. `self_attn.q_proj.weight`
+    roles: BTreeMap<String, Vec<(usize, &'a TensorInfo)>>,
+}
+
+/// Compare every layer of each stack role by role: a role should appear in every layer
+/// with the same shape and dtype. Missing tensors and differing shapes usually mean a
+/// truncated download or a bad merge. One finding per role that disagrees, or one per
+/// stack when all of it agrees; stacks of a single layer are skipped.
+pub fn layer_audit(tensors: &[TensorInfo]) -> Vec<Finding> {
+    let mut stacks: BTreeMap<String, LayerStack> = BTreeMap::new();
+    for tensor in tensors {
+        if let Some((stack, index, role)) = summary::split_layer_name(&tensor.name) {
+            let stack = stacks.entry(stack).or_default();
+            stack.layers.insert(index);
+            stack.roles.entry(role).or_default().push((index, tensor));
+        }
+    }
+
+    let mut findings = Vec::new();
+    for (name, stack) in &stacks {
+        if stack.layers.len() < 2 {
+            continue;
+        }
+        let before = findings.len();
+        for (role, entries) in &stack.roles {
+            if let Some((severity, problems, focus)) = audit_role(&stack.layers, entries) {
+                let related = entries
+                    .iter()
+                    .find(|(index, _)| Some(*index) == focus)
+                    .or(entries.first());
+                findings.push(Finding {
+                    check: "layers",
+                    severity,
+                    message: format!("{name}.*.{role}: {problems}"),
+                    related_node_path: related.map(|(_, t)| t.name.clone()),
+                    values: BTreeMap::new(),
+                });
+            }
+        }
+        if findings.len() == before {
+            findings.push(Finding {
+                check: "layers",
+                severity: Severity::Info,
+                message: format!(
+                    "{} layers of {name} agree on {} tensor role{}",
+                    stack.layers.len(),
+                    stack.roles.len(),
+                    if stack.roles.len() == 1 { "" } else { "s" }
+                ),
+                related_node_path: None,
+                values: BTreeMap::new(),
+            });
+        }
+    }
+    findings
+}
+
+/// What is wrong with one role across the layers of its stack, how bad it is, and the
+/// first layer whose tensor differs from the rest
+fn audit_role(
+    layers: &BTreeSet<usize>,
+    entries: &[(usize, &TensorInfo)],
+) -> Option<(Severity, String, Option<usize>)> {
+    let present: BTreeSet<usize> = entries.iter().map(|(index, _)| *index).collect();
+    let mut problems = Vec::new();
+    let mut severity = Severity::Info;
+
+    // A role most layers have is missing from the others; one only a few have is extra
+    if present.len() * 2 >= layers.len() {
+        let missing: Vec<usize> = layers.difference(&present).copied().collect();
+        if !missing.is_empty() {
+            severity = Severity::Error;
+            problems.push(format!("missing in {}", format_layers(&missing)));
+        }
+    } else {
+        severity = Severity::Warning;
+        let extra: Vec<usize> = present.iter().copied().collect();
+        problems.push(format!(
+            "only in {} of {} layers",
+            format_layers(&extra),
+            layers.len()
+        ));
+    }
+
+    let (shapes, odd_shape) = odd_ones_out(entries, |t| format_shape(&t.shape));
+    if !shapes.is_empty() {
+        severity = Severity::Error;
+        problems.extend(shapes);
+    }
+    // GGUF quantization mixes pick a type per layer on purpose
+    let (dtypes, odd_dtype) = odd_ones_out(entries, |t| t.dtype.clone());
+    if !dtypes.is_empty() {
+        let deliberate = entries.iter().all(|(_, t)| t.storage_shape.is_some());
+        if !deliberate {
+            severity = severity.max(Severity::Warning);
+        }
+        problems.extend(dtypes);
+    }
+
+    (!problems.is_empty()).then(|| (severity, problems.join("; "), odd_shape.or(odd_dtype)))
+}
+
+/// Layers whose `key` differs from the most common one, e.g. `(4096, 1024) in layer 7
+/// instead of (4096, 4096)`, and the lowest of them; empty when all agree. When the odd
+/// layers all come from a shard none of the usual ones do, it is named, since a re-exported
+/// shard is the usual cause.
+fn odd_ones_out(
+    entries: &[(usize, &TensorInfo)],
+    key: impl Fn(&TensorInfo) -> String,
+) -> (Vec<String>, Option<usize>) {
+    let mut by_value: Vec<(String, Vec<usize>, BTreeSet<&Path>)> = Vec::new();
+    for (index, tensor) in entries {
+        let value = key(tensor);
+        let file = tensor.source_file.as_path();
+        match by_value.iter_mut().find(|(v, _, _)| *v == value) {
+            Some((_, indices, files)) => {
+                indices.push(*index);
+                files.insert(file);
+            }
+            None => by_value.push((value, vec![*index], BTreeSet::from([file]))),
+        }
+    }
+    if by_value.len() < 2 {
+        return (Vec::new(), None);
+    }
+    by_value.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.1.cmp(&b.1)));
+    let (usual, _, usual_files) = &by_value[0];
+    let first = by_value[1..]
+        .iter()
+        .flat_map(|(_, indices, _)| indices)
+        .min();
+    let problems = by_value[1..]
+        .iter()
+        .map(|(value, indices, files)| {
+            let mut problem = format!("{value} in {} instead of {usual}", format_layers(indices));
+            if let [file] = Vec::from_iter(files).as_slice()
+                && !usual_files.contains(*file)
+            {
+                problem.push_str(&format!(", all from {}", display_file_name(file)));
+            }
+            problem
+        })
+        .collect();
+    (problems, first.copied())
+}
+
+/// `layer 3` or `layers 0-2, 5`
+fn format_layers(indices: &[usize]) -> String {
+    let mut sorted = indices.to_vec();
+    sorted.sort_unstable();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for index in sorted {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == index => *end = index,
+            _ => ranges.push((index, index)),
+        }
+    }
+    let listing = ranges
+        .iter()
+        .map(|&(start, end)| match end - start {
+            0 => start.to_string(),
+            1 => format!("{start}, {end}"),
+            _ => format!("{start}-{end}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if indices.len() == 1 {
+        format!("layer {listing}")
+    } else {
+        format!("layers {listing}")
+    }
+}
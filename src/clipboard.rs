@@ -0,0 +1,105 @@
+//! Copying text to the system clipboard: through the platform's clipboard tool when one is
+//! installed, otherwise with an OSC 52 escape, which most terminals (and tmux, and ssh
+//! sessions) pass on to the local clipboard.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// How the text reached the clipboard, for the status line
+pub enum Method {
+    /// A clipboard tool such as `wl-copy` or `pbcopy`
+    Tool(&'static str),
+    /// An OSC 52 escape written to the terminal
+    Osc52,
+}
+
+impl Method {
+    pub fn label(&self) -> String {
+        match self {
+            Method::Tool(tool) => tool.to_string(),
+            Method::Osc52 => "OSC 52".to_string(),
+        }
+    }
+}
+
+/// Clipboard tools to try, with their arguments, in order of preference
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+    let mut tools: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+    if cfg!(target_os = "macos") {
+        tools.push(("pbcopy", &[]));
+    }
+    if cfg!(windows) {
+        tools.push(("clip.exe", &[]));
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(("wl-copy", &[]));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        tools.push(("xclip", &["-selection", "clipboard"]));
+        tools.push(("xsel", &["--clipboard", "--input"]));
+    }
+    tools
+}
+
+/// Pipe `text` into `tool`; false when it isn't installed or fails
+fn copy_with(tool: &str, args: &[&str], text: &str) -> bool {
+    let Ok(mut child) = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    child.wait().is_ok_and(|status| status.success()) && written
+}
+
+/// Put `text` on the clipboard
+pub fn copy(text: &str) -> Result<Method> {
+    for (tool, args) in tools() {
+        if copy_with(tool, args, text) {
+            return Ok(Method::Tool(tool));
+        }
+    }
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    // tmux only forwards escapes wrapped in a passthrough sequence
+    let sequence = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    };
+    let mut stdout = std::io::stdout();
+    stdout
+        .write_all(sequence.as_bytes())
+        .and_then(|_| stdout.flush())
+        .context("Failed to write the OSC 52 sequence")?;
+    Ok(Method::Osc52)
+}
+
+/// Standard base64 with padding, as OSC 52 expects
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,337 @@
+//! Files loaded next to a model that are models of their own: a vision projector
+//! (`mmproj-*.gguf`), a small draft model for speculative decoding, or a LoRA adapter
+//! (`adapter_model.safetensors`). Each stays a labeled component beside the base model
+//! instead of having its tensors merged into the base model's names, and is checked
+//! against it.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::model::ModelDescription;
+use crate::summary::{self, infer_architecture, metadata_string, metadata_usize};
+use crate::tree::{MetadataInfo, TensorInfo};
+use crate::utils::display_file_name;
+
+/// What a companion file is to the base model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompanionKind {
+    Projector,
+    Draft,
+    Adapter,
+}
+
+impl CompanionKind {
+    /// Name of the component in the tree and the summary
+    pub fn label(self) -> &'static str {
+        match self {
+            CompanionKind::Projector => "Vision projector (mmproj)",
+            CompanionKind::Draft => "Draft model",
+            CompanionKind::Adapter => "Adapter",
+        }
+    }
+}
+
+/// A loaded file taken for a companion of the base model, with why
+#[derive(Debug, Clone, Serialize)]
+pub struct Companion {
+    pub kind: CompanionKind,
+    pub file: PathBuf,
+    pub reason: String,
+}
+
+impl Companion {
+    /// Metadata group of the companion's keys, so its `general.*` keys don't mix with the
+    /// base model's
+    pub fn source(&self) -> String {
+        match self.kind {
+            CompanionKind::Projector => summary::mmproj_source(&self.file),
+            CompanionKind::Draft => format!("Draft model: {}", display_file_name(&self.file)),
+            CompanionKind::Adapter => format!("Adapter: {}", display_file_name(&self.file)),
+        }
+    }
+
+    /// True when a metadata entry was read from (or computed for) this file
+    pub fn owns_metadata(&self, entry: &MetadataInfo) -> bool {
+        entry.source.as_deref().is_some_and(|source| {
+            source == self.source() || source == summary::file_info_source(&self.file)
+        })
+    }
+}
+
+/// Name of the base model's component: "Language model" next to projectors alone
+pub fn base_label(companions: &[Companion]) -> &'static str {
+    if companions
+        .iter()
+        .all(|c| c.kind == CompanionKind::Projector)
+    {
+        "Language model"
+    } else {
+        "Base model"
+    }
+}
+
+/// Name of a companion's component, with its file name when several are of its kind
+pub fn component_label(companions: &[Companion], companion: &Companion) -> String {
+    let same_kind = companions
+        .iter()
+        .filter(|c| c.kind == companion.kind)
+        .count();
+    if same_kind > 1 {
+        format!(
+            "{}: {}",
+            companion.kind.label(),
+            display_file_name(&companion.file)
+        )
+    } else {
+        companion.kind.label().to_string()
+    }
+}
+
+/// The companion owning a tensor, if any
+pub fn companion_of<'a>(companions: &'a [Companion], tensor: &TensorInfo) -> Option<&'a Companion> {
+    companions.iter().find(|c| c.file == tensor.source_file)
+}
+
+/// True when a metadata entry belongs to one of `companions`
+pub fn metadata_in_companion(companions: &[Companion], entry: &MetadataInfo) -> bool {
+    companions.iter().any(|c| c.owns_metadata(entry))
+}
+
+/// Whether a tensor name is one half of a LoRA pair: `q_proj.lora_A.weight` (PEFT),
+/// `attn_q.weight.lora_a` (llama.cpp) or `lora_down`/`lora_up` (kohya)
+fn is_lora_tensor(name: &str) -> bool {
+    [
+        ".lora_A.",
+        ".lora_B.",
+        ".lora_a",
+        ".lora_b",
+        ".lora_down.",
+        ".lora_up.",
+    ]
+    .iter()
+    .any(|marker| name.contains(marker))
+}
+
+/// What a file is to the model loaded with it, from its name, its metadata and its tensor
+/// names alone; drafts that only show as a smaller model of the same architecture are
+/// found by [`find_drafts`] once every file is read
+pub fn classify(
+    path: &Path,
+    metadata: &[MetadataInfo],
+    tensors: &[TensorInfo],
+) -> Option<(CompanionKind, String)> {
+    let name = display_file_name(path).to_lowercase();
+    if summary::is_mmproj(path, metadata) {
+        let reason = if metadata_string(metadata, "general.architecture").as_deref() == Some("clip")
+        {
+            "clip architecture"
+        } else {
+            "mmproj file name"
+        };
+        return Some((CompanionKind::Projector, reason.to_string()));
+    }
+    if metadata_string(metadata, "general.type").as_deref() == Some("adapter") {
+        let reason = match metadata_string(metadata, "general.base_model.0.name") {
+            Some(base) => format!("general.type adapter, base model {base}"),
+            None => "general.type adapter".to_string(),
+        };
+        return Some((CompanionKind::Adapter, reason));
+    }
+    if name.starts_with("adapter_model") {
+        return Some((
+            CompanionKind::Adapter,
+            "adapter_model file name".to_string(),
+        ));
+    }
+    if !tensors.is_empty() && tensors.iter().all(|t| is_lora_tensor(&t.name)) {
+        return Some((CompanionKind::Adapter, "LoRA tensor names".to_string()));
+    }
+    if name.contains("draft") {
+        return Some((CompanionKind::Draft, "draft file name".to_string()));
+    }
+    None
+}
+
+/// Architecture and width of a GGUF model that isn't part of a split, for telling drafts
+/// from the model they serve
+#[derive(Debug, Clone)]
+pub struct GgufShape {
+    pub file: PathBuf,
+    pub architecture: String,
+    pub embedding_length: usize,
+}
+
+impl GgufShape {
+    pub fn of(file: &Path, metadata: &[MetadataInfo]) -> Option<Self> {
+        if metadata_usize(metadata, "split.count").is_some_and(|count| count > 1) {
+            return None;
+        }
+        let architecture = metadata_string(metadata, "general.architecture")?;
+        let embedding_length =
+            metadata_usize(metadata, &format!("{architecture}.embedding_length"))?;
+        Some(Self {
+            file: file.to_path_buf(),
+            architecture,
+            embedding_length,
+        })
+    }
+}
+
+/// GGUF models next to a wider model of the same architecture, such as Qwen2.5-0.5B next
+/// to Qwen2.5-7B: the narrower ones are drafts. Quantizations of one model share a width,
+/// so they are never taken for drafts of each other.
+pub fn find_drafts(shapes: &[GgufShape]) -> Vec<(PathBuf, String)> {
+    shapes
+        .iter()
+        .filter_map(|shape| {
+            let base = shapes
+                .iter()
+                .filter(|other| {
+                    other.architecture == shape.architecture
+                        && other.embedding_length > shape.embedding_length
+                })
+                .max_by_key(|other| other.embedding_length)?;
+            Some((
+                shape.file.clone(),
+                format!(
+                    "{} model {} wide next to {} ({} wide)",
+                    shape.architecture,
+                    shape.embedding_length,
+                    display_file_name(&base.file),
+                    base.embedding_length
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// The base tensor a LoRA tensor adapts: `base_model.model.model.layers.0.q_proj.lora_A.weight`
+/// and `blk.0.attn_q.weight.lora_a` both name the weight of the module before the LoRA
+/// part. None for names that don't follow the PEFT or llama.cpp layout.
+pub fn adapter_target(name: &str) -> Option<String> {
+    if let Some(base) = name
+        .strip_suffix(".lora_a")
+        .or_else(|| name.strip_suffix(".lora_b"))
+    {
+        return Some(base.to_string());
+    }
+    let module = name
+        .split_once(".lora_A.")
+        .or_else(|| name.split_once(".lora_B."))?
+        .0;
+    let module = module.strip_prefix("base_model.model.").unwrap_or(module);
+    Some(format!("{module}.weight"))
+}
+
+/// Tensors of the base model: every tensor not read from a companion
+pub fn base_tensors(model: &ModelDescription) -> impl Iterator<Item = &TensorInfo> {
+    model
+        .tensors
+        .iter()
+        .filter(|t| companion_of(&model.companions, t).is_none())
+}
+
+/// Tensors read from `companion`'s file
+pub fn companion_tensors<'a>(
+    model: &'a ModelDescription,
+    companion: &'a Companion,
+) -> impl Iterator<Item = &'a TensorInfo> {
+    model
+        .tensors
+        .iter()
+        .filter(|t| t.source_file == companion.file)
+}
+
+/// Modules an adapter targets and how many of them the base model lacks, with the first
+/// missing one; None when the adapter's names follow no known layout
+pub fn adapter_targets(
+    model: &ModelDescription,
+    adapter: &Companion,
+) -> Option<(usize, usize, Option<String>)> {
+    let targets: HashSet<String> = companion_tensors(model, adapter)
+        .filter_map(|t| adapter_target(&t.name))
+        .collect();
+    if targets.is_empty() {
+        return None;
+    }
+    let base: HashSet<&str> = base_tensors(model).map(|t| t.name.as_str()).collect();
+    let mut missing: Vec<&String> = targets
+        .iter()
+        .filter(|target| !base.contains(target.as_str()))
+        .collect();
+    missing.sort();
+    Some((
+        targets.len(),
+        missing.len(),
+        missing.first().map(|name| name.to_string()),
+    ))
+}
+
+/// How a draft or adapter fits the base model, as the rest of a sentence naming it
+/// ("targets 12 modules, all in the base model"), and whether it fits. None for projectors,
+/// checked by `summary::projector_mismatch`, and when there is nothing to compare.
+pub fn companion_fit(model: &ModelDescription, companion: &Companion) -> Option<(bool, String)> {
+    match companion.kind {
+        CompanionKind::Adapter => match adapter_targets(model, companion)? {
+            (targets, 0, _) => Some((
+                true,
+                format!("targets {targets} modules, all in the base model"),
+            )),
+            (targets, missing, first) => Some((
+                false,
+                format!(
+                    "targets {missing} of {targets} modules that are not in the base model, e.g. {}",
+                    first.unwrap_or_default()
+                ),
+            )),
+        },
+        CompanionKind::Draft => {
+            let tensors: Vec<TensorInfo> = companion_tensors(model, companion).cloned().collect();
+            let base: Vec<TensorInfo> = base_tensors(model).cloned().collect();
+            let draft = infer_architecture(&tensors).vocab_size?;
+            let base = infer_architecture(&base).vocab_size?;
+            Some(if draft == base {
+                (
+                    true,
+                    format!("has a vocabulary of {draft} tokens, as the base model does"),
+                )
+            } else {
+                (
+                    false,
+                    format!(
+                        "has a vocabulary of {draft} tokens but the base model has {base}; speculative decoding needs the same tokenizer"
+                    ),
+                )
+            })
+        }
+        CompanionKind::Projector => None,
+    }
+}
+
+/// Sentence naming a companion by kind and file, for messages about it
+pub fn describe(companion: &Companion) -> String {
+    let kind = match companion.kind {
+        CompanionKind::Projector => "Vision projector",
+        CompanionKind::Draft => "Draft model",
+        CompanionKind::Adapter => "Adapter",
+    };
+    format!("{kind} {}", display_file_name(&companion.file))
+}
+
+/// Warnings for companions that don't fit the base model: adapter targets missing from it
+/// and drafts with another vocabulary. Nothing is checked without base tensors.
+pub fn companion_mismatches(model: &ModelDescription) -> Vec<String> {
+    if base_tensors(model).next().is_none() {
+        return Vec::new();
+    }
+    model
+        .companions
+        .iter()
+        .filter_map(|c| match companion_fit(model, c)? {
+            (false, message) => Some(format!("{} {message}", describe(c))),
+            (true, _) => None,
+        })
+        .collect()
+}
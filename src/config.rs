@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::tree::{GroupBy, SortOrder};
+use crate::ui::Theme;
+
+/// Settings read from `config.toml`; every field is optional so the file can be partial
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub view: ViewConfig,
+    pub cli: CliConfig,
+    pub recent: RecentConfig,
+    /// Action name to key, e.g. `export = "x"`
+    pub keys: HashMap<String, toml::Spanned<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewConfig {
+    pub ascii_icons: Option<bool>,
+    pub theme: Option<Theme>,
+    pub sort: Option<SortOrder>,
+    pub group_by: Option<GroupBy>,
+    /// Size floor such as `"1MiB"`
+    pub min_size: Option<String>,
+    /// Show GGUF shapes in storage (ne) order rather than logical order
+    pub gguf_storage_order: Option<bool>,
+    /// Tensors above this size (e.g. `"512MiB"`) get sampled statistics first
+    pub stats_sample_threshold: Option<String>,
+    /// Levels of name-prefix groups before the rest are listed under `…`
+    pub max_tree_depth: Option<usize>,
+    /// Top-level name prefixes past which tensors are listed flat instead of grouped
+    pub max_top_level_groups: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CliConfig {
+    pub recursive: Option<bool>,
+    pub si_units: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecentConfig {
+    /// Remember the paths the explorer is opened on; off for shared machines
+    pub enabled: Option<bool>,
+    /// Path sets to keep
+    pub limit: Option<usize>,
+}
+
+impl RecentConfig {
+    /// How many path sets to remember; None when turned off
+    pub fn limit(&self) -> Option<usize> {
+        match (self.enabled.unwrap_or(true), self.limit) {
+            (false, _) | (true, Some(0)) => None,
+            (true, limit) => Some(limit.unwrap_or(crate::recent::DEFAULT_LIMIT)),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/safetensors-explorer/config.toml`, falling back to `~/.config`
+pub fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("safetensors-explorer").join("config.toml"))
+}
+
+/// 1-based line of a byte offset, for pointing at the offending entry
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+/// Load the config at `path` (or the default location), printing problems as warnings.
+/// A file that fails to parse is ignored as a whole so the explorer still starts.
+pub fn load(path: Option<&Path>) -> Config {
+    let (path, explicit) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Config::default(),
+        },
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        // Having no config file is the normal case unless one was asked for
+        Err(e) if explicit || e.kind() != std::io::ErrorKind::NotFound => {
+            eprintln!("Warning: Failed to read {}: {e}", path.display());
+            return Config::default();
+        }
+        Err(_) => return Config::default(),
+    };
+
+    let config: Config = match toml::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            let location = e
+                .span()
+                .map(|span| format!(" (line {})", line_of(&content, span.start)))
+                .unwrap_or_default();
+            eprintln!(
+                "Warning: Ignoring {}{location}: {}; using defaults",
+                path.display(),
+                e.message()
+            );
+            return Config::default();
+        }
+    };
+    if let Some(min_size) = &config.view.min_size
+        && let Err(e) = crate::utils::parse_size(min_size)
+    {
+        eprintln!("Warning: {}: view.min_size: {e}", path.display());
+    }
+    if let Some(threshold) = &config.view.stats_sample_threshold
+        && let Err(e) = crate::utils::parse_size(threshold)
+    {
+        eprintln!(
+            "Warning: {}: view.stats_sample_threshold: {e}",
+            path.display()
+        );
+    }
+    let mut keys: Vec<_> = config.keys.iter().collect();
+    keys.sort_by_key(|(_, key)| key.span().start);
+    for (action, key) in keys {
+        if let Err(e) = crate::keymap::parse_key(action, key.get_ref()) {
+            eprintln!(
+                "Warning: {} (line {}): {e}",
+                path.display(),
+                line_of(&content, key.span().start)
+            );
+        }
+    }
+    config
+}
@@ -0,0 +1,207 @@
+//! Directories holding more than one copy of a checkpoint, such as Mistral repos with both
+//! `consolidated.safetensors` and HF shards, or diffusers folders with fp32 and fp16
+//! variants. Merging the copies would show a model twice the size with a mix of tensors,
+//! so one copy is loaded and the others are reported as ignored.
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::check::split_set_name;
+use crate::model::read_safetensors_header;
+use crate::utils::{display_file_name, format_size};
+
+/// Which copy to load when a directory holds several
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Prefer {
+    /// `consolidated.safetensors`, or else the copy in the fewest files
+    Consolidated,
+    /// The copy split over the most files
+    Sharded,
+}
+
+/// The safetensors files of one copy: a single file, or shards sharing a name such as
+/// `model-00001-of-00003`, `model_part_0` or `consolidated.00`
+#[derive(Debug, Clone)]
+pub struct CheckpointSet {
+    pub name: String,
+    pub files: Vec<PathBuf>,
+    /// Bytes of tensor data over all files
+    pub tensor_bytes: u64,
+}
+
+impl CheckpointSet {
+    /// `consolidated.safetensors`, or `model*.safetensors (3 files)`
+    pub fn label(&self) -> String {
+        match self.files.as_slice() {
+            [single] => display_file_name(single),
+            files => format!("{}*.safetensors ({} files)", self.name, files.len()),
+        }
+    }
+
+    fn is_consolidated(&self) -> bool {
+        self.name == "consolidated"
+    }
+}
+
+/// Copies of one checkpoint found in a directory, with why they were taken for copies
+#[derive(Debug, Clone)]
+pub struct Copies {
+    pub dir: PathBuf,
+    pub sets: Vec<CheckpointSet>,
+    pub reason: &'static str,
+}
+
+impl Copies {
+    /// Index into `sets` of the copy `prefer` picks; ties go to the first by name
+    pub fn choose(&self, prefer: Prefer) -> usize {
+        let position = match prefer {
+            Prefer::Consolidated => self
+                .sets
+                .iter()
+                .position(CheckpointSet::is_consolidated)
+                .or_else(|| {
+                    self.sets
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, set)| set.files.len())
+                        .map(|(i, _)| i)
+                }),
+            Prefer::Sharded => self
+                .sets
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, set)| set.files.len())
+                .map(|(i, _)| i),
+        };
+        position.unwrap_or(0)
+    }
+
+    /// One line per copy, numbered from 1 for a prompt
+    pub fn listing(&self) -> Vec<String> {
+        self.sets
+            .iter()
+            .enumerate()
+            .map(|(i, set)| {
+                format!(
+                    "  {}) {}, {}",
+                    i + 1,
+                    set.label(),
+                    format_size(set.tensor_bytes as usize)
+                )
+            })
+            .collect()
+    }
+}
+
+/// Name shared by the shards of one copy: the `split_set_name`, less a trailing `_part_0`,
+/// `-part-0` or `.00` numbering. A single digit after a dot is a version (`sd-v1.5`).
+pub fn checkpoint_set_name(path: &Path) -> String {
+    let name = split_set_name(path);
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    for separator in ["_part_", "-part-", "_part", "-part", "."] {
+        if let Some((stem, number)) = name.rsplit_once(separator)
+            && digits(number)
+            && (separator != "." || number.len() > 1)
+            && !stem.is_empty()
+        {
+            return stem.to_string();
+        }
+    }
+    name
+}
+
+/// Group safetensors files of one directory into copies, in name order
+pub fn checkpoint_sets(files: &[PathBuf]) -> Vec<CheckpointSet> {
+    let mut sets: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for file in files {
+        sets.entry(checkpoint_set_name(file))
+            .or_default()
+            .push(file.clone());
+    }
+    sets.into_iter()
+        .map(|(name, mut files)| {
+            files.sort();
+            CheckpointSet {
+                name,
+                files,
+                tensor_bytes: 0,
+            }
+        })
+        .collect()
+}
+
+/// Tensor names and data bytes of a set's files, from their headers
+fn read_set(set: &CheckpointSet) -> Result<(HashSet<String>, u64)> {
+    let mut names = HashSet::new();
+    let mut bytes = 0u64;
+    for path in &set.files {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        let (metadata, _) = read_safetensors_header(&mut file, path, size)?;
+        for (name, tensor) in metadata.tensors() {
+            let (start, end) = tensor.data_offsets;
+            bytes += (end - start) as u64;
+            names.insert(name);
+        }
+    }
+    Ok((names, bytes))
+}
+
+/// The copies among the safetensors `files` of `dir`: the sets sharing tensor names with
+/// another set, or holding the same amount of tensor data (within 1%) under other names.
+/// None when every set is a different model or part; sets whose headers can't be read are
+/// left to the loader to report.
+pub fn find_copies(dir: &Path, files: &[PathBuf]) -> Option<Copies> {
+    let mut sets = checkpoint_sets(files);
+    if sets.len() < 2 {
+        return None;
+    }
+    let contents: Vec<Option<(HashSet<String>, u64)>> = sets
+        .iter()
+        .map(|set| {
+            read_set(set)
+                .inspect_err(|e| log::debug!("Not checking {} for copies: {e:#}", set.name))
+                .ok()
+        })
+        .collect();
+
+    let mut copy = vec![false; sets.len()];
+    let mut shared_names = false;
+    for i in 0..sets.len() {
+        for j in i + 1..sets.len() {
+            let (Some((names_i, bytes_i)), Some((names_j, bytes_j))) = (&contents[i], &contents[j])
+            else {
+                continue;
+            };
+            let same_names = !names_i.is_disjoint(names_j);
+            let (small, large) = (*bytes_i.min(bytes_j), *bytes_i.max(bytes_j));
+            let same_size = small > 0 && (large - small) * 100 <= large;
+            if same_names || same_size {
+                copy[i] = true;
+                copy[j] = true;
+                shared_names |= same_names;
+            }
+        }
+    }
+    for (set, content) in sets.iter_mut().zip(&contents) {
+        set.tensor_bytes = content.as_ref().map_or(0, |(_, bytes)| *bytes);
+    }
+    let sets: Vec<CheckpointSet> = sets
+        .into_iter()
+        .zip(copy)
+        .filter_map(|(set, copy)| copy.then_some(set))
+        .collect();
+    (!sets.is_empty()).then(|| Copies {
+        dir: dir.to_path_buf(),
+        sets,
+        reason: if shared_names {
+            "same tensor names"
+        } else {
+            "same tensor data size"
+        },
+    })
+}
@@ -0,0 +1,386 @@
+//! Rough per-token inference cost: FLOPs and bytes read to decode one token, per layer and
+//! split between attention and MLP, from the tensors' sizes and the architecture's widths.
+//! Standard dense-transformer math (two FLOPs per weight, attention scores over the whole
+//! context, a 16-bit KV cache) with mixture-of-experts weights scaled by the experts used.
+//! Estimates for capacity planning, not measurements.
+
+use serde::Serialize;
+
+use crate::model::ModelDescription;
+use crate::report::Report;
+use crate::size_label::{expert_counts, is_expert_tensor};
+use crate::summary::{self, ArchitectureParams};
+use crate::tree::TensorInfo;
+use crate::utils::{fit_to_width, format_size, format_thousands};
+
+/// Context length when none is given
+pub const DEFAULT_CONTEXT: usize = 4096;
+
+/// Bytes per cached key or value element
+const KV_ELEMENT_BYTES: f64 = 2.0;
+
+/// Cost of one layer, or of the tensors outside the numbered layers
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LayerCost {
+    /// `L3`, or `embed/head` for the embeddings, output head and final norm
+    pub label: String,
+    pub attention_flops: f64,
+    pub mlp_flops: f64,
+    /// Weight bytes read per token, shared between the tokens of a batch
+    pub attention_bytes: f64,
+    pub mlp_bytes: f64,
+    /// Keys and values of the context read per token
+    pub kv_cache_bytes: f64,
+    /// Embeddings, output head and final norm, outside the attention/MLP split
+    pub other_flops: f64,
+    pub other_bytes: f64,
+}
+
+impl LayerCost {
+    pub fn flops(&self) -> f64 {
+        self.attention_flops + self.mlp_flops + self.other_flops
+    }
+
+    pub fn bytes(&self) -> f64 {
+        self.attention_bytes + self.mlp_bytes + self.kv_cache_bytes + self.other_bytes
+    }
+
+    fn add(&mut self, other: &LayerCost) {
+        self.attention_flops += other.attention_flops;
+        self.mlp_flops += other.mlp_flops;
+        self.attention_bytes += other.attention_bytes;
+        self.mlp_bytes += other.mlp_bytes;
+        self.kv_cache_bytes += other.kv_cache_bytes;
+        self.other_flops += other.other_flops;
+        self.other_bytes += other.other_bytes;
+    }
+}
+
+/// `--cost-estimate`: cost of decoding one token at a batch size and context length
+#[derive(Debug, Clone, Serialize)]
+pub struct CostEstimate {
+    pub batch: usize,
+    pub context: usize,
+    /// Width of the queries and of the keys (or values) of one layer; 0 when unknown
+    pub query_width: usize,
+    pub kv_width: usize,
+    /// Experts per layer and experts used per token, for a mixture of experts
+    pub experts: Option<(usize, usize)>,
+    pub layers: Vec<LayerCost>,
+    /// Embeddings, output head and final norm
+    pub other: LayerCost,
+    pub total: LayerCost,
+}
+
+/// Query and key/value widths from the declared hyperparameters, falling back to the ones
+/// inferred from tensor shapes; keys as wide as queries when the KV head count is unknown
+fn attention_widths(
+    declared: Option<ArchitectureParams>,
+    inferred: ArchitectureParams,
+) -> (usize, usize) {
+    let declared = declared.unwrap_or_default();
+    let hidden = declared.hidden_size.or(inferred.hidden_size);
+    let heads = declared.attention_heads.or(inferred.attention_heads);
+    let kv_heads = declared.kv_heads.or(inferred.kv_heads).or(heads);
+    let head_dim = declared
+        .head_dim
+        .or(inferred.head_dim)
+        .or_else(|| hidden.zip(heads).map(|(h, n)| h / n.max(1)));
+    let query = heads.zip(head_dim).map(|(n, d)| n * d).or(hidden);
+    let kv = kv_heads.zip(head_dim).map(|(n, d)| n * d).or(query);
+    (query.unwrap_or(0), kv.unwrap_or(0))
+}
+
+/// Attention tensors by name; norms, routers and everything else count with the MLP
+fn is_attention(role: &str) -> bool {
+    const WORDS: &[&str] = &[
+        "attn",
+        "attention",
+        "q_proj",
+        "k_proj",
+        "v_proj",
+        "o_proj",
+        "query",
+        "key",
+        "value",
+    ];
+    !role.contains("post_attention") && WORDS.iter().any(|word| role.contains(word))
+}
+
+/// What the estimate needs beyond the tensors: attention widths and expert counts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dimensions {
+    /// Width of the queries and of the keys (or values) of one layer; 0 when unknown
+    pub query_width: usize,
+    pub kv_width: usize,
+    /// Experts per layer and experts used per token, for a mixture of experts
+    pub experts: Option<(usize, usize)>,
+}
+
+impl Dimensions {
+    pub fn of(model: &ModelDescription) -> Self {
+        let (tensors, metadata) = summary::language_model_parts(model);
+        let declared = summary::declared_from_gguf(&metadata)
+            .or_else(|| model.config.as_ref().map(summary::declared_from_config));
+        let (query_width, kv_width) =
+            attention_widths(declared, summary::infer_architecture(&tensors));
+        Dimensions {
+            query_width,
+            kv_width,
+            experts: expert_counts(&metadata, model.config.as_ref()),
+        }
+    }
+}
+
+/// `--cost-estimate` for a loaded model
+pub fn estimate_model(model: &ModelDescription, batch: usize, context: usize) -> CostEstimate {
+    let (tensors, _) = summary::language_model_parts(model);
+    estimate(&tensors, Dimensions::of(model), batch, context)
+}
+
+/// Cost of decoding one token with the language model `tensors`
+pub fn estimate(
+    tensors: &[TensorInfo],
+    dimensions: Dimensions,
+    batch: usize,
+    context: usize,
+) -> CostEstimate {
+    let batch = batch.max(1);
+    let Dimensions {
+        query_width,
+        kv_width,
+        experts,
+    } = dimensions;
+
+    // Every token runs `used` of `count` experts; a batch reads every expert any token picks
+    let (flops_share, bytes_share) = match experts {
+        Some((count, used)) => {
+            let share = used as f64 / count as f64;
+            (share, 1.0 - (1.0 - share).powi(batch as i32))
+        }
+        None => (1.0, 1.0),
+    };
+    let weight_cost = |tensor: &TensorInfo| {
+        let (flops, bytes) = if is_expert_tensor(&tensor.name) {
+            (flops_share, bytes_share)
+        } else {
+            (1.0, 1.0)
+        };
+        (
+            2.0 * crate::quant::logical_parameters(tensor) as f64 * flops,
+            tensor.size_bytes as f64 * bytes / batch as f64,
+        )
+    };
+
+    let mut layers: Vec<(usize, LayerCost)> = Vec::new();
+    let mut other = LayerCost {
+        label: "embed/head".to_string(),
+        ..LayerCost::default()
+    };
+    let embedding = summary::token_embedding(tensors).map(|t| t.name.clone());
+    let tied = summary::output_head(tensors).is_none();
+    for tensor in tensors {
+        let Some((_, index, role)) = summary::split_layer_name(&tensor.name) else {
+            if Some(&tensor.name) == embedding.as_ref() {
+                // A lookup reads one row, unless the output head reuses the table
+                let rows = tensor.shape.first().copied().unwrap_or(1).max(1);
+                other.other_bytes += tensor.size_bytes as f64 / rows as f64;
+                if tied {
+                    let (flops, bytes) = weight_cost(tensor);
+                    other.other_flops += flops;
+                    other.other_bytes += bytes;
+                }
+            } else {
+                let (flops, bytes) = weight_cost(tensor);
+                other.other_flops += flops;
+                other.other_bytes += bytes;
+            }
+            continue;
+        };
+        let position = match layers.iter().position(|(i, _)| *i == index) {
+            Some(position) => position,
+            None => {
+                layers.push((
+                    index,
+                    LayerCost {
+                        label: format!("L{index}"),
+                        ..LayerCost::default()
+                    },
+                ));
+                layers.len() - 1
+            }
+        };
+        let layer = &mut layers[position].1;
+        let (flops, bytes) = weight_cost(tensor);
+        if is_attention(&role) {
+            layer.attention_flops += flops;
+            layer.attention_bytes += bytes;
+        } else {
+            layer.mlp_flops += flops;
+            layer.mlp_bytes += bytes;
+        }
+    }
+    layers.sort_by_key(|(index, _)| *index);
+
+    let mut total = LayerCost {
+        label: "Total".to_string(),
+        ..LayerCost::default()
+    };
+    let layers: Vec<LayerCost> = layers
+        .into_iter()
+        .map(|(_, mut layer)| {
+            // Scores against every cached key, then the weighted sum of the values
+            if layer.attention_bytes > 0.0 {
+                layer.attention_flops += 4.0 * query_width as f64 * context as f64;
+                layer.kv_cache_bytes = 2.0 * kv_width as f64 * context as f64 * KV_ELEMENT_BYTES;
+            }
+            total.add(&layer);
+            layer
+        })
+        .collect();
+    total.add(&other);
+
+    CostEstimate {
+        batch,
+        context,
+        query_width,
+        kv_width,
+        experts,
+        layers,
+        other,
+        total,
+    }
+}
+
+/// `14.2 GFLOP`
+pub fn format_flops(flops: f64) -> String {
+    const UNITS: &[(&str, f64)] = &[
+        ("PFLOP", 1e15),
+        ("TFLOP", 1e12),
+        ("GFLOP", 1e9),
+        ("MFLOP", 1e6),
+        ("KFLOP", 1e3),
+    ];
+    UNITS.iter().find(|(_, scale)| flops >= *scale).map_or_else(
+        || format!("{flops:.0} FLOP"),
+        |(unit, scale)| format!("{:.1} {unit}", flops / scale),
+    )
+}
+
+impl CostEstimate {
+    /// One row of the table; `split` leaves out the attention and MLP columns, which don't
+    /// apply to the embeddings and head
+    fn table_row(cost: &LayerCost, split: bool) -> String {
+        let mut flops = [cost.attention_flops, cost.mlp_flops, cost.flops()].map(format_flops);
+        let mut bytes = [
+            cost.attention_bytes,
+            cost.mlp_bytes,
+            cost.kv_cache_bytes,
+            cost.bytes(),
+        ]
+        .map(|b| format_size(b.round() as usize));
+        if !split {
+            flops[..2].fill("-".to_string());
+            bytes[..2].fill("-".to_string());
+        }
+        format!(
+            "{} {} {} {} {} {} {} {}",
+            fit_to_width(&cost.label, 10, false),
+            fit_to_width(&flops[0], 11, true),
+            fit_to_width(&flops[1], 11, true),
+            fit_to_width(&flops[2], 11, true),
+            fit_to_width(&bytes[0], 11, true),
+            fit_to_width(&bytes[1], 11, true),
+            fit_to_width(&bytes[2], 11, true),
+            fit_to_width(&bytes[3], 11, true),
+        )
+    }
+}
+
+impl Report for CostEstimate {
+    fn text_lines(&self) -> Vec<String> {
+        if self.layers.is_empty() {
+            return vec!["No numbered layers to estimate the cost of".to_string()];
+        }
+        let mut lines = vec![
+            format!(
+                "ESTIMATE of the cost of decoding one token: batch {}, context {} (dense-transformer math, not a measurement)",
+                format_thousands(self.batch),
+                format_thousands(self.context)
+            ),
+            if self.query_width == 0 {
+                "Attention width unknown: attention scores and the KV cache are left out"
+                    .to_string()
+            } else {
+                format!(
+                    "Attention {} wide, keys and values {} wide each, KV cache assumed 16-bit",
+                    format_thousands(self.query_width),
+                    format_thousands(self.kv_width)
+                )
+            },
+            "Weights: 2 FLOPs per parameter, read once per batch and shared by its tokens"
+                .to_string(),
+        ];
+        if let Some((count, used)) = self.experts {
+            lines.push(format!(
+                "Mixture of experts: {used} of {count} experts per token scale the expert FLOPs; the weight reads cover every expert a batch of {} is likely to touch",
+                self.batch
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "{} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11}",
+            fit_to_width("Layer", 10, false),
+            "Attn FLOPs",
+            "MLP FLOPs",
+            "FLOPs",
+            "Attn read",
+            "MLP read",
+            "KV read",
+            "Read"
+        ));
+        lines.extend(self.layers.iter().map(|layer| Self::table_row(layer, true)));
+        lines.push(Self::table_row(&self.other, false));
+        lines.push(Self::table_row(&self.total, true));
+        lines.push(String::new());
+        lines.push(format!(
+            "Per token: ≈ {} and ≈ {} read, {:.1} FLOPs per byte",
+            format_flops(self.total.flops()),
+            format_size(self.total.bytes().round() as usize),
+            self.total.flops() / self.total.bytes().max(1.0)
+        ));
+        lines
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &[
+            "layer",
+            "attention_flops",
+            "mlp_flops",
+            "flops",
+            "attention_bytes",
+            "mlp_bytes",
+            "kv_cache_bytes",
+            "bytes",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.layers
+            .iter()
+            .chain([&self.other, &self.total])
+            .map(|cost| {
+                vec![
+                    cost.label.clone(),
+                    format!("{:.0}", cost.attention_flops),
+                    format!("{:.0}", cost.mlp_flops),
+                    format!("{:.0}", cost.flops()),
+                    format!("{:.0}", cost.attention_bytes),
+                    format!("{:.0}", cost.mlp_bytes),
+                    format!("{:.0}", cost.kv_cache_bytes),
+                    format!("{:.0}", cost.bytes()),
+                ]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,641 @@
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::check::{Finding, Severity};
+use crate::name_map::NameMap;
+use crate::report::Report;
+use crate::summary::FILE_INFO_PREFIX;
+use crate::tree::{MetadataInfo, TensorInfo};
+use crate::utils::format_shape;
+
+/// Tensors the converter computes rather than copies, so they have no HF source
+const GENERATED_GGUF_TENSORS: &[&str] = &["rope_freqs.weight"];
+
+/// HF buffers the converter drops because llama.cpp recomputes them
+const DROPPED_HF_SUFFIXES: &[&str] = &[".rotary_emb.inv_freq"];
+
+/// A GGUF tensor paired with the HF tensor it was converted from
+#[derive(Debug, Clone, Serialize)]
+pub struct MappedPair {
+    pub gguf: String,
+    pub hf: String,
+    /// Logical GGUF shape and the HF shape, when they differ
+    pub shape_mismatch: Option<(Vec<usize>, Vec<usize>)>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckpointDiff {
+    pub architecture: String,
+    /// False when the architecture has no name table and names were matched fuzzily
+    pub known_architecture: bool,
+    pub pairs: Vec<MappedPair>,
+    pub only_gguf: Vec<String>,
+    pub only_hf: Vec<String>,
+    /// GGUF tensors the converter computes itself, such as `rope_freqs.weight`
+    pub generated: Vec<String>,
+    /// HF tensors the converter leaves out, such as `rotary_emb.inv_freq`
+    pub dropped: Vec<String>,
+}
+
+impl CheckpointDiff {
+    pub fn mismatches(&self) -> impl Iterator<Item = &MappedPair> {
+        self.pairs
+            .iter()
+            .filter(|pair| pair.shape_mismatch.is_some())
+    }
+
+    /// True when every tensor was accounted for with a matching shape
+    pub fn is_clean(&self) -> bool {
+        self.only_gguf.is_empty() && self.only_hf.is_empty() && self.mismatches().next().is_none()
+    }
+
+    pub fn report_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Architecture {}: {} tensors mapped, {} shape mismatches, {} only in GGUF, {} only in HF",
+            self.architecture,
+            self.pairs.len(),
+            self.mismatches().count(),
+            self.only_gguf.len(),
+            self.only_hf.len()
+        )];
+        if !self.known_architecture {
+            lines.push(format!(
+                "No name table for '{}'; tensors were matched by fuzzy name and shape, so check the pairs",
+                self.architecture
+            ));
+        }
+        if self
+            .pairs
+            .iter()
+            .any(|pair| pair.gguf.contains(".attn_q.") || pair.gguf.contains(".attn_k."))
+        {
+            lines.push(
+                "attn_q/attn_k rows are permuted by the converter; shapes are unaffected, values differ in order"
+                    .to_string(),
+            );
+        }
+
+        let mut section = |title: &str, entries: Vec<String>| {
+            if !entries.is_empty() {
+                lines.push(String::new());
+                lines.push(format!("{title} ({}):", entries.len()));
+                lines.extend(entries.into_iter().map(|entry| format!("  {entry}")));
+            }
+        };
+        section(
+            "Shape mismatches (GGUF logical shape vs HF shape)",
+            self.mismatches()
+                .filter_map(|pair| {
+                    let (gguf, hf) = pair.shape_mismatch.as_ref()?;
+                    Some(format!(
+                        "{} {} vs {} {}",
+                        pair.gguf,
+                        format_shape(gguf),
+                        pair.hf,
+                        format_shape(hf)
+                    ))
+                })
+                .collect(),
+        );
+        section("Only in GGUF", self.only_gguf.clone());
+        section("Only in HF", self.only_hf.clone());
+        section("Computed by the converter", self.generated.clone());
+        section("Dropped by the converter", self.dropped.clone());
+        if !self.known_architecture {
+            section(
+                "Fuzzy pairs",
+                self.pairs
+                    .iter()
+                    .map(|pair| format!("{} <- {}", pair.gguf, pair.hf))
+                    .collect(),
+            );
+        }
+        lines
+    }
+}
+
+impl Report for CheckpointDiff {
+    fn text_lines(&self) -> Vec<String> {
+        self.report_lines()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["status", "gguf", "hf", "gguf_shape", "hf_shape"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let mut rows: Vec<Vec<String>> = self
+            .pairs
+            .iter()
+            .map(|pair| {
+                let (status, gguf_shape, hf_shape) = match &pair.shape_mismatch {
+                    Some((gguf, hf)) => ("mismatch", format_shape(gguf), format_shape(hf)),
+                    None => ("mapped", String::new(), String::new()),
+                };
+                vec![
+                    status.to_string(),
+                    pair.gguf.clone(),
+                    pair.hf.clone(),
+                    gguf_shape,
+                    hf_shape,
+                ]
+            })
+            .collect();
+        let mut unpaired = |status: &str, names: &[String], gguf_side: bool| {
+            for name in names {
+                let (gguf, hf) = if gguf_side {
+                    (name.clone(), String::new())
+                } else {
+                    (String::new(), name.clone())
+                };
+                rows.push(vec![
+                    status.to_string(),
+                    gguf,
+                    hf,
+                    String::new(),
+                    String::new(),
+                ]);
+            }
+        };
+        unpaired("only_gguf", &self.only_gguf, true);
+        unpaired("only_hf", &self.only_hf, false);
+        unpaired("generated", &self.generated, true);
+        unpaired("dropped", &self.dropped, false);
+        rows
+    }
+
+    /// Shape mismatches as errors, unpaired tensors as warnings, and what the converter
+    /// computes or drops as info; pairs that match are not findings
+    fn findings(&self) -> Vec<Finding> {
+        let finding = |check, severity, name: &str, message: String| Finding {
+            check,
+            severity,
+            message,
+            related_node_path: Some(name.to_string()),
+            values: BTreeMap::new(),
+        };
+        let mut findings: Vec<Finding> = self
+            .mismatches()
+            .filter_map(|pair| {
+                let (gguf, hf) = pair.shape_mismatch.as_ref()?;
+                Some(finding(
+                    "shape_mismatch",
+                    Severity::Error,
+                    &pair.gguf,
+                    format!(
+                        "{} {} vs {} {}",
+                        pair.gguf,
+                        format_shape(gguf),
+                        pair.hf,
+                        format_shape(hf)
+                    ),
+                ))
+            })
+            .collect();
+        let unpaired = [
+            (
+                "only_gguf",
+                Severity::Warning,
+                &self.only_gguf,
+                "has no HF source",
+            ),
+            (
+                "only_hf",
+                Severity::Warning,
+                &self.only_hf,
+                "is missing from the GGUF",
+            ),
+            (
+                "generated",
+                Severity::Info,
+                &self.generated,
+                "is computed by the converter",
+            ),
+            (
+                "dropped",
+                Severity::Info,
+                &self.dropped,
+                "is dropped by the converter",
+            ),
+        ];
+        for (check, severity, names, what) in unpaired {
+            findings.extend(
+                names
+                    .iter()
+                    .map(|name| finding(check, severity, name, format!("{name} {what}"))),
+            );
+        }
+        findings
+    }
+}
+
+/// Pair up the tensors of a GGUF conversion with those of its HF source, translating the
+/// GGUF names with `names`
+pub fn diff_checkpoints(
+    names: &NameMap,
+    architecture: &str,
+    gguf: &[TensorInfo],
+    hf: &[TensorInfo],
+) -> CheckpointDiff {
+    let hf_by_name: HashMap<&str, &TensorInfo> = hf.iter().map(|t| (t.name.as_str(), t)).collect();
+    let mut diff = CheckpointDiff {
+        architecture: architecture.to_string(),
+        known_architecture: names.covers(architecture),
+        ..Default::default()
+    };
+    let mut used: HashSet<&str> = HashSet::new();
+    let mut unmatched = Vec::new();
+
+    for tensor in gguf {
+        if GENERATED_GGUF_TENSORS.contains(&tensor.name.as_str()) {
+            diff.generated.push(tensor.name.clone());
+            continue;
+        }
+        let source = names
+            .translate(architecture, &tensor.name)
+            .and_then(|name| hf_by_name.get(name.as_str()).copied());
+        match source {
+            Some(source) => {
+                used.insert(&source.name);
+                diff.pairs.push(pair(tensor, source));
+            }
+            None => unmatched.push(tensor),
+        }
+    }
+
+    if diff.known_architecture {
+        diff.only_gguf = unmatched.iter().map(|t| t.name.clone()).collect();
+    } else {
+        let matcher = SkimMatcherV2::default();
+        for tensor in unmatched {
+            match fuzzy_source(&matcher, tensor, hf, &used) {
+                Some(source) => {
+                    used.insert(&source.name);
+                    diff.pairs.push(pair(tensor, source));
+                }
+                None => diff.only_gguf.push(tensor.name.clone()),
+            }
+        }
+    }
+    for tensor in hf.iter().filter(|t| !used.contains(t.name.as_str())) {
+        if DROPPED_HF_SUFFIXES
+            .iter()
+            .any(|suffix| tensor.name.ends_with(suffix))
+        {
+            diff.dropped.push(tensor.name.clone());
+        } else {
+            diff.only_hf.push(tensor.name.clone());
+        }
+    }
+    diff
+}
+
+fn pair(gguf: &TensorInfo, hf: &TensorInfo) -> MappedPair {
+    MappedPair {
+        gguf: gguf.name.clone(),
+        hf: hf.name.clone(),
+        shape_mismatch: (gguf.shape != hf.shape).then(|| (gguf.shape.clone(), hf.shape.clone())),
+    }
+}
+
+/// First number between dots, taken as the layer index
+fn layer_number(name: &str) -> Option<usize> {
+    name.split('.').find_map(|part| part.parse().ok())
+}
+
+/// Best unused HF tensor for a GGUF tensor of an unknown architecture: same layer, same
+/// weight/bias suffix and same logical shape, ranked by fuzzy similarity of the role names.
+/// A lone candidate is taken even when the names share nothing.
+fn fuzzy_source<'a>(
+    matcher: &SkimMatcherV2,
+    tensor: &TensorInfo,
+    hf: &'a [TensorInfo],
+    used: &HashSet<&str>,
+) -> Option<&'a TensorInfo> {
+    let (base, suffix) = tensor.name.rsplit_once('.')?;
+    let layer = layer_number(base);
+    let role: String = base
+        .split('.')
+        .filter(|part| *part != "blk" && part.parse::<usize>().is_err())
+        .collect::<String>()
+        .replace('_', "");
+    let candidates: Vec<&TensorInfo> = hf
+        .iter()
+        .filter(|candidate| {
+            !used.contains(candidate.name.as_str())
+                && candidate.name.ends_with(&format!(".{suffix}"))
+                && layer_number(&candidate.name) == layer
+                && candidate.shape == tensor.shape
+        })
+        .collect();
+    if let [only] = candidates[..] {
+        return Some(only);
+    }
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let score = matcher.fuzzy_match(&candidate.name, &role)?;
+            Some((score, candidate))
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Exit status of `--diff` when both sides match
+pub const EXIT_IDENTICAL: i32 = 0;
+/// Exit status of `--diff` when only metadata differs
+pub const EXIT_METADATA_ONLY: i32 = 1;
+/// Exit status of `--diff` when tensors were added, removed or changed
+pub const EXIT_TENSORS_DIFFER: i32 = 2;
+
+impl CheckpointDiff {
+    /// Status under the `--diff` contract; a conversion diff compares tensors only
+    pub fn exit_code(&self) -> i32 {
+        if self.is_clean() {
+            EXIT_IDENTICAL
+        } else {
+            EXIT_TENSORS_DIFFER
+        }
+    }
+}
+
+/// One field that differs between the two sides; `a` or `b` is None when absent there
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub name: String,
+    /// `dtype` or `shape` for tensors, `metadata` for metadata keys
+    pub field: &'static str,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+/// Tensor-by-tensor comparison of two checkpoints in the same format
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ModelDiff {
+    /// Tensors only in B
+    pub added: Vec<String>,
+    /// Tensors only in A
+    pub removed: Vec<String>,
+    pub changed: Vec<FieldChange>,
+    /// Tensors with the same dtype and shape on both sides
+    pub identical_count: usize,
+}
+
+impl ModelDiff {
+    fn tensors_differ(&self) -> bool {
+        !self.added.is_empty()
+            || !self.removed.is_empty()
+            || self.changed.iter().any(|change| change.field != "metadata")
+    }
+
+    /// 0 when identical, 1 when only metadata differs, 2 when tensors differ
+    pub fn exit_code(&self) -> i32 {
+        if self.tensors_differ() {
+            EXIT_TENSORS_DIFFER
+        } else if !self.changed.is_empty() {
+            EXIT_METADATA_ONLY
+        } else {
+            EXIT_IDENTICAL
+        }
+    }
+
+    pub fn report_lines(&self) -> Vec<String> {
+        let metadata_changes = self
+            .changed
+            .iter()
+            .filter(|change| change.field == "metadata")
+            .count();
+        let mut lines = vec![format!(
+            "{} identical tensors, {} added, {} removed, {} changed; {} metadata differences",
+            self.identical_count,
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len() - metadata_changes,
+            metadata_changes
+        )];
+        let mut section = |title: &str, entries: Vec<String>| {
+            if !entries.is_empty() {
+                lines.push(String::new());
+                lines.push(format!("{title} ({}):", entries.len()));
+                lines.extend(entries.into_iter().map(|entry| format!("  {entry}")));
+            }
+        };
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "(absent)".to_string());
+        section("Only in B", self.added.clone());
+        section("Only in A", self.removed.clone());
+        section(
+            "Changed tensors",
+            self.changed
+                .iter()
+                .filter(|change| change.field != "metadata")
+                .map(|change| {
+                    format!(
+                        "{} {}: {} -> {}",
+                        change.name,
+                        change.field,
+                        show(&change.a),
+                        show(&change.b)
+                    )
+                })
+                .collect(),
+        );
+        section(
+            "Changed metadata",
+            self.changed
+                .iter()
+                .filter(|change| change.field == "metadata")
+                .map(|change| {
+                    format!(
+                        "{}: {} -> {}",
+                        change.name,
+                        show(&change.a),
+                        show(&change.b)
+                    )
+                })
+                .collect(),
+        );
+        lines
+    }
+}
+
+impl Report for ModelDiff {
+    fn text_lines(&self) -> Vec<String> {
+        self.report_lines()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["change", "name", "field", "a", "b"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        for name in &self.added {
+            rows.push(vec![
+                "added".to_string(),
+                name.clone(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+        for name in &self.removed {
+            rows.push(vec![
+                "removed".to_string(),
+                name.clone(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+        for change in &self.changed {
+            rows.push(vec![
+                "changed".to_string(),
+                change.name.clone(),
+                change.field.to_string(),
+                change.a.clone().unwrap_or_default(),
+                change.b.clone().unwrap_or_default(),
+            ]);
+        }
+        rows
+    }
+
+    /// Tensor differences as warnings and metadata differences as info, matching the exit
+    /// codes
+    fn findings(&self) -> Vec<Finding> {
+        let finding = |check, severity, name: &str, message: String| Finding {
+            check,
+            severity,
+            message,
+            related_node_path: Some(name.to_string()),
+            values: BTreeMap::new(),
+        };
+        let mut findings = Vec::new();
+        for name in &self.added {
+            findings.push(finding(
+                "added",
+                Severity::Warning,
+                name,
+                format!("{name} is only in B"),
+            ));
+        }
+        for name in &self.removed {
+            findings.push(finding(
+                "removed",
+                Severity::Warning,
+                name,
+                format!("{name} is only in A"),
+            ));
+        }
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "(absent)".to_string());
+        for change in &self.changed {
+            let (check, severity, field) = match change.field {
+                "dtype" => ("dtype_changed", Severity::Warning, " dtype"),
+                "shape" => ("shape_changed", Severity::Warning, " shape"),
+                _ => ("metadata_changed", Severity::Info, ""),
+            };
+            findings.push(finding(
+                check,
+                severity,
+                &change.name,
+                format!(
+                    "{}{field}: {} -> {}",
+                    change.name,
+                    show(&change.a),
+                    show(&change.b)
+                ),
+            ));
+        }
+        findings
+    }
+}
+
+/// Compare two checkpoints by tensor name, and their metadata by key unless
+/// `metadata` is None. Computed layout groups (`File info`) are never compared.
+pub fn diff_models(
+    a: &[TensorInfo],
+    b: &[TensorInfo],
+    metadata: Option<(&[MetadataInfo], &[MetadataInfo])>,
+) -> ModelDiff {
+    let mut diff = ModelDiff::default();
+    let b_by_name: HashMap<&str, &TensorInfo> = b.iter().map(|t| (t.name.as_str(), t)).collect();
+    let a_names: HashSet<&str> = a.iter().map(|t| t.name.as_str()).collect();
+
+    for tensor in a {
+        let Some(other) = b_by_name.get(tensor.name.as_str()) else {
+            diff.removed.push(tensor.name.clone());
+            continue;
+        };
+        let before = diff.changed.len();
+        if tensor.dtype != other.dtype {
+            diff.changed.push(FieldChange {
+                name: tensor.name.clone(),
+                field: "dtype",
+                a: Some(tensor.dtype.clone()),
+                b: Some(other.dtype.clone()),
+            });
+        }
+        if tensor.shape != other.shape {
+            diff.changed.push(FieldChange {
+                name: tensor.name.clone(),
+                field: "shape",
+                a: Some(format_shape(&tensor.shape)),
+                b: Some(format_shape(&other.shape)),
+            });
+        }
+        if diff.changed.len() == before {
+            diff.identical_count += 1;
+        }
+    }
+    diff.added = b
+        .iter()
+        .filter(|t| !a_names.contains(t.name.as_str()))
+        .map(|t| t.name.clone())
+        .collect();
+
+    if let Some((a, b)) = metadata {
+        // The first entry of a key wins, so embedded metadata shadows sidecar files
+        let compared = |entries: &[MetadataInfo]| -> Vec<(String, String)> {
+            let mut seen = HashSet::new();
+            entries
+                .iter()
+                .filter(|m| {
+                    !m.source
+                        .as_deref()
+                        .is_some_and(|source| source.starts_with(FILE_INFO_PREFIX))
+                        && seen.insert(m.name.as_str())
+                })
+                .map(|m| (m.name.clone(), m.value.clone()))
+                .collect()
+        };
+        let a_values: HashMap<String, String> = compared(a).into_iter().collect();
+        let b_values = compared(b);
+        let b_keys: HashSet<&str> = b_values.iter().map(|(key, _)| key.as_str()).collect();
+        let mut changes: Vec<FieldChange> = Vec::new();
+        for (key, value) in &b_values {
+            match a_values.get(key) {
+                Some(old) if old == value => {}
+                old => changes.push(FieldChange {
+                    name: key.clone(),
+                    field: "metadata",
+                    a: old.cloned(),
+                    b: Some(value.clone()),
+                }),
+            }
+        }
+        for (key, value) in &a_values {
+            if !b_keys.contains(key.as_str()) {
+                changes.push(FieldChange {
+                    name: key.clone(),
+                    field: "metadata",
+                    a: Some(value.clone()),
+                    b: None,
+                });
+            }
+        }
+        changes.sort_by(|x, y| x.name.cmp(&y.name));
+        diff.changed.extend(changes);
+    }
+    diff
+}
@@ -0,0 +1,97 @@
+//! Canonical names of the fixed-width element types shared by the non-GGUF formats.
+//! Every reader maps its own type codes onto these, so grouping and filtering by
+//! dtype sees one spelling per type whatever the source format.
+
+use safetensors::Dtype;
+
+/// How the bits of one element are interpreted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DtypeKind {
+    Bool,
+    Signed,
+    Unsigned,
+    /// IEEE-style float with the given exponent and mantissa widths
+    Float {
+        exponent: u32,
+        mantissa: u32,
+    },
+}
+
+/// Width and interpretation of a canonical dtype
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DtypeInfo {
+    pub bits: u32,
+    pub kind: DtypeKind,
+}
+
+/// Canonical dtypes with their layout
+const DTYPES: &[(&str, DtypeInfo)] = &[
+    ("BOOL", info(8, DtypeKind::Bool)),
+    ("U8", info(8, DtypeKind::Unsigned)),
+    ("I8", info(8, DtypeKind::Signed)),
+    ("F8_E5M2", float(8, 5, 2)),
+    ("F8_E4M3", float(8, 4, 3)),
+    ("U16", info(16, DtypeKind::Unsigned)),
+    ("I16", info(16, DtypeKind::Signed)),
+    ("F16", float(16, 5, 10)),
+    ("BF16", float(16, 8, 7)),
+    ("U32", info(32, DtypeKind::Unsigned)),
+    ("I32", info(32, DtypeKind::Signed)),
+    ("F32", float(32, 8, 23)),
+    ("U64", info(64, DtypeKind::Unsigned)),
+    ("I64", info(64, DtypeKind::Signed)),
+    ("F64", float(64, 11, 52)),
+];
+
+const fn info(bits: u32, kind: DtypeKind) -> DtypeInfo {
+    DtypeInfo { bits, kind }
+}
+
+const fn float(bits: u32, exponent: u32, mantissa: u32) -> DtypeInfo {
+    info(bits, DtypeKind::Float { exponent, mantissa })
+}
+
+/// Canonical name of a safetensors dtype. The enum is non-exhaustive, so types added
+/// upstream show up as `OTHER(<debug>)` until they get a name here.
+pub fn safetensors_name(dtype: Dtype) -> String {
+    let name = match dtype {
+        Dtype::BOOL => "BOOL",
+        Dtype::U8 => "U8",
+        Dtype::I8 => "I8",
+        Dtype::F8_E5M2 => "F8_E5M2",
+        Dtype::F8_E4M3 => "F8_E4M3",
+        Dtype::U16 => "U16",
+        Dtype::I16 => "I16",
+        Dtype::F16 => "F16",
+        Dtype::BF16 => "BF16",
+        Dtype::U32 => "U32",
+        Dtype::I32 => "I32",
+        Dtype::F32 => "F32",
+        Dtype::U64 => "U64",
+        Dtype::I64 => "I64",
+        Dtype::F64 => "F64",
+        other => return format!("OTHER({other:?})"),
+    };
+    name.to_string()
+}
+
+/// Layout of a canonical dtype; None for quantized GGUF types and unknown names
+pub fn dtype_info(name: &str) -> Option<DtypeInfo> {
+    DTYPES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|&(_, info)| info)
+}
+
+/// Human description such as `8-bit float, e4m3` or `32-bit signed integer`
+pub fn describe(name: &str) -> Option<String> {
+    let DtypeInfo { bits, kind } = dtype_info(name)?;
+    Some(match kind {
+        DtypeKind::Bool => format!("{bits}-bit boolean"),
+        DtypeKind::Signed => format!("{bits}-bit signed integer"),
+        DtypeKind::Unsigned => format!("{bits}-bit unsigned integer"),
+        DtypeKind::Float { exponent, mantissa } => {
+            format!("{bits}-bit float, e{exponent}m{mantissa}")
+        }
+    })
+}
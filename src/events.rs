@@ -0,0 +1,239 @@
+//! `--json-events`: newline-delimited JSON on stdout for programs wrapping the command
+//! line, with everything meant for people moved to stderr. Every event carries `version`;
+//! within a version, fields may be added but are never renamed or removed.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::check::Finding;
+use crate::model::{FileOutcome, Incomplete, ModelDescription};
+use crate::report::Report;
+use crate::scan::{Progress, StderrProgress};
+
+/// Schema version of the events
+pub const VERSION: u32 = 1;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The result sent with `done`, set by the operation that ran
+static SUMMARY: Mutex<Option<Value>> = Mutex::new(None);
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    FileParsed {
+        path: &'a Path,
+        format: &'a str,
+        tensors: usize,
+        metadata_keys: usize,
+        bytes: u64,
+        parse_ms: f64,
+        /// Present when tensor data runs past the end of the file
+        #[serde(skip_serializing_if = "Option::is_none")]
+        incomplete: Option<&'a Incomplete>,
+    },
+    FileFailed {
+        path: &'a Path,
+        error: String,
+    },
+    Warning {
+        message: &'a str,
+    },
+    /// `stage` is `load` (files) or `scan` (tensors)
+    Progress {
+        stage: &'a str,
+        label: &'a str,
+        done: u64,
+        total: u64,
+    },
+    /// A problem or difference a check, scan or diff found
+    Finding {
+        finding: &'a Finding,
+    },
+    Error {
+        message: String,
+    },
+    /// Always the last event: the operation's JSON report, as `--format json` prints it
+    Done {
+        exit_code: i32,
+        summary: Value,
+    },
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn send(event: Event) {
+    if !enabled() {
+        return;
+    }
+    let envelope = Envelope {
+        version: VERSION,
+        event,
+    };
+    if let Ok(line) = serde_json::to_string(&envelope) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{line}");
+        let _ = stdout.flush();
+    }
+}
+
+/// A line of human-readable output: stdout normally, stderr under `--json-events`
+pub fn line(text: impl std::fmt::Display) {
+    if enabled() {
+        eprintln!("{text}");
+    } else {
+        println!("{text}");
+    }
+}
+
+/// Human-readable output already split into lines, as `line` prints it
+pub fn text(content: &str) {
+    if enabled() {
+        eprint!("{content}");
+    } else {
+        print!("{content}");
+    }
+}
+
+/// Print a warning on stderr, and send it as an event
+pub fn warn(message: &str) {
+    eprintln!("Warning: {message}");
+    send(Event::Warning { message });
+}
+
+/// Observer for `ModelDescription::load_observed`: one event per file read or failed
+pub fn file_observer(total: usize) -> impl FnMut(FileOutcome) {
+    let mut done = 0;
+    move |outcome| {
+        done += 1;
+        let path = match outcome {
+            FileOutcome::Parsed(record) => {
+                send(Event::FileParsed {
+                    path: &record.path,
+                    format: record.format,
+                    tensors: record.tensors,
+                    metadata_keys: record.metadata_keys,
+                    bytes: record.size,
+                    parse_ms: record.parse_time.as_secs_f64() * 1000.0,
+                    incomplete: record.incomplete.as_ref(),
+                });
+                &record.path
+            }
+            FileOutcome::Failed(path, error) => {
+                send(Event::FileFailed {
+                    path,
+                    error: format!("{error:#}"),
+                });
+                path
+            }
+            _ => return,
+        };
+        send(Event::Progress {
+            stage: "load",
+            label: &path.display().to_string(),
+            done,
+            total: total as u64,
+        });
+    }
+}
+
+/// Scan progress as events, one per percent
+#[derive(Default)]
+struct EventProgress {
+    last_percent: Option<u64>,
+}
+
+impl Progress for EventProgress {
+    fn update(&mut self, label: &str, done: u64, total: u64) -> bool {
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        if self.last_percent != Some(percent) {
+            self.last_percent = Some(percent);
+            send(Event::Progress {
+                stage: "scan",
+                label,
+                done,
+                total,
+            });
+        }
+        true
+    }
+}
+
+/// Progress of a scan: events under `--json-events`, a bar on stderr otherwise
+pub fn progress() -> Box<dyn Progress> {
+    if enabled() {
+        Box::new(EventProgress::default())
+    } else {
+        Box::new(StderrProgress::default())
+    }
+}
+
+/// Send a report: each of its findings as a `finding`, and the whole report as the summary
+/// of `done`
+pub fn report(report: &(impl Report + ?Sized)) {
+    if !enabled() {
+        return;
+    }
+    for finding in &report.findings() {
+        send(Event::Finding { finding });
+    }
+    set_summary(serde_json::to_value(report).unwrap_or(Value::Null));
+}
+
+/// Totals of the loaded files, the summary when no other operation ran
+pub fn loaded(model: &ModelDescription) {
+    set_summary(serde_json::json!({
+        "files": model.files.len(),
+        "failures": model.failures.len(),
+        "tensors": model.tensors.len(),
+        "parameters": model.total_parameters,
+        "bytes": model.total_bytes,
+        "warnings": model.warnings,
+    }));
+}
+
+pub fn set_summary(summary: Value) {
+    if let Ok(mut slot) = SUMMARY.lock() {
+        *slot = Some(summary);
+    }
+}
+
+/// Send `error` for a failed run
+pub fn error(error: &anyhow::Error) {
+    send(Event::Error {
+        message: format!("{error:#}"),
+    });
+}
+
+/// Send `done` and exit with `code`
+pub fn exit(code: i32) -> ! {
+    finish(code);
+    std::process::exit(code)
+}
+
+/// Send `done` with the summary set so far
+pub fn finish(exit_code: i32) {
+    let summary = SUMMARY
+        .lock()
+        .ok()
+        .and_then(|mut slot| slot.take())
+        .unwrap_or(Value::Null);
+    send(Event::Done { exit_code, summary });
+}
@@ -0,0 +1,230 @@
+//! The tensors llama.cpp loads for each GGUF architecture, for telling a conversion that
+//! dropped or misnamed tensors from a good one before llama.cpp fails on it. The tables are
+//! JSON, one entry per architecture:
+//!
+//! ```json
+//! {
+//!   "qwen2": {
+//!     "tensors": ["token_embd.weight", "output_norm.weight"],
+//!     "optional": ["output.weight"],
+//!     "layer": ["attn_q.weight", "attn_q.bias", { "one_of": [["attn_qkv.weight"], ["..."]] }],
+//!     "layer_optional": ["attn_output.bias"]
+//!   }
+//! }
+//! ```
+//!
+//! `layer` names go under `blk.N.` for every layer the metadata declares. A `one_of` entry
+//! is met by any of its name lists, such as a fused QKV projection or three separate ones.
+//! Adding an architecture is adding its entry.
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::OnceLock;
+
+const TABLES: &str = include_str!("expected_tensors.json");
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Name(String),
+    OneOf { one_of: Vec<Vec<String>> },
+}
+
+impl Entry {
+    /// The names meeting the entry, each list one way to meet it
+    fn alternatives(&self) -> Vec<Vec<&str>> {
+        match self {
+            Entry::Name(name) => vec![vec![name.as_str()]],
+            Entry::OneOf { one_of } => one_of
+                .iter()
+                .map(|names| names.iter().map(String::as_str).collect())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Table {
+    #[serde(default)]
+    tensors: Vec<Entry>,
+    #[serde(default)]
+    optional: Vec<String>,
+    #[serde(default)]
+    layer: Vec<Entry>,
+    #[serde(default)]
+    layer_optional: Vec<String>,
+}
+
+fn tables() -> &'static BTreeMap<String, Table> {
+    static TABLES_PARSED: OnceLock<BTreeMap<String, Table>> = OnceLock::new();
+    TABLES_PARSED.get_or_init(|| {
+        serde_json::from_str(TABLES).expect("built-in expected-tensor tables are valid")
+    })
+}
+
+/// Layers the `blk.N.` names count, for files that don't declare a block count
+pub fn block_count(names: &[&str]) -> usize {
+    names
+        .iter()
+        .filter_map(|name| split_block_name(name))
+        .map(|(layer, _)| layer + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// A tensor the table and the file disagree on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gap {
+    /// `output_norm.weight`, or `blk.*.attn_q.bias` for a layer tensor
+    pub name: String,
+    /// Layers the gap is in; empty for tensors outside the layers
+    pub layers: Vec<usize>,
+    /// Full name of one tensor of an unexpected gap, to jump to
+    pub example: Option<String>,
+}
+
+/// A file's tensor names against its architecture's table
+#[derive(Debug, Clone)]
+pub struct Checklist {
+    pub architecture: String,
+    pub layers: usize,
+    /// Tensors the table requires for this many layers
+    pub required: usize,
+    pub missing: Vec<Gap>,
+    pub unexpected: Vec<Gap>,
+}
+
+impl Checklist {
+    /// Which of `names`, all in the file, are tensors the table expects, grouped into gaps
+    /// by name as the missing ones are; for naming the ones whose data was cut off
+    pub fn expected_among(&self, names: &[&str]) -> Vec<Gap> {
+        let mut gaps = Vec::new();
+        for name in names {
+            let (gap, layer) = match split_block_name(name) {
+                Some((layer, role)) => (format!("blk.*.{role}"), Some(layer)),
+                None => (name.to_string(), None),
+            };
+            let unexpected = self.unexpected.iter().any(|unexpected| {
+                unexpected.name == gap && layer.is_none_or(|l| unexpected.layers.contains(&l))
+            });
+            if !unexpected {
+                note(&mut gaps, gap, layer, Some(name));
+            }
+        }
+        gaps
+    }
+}
+
+/// Layer index and the name within the layer of `blk.N.<name>`
+fn split_block_name(name: &str) -> Option<(usize, &str)> {
+    let (index, role) = name.strip_prefix("blk.")?.split_once('.')?;
+    Some((index.parse().ok()?, role))
+}
+
+/// Add `layer` to the gap named `name`, creating it in order of first sight
+fn note(gaps: &mut Vec<Gap>, name: String, layer: Option<usize>, example: Option<&str>) {
+    let index = match gaps.iter().position(|gap| gap.name == name) {
+        Some(index) => index,
+        None => {
+            gaps.push(Gap {
+                name,
+                layers: Vec::new(),
+                example: example.map(str::to_string),
+            });
+            gaps.len() - 1
+        }
+    };
+    gaps[index].layers.extend(layer);
+}
+
+/// How many names the entry requires, and which are missing: of the alternative `present`
+/// holds the most of, the first one on a tie
+fn missing_names(entry: &Entry, present: impl Fn(&str) -> bool) -> (usize, Vec<&str>) {
+    let alternatives = entry.alternatives();
+    let (_, best) = alternatives
+        .into_iter()
+        .enumerate()
+        .max_by_key(|(index, names)| {
+            (
+                names.iter().filter(|name| present(name)).count(),
+                std::cmp::Reverse(*index),
+            )
+        })
+        .expect("entries have a name");
+    let missing = best.iter().copied().filter(|name| !present(name)).collect();
+    (best.len(), missing)
+}
+
+/// Check tensor `names` against the table of `architecture` for a model of `layers` layers;
+/// None when there is no table for it
+pub fn checklist(architecture: &str, layers: usize, names: &[&str]) -> Option<Checklist> {
+    let table = tables().get(architecture)?;
+    let present: HashSet<&str> = names.iter().copied().collect();
+    let mut checklist = Checklist {
+        architecture: architecture.to_string(),
+        layers,
+        required: 0,
+        missing: Vec::new(),
+        unexpected: Vec::new(),
+    };
+
+    for entry in &table.tensors {
+        let (required, missing) = missing_names(entry, |name| present.contains(name));
+        checklist.required += required;
+        for name in missing {
+            note(&mut checklist.missing, name.to_string(), None, None);
+        }
+    }
+    for layer in 0..layers {
+        for entry in &table.layer {
+            let (required, missing) = missing_names(entry, |name| {
+                present.contains(format!("blk.{layer}.{name}").as_str())
+            });
+            checklist.required += required;
+            for name in missing {
+                note(
+                    &mut checklist.missing,
+                    format!("blk.*.{name}"),
+                    Some(layer),
+                    None,
+                );
+            }
+        }
+    }
+
+    let known = |entries: &[Entry], optional: &[String]| -> HashSet<String> {
+        entries
+            .iter()
+            .flat_map(Entry::alternatives)
+            .flatten()
+            .map(str::to_string)
+            .chain(optional.iter().cloned())
+            .collect()
+    };
+    let known_global = known(&table.tensors, &table.optional);
+    let known_layer = known(&table.layer, &table.layer_optional);
+    for name in names {
+        match split_block_name(name) {
+            Some((layer, role)) if layer < layers && known_layer.contains(role) => {}
+            Some((layer, role)) => note(
+                &mut checklist.unexpected,
+                format!("blk.*.{role}"),
+                Some(layer),
+                Some(name),
+            ),
+            None if known_global.contains(*name) => {}
+            None => note(
+                &mut checklist.unexpected,
+                name.to_string(),
+                None,
+                Some(name),
+            ),
+        }
+    }
+    for gap in &mut checklist.unexpected {
+        gap.layers.sort_unstable();
+        gap.layers.dedup();
+    }
+    Some(checklist)
+}
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use crossterm::{
     cursor,
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
@@ -7,455 +7,3466 @@ use crossterm::{
 };
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
-use safetensors::SafeTensors;
 use std::{
-    collections::HashSet,
-    fs::File,
-    io::{self, Read},
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use crate::gguf::GGUFFile;
+use crate::browser;
+use crate::check;
+use crate::clipboard;
+use crate::companion::{self, Companion};
+use crate::cost;
+use crate::export::{self, Listing};
+use crate::extract;
+use crate::filter::{self, FilterEntry, TypeFilter};
+use crate::gguf::{GGUFValue, merge_pieces, merge_stats};
+use crate::index;
+use crate::keymap::Keymap;
+use crate::model::{FileRecord, ModelDescription};
+use crate::name_map::{self, NameMap};
+use crate::offsets::{self, OffsetMap, RegionKind};
+use crate::peek;
+use crate::preset;
+use crate::prompt::PromptCounter;
+use crate::raw_header::{self, RawHeader};
+use crate::recent::{self, RecentEntry};
+use crate::report::{Format, Report};
+use crate::scan::{self, Histogram, Progress, ScanStatus, SparsityStats, StatsMode};
+use crate::sd_metadata::{EmbeddedImage, embedded_image};
+use crate::session::{self, Session};
+use crate::size_label;
+use crate::summary::{self, SummarySection};
+use crate::tree::{
+    GroupBy, LoadFailure, MetadataInfo, SortOrder, TensorInfo, TreeBuilder, TreeLimits, TreeNode,
+    ValueMatch, json_at_path,
+};
+use crate::treemap::{self, Level};
+use crate::ui::{DrawConfig, FilterPane, ReportView, Status, StatusLevel, Theme, UI};
+use crate::utils::{
+    complete_path, display_file_name, display_parameters, display_size, file_format, format_shape,
+    format_size, format_thousands, model_extension, parse_offset,
+};
+use crate::watch::{self, ReloadChanges, Watcher};
+
+/// View settings chosen on the command line or in the config file
+#[derive(Debug, Clone, Default)]
+pub struct ViewOptions {
+    pub group_by: GroupBy,
+    pub sort: SortOrder,
+    /// Tensors smaller than this many bytes are hidden from the tree
+    pub min_size: usize,
+    /// Exact byte and parameter counts instead of rounded ones (toggled with `u`)
+    pub exact: bool,
+    /// GGUF shapes in storage (ne) order instead of logical order (toggled with `S`)
+    pub storage_order: bool,
+    /// Tensors larger than this many bytes get sampled statistics first
+    pub sample_threshold: usize,
+    pub ascii: bool,
+    pub theme: Theme,
+    /// Batch size and context length of the cost estimate (`C`), from `--batch` and `--ctx`
+    pub cost_batch: usize,
+    pub cost_context: usize,
+    /// Depth and width past which prefix groups give way to `…` groups and flat lists
+    pub limits: TreeLimits,
+}
 
-use crate::tree::{MetadataInfo, TensorInfo, TreeBuilder, TreeNode, natural_sort_key};
-use crate::ui::{DrawConfig, UI};
+/// Size floors cycled with `m`: off, 1 KiB, 1 MiB, 100 MiB
+const MIN_SIZE_STEPS: [usize; 4] = [0, 1 << 10, 1 << 20, 100 << 20];
+
+/// Most tensors the comparison panel holds
+const MAX_PINNED: usize = 4;
+
+/// Columns ←/→ move the raw header view by
+const HEADER_COLUMN_STEP: usize = 20;
 
 pub struct Explorer {
     files: Vec<PathBuf>,
+    options: ViewOptions,
+    keymap: Keymap,
+    /// Where to save the session on quit, and the saved one still to be restored
+    session: Option<(PathBuf, Option<Session>)>,
+    /// Paths the files were found in, recorded in the recent list once they load, and how
+    /// many path sets the list keeps; None when the config turns it off
+    opened_paths: Vec<PathBuf>,
+    recent_limit: Option<usize>,
     tensors: Vec<TensorInfo>,
     metadata: Vec<MetadataInfo>,
+    /// Files that failed to parse, shown as error rows above the tree
+    failures: Vec<LoadFailure>,
+    /// Files not read because loading was cancelled; reloading reads them
+    not_loaded: Vec<PathBuf>,
+    /// Vision projectors, draft models and adapters, each shown as a component apart from
+    /// the base model
+    companions: Vec<Companion>,
+    /// Counted parameters of the base model and of each companion, for the footer
+    component_parameters: Vec<(String, usize)>,
+    /// Tables from `--name-map`, and the translated name of every tensor they match, which
+    /// the search also finds the tensor by
+    name_map: Option<NameMap>,
+    name_aliases: HashMap<String, String>,
+    /// Model subdirectories loaded side by side, each a top-level group
+    components: usize,
+    /// What each loaded file contributed, for the files panel
+    file_records: Vec<FileRecord>,
+    /// Only tensors from this file are shown (set from the files panel)
+    file_filter: Option<PathBuf>,
+    /// Files left out of the totals and the search, but still shown (greyed out)
+    excluded_files: Vec<PathBuf>,
+    /// Dtypes and categories checked in the filter pane
+    type_filter: TypeFilter,
+    /// The filter pane is open (toggled with Ctrl+F), and has the keyboard
+    filter_pane: bool,
+    filter_focus: bool,
+    filter_selected: usize,
     tree: Vec<TreeNode>,
+    /// Expansion preset from `--preset`, applied over the session once the files load
+    preset: Option<HashSet<Vec<String>>>,
+    /// Child orders set on single groups with `s`, by the group's name path
+    sort_overrides: HashMap<Vec<String>, SortOrder>,
     selected_idx: usize,
     scroll_offset: usize,
     flattened_tree: Vec<(TreeNode, usize)>,
     total_parameters: usize,
+    /// Parameters in embedding tables and output heads
+    embedding_parameters: usize,
+    /// The footer counts parameters without embeddings (toggled with `E`)
+    exclude_embeddings: bool,
     search_query: String,
     search_mode: bool,
+    /// Search only names, skipping metadata values (toggled with Tab while searching)
+    search_names_only: bool,
     filtered_tree: Vec<(TreeNode, usize)>,
+    /// For each row of `filtered_tree`, where the query was found in a metadata value
+    value_matches: Vec<Option<ValueMatch>>,
+    /// Searching moves between matches in the full tree instead of filtering it
+    /// (toggled with Ctrl+F while searching)
+    search_jump: bool,
+    /// Row selected when search mode was entered, restored when a jump search is cancelled
+    search_origin: usize,
+    /// Name paths of the jump search matches in tree order, and their names for highlighting
+    jump_matches: Vec<Vec<String>>,
+    jump_names: HashSet<String>,
+    /// Match the selection was last moved to with `n`/`N`
+    jump_index: Option<usize>,
+    show_sparsity: bool,
+    sparsity: HashMap<String, SparsityStats>,
+    duplicate_ids: HashMap<String, usize>,
+    /// Histograms computed in the tensor detail view, shown again in the comparison panel
+    histograms: HashMap<String, Histogram>,
+    /// Names of the tensors pinned with `p`, in panel order
+    pinned: Vec<String>,
+    summary: Vec<SummarySection>,
+    /// Rounded parameter count for the header, with any size label it disagrees with
+    size_label: Option<String>,
+    /// Byte regions of each loaded file, for the offset view
+    offset_maps: Vec<OffsetMap>,
+    /// Attention widths and expert counts for the cost estimate
+    cost_dimensions: cost::Dimensions,
+    /// System and user message of the context usage view, kept between visits
+    prompt_messages: (String, String),
+    status: Option<Status>,
+    /// Tensors pruned from the tree by the size floor
+    hidden_tensors: usize,
+    /// Reloads the files when they change on disk, with `--watch`
+    watcher: Option<Watcher>,
+    /// What the last reload changed, marked in the tree
+    changes: ReloadChanges,
+    /// Only the tensors the last reload changed are shown (toggled with `H`)
+    changes_only: bool,
 }
 
 impl Explorer {
-    pub fn new(files: Vec<PathBuf>) -> Self {
+    pub fn new(files: Vec<PathBuf>, options: ViewOptions, keymap: Keymap) -> Self {
         Self {
             files,
+            options,
+            keymap,
+            session: None,
+            opened_paths: Vec::new(),
+            recent_limit: None,
             tensors: Vec::new(),
             metadata: Vec::new(),
+            failures: Vec::new(),
+            not_loaded: Vec::new(),
+            companions: Vec::new(),
+            component_parameters: Vec::new(),
+            name_map: None,
+            name_aliases: HashMap::new(),
+            components: 0,
+            file_records: Vec::new(),
+            file_filter: None,
+            excluded_files: Vec::new(),
+            type_filter: TypeFilter::default(),
+            filter_pane: false,
+            filter_focus: false,
+            filter_selected: 0,
             tree: Vec::new(),
+            preset: None,
+            sort_overrides: HashMap::new(),
             selected_idx: 0,
             scroll_offset: 0,
             flattened_tree: Vec::new(),
             total_parameters: 0,
+            embedding_parameters: 0,
+            exclude_embeddings: false,
             search_query: String::new(),
             search_mode: false,
+            search_names_only: false,
             filtered_tree: Vec::new(),
+            value_matches: Vec::new(),
+            search_jump: false,
+            search_origin: 0,
+            jump_matches: Vec::new(),
+            jump_names: HashSet::new(),
+            jump_index: None,
+            show_sparsity: false,
+            sparsity: HashMap::new(),
+            duplicate_ids: HashMap::new(),
+            histograms: HashMap::new(),
+            pinned: Vec::new(),
+            summary: Vec::new(),
+            size_label: None,
+            offset_maps: Vec::new(),
+            cost_dimensions: cost::Dimensions::default(),
+            prompt_messages: (String::new(), String::new()),
+            status: None,
+            hidden_tensors: 0,
+            watcher: None,
+            changes: ReloadChanges::default(),
+            changes_only: false,
         }
     }
 
-    fn load_all_files(&mut self) -> Result<()> {
-        self.tensors.clear();
-        self.metadata.clear();
+    /// Save expansion, selection and view settings to `path` on quit, restoring `saved` on start
+    pub fn set_session(&mut self, path: PathBuf, saved: Option<Session>) {
+        self.session = Some((path, saved));
+    }
+
+    /// Expand exactly the groups of a preset once the files load, instead of the session's
+    pub fn set_preset(&mut self, paths: HashSet<Vec<String>>) {
+        self.preset = Some(paths);
+    }
 
-        let files = self.files.clone();
-        for file_path in &files {
-            let extension = file_path.extension().and_then(|s| s.to_str());
+    /// Let the search match tensors by their names translated with `names`
+    pub fn set_name_map(&mut self, names: NameMap) {
+        self.name_map = Some(names);
+    }
 
-            match extension {
-                Some("safetensors") => {
-                    self.load_safetensors_file(file_path)?;
-                }
-                Some("gguf") => {
-                    self.load_gguf_file(file_path)?;
-                }
-                _ => {
-                    eprintln!("Warning: Unsupported file format: {}", file_path.display());
-                }
-            }
+    pub fn name_map(&self) -> Option<&NameMap> {
+        self.name_map.as_ref()
+    }
+
+    /// Reload the files whenever they change on disk
+    pub fn set_watch(&mut self) {
+        self.watcher = Some(Watcher::default());
+    }
+
+    /// Remember `paths` in the recent list once their files load, keeping `limit` entries
+    pub fn set_recent(&mut self, paths: Vec<PathBuf>, limit: Option<usize>) {
+        self.opened_paths = paths;
+        self.recent_limit = limit;
+    }
+
+    fn record_recent(&self) {
+        if let Some(limit) = self.recent_limit
+            && let Err(e) = recent::record(&self.opened_paths, limit)
+        {
+            log::warn!("Failed to update the recent list: {e:#}");
         }
+    }
 
-        // Deduplicate tensors by name
-        let mut seen_names = HashSet::new();
-        self.tensors
-            .retain(|tensor| seen_names.insert(tensor.name.clone()));
+    /// Apply the saved expansion and selection; paths that no longer exist are skipped
+    fn restore_session(&mut self) {
+        let Some(saved) = self.session.as_mut().and_then(|(_, saved)| saved.take()) else {
+            return;
+        };
+        self.excluded_files = saved
+            .excluded
+            .into_iter()
+            .filter(|path| self.file_records.iter().any(|r| r.path == *path))
+            .collect();
+        self.update_totals();
+        self.sort_overrides = saved.sort_overrides.into_iter().collect();
+        if !self.sort_overrides.is_empty() {
+            self.build_tree();
+        }
+        let expanded = saved.expanded.into_iter().collect();
+        TreeBuilder::restore_expanded(&mut self.tree, &expanded);
+        self.flatten_tree();
+        self.selected_idx =
+            TreeBuilder::find_path(&self.flattened_tree, &saved.selected).unwrap_or(0);
+    }
 
-        self.tensors
-            .sort_by(|a, b| natural_sort_key(&a.name).cmp(&natural_sort_key(&b.name)));
-        self.total_parameters = self.tensors.iter().map(|t| t.num_elements).sum::<usize>();
-        self.build_tree();
-        Ok(())
+    fn save_session(&self) -> Result<()> {
+        let Some((path, _)) = &self.session else {
+            return Ok(());
+        };
+        let session = Session {
+            expanded: TreeBuilder::expanded_paths(&self.tree)
+                .into_iter()
+                .collect(),
+            selected: TreeBuilder::node_path(&self.flattened_tree, self.selected_idx),
+            group_by: self.options.group_by,
+            sort: self.options.sort,
+            min_size: self.options.min_size,
+            exact: self.options.exact,
+            storage_order: self.options.storage_order,
+            excluded: self.excluded_files.clone(),
+            sort_overrides: self
+                .sort_overrides
+                .iter()
+                .map(|(path, order)| (path.clone(), *order))
+                .collect(),
+        };
+        session::save(path, &session)
     }
 
-    fn load_safetensors_file(&mut self, file_path: &PathBuf) -> Result<()> {
-        let mut file = File::open(file_path)
-            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
-
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)
-            .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
-
-        // First, try to read metadata
-        if let Ok((_, metadata)) = SafeTensors::read_metadata(&buffer) {
-            // Check if there's a __metadata__ key in the header
-            if let Some(metadata_value) = metadata.metadata() {
-                // Parse the metadata as key-value pairs
-                for (key, value) in metadata_value {
-                    self.metadata.push(MetadataInfo {
-                        name: key.clone(),
-                        value: value.clone(),
-                        value_type: "string".to_string(),
-                    });
+    /// Read every file; `previous` holds the tensors of the same files loaded before, which
+    /// the tree marks the changes against
+    fn load_all_files(&mut self, previous: Option<&[TensorInfo]>) -> Result<()> {
+        if let Some(watcher) = &mut self.watcher {
+            watcher.loading(&self.files);
+        }
+        let model =
+            ModelDescription::load_cancellable(&self.files, &mut TuiProgress::delayed("Loading"))?;
+        self.summary = summary::build_summary(&model, &self.files);
+        self.offset_maps = offsets::offset_maps(&model);
+        self.cost_dimensions = cost::Dimensions::of(&model);
+        // Shards exported in different dtypes, or a split GGUF with parts missing, are
+        // worth a warning before anything is opened
+        let shard_warnings: Vec<String> = check::shard_check(&model)
+            .into_iter()
+            .chain(check::split_check(&model))
+            .filter(|f| f.severity > check::Severity::Info)
+            .map(|f| f.message)
+            .collect();
+        self.size_label = size_label::size_check(&model).map(|check| {
+            let disagreements: Vec<&str> =
+                check.disagreements().map(|d| d.label.as_str()).collect();
+            match disagreements.as_slice() {
+                [] => check.label(),
+                labels => format!("{} ⚠ labelled {}", check.label(), labels.join(", ")),
+            }
+        });
+        self.name_aliases.clear();
+        if let Some(names) = &self.name_map {
+            let architecture = name_map::architecture_of(&model).unwrap_or_default();
+            for tensor in &model.tensors {
+                if let Some(alias) = names.translate(&architecture, &tensor.name) {
+                    self.name_aliases.insert(tensor.name.clone(), alias);
                 }
             }
         }
+        self.changes = previous.map_or_else(ReloadChanges::default, |previous| {
+            ReloadChanges::between(previous, &model.tensors)
+        });
+        self.tensors = model.tensors;
+        self.metadata = model.metadata;
+        self.failures = model.failures;
+        self.not_loaded = model.not_loaded;
+        self.companions = model.companions;
+        self.components = model.components.len();
+        self.file_records = model.files;
+        self.type_filter.retain_dtypes(&self.tensors);
+        let records = &self.file_records;
+        self.excluded_files
+            .retain(|path| records.iter().any(|r| r.path == *path));
+        if self.excluded_files.len() >= self.file_records.len() {
+            self.excluded_files.clear();
+        }
+        if let Some(filter) = &self.file_filter
+            && !self.file_records.iter().any(|r| r.path == *filter)
+        {
+            self.file_filter = None;
+        }
+        self.update_totals();
+        self.build_tree();
+        if index::missing_index_dir(&self.files).is_some() {
+            self.set_status(
+                StatusLevel::Info,
+                format!(
+                    "No {} for these {} shards | press I to write one",
+                    index::INDEX_FILE_NAME,
+                    self.files.len()
+                ),
+            );
+        }
+        let mut warnings = model.warnings;
+        warnings.extend(shard_warnings);
+        if let Some(first) = warnings.first() {
+            let message = match warnings.len() {
+                1 => format!("Warning: {first}"),
+                n => format!("{n} warnings, first: {first}"),
+            };
+            self.set_status(StatusLevel::Warning, message);
+        }
+        if !self.not_loaded.is_empty() {
+            let reload = self.keymap.key_for("reload").unwrap_or('-');
+            self.set_status(
+                StatusLevel::Warning,
+                format!(
+                    "Load cancelled: {} of {} files loaded | press {reload} to load the rest",
+                    self.files.len() - self.not_loaded.len(),
+                    self.files.len()
+                ),
+            );
+        }
+        Ok(())
+    }
 
-        let tensors = SafeTensors::deserialize(&buffer).with_context(|| {
-            format!("Failed to parse SafeTensors file: {}", file_path.display())
-        })?;
+    /// Re-read every file from disk, keeping expanded groups and the selection by name path;
+    /// with `compare`, the files are the ones loaded before and what changed is marked
+    fn reload(&mut self, compare: bool) -> Result<()> {
+        let mut expanded = TreeBuilder::expanded_paths(&self.tree);
+        let selected = TreeBuilder::node_path(&self.flattened_tree, self.selected_idx);
 
-        for name in tensors.names() {
-            let tensor = tensors.tensor(name)?;
-            let shape = tensor.shape().to_vec();
-            let num_elements = shape.iter().product::<usize>();
-            let dtype = format!("{:?}", tensor.dtype());
-            let size_bytes = tensor.data().len();
+        let previous = compare.then(|| self.tensors.clone());
+        self.load_all_files(previous.as_deref())?;
+        // Scan results describe the old data
+        self.sparsity.clear();
+        self.show_sparsity = false;
+        self.duplicate_ids.clear();
+        self.histograms.clear();
+        let tensors = &self.tensors;
+        self.pinned
+            .retain(|name| tensors.iter().any(|t| t.name == *name));
 
-            self.tensors.push(TensorInfo {
-                name: name.to_string(),
-                dtype,
-                shape,
-                size_bytes,
-                num_elements,
-            });
+        expanded.insert(vec![watch::REMOVED_GROUP.to_string()]);
+        TreeBuilder::restore_expanded(&mut self.tree, &expanded);
+        if self.changes_only {
+            TreeBuilder::set_depth(&mut self.tree, usize::MAX);
         }
-
+        self.flatten_tree();
+        self.selected_idx = TreeBuilder::find_path(&self.flattened_tree, &selected).unwrap_or(0);
+        self.scroll_offset = 0;
         Ok(())
     }
 
-    fn load_gguf_file(&mut self, file_path: &PathBuf) -> Result<()> {
-        let mut file = File::open(file_path)
-            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
-
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)
-            .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
-
-        let gguf = GGUFFile::read(&buffer)
-            .with_context(|| format!("Failed to parse GGUF file: {}", file_path.display()))?;
-
-        // Load metadata
-        for (key, value) in &gguf.metadata {
-            let value_type = match value {
-                crate::gguf::GGUFValue::U8(_) => "u8".to_string(),
-                crate::gguf::GGUFValue::I8(_) => "i8".to_string(),
-                crate::gguf::GGUFValue::U16(_) => "u16".to_string(),
-                crate::gguf::GGUFValue::I16(_) => "i16".to_string(),
-                crate::gguf::GGUFValue::U32(_) => "u32".to_string(),
-                crate::gguf::GGUFValue::I32(_) => "i32".to_string(),
-                crate::gguf::GGUFValue::F32(_) => "f32".to_string(),
-                crate::gguf::GGUFValue::U64(_) => "u64".to_string(),
-                crate::gguf::GGUFValue::I64(_) => "i64".to_string(),
-                crate::gguf::GGUFValue::F64(_) => "f64".to_string(),
-                crate::gguf::GGUFValue::Bool(_) => "bool".to_string(),
-                crate::gguf::GGUFValue::String(_) => "string".to_string(),
-                crate::gguf::GGUFValue::Array(ty, _) => format!("array<{}>", ty),
-            };
+    /// Reload with a new file list, going back to the old one if the new one fails to load
+    fn reload_files(&mut self, files: Vec<PathBuf>) -> bool {
+        let compare = files == self.files;
+        let previous = std::mem::replace(&mut self.files, files);
+        match self.reload(compare) {
+            Ok(()) => true,
+            Err(e) => {
+                self.files = previous;
+                self.set_status(StatusLevel::Error, format!("Reload failed: {e:#}"));
+                false
+            }
+        }
+    }
 
-            self.metadata.push(MetadataInfo {
-                name: key.clone(),
-                value: value.to_string(),
-                value_type: value_type,
-            });
+    fn reload_all(&mut self) {
+        // A cancelled reload keeps its banner
+        if self.reload_files(self.files.clone()) && self.not_loaded.is_empty() {
+            let mut message = format!(
+                "Reloaded {} file(s): {} tensors, {}",
+                self.files.len(),
+                self.tensors.len(),
+                self.changes.summary(self.options.exact)
+            );
+            if self.failures.is_empty() {
+                self.set_status(StatusLevel::Success, message);
+            } else {
+                message.push_str(&format!(", {} failed to load", self.failures.len()));
+                self.set_status(StatusLevel::Warning, message);
+            }
         }
+    }
 
-        // Load tensors
-        for tensor in &gguf.tensors {
-            let shape: Vec<usize> = tensor.dimensions.iter().map(|&d| d as usize).collect();
-            let dtype = tensor.tensor_type.to_string();
+    /// Open a file or directory picked in the file browser, which starts next to the loaded
+    /// files: Enter replaces the loaded files, `a` adds to them
+    fn open_path(&mut self) -> Result<()> {
+        let start = self
+            .files
+            .first()
+            .and_then(|file| file.parent())
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+        let Some(choice) = browse_files(&start, self.options.theme, true)? else {
+            return Ok(());
+        };
+        if choice.add {
+            self.add_path(&choice.path)
+        } else {
+            self.switch_to(vec![choice.path])
+        }
+    }
 
-            // Calculate size using the element size from our custom implementation
-            let num_elements = shape.iter().product::<usize>();
-            let size_bytes =
-                (num_elements as f32 * tensor.tensor_type.element_size_bytes()) as usize;
+    /// Add a file, or the model files in a directory, to the session
+    fn add_path(&mut self, path: &Path) -> Result<()> {
+        let input = path.display();
+        let found = crate::collect_safetensors_files(
+            &[path.to_path_buf()],
+            &crate::walk::WalkOptions::default(),
+        )?;
+        let mut files = self.files.clone();
+        let mut added = 0;
+        for file in found {
+            if !files.contains(&file) {
+                files.push(file);
+                added += 1;
+            }
+        }
+        if added == 0 {
+            self.set_status(
+                StatusLevel::Warning,
+                format!("No new model files found in {input}"),
+            );
+            return Ok(());
+        }
+        files.sort();
+        if self.reload_files(files) {
+            self.set_status(
+                StatusLevel::Success,
+                format!("Added {added} file(s) from {input}"),
+            );
+        }
+        Ok(())
+    }
 
-            self.tensors.push(TensorInfo {
-                name: tensor.name.clone(),
-                dtype,
-                shape,
-                size_bytes,
-                num_elements,
-            });
+    /// Replace the loaded files with a recently opened path set, saving this model's session
+    /// and restoring the other's
+    fn switch_recent(&mut self) -> Result<()> {
+        if self.recent_limit.is_none() {
+            self.set_status(
+                StatusLevel::Info,
+                "The recent list is turned off in the config file",
+            );
+            return Ok(());
         }
+        let Some(paths) = choose_recent(self.options.theme)? else {
+            return Ok(());
+        };
+        self.switch_to(paths)
+    }
 
+    /// Replace the loaded files with the model files found in `paths`, saving this model's
+    /// session and restoring the other's
+    fn switch_to(&mut self, paths: Vec<PathBuf>) -> Result<()> {
+        let files = crate::collect_safetensors_files(&paths, &crate::walk::WalkOptions::default())?;
+        if files.is_empty() {
+            self.set_status(StatusLevel::Error, "No model files found in those paths");
+            return Ok(());
+        }
+        if let Err(e) = self.save_session() {
+            self.set_status(
+                StatusLevel::Warning,
+                format!("Failed to save session: {e:#}"),
+            );
+        }
+        let saved = self.session.as_ref().and_then(|_| {
+            let path = session::session_path(&files)?;
+            Some((path.clone(), session::load(&path)))
+        });
+        if let Some((_, Some(saved))) = &saved {
+            self.options.group_by = saved.group_by;
+            self.options.sort = saved.sort;
+            self.options.min_size = saved.min_size;
+            self.options.exact = saved.exact;
+            self.options.storage_order = saved.storage_order;
+        }
+        self.file_filter = None;
+        self.excluded_files.clear();
+        self.sort_overrides.clear();
+        if !self.reload_files(files) {
+            return Ok(());
+        }
+        if saved.is_some() {
+            self.session = saved;
+            self.restore_session();
+        }
+        self.opened_paths = paths;
+        self.record_recent();
+        self.set_status(
+            StatusLevel::Success,
+            format!(
+                "Opened {} file(s): {} tensors",
+                self.files.len(),
+                self.tensors.len()
+            ),
+        );
         Ok(())
     }
 
-    fn build_tree(&mut self) {
-        if self.metadata.is_empty() {
-            self.tree = TreeBuilder::build_tree(&self.tensors);
-        } else {
-            self.tree = TreeBuilder::build_tree_mixed(&self.tensors, &self.metadata);
+    fn is_excluded(&self, tensor: &TensorInfo) -> bool {
+        self.excluded_files.contains(&tensor.source_file)
+    }
+
+    /// Footer parameter counts over the files not excluded
+    fn update_totals(&mut self) {
+        let counted: Vec<TensorInfo> = self
+            .tensors
+            .iter()
+            .filter(|t| !self.is_excluded(t))
+            .cloned()
+            .collect();
+        self.total_parameters = summary::total_parameters(&counted);
+        self.embedding_parameters = summary::embedding_parameters(&counted);
+        self.component_parameters.clear();
+        if !self.companions.is_empty() {
+            let base: Vec<TensorInfo> = counted
+                .iter()
+                .filter(|t| companion::companion_of(&self.companions, t).is_none())
+                .cloned()
+                .collect();
+            self.component_parameters.push((
+                companion::base_label(&self.companions).to_string(),
+                summary::total_parameters(&base),
+            ));
+            for c in &self.companions {
+                let tensors: Vec<TensorInfo> = counted
+                    .iter()
+                    .filter(|t| t.source_file == c.file)
+                    .cloned()
+                    .collect();
+                self.component_parameters.push((
+                    companion::component_label(&self.companions, c),
+                    summary::total_parameters(&tensors),
+                ));
+            }
         }
-        self.flatten_tree();
     }
 
-    fn flatten_tree(&mut self) {
-        self.flattened_tree = TreeBuilder::flatten_tree(&self.tree);
-        self.update_filtered_tree();
+    fn counted_bytes(&self) -> usize {
+        self.tensors
+            .iter()
+            .filter(|t| !self.is_excluded(t))
+            .map(|t| t.size_bytes)
+            .sum()
     }
 
-    fn update_filtered_tree(&mut self) {
-        if self.search_query.is_empty() {
-            self.filtered_tree = self.flattened_tree.clone();
+    /// In the per-file view, Space on a file's group leaves the file out of the totals and
+    /// the search, or counts it again; false when the selection is not a file group
+    fn toggle_excluded_file(&mut self) -> bool {
+        if self.options.group_by != GroupBy::File {
+            return false;
+        }
+        let Some((TreeNode::Group { name, .. }, 0)) = self.flattened_tree.get(self.selected_idx)
+        else {
+            return false;
+        };
+        let Some(path) = self
+            .file_records
+            .iter()
+            .map(|r| &r.path)
+            .find(|path| display_file_name(path) == *name)
+            .cloned()
+        else {
+            return false;
+        };
+        if let Some(position) = self.excluded_files.iter().position(|p| *p == path) {
+            self.excluded_files.remove(position);
+            self.set_status(StatusLevel::Info, format!("Counting {name} again"));
+        } else if self.excluded_files.len() + 1 == self.file_records.len() {
+            self.set_status(StatusLevel::Warning, "At least one file has to be counted");
+            return true;
         } else {
-            let matcher = SkimMatcherV2::default();
-            let mut scored_results: Vec<(TreeNode, i64)> = Vec::new();
+            self.excluded_files.push(path);
+            self.set_status(
+                StatusLevel::Info,
+                format!("Left {name} out of the totals | Space again counts it"),
+            );
+        }
+        self.update_totals();
+        true
+    }
 
-            // Search through ALL tensors, not just the flattened tree
-            for tensor in &self.tensors {
-                if let Some(score) = matcher.fuzzy_match(&tensor.name, &self.search_query) {
-                    scored_results.push((
-                        TreeNode::Tensor {
-                            info: tensor.clone(),
-                        },
-                        score,
-                    ));
-                }
+    /// Space: on a file group in the per-file view, count or stop counting the file;
+    /// anywhere else the same as Enter
+    fn toggle_file_or_select(&mut self) {
+        if !self.toggle_excluded_file() {
+            self.handle_selection();
+        }
+    }
+
+    /// Drop the file whose group is selected in the per-file view
+    fn drop_selected_file(&mut self) {
+        let selected = match self.flattened_tree.get(self.selected_idx) {
+            Some((TreeNode::Group { name, .. }, 0)) if self.options.group_by == GroupBy::File => {
+                name.clone()
             }
+            Some((TreeNode::Failed { name, .. }, _)) => name.clone(),
+            _ => {
+                self.set_status(
+                    StatusLevel::Warning,
+                    "Select a failed file, or a file group in the per-file view (G), to drop it",
+                );
+                return;
+            }
+        };
+        let Some(position) = self.files.iter().position(|f| {
+            f.file_name()
+                .is_some_and(|name| name.to_string_lossy() == selected)
+        }) else {
+            self.set_status(
+                StatusLevel::Warning,
+                format!("{selected} is not a loaded file"),
+            );
+            return;
+        };
+        if self.files.len() == 1 {
+            self.set_status(StatusLevel::Warning, "Cannot drop the only loaded file");
+            return;
+        }
+        let mut files = self.files.clone();
+        files.remove(position);
+        if self.reload_files(files) {
+            self.set_status(StatusLevel::Success, format!("Dropped {selected}"));
+        }
+    }
 
-            // Also search through metadata if present
-            for metadata in &self.metadata {
-                if let Some(score) = matcher.fuzzy_match(&metadata.name, &self.search_query) {
-                    scored_results.push((
-                        TreeNode::Metadata {
-                            info: metadata.clone(),
-                        },
-                        score,
-                    ));
+    /// Copy the selected tensor or key name, or the full names of every tensor under the
+    /// selected group, one per line or as a JSON array; with nothing selected, offer to
+    /// copy every tensor name in the model
+    fn copy_names(&mut self, json: bool) -> Result<()> {
+        let names: Vec<String> = match self.flattened_tree.get(self.selected_idx) {
+            Some((node @ TreeNode::Group { .. }, _)) => {
+                node.tensors().iter().map(|t| t.name.clone()).collect()
+            }
+            Some((TreeNode::Tensor { info }, _)) => vec![info.name.clone()],
+            Some((TreeNode::Metadata { info }, _)) => vec![info.name.clone()],
+            _ => {
+                let rows = [format!(
+                    "Copy all {} tensor names in the model",
+                    self.tensors.len()
+                )];
+                if self
+                    .pick("Copy Tensor Names", "Nothing is selected", &rows)?
+                    .is_none()
+                {
+                    return Ok(());
                 }
+                self.tensors.iter().map(|t| t.name.clone()).collect()
             }
+        };
+        if names.is_empty() {
+            self.set_status(StatusLevel::Warning, "No tensors under this group");
+            return Ok(());
+        }
+        let text = if json {
+            serde_json::to_string_pretty(&names)?
+        } else {
+            names.join("\n")
+        };
+        let copied = match names.as_slice() {
+            [name] if !json => name.clone(),
+            _ => format!(
+                "{} name{}{}",
+                names.len(),
+                if names.len() == 1 { "" } else { "s" },
+                if json { " as a JSON array" } else { "" }
+            ),
+        };
+        match clipboard::copy(&text) {
+            Ok(method) => self.set_status(
+                StatusLevel::Success,
+                format!("Copied {copied} (via {})", method.label()),
+            ),
+            Err(e) => self.set_status(StatusLevel::Error, format!("Copy failed: {e:#}")),
+        }
+        Ok(())
+    }
 
-            // Sort by score (highest first)
-            scored_results.sort_by(|a, b| b.1.cmp(&a.1));
+    /// Overlay of the largest tensors still shown under the size floor; Enter jumps to one
+    fn show_top_tensors(&mut self) -> Result<()> {
+        let model_bytes = self.tensors.iter().map(|t| t.size_bytes).sum();
+        let shown: Vec<TensorInfo> = self
+            .tensors
+            .iter()
+            .filter(|t| t.size_bytes >= self.options.min_size)
+            .cloned()
+            .collect();
+        let top = summary::largest_tensors(&shown, summary::TOP_TENSORS);
+        let (header, rows) = summary::top_tensor_table(&top, model_bytes);
+        let title = format!("Top {} Largest Tensors", top.len());
 
-            // Create a flat list with depth 0 for all results
-            self.filtered_tree = scored_results
-                .into_iter()
-                .map(|(node, _)| (node, 0))
-                .collect();
+        if let Some(tensor) = self
+            .pick(&title, &header, &rows)?
+            .and_then(|idx| top.get(idx))
+        {
+            self.scroll_offset = 0;
+            self.select_tensor(&tensor.name);
         }
+        Ok(())
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        if self.files.is_empty() {
+    /// One bar per transformer layer; Enter jumps to the layer's group in the prefix tree
+    fn show_layer_chart(&mut self) -> Result<()> {
+        let layers = summary::layer_sizes(&self.tensors);
+        if layers.is_empty() {
+            self.set_status(StatusLevel::Warning, "No numbered layers found");
             return Ok(());
         }
+        let (terminal_width, _) = terminal::size()?;
+        let rows = summary::layer_chart_lines(&layers, (terminal_width as usize).saturating_sub(1));
+        let largest = layers.iter().map(|l| l.bytes).max().unwrap_or(0);
+        let header = format!(
+            "{} layers | bars relative to the largest layer ({})",
+            layers.len(),
+            format_size(largest)
+        );
+        let Some(layer) = self
+            .pick("Layer Sizes", &header, &rows)?
+            .and_then(|idx| layers.get(idx))
+        else {
+            return Ok(());
+        };
 
-        terminal::enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, terminal::Clear(ClearType::All), cursor::Hide)?;
-
-        let result = self.interactive_loop();
-
-        execute!(stdout, terminal::Clear(ClearType::All), cursor::Show)?;
-        terminal::disable_raw_mode()?;
-
-        result
+        if self.options.group_by != GroupBy::Prefix {
+            self.options.group_by = GroupBy::Prefix;
+            self.build_tree();
+            self.set_status(StatusLevel::Info, "Grouped by prefix to show the layer");
+        }
+        let path: Vec<String> = layer.prefix.split('.').map(str::to_string).collect();
+        if TreeBuilder::reveal_group(&mut self.tree, &path) {
+            self.flatten_tree();
+            self.selected_idx = TreeBuilder::find_path(&self.flattened_tree, &path).unwrap_or(0);
+            self.scroll_offset = 0;
+        }
+        Ok(())
     }
 
-    fn interactive_loop(&mut self) -> Result<()> {
-        self.load_all_files()?;
-
+    /// Treemap of the tree's top level by bytes; Enter opens a group or the "other" block,
+    /// or selects a tensor in the tree, and Esc goes back up
+    fn show_treemap(&mut self) -> Result<()> {
+        // A chain of single groups opens at its first level with a choice
+        let open = |levels: &mut Vec<Level<'_>>| {
+            while let Some(level) = levels.last()
+                && let [TreeNode::Group { name, children, .. }] = level.nodes.as_slice()
+            {
+                levels.push(Level {
+                    title: name.clone(),
+                    nodes: treemap::level_nodes(children),
+                    parent: Some(name),
+                    selected: 0,
+                });
+            }
+        };
+        let mut levels = vec![Level {
+            title: self.model_title(),
+            nodes: treemap::level_nodes(&self.tree),
+            parent: None,
+            selected: 0,
+        }];
+        open(&mut levels);
+        let model_bytes = self.tensors.iter().map(|t| t.size_bytes).sum();
+        let mut jump_to = None;
         loop {
-            let title = if self.files.len() == 1 {
-                self.files[0].to_string_lossy().to_string()
-            } else {
-                "SafeTensors Model".to_string()
+            let (width, height) = UI::treemap_area()?;
+            let Some(level) = levels.last_mut() else {
+                break;
             };
-
-            let tree_to_display = if self.search_mode {
-                &self.filtered_tree
-            } else {
-                &self.flattened_tree
+            let blocks = treemap::layout(&level.nodes, width, height);
+            level.selected = level.selected.min(blocks.len().saturating_sub(1));
+            UI::draw_treemap(
+                &levels,
+                &blocks,
+                model_bytes,
+                self.options.exact,
+                self.options.ascii,
+                self.options.theme,
+            )?;
+            let Event::Key(key_event) = event::read()? else {
+                continue;
             };
-
-            let config = DrawConfig {
-                tree: tree_to_display,
-                current_file: &title,
-                file_idx: 0,
-                total_files: 1,
-                total_parameters: self.total_parameters,
-                selected_idx: self.selected_idx,
-                scroll_offset: self.scroll_offset,
-                search_mode: self.search_mode,
-                search_query: &self.search_query,
+            let Some(level) = levels.last_mut() else {
+                break;
             };
-            self.scroll_offset = UI::draw_screen(&config)?;
-
-            if let Event::Key(key_event) = event::read()? {
-                match key_event {
-                    KeyEvent {
-                        code: KeyCode::Char('q'),
-                        ..
-                    } => {
-                        if self.search_mode {
-                            self.exit_search_mode();
-                        } else {
-                            break;
-                        }
-                    }
-                    KeyEvent {
-                        code: KeyCode::Char('c'),
-                        modifiers: KeyModifiers::CONTROL,
-                        ..
-                    } => break,
-                    KeyEvent {
-                        code: KeyCode::Char('/'),
-                        ..
-                    } => {
-                        if !self.search_mode {
-                            self.enter_search_mode();
-                        }
-                    }
-                    KeyEvent {
-                        code: KeyCode::Esc, ..
-                    } => {
-                        if self.search_mode {
-                            self.exit_search_mode();
-                        }
+            let direction = match key_event.code {
+                KeyCode::Left => Some((-1, 0)),
+                KeyCode::Right => Some((1, 0)),
+                KeyCode::Up => Some((0, -1)),
+                KeyCode::Down => Some((0, 1)),
+                _ => None,
+            };
+            if let Some((dx, dy)) = direction {
+                if let Some(next) = treemap::neighbor(&blocks, level.selected, dx, dy) {
+                    level.selected = next;
+                }
+                continue;
+            }
+            match key_event.code {
+                KeyCode::Tab if !blocks.is_empty() => {
+                    level.selected = (level.selected + 1) % blocks.len();
+                }
+                KeyCode::BackTab if !blocks.is_empty() => {
+                    level.selected = (level.selected + blocks.len() - 1) % blocks.len();
+                }
+                KeyCode::Enter => match blocks.get(level.selected).map(|b| b.nodes.as_slice()) {
+                    Some([TreeNode::Tensor { info }]) => {
+                        jump_to = Some(info.name.clone());
+                        break;
                     }
-                    KeyEvent {
-                        code: KeyCode::Up, ..
-                    } => self.move_selection(-1),
-                    KeyEvent {
-                        code: KeyCode::Down,
-                        ..
-                    } => self.move_selection(1),
-                    KeyEvent {
-                        code: KeyCode::Enter,
-                        ..
-                    } => {
-                        if self.search_mode {
-                            self.exit_search_mode();
-                        } else {
-                            self.handle_selection();
+                    Some([TreeNode::Group { name, children, .. }]) => {
+                        let level = Level {
+                            title: name.clone(),
+                            nodes: treemap::level_nodes(children),
+                            parent: Some(name),
+                            selected: 0,
+                        };
+                        levels.push(level);
+                        open(&mut levels);
+                        // A group of one tensor, such as `down_proj` holding `weight`
+                        if let Some(level) = levels.last()
+                            && let [TreeNode::Tensor { info }] = level.nodes.as_slice()
+                        {
+                            jump_to = Some(info.name.clone());
+                            break;
                         }
                     }
-                    KeyEvent {
-                        code: KeyCode::Char(' '),
-                        ..
-                    } => {
-                        if !self.search_mode {
-                            self.handle_selection();
-                        }
+                    Some(nodes) if nodes.len() > 1 => {
+                        let level = Level {
+                            title: "other".to_string(),
+                            nodes: nodes.to_vec(),
+                            parent: level.parent,
+                            selected: 0,
+                        };
+                        levels.push(level);
                     }
-                    KeyEvent {
-                        code: KeyCode::Backspace,
-                        ..
-                    } => {
-                        if self.search_mode {
-                            self.search_query.pop();
-                            self.update_filtered_tree();
-                            self.selected_idx = 0;
-                            self.scroll_offset = 0;
-                        }
+                    _ => {}
+                },
+                KeyCode::Esc | KeyCode::Backspace => {
+                    // Back past levels that were only passed through
+                    levels.pop();
+                    while levels.len() > 1 && levels.last().is_some_and(|l| l.nodes.len() == 1) {
+                        levels.pop();
                     }
-                    KeyEvent {
-                        code: KeyCode::Char(c),
-                        ..
-                    } => {
-                        if self.search_mode {
-                            self.search_query.push(c);
-                            self.update_filtered_tree();
-                            self.selected_idx = 0;
-                            self.scroll_offset = 0;
-                        }
+                    if levels.is_empty() || levels.iter().all(|l| l.nodes.len() == 1) {
+                        break;
                     }
-                    // Remove left/right file navigation since we're showing all files merged
-                    _ => {}
                 }
+                KeyCode::Char('q') => break,
+                _ => {}
             }
         }
-
+        if let Some(name) = jump_to
+            && !self.select_tensor(&name)
+        {
+            self.set_status(
+                StatusLevel::Warning,
+                format!("{name} is hidden by a filter or the size floor"),
+            );
+        }
         Ok(())
     }
 
-    fn move_selection(&mut self, delta: i32) {
-        let tree = if self.search_mode {
-            &self.filtered_tree
-        } else {
-            &self.flattened_tree
-        };
+    /// Estimated FLOPs and bytes read per token, layer by layer; `c` and `b` change the
+    /// context length and batch size
+    fn show_cost_estimate(&mut self) -> Result<()> {
+        const TITLE: &str = "Cost Estimate per Token";
+        let mut scroll_offset = 0;
+        loop {
+            let tensors: Vec<TensorInfo> = self
+                .tensors
+                .iter()
+                .filter(|t| companion::companion_of(&self.companions, t).is_none())
+                .cloned()
+                .collect();
+            let estimate = cost::estimate(
+                &tensors,
+                self.cost_dimensions,
+                self.options.cost_batch,
+                self.options.cost_context,
+            );
+            let mut lines = estimate.text_lines();
+            lines.push(String::new());
+            lines.push("c to change the context length, b to change the batch size".to_string());
+            scroll_offset = UI::draw_report(TITLE, &lines, scroll_offset)?;
+            let (_, terminal_height) = terminal::size()?;
+            let page = (terminal_height as usize).saturating_sub(4).max(1);
 
-        if tree.is_empty() {
+            let Event::Key(key_event) = event::read()? else {
+                continue;
+            };
+            match key_event.code {
+                KeyCode::Up => scroll_offset = scroll_offset.saturating_sub(1),
+                KeyCode::Down => scroll_offset += 1,
+                KeyCode::PageUp => scroll_offset = scroll_offset.saturating_sub(page),
+                KeyCode::PageDown => scroll_offset += page,
+                KeyCode::Home => scroll_offset = 0,
+                KeyCode::End => scroll_offset = lines.len(),
+                KeyCode::Char(key @ ('c' | 'b')) => {
+                    let (label, current) = if key == 'c' {
+                        ("Context length (tokens):", self.options.cost_context)
+                    } else {
+                        ("Batch size:", self.options.cost_batch)
+                    };
+                    let Some(input) = self.prompt_text(TITLE, label, &current.to_string())? else {
+                        continue;
+                    };
+                    match input.trim().replace(',', "").parse::<usize>().ok() {
+                        Some(value) if key == 'c' => self.options.cost_context = value,
+                        Some(value) => self.options.cost_batch = value.max(1),
+                        None => {}
+                    }
+                }
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Tokens of a system and user message put through the chat template, against the
+    /// context length. Pasted text becomes the user message.
+    fn show_context_usage(&mut self) -> Result<()> {
+        let counter = match PromptCounter::from_metadata(&self.metadata) {
+            Ok(counter) => counter,
+            Err(e) => {
+                self.set_status(StatusLevel::Warning, e.to_string());
+                return Ok(());
+            }
+        };
+        let mut stdout = io::stdout();
+        execute!(stdout, event::EnableBracketedPaste)?;
+        let result = self.context_usage_loop(&counter);
+        execute!(stdout, event::DisableBracketedPaste)?;
+        result
+    }
+
+    fn context_usage_loop(&mut self, counter: &PromptCounter) -> Result<()> {
+        const TITLE: &str = "Context Usage";
+        let mut scroll_offset = 0;
+        let mut note = None;
+        loop {
+            let (system, user) = &self.prompt_messages;
+            let mut lines = counter.count(system, user).text_lines();
+            lines.push(String::new());
+            if let Some(note) = note.take() {
+                lines.push(note);
+            }
+            lines.push(
+                "s to set the system message, u the user message (@path reads a file); pasted text becomes the user message"
+                    .to_string(),
+            );
+            scroll_offset = UI::draw_report(TITLE, &lines, scroll_offset)?;
+            let (_, terminal_height) = terminal::size()?;
+            let page = (terminal_height as usize).saturating_sub(4).max(1);
+
+            let key_event = match event::read()? {
+                Event::Paste(text) => {
+                    self.prompt_messages.1 = text;
+                    continue;
+                }
+                Event::Key(key_event) => key_event,
+                _ => continue,
+            };
+            match key_event.code {
+                KeyCode::Up => scroll_offset = scroll_offset.saturating_sub(1),
+                KeyCode::Down => scroll_offset += 1,
+                KeyCode::PageUp => scroll_offset = scroll_offset.saturating_sub(page),
+                KeyCode::PageDown => scroll_offset += page,
+                KeyCode::Home => scroll_offset = 0,
+                KeyCode::End => scroll_offset = lines.len(),
+                KeyCode::Char(key @ ('s' | 'u')) => {
+                    let label = if key == 's' {
+                        "System message (@path reads a file, - for none):"
+                    } else {
+                        "User message (@path reads a file):"
+                    };
+                    let Some(input) = self.prompt_text(TITLE, label, "")? else {
+                        continue;
+                    };
+                    let message = match input.strip_prefix('@') {
+                        Some(path) => match std::fs::read_to_string(path) {
+                            Ok(content) => content,
+                            Err(e) => {
+                                note = Some(format!("Failed to read {path}: {e}"));
+                                continue;
+                            }
+                        },
+                        None if key == 's' && input == "-" => String::new(),
+                        None => input,
+                    };
+                    if key == 's' {
+                        self.prompt_messages.0 = message;
+                    } else {
+                        self.prompt_messages.1 = message;
+                    }
+                }
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Layers compared role by role, as `--check` does
+    fn show_layer_audit(&mut self) -> Result<()> {
+        let findings = check::layer_audit(&self.tensors);
+        if findings.is_empty() {
+            self.set_status(StatusLevel::Warning, "No numbered layers to compare");
+            return Ok(());
+        }
+        self.show_findings(ReportView::new("Layer Audit", Vec::new(), findings))
+    }
+
+    /// Findings of an analysis in the report view: Enter selects the related tensor in the
+    /// tree, `e` writes the findings to a JSON file
+    fn show_findings(&mut self, mut view: ReportView) -> Result<()> {
+        loop {
+            view.draw(self.options.theme)?;
+            let Event::Key(key_event) = event::read()? else {
+                continue;
+            };
+            view.note = None;
+            match key_event.code {
+                KeyCode::Up => view.move_selection(-1),
+                KeyCode::Down => view.move_selection(1),
+                KeyCode::PageUp => view.move_selection(-10),
+                KeyCode::PageDown => view.move_selection(10),
+                KeyCode::Home => view.selected = 0,
+                KeyCode::End => view.move_selection(isize::MAX),
+                KeyCode::Enter => {
+                    let Some(name) = view
+                        .selected_finding()
+                        .and_then(|f| f.related_node_path.clone())
+                    else {
+                        continue;
+                    };
+                    if !self.select_tensor(&name) {
+                        self.set_status(
+                            StatusLevel::Warning,
+                            format!("{name} is hidden by a filter or the size floor"),
+                        );
+                    }
+                    return Ok(());
+                }
+                KeyCode::Char('e') => {
+                    let Some(path) =
+                        self.prompt_text(&view.title, "Output path:", "findings.json")?
+                    else {
+                        continue;
+                    };
+                    view.note = Some(
+                        match check::write_findings(&view.findings, Path::new(&path)) {
+                            Ok(()) => format!("Wrote {} findings to {path}", view.findings.len()),
+                            Err(e) => format!("Export failed: {e:#}"),
+                        },
+                    );
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Regions of each file in offset order; `g` asks for a byte offset and selects the
+    /// region holding it, Enter on a tensor selects it in the tree
+    fn show_offsets(&mut self) -> Result<()> {
+        if self.offset_maps.is_empty() {
+            self.set_status(
+                StatusLevel::Warning,
+                "No loaded file records tensor offsets",
+            );
+            return Ok(());
+        }
+        let (mut file_idx, mut selected, mut scroll_offset) = (0, 0, 0);
+        let mut note: Option<String> = None;
+        loop {
+            let map = &self.offset_maps[file_idx];
+            let last = map.regions.len().saturating_sub(1);
+            scroll_offset = UI::draw_offsets(
+                map,
+                (file_idx, self.offset_maps.len()),
+                selected,
+                scroll_offset,
+                note.as_deref(),
+                self.options.theme,
+            )?;
+            let Event::Key(key_event) = event::read()? else {
+                continue;
+            };
+            match key_event.code {
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected = (selected + 1).min(last),
+                KeyCode::PageUp => selected = selected.saturating_sub(10),
+                KeyCode::PageDown => selected = (selected + 10).min(last),
+                KeyCode::Home => selected = 0,
+                KeyCode::End => selected = last,
+                KeyCode::Left | KeyCode::Right => {
+                    let count = self.offset_maps.len();
+                    file_idx = match key_event.code {
+                        KeyCode::Left => (file_idx + count - 1) % count,
+                        _ => (file_idx + 1) % count,
+                    };
+                    (selected, scroll_offset, note) = (0, 0, None);
+                }
+                KeyCode::Char('g') | KeyCode::Char('/') => {
+                    let Some(input) = self.prompt_text("Locate Offset", "Byte offset:", "")? else {
+                        continue;
+                    };
+                    note = Some(match parse_offset(&input) {
+                        Ok(offset) => {
+                            let location = map.locate(offset);
+                            if let Some(idx) = map.region_index(offset) {
+                                selected = idx;
+                            }
+                            offsets::describe_location(&map.path, offset, &location, map.file_size)
+                        }
+                        Err(e) => e,
+                    });
+                }
+                KeyCode::Enter => {
+                    let Some(region) = map.regions.get(selected) else {
+                        continue;
+                    };
+                    if region.kind != RegionKind::Tensor {
+                        continue;
+                    }
+                    let name = region.name.clone();
+                    if !self.select_tensor(&name) {
+                        self.set_status(
+                            StatusLevel::Warning,
+                            format!("{name} is hidden by a filter or the size floor"),
+                        );
+                    }
+                    return Ok(());
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Panel of the loaded files; Enter jumps to the file's group in the per-file view, or
+    /// otherwise limits the tree to its tensors (Enter on that file again lifts the limit)
+    fn show_files(&mut self) -> Result<()> {
+        // Start on the file the tree is limited to, if any
+        let mut selected = self
+            .file_records
+            .iter()
+            .position(|r| Some(r.path.as_path()) == self.file_filter.as_deref())
+            .unwrap_or(0);
+        let mut scroll_offset = 0;
+        let last = self.file_records.len().saturating_sub(1);
+        loop {
+            scroll_offset = UI::draw_files(
+                &self.file_records,
+                selected,
+                scroll_offset,
+                self.file_filter.as_deref(),
+                self.options.exact,
+                self.options.ascii,
+                self.options.theme,
+            )?;
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Down => selected = (selected + 1).min(last),
+                    KeyCode::Home => selected = 0,
+                    KeyCode::End => selected = last,
+                    KeyCode::Enter => {
+                        if let Some(record) = self.file_records.get(selected) {
+                            let path = record.path.clone();
+                            self.focus_file(&path);
+                        }
+                        return Ok(());
+                    }
+                    KeyCode::Char('h') => {
+                        if let Some(record) = self.file_records.get(selected) {
+                            self.show_raw_header(&record.path)?;
+                        }
+                    }
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// The JSON header of a safetensors file as written, for debugging its producer
+    fn show_raw_header(&self, path: &Path) -> Result<()> {
+        let title = format!("Header of {}", display_file_name(path));
+        if model_extension(path) != Some("safetensors") {
+            let lines = [format!(
+                "{} is a {} file; only safetensors files have a JSON header",
+                display_file_name(path),
+                file_format(path)
+            )];
+            return self.show_report(&title, &lines);
+        }
+        let header = match RawHeader::read(path) {
+            Ok(header) => header,
+            Err(e) => return self.show_report(&title, &[format!("{e:#}")]),
+        };
+        let summary = header.summary();
+        let lines = raw_header::pretty_print(&header.json);
+        let (mut scroll_offset, mut column) = (0, 0);
+        loop {
+            scroll_offset = UI::draw_raw_header(
+                &title,
+                &summary,
+                &lines,
+                scroll_offset,
+                column,
+                self.options.theme,
+            )?;
+            let (_, terminal_height) = terminal::size()?;
+            let page = (terminal_height as usize)
+                .saturating_sub(5 + summary.len())
+                .max(1);
+
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Up => scroll_offset = scroll_offset.saturating_sub(1),
+                    KeyCode::Down => scroll_offset += 1,
+                    KeyCode::PageUp => scroll_offset = scroll_offset.saturating_sub(page),
+                    KeyCode::PageDown => scroll_offset += page,
+                    KeyCode::Home => (scroll_offset, column) = (0, 0),
+                    KeyCode::End => scroll_offset = lines.len(),
+                    KeyCode::Left => column = column.saturating_sub(HEADER_COLUMN_STEP),
+                    KeyCode::Right => column += HEADER_COLUMN_STEP,
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn focus_file(&mut self, path: &Path) {
+        let name = display_file_name(path);
+        if self.options.group_by == GroupBy::File {
+            if self.file_filter.take().is_some() {
+                self.rebuild_keeping_selection();
+            }
+            match TreeBuilder::find_path(&self.flattened_tree, std::slice::from_ref(&name)) {
+                Some(row) => {
+                    self.selected_idx = row;
+                    self.set_status(StatusLevel::Info, format!("Jumped to {name}"));
+                }
+                None => self.set_status(
+                    StatusLevel::Warning,
+                    format!("{name} has no tensors in the tree"),
+                ),
+            }
             return;
         }
+        if self.file_filter.as_deref() == Some(path) {
+            self.file_filter = None;
+            self.set_status(StatusLevel::Info, "Showing tensors from every file");
+        } else {
+            self.file_filter = Some(path.to_path_buf());
+            self.set_status(
+                StatusLevel::Info,
+                format!(
+                    "Showing only tensors from {name} | F, then Enter on it again shows every file"
+                ),
+            );
+        }
+        self.rebuild_keeping_selection();
+        self.update_filtered_tree();
+    }
 
-        let new_idx = if delta < 0 {
-            self.selected_idx.saturating_sub((-delta) as usize)
+    /// Let the user choose one of `rows`; None when they back out with q/Esc
+    fn pick(&self, title: &str, header: &str, rows: &[String]) -> Result<Option<usize>> {
+        let (mut selected, mut scroll_offset) = (0, 0);
+        let last = rows.len().saturating_sub(1);
+        loop {
+            scroll_offset = UI::draw_picker(
+                title,
+                header,
+                rows,
+                selected,
+                scroll_offset,
+                self.options.theme,
+            )?;
+            let (_, terminal_height) = terminal::size()?;
+            let page = (terminal_height as usize).saturating_sub(5).max(1);
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Down => selected = (selected + 1).min(last),
+                    KeyCode::PageUp => selected = selected.saturating_sub(page),
+                    KeyCode::PageDown => selected = (selected + page).min(last),
+                    KeyCode::Home => selected = 0,
+                    KeyCode::End => selected = last,
+                    KeyCode::Enter => return Ok((!rows.is_empty()).then_some(selected)),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Aggregates of the selected group: totals, dtype breakdown, extremes, shapes and layers
+    fn show_group_info(&mut self) -> Result<()> {
+        let Some((node @ TreeNode::Group { tensor_count, .. }, _)) =
+            self.flattened_tree.get(self.selected_idx)
+        else {
+            self.set_status(StatusLevel::Warning, "Select a group to show its info");
+            return Ok(());
+        };
+        let Some(stats) = node.group_stats() else {
+            return Ok(());
+        };
+        let exact = self.options.exact;
+        let path = TreeBuilder::node_path(&self.flattened_tree, self.selected_idx).join(".");
+        let mut lines = vec![format!("Group: {path}")];
+
+        if stats.metadata_keys > 0 {
+            lines.push(format!("Keys: {}", stats.metadata_keys));
+            if let Some((key, len)) = &stats.largest_value {
+                lines.push(format!(
+                    "Largest value: {key} ({})",
+                    display_size(*len, exact)
+                ));
+            }
+        }
+        if *tensor_count > 0 {
+            lines.push(format!(
+                "Tensors: {} | Parameters: {} | Size: {}",
+                stats.tensors,
+                display_parameters(stats.parameters, exact),
+                display_size(stats.bytes, exact)
+            ));
+            let hidden = tensor_count - stats.tensors;
+            if hidden > 0 {
+                lines.push(format!(
+                    "These figures cover the shown tensors; {hidden} small tensors are hidden"
+                ));
+            }
+            for (label, extreme) in [("Largest", &stats.largest), ("Smallest", &stats.smallest)] {
+                if let Some((name, bytes)) = extreme {
+                    lines.push(format!(
+                        "{label} tensor: {name} ({})",
+                        display_size(*bytes, exact)
+                    ));
+                }
+            }
+            if let Some((low, high)) = stats.layers {
+                lines.push(format!("Layers: {low}–{high}"));
+            }
+            lines.push(String::new());
+            lines.extend(summary::composition_lines(&stats.composition));
+            lines.push(String::new());
+            lines.push(format!("Shapes ({} distinct):", stats.shapes.len()));
+            lines.extend(
+                stats
+                    .shapes
+                    .iter()
+                    .map(|(shape, count)| format!("  {} × {count}", format_shape(shape))),
+            );
+        }
+        self.show_report("Group Info", &lines)
+    }
+
+    /// Report the outcome of an action on the footer row
+    fn set_status(&mut self, level: StatusLevel, message: impl Into<String>) {
+        self.status = Some(Status::new(level, message));
+    }
+
+    fn build_tree(&mut self) {
+        // Match paths refer to the old tree
+        self.clear_jump_matches();
+        let group_by = self.options.group_by;
+        let shown: Vec<TensorInfo> = self
+            .tensors
+            .iter()
+            .filter(|t| {
+                self.file_filter
+                    .as_ref()
+                    .is_none_or(|f| t.source_file == *f)
+            })
+            .filter(|t| self.type_filter.shows_tensor(t))
+            .filter(|t| !self.changes_only || self.changes.tensor(&t.name).is_some())
+            .cloned()
+            .collect();
+        let metadata: &[MetadataInfo] = if self.type_filter.shows_metadata() && !self.changes_only {
+            &self.metadata
         } else {
-            (self.selected_idx + delta as usize).min(tree.len() - 1)
+            &[]
         };
+        // The base model and each companion loaded with it, labeled; empty parts are dropped
+        let mut parts: Vec<(String, Vec<TensorInfo>, Vec<MetadataInfo>)> = vec![(
+            companion::base_label(&self.companions).to_string(),
+            shown
+                .iter()
+                .filter(|t| companion::companion_of(&self.companions, t).is_none())
+                .cloned()
+                .collect(),
+            metadata
+                .iter()
+                .filter(|m| !companion::metadata_in_companion(&self.companions, m))
+                .cloned()
+                .collect(),
+        )];
+        for c in &self.companions {
+            parts.push((
+                companion::component_label(&self.companions, c),
+                shown
+                    .iter()
+                    .filter(|t| t.source_file == c.file)
+                    .cloned()
+                    .collect(),
+                metadata
+                    .iter()
+                    .filter(|m| c.owns_metadata(m))
+                    .cloned()
+                    .collect(),
+            ));
+        }
+        parts.retain(|(_, tensors, _)| !tensors.is_empty());
+        let limits = self.options.limits;
+        let too_wide = group_by == GroupBy::Prefix
+            && parts.iter().any(|(_, tensors, _)| limits.too_wide(tensors));
+        if parts.len() < 2 {
+            self.tree = Self::component_tree(&shown, metadata, group_by, limits);
+        } else {
+            self.tree = parts
+                .iter()
+                .map(|(name, tensors, metadata)| TreeNode::Group {
+                    name: name.clone(),
+                    children: Self::component_tree(tensors, metadata, group_by, limits),
+                    expanded: true,
+                    tensor_count: tensors.len(),
+                    total_size: tensors.iter().map(|t| t.size_bytes).sum(),
+                    stats: Default::default(),
+                    sort: None,
+                })
+                .collect();
+        }
+        if self.options.sort != SortOrder::Name || !self.sort_overrides.is_empty() {
+            TreeBuilder::apply_sort(&mut self.tree, self.options.sort, &self.sort_overrides);
+        }
+        TreeBuilder::prune_small(&mut self.tree, self.options.min_size);
+        let failed = self.failures.iter().map(|failure| TreeNode::Failed {
+            name: failure.file_name(),
+            info: failure.clone(),
+        });
+        self.tree.splice(0..0, failed);
+        self.hidden_tensors = shown.len()
+            - self
+                .tree
+                .iter()
+                .map(TreeNode::visible_tensor_count)
+                .sum::<usize>();
+        // Tensors the last reload lost, struck through under the failed files
+        if !self.changes.removed.is_empty() {
+            let removed = &self.changes.removed;
+            self.tree.insert(
+                self.failures.len(),
+                TreeNode::Group {
+                    name: watch::REMOVED_GROUP.to_string(),
+                    children: removed
+                        .iter()
+                        .map(|info| TreeNode::Tensor { info: info.clone() })
+                        .collect(),
+                    expanded: true,
+                    tensor_count: removed.len(),
+                    total_size: removed.iter().map(|t| t.size_bytes).sum(),
+                    stats: Default::default(),
+                    sort: None,
+                },
+            );
+        }
+        self.flatten_tree();
+        if too_wide {
+            let message = format!(
+                "Listing tensors by full name: more than {} top-level name prefixes to group (view.max_top_level_groups)",
+                limits.max_top_level
+            );
+            self.set_status(StatusLevel::Info, message);
+        }
+    }
 
-        self.selected_idx = new_idx;
+    fn component_tree(
+        tensors: &[TensorInfo],
+        metadata: &[MetadataInfo],
+        group_by: GroupBy,
+        limits: TreeLimits,
+    ) -> Vec<TreeNode> {
+        if metadata.is_empty() {
+            TreeBuilder::build_tree_by(tensors, group_by, limits)
+        } else {
+            TreeBuilder::build_tree_mixed(tensors, metadata, group_by, limits)
+        }
     }
 
-    fn enter_search_mode(&mut self) {
-        self.search_mode = true;
-        self.search_query.clear();
+    /// Switch to the next grouping, keeping the selected tensor selected when possible
+    fn cycle_group_by(&mut self) {
+        self.options.group_by = self.options.group_by.next();
+        self.rebuild_keeping_selection();
+        let message = format!("Grouped by {}", self.options.group_by.label());
+        self.set_status(StatusLevel::Info, message);
+    }
+
+    /// Step the size floor to the next larger preset, wrapping back to off
+    fn cycle_min_size(&mut self) {
+        self.options.min_size = MIN_SIZE_STEPS
+            .iter()
+            .copied()
+            .find(|&step| step > self.options.min_size)
+            .unwrap_or(0);
+        self.rebuild_keeping_selection();
         self.update_filtered_tree();
+        let message = if self.options.min_size == 0 {
+            "Showing tensors of every size".to_string()
+        } else {
+            format!(
+                "Hiding tensors under {} (+{} small tensors hidden)",
+                display_size(self.options.min_size, self.options.exact),
+                self.hidden_tensors
+            )
+        };
+        self.set_status(StatusLevel::Info, message);
+    }
+
+    /// Step the order of the selected group's children (a tensor's parent group) through
+    /// the orders other than the tree's own, then back to it
+    fn cycle_group_sort(&mut self) {
+        let mut path = TreeBuilder::node_path(&self.flattened_tree, self.selected_idx);
+        if !matches!(
+            self.flattened_tree.get(self.selected_idx),
+            Some((TreeNode::Group { .. }, _))
+        ) {
+            path.pop();
+        }
+        if path.is_empty() {
+            self.set_status(StatusLevel::Warning, "Select a group to sort its children");
+            return;
+        }
+        let tree_order = self.options.sort;
+        let choices: Vec<SortOrder> = [SortOrder::Name, SortOrder::Size, SortOrder::Params]
+            .into_iter()
+            .filter(|order| *order != tree_order)
+            .collect();
+        let next = match self.sort_overrides.get(&path) {
+            None => choices.first().copied(),
+            Some(current) => choices
+                .iter()
+                .skip_while(|order| *order != current)
+                .nth(1)
+                .copied(),
+        };
+        let name = path.last().cloned().unwrap_or_default();
+        let message = match next {
+            Some(order) => {
+                self.sort_overrides.insert(path, order);
+                format!("Children of {name} sorted by {}", order.label())
+            }
+            None => {
+                self.sort_overrides.remove(&path);
+                format!(
+                    "Children of {name} sorted by {} like the rest of the tree",
+                    tree_order.label()
+                )
+            }
+        };
+        self.rebuild_keeping_layout();
+        self.set_status(StatusLevel::Info, message);
+    }
+
+    fn clear_group_sorts(&mut self) {
+        if self.sort_overrides.is_empty() {
+            self.set_status(StatusLevel::Info, "No group has its own order");
+            return;
+        }
+        let count = self.sort_overrides.len();
+        self.sort_overrides.clear();
+        self.rebuild_keeping_layout();
+        let message = format!(
+            "Cleared the order of {count} group{}; sorting everything by {}",
+            if count == 1 { "" } else { "s" },
+            self.options.sort.label()
+        );
+        self.set_status(StatusLevel::Info, message);
+    }
+
+    /// Rebuild the tree keeping the expanded groups and the selected row by name path
+    fn rebuild_keeping_layout(&mut self) {
+        let expanded = TreeBuilder::expanded_paths(&self.tree);
+        let selected = TreeBuilder::node_path(&self.flattened_tree, self.selected_idx);
+        self.build_tree();
+        TreeBuilder::restore_expanded(&mut self.tree, &expanded);
+        self.flatten_tree();
+        self.selected_idx = TreeBuilder::find_path(&self.flattened_tree, &selected).unwrap_or(0);
+    }
+
+    fn toggle_storage_order(&mut self) {
+        self.options.storage_order = !self.options.storage_order;
+        let message = if self.options.storage_order {
+            "GGUF shapes in storage (ne) order, fastest-varying first"
+        } else {
+            "GGUF shapes in logical order, as in the HF checkpoint"
+        };
+        self.set_status(StatusLevel::Info, message.to_string());
+    }
+
+    /// Rebuild the tree after a view change, keeping the selected tensor selected when possible
+    fn rebuild_keeping_selection(&mut self) {
+        let selected_tensor = match self.flattened_tree.get(self.selected_idx) {
+            Some((TreeNode::Tensor { info }, _)) => Some(info.name.clone()),
+            _ => None,
+        };
+        self.build_tree();
+
         self.selected_idx = 0;
         self.scroll_offset = 0;
+        if let Some(name) = selected_tensor {
+            self.select_tensor(&name);
+        }
     }
 
-    fn exit_search_mode(&mut self) {
-        self.search_mode = false;
-        self.search_query.clear();
+    /// Expand the tree to `levels` levels of groups, keeping the selected row or, when it gets
+    /// hidden, the nearest group above it that is still shown
+    fn set_depth(&mut self, levels: usize) {
+        let mut selected = TreeBuilder::node_path(&self.flattened_tree, self.selected_idx);
+        TreeBuilder::set_depth(&mut self.tree, levels);
+        self.flatten_tree();
+        self.selected_idx = loop {
+            if selected.is_empty() {
+                break 0;
+            }
+            if let Some(row) = TreeBuilder::find_path(&self.flattened_tree, &selected) {
+                break row;
+            }
+            selected.pop();
+        };
+        let message = match levels {
+            0 => "Collapsed all groups".to_string(),
+            1 => "Showing the top-level groups".to_string(),
+            _ => format!("Showing {levels} levels of groups"),
+        };
+        self.set_status(StatusLevel::Info, message);
+    }
+
+    /// Expand the groups above the named tensor and select it; false when it isn't in the tree
+    fn select_tensor(&mut self, name: &str) -> bool {
+        if !TreeBuilder::reveal_tensor(&mut self.tree, name) {
+            return false;
+        }
+        self.flatten_tree();
+        self.selected_idx = self
+            .flattened_tree
+            .iter()
+            .position(|(node, _)| matches!(node, TreeNode::Tensor { info } if info.name == name))
+            .unwrap_or(0);
+        true
+    }
+
+    fn flatten_tree(&mut self) {
+        self.flattened_tree = TreeBuilder::flatten_tree(&self.tree);
         self.update_filtered_tree();
-        self.selected_idx = 0;
-        self.scroll_offset = 0;
     }
 
-    fn handle_selection(&mut self) {
-        let tree = if self.search_mode {
-            &self.filtered_tree
+    fn update_filtered_tree(&mut self) {
+        if self.search_query.is_empty() {
+            self.filtered_tree = self.flattened_tree.clone();
+            self.value_matches.clear();
         } else {
-            &self.flattened_tree
-        };
+            let matcher = SkimMatcherV2::default();
+            let mut scored_results: Vec<(TreeNode, i64, Option<ValueMatch>)> = Vec::new();
 
-        if self.selected_idx < tree.len() {
-            let (selected_node, _) = &tree[self.selected_idx];
+            // Search through ALL tensors, not just the flattened tree
+            for tensor in self.tensors.iter().filter(|t| {
+                t.size_bytes >= self.options.min_size
+                    && !self.is_excluded(t)
+                    && self
+                        .file_filter
+                        .as_ref()
+                        .is_none_or(|f| t.source_file == *f)
+                    && self.type_filter.shows_tensor(t)
+            }) {
+                if let Some(score) = self.tensor_score(&matcher, tensor) {
+                    scored_results.push((
+                        TreeNode::Tensor {
+                            info: tensor.clone(),
+                        },
+                        score,
+                        None,
+                    ));
+                }
+            }
 
-            match selected_node {
-                TreeNode::Group { .. } => {
-                    // In search mode, groups shouldn't appear, but if they do, do nothing
-                    if !self.search_mode {
-                        let mut tree_clone = self.tree.clone();
-                        let _ =
-                            TreeBuilder::toggle_node_by_index(self.selected_idx, &mut tree_clone);
-                        self.tree = tree_clone;
-                        self.flatten_tree();
-                    }
+            // Also search through metadata if present
+            let metadata: &[MetadataInfo] = if self.type_filter.shows_metadata() {
+                &self.metadata
+            } else {
+                &[]
+            };
+            for metadata in metadata {
+                if let Some((score, value_match)) = metadata_hit(
+                    &matcher,
+                    &self.search_query,
+                    self.search_names_only,
+                    metadata,
+                ) {
+                    scored_results.push((
+                        TreeNode::Metadata {
+                            info: metadata.clone(),
+                        },
+                        score,
+                        value_match,
+                    ));
                 }
+            }
+
+            // Sort by score (highest first); value hits keep the metadata order
+            scored_results.sort_by_key(|a| std::cmp::Reverse(a.1));
+
+            // Create a flat list with depth 0 for all results
+            (self.filtered_tree, self.value_matches) = scored_results
+                .into_iter()
+                .map(|(node, _, value_match)| ((node, 0), value_match))
+                .unzip();
+        }
+    }
+
+    /// How well a tensor's name, or its name translated by `--name-map`, matches the query
+    fn tensor_score(&self, matcher: &SkimMatcherV2, tensor: &TensorInfo) -> Option<i64> {
+        let alias = self
+            .name_aliases
+            .get(&tensor.name)
+            .and_then(|alias| matcher.fuzzy_match(alias, &self.search_query));
+        matcher
+            .fuzzy_match(&tensor.name, &self.search_query)
+            .max(alias)
+    }
+
+    /// Find the search query in the whole tree, collapsed groups included, for `n`/`N`
+    fn update_jump_matches(&mut self) {
+        self.jump_matches.clear();
+        self.jump_names.clear();
+        self.jump_index = None;
+        if self.search_query.is_empty() {
+            return;
+        }
+        let matcher = SkimMatcherV2::default();
+        for (path, node) in TreeBuilder::all_paths(&self.tree) {
+            let hit = match node {
                 TreeNode::Tensor { info } => {
-                    self.show_tensor_detail(info);
+                    !self.is_excluded(info) && self.tensor_score(&matcher, info).is_some()
                 }
                 TreeNode::Metadata { info } => {
-                    self.show_metadata_detail(info);
+                    metadata_hit(&matcher, &self.search_query, self.search_names_only, info)
+                        .is_some()
                 }
+                _ => false,
+            };
+            if hit {
+                self.jump_names.insert(node.name().to_string());
+                self.jump_matches.push(path);
             }
         }
     }
 
-    fn show_tensor_detail(&self, tensor: &TensorInfo) {
-        if UI::draw_tensor_detail(tensor).is_ok() {
-            // Wait for any key press
-            let _ = event::read();
+    /// Select the first match at or below the selected row, wrapping around to the top
+    fn jump_to_first_match(&mut self) {
+        if self.jump_matches.is_empty() {
+            self.set_status(
+                StatusLevel::Warning,
+                format!("No matches for \"{}\"", self.search_query),
+            );
+            return;
         }
+        let selected = TreeBuilder::node_path(&self.flattened_tree, self.selected_idx);
+        let first = TreeBuilder::all_paths(&self.tree)
+            .iter()
+            .map(|(path, _)| path)
+            .skip_while(|path| **path != selected)
+            .find_map(|path| self.jump_matches.iter().position(|m| m == path))
+            .unwrap_or(0);
+        self.jump_to_match(first);
     }
 
-    fn show_metadata_detail(&self, metadata: &MetadataInfo) {
-        if UI::draw_metadata_detail(metadata).is_ok() {
-            // Wait for any key press
-            let _ = event::read();
+    /// Step through the matches of a jump search, wrapping at either end
+    fn step_match(&mut self, forward: bool) {
+        let count = self.jump_matches.len();
+        let next = match self.jump_index {
+            Some(index) if forward => (index + 1) % count,
+            Some(index) => (index + count - 1) % count,
+            None => 0,
+        };
+        self.jump_to_match(next);
+    }
+
+    /// Expand the groups above a match and select it
+    fn jump_to_match(&mut self, index: usize) {
+        let path = self.jump_matches[index].clone();
+        TreeBuilder::reveal_path(&mut self.tree, &path);
+        self.flatten_tree();
+        if let Some(row) = TreeBuilder::find_path(&self.flattened_tree, &path) {
+            self.selected_idx = row;
+        }
+        self.jump_index = Some(index);
+    }
+
+    fn clear_jump_matches(&mut self) {
+        self.jump_matches.clear();
+        self.jump_names.clear();
+        self.jump_index = None;
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        if self.files.is_empty() {
+            return Ok(());
+        }
+
+        // The loading screen takes Esc; loading errors are printed once the terminal is back
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::Clear(ClearType::All), cursor::Hide)?;
+
+        let result = self.load_all_files(None).and_then(|()| {
+            self.restore_session();
+            if let Some(paths) = self.preset.take() {
+                self.apply_preset(&paths);
+            }
+            self.record_recent();
+            self.interactive_loop()
+        });
+
+        execute!(stdout, terminal::Clear(ClearType::All), cursor::Show)?;
+        terminal::disable_raw_mode()?;
+
+        if result.is_ok()
+            && let Err(e) = self.save_session()
+        {
+            eprintln!("Warning: Failed to save session: {e:#}");
         }
+        result
     }
+
+    fn interactive_loop(&mut self) -> Result<()> {
+        loop {
+            let title = if self.files.len() == 1 {
+                self.files[0].to_string_lossy().to_string()
+            } else {
+                "SafeTensors Model".to_string()
+            };
+
+            let context = self.context_path();
+            let filter_entries = if self.filter_pane {
+                filter::entries(&self.tensors, self.metadata.len())
+            } else {
+                Vec::new()
+            };
+            let tree_to_display = self.shown_tree();
+
+            let config = DrawConfig {
+                tree: tree_to_display,
+                current_file: &title,
+                context: &context,
+                total_files: self.files.len(),
+                total_parameters: self.total_parameters,
+                embedding_parameters: self.exclude_embeddings.then_some(self.embedding_parameters),
+                selected_idx: self.selected_idx,
+                scroll_offset: self.scroll_offset,
+                search_mode: self.search_mode,
+                search_query: &self.search_query,
+                search_names_only: self.search_names_only,
+                search_jump: self.search_jump,
+                jump_matches: &self.jump_names,
+                jump_index: self.jump_index,
+                jump_count: self.jump_matches.len(),
+                value_matches: if self.filtering() {
+                    &self.value_matches
+                } else {
+                    &[]
+                },
+                sparsity: self.show_sparsity.then_some(&self.sparsity),
+                duplicates: &self.duplicate_ids,
+                pinned: &self.pinned,
+                changes: &self.changes,
+                group_by: self.options.group_by,
+                exact: self.options.exact,
+                storage_order: self.options.storage_order,
+                min_size: self.options.min_size,
+                hidden_tensors: self.hidden_tensors,
+                failed_files: self.failures.len(),
+                not_loaded_files: self.not_loaded.len(),
+                incomplete_files: self
+                    .file_records
+                    .iter()
+                    .filter(|record| record.incomplete.is_some())
+                    .count(),
+                components: self.components,
+                component_parameters: &self.component_parameters,
+                file_filter: self.file_filter.as_deref().map(display_file_name),
+                excluded: &self.excluded_files,
+                counted_files: (!self.excluded_files.is_empty()).then(|| {
+                    (
+                        self.file_records.len() - self.excluded_files.len(),
+                        self.file_records.len(),
+                        self.counted_bytes(),
+                    )
+                }),
+                size_label: self.size_label.as_deref(),
+                type_filter: self
+                    .type_filter
+                    .is_active()
+                    .then(|| self.type_filter.summary()),
+                filter_pane: self.filter_pane.then(|| FilterPane {
+                    entries: &filter_entries,
+                    filter: &self.type_filter,
+                    selected: self.filter_selected,
+                    focused: self.filter_focus,
+                }),
+                status: self.status.as_ref(),
+                ascii: self.options.ascii,
+                theme: self.options.theme,
+                keys: &self.keymap,
+            };
+            self.scroll_offset = UI::draw_screen(&config)?;
+
+            // Wake up to clear a timed-out status, and to look at watched files, even when no
+            // key arrives
+            let remaining = self.status.as_ref().and_then(Status::remaining);
+            let watch_poll = self.watcher.as_ref().map(|_| watch::POLL_INTERVAL);
+            if let Some(wait) = remaining.into_iter().chain(watch_poll).min()
+                && !event::poll(wait)?
+            {
+                if remaining.is_some_and(|remaining| remaining <= wait) {
+                    self.status = None;
+                }
+                if let Some(watcher) = &mut self.watcher
+                    && watcher.poll(&self.files)
+                {
+                    self.reload_all();
+                }
+                continue;
+            }
+            if let Event::Key(mut key_event) = event::read()? {
+                self.status = None;
+                if self.filter_pane_key(key_event, &filter_entries) {
+                    continue;
+                }
+                // After a jump search, n/N step through its matches until Esc clears them
+                if !self.search_mode && !self.jump_matches.is_empty() {
+                    match key_event.code {
+                        KeyCode::Char('n') => {
+                            self.step_match(true);
+                            continue;
+                        }
+                        KeyCode::Char('N') => {
+                            self.step_match(false);
+                            continue;
+                        }
+                        KeyCode::Esc => {
+                            self.clear_jump_matches();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                }
+                // Apply [keys] overrides; while searching, characters are query text
+                if !self.search_mode
+                    && !key_event.modifiers.contains(KeyModifiers::CONTROL)
+                    && let KeyCode::Char(c) = key_event.code
+                {
+                    match self.keymap.translate(c) {
+                        Some(action_key) => key_event.code = KeyCode::Char(action_key),
+                        None => continue,
+                    }
+                }
+                match key_event {
+                    KeyEvent {
+                        code: KeyCode::Char('q'),
+                        ..
+                    } => {
+                        if self.search_mode {
+                            self.exit_search_mode();
+                        } else {
+                            break;
+                        }
+                    }
+                    KeyEvent {
+                        code: KeyCode::Char('c'),
+                        modifiers: KeyModifiers::CONTROL,
+                        ..
+                    } => break,
+                    KeyEvent {
+                        code: KeyCode::Char('/'),
+                        ..
+                    } if !self.search_mode => self.enter_search_mode(),
+                    // Ctrl+E also works while typing a search, so search results can be exported
+                    KeyEvent {
+                        code: KeyCode::Char('e'),
+                        modifiers: KeyModifiers::CONTROL,
+                        ..
+                    } => self.export_view()?,
+                    KeyEvent {
+                        code: KeyCode::Char('e'),
+                        ..
+                    } if !self.search_mode => self.export_view()?,
+                    KeyEvent {
+                        code: KeyCode::Char('N'),
+                        ..
+                    } if !self.search_mode => self.run_nan_scan()?,
+                    KeyEvent {
+                        code: KeyCode::Char('Z'),
+                        ..
+                    } if !self.search_mode => self.run_degenerate_scan()?,
+                    KeyEvent {
+                        code: KeyCode::Char('L'),
+                        ..
+                    } if !self.search_mode => self.toggle_sparsity_column(),
+                    KeyEvent {
+                        code: KeyCode::Char('E'),
+                        ..
+                    } if !self.search_mode => self.toggle_embeddings(),
+                    KeyEvent {
+                        code: KeyCode::Char('M'),
+                        ..
+                    } if !self.search_mode => {
+                        self.show_report("Model Summary", &summary::render_sections(&self.summary))?
+                    }
+                    KeyEvent {
+                        code: KeyCode::Char('D'),
+                        ..
+                    } if !self.search_mode => self.run_duplicate_scan()?,
+                    KeyEvent {
+                        code: KeyCode::Char('G'),
+                        ..
+                    } if !self.search_mode => self.cycle_group_by(),
+                    KeyEvent {
+                        code: KeyCode::Char('m'),
+                        ..
+                    } if !self.search_mode => self.cycle_min_size(),
+                    KeyEvent {
+                        code: KeyCode::Char('u'),
+                        ..
+                    } if !self.search_mode => self.options.exact = !self.options.exact,
+                    KeyEvent {
+                        code: KeyCode::Char('S'),
+                        ..
+                    } if !self.search_mode => self.toggle_storage_order(),
+                    KeyEvent {
+                        code: KeyCode::Char('s'),
+                        ..
+                    } if !self.search_mode => self.cycle_group_sort(),
+                    KeyEvent {
+                        code: KeyCode::Char('='),
+                        ..
+                    } if !self.search_mode => self.clear_group_sorts(),
+                    KeyEvent {
+                        code: KeyCode::Char('w'),
+                        ..
+                    } if !self.search_mode => self.save_preset()?,
+                    KeyEvent {
+                        code: KeyCode::Char('W'),
+                        ..
+                    } if !self.search_mode => self.load_preset()?,
+                    KeyEvent {
+                        code: KeyCode::Char('I'),
+                        ..
+                    } if !self.search_mode => self.write_missing_index(),
+                    KeyEvent {
+                        code: KeyCode::Char('r'),
+                        ..
+                    } if !self.search_mode => self.reload_all(),
+                    KeyEvent {
+                        code: KeyCode::Char('H'),
+                        ..
+                    } if !self.search_mode => self.toggle_changes_only(),
+                    KeyEvent {
+                        code: KeyCode::Char('i'),
+                        ..
+                    } if !self.search_mode => self.show_group_info()?,
+                    KeyEvent {
+                        code: KeyCode::Char('p'),
+                        ..
+                    } if !self.search_mode => self.toggle_pin(),
+                    KeyEvent {
+                        code: KeyCode::Char('P'),
+                        ..
+                    } if !self.search_mode => self.show_comparison()?,
+                    KeyEvent {
+                        code: KeyCode::Char('X'),
+                        ..
+                    } if !self.search_mode => self.extract_selected()?,
+                    KeyEvent {
+                        code: KeyCode::Char('F'),
+                        ..
+                    } if !self.search_mode => self.show_files()?,
+                    KeyEvent {
+                        code: KeyCode::Char('O'),
+                        ..
+                    } if !self.search_mode => self.show_offsets()?,
+                    KeyEvent {
+                        code: KeyCode::Char('?'),
+                        ..
+                    } if !self.search_mode => {
+                        self.show_report("Keyboard Shortcuts", &self.keymap.help_lines())?
+                    }
+                    KeyEvent {
+                        code: KeyCode::Char('B'),
+                        ..
+                    } if !self.search_mode => self.show_layer_chart()?,
+                    KeyEvent {
+                        code: KeyCode::Char('V'),
+                        ..
+                    } if !self.search_mode => self.show_treemap()?,
+                    KeyEvent {
+                        code: KeyCode::Char('T'),
+                        ..
+                    } if !self.search_mode => self.show_top_tensors()?,
+                    KeyEvent {
+                        code: KeyCode::Char('A'),
+                        ..
+                    } if !self.search_mode => self.show_layer_audit()?,
+                    KeyEvent {
+                        code: KeyCode::Char('C'),
+                        ..
+                    } if !self.search_mode => self.show_cost_estimate()?,
+                    KeyEvent {
+                        code: KeyCode::Char('U'),
+                        ..
+                    } if !self.search_mode => self.show_context_usage()?,
+                    KeyEvent {
+                        code: KeyCode::Char('o'),
+                        ..
+                    } if !self.search_mode => self.open_path()?,
+                    KeyEvent {
+                        code: KeyCode::Char('R'),
+                        ..
+                    } if !self.search_mode => self.switch_recent()?,
+                    KeyEvent {
+                        code: KeyCode::Char('d'),
+                        ..
+                    } if !self.search_mode => self.drop_selected_file(),
+                    KeyEvent {
+                        code: KeyCode::Char('y'),
+                        ..
+                    } if !self.search_mode => self.copy_names(false)?,
+                    KeyEvent {
+                        code: KeyCode::Char('Y'),
+                        ..
+                    } if !self.search_mode => self.copy_names(true)?,
+                    KeyEvent {
+                        code: KeyCode::Esc, ..
+                    } if self.search_mode => self.exit_search_mode(),
+                    KeyEvent {
+                        code: KeyCode::Tab, ..
+                    } if self.search_mode => {
+                        self.search_names_only = !self.search_names_only;
+                        self.update_search();
+                    }
+                    KeyEvent {
+                        code: KeyCode::Char('f'),
+                        modifiers: KeyModifiers::CONTROL,
+                        ..
+                    } if self.search_mode => self.toggle_search_jump(),
+                    KeyEvent {
+                        code: KeyCode::Up, ..
+                    } => self.move_selection(-1),
+                    KeyEvent {
+                        code: KeyCode::Down,
+                        ..
+                    } => self.move_selection(1),
+                    KeyEvent {
+                        code: KeyCode::Enter,
+                        ..
+                    } => {
+                        if self.search_mode && self.search_jump {
+                            self.finish_jump_search();
+                        } else if self.search_mode {
+                            self.exit_search_mode();
+                        } else {
+                            self.handle_selection();
+                        }
+                    }
+                    KeyEvent {
+                        code: KeyCode::Char(' '),
+                        ..
+                    } if !self.search_mode => self.toggle_file_or_select(),
+                    KeyEvent {
+                        code: KeyCode::Char(c @ '0'..='4'),
+                        ..
+                    } if !self.search_mode => self.set_depth(c as usize - '0' as usize),
+                    KeyEvent {
+                        code: KeyCode::Backspace,
+                        ..
+                    } if self.search_mode => {
+                        self.search_query.pop();
+                        self.update_search();
+                    }
+                    KeyEvent {
+                        code: KeyCode::Char(c),
+                        ..
+                    } if self.search_mode => {
+                        self.search_query.push(c);
+                        self.update_search();
+                    }
+                    // Remove left/right file navigation since we're showing all files merged
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn move_selection(&mut self, delta: i32) {
+        let tree = self.shown_tree();
+
+        if tree.is_empty() {
+            return;
+        }
+
+        let new_idx = if delta < 0 {
+            self.selected_idx.saturating_sub((-delta) as usize)
+        } else {
+            (self.selected_idx + delta as usize).min(tree.len() - 1)
+        };
+
+        self.selected_idx = new_idx;
+    }
+
+    /// Rows on screen: the search results while filtering, the tree otherwise
+    fn shown_tree(&self) -> &[(TreeNode, usize)] {
+        if self.filtering() {
+            &self.filtered_tree
+        } else {
+            &self.flattened_tree
+        }
+    }
+
+    /// Dotted path of the nearest group above the selected row; top-level rows have none,
+    /// so they show the file they come from instead
+    fn context_path(&self) -> String {
+        let tree = self.shown_tree();
+        let Some((node, _)) = tree.get(self.selected_idx) else {
+            return String::new();
+        };
+        let mut path = TreeBuilder::node_path(tree, self.selected_idx);
+        path.pop();
+        if !path.is_empty() {
+            return path.join(".");
+        }
+        let files: HashSet<&PathBuf> = node.tensors().iter().map(|t| &t.source_file).collect();
+        let mut files = files.into_iter();
+        match (node, files.next(), files.next()) {
+            (_, Some(file), None) => display_file_name(file),
+            (_, Some(_), Some(_)) => format!("{} files", files.count() + 2),
+            (TreeNode::Metadata { info }, ..) => {
+                info.source.clone().unwrap_or_else(|| self.model_title())
+            }
+            (TreeNode::Failed { info, .. }, ..) => info.file_name(),
+            _ => self.model_title(),
+        }
+    }
+
+    /// The file name when a single file is open, a generic title otherwise
+    fn model_title(&self) -> String {
+        match self.files.as_slice() {
+            [file] => display_file_name(file),
+            _ => "SafeTensors Model".to_string(),
+        }
+    }
+
+    /// A search is being typed and filters the tree rather than jumping through it
+    fn filtering(&self) -> bool {
+        self.search_mode && !self.search_jump
+    }
+
+    fn enter_search_mode(&mut self) {
+        self.search_mode = true;
+        self.search_query.clear();
+        self.search_origin = self.selected_idx;
+        self.clear_jump_matches();
+        self.update_filtered_tree();
+        if !self.search_jump {
+            self.selected_idx = 0;
+            self.scroll_offset = 0;
+        }
+    }
+
+    fn exit_search_mode(&mut self) {
+        self.search_mode = false;
+        self.search_query.clear();
+        self.update_filtered_tree();
+        if self.search_jump {
+            self.clear_jump_matches();
+            self.selected_idx = self.search_origin;
+        } else {
+            self.selected_idx = 0;
+            self.scroll_offset = 0;
+        }
+    }
+
+    /// Enter on a jump search: leave the prompt and select the first match
+    fn finish_jump_search(&mut self) {
+        self.search_mode = false;
+        self.jump_to_first_match();
+        self.search_query.clear();
+        self.update_filtered_tree();
+    }
+
+    /// Rerun the search after the query or its scope changed
+    fn update_search(&mut self) {
+        if self.search_jump {
+            self.update_jump_matches();
+        } else {
+            self.update_filtered_tree();
+            self.selected_idx = 0;
+            self.scroll_offset = 0;
+        }
+    }
+
+    /// Switch the search being typed between filtering the tree and jumping through it
+    fn toggle_search_jump(&mut self) {
+        self.search_jump = !self.search_jump;
+        if self.search_jump {
+            self.selected_idx = self.search_origin;
+            self.update_jump_matches();
+        } else {
+            self.clear_jump_matches();
+            self.update_filtered_tree();
+            self.selected_idx = 0;
+        }
+        self.scroll_offset = 0;
+    }
+
+    fn handle_selection(&mut self) {
+        let tree = self.shown_tree();
+
+        if self.selected_idx < tree.len() {
+            let (selected_node, _) = &tree[self.selected_idx];
+
+            match selected_node {
+                // In search mode, groups shouldn't appear, but if they do, do nothing
+                TreeNode::Group { .. } if !self.search_mode => {
+                    let mut tree_clone = self.tree.clone();
+                    let _ = TreeBuilder::toggle_node_by_index(self.selected_idx, &mut tree_clone);
+                    self.tree = tree_clone;
+                    self.flatten_tree();
+                }
+                TreeNode::Tensor { .. } => self.show_tensor_detail(),
+                TreeNode::Metadata { info } => {
+                    self.show_metadata_detail(info);
+                }
+                TreeNode::Failed { info, .. } => {
+                    if UI::draw_failure_detail(info).is_ok()
+                        && let Ok(Event::Key(key_event)) = event::read()
+                        && key_event.code == KeyCode::Char('h')
+                        && model_extension(&info.file) == Some("safetensors")
+                    {
+                        let _ = self.show_raw_header(&info.file);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Row of the nearest tensor before or after `from` in the shown tree, skipping group
+    /// and metadata rows
+    fn neighbor_tensor(&self, from: usize, forward: bool) -> Option<usize> {
+        let tree = self.shown_tree();
+        let is_tensor = |idx: &usize| matches!(tree[*idx].0, TreeNode::Tensor { .. });
+        if forward {
+            (from + 1..tree.len()).find(is_tensor)
+        } else {
+            (0..from).rev().find(is_tensor)
+        }
+    }
+
+    /// Which tensor of the shown tree the row is, counting from 1, and how many there are
+    fn tensor_position(&self, row: usize) -> (usize, usize) {
+        let tree = self.shown_tree();
+        let tensors = |rows: &[(TreeNode, usize)]| {
+            rows.iter()
+                .filter(|(node, _)| matches!(node, TreeNode::Tensor { .. }))
+                .count()
+        };
+        (tensors(&tree[..=row]), tensors(tree))
+    }
+
+    fn sparsity_analysis(&self, tensor: &TensorInfo) -> Vec<String> {
+        self.sparsity
+            .get(&tensor.name)
+            .map(|stats| {
+                format!(
+                    "Sparsity: {:.2}% zeros | L2 norm: {:.6}",
+                    stats.zero_fraction * 100.0,
+                    stats.l2_norm
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Details of the selected tensor. Left/Right (or `[`/`]`) step to the previous or next
+    /// tensor of the shown tree, moving the tree selection along.
+    fn show_tensor_detail(&mut self) {
+        let Some((TreeNode::Tensor { info }, _)) = self.shown_tree().get(self.selected_idx) else {
+            return;
+        };
+        let mut current = info.clone();
+        let mut analysis = self.sparsity_analysis(&current);
+        let mut peek_index = String::new();
+        let model_bytes = self.tensors.iter().map(|t| t.size_bytes).sum();
+        while UI::draw_tensor_detail(
+            &current,
+            model_bytes,
+            &analysis,
+            self.tensor_position(self.selected_idx),
+        )
+        .is_ok()
+        {
+            let tensor = &current;
+            match event::read() {
+                Ok(Event::Key(KeyEvent {
+                    code: code @ (KeyCode::Left | KeyCode::Right | KeyCode::Char('[' | ']')),
+                    ..
+                })) => {
+                    let forward = matches!(code, KeyCode::Right | KeyCode::Char(']'));
+                    if let Some(row) = self.neighbor_tensor(self.selected_idx, forward)
+                        && let (TreeNode::Tensor { info }, _) = &self.shown_tree()[row]
+                    {
+                        current = info.clone();
+                        self.selected_idx = row;
+                        analysis = self.sparsity_analysis(&current);
+                    }
+                }
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('Z'),
+                    ..
+                })) => {
+                    let mut progress = TuiProgress::new("Checking for degenerate values");
+                    analysis = match scan::check_degenerate(tensor, &mut progress) {
+                        Ok(Some(kind)) => vec![format!("Degenerate: {kind}")],
+                        Ok(None) => vec!["Degenerate: no (values vary)".to_string()],
+                        Err(e) => vec![format!("Degenerate check failed: {e:#}")],
+                    };
+                }
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char(key @ ('H' | 'F')),
+                    ..
+                })) => {
+                    // Huge tensors get a quick estimate first; F always reads everything
+                    let mode = if key == 'H' && tensor.size_bytes > self.options.sample_threshold {
+                        StatsMode::Sampled
+                    } else {
+                        StatsMode::Full
+                    };
+                    let mut progress = TuiProgress::new(match mode {
+                        StatsMode::Full => "Computing histogram",
+                        StatsMode::Sampled => "Sampling histogram",
+                    });
+                    analysis = match scan::compute_histogram(tensor, mode, &mut progress) {
+                        Ok(Some(histogram)) => {
+                            let (terminal_width, _) = terminal::size().unwrap_or((80, 24));
+                            let bar_width = (terminal_width as usize).saturating_sub(36).max(10);
+                            let lines =
+                                UI::histogram_lines(&histogram, tensor.size_bytes, bar_width);
+                            self.histograms.insert(tensor.name.clone(), histogram);
+                            lines
+                        }
+                        Ok(None) => vec!["Histogram cancelled".to_string()],
+                        Err(e) => vec![format!("Histogram failed: {e:#}")],
+                    };
+                }
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('V'),
+                    ..
+                })) => {
+                    let label = format!(
+                        "Index into {}, e.g. 0, 1 or -1, 0, or one flat index:",
+                        format_shape(&tensor.shape)
+                    );
+                    let Ok(Some(input)) = self.prompt_text("Read Value", &label, &peek_index)
+                    else {
+                        continue;
+                    };
+                    analysis = match peek::parse_index(&input)
+                        .and_then(|index| peek::peek(tensor, &index))
+                    {
+                        Ok(element) => element.lines(tensor),
+                        Err(e) => vec![format!("Read failed: {e:#}")],
+                    };
+                    peek_index = input;
+                }
+                // Any other key returns to the tree
+                _ => break,
+            }
+        }
+    }
+
+    /// Pin the selected tensor for the comparison panel, or unpin it if it already is
+    fn toggle_pin(&mut self) {
+        let Some((TreeNode::Tensor { info }, _)) = self.flattened_tree.get(self.selected_idx)
+        else {
+            self.set_status(StatusLevel::Warning, "Select a tensor to pin it");
+            return;
+        };
+        let name = info.name.clone();
+        if let Some(slot) = self.pinned.iter().position(|pinned| *pinned == name) {
+            self.pinned.remove(slot);
+            self.set_status(StatusLevel::Info, format!("Unpinned {name}"));
+        } else if self.pinned.len() >= MAX_PINNED {
+            self.set_status(
+                StatusLevel::Warning,
+                format!("{MAX_PINNED} tensors are pinned already; unpin one first"),
+            );
+        } else {
+            self.pinned.push(name.clone());
+            let compare = self.keymap.key_for("compare").unwrap_or('-');
+            self.set_status(
+                StatusLevel::Info,
+                format!(
+                    "Pinned {name} ({}/{MAX_PINNED}) | {compare} to compare",
+                    self.pinned.len()
+                ),
+            );
+        }
+    }
+
+    /// The pinned tensors side by side; p or x unpins the selected one
+    fn show_comparison(&mut self) -> Result<()> {
+        if self.pinned.is_empty() {
+            let pin = self.keymap.key_for("pin").unwrap_or('-');
+            self.set_status(
+                StatusLevel::Warning,
+                format!("No pinned tensors | press {pin} on a tensor to pin it"),
+            );
+            return Ok(());
+        }
+        let mut selected = 0;
+        loop {
+            let tensors: Vec<(&TensorInfo, Option<&Histogram>)> = self
+                .pinned
+                .iter()
+                .filter_map(|name| self.tensors.iter().find(|t| t.name == *name))
+                .map(|t| (t, self.histograms.get(&t.name)))
+                .collect();
+            UI::draw_comparison(&tensors, selected, self.options.exact, self.options.theme)?;
+
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Left | KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Right | KeyCode::Down => {
+                        selected = (selected + 1).min(self.pinned.len() - 1)
+                    }
+                    KeyCode::Char('p' | 'x') | KeyCode::Delete => {
+                        self.pinned.remove(selected);
+                        if self.pinned.is_empty() {
+                            return Ok(());
+                        }
+                        selected = selected.min(self.pinned.len() - 1);
+                    }
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn show_metadata_detail(&self, metadata: &MetadataInfo) {
+        if metadata.name == "tokenizer.ggml.merges"
+            && let Some(GGUFValue::Array(_, rules)) = metadata.raw_value.as_deref()
+        {
+            let _ = self.show_merges(rules);
+            return;
+        }
+        if let Some(document) = json_document(metadata) {
+            let _ = self.show_json_tree(&metadata.name, &document);
+            return;
+        }
+        if metadata.value_type == "image"
+            && let Some(GGUFValue::String(text)) = metadata.raw_value.as_deref()
+            && let Some(image) = embedded_image(text)
+        {
+            let _ = self.show_image_detail(metadata, &image);
+            return;
+        }
+        if UI::draw_metadata_detail(metadata, "Press any key to return...").is_ok() {
+            // Wait for any key press
+            let _ = event::read();
+        }
+    }
+
+    /// An image embedded in a metadata value as base64; `e` writes the decoded bytes to a file
+    fn show_image_detail(&self, metadata: &MetadataInfo, image: &EmbeddedImage) -> Result<()> {
+        let mut footer = "Press e to export the image, any other key to return...".to_string();
+        loop {
+            UI::draw_metadata_detail(metadata, &footer)?;
+            let Event::Key(key_event) = event::read()? else {
+                continue;
+            };
+            if key_event.code != KeyCode::Char('e') {
+                return Ok(());
+            }
+            let initial = format!("{}.{}", file_stem_for(&metadata.name), image.extension);
+            let Some(output) = self.prompt_text("Export Image", "Output path:", &initial)? else {
+                continue;
+            };
+            footer = match fs::write(&output, &image.bytes) {
+                Ok(()) => format!(
+                    "Wrote {} to {output}. Press any key to return...",
+                    format_size(image.bytes.len())
+                ),
+                Err(e) => {
+                    format!("Export failed: {e}. Press e to retry, any other key to return...")
+                }
+            };
+        }
+    }
+
+    /// A JSON document stored in a metadata value as a tree: Enter expands or collapses,
+    /// `y` copies the selected value and `e` writes it to a file, both pretty-printed
+    fn show_json_tree(&self, key: &str, document: &serde_json::Value) -> Result<()> {
+        let title = format!("Metadata JSON: {key}");
+        let mut tree = TreeBuilder::build_json_tree(key, document);
+        let (mut selected, mut scroll_offset) = (0, 0);
+        let mut note: Option<String> = None;
+        loop {
+            let rows = TreeBuilder::flatten_tree(&tree);
+            scroll_offset = UI::draw_json_tree(
+                &title,
+                &rows,
+                selected,
+                scroll_offset,
+                note.as_deref(),
+                self.options.ascii,
+                self.options.theme,
+            )?;
+            let Event::Key(key_event) = event::read()? else {
+                continue;
+            };
+            note = None;
+            let last = rows.len().saturating_sub(1);
+            let path = TreeBuilder::node_path(&rows, selected);
+            let value = || {
+                let value = json_at_path(document, &path).unwrap_or(document);
+                serde_json::to_string_pretty(value)
+            };
+            match key_event.code {
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected = (selected + 1).min(last),
+                KeyCode::PageUp => selected = selected.saturating_sub(10),
+                KeyCode::PageDown => selected = (selected + 10).min(last),
+                KeyCode::Home => selected = 0,
+                KeyCode::End => selected = last,
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    TreeBuilder::toggle_node_by_index(selected, &mut tree);
+                }
+                KeyCode::Char('y') => {
+                    let name = path.last().map_or(key, String::as_str);
+                    note = Some(match clipboard::copy(&value()?) {
+                        Ok(method) => format!("Copied {name} as JSON (via {})", method.label()),
+                        Err(e) => format!("Copy failed: {e:#}"),
+                    });
+                }
+                KeyCode::Char('e') => {
+                    let initial = format!("{}.json", file_stem_for(key));
+                    let Some(output) = self.prompt_text(&title, "Output path:", &initial)? else {
+                        continue;
+                    };
+                    note = Some(match fs::write(&output, value()? + "\n") {
+                        Ok(()) => format!("Wrote {output}"),
+                        Err(e) => format!("Export failed: {e}"),
+                    });
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Page through merge rules by rank; `/` keeps only the rules whose pieces or
+    /// result equal a given piece. Rows are formatted a page at a time.
+    fn show_merges(&self, rules: &[GGUFValue]) -> Result<()> {
+        let stats = merge_stats(rules);
+        let mut filter: Option<(String, Vec<usize>)> = None;
+        let mut scroll_offset = 0;
+        loop {
+            let view = filter
+                .as_ref()
+                .map(|(piece, matches)| (piece.as_str(), matches.as_slice()));
+            scroll_offset = UI::draw_merges(rules, &stats, view, scroll_offset)?;
+            let (_, terminal_height) = terminal::size()?;
+            let page = (terminal_height as usize).saturating_sub(6).max(1);
+
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Up => scroll_offset = scroll_offset.saturating_sub(1),
+                    KeyCode::Down => scroll_offset += 1,
+                    KeyCode::PageUp => scroll_offset = scroll_offset.saturating_sub(page),
+                    KeyCode::PageDown => scroll_offset += page,
+                    KeyCode::Home => scroll_offset = 0,
+                    KeyCode::End => scroll_offset = usize::MAX,
+                    KeyCode::Char('/') => {
+                        let initial = filter.as_ref().map_or("", |(piece, _)| piece.as_str());
+                        let Some(piece) = self.prompt_text("Find Merges", "Piece:", initial)?
+                        else {
+                            continue;
+                        };
+                        scroll_offset = 0;
+                        filter = (!piece.is_empty()).then(|| {
+                            let matches = rules
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, rule)| {
+                                    merge_pieces(rule).is_some_and(|(left, right)| {
+                                        left == piece
+                                            || right == piece
+                                            || (piece.len() == left.len() + right.len()
+                                                && piece.starts_with(left)
+                                                && piece.ends_with(right))
+                                    })
+                                })
+                                .map(|(rank, _)| rank)
+                                .collect();
+                            (piece, matches)
+                        });
+                    }
+                    KeyCode::Esc if filter.is_some() => {
+                        filter = None;
+                        scroll_offset = 0;
+                    }
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Keys for the filter pane: Ctrl+F opens and closes it, Tab moves the focus between it
+    /// and the tree. Returns whether the key was used here.
+    fn filter_pane_key(&mut self, key_event: KeyEvent, entries: &[(FilterEntry, usize)]) -> bool {
+        if self.search_mode {
+            return false;
+        }
+        let ctrl_f = key_event.code == KeyCode::Char('f')
+            && key_event.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl_f {
+            self.filter_pane = !self.filter_pane;
+            self.filter_focus = self.filter_pane;
+            return true;
+        }
+        if !self.filter_pane {
+            return false;
+        }
+        if key_event.code == KeyCode::Tab {
+            self.filter_focus = !self.filter_focus;
+            return true;
+        }
+        if !self.filter_focus {
+            return false;
+        }
+        let last = entries.len().saturating_sub(1);
+        self.filter_selected = self.filter_selected.min(last);
+        match key_event.code {
+            KeyCode::Up => self.filter_selected = self.filter_selected.saturating_sub(1),
+            KeyCode::Down => self.filter_selected = (self.filter_selected + 1).min(last),
+            KeyCode::Home => self.filter_selected = 0,
+            KeyCode::End => self.filter_selected = last,
+            KeyCode::Char(' ') => {
+                if let Some((entry, _)) = entries.get(self.filter_selected) {
+                    self.type_filter.toggle(entry);
+                    self.apply_type_filter();
+                }
+            }
+            KeyCode::Char('c') => {
+                self.type_filter.clear();
+                self.apply_type_filter();
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.filter_pane = false;
+                self.filter_focus = false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn apply_type_filter(&mut self) {
+        self.rebuild_keeping_selection();
+        self.update_filtered_tree();
+    }
+
+    /// Switch the footer between the total and the non-embedding parameter count
+    fn toggle_changes_only(&mut self) {
+        self.changes_only = !self.changes_only;
+        self.rebuild_keeping_selection();
+        let message = if !self.changes_only {
+            "Showing every tensor".to_string()
+        } else if self.changes.is_empty() {
+            let reload = self.keymap.key_for("reload").unwrap_or('-');
+            format!("Nothing changed at the last reload | press {reload} to reload")
+        } else {
+            TreeBuilder::set_depth(&mut self.tree, usize::MAX);
+            self.flatten_tree();
+            format!(
+                "Showing what changed at the last reload: {}",
+                self.changes.summary(self.options.exact)
+            )
+        };
+        self.set_status(StatusLevel::Info, message);
+    }
+
+    fn toggle_embeddings(&mut self) {
+        self.exclude_embeddings = !self.exclude_embeddings;
+        let message = if !self.exclude_embeddings {
+            "Counting all parameters".to_string()
+        } else if self.embedding_parameters == 0 {
+            "No embedding tables or output heads recognised by name".to_string()
+        } else {
+            format!(
+                "Leaving out {} parameters in embeddings and output heads",
+                display_parameters(self.embedding_parameters, self.options.exact)
+            )
+        };
+        self.set_status(StatusLevel::Info, message);
+    }
+
+    fn toggle_sparsity_column(&mut self) {
+        self.show_sparsity = !self.show_sparsity;
+        if self.show_sparsity && self.sparsity.is_empty() {
+            // Computed once on first use, then served from the cache
+            let mut progress = TuiProgress::new("Computing sparsity and L2 norms");
+            match scan::scan_sparsity(&self.tensors, &mut progress) {
+                Ok(scan) => {
+                    if scan.cancelled {
+                        self.set_status(
+                            StatusLevel::Warning,
+                            format!(
+                                "Sparsity scan cancelled; {} of {} tensors have stats",
+                                scan.stats.len(),
+                                self.tensors.len()
+                            ),
+                        );
+                    }
+                    self.sparsity.extend(scan.stats);
+                }
+                Err(e) => {
+                    self.show_sparsity = false;
+                    self.set_status(StatusLevel::Error, format!("Sparsity scan failed: {e:#}"));
+                }
+            }
+        }
+    }
+
+    fn run_duplicate_scan(&mut self) -> Result<()> {
+        let mut progress = TuiProgress::new("Duplicate Tensor Scan");
+        match scan::find_duplicates(&self.tensors, scan::DUPLICATE_MIN_BYTES, &mut progress) {
+            Ok(scan) => {
+                self.duplicate_ids = scan.group_ids();
+                self.show_report("Duplicate Tensor Report", &scan.report_lines())
+            }
+            Err(e) => self.show_report("Duplicate Tensor Scan Failed", &[format!("{e:#}")]),
+        }
+    }
+
+    fn run_degenerate_scan(&mut self) -> Result<()> {
+        let mut progress = TuiProgress::new("Degenerate Tensor Scan");
+        match scan::scan_degenerate(&self.tensors, &mut progress) {
+            Ok(scan) => self.show_findings(ReportView::new(
+                "Degenerate Tensor Report",
+                scan.summary_lines(),
+                scan.findings(),
+            )),
+            Err(e) => self.show_report("Degenerate Tensor Scan Failed", &[format!("{e:#}")]),
+        }
+    }
+
+    fn run_nan_scan(&mut self) -> Result<()> {
+        let mut progress = TuiProgress::new("NaN/Inf Scan");
+        let result = scan::scan_nan(&self.tensors, &mut progress);
+        match result {
+            Ok(scan) => self.show_findings(ReportView::new(
+                "NaN/Inf Scan Report",
+                scan.summary_lines(),
+                scan.findings(),
+            )),
+            Err(e) => self.show_report("NaN/Inf Scan Failed", &[format!("{e:#}")]),
+        }
+    }
+
+    /// Write exactly the rows on screen (search results or the expanded tree) to a file
+    fn export_view(&mut self) -> Result<()> {
+        const TITLE: &str = "Export View";
+        UI::draw_prompt(
+            TITLE,
+            &[
+                "Choose a format:",
+                "  j  JSON",
+                "  y  YAML",
+                "  c  CSV",
+                "  m  Markdown",
+                "",
+                "Esc to cancel",
+            ],
+            None,
+        )?;
+        let format = loop {
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Char('j') => break Format::Json,
+                    KeyCode::Char('y') => break Format::Yaml,
+                    KeyCode::Char('c') => break Format::Csv,
+                    KeyCode::Char('m') => break Format::Markdown,
+                    KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                    _ => {}
+                }
+            }
+        };
+
+        let default_path = format!("safetensors_view.{}", format.extension());
+        let Some(path) = self.prompt_text(TITLE, "Output path:", &default_path)? else {
+            return Ok(());
+        };
+        let tree = self.shown_tree();
+        let listing = Listing(export::rows_from_tree(tree));
+        match export::write_listing(&listing, format, Path::new(&path)) {
+            Ok(count) => self.set_status(
+                StatusLevel::Success,
+                format!("Exported {count} rows to {path}"),
+            ),
+            Err(e) => self.set_status(StatusLevel::Error, format!("Export failed: {e:#}")),
+        }
+        Ok(())
+    }
+
+    /// Write the selected tensor's raw bytes and a JSON description to a chosen directory
+    fn extract_selected(&mut self) -> Result<()> {
+        const TITLE: &str = "Extract Raw Bytes";
+        let Some((TreeNode::Tensor { info }, _)) = self.shown_tree().get(self.selected_idx) else {
+            self.set_status(StatusLevel::Warning, "Select a tensor to extract it");
+            return Ok(());
+        };
+        let tensor = info.clone();
+        let Some(dir) = self.prompt_text(TITLE, "Output directory:", ".")? else {
+            return Ok(());
+        };
+        match extract::extract_raw(&tensor, Path::new(&dir)) {
+            Ok(extracted) => self.set_status(
+                StatusLevel::Success,
+                format!(
+                    "Wrote {} ({} bytes) and {}",
+                    extracted.data.display(),
+                    format_thousands(extracted.bytes),
+                    extracted.sidecar.display()
+                ),
+            ),
+            Err(e) => self.set_status(StatusLevel::Error, format!("Extract failed: {e:#}")),
+        }
+        Ok(())
+    }
+
+    /// Expand the preset's groups and collapse all others; returns how many of its groups
+    /// this model has
+    fn apply_preset(&mut self, paths: &HashSet<Vec<String>>) -> usize {
+        let selected = TreeBuilder::node_path(&self.flattened_tree, self.selected_idx);
+        TreeBuilder::restore_expanded(&mut self.tree, paths);
+        self.flatten_tree();
+        self.selected_idx = TreeBuilder::find_path(&self.flattened_tree, &selected).unwrap_or(0);
+        TreeBuilder::all_paths(&self.tree)
+            .iter()
+            .filter(|(path, node)| matches!(node, TreeNode::Group { .. }) && paths.contains(path))
+            .count()
+    }
+
+    fn save_preset(&mut self) -> Result<()> {
+        let Some(output) = self.prompt_text("Save Preset", "Preset file:", "preset.json")? else {
+            return Ok(());
+        };
+        let expanded = TreeBuilder::expanded_paths(&self.tree);
+        match preset::save(Path::new(&output), &expanded) {
+            Ok(count) => self.set_status(
+                StatusLevel::Success,
+                format!(
+                    "Wrote {count} expanded group{} to {output}",
+                    if count == 1 { "" } else { "s" }
+                ),
+            ),
+            Err(e) => self.set_status(StatusLevel::Error, format!("{e:#}")),
+        }
+        Ok(())
+    }
+
+    fn load_preset(&mut self) -> Result<()> {
+        let Some(input) = self.prompt_text("Apply Preset", "Preset file:", "preset.json")? else {
+            return Ok(());
+        };
+        match preset::load(Path::new(&input)) {
+            Ok(paths) => {
+                let found = self.apply_preset(&paths);
+                self.set_status(
+                    StatusLevel::Success,
+                    format!(
+                        "Applied {input}: {found} of {} groups are in this model",
+                        paths.len()
+                    ),
+                );
+            }
+            Err(e) => self.set_status(StatusLevel::Error, format!("{e:#}")),
+        }
+        Ok(())
+    }
+
+    /// Offered when the loaded shards sit in a directory without an index file
+    fn write_missing_index(&mut self) {
+        let Some(dir) = index::missing_index_dir(&self.files) else {
+            self.set_status(
+                StatusLevel::Warning,
+                format!(
+                    "{} is only written for index-less multi-shard directories",
+                    index::INDEX_FILE_NAME
+                ),
+            );
+            return;
+        };
+        match index::write_index(&dir, false) {
+            Ok(summary) => self.set_status(
+                StatusLevel::Success,
+                format!(
+                    "Wrote {} ({} tensors from {} shards)",
+                    summary.path.display(),
+                    summary.tensors,
+                    summary.shards
+                ),
+            ),
+            Err(e) => self.set_status(StatusLevel::Error, format!("Writing index failed: {e:#}")),
+        }
+    }
+
+    /// Read a line of text in a modal prompt, with Tab completing file system paths;
+    /// None when cancelled with Esc
+    fn prompt_text(&self, title: &str, label: &str, initial: &str) -> Result<Option<String>> {
+        const MAX_CANDIDATES: usize = 12;
+        let mut input = initial.to_string();
+        let mut candidates: Vec<String> = Vec::new();
+        loop {
+            let mut lines = vec![label];
+            if !candidates.is_empty() {
+                lines.push("");
+                lines.extend(candidates.iter().take(MAX_CANDIDATES).map(String::as_str));
+                if candidates.len() > MAX_CANDIDATES {
+                    lines.push("...");
+                }
+                lines.push("");
+            }
+            UI::draw_prompt(title, &lines, Some(&input))?;
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Enter if !input.trim().is_empty() => {
+                        return Ok(Some(input.trim().to_string()));
+                    }
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Tab => (input, candidates) = complete_path(&input),
+                    KeyCode::Backspace => {
+                        input.pop();
+                        candidates.clear();
+                    }
+                    KeyCode::Char(c) => {
+                        input.push(c);
+                        candidates.clear();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn show_report(&self, title: &str, lines: &[String]) -> Result<()> {
+        let mut scroll_offset = 0;
+        loop {
+            scroll_offset = UI::draw_report(title, lines, scroll_offset)?;
+            let (_, terminal_height) = terminal::size()?;
+            let page = (terminal_height as usize).saturating_sub(4).max(1);
+
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Up => scroll_offset = scroll_offset.saturating_sub(1),
+                    KeyCode::Down => scroll_offset += 1,
+                    KeyCode::PageUp => scroll_offset = scroll_offset.saturating_sub(page),
+                    KeyCode::PageDown => scroll_offset += page,
+                    KeyCode::Home => scroll_offset = 0,
+                    KeyCode::End => scroll_offset = lines.len(),
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// The JSON object or array a string metadata value holds, such as a ComfyUI workflow or a
+/// training config stored in safetensors `__metadata__`
+fn json_document(metadata: &MetadataInfo) -> Option<serde_json::Value> {
+    if !matches!(
+        metadata.raw_value.as_deref(),
+        None | Some(GGUFValue::String(_))
+    ) || !metadata.value.trim_start().starts_with(['{', '['])
+    {
+        return None;
+    }
+    serde_json::from_str(&metadata.value)
+        .ok()
+        .filter(|value: &serde_json::Value| value.is_object() || value.is_array())
+}
+
+/// A metadata key made safe for a file name: `ss_dataset_dirs` stays, `a/b:c` becomes `a_b_c`
+fn file_stem_for(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Search hit on a metadata entry: a fuzzy match on the key, or else a substring in the value
+/// (unless searching names only), which ranks below every name match
+fn metadata_hit(
+    matcher: &SkimMatcherV2,
+    query: &str,
+    names_only: bool,
+    metadata: &MetadataInfo,
+) -> Option<(i64, Option<ValueMatch>)> {
+    match matcher.fuzzy_match(&metadata.name, query) {
+        Some(score) => Some((score, None)),
+        None if !names_only => metadata
+            .find_in_value(query)
+            .map(|found| (i64::MIN, Some(found))),
+        None => None,
+    }
+}
+
+/// Draws scan progress in the TUI and lets Esc cancel the scan
+struct TuiProgress {
+    title: &'static str,
+    last_draw: Option<Instant>,
+}
+
+impl TuiProgress {
+    fn new(title: &'static str) -> Self {
+        Self {
+            title,
+            last_draw: None,
+        }
+    }
+
+    /// Draws only once the work has run for 100 ms, so quick loads don't flash a screen
+    fn delayed(title: &'static str) -> Self {
+        Self {
+            title,
+            last_draw: Some(Instant::now()),
+        }
+    }
+
+    /// Redraw at most every 100 ms
+    fn should_draw(&mut self) -> bool {
+        let due = self
+            .last_draw
+            .is_none_or(|t| t.elapsed() >= Duration::from_millis(100));
+        if due {
+            self.last_draw = Some(Instant::now());
+        }
+        due
+    }
+
+    /// False once Esc or q has been pressed
+    fn keep_going() -> bool {
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            if let Ok(Event::Key(key_event)) = event::read()
+                && matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q'))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Progress for TuiProgress {
+    fn update(&mut self, label: &str, done: u64, total: u64) -> bool {
+        if self.should_draw() {
+            let _ = UI::draw_progress(self.title, label, done, total);
+        }
+        Self::keep_going()
+    }
+
+    fn update_workers(&mut self, status: &ScanStatus) -> bool {
+        if self.should_draw() {
+            let _ = UI::draw_worker_progress(self.title, status);
+        }
+        Self::keep_going()
+    }
+}
+
+/// Let the user pick a recently opened path set, forgetting entries with `d` and pruning
+/// the ones whose paths are gone with `x`; the list is saved after every change. Expects
+/// raw mode to be on.
+pub fn choose_recent(theme: Theme) -> Result<Option<Vec<PathBuf>>> {
+    let mut entries = recent::load();
+    let (mut selected, mut scroll_offset) = (0, 0);
+    let mut note: Option<String> = None;
+    loop {
+        scroll_offset = UI::draw_recent(&entries, selected, scroll_offset, note.as_deref(), theme)?;
+        let Event::Key(key_event) = event::read()? else {
+            continue;
+        };
+        note = None;
+        let last = entries.len().saturating_sub(1);
+        let before = entries.len();
+        match key_event.code {
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(last),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            KeyCode::Enter => match entries.get(selected) {
+                Some(entry) if entry.exists() => return Ok(Some(entry.paths.clone())),
+                Some(entry) => {
+                    let gone: Vec<String> = entry
+                        .paths
+                        .iter()
+                        .filter(|path| !path.exists())
+                        .map(|path| path.display().to_string())
+                        .collect();
+                    note = Some(format!("No longer exists: {}", gone.join(", ")));
+                }
+                None => {}
+            },
+            KeyCode::Char('d') if selected < entries.len() => {
+                entries.remove(selected);
+            }
+            KeyCode::Char('x') => {
+                entries.retain(RecentEntry::exists);
+                note = Some(match before - entries.len() {
+                    0 => "Every entry still exists".to_string(),
+                    n => format!(
+                        "Pruned {n} missing entr{}",
+                        if n == 1 { "y" } else { "ies" }
+                    ),
+                });
+            }
+            KeyCode::Char('o') => {
+                if let Some(choice) = browse_files(Path::new("."), theme, false)? {
+                    return Ok(Some(vec![choice.path]));
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+        if entries.len() != before {
+            selected = selected.min(entries.len().saturating_sub(1));
+            if let Err(e) = recent::save(&entries) {
+                note = Some(format!("Failed to save the recent list: {e:#}"));
+            }
+        }
+    }
+}
+
+/// What was picked in the file browser
+pub struct BrowserChoice {
+    pub path: PathBuf,
+    /// Add to the loaded files (`a`) instead of replacing them (Enter)
+    pub add: bool,
+}
+
+/// Let the user pick a file or directory to open, starting in `start`: → enters the
+/// selected directory, ← or Backspace goes up, `.` shows hidden entries. `adding` lets `a`
+/// pick the selection to be added to the loaded files. Expects raw mode to be on.
+pub fn browse_files(start: &Path, theme: Theme, adding: bool) -> Result<Option<BrowserChoice>> {
+    let mut dir = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+    let mut hidden = false;
+    let mut note: Option<String> = None;
+    let (mut selected, mut scroll_offset) = (0, 0);
+    let mut entries = browser::list_dir(&dir, hidden).unwrap_or_else(|e| {
+        note = Some(format!("{e:#}"));
+        Vec::new()
+    });
+    loop {
+        scroll_offset = UI::draw_browser(
+            &dir,
+            &entries,
+            selected,
+            scroll_offset,
+            note.as_deref(),
+            adding,
+            theme,
+        )?;
+        let Event::Key(key_event) = event::read()? else {
+            continue;
+        };
+        note = None;
+        let last = entries.len().saturating_sub(1);
+        // The directory to list next, and the entry to select in it
+        let mut next: Option<(PathBuf, Option<PathBuf>)> = None;
+        match key_event.code {
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(last),
+            KeyCode::PageUp => selected = selected.saturating_sub(10),
+            KeyCode::PageDown => selected = (selected + 10).min(last),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            KeyCode::Right => match entries.get(selected) {
+                Some(entry) if entry.is_dir() => next = Some((entry.path.clone(), None)),
+                _ => {}
+            },
+            KeyCode::Left | KeyCode::Backspace => {
+                if let Some(parent) = dir.parent() {
+                    next = Some((parent.to_path_buf(), Some(dir.clone())));
+                }
+            }
+            KeyCode::Char('.') => {
+                hidden = !hidden;
+                let current = entries.get(selected).map(|entry| entry.path.clone());
+                next = Some((dir.clone(), current));
+                note = Some(if hidden {
+                    "Showing hidden entries".to_string()
+                } else {
+                    "Hiding entries starting with a dot".to_string()
+                });
+            }
+            KeyCode::Enter => {
+                if let Some(entry) = entries.get(selected) {
+                    return Ok(Some(BrowserChoice {
+                        path: entry.path.clone(),
+                        add: false,
+                    }));
+                }
+            }
+            KeyCode::Char('a') if adding => {
+                if let Some(entry) = entries.get(selected) {
+                    return Ok(Some(BrowserChoice {
+                        path: entry.path.clone(),
+                        add: true,
+                    }));
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+        if let Some((target, select)) = next {
+            match browser::list_dir(&target, hidden) {
+                Ok(listed) => {
+                    selected = select
+                        .and_then(|path| listed.iter().position(|entry| entry.path == path))
+                        .unwrap_or(0);
+                    entries = listed;
+                    dir = target;
+                    scroll_offset = 0;
+                }
+                Err(e) => note = Some(format!("{e:#}")),
+            }
+        }
+    }
+}
+
+/// The recent list, or the file browser when nothing was opened yet, on its own for a start
+/// without paths: takes over the terminal only while it is shown
+pub fn choose_at_startup(theme: Theme, recent: bool) -> Result<Option<Vec<PathBuf>>> {
+    terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, terminal::Clear(ClearType::All), cursor::Hide)?;
+    let chosen = if recent {
+        choose_recent(theme)
+    } else {
+        browse_files(Path::new("."), theme, false)
+            .map(|choice| choice.map(|choice| vec![choice.path]))
+    };
+    execute!(stdout, terminal::Clear(ClearType::All), cursor::Show)?;
+    terminal::disable_raw_mode()?;
+    chosen
 }
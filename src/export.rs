@@ -0,0 +1,168 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{fs, path::Path};
+
+use crate::report::{self, Format, Report};
+use crate::tree::{TensorInfo, TreeNode};
+use crate::utils::{format_shape, format_size};
+
+/// One exported row; groups carry totals over all tensors below them
+#[derive(Debug, Serialize)]
+pub struct ExportRow {
+    pub kind: &'static str,
+    pub name: String,
+    pub dtype: String,
+    pub shape: Vec<usize>,
+    pub elements: usize,
+    pub size_bytes: usize,
+    pub tensor_count: usize,
+    pub value: String,
+}
+
+const COLUMNS: &[&str] = &[
+    "kind",
+    "name",
+    "dtype",
+    "shape",
+    "elements",
+    "size_bytes",
+    "tensor_count",
+    "value",
+];
+
+impl ExportRow {
+    fn cells(&self) -> [String; 8] {
+        [
+            self.kind.to_string(),
+            self.name.clone(),
+            self.dtype.clone(),
+            if self.kind == "tensor" {
+                format_shape(&self.shape)
+            } else {
+                String::new()
+            },
+            self.elements.to_string(),
+            self.size_bytes.to_string(),
+            self.tensor_count.to_string(),
+            self.value.clone(),
+        ]
+    }
+}
+
+fn subtree_elements(node: &TreeNode) -> usize {
+    match node {
+        TreeNode::Group { children, .. } => children.iter().map(subtree_elements).sum(),
+        TreeNode::Tensor { info } => info.num_elements,
+        _ => 0,
+    }
+}
+
+fn tensor_row(info: &TensorInfo) -> ExportRow {
+    ExportRow {
+        kind: "tensor",
+        name: info.name.clone(),
+        dtype: info.dtype.clone(),
+        shape: info.shape.clone(),
+        elements: info.num_elements,
+        size_bytes: info.size_bytes,
+        tensor_count: 1,
+        value: String::new(),
+    }
+}
+
+/// One row per tensor, for the non-interactive listing
+pub fn rows_from_tensors(tensors: &[TensorInfo]) -> Vec<ExportRow> {
+    tensors.iter().map(tensor_row).collect()
+}
+
+/// Convert displayed rows into export rows, rebuilding full group paths from the depths
+pub fn rows_from_tree(tree: &[(TreeNode, usize)]) -> Vec<ExportRow> {
+    let mut path: Vec<String> = Vec::new();
+    let mut rows = Vec::with_capacity(tree.len());
+    for (node, depth) in tree {
+        path.truncate(*depth);
+        let row = match node {
+            TreeNode::Group {
+                name,
+                tensor_count,
+                total_size,
+                ..
+            } => {
+                path.push(name.clone());
+                ExportRow {
+                    kind: "group",
+                    name: path.join("."),
+                    dtype: String::new(),
+                    shape: Vec::new(),
+                    elements: subtree_elements(node),
+                    size_bytes: *total_size,
+                    tensor_count: *tensor_count,
+                    value: String::new(),
+                }
+            }
+            TreeNode::Tensor { info } => tensor_row(info),
+            TreeNode::Metadata { info } => ExportRow {
+                kind: "metadata",
+                name: info.name.clone(),
+                dtype: info.value_type.clone(),
+                shape: Vec::new(),
+                elements: 0,
+                size_bytes: 0,
+                tensor_count: 0,
+                value: info.value.clone(),
+            },
+            TreeNode::Failed { info, .. } => ExportRow {
+                kind: "error",
+                name: info.file.to_string_lossy().into_owned(),
+                dtype: String::new(),
+                shape: Vec::new(),
+                elements: 0,
+                size_bytes: 0,
+                tensor_count: 0,
+                value: info.chain.join(": "),
+            },
+            _ => continue,
+        };
+        rows.push(row);
+    }
+    rows
+}
+
+/// Rows of the tree or of a tensor listing; serialized as a bare array
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct Listing(pub Vec<ExportRow>);
+
+impl Report for Listing {
+    fn text_lines(&self) -> Vec<String> {
+        let rows: Vec<Vec<String>> = self
+            .0
+            .iter()
+            .map(|row| {
+                let [kind, name, dtype, shape, ..] = row.cells();
+                let size = if row.kind == "metadata" {
+                    row.value.clone()
+                } else {
+                    format_size(row.size_bytes)
+                };
+                vec![kind, name, dtype, shape, size]
+            })
+            .collect();
+        report::aligned_lines(&["kind", "name", "dtype", "shape", "size/value"], &rows)
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.0.iter().map(|row| row.cells().to_vec()).collect()
+    }
+}
+
+/// Write a listing to `path`, returning how many rows were written
+pub fn write_listing(listing: &Listing, format: Format, path: &Path) -> Result<usize> {
+    let content = report::render(listing, format)?;
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(listing.0.len())
+}
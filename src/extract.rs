@@ -0,0 +1,112 @@
+//! Copy the untouched bytes of a tensor out of its file, with a JSON sidecar saying
+//! what they are and where they came from.
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::gguf::GGMLType;
+use crate::scan;
+use crate::tree::TensorInfo;
+
+/// Contents of the `<name>.json` sidecar
+#[derive(Debug, Serialize)]
+pub struct RawDescription {
+    pub name: String,
+    pub dtype: String,
+    /// `ggml_type` id of a GGUF tensor
+    pub ggml_type: Option<u32>,
+    /// Row-major (HF) order
+    pub shape: Vec<usize>,
+    /// GGUF ne order, fastest-varying first
+    pub storage_shape: Option<Vec<usize>>,
+    pub byte_length: usize,
+    pub source_file: PathBuf,
+    /// Absolute byte range of the data in `source_file`, end exclusive
+    pub source_offset: u64,
+    pub source_end: u64,
+}
+
+/// Files written for one tensor
+pub struct Extracted {
+    pub data: PathBuf,
+    pub sidecar: PathBuf,
+    pub bytes: usize,
+}
+
+/// Exact length of a tensor's data. GGUF sizes come from the block layout of the type;
+/// an estimated size would cut or overrun the range, so such tensors are refused.
+fn exact_length(tensor: &TensorInfo) -> Result<(usize, Option<GGMLType>)> {
+    // Only GGUF tensors carry a storage shape
+    if tensor.storage_shape.is_none() {
+        return Ok((tensor.size_bytes, None));
+    }
+    let Some(ty) = GGMLType::from_name(&tensor.dtype) else {
+        bail!("Unknown GGML type {} of {}", tensor.dtype, tensor.name);
+    };
+    let bytes = ty.tensor_bytes(tensor.num_elements as u64).with_context(|| {
+        format!(
+            "The block layout of {} is unknown, so the byte range of {} can't be computed exactly",
+            tensor.dtype, tensor.name
+        )
+    })?;
+    Ok((bytes as usize, Some(ty)))
+}
+
+/// File stem for a tensor: its name with path separators replaced
+pub(crate) fn file_stem(name: &str) -> String {
+    name.replace(['/', '\\'], "_")
+}
+
+/// Write the tensor's bytes to `<dir>/<name>.bin` and its description to `<dir>/<name>.json`
+pub fn extract_raw(tensor: &TensorInfo, dir: &Path) -> Result<Extracted> {
+    let offset = tensor.data_location()?;
+    let (bytes, ggml_type) = exact_length(tensor)?;
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let stem = file_stem(&tensor.name);
+    let data = dir.join(format!("{stem}.bin"));
+    let sidecar = dir.join(format!("{stem}.json"));
+
+    let mut out = BufWriter::new(
+        File::create(&data).with_context(|| format!("Failed to create {}", data.display()))?,
+    );
+    let exact = TensorInfo {
+        size_bytes: bytes,
+        ..tensor.clone()
+    };
+    let mut written = Ok(());
+    scan::stream_tensor(&exact, |chunk| {
+        written = out.write_all(chunk);
+        written.is_ok()
+    })?;
+    written
+        .and_then(|()| out.flush())
+        .with_context(|| format!("Failed to write {}", data.display()))?;
+
+    let description = RawDescription {
+        name: tensor.name.clone(),
+        dtype: tensor.dtype.clone(),
+        ggml_type: ggml_type.map(|ty| ty as u32),
+        shape: tensor.shape.clone(),
+        storage_shape: tensor.storage_shape.clone(),
+        byte_length: bytes,
+        source_file: tensor.source_file.clone(),
+        source_offset: offset,
+        source_end: offset + bytes as u64,
+    };
+    write_sidecar(&sidecar, &description)?;
+
+    Ok(Extracted {
+        data,
+        sidecar,
+        bytes,
+    })
+}
+
+pub(crate) fn write_sidecar(path: &Path, description: &RawDescription) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(description)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
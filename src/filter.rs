@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+
+use crate::summary;
+use crate::tree::TensorInfo;
+
+/// Kinds of rows told apart by name, offered next to the dtypes in the filter pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Metadata,
+    Embeddings,
+    Norms,
+    Biases,
+    /// Tensors in none of the named categories
+    Other,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Metadata,
+        Category::Embeddings,
+        Category::Norms,
+        Category::Biases,
+        Category::Other,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Metadata => "metadata",
+            Category::Embeddings => "embeddings",
+            Category::Norms => "norms",
+            Category::Biases => "biases",
+            Category::Other => "other tensors",
+        }
+    }
+
+    /// Whether a tensor belongs here; a norm's bias is both a norm and a bias
+    pub fn contains(self, tensor: &TensorInfo) -> bool {
+        let name = tensor.name.as_str();
+        match self {
+            Category::Metadata => false,
+            Category::Embeddings => summary::is_embedding(name),
+            Category::Norms => name
+                .split('.')
+                .any(|part| part.contains("norm") || part == "ln" || part.starts_with("ln_")),
+            Category::Biases => name.ends_with("bias"),
+            Category::Other => [Category::Embeddings, Category::Norms, Category::Biases]
+                .iter()
+                .all(|category| !category.contains(tensor)),
+        }
+    }
+}
+
+/// One line of the filter pane
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterEntry {
+    Dtype(String),
+    Category(Category),
+}
+
+/// The entries checked in the filter pane. A tensor is shown when its dtype is checked
+/// (or no dtype is) and one of its categories is checked (or no category is); metadata
+/// is shown while nothing is checked or `metadata` is.
+#[derive(Debug, Clone, Default)]
+pub struct TypeFilter {
+    dtypes: BTreeSet<String>,
+    categories: BTreeSet<Category>,
+}
+
+impl TypeFilter {
+    pub fn is_active(&self) -> bool {
+        !self.dtypes.is_empty() || !self.categories.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.dtypes.clear();
+        self.categories.clear();
+    }
+
+    pub fn is_checked(&self, entry: &FilterEntry) -> bool {
+        match entry {
+            FilterEntry::Dtype(dtype) => self.dtypes.contains(dtype),
+            FilterEntry::Category(category) => self.categories.contains(category),
+        }
+    }
+
+    pub fn toggle(&mut self, entry: &FilterEntry) {
+        match entry {
+            FilterEntry::Dtype(dtype) => {
+                if !self.dtypes.remove(dtype) {
+                    self.dtypes.insert(dtype.clone());
+                }
+            }
+            FilterEntry::Category(category) => {
+                if !self.categories.remove(category) {
+                    self.categories.insert(*category);
+                }
+            }
+        }
+    }
+
+    /// Drop checked dtypes no loaded tensor has any more, e.g. after a reload
+    pub fn retain_dtypes(&mut self, tensors: &[TensorInfo]) {
+        self.dtypes
+            .retain(|dtype| tensors.iter().any(|t| t.dtype == *dtype));
+    }
+
+    pub fn shows_tensor(&self, tensor: &TensorInfo) -> bool {
+        (self.dtypes.is_empty() || self.dtypes.contains(&tensor.dtype))
+            && (self.categories.is_empty()
+                || self
+                    .categories
+                    .iter()
+                    .any(|category| category.contains(tensor)))
+    }
+
+    pub fn shows_metadata(&self) -> bool {
+        !self.is_active() || self.categories.contains(&Category::Metadata)
+    }
+
+    /// Checked entries for the header, e.g. `F16, BF16; norms`
+    pub fn summary(&self) -> String {
+        let dtypes: Vec<&str> = self.dtypes.iter().map(String::as_str).collect();
+        let categories: Vec<&str> = self.categories.iter().map(|c| c.label()).collect();
+        [dtypes.join(", "), categories.join(", ")]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Every dtype present (most tensors first), then the categories, each with the number
+/// of rows it covers; entries that cover nothing are left out
+pub fn entries(tensors: &[TensorInfo], metadata_keys: usize) -> Vec<(FilterEntry, usize)> {
+    let mut dtypes: Vec<(String, usize)> = Vec::new();
+    for tensor in tensors {
+        match dtypes.iter_mut().find(|(dtype, _)| *dtype == tensor.dtype) {
+            Some((_, count)) => *count += 1,
+            None => dtypes.push((tensor.dtype.clone(), 1)),
+        }
+    }
+    dtypes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let categories = Category::ALL.into_iter().map(|category| {
+        let count = match category {
+            Category::Metadata => metadata_keys,
+            _ => tensors.iter().filter(|t| category.contains(t)).count(),
+        };
+        (FilterEntry::Category(category), count)
+    });
+    dtypes
+        .into_iter()
+        .map(|(dtype, count)| (FilterEntry::Dtype(dtype), count))
+        .chain(categories)
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
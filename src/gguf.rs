@@ -1,18 +1,28 @@
 #![allow(unused, non_camel_case_types)]
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::io::{Cursor, Read};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::model::AtOffset;
 
 /// GGUF file format parser
 /// Based on llama.cpp GGUF specification
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct GGUFFile {
     pub header: GGUFHeader,
     pub metadata: HashMap<String, GGUFValue>,
     pub tensors: Vec<GGUFTensorInfo>,
+    /// Absolute file offset where the tensor data section begins
+    pub data_offset: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct GGUFHeader {
     pub magic: u32,
     pub version: u32,
@@ -20,7 +30,8 @@ pub struct GGUFHeader {
     pub metadata_kv_count: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct GGUFTensorInfo {
     pub name: String,
     pub dimensions: Vec<u64>,
@@ -28,8 +39,154 @@ pub struct GGUFTensorInfo {
     pub offset: u64,
 }
 
+impl GGUFTensorInfo {
+    /// Product of the dimensions; None when it overflows, which only a corrupt record has
+    pub fn num_elements(&self) -> Option<u64> {
+        self.dimensions
+            .iter()
+            .try_fold(1u64, |product, &dim| product.checked_mul(dim))
+    }
+
+    /// Exact bytes of tensor data, or None when the type's block layout is unknown or the
+    /// element count is not a whole number of blocks
+    pub fn data_size(&self) -> Option<u64> {
+        self.tensor_type.tensor_bytes(self.num_elements()?)
+    }
+
+    /// Bytes of tensor data, estimated from the element size for types without a known
+    /// block layout, with whether it was estimated; None when it overflows
+    pub fn stored_size(&self) -> Option<(u64, bool)> {
+        match self.data_size() {
+            Some(size) => Some((size, false)),
+            None => {
+                let estimate =
+                    self.num_elements()? as f64 * self.tensor_type.element_size_bytes() as f64;
+                (estimate < u64::MAX as f64).then_some((estimate as u64, true))
+            }
+        }
+    }
+}
+
+/// Where the bytes of a GGUF data section go: tensor data, alignment padding after each
+/// tensor, and anything the layout does not explain
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct GGUFLayout {
+    /// `general.alignment`, 32 when the file does not set it
+    pub alignment: u64,
+    /// End of the tensor records; the data section starts at the next aligned offset
+    pub tensor_info_end: u64,
+    pub data_offset: u64,
+    pub file_size: u64,
+    /// Each tensor by data offset, with the bytes between its end and the next tensor (or
+    /// the end of the file, at least 0); negative when the next tensor overlaps it
+    pub tensors: Vec<(String, i64)>,
+    /// Bytes between the data section start and the first tensor
+    pub leading_gap: u64,
+    /// Tensors whose size had to be estimated, which makes their padding approximate
+    pub estimated: usize,
+}
+
+impl GGUFLayout {
+    /// Lay out the tensors of a file whose tensor records end at `tensor_info_end`; fails
+    /// for a tensor whose size or end offset overflows
+    pub fn new(
+        alignment: u64,
+        tensor_info_end: u64,
+        data_offset: u64,
+        file_size: u64,
+        tensors: &[GGUFTensorInfo],
+    ) -> Result<Self> {
+        let mut sorted: Vec<&GGUFTensorInfo> = tensors.iter().collect();
+        sorted.sort_by_key(|tensor| tensor.offset);
+        let data_end = file_size.saturating_sub(data_offset);
+        let mut estimated = 0;
+        let mut padding = Vec::with_capacity(sorted.len());
+        for (i, tensor) in sorted.iter().enumerate() {
+            let end = tensor_end(tensor)? as i64;
+            if tensor.data_size().is_none() {
+                estimated += 1;
+            }
+            let after = match sorted.get(i + 1) {
+                Some(next) => next.offset as i64 - end,
+                // A file ending inside its last tensor is truncated, not overlapping;
+                // the loader reports that
+                None => (data_end as i64 - end).max(0),
+            };
+            padding.push((tensor.name.clone(), after));
+        }
+        Ok(Self {
+            alignment,
+            tensor_info_end,
+            data_offset,
+            file_size,
+            tensors: padding,
+            leading_gap: sorted.first().map_or(0, |tensor| tensor.offset),
+            estimated,
+        })
+    }
+
+    /// Bytes spent on padding: before the data section, before the first tensor and after
+    /// each tensor
+    pub fn padding_bytes(&self) -> u64 {
+        (self.data_offset - self.tensor_info_end)
+            + self.leading_gap
+            + self
+                .tensors
+                .iter()
+                .map(|(_, padding)| (*padding).max(0) as u64)
+                .sum::<u64>()
+    }
+
+    /// Padding as a percentage of the file size
+    pub fn padding_percent(&self) -> f64 {
+        if self.file_size == 0 {
+            0.0
+        } else {
+            self.padding_bytes() as f64 / self.file_size as f64 * 100.0
+        }
+    }
+
+    /// Tensors by padding after them, largest first, ties in file order
+    pub fn largest_padding(&self) -> Vec<(&str, i64)> {
+        let mut sorted: Vec<(&str, i64)> = self
+            .tensors
+            .iter()
+            .map(|(name, padding)| (name.as_str(), *padding))
+            .collect();
+        sorted.sort_by_key(|(_, padding)| std::cmp::Reverse(*padding));
+        sorted
+    }
+
+    /// Gaps wider than the alignment can explain, and overlapping tensors
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.leading_gap > 0 {
+            problems.push(format!(
+                "{} bytes between the data section start and the first tensor",
+                self.leading_gap
+            ));
+        }
+        for (name, padding) in &self.tensors {
+            if *padding < 0 {
+                problems.push(format!(
+                    "{name} overlaps the next tensor by {} bytes",
+                    padding.unsigned_abs()
+                ));
+            } else if *padding as u64 >= self.alignment {
+                problems.push(format!(
+                    "{padding}-byte gap after {name}, more than alignment {} explains",
+                    self.alignment
+                ));
+            }
+        }
+        problems
+    }
+}
+
 #[repr(u32)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub enum MetadataType {
     U8 = 0,
     I8 = 1,
@@ -49,7 +206,7 @@ pub enum MetadataType {
 impl TryFrom<u32> for MetadataType {
     type Error = anyhow::Error;
     fn try_from(val: u32) -> Result<Self, Self::Error> {
-        return match val {
+        match val {
             0 => Ok(MetadataType::U8),
             1 => Ok(MetadataType::I8),
             2 => Ok(MetadataType::U16),
@@ -64,7 +221,7 @@ impl TryFrom<u32> for MetadataType {
             11 => Ok(MetadataType::I64),
             12 => Ok(MetadataType::F64),
             _ => Err(anyhow::anyhow!("Invalid metadata type: {val}")),
-        };
+        }
     }
 }
 
@@ -90,7 +247,8 @@ impl std::fmt::Display for MetadataType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub enum GGUFValue {
     U8(u8),
     I8(i8),
@@ -110,7 +268,8 @@ pub enum GGUFValue {
 /// GGML tensor types from llama.cpp
 /// Includes all quantization formats
 #[repr(u32)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[non_exhaustive]
 pub enum GGMLType {
     F32 = 0,
     F16 = 1,
@@ -145,6 +304,7 @@ pub enum GGMLType {
 }
 
 impl GGMLType {
+    /// The type for a raw `ggml_type` id; None for ids this parser does not know
     pub fn from_u32(value: u32) -> Option<Self> {
         match value {
             0 => Some(GGMLType::F32),
@@ -181,8 +341,58 @@ impl GGMLType {
         }
     }
 
+    /// The type whose display name is `name`, e.g. `Q4_K`
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..64)
+            .filter_map(Self::from_u32)
+            .find(|ty| ty.to_string() == name)
+    }
+
+    /// Elements per block and bytes per block, as in ggml's type traits; None for types
+    /// whose block layout this parser does not know
+    pub fn block_layout(&self) -> Option<(u64, u64)> {
+        Some(match self {
+            GGMLType::F32 | GGMLType::I32 => (1, 4),
+            GGMLType::F16 | GGMLType::BF16 | GGMLType::I16 => (1, 2),
+            GGMLType::F64 | GGMLType::I64 => (1, 8),
+            GGMLType::I8 => (1, 1),
+            GGMLType::Q4_0 => (32, 18),
+            GGMLType::Q4_1 => (32, 20),
+            GGMLType::Q5_0 => (32, 22),
+            GGMLType::Q5_1 => (32, 24),
+            GGMLType::Q8_0 => (32, 34),
+            GGMLType::Q8_1 => (32, 36),
+            GGMLType::IQ4_NL => (32, 18),
+            GGMLType::Q2_K => (256, 84),
+            GGMLType::Q3_K => (256, 110),
+            GGMLType::Q4_K => (256, 144),
+            GGMLType::Q5_K => (256, 176),
+            GGMLType::Q6_K => (256, 210),
+            GGMLType::Q8_K => (256, 292),
+            GGMLType::IQ1_S => (256, 50),
+            GGMLType::IQ1_M => (256, 56),
+            GGMLType::IQ2_XXS => (256, 66),
+            GGMLType::IQ2_XS => (256, 74),
+            GGMLType::IQ2_S => (256, 82),
+            GGMLType::IQ3_XXS => (256, 98),
+            GGMLType::IQ3_S => (256, 110),
+            GGMLType::IQ4_XS => (256, 136),
+            GGMLType::GGML_TYPE_Q1_58 => return None,
+        })
+    }
+
+    /// Exact bytes taken by `elements` values; None when the block layout is unknown,
+    /// `elements` is not a whole number of blocks or the byte count overflows
+    pub fn tensor_bytes(&self, elements: u64) -> Option<u64> {
+        let (block, bytes) = self.block_layout()?;
+        elements
+            .is_multiple_of(block)
+            .then(|| (elements / block).checked_mul(bytes))
+            .flatten()
+    }
+
     /// Get the size in bytes per element for this type
-    /// For quantized types, this is an approximation
+    /// For quantized types, this is an approximation; `tensor_bytes` is exact
     pub fn element_size_bytes(&self) -> f32 {
         match self {
             GGMLType::F32 | GGMLType::I32 => 4.0,
@@ -211,10 +421,10 @@ impl GGMLType {
             GGMLType::IQ1_M => 0.218_75,         // 1.75   bpw
             GGMLType::IQ2_XXS => 0.257_812_5,    // 2.0625 bpw
             GGMLType::IQ2_XS => 0.289_062_5,     // 2.3125 bpw
-            GGMLType::IQ2_S => 0.3125,           // 2.5    bpw
+            GGMLType::IQ2_S => 0.320_312_5,      // 2.5625 bpw
             GGMLType::IQ3_XXS => 0.382_812_5,    // 3.0625 bpw
             GGMLType::IQ3_S => 0.429_687_5,      // 3.4375 bpw
-            GGMLType::IQ4_NL => 0.53125,         // 4.25   bpw
+            GGMLType::IQ4_NL => 0.5625,          // 4.5    bpw
             GGMLType::IQ4_XS => 0.53125,         // 4.25   bpw
             GGMLType::GGML_TYPE_Q1_58 => 0.1975, // 1.58 / 8
         }
@@ -259,6 +469,138 @@ impl std::fmt::Display for GGMLType {
     }
 }
 
+impl GGUFValue {
+    /// Type label shown next to metadata values, e.g. `u32` or `array<string>`
+    pub fn type_name(&self) -> String {
+        match self {
+            GGUFValue::U8(_) => "u8".to_string(),
+            GGUFValue::I8(_) => "i8".to_string(),
+            GGUFValue::U16(_) => "u16".to_string(),
+            GGUFValue::I16(_) => "i16".to_string(),
+            GGUFValue::U32(_) => "u32".to_string(),
+            GGUFValue::I32(_) => "i32".to_string(),
+            GGUFValue::F32(_) => "f32".to_string(),
+            GGUFValue::U64(_) => "u64".to_string(),
+            GGUFValue::I64(_) => "i64".to_string(),
+            GGUFValue::F64(_) => "f64".to_string(),
+            GGUFValue::Bool(_) => "bool".to_string(),
+            GGUFValue::String(_) => "string".to_string(),
+            GGUFValue::Array(ty, _) => format!("array<{ty}>"),
+        }
+    }
+
+    /// The value as JSON, with arrays in full (used by `--get` for scripting)
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            GGUFValue::U8(v) => (*v).into(),
+            GGUFValue::I8(v) => (*v).into(),
+            GGUFValue::U16(v) => (*v).into(),
+            GGUFValue::I16(v) => (*v).into(),
+            GGUFValue::U32(v) => (*v).into(),
+            GGUFValue::I32(v) => (*v).into(),
+            GGUFValue::F32(v) => (*v).into(),
+            GGUFValue::U64(v) => (*v).into(),
+            GGUFValue::I64(v) => (*v).into(),
+            GGUFValue::F64(v) => (*v).into(),
+            GGUFValue::Bool(v) => (*v).into(),
+            GGUFValue::String(v) => v.as_str().into(),
+            GGUFValue::Array(_, items) => items.iter().map(GGUFValue::to_json).collect(),
+        }
+    }
+
+    /// The value of an integer entry; None for floats, bools, strings and arrays
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            GGUFValue::U8(v) => Some(*v as i64),
+            GGUFValue::I8(v) => Some(*v as i64),
+            GGUFValue::U16(v) => Some(*v as i64),
+            GGUFValue::I16(v) => Some(*v as i64),
+            GGUFValue::U32(v) => Some(*v as i64),
+            GGUFValue::I32(v) => Some(*v as i64),
+            GGUFValue::U64(v) => i64::try_from(*v).ok(),
+            GGUFValue::I64(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+/// Category of a `tokenizer.ggml.token_type` entry, as in llama.cpp's `llama_token_type`
+pub fn token_type_name(value: i64) -> &'static str {
+    match value {
+        0 => "undefined",
+        1 => "normal",
+        2 => "unknown",
+        3 => "control",
+        4 => "user-defined",
+        5 => "unused",
+        6 => "byte",
+        _ => "invalid",
+    }
+}
+
+/// Tokens per category of a `tokenizer.ggml.token_type` array: normal first, then the
+/// other types by id, with undefined and invalid (out-of-range or non-integer) entries last
+pub fn token_type_counts(values: &[GGUFValue]) -> Vec<(&'static str, usize)> {
+    let mut counts = [0usize; 8];
+    for value in values {
+        let index = match value.as_i64() {
+            Some(id @ 0..=6) => id as usize,
+            _ => 7,
+        };
+        counts[index] += 1;
+    }
+    [1, 2, 3, 4, 5, 6, 0, 7]
+        .into_iter()
+        .filter(|&index| counts[index] > 0)
+        .map(|index| (token_type_name(index as i64), counts[index]))
+        .collect()
+}
+
+/// The two pieces of a `tokenizer.ggml.merges` rule, stored as `"left right"`
+pub fn merge_pieces(rule: &GGUFValue) -> Option<(&str, &str)> {
+    match rule {
+        GGUFValue::String(rule) => rule.split_once(' '),
+        _ => None,
+    }
+}
+
+/// Count of a merges array and the longest and shortest pieces its rules produce
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct MergeStats {
+    pub count: usize,
+    pub longest: Option<String>,
+    pub shortest: Option<String>,
+}
+
+/// One pass over the rules; only the current extremes are ever concatenated
+pub fn merge_stats(rules: &[GGUFValue]) -> MergeStats {
+    let mut longest: Option<(usize, usize)> = None;
+    let mut shortest: Option<(usize, usize)> = None;
+    for (index, rule) in rules.iter().enumerate() {
+        let Some((left, right)) = merge_pieces(rule) else {
+            continue;
+        };
+        let length = left.chars().count() + right.chars().count();
+        if longest.is_none_or(|(_, l)| length > l) {
+            longest = Some((index, length));
+        }
+        if shortest.is_none_or(|(_, l)| length < l) {
+            shortest = Some((index, length));
+        }
+    }
+    let result = |extreme: Option<(usize, usize)>| {
+        extreme
+            .and_then(|(index, _)| merge_pieces(&rules[index]))
+            .map(|(left, right)| format!("{left}{right}"))
+    };
+    MergeStats {
+        count: rules.len(),
+        longest: result(longest),
+        shortest: result(shortest),
+    }
+}
+
 impl std::fmt::Display for GGUFValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -302,174 +644,379 @@ impl std::fmt::Display for GGUFValue {
 }
 
 impl GGUFFile {
+    /// Parse the header, metadata and tensor records of a GGUF file held in memory. Only the
+    /// bytes up to the data section are needed; errors name the offset where parsing stopped.
     pub fn read(data: &[u8]) -> Result<Self> {
-        let mut cursor = Cursor::new(data);
+        let mut reader = GGUFReader::new(data)?;
+        let metadata = reader.metadata().collect::<Result<HashMap<_, _>>>()?;
+        let tensors = reader.tensors().collect::<Result<Vec<_>>>()?;
+        let data_offset = reader.data_offset().expect("all tensor records were read");
+        Ok(GGUFFile {
+            header: reader.header,
+            metadata,
+            tensors,
+            data_offset,
+        })
+    }
+
+    /// Open a GGUF file for streaming: only the header is read here, metadata and tensor
+    /// records are read as the reader's iterators are advanced
+    pub fn open(path: impl AsRef<Path>) -> Result<GGUFReader<BufReader<File>>> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+        GGUFReader::new(BufReader::new(file))
+    }
+}
 
-        // Read header
-        let header = Self::read_header(&mut cursor)?;
+/// Reads a GGUF file's metadata and tensor records lazily, in file order. The metadata must
+/// come off the reader before the tensor records; `tensors` skips whatever is left of it.
+/// After an error both iterators end.
+pub struct GGUFReader<R> {
+    reader: Counted<R>,
+    pub header: GGUFHeader,
+    metadata_read: u64,
+    tensors_read: u64,
+    /// `general.alignment`, once the metadata has been read past it
+    alignment: u64,
+    failed: bool,
+}
+
+impl<R: Read> GGUFReader<R> {
+    /// Read and check the header from a reader positioned at the start of a GGUF file
+    pub fn new(reader: R) -> Result<Self> {
+        let mut reader = Counted {
+            inner: reader,
+            position: 0,
+        };
+        let header = read_header(&mut reader).with_context(|| AtOffset {
+            offset: reader.position,
+            what: "Truncated GGUF header".to_string(),
+        })?;
 
         // Validate magic number
         if header.magic != 0x46554747 {
-            return Err(anyhow::anyhow!("Invalid GGUF magic number"));
+            return Err(anyhow::Error::msg(AtOffset {
+                offset: 0,
+                what: format!("Invalid GGUF magic number {:#010x}", header.magic),
+            }));
         }
-
-        // Read metadata
-        let metadata = Self::read_metadata(&mut cursor, header.metadata_kv_count)?;
-
-        // Read tensor info
-        let tensors = Self::read_tensor_info(&mut cursor, header.tensor_count)?;
-
-        Ok(GGUFFile {
+        log::info!(
+            "GGUF header: version={} tensor_count={} metadata_kv_count={}",
+            header.version,
+            header.tensor_count,
+            header.metadata_kv_count
+        );
+        Ok(Self {
+            reader,
             header,
-            metadata,
-            tensors,
+            metadata_read: 0,
+            tensors_read: 0,
+            alignment: 32,
+            failed: false,
         })
     }
 
-    fn read_header(cursor: &mut Cursor<&[u8]>) -> Result<GGUFHeader> {
-        let magic = Self::read_u32(cursor)?;
-        let version = Self::read_u32(cursor)?;
-        let tensor_count = Self::read_u64(cursor)?;
-        let metadata_kv_count = Self::read_u64(cursor)?;
+    /// `general.alignment` if the metadata read so far set it, else the default of 32
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
 
-        Ok(GGUFHeader {
-            magic,
-            version,
-            tensor_count,
-            metadata_kv_count,
-        })
+    /// Offset just past the last tensor record, once all of them were read without error
+    pub fn tensor_info_end(&self) -> Option<u64> {
+        if self.failed || self.tensors_read < self.header.tensor_count {
+            return None;
+        }
+        Some(self.reader.position)
     }
 
-    fn read_metadata(cursor: &mut Cursor<&[u8]>, count: u64) -> Result<HashMap<String, GGUFValue>> {
-        let mut metadata = HashMap::new();
+    /// Metadata key/value pairs not read yet
+    pub fn metadata(&mut self) -> GGUFMetadataIter<'_, R> {
+        GGUFMetadataIter { gguf: self }
+    }
 
-        for _ in 0..count {
-            let key = Self::read_string(cursor)?;
-            let value_type = Self::read_u32(cursor)?;
-            let value = Self::read_value(cursor, value_type)?;
-            metadata.insert(key, value);
+    /// Tensor records not read yet, after skipping any unread metadata
+    pub fn tensors(&mut self) -> GGUFTensorIter<'_, R> {
+        for entry in self.metadata() {
+            if entry.is_err() {
+                break;
+            }
         }
+        GGUFTensorIter { gguf: self }
+    }
 
-        Ok(metadata)
+    /// Absolute offset of the tensor data section; known once every tensor record was read
+    pub fn data_offset(&self) -> Option<u64> {
+        let info_end = self.tensor_info_end()?;
+        // Tensor data starts at the next multiple of `general.alignment` (default 32)
+        let data_offset = info_end.div_ceil(self.alignment) * self.alignment;
+        log::debug!(
+            "GGUF tensor info ends at offset {}; data starts at {data_offset} (alignment {})",
+            self.reader.position,
+            self.alignment
+        );
+        Some(data_offset)
     }
+}
 
-    fn read_tensor_info(cursor: &mut Cursor<&[u8]>, count: u64) -> Result<Vec<GGUFTensorInfo>> {
-        let mut tensors = Vec::new();
+pub struct GGUFMetadataIter<'a, R> {
+    gguf: &'a mut GGUFReader<R>,
+}
 
-        for _ in 0..count {
-            let name = Self::read_string(cursor)?;
-            let n_dimensions = Self::read_u32(cursor)?;
-            let mut dimensions = Vec::new();
+impl<R: Read> Iterator for GGUFMetadataIter<'_, R> {
+    type Item = Result<(String, GGUFValue)>;
 
-            for _ in 0..n_dimensions {
-                dimensions.push(Self::read_u64(cursor)?);
+    fn next(&mut self) -> Option<Self::Item> {
+        let gguf = &mut *self.gguf;
+        if gguf.failed || gguf.metadata_read >= gguf.header.metadata_kv_count {
+            return None;
+        }
+        let index = gguf.metadata_read;
+        let offset = gguf.reader.position;
+        let entry = read_metadata_entry(&mut gguf.reader).with_context(|| AtOffset {
+            offset: gguf.reader.position,
+            what: format!("GGUF metadata entry {index} starting at offset {offset}"),
+        });
+        match &entry {
+            Ok((key, value)) => {
+                log::debug!(
+                    "GGUF metadata[{index}] offset={offset} key={key} type={}",
+                    value.type_name()
+                );
+                if key == "general.alignment"
+                    && let GGUFValue::U32(alignment) = value
+                    && *alignment > 0
+                {
+                    gguf.alignment = *alignment as u64;
+                }
+                gguf.metadata_read += 1;
             }
+            Err(_) => gguf.failed = true,
+        }
+        Some(entry)
+    }
 
-            let tensor_type_u32 = Self::read_u32(cursor)?;
-            let tensor_type = GGMLType::from_u32(tensor_type_u32)
-                .ok_or_else(|| anyhow::anyhow!("Unknown tensor type: {}", tensor_type_u32))?;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = (self.gguf.header.metadata_kv_count - self.gguf.metadata_read) as usize;
+        (0, Some(left))
+    }
+}
 
-            let offset = Self::read_u64(cursor)?;
+pub struct GGUFTensorIter<'a, R> {
+    gguf: &'a mut GGUFReader<R>,
+}
 
-            tensors.push(GGUFTensorInfo {
-                name,
-                dimensions,
-                tensor_type,
-                offset,
-            });
-        }
+impl<R: Read> Iterator for GGUFTensorIter<'_, R> {
+    type Item = Result<GGUFTensorInfo>;
 
-        Ok(tensors)
-    }
-
-    fn read_value(cursor: &mut Cursor<&[u8]>, value_type: u32) -> Result<GGUFValue> {
-        match MetadataType::try_from(value_type)? {
-            MetadataType::U8 => Ok(GGUFValue::U8(Self::read_u8(cursor)?)),
-            MetadataType::I8 => Ok(GGUFValue::I8(Self::read_i8(cursor)?)),
-            MetadataType::U16 => Ok(GGUFValue::U16(Self::read_u16(cursor)?)),
-            MetadataType::I16 => Ok(GGUFValue::I16(Self::read_i16(cursor)?)),
-            MetadataType::U32 => Ok(GGUFValue::U32(Self::read_u32(cursor)?)),
-            MetadataType::I32 => Ok(GGUFValue::I32(Self::read_i32(cursor)?)),
-            MetadataType::F32 => Ok(GGUFValue::F32(Self::read_f32(cursor)?)),
-            MetadataType::Bool => Ok(GGUFValue::Bool(Self::read_u8(cursor)? != 0)),
-            MetadataType::String => Ok(GGUFValue::String(Self::read_string(cursor)?)),
-            MetadataType::Array => {
-                let array_type = Self::read_u32(cursor)?;
-                let array_len = Self::read_u64(cursor)?;
-                let mut array = Vec::new();
-                for _ in 0..array_len {
-                    array.push(Self::read_value(cursor, array_type)?);
-                }
-                Ok(GGUFValue::Array(MetadataType::try_from(array_type)?, array))
+    fn next(&mut self) -> Option<Self::Item> {
+        let gguf = &mut *self.gguf;
+        if gguf.failed || gguf.tensors_read >= gguf.header.tensor_count {
+            return None;
+        }
+        let index = gguf.tensors_read;
+        let offset = gguf.reader.position;
+        let tensor = read_tensor_record(&mut gguf.reader).with_context(|| AtOffset {
+            offset: gguf.reader.position,
+            what: format!("GGUF tensor info {index} starting at offset {offset}"),
+        });
+        match &tensor {
+            Ok(tensor) => {
+                log::debug!(
+                    "GGUF tensor[{index}] offset={offset} name={} dims={:?} type={} data_offset={}",
+                    tensor.name,
+                    tensor.dimensions,
+                    tensor.tensor_type,
+                    tensor.offset
+                );
+                gguf.tensors_read += 1;
             }
-            MetadataType::U64 => Ok(GGUFValue::U64(Self::read_u64(cursor)?)),
-            MetadataType::I64 => Ok(GGUFValue::I64(Self::read_i64(cursor)?)),
-            MetadataType::F64 => Ok(GGUFValue::F64(Self::read_f64(cursor)?)),
+            Err(_) => gguf.failed = true,
         }
+        Some(tensor)
     }
 
-    fn read_string(cursor: &mut Cursor<&[u8]>) -> Result<String> {
-        let len = Self::read_u64(cursor)?;
-        let mut bytes = vec![0u8; len as usize];
-        cursor.read_exact(&mut bytes)?;
-        Ok(String::from_utf8(bytes)?)
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = (self.gguf.header.tensor_count - self.gguf.tensors_read) as usize;
+        (0, Some(left))
     }
+}
 
-    fn read_u8(cursor: &mut Cursor<&[u8]>) -> Result<u8> {
-        let mut buf = [0u8; 1];
-        cursor.read_exact(&mut buf)?;
-        Ok(buf[0])
-    }
+/// Reader that counts the bytes taken from it, so errors can name their offset
+struct Counted<R> {
+    inner: R,
+    position: u64,
+}
 
-    fn read_i8(cursor: &mut Cursor<&[u8]>) -> Result<i8> {
-        Ok(Self::read_u8(cursor)? as i8)
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
     }
+}
 
-    fn read_u16(cursor: &mut Cursor<&[u8]>) -> Result<u16> {
-        let mut buf = [0u8; 2];
-        cursor.read_exact(&mut buf)?;
-        Ok(u16::from_le_bytes(buf))
-    }
+fn read_header(reader: &mut impl Read) -> Result<GGUFHeader> {
+    let magic = read_u32(reader)?;
+    let version = read_u32(reader)?;
+    let tensor_count = read_u64(reader)?;
+    let metadata_kv_count = read_u64(reader)?;
+
+    Ok(GGUFHeader {
+        magic,
+        version,
+        tensor_count,
+        metadata_kv_count,
+    })
+}
 
-    fn read_i16(cursor: &mut Cursor<&[u8]>) -> Result<i16> {
-        let mut buf = [0u8; 2];
-        cursor.read_exact(&mut buf)?;
-        Ok(i16::from_le_bytes(buf))
-    }
+fn read_metadata_entry(reader: &mut impl Read) -> Result<(String, GGUFValue)> {
+    let key = read_string(reader)?;
+    let value_type = read_u32(reader)?;
+    let value = read_value(reader, value_type)?;
+    Ok((key, value))
+}
 
-    fn read_u32(cursor: &mut Cursor<&[u8]>) -> Result<u32> {
-        let mut buf = [0u8; 4];
-        cursor.read_exact(&mut buf)?;
-        Ok(u32::from_le_bytes(buf))
-    }
+fn read_tensor_record(reader: &mut impl Read) -> Result<GGUFTensorInfo> {
+    let name = read_string(reader)?;
+    let n_dimensions = read_u32(reader)?;
+    let mut dimensions = Vec::new();
 
-    fn read_i32(cursor: &mut Cursor<&[u8]>) -> Result<i32> {
-        let mut buf = [0u8; 4];
-        cursor.read_exact(&mut buf)?;
-        Ok(i32::from_le_bytes(buf))
+    for _ in 0..n_dimensions {
+        dimensions.push(read_u64(reader)?);
     }
 
-    fn read_f32(cursor: &mut Cursor<&[u8]>) -> Result<f32> {
-        let mut buf = [0u8; 4];
-        cursor.read_exact(&mut buf)?;
-        Ok(f32::from_le_bytes(buf))
-    }
+    let tensor_type_u32 = read_u32(reader)?;
+    let tensor_type = GGMLType::from_u32(tensor_type_u32)
+        .ok_or_else(|| anyhow::anyhow!("Unknown tensor type: {}", tensor_type_u32))?;
 
-    fn read_u64(cursor: &mut Cursor<&[u8]>) -> Result<u64> {
-        let mut buf = [0u8; 8];
-        cursor.read_exact(&mut buf)?;
-        Ok(u64::from_le_bytes(buf))
-    }
+    let offset = read_u64(reader)?;
+
+    let tensor = GGUFTensorInfo {
+        name,
+        dimensions,
+        tensor_type,
+        offset,
+    };
+    tensor_end(&tensor)?;
+    Ok(tensor)
+}
 
-    fn read_i64(cursor: &mut Cursor<&[u8]>) -> Result<i64> {
-        let mut buf = [0u8; 8];
-        cursor.read_exact(&mut buf)?;
-        Ok(i64::from_le_bytes(buf))
+/// Offset relative to the data section where a tensor's data ends. Fails for records whose
+/// element count or end overflows, or lies past the largest offset a file can have, so
+/// that offsets can be added to the data section start and compared as `i64`.
+fn tensor_end(tensor: &GGUFTensorInfo) -> Result<u64> {
+    let Some((size, _)) = tensor.stored_size() else {
+        bail!(
+            "Tensor {} has dimensions {:?}, too large to count its bytes",
+            tensor.name,
+            tensor.dimensions
+        );
+    };
+    tensor
+        .offset
+        .checked_add(size)
+        .filter(|&end| end <= i64::MAX as u64)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Tensor {}'s {size} bytes at data offset {} end past the largest file offset",
+                tensor.name,
+                tensor.offset
+            )
+        })
+}
+
+fn read_value(reader: &mut impl Read, value_type: u32) -> Result<GGUFValue> {
+    match MetadataType::try_from(value_type)? {
+        MetadataType::U8 => Ok(GGUFValue::U8(read_u8(reader)?)),
+        MetadataType::I8 => Ok(GGUFValue::I8(read_i8(reader)?)),
+        MetadataType::U16 => Ok(GGUFValue::U16(read_u16(reader)?)),
+        MetadataType::I16 => Ok(GGUFValue::I16(read_i16(reader)?)),
+        MetadataType::U32 => Ok(GGUFValue::U32(read_u32(reader)?)),
+        MetadataType::I32 => Ok(GGUFValue::I32(read_i32(reader)?)),
+        MetadataType::F32 => Ok(GGUFValue::F32(read_f32(reader)?)),
+        MetadataType::Bool => Ok(GGUFValue::Bool(read_u8(reader)? != 0)),
+        MetadataType::String => Ok(GGUFValue::String(read_string(reader)?)),
+        MetadataType::Array => {
+            let array_type = read_u32(reader)?;
+            let array_len = read_u64(reader)?;
+            let mut array = Vec::new();
+            for _ in 0..array_len {
+                array.push(read_value(reader, array_type)?);
+            }
+            Ok(GGUFValue::Array(MetadataType::try_from(array_type)?, array))
+        }
+        MetadataType::U64 => Ok(GGUFValue::U64(read_u64(reader)?)),
+        MetadataType::I64 => Ok(GGUFValue::I64(read_i64(reader)?)),
+        MetadataType::F64 => Ok(GGUFValue::F64(read_f64(reader)?)),
     }
+}
 
-    fn read_f64(cursor: &mut Cursor<&[u8]>) -> Result<f64> {
-        let mut buf = [0u8; 8];
-        cursor.read_exact(&mut buf)?;
-        Ok(f64::from_le_bytes(buf))
+fn read_string(reader: &mut impl Read) -> Result<String> {
+    let len = read_u64(reader)?;
+    // Read through `take` so a corrupt length fails at end of file instead of allocating it
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        anyhow::bail!("string of {len} bytes runs past the end of the file");
     }
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_i8(reader: &mut impl Read) -> Result<i8> {
+    Ok(read_u8(reader)? as i8)
+}
+
+fn read_u16(reader: &mut impl Read) -> Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_i16(reader: &mut impl Read) -> Result<i16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(i16::from_le_bytes(buf))
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i32(reader: &mut impl Read) -> Result<i32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_f32(reader: &mut impl Read) -> Result<f32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_i64(reader: &mut impl Read) -> Result<i64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(i64::from_le_bytes(buf))
+}
+
+fn read_f64(reader: &mut impl Read) -> Result<f64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
 }
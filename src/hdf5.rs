@@ -0,0 +1,921 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::gguf::{GGUFValue, MetadataType};
+use crate::tree::TensorInfo;
+
+const SIGNATURE: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+const MSG_DATASPACE: u16 = 0x0001;
+const MSG_LINK_INFO: u16 = 0x0002;
+const MSG_DATATYPE: u16 = 0x0003;
+const MSG_LINK: u16 = 0x0006;
+const MSG_LAYOUT: u16 = 0x0008;
+const MSG_FILTER_PIPELINE: u16 = 0x000B;
+const MSG_ATTRIBUTE: u16 = 0x000C;
+const MSG_CONTINUATION: u16 = 0x0010;
+const MSG_SYMBOL_TABLE: u16 = 0x0011;
+
+/// Datasets and attributes found while walking an HDF5 file
+pub struct Hdf5Contents {
+    pub tensors: Vec<TensorInfo>,
+    pub metadata: Vec<(String, GGUFValue)>,
+    /// Groups that could not be listed (e.g. dense link storage)
+    pub warnings: Vec<String>,
+}
+
+/// Bounds-checked little-endian reader over a buffer
+struct Buf<'a> {
+    data: &'a [u8],
+    pos: usize,
+    offset_size: usize,
+    length_size: usize,
+}
+
+impl<'a> Buf<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.pos + len > self.data.len() {
+            bail!("Truncated HDF5 structure");
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, size: usize) -> Result<u64> {
+        let bytes = self.bytes(size)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(self.uint(2)? as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(self.uint(4)? as u32)
+    }
+
+    /// File address; None for the all-ones "undefined address"
+    fn address(&mut self) -> Result<Option<u64>> {
+        let value = self.uint(self.offset_size)?;
+        let undefined = if self.offset_size == 8 {
+            u64::MAX
+        } else {
+            (1u64 << (8 * self.offset_size)) - 1
+        };
+        Ok((value != undefined).then_some(value))
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        self.uint(self.length_size)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Datatype {
+    class: u8,
+    flags: u32,
+    size: usize,
+    /// Base type of enum, variable-length and array types
+    base: Option<Box<Datatype>>,
+}
+
+impl Datatype {
+    fn parse(buf: &mut Buf) -> Result<Self> {
+        let class_and_version = buf.u8()?;
+        let class = class_and_version & 0x0f;
+        let version = class_and_version >> 4;
+        let flags = buf.uint(3)? as u32;
+        let size = buf.u32()? as usize;
+        let mut datatype = Datatype {
+            class,
+            flags,
+            size,
+            base: None,
+        };
+        match class {
+            // Fixed-point and bitfield: bit offset, bit precision
+            0 | 4 => buf.skip(4)?,
+            // Floating point: bit offset, precision, exponent/mantissa layout, bias
+            1 => buf.skip(12)?,
+            // Enumeration: base type, then names and values we don't need
+            8 => datatype.base = Some(Box::new(Datatype::parse(buf)?)),
+            9 => datatype.base = Some(Box::new(Datatype::parse(buf)?)),
+            // Array: dimensions precede the base type
+            10 => {
+                let rank = buf.u8()? as usize;
+                if version < 3 {
+                    buf.skip(3)?;
+                }
+                buf.skip(4 * rank)?;
+                if version < 3 {
+                    buf.skip(4 * rank)?;
+                }
+                datatype.base = Some(Box::new(Datatype::parse(buf)?));
+            }
+            _ => {}
+        }
+        Ok(datatype)
+    }
+
+    fn big_endian(&self) -> bool {
+        matches!(self.class, 0 | 1 | 4) && self.flags & 1 == 1
+    }
+
+    /// Dtype name using the safetensors conventions where one exists
+    fn name(&self) -> String {
+        let name = match (self.class, self.size) {
+            (0, n) if self.flags & 0x8 != 0 => format!("I{}", n * 8),
+            (0, n) => format!("U{}", n * 8),
+            (1, 2) => "F16".to_string(),
+            (1, 4) => "F32".to_string(),
+            (1, 8) => "F64".to_string(),
+            (1, n) => format!("F{}", n * 8),
+            (2, _) => "TIME".to_string(),
+            (3, _) => "STRING".to_string(),
+            (4, n) => format!("BITFIELD{}", n * 8),
+            (5, _) => "OPAQUE".to_string(),
+            (6, _) => "COMPOUND".to_string(),
+            (7, _) => "REFERENCE".to_string(),
+            // h5py stores numpy bools as an int8 enum {FALSE, TRUE}
+            (8, 1) => "BOOL".to_string(),
+            (8, _) => format!(
+                "ENUM({})",
+                self.base.as_ref().map(|b| b.name()).unwrap_or_default()
+            ),
+            (9, _) if self.flags & 0xf == 1 => "VLEN_STRING".to_string(),
+            (9, _) => "VLEN".to_string(),
+            (10, _) => format!(
+                "ARRAY({})",
+                self.base.as_ref().map(|b| b.name()).unwrap_or_default()
+            ),
+            (class, _) => format!("CLASS{class}"),
+        };
+        // Keep big-endian data distinguishable so float scans don't misread it
+        if self.big_endian() && self.size > 1 {
+            format!("{name}_BE")
+        } else {
+            name
+        }
+    }
+}
+
+fn parse_dataspace(buf: &mut Buf) -> Result<Vec<usize>> {
+    let version = buf.u8()?;
+    let rank = buf.u8()? as usize;
+    let _flags = buf.u8()?;
+    if version == 1 {
+        buf.skip(5)?;
+    } else {
+        // Dataspace type: 0 scalar, 1 simple, 2 null
+        let kind = buf.u8()?;
+        if kind == 2 {
+            return Ok(vec![0]);
+        }
+    }
+    (0..rank)
+        .map(|_| buf.length().map(|d| d as usize))
+        .collect()
+}
+
+#[derive(Debug)]
+enum Layout {
+    Compact {
+        size: usize,
+    },
+    Contiguous {
+        address: Option<u64>,
+        size: u64,
+    },
+    /// B-tree v1 chunk index (layout versions 1-3)
+    Chunked {
+        btree: Option<u64>,
+        rank: usize,
+    },
+    /// Newer chunk indexes (layout version 4) are not walked
+    ChunkedIndexed,
+}
+
+fn parse_layout(buf: &mut Buf) -> Result<Layout> {
+    let version = buf.u8()?;
+    if version < 3 {
+        let rank = buf.u8()? as usize;
+        let class = buf.u8()?;
+        buf.skip(5)?;
+        let address = if class != 0 { buf.address()? } else { None };
+        let dims: Vec<u64> = (0..rank)
+            .map(|_| buf.u32().map(|d| d as u64))
+            .collect::<Result<_>>()?;
+        return Ok(match class {
+            0 => Layout::Compact {
+                size: buf.u32()? as usize,
+            },
+            1 => Layout::Contiguous {
+                address,
+                size: dims
+                    .iter()
+                    .try_fold(1u64, |product, &dim| product.checked_mul(dim))
+                    .with_context(|| format!("Layout dimensions {dims:?} overflow"))?,
+            },
+            _ => Layout::Chunked {
+                btree: address,
+                rank,
+            },
+        });
+    }
+    let class = buf.u8()?;
+    Ok(match class {
+        0 => Layout::Compact {
+            size: buf.u16()? as usize,
+        },
+        1 => Layout::Contiguous {
+            address: buf.address()?,
+            size: buf.length()?,
+        },
+        _ if version == 3 => {
+            let rank = buf.u8()? as usize;
+            Layout::Chunked {
+                btree: buf.address()?,
+                rank,
+            }
+        }
+        _ => Layout::ChunkedIndexed,
+    })
+}
+
+/// Filter names from a filter pipeline message
+fn parse_filters(buf: &mut Buf) -> Result<Vec<String>> {
+    let version = buf.u8()?;
+    let count = buf.u8()? as usize;
+    if version == 1 {
+        buf.skip(6)?;
+    }
+    let mut filters = Vec::new();
+    for _ in 0..count {
+        let id = buf.u16()?;
+        let name_len = if version == 1 || id >= 256 {
+            buf.u16()? as usize
+        } else {
+            0
+        };
+        let _flags = buf.u16()?;
+        let values = buf.u16()? as usize;
+        let name_bytes = buf.bytes(if version == 1 {
+            name_len.div_ceil(8) * 8
+        } else {
+            name_len
+        })?;
+        buf.skip(4 * values)?;
+        if version == 1 && values % 2 == 1 {
+            buf.skip(4)?;
+        }
+        let name = match id {
+            1 => "deflate".to_string(),
+            2 => "shuffle".to_string(),
+            3 => "fletcher32".to_string(),
+            4 => "szip".to_string(),
+            5 => "nbit".to_string(),
+            6 => "scaleoffset".to_string(),
+            32000 => "lzf".to_string(),
+            _ => {
+                let name = String::from_utf8_lossy(name_bytes);
+                let name = name.trim_end_matches('\0');
+                if name.is_empty() {
+                    format!("filter {id}")
+                } else {
+                    name.to_string()
+                }
+            }
+        };
+        filters.push(name);
+    }
+    Ok(filters)
+}
+
+struct Message {
+    kind: u16,
+    data: Vec<u8>,
+}
+
+struct Hdf5Reader {
+    file: File,
+    path: PathBuf,
+    base: u64,
+    file_len: u64,
+    offset_size: usize,
+    length_size: usize,
+}
+
+impl Hdf5Reader {
+    /// File offset of `len` bytes at `address`, which must lie within the file
+    fn file_offset(&self, address: u64, len: u64) -> Result<u64> {
+        let offset = self.base.checked_add(address);
+        match offset.zip(offset.and_then(|offset| offset.checked_add(len))) {
+            Some((offset, end)) if end <= self.file_len => Ok(offset),
+            _ => bail!(
+                "Truncated HDF5 file: {}: {len} bytes at address {address} run past its end",
+                self.path.display()
+            ),
+        }
+    }
+
+    fn read_at(&mut self, address: u64, len: usize) -> Result<Vec<u8>> {
+        // Lengths come from the file, so they are checked before allocating
+        let offset = self.file_offset(address, len as u64)?;
+        let mut data = vec![0u8; len];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file
+            .read_exact(&mut data)
+            .with_context(|| format!("Truncated HDF5 file: {}", self.path.display()))?;
+        Ok(data)
+    }
+
+    fn buf<'a>(&self, data: &'a [u8]) -> Buf<'a> {
+        Buf {
+            data,
+            pos: 0,
+            offset_size: self.offset_size,
+            length_size: self.length_size,
+        }
+    }
+
+    /// All header messages of an object, following continuation blocks
+    fn object_header(&mut self, address: u64) -> Result<Vec<Message>> {
+        let prefix = self.read_at(address, 16)?;
+        let mut messages = Vec::new();
+        let mut blocks = Vec::new();
+
+        if &prefix[..4] == b"OHDR" {
+            let flags = prefix[5];
+            let mut pos = 6;
+            if flags & 0x20 != 0 {
+                pos += 16;
+            }
+            if flags & 0x10 != 0 {
+                pos += 4;
+            }
+            let size_len = 1usize << (flags & 3);
+            let header = self.read_at(address, pos + size_len)?;
+            let chunk_size = self.buf(&header[pos..]).uint(size_len)? as usize;
+            let start = address + (pos + size_len) as u64;
+            blocks.push((start, chunk_size, 2, flags));
+            while let Some((block_start, block_len, version, flags)) = blocks.pop() {
+                let data = self.read_at(block_start, block_len)?;
+                let mut buf = self.buf(&data);
+                let header_len = if flags & 0x04 != 0 { 6 } else { 4 };
+                // The checksum occupies the last four bytes of every chunk
+                while buf.pos + header_len <= data.len().saturating_sub(4) {
+                    let kind = buf.u8()? as u16;
+                    let size = buf.u16()? as usize;
+                    let _msg_flags = buf.u8()?;
+                    if flags & 0x04 != 0 {
+                        buf.skip(2)?;
+                    }
+                    let body = buf.bytes(size)?.to_vec();
+                    if kind == MSG_CONTINUATION {
+                        let mut cont = self.buf(&body);
+                        if let Some(offset) = cont.address()? {
+                            let len = cont.length()? as usize;
+                            // Continuation chunks start with an "OCHK" signature
+                            blocks.push((offset + 4, len.saturating_sub(4), version, flags));
+                        }
+                    } else {
+                        messages.push(Message { kind, data: body });
+                    }
+                }
+            }
+        } else {
+            if prefix[0] != 1 {
+                bail!("Unsupported object header version {}", prefix[0]);
+            }
+            let count = u16::from_le_bytes([prefix[2], prefix[3]]) as usize;
+            let size = u32::from_le_bytes(prefix[8..12].try_into()?) as usize;
+            blocks.push((address + 16, size, 1, 0));
+            while let Some((block_start, block_len, _, _)) = blocks.pop() {
+                let data = self.read_at(block_start, block_len)?;
+                let mut buf = self.buf(&data);
+                while buf.pos + 8 <= data.len() && messages.len() < count {
+                    let kind = buf.u16()?;
+                    let size = buf.u16()? as usize;
+                    buf.skip(4)?;
+                    let body = buf.bytes(size)?.to_vec();
+                    if kind == MSG_CONTINUATION {
+                        let mut cont = self.buf(&body);
+                        if let Some(offset) = cont.address()? {
+                            let len = cont.length()? as usize;
+                            blocks.push((offset, len, 1, 0));
+                        }
+                    } else {
+                        messages.push(Message { kind, data: body });
+                    }
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Members of an old-style group: walk the B-tree and read names from the local heap
+    fn symbol_table_members(&mut self, btree: u64, heap: u64) -> Result<Vec<(String, u64)>> {
+        let heap_header = self.read_at(heap, 8 + 2 * self.length_size + self.offset_size)?;
+        let mut buf = self.buf(&heap_header);
+        if buf.bytes(4)? != b"HEAP" {
+            bail!("Bad local heap signature");
+        }
+        buf.skip(4)?;
+        let heap_size = buf.length()? as usize;
+        buf.length()?;
+        let heap_data_address = buf.address()?.context("Local heap has no data segment")?;
+        let heap_data = self.read_at(heap_data_address, heap_size)?;
+
+        let mut members = Vec::new();
+        let mut pending = vec![btree];
+        let mut visited = HashSet::new();
+        while let Some(node) = pending.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            let header = self.read_at(node, 8 + 2 * self.offset_size)?;
+            if &header[..4] == b"SNOD" {
+                let count = u16::from_le_bytes([header[6], header[7]]) as usize;
+                let entry_size = 2 * self.offset_size + 24;
+                let entries = self.read_at(node + 8, count * entry_size)?;
+                let mut buf = self.buf(&entries);
+                for _ in 0..count {
+                    let name_offset = buf.uint(self.offset_size)? as usize;
+                    let object = buf.address()?;
+                    buf.skip(24)?;
+                    let name = heap_data
+                        .get(name_offset..)
+                        .map(|s| {
+                            let end = s.iter().position(|&b| b == 0).unwrap_or(s.len());
+                            String::from_utf8_lossy(&s[..end]).into_owned()
+                        })
+                        .unwrap_or_default();
+                    if let Some(object) = object {
+                        members.push((name, object));
+                    }
+                }
+                continue;
+            }
+            if &header[..4] != b"TREE" {
+                bail!("Bad group B-tree node signature");
+            }
+            let entries = u16::from_le_bytes([header[6], header[7]]) as usize;
+            // Group node keys are heap offsets (length-sized); children alternate with keys
+            let body_len = entries * (self.length_size + self.offset_size) + self.length_size;
+            let body = self.read_at(node + 8 + 2 * self.offset_size as u64, body_len)?;
+            let mut buf = self.buf(&body);
+            for _ in 0..entries {
+                buf.length()?;
+                if let Some(child) = buf.address()? {
+                    pending.push(child);
+                }
+            }
+        }
+        members.sort();
+        Ok(members)
+    }
+
+    /// Total stored bytes of a chunked dataset, from a version 1 chunk B-tree
+    fn chunked_size(&mut self, btree: u64, rank: usize) -> Result<u64> {
+        let key_size = 8 + 8 * rank;
+        let mut total = 0u64;
+        let mut pending = vec![btree];
+        let mut visited = HashSet::new();
+        while let Some(node) = pending.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            let header = self.read_at(node, 8 + 2 * self.offset_size)?;
+            if &header[..4] != b"TREE" {
+                bail!("Bad chunk B-tree node signature");
+            }
+            let level = header[5];
+            let entries = u16::from_le_bytes([header[6], header[7]]) as usize;
+            let body_len = entries * (key_size + self.offset_size) + key_size;
+            let body = self.read_at(node + 8 + 2 * self.offset_size as u64, body_len)?;
+            let mut buf = self.buf(&body);
+            for _ in 0..entries {
+                let chunk_size = buf.u32()? as u64;
+                buf.skip(key_size - 4)?;
+                let child = buf.address()?;
+                if level == 0 {
+                    total += chunk_size;
+                } else if let Some(child) = child {
+                    pending.push(child);
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// Bytes of a variable-length sequence stored in a global heap collection
+    fn global_heap_object(&mut self, collection: u64, index: u32) -> Result<Vec<u8>> {
+        let header = self.read_at(collection, 8 + self.length_size)?;
+        if &header[..4] != b"GCOL" {
+            bail!("Bad global heap signature");
+        }
+        let size = self.buf(&header[8..]).length()? as usize;
+        let data = self.read_at(collection, size)?;
+        let mut buf = self.buf(&data);
+        buf.pos = 8 + self.length_size;
+        while buf.pos + 8 + self.length_size <= data.len() {
+            let object_index = buf.u16()? as u32;
+            buf.skip(6)?;
+            let object_size = buf.length()? as usize;
+            if object_index == 0 {
+                break;
+            }
+            let start = buf.pos;
+            let object = buf.bytes(object_size)?;
+            if object_index == index {
+                return Ok(object.to_vec());
+            }
+            buf.pos = start;
+            buf.skip(object_size.div_ceil(8) * 8)?;
+        }
+        bail!("Global heap object {index} not found")
+    }
+
+    /// Decode an attribute value; unsupported types are described instead of failing
+    fn attribute_value(&mut self, datatype: &Datatype, dims: &[usize], data: &[u8]) -> GGUFValue {
+        let Some(count) = dims
+            .iter()
+            .try_fold(1usize, |product, &dim| product.checked_mul(dim))
+        else {
+            return GGUFValue::String(format!("<attribute of shape {dims:?}>"));
+        };
+        let mut values = Vec::with_capacity(count.min(4096));
+        for i in 0..count {
+            let Some(element) = data.get(i * datatype.size..(i + 1) * datatype.size) else {
+                break;
+            };
+            match self.element_value(datatype, element) {
+                Some(value) => values.push(value),
+                None => {
+                    return GGUFValue::String(format!(
+                        "<{} attribute, {} elements>",
+                        datatype.name(),
+                        count
+                    ));
+                }
+            }
+        }
+        if dims.is_empty() && values.len() == 1 {
+            return values.pop().unwrap();
+        }
+        let item_type = match values.first() {
+            Some(GGUFValue::I64(_)) => MetadataType::I64,
+            Some(GGUFValue::U64(_)) => MetadataType::U64,
+            Some(GGUFValue::F64(_)) => MetadataType::F64,
+            _ => MetadataType::String,
+        };
+        GGUFValue::Array(item_type, values)
+    }
+
+    fn element_value(&mut self, datatype: &Datatype, bytes: &[u8]) -> Option<GGUFValue> {
+        let ordered = |bytes: &[u8]| -> Vec<u8> {
+            if datatype.big_endian() {
+                bytes.iter().rev().copied().collect()
+            } else {
+                bytes.to_vec()
+            }
+        };
+        match datatype.class {
+            0 | 8 if bytes.len() <= 8 => {
+                let mut raw = [0u8; 8];
+                raw[..bytes.len()].copy_from_slice(&ordered(bytes));
+                let unsigned = u64::from_le_bytes(raw);
+                let signed = datatype.class == 0 && datatype.flags & 0x8 != 0;
+                if signed {
+                    let shift = 64 - 8 * bytes.len() as u32;
+                    Some(GGUFValue::I64(((unsigned << shift) as i64) >> shift))
+                } else {
+                    Some(GGUFValue::U64(unsigned))
+                }
+            }
+            1 => {
+                let bytes = ordered(bytes);
+                let value = match bytes.len() {
+                    4 => f32::from_le_bytes(bytes.try_into().ok()?) as f64,
+                    8 => f64::from_le_bytes(bytes.try_into().ok()?),
+                    _ => return None,
+                };
+                Some(GGUFValue::F64(value))
+            }
+            3 => {
+                let text = String::from_utf8_lossy(bytes);
+                Some(GGUFValue::String(
+                    text.trim_end_matches(['\0', ' ']).to_string(),
+                ))
+            }
+            // Variable-length strings: length, global heap collection address, object index
+            9 if datatype.flags & 0xf == 1 => {
+                let mut buf = self.buf(bytes);
+                buf.u32().ok()?;
+                let collection = buf.address().ok()??;
+                let index = buf.u32().ok()?;
+                let object = self.global_heap_object(collection, index).ok()?;
+                Some(GGUFValue::String(
+                    String::from_utf8_lossy(&object).into_owned(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_attribute(&mut self, data: &[u8]) -> Result<(String, GGUFValue)> {
+        let mut buf = self.buf(data);
+        let version = buf.u8()?;
+        buf.skip(1)?;
+        let name_size = buf.u16()? as usize;
+        let datatype_size = buf.u16()? as usize;
+        let dataspace_size = buf.u16()? as usize;
+        if version >= 3 {
+            buf.skip(1)?;
+        }
+        let padded = |len: usize| {
+            if version == 1 {
+                len.div_ceil(8) * 8
+            } else {
+                len
+            }
+        };
+        let name_bytes = buf.bytes(padded(name_size))?;
+        let name = String::from_utf8_lossy(&name_bytes[..name_size.saturating_sub(1)])
+            .trim_end_matches('\0')
+            .to_string();
+        let datatype_bytes = buf.bytes(padded(datatype_size))?;
+        let dataspace_bytes = buf.bytes(padded(dataspace_size))?;
+        let datatype = Datatype::parse(&mut self.buf(datatype_bytes))?;
+        let dims = parse_dataspace(&mut self.buf(dataspace_bytes))?;
+        let value = self.attribute_value(&datatype, &dims, &data[buf.pos..]);
+        Ok((name, value))
+    }
+}
+
+struct Walker {
+    reader: Hdf5Reader,
+    contents: Hdf5Contents,
+    visited: HashSet<u64>,
+}
+
+impl Walker {
+    fn visit(&mut self, address: u64, path: &str) -> Result<()> {
+        if !self.visited.insert(address) {
+            return Ok(());
+        }
+        let messages = self.reader.object_header(address)?;
+        let child_path = |name: &str| {
+            if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{path}.{name}")
+            }
+        };
+
+        for message in messages.iter().filter(|m| m.kind == MSG_ATTRIBUTE) {
+            match self.reader.parse_attribute(&message.data) {
+                Ok((name, value)) => self.contents.metadata.push((child_path(&name), value)),
+                Err(e) => self
+                    .contents
+                    .warnings
+                    .push(format!("Unreadable attribute on '{path}': {e}")),
+            }
+        }
+
+        let find = |kind: u16| messages.iter().find(|m| m.kind == kind);
+        if let Some(symbol_table) = find(MSG_SYMBOL_TABLE) {
+            let mut buf = self.reader.buf(&symbol_table.data);
+            let btree = buf.address()?.context("Group has no B-tree")?;
+            let heap = buf.address()?.context("Group has no local heap")?;
+            for (name, object) in self.reader.symbol_table_members(btree, heap)? {
+                self.visit(object, &child_path(&name))?;
+            }
+            return Ok(());
+        }
+
+        let links: Vec<(String, u64)> = messages
+            .iter()
+            .filter(|m| m.kind == MSG_LINK)
+            .filter_map(|m| self.parse_link(&m.data).ok().flatten())
+            .collect();
+        if let Some(info) = find(MSG_LINK_INFO) {
+            let mut buf = self.reader.buf(&info.data);
+            buf.skip(1)?;
+            let flags = buf.u8()?;
+            if flags & 1 != 0 {
+                buf.skip(8)?;
+            }
+            if buf.address()?.is_some() {
+                self.contents.warnings.push(format!(
+                    "Group '{path}' uses dense link storage, which is not supported; its members are not listed"
+                ));
+            }
+        }
+        if !links.is_empty() || find(MSG_LINK_INFO).is_some() {
+            for (name, object) in links {
+                self.visit(object, &child_path(&name))?;
+            }
+            return Ok(());
+        }
+
+        if let (Some(datatype), Some(dataspace), Some(layout)) =
+            (find(MSG_DATATYPE), find(MSG_DATASPACE), find(MSG_LAYOUT))
+        {
+            let datatype = Datatype::parse(&mut self.reader.buf(&datatype.data))?;
+            let shape = parse_dataspace(&mut self.reader.buf(&dataspace.data))?;
+            let layout = parse_layout(&mut self.reader.buf(&layout.data))?;
+            let filters = match find(MSG_FILTER_PIPELINE) {
+                Some(message) => parse_filters(&mut self.reader.buf(&message.data))?,
+                None => Vec::new(),
+            };
+            let tensor = self.dataset_tensor(path, datatype, shape, layout, filters)?;
+            self.contents.tensors.push(tensor);
+        }
+        Ok(())
+    }
+
+    /// Hard link target of a link message; soft and external links are skipped
+    fn parse_link(&self, data: &[u8]) -> Result<Option<(String, u64)>> {
+        let mut buf = self.reader.buf(data);
+        buf.skip(1)?;
+        let flags = buf.u8()?;
+        let link_type = if flags & 0x8 != 0 { buf.u8()? } else { 0 };
+        if flags & 0x4 != 0 {
+            buf.skip(8)?;
+        }
+        if flags & 0x10 != 0 {
+            buf.skip(1)?;
+        }
+        let name_len = buf.uint(1 << (flags & 3))? as usize;
+        let name = String::from_utf8_lossy(buf.bytes(name_len)?).into_owned();
+        if link_type != 0 {
+            return Ok(None);
+        }
+        Ok(buf.address()?.map(|address| (name, address)))
+    }
+
+    fn dataset_tensor(
+        &mut self,
+        path: &str,
+        datatype: Datatype,
+        shape: Vec<usize>,
+        layout: Layout,
+        filters: Vec<String>,
+    ) -> Result<TensorInfo> {
+        let counts = shape
+            .iter()
+            .try_fold(1usize, |product, &dim| product.checked_mul(dim))
+            .and_then(|num_elements| {
+                Some((num_elements, num_elements.checked_mul(datatype.size)?))
+            });
+        let Some((num_elements, logical_size)) = counts else {
+            bail!("Dataset {path} has shape {shape:?}, too large to count its bytes");
+        };
+        let mut notes = Vec::new();
+        let mut data_offset = None;
+        let size_bytes = match layout {
+            Layout::Compact { size } => {
+                notes.push("Compact storage (data kept in the object header)".to_string());
+                size
+            }
+            Layout::Contiguous { address, size } => {
+                let offset = address
+                    .map(|address| self.reader.file_offset(address, size))
+                    .transpose()
+                    .with_context(|| format!("Dataset {path}'s data"))?;
+                data_offset = offset.filter(|_| filters.is_empty());
+                if address.is_none() {
+                    notes.push("Storage not allocated".to_string());
+                }
+                size as usize
+            }
+            Layout::Chunked { btree, rank } => {
+                notes.push("Chunked storage".to_string());
+                match btree.map(|b| self.reader.chunked_size(b, rank)) {
+                    Some(Ok(size)) => size as usize,
+                    _ => logical_size,
+                }
+            }
+            Layout::ChunkedIndexed => {
+                notes.push("Chunked storage (size shown uncompressed)".to_string());
+                logical_size
+            }
+        };
+        if !filters.is_empty() {
+            notes.push(format!(
+                "Filters: {} (values can't be read in place)",
+                filters.join(", ")
+            ));
+        }
+        Ok(TensorInfo {
+            name: path.to_string(),
+            dtype: datatype.name(),
+            shape,
+            size_bytes,
+            num_elements,
+            source_file: self.reader.path.clone(),
+            data_offset,
+            quant: None,
+            notes,
+            storage_shape: None,
+        })
+    }
+}
+
+/// Walk every group of an HDF5 file, listing datasets as tensors named by their dotted path
+pub fn read_hdf5(path: &Path) -> Result<Hdf5Contents> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let file_len = file.metadata()?.len();
+
+    // The superblock may sit at 0, 512, 1024, 2048, ... to leave room for a user block
+    let mut superblock_at = None;
+    let mut candidate = 0u64;
+    while candidate + 8 <= file_len {
+        let mut signature = [0u8; 8];
+        file.seek(SeekFrom::Start(candidate))?;
+        file.read_exact(&mut signature)?;
+        if signature == SIGNATURE {
+            superblock_at = Some(candidate);
+            break;
+        }
+        candidate = if candidate == 0 { 512 } else { candidate * 2 };
+    }
+    let superblock_at =
+        superblock_at.with_context(|| format!("Not an HDF5 file: {}", path.display()))?;
+
+    let mut header = [0u8; 128];
+    file.seek(SeekFrom::Start(superblock_at))?;
+    let read = file.read(&mut header)?;
+    let header = &header[..read];
+    let version = *header.get(8).context("Truncated HDF5 superblock")?;
+    let (offset_size, length_size, fields_at) = match version {
+        0 | 1 => (
+            header[13] as usize,
+            header[14] as usize,
+            if version == 0 { 24 } else { 28 },
+        ),
+        2 | 3 => (header[9] as usize, header[10] as usize, 12),
+        other => bail!("Unsupported HDF5 superblock version {other}"),
+    };
+    if !(1..=8).contains(&offset_size) || !(1..=8).contains(&length_size) {
+        bail!("Corrupt HDF5 superblock in {}", path.display());
+    }
+    let mut buf = Buf {
+        data: header,
+        pos: fields_at,
+        offset_size,
+        length_size,
+    };
+    let base = buf.address()?.unwrap_or(0);
+    let root = if version < 2 {
+        // Free-space, end-of-file and driver addresses, then the root symbol table entry
+        buf.skip(3 * offset_size)?;
+        buf.skip(offset_size)?;
+        buf.address()?
+    } else {
+        // Superblock extension and end-of-file addresses precede the root object header
+        buf.skip(2 * offset_size)?;
+        buf.address()?
+    }
+    .context("HDF5 file has no root group")?;
+
+    let mut walker = Walker {
+        reader: Hdf5Reader {
+            file,
+            path: path.to_path_buf(),
+            base,
+            file_len,
+            offset_size,
+            length_size,
+        },
+        contents: Hdf5Contents {
+            tensors: Vec::new(),
+            metadata: Vec::new(),
+            warnings: Vec::new(),
+        },
+        visited: HashSet::new(),
+    };
+    walker
+        .visit(root, "")
+        .with_context(|| format!("Failed to read HDF5 file: {}", path.display()))?;
+    Ok(walker.contents)
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde::de::{self, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Deserializes a JSON map or array into just its element count,
+/// so huge vocab and merges sections are never materialized
+#[derive(Debug, Default, Clone, Copy)]
+struct Count(usize);
+
+impl<'de> Deserialize<'de> for Count {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CountVisitor;
+
+        impl<'de> Visitor<'de> for CountVisitor {
+            type Value = Count;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a map or sequence")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Count, A::Error> {
+                let mut count = 0;
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {
+                    count += 1;
+                }
+                Ok(Count(count))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Count, A::Error> {
+                let mut count = 0;
+                while seq.next_element::<IgnoredAny>()?.is_some() {
+                    count += 1;
+                }
+                Ok(Count(count))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Count, E> {
+                Ok(Count(0))
+            }
+        }
+
+        deserializer.deserialize_any(CountVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenizerFile {
+    model: Option<TokenizerModel>,
+    #[serde(default)]
+    added_tokens: Vec<AddedToken>,
+}
+
+#[derive(Deserialize)]
+struct TokenizerModel {
+    #[serde(rename = "type")]
+    model_type: Option<String>,
+    #[serde(default)]
+    vocab: Count,
+    #[serde(default)]
+    merges: Count,
+}
+
+#[derive(Deserialize)]
+struct AddedToken {
+    id: usize,
+    content: String,
+    #[serde(default)]
+    special: bool,
+}
+
+/// Vocabulary facts from an HF `tokenizer.json` and `tokenizer_config.json`
+#[derive(Debug, Clone, Default)]
+pub struct TokenizerStats {
+    pub model_type: Option<String>,
+    /// Entries in `model.vocab`
+    pub base_vocab: usize,
+    /// Highest token id plus one, counting added tokens
+    pub vocab_size: usize,
+    pub added_tokens: usize,
+    pub merges: usize,
+    pub special_tokens: Vec<String>,
+    /// Named special tokens from tokenizer_config.json, e.g. ("bos_token", "<s>")
+    pub named_tokens: Vec<(String, String)>,
+    pub has_chat_template: bool,
+}
+
+/// Read tokenizer stats from `dir`, returning None when no tokenizer.json exists
+pub fn read_tokenizer_stats(dir: &Path) -> Result<Option<TokenizerStats>> {
+    let path = dir.join("tokenizer.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let tokenizer: TokenizerFile = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let mut stats = TokenizerStats::default();
+    if let Some(model) = &tokenizer.model {
+        stats.model_type = model.model_type.clone();
+        stats.base_vocab = model.vocab.0;
+        stats.merges = model.merges.0;
+    }
+    stats.added_tokens = tokenizer.added_tokens.len();
+    stats.special_tokens = tokenizer
+        .added_tokens
+        .iter()
+        .filter(|t| t.special)
+        .map(|t| t.content.clone())
+        .collect();
+    let max_added_id = tokenizer.added_tokens.iter().map(|t| t.id + 1).max();
+    stats.vocab_size = stats.base_vocab.max(max_added_id.unwrap_or(0));
+
+    let config_path = dir.join("tokenizer_config.json");
+    if let Ok(content) = std::fs::read_to_string(&config_path) {
+        let config: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+        for key in ["bos_token", "eos_token", "pad_token", "unk_token"] {
+            // Tokens are either plain strings or AddedToken objects with a `content` field
+            let token = match config.get(key) {
+                Some(serde_json::Value::String(s)) => Some(s.clone()),
+                Some(obj) => obj
+                    .get("content")
+                    .and_then(|c| c.as_str())
+                    .map(str::to_string),
+                None => None,
+            };
+            if let Some(token) = token {
+                stats.named_tokens.push((key.to_string(), token));
+            }
+        }
+        stats.has_chat_template = config.get("chat_template").is_some();
+    }
+
+    Ok(Some(stats))
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use crate::model::read_safetensors_header;
+
+pub const INDEX_FILE_NAME: &str = "model.safetensors.index.json";
+
+/// What was written by `write_index`
+pub struct IndexSummary {
+    pub path: PathBuf,
+    pub shards: usize,
+    pub tensors: usize,
+    pub total_size: u64,
+}
+
+/// The shard index of `dir`: `model.safetensors.index.json`, or else the first other
+/// `*.safetensors.index.json` by name (`consolidated.safetensors.index.json`,
+/// `diffusion_pytorch_model.safetensors.index.json`)
+pub fn find_index(dir: &Path) -> Option<PathBuf> {
+    let standard = dir.join(INDEX_FILE_NAME);
+    if standard.is_file() {
+        return Some(standard);
+    }
+    let mut indexes: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.ends_with(".safetensors.index.json"))
+        })
+        .collect();
+    indexes.sort();
+    indexes.into_iter().next()
+}
+
+/// Safetensors files directly inside `dir`, sorted by name
+pub fn shard_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut shards: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("safetensors")
+        })
+        .collect();
+    shards.sort();
+    Ok(shards)
+}
+
+/// Whether `files` are several shards from one directory that has no index file
+pub fn missing_index_dir(files: &[PathBuf]) -> Option<PathBuf> {
+    let dir = files.first()?.parent()?;
+    let all_shards = files.iter().all(|f| {
+        f.parent() == Some(dir) && f.extension().and_then(|s| s.to_str()) == Some("safetensors")
+    });
+    (files.len() > 1 && all_shards && find_index(dir).is_none()).then(|| dir.to_path_buf())
+}
+
+/// Build `model.safetensors.index.json` for the shards in `dir`, as written by `transformers`
+pub fn build_index(dir: &Path) -> Result<(serde_json::Value, IndexSummary)> {
+    let shards = shard_files(dir)?;
+    if shards.is_empty() {
+        bail!("No .safetensors files found in {}", dir.display());
+    }
+
+    let mut weight_map: BTreeMap<String, String> = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    let mut total_size = 0u64;
+    for shard in &shards {
+        let file_name = shard
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut file = File::open(shard)
+            .with_context(|| format!("Failed to open file: {}", shard.display()))?;
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        let (metadata, _) = read_safetensors_header(&mut file, shard, size)?;
+        for (name, tensor) in metadata.tensors() {
+            let (start, end) = tensor.data_offsets;
+            total_size += (end - start) as u64;
+            if let Some(existing) = weight_map.get(&name) {
+                conflicts.push(format!("  {name}: {existing} and {file_name}"));
+            } else {
+                weight_map.insert(name, file_name.clone());
+            }
+        }
+    }
+    if !conflicts.is_empty() {
+        bail!(
+            "Duplicate tensor names across shards ({}):\n{}",
+            conflicts.len(),
+            conflicts.join("\n")
+        );
+    }
+
+    let summary = IndexSummary {
+        path: dir.join(INDEX_FILE_NAME),
+        shards: shards.len(),
+        tensors: weight_map.len(),
+        total_size,
+    };
+    let index = serde_json::json!({
+        "metadata": { "total_size": total_size },
+        "weight_map": weight_map,
+    });
+    Ok((index, summary))
+}
+
+/// Write the index next to the shards, refusing to replace an existing one unless `force`
+pub fn write_index(dir: &Path, force: bool) -> Result<IndexSummary> {
+    let path = dir.join(INDEX_FILE_NAME);
+    if path.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+    let (index, summary) = build_index(dir)?;
+    let content = serde_json::to_string_pretty(&index)? + "\n";
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(summary)
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+/// Remappable explorer actions: config name, default key and help text
+pub const ACTIONS: &[(&str, char, &str)] = &[
+    ("search", '/', "Search tensors and metadata"),
+    ("help", '?', "Show this help"),
+    ("quit", 'q', "Quit (or leave search mode)"),
+    ("summary", 'M', "Model summary"),
+    (
+        "group_info",
+        'i',
+        "Totals and dtype breakdown of the selected group",
+    ),
+    ("top_tensors", 'T', "Largest tensors"),
+    ("layer_chart", 'B', "Per-layer size chart"),
+    (
+        "treemap",
+        'V',
+        "Treemap of the model's bytes, one level at a time",
+    ),
+    (
+        "layer_audit",
+        'A',
+        "Compare shapes and dtypes across layers",
+    ),
+    (
+        "cost_estimate",
+        'C',
+        "Estimated FLOPs and bytes read per token, per layer",
+    ),
+    (
+        "context_usage",
+        'U',
+        "Count a prompt's tokens against the context length",
+    ),
+    ("nan_scan", 'N', "Scan for NaN/Inf values"),
+    ("degenerate_scan", 'Z', "Find all-zero and constant tensors"),
+    ("sparsity", 'L', "Toggle the sparsity and L2-norm column"),
+    ("embeddings", 'E', "Toggle non-embedding parameter totals"),
+    ("duplicates", 'D', "Find duplicate tensors"),
+    ("pin", 'p', "Pin or unpin the selected tensor"),
+    ("compare", 'P', "Compare the pinned tensors"),
+    (
+        "extract",
+        'X',
+        "Write the selected tensor's raw bytes to disk",
+    ),
+    ("group_by", 'G', "Cycle the grouping"),
+    ("min_size", 'm', "Cycle the size floor"),
+    ("exact", 'u', "Toggle exact sizes and counts"),
+    (
+        "storage_order",
+        'S',
+        "Toggle GGUF shapes between logical and storage order",
+    ),
+    (
+        "group_sort",
+        's',
+        "Sort the selected group's children by name, size or params",
+    ),
+    (
+        "clear_group_sorts",
+        '=',
+        "Clear every group's own sort order",
+    ),
+    ("write_index", 'I', "Write a missing shard index"),
+    (
+        "copy_names",
+        'y',
+        "Copy the selected name, or all tensor names under a group",
+    ),
+    (
+        "copy_names_json",
+        'Y',
+        "Copy the tensor names under a group as a JSON array",
+    ),
+    ("export", 'e', "Export the rows shown"),
+    ("files", 'F', "Loaded files: sizes, dates, parse times"),
+    (
+        "offsets",
+        'O',
+        "Byte offset map: which tensor holds an offset",
+    ),
+    ("reload", 'r', "Reload files from disk"),
+    (
+        "changes_only",
+        'H',
+        "Hide what the last reload didn't change",
+    ),
+    (
+        "open",
+        'o',
+        "Open or add a file or directory from a file browser",
+    ),
+    ("recent", 'R', "Switch to a recently opened model"),
+    ("drop_file", 'd', "Drop the selected or failed file"),
+];
+
+/// Check one `[keys]` entry, returning the key it binds
+pub fn parse_key(action: &str, key: &str) -> Result<char, String> {
+    if !ACTIONS.iter().any(|(name, _, _)| *name == action) {
+        return Err(format!("unknown action '{action}' in [keys]"));
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_whitespace() => Ok(c),
+        _ => Err(format!(
+            "key for '{action}' must be a single character, got \"{key}\""
+        )),
+    }
+}
+
+/// Key bindings after applying the `[keys]` overrides
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    /// Pressed key to the default key of the action it triggers; absent keys keep their meaning
+    remapped: HashMap<char, Option<char>>,
+}
+
+impl Keymap {
+    /// Invalid entries are skipped; `config::load` has already warned about them
+    pub fn new<'a>(overrides: impl IntoIterator<Item = (&'a String, &'a String)>) -> Self {
+        let mut remapped = HashMap::new();
+        for (action, key) in overrides {
+            let (Ok(key), Some((_, default, _))) = (
+                parse_key(action, key),
+                ACTIONS.iter().find(|(name, _, _)| name == action),
+            ) else {
+                continue;
+            };
+            if key == *default {
+                continue;
+            }
+            // The default key stops triggering the action unless something else claims it
+            remapped.entry(*default).or_insert(None);
+            remapped.insert(key, Some(*default));
+        }
+        Self { remapped }
+    }
+
+    /// The key the event loop should act on for a pressed key; None when it is unbound
+    pub fn translate(&self, pressed: char) -> Option<char> {
+        match self.remapped.get(&pressed) {
+            Some(target) => *target,
+            None => Some(pressed),
+        }
+    }
+
+    /// Key currently bound to an action; None when its default key was given to another one
+    pub fn key_for(&self, action: &str) -> Option<char> {
+        let (_, default, _) = ACTIONS.iter().find(|(name, _, _)| *name == action)?;
+        match self
+            .remapped
+            .iter()
+            .find(|(_, target)| **target == Some(*default))
+        {
+            Some((key, _)) => Some(*key),
+            None if self.remapped.contains_key(default) => None,
+            None => Some(*default),
+        }
+    }
+
+    /// Help overlay lines listing every action with its current key and its `[keys]` name
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            "  ↑/↓         Move the selection".to_string(),
+            "  Enter/Space Expand/collapse a group, open tensor details".to_string(),
+            "  Space       On a file group (per-file view): leave it out of the totals".to_string(),
+            "  1-4         Expand groups to that many levels, 0 collapses all".to_string(),
+            "  Ctrl+E      Export (also while searching)".to_string(),
+            "  Ctrl+F      Filter pane: Space toggles, c clears all, Tab switches focus"
+                .to_string(),
+            "  n/N         Next/previous match after a jump search (Ctrl+F while searching)"
+                .to_string(),
+            "  Ctrl+C      Quit immediately".to_string(),
+            String::new(),
+        ];
+        for (name, _, description) in ACTIONS {
+            let key = self.key_for(name).map_or("-".to_string(), String::from);
+            lines.push(format!("  {key:<11} {description:<40} [{name}]"));
+        }
+        lines
+    }
+}
@@ -0,0 +1,82 @@
+//! Header-only readers for safetensors, GGUF, DDUF, ONNX, HDF5, NumPy and PyTorch
+//! checkpoints, as used by the `safetensors_explorer` TUI.
+//!
+//! The stable surface is [`model`] (with [`model::load_model`]), [`tree`] and [`gguf`];
+//! it follows semver from 0.3. Its structs and enums are `#[non_exhaustive]` so that fields
+//! and variants can be added in minor releases; [`tree::TensorInfo::new`] and
+//! [`tree::TreeLimits::new`] build the ones passed in. The other public modules exist for
+//! the bundled binary and may change in any release.
+//!
+//! ```no_run
+//! let model = safetensors_explorer::model::load_model(&["model.gguf"])?;
+//! println!("{} tensors, {} bytes", model.tensors.len(), model.total_bytes);
+//! for warning in &model.warnings {
+//!     eprintln!("warning: {warning}");
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod gguf;
+pub mod model;
+pub mod tree;
+
+#[doc(hidden)]
+pub mod batch;
+#[doc(hidden)]
+pub mod bench;
+#[doc(hidden)]
+pub mod check;
+#[doc(hidden)]
+pub mod companion;
+#[doc(hidden)]
+pub mod copies;
+#[doc(hidden)]
+pub mod cost;
+#[doc(hidden)]
+pub mod diff;
+#[doc(hidden)]
+pub mod dtype;
+#[doc(hidden)]
+pub mod expected;
+#[doc(hidden)]
+pub mod extract;
+#[doc(hidden)]
+pub mod index;
+#[doc(hidden)]
+pub mod name_map;
+#[doc(hidden)]
+pub mod offsets;
+#[doc(hidden)]
+pub mod peek;
+#[doc(hidden)]
+pub mod prompt;
+#[doc(hidden)]
+pub mod raw_header;
+#[doc(hidden)]
+pub mod remote;
+#[doc(hidden)]
+pub mod report;
+#[doc(hidden)]
+pub mod role;
+#[doc(hidden)]
+pub mod scan;
+#[doc(hidden)]
+pub mod sd_metadata;
+#[doc(hidden)]
+pub mod size_label;
+#[doc(hidden)]
+pub mod summary;
+#[doc(hidden)]
+pub mod utils;
+
+mod hdf5;
+mod hf;
+mod npy;
+mod onnx;
+mod pickle;
+mod pytorch;
+mod quant;
+mod template;
+mod tokenizer;
+mod work;
+mod zip;
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    fs::File,
+    io::{LineWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::Instant,
+};
+
+/// Verbosity of `--log-file`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Writes one line per record to a file, never to the terminal, so it is safe while the
+/// TUI owns the screen
+struct FileLogger {
+    out: Mutex<LineWriter<File>>,
+    start: Instant,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        if let Ok(mut out) = self.out.lock() {
+            // Logging must never take the explorer down, so write errors are dropped
+            let _ = writeln!(
+                out,
+                "{:>10.4}s {:<5} {}: {}",
+                self.start.elapsed().as_secs_f64(),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut out) = self.out.lock() {
+            let _ = out.flush();
+        }
+    }
+}
+
+/// Send `log` records at `level` and above to `path`, truncating it
+pub fn init(path: &Path, level: LogLevel) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create log file {}", path.display()))?;
+    let logger = FileLogger {
+        out: Mutex::new(LineWriter::new(file)),
+        start: Instant::now(),
+    };
+    log::set_boxed_logger(Box::new(logger)).context("Logger already initialized")?;
+    log::set_max_level(level.filter());
+    log::info!(
+        "safetensors_explorer {} logging at {level:?}",
+        env!("CARGO_PKG_VERSION")
+    );
+    Ok(())
+}
@@ -1,127 +1,1364 @@
+mod browser;
+mod capabilities;
+mod clipboard;
+mod config;
+mod events;
 mod explorer;
-mod gguf;
-mod tree;
+mod export;
+mod filter;
+mod keymap;
+mod logging;
+mod preset;
+mod recent;
+mod session;
+mod treemap;
 mod ui;
-mod utils;
+mod walk;
+mod watch;
 
 use anyhow::{Context, Result};
 use clap::Parser;
+use safetensors_explorer::{
+    batch, bench, check, companion, copies, cost, diff, dtype, extract, gguf, index, model,
+    name_map, offsets, peek, prompt, raw_header, remote, report, role, scan, sd_metadata,
+    size_label, summary, tree, utils,
+};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use crate::explorer::Explorer;
+use crate::capabilities::Capabilities;
+use crate::copies::{Copies, Prefer};
+use crate::explorer::{Explorer, ViewOptions};
+use crate::export::Listing;
+use crate::keymap::Keymap;
+use crate::logging::LogLevel;
+use crate::model::ModelDescription;
+use crate::report::{Format, Report};
+use crate::tree::{GroupBy, SortOrder, TreeLimits};
+use crate::ui::Theme;
+use crate::walk::WalkOptions;
+
+/// File extensions accepted on the command line and picked up when scanning directories
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "safetensors",
+    "gguf",
+    "dduf",
+    "zip",
+    "bin",
+    "pt",
+    "pth",
+    "npy",
+    "npz",
+    "onnx",
+    "h5",
+    "hdf5",
+];
+const PYTORCH_EXTENSIONS: &[&str] = &["bin", "pt", "pth"];
+
+/// Charts printed by `--chart`
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Chart {
+    /// One bar per transformer layer, scaled to the largest layer
+    Layers,
+}
 
 #[derive(Parser)]
 #[command(name = "safetensors-explorer")]
-#[command(about = "Interactive explorer for SafeTensors and GGUF files")]
+#[command(about = "Interactive explorer for SafeTensors, GGUF and PyTorch checkpoint files")]
+#[command(group(clap::ArgGroup::new("extracting").multiple(true).args(["extract_raw", "remote_extract"])))]
 struct Args {
     #[arg(
-        help = "SafeTensors and GGUF files, directories, or glob patterns to explore (e.g., *.safetensors, model-*.gguf)"
+        help = "SafeTensors, GGUF or PyTorch (.bin/.pt/.pth) files, directories, or glob patterns to explore (e.g., *.safetensors, model-*.gguf)"
     )]
     paths: Vec<PathBuf>,
 
     #[arg(
         short,
         long,
-        help = "Recursively search directories for SafeTensors and GGUF files"
+        help = "Recursively search directories for SafeTensors, GGUF and PyTorch files"
     )]
     recursive: bool,
+
+    #[arg(
+        long,
+        help = "With -r, also descend into hidden directories such as .git or .cache"
+    )]
+    hidden: bool,
+
+    #[arg(
+        long,
+        help = "With -r, descend into symlinked directories (cycles are detected)"
+    )]
+    follow_symlinks: bool,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = parse_glob_pattern,
+        help = "Skip files and directories whose name or relative path matches PATTERN when scanning directories (repeatable)"
+    )]
+    ignore: Vec<glob::Pattern>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "When a directory holds several copies of one checkpoint (e.g. consolidated.safetensors and HF shards), load this one without asking"
+    )]
+    prefer: Option<Prefer>,
+
+    #[arg(
+        long,
+        help = "Scan float tensors for NaN/Inf values, print a report, and exit non-zero if any are found"
+    )]
+    scan_nan: bool,
+
+    #[arg(
+        long,
+        help = "Report all-zero and constant tensors and exit non-zero if any are found"
+    )]
+    scan_degenerate: bool,
+
+    #[arg(
+        long,
+        help = "Print the exact-zero fraction and L2 norm of every float tensor, most sparse first"
+    )]
+    sparsity_report: bool,
+
+    #[arg(
+        long,
+        help = "Find tensors (1 MiB and larger) whose data is an identical copy of another tensor"
+    )]
+    find_duplicates: bool,
+
+    #[arg(
+        long,
+        help = "Print the model summary (overview, architecture, dtype mix per top-level group) and exit"
+    )]
+    summary: bool,
+
+    #[arg(
+        long,
+        help = "Compare the models among the files: print one row per model (file size, parameters, bits per weight, dominant type, context length, architecture), smallest first, and exit. Split and indexed checkpoints count as one model, and each model is loaded on its own; exits with status 1 if a model can't be read"
+    )]
+    batch_summary: bool,
+
+    #[arg(
+        long,
+        help = "Inventory a collection of models (with --recursive, every subdirectory) and exit: one row per model (split and indexed checkpoints count as one), then grand totals of disk usage by format and by tensor type, models sharing an architecture and parameter count, and the models that could not be read. Models are loaded one at a time; exits with status 1 if a model can't be read"
+    )]
+    aggregate: bool,
+
+    #[arg(
+        long,
+        help = "Cross-check facts recorded in several places (tokenizer vocab vs embedding rows, tensor shapes and dtypes across layers, dtype mix across shards, split GGUF parts, parameter count vs size label) and exit; exits with status 1 if a check fails"
+    )]
+    check: bool,
+
+    #[arg(
+        long,
+        help = "Time loading each file (header, tensor info, parse, tree build) and print the timings and throughput instead of opening the TUI"
+    )]
+    bench: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        requires = "bench",
+        help = "Load the files N times for --bench and report the fastest and median run"
+    )]
+    bench_iterations: usize,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KIND",
+        help = "Print an ASCII bar chart (layers: size of each transformer layer) and exit"
+    )]
+    chart: Option<Chart>,
+
+    #[arg(
+        long,
+        help = "Print an estimate of the FLOPs and bytes read per token, per layer and split between attention and MLP, at --ctx and --batch, and exit"
+    )]
+    cost_estimate: bool,
+
+    #[arg(
+        long,
+        value_name = "TOKENS",
+        default_value_t = cost::DEFAULT_CONTEXT,
+        help = "Context length of the cost estimate (--cost-estimate, and C in the explorer)"
+    )]
+    ctx: usize,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        help = "Batch size of the cost estimate (--cost-estimate, and C in the explorer)"
+    )]
+    batch: usize,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Apply the GGUF's chat template to the user message in FILE (- for stdin), count its tokens with the embedded BPE tokenizer, print them against the context length and exit; exits with status 1 if the prompt doesn't fit"
+    )]
+    count_tokens: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "count_tokens",
+        help = "With --count-tokens, the system message"
+    )]
+    system: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Print the N largest tensors with their cumulative share of the model and exit"
+    )]
+    top: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Print the alignment padding overhead of each GGUF file with the N most padded tensors and exit"
+    )]
+    padding_report: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "BYTES",
+        value_parser = |text: &str| utils::parse_offset(text).map(|_| text.to_string()),
+        help = "Print which tensor (or header or padding region) holds byte offset BYTES of each file, how much of it comes before the offset and how many tensors start after it, and exit. Accepts 1234567, 1,234,567, 0x12d687 or 4GiB"
+    )]
+    // Kept as typed: sizes such as 4GB are read in the units chosen with --si
+    offset_of: Option<String>,
+
+    #[arg(
+        long,
+        help = "Print the total parameter count as one line, e.g. \"7615616512 (7.6B)\", and exit"
+    )]
+    params: bool,
+
+    #[arg(
+        long,
+        requires = "params",
+        help = "With --params, print one line per dtype instead"
+    )]
+    by_dtype: bool,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = parse_glob_pattern,
+        help = "Only count or list tensors whose name matches the glob PATTERN (--params, --top and the --format listing)"
+    )]
+    filter: Option<glob::Pattern>,
+
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Print the raw value of metadata KEY and exit (repeatable); exits with status 3 if a key is missing"
+    )]
+    get: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Print every metadata key matching REGEX as key<TAB>value and exit"
+    )]
+    get_regex: Option<regex::Regex>,
+
+    #[arg(
+        long,
+        value_name = "TENSOR[INDEX]",
+        help = "Print one element of a tensor, e.g. 'model.embed_tokens.weight[151645,0]' or a flat index 'name[42]', reading only its bytes (or its quantization block, dequantized) from disk, and exit (repeatable); exits with status 3 if a tensor is missing"
+    )]
+    peek: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_parser = Format::from_str,
+        help = "Output format of the tensor listing, --summary, --check, --diff and the scans: table (default), json, yaml, csv or markdown. Without another mode, prints the tensor listing instead of opening the explorer"
+    )]
+    format: Option<Format>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the --format output to PATH instead of stdout"
+    )]
+    output: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Write newline-delimited JSON events (file_parsed, progress, finding, warning, done) to stdout and everything else to stderr, for programs wrapping the command line. Without another mode, loads the files and reports their totals instead of opening the explorer"
+    )]
+    json_events: bool,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = parse_glob_pattern,
+        help = "Write the raw bytes of each tensor whose name matches the glob NAME to <name>.bin, with a <name>.json sidecar (dtype, shape, source offsets), and exit (repeatable)"
+    )]
+    extract_raw: Vec<glob::Pattern>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = parse_glob_pattern,
+        conflicts_with = "extract_raw",
+        help = "Like --extract-raw for a safetensors file given as an http(s) or Hugging Face Hub URL: only the header and the matching tensors' bytes are downloaded, with Range requests through curl (repeatable; HF_TOKEN is sent for gated repos)"
+    )]
+    remote_extract: Vec<glob::Pattern>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "extracting",
+        help = "Directory --extract-raw and --remote-extract write to [default: current directory]"
+    )]
+    out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Write model.safetensors.index.json for the safetensors shards in DIR and exit"
+    )]
+    write_index: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Print the JSON header of a safetensors FILE as written, key order kept, with its length, padding and data offset on stderr, and exit"
+    )]
+    dump_header: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "write_index",
+        help = "Overwrite an existing index file with --write-index"
+    )]
+    force: bool,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["A", "B"],
+        help = "Compare two checkpoints (files or directories) tensor by tensor and exit. A GGUF side and a non-GGUF side are compared as a conversion, mapping llama.cpp tensor names. Exit status: 0 identical, 1 only metadata differs, 2 tensors differ"
+    )]
+    diff: Option<Vec<PathBuf>>,
+
+    #[arg(
+        long,
+        requires = "diff",
+        help = "With --diff, leave metadata out of the comparison"
+    )]
+    ignore_metadata: bool,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = parse_glob_pattern,
+        requires = "diff",
+        help = "With --diff, leave out tensors whose name matches the glob PATTERN, e.g. 'lm_head.*' (repeatable)"
+    )]
+    ignore_tensor: Vec<glob::Pattern>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Translate tensor names with the regex pattern/replacement rules of a JSON FILE, ahead of the built-in llama.cpp/HF tables: --diff pairs and compares the translated names, and the explorer's search also matches them. Rules that never match are reported at the end"
+    )]
+    name_map: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "How the tree groups tensors (switch with G in the explorer) [default: prefix]"
+    )]
+    group_by: Option<GroupBy>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Order of the entries within each group [default: name]"
+    )]
+    sort: Option<SortOrder>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = |text: &str| utils::parse_size(text).map(|_| text.to_string()),
+        help = "Hide tensors smaller than SIZE (e.g. 1MiB) in the explorer; cycle presets with m"
+    )]
+    // Kept as typed: sizes such as 1MB are read in the units chosen with --si
+    min_size: Option<String>,
+
+    #[arg(
+        long,
+        help = "Use plain ASCII markers instead of emoji icons (the default on TERM=dumb and the Linux console)"
+    )]
+    ascii: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Color theme [default: dark, or mono when NO_COLOR is set or TERM=dumb]"
+    )]
+    theme: Option<Theme>,
+
+    #[arg(
+        long,
+        help = "Open the explorer even when the terminal looks unable to run it (TERM=dumb, no raw mode, output not a terminal) instead of printing the tensor listing"
+    )]
+    force_tui: bool,
+
+    #[arg(
+        long,
+        help = "Show sizes in decimal units (kB, MB, GB) instead of KiB, MiB, GiB, and read sizes such as --min-size 1GB in them too"
+    )]
+    si: bool,
+
+    #[arg(
+        long,
+        help = "Neither restore nor save the expanded groups, selection and view for these files"
+    )]
+    no_session: bool,
+
+    #[arg(
+        long,
+        help = "Reload the files whenever they change on disk, marking what changed in the tree (H shows only the changes)"
+    )]
+    watch: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Expand exactly the groups listed in a preset FILE (saved with w in the explorer) instead of the session's; groups this model lacks are skipped"
+    )]
+    preset: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Config file to use instead of ~/.config/safetensors-explorer/config.toml"
+    )]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write a log of file discovery and header parsing to PATH"
+    )]
+    log_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LogLevel::Info,
+        requires = "log_file",
+        help = "Detail of --log-file; debug lists every metadata key and tensor record"
+    )]
+    log_level: LogLevel,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    events::set_enabled(args.json_events);
+    let result = run(args);
+    match &result {
+        Ok(()) => events::finish(0),
+        Err(e) => {
+            events::error(e);
+            events::finish(1);
+        }
+    }
+    result
+}
 
-    if args.paths.is_empty() {
-        eprintln!(
-            "Error: Please specify one or more SafeTensors or GGUF files or directories to explore."
-        );
-        eprintln!(
-            "Usage: safetensors-explorer <file1.safetensors> [file2.gguf] [directory] [*.safetensors] ..."
+fn run(mut args: Args) -> Result<()> {
+    if let Some(path) = &args.log_file {
+        logging::init(path, args.log_level)?;
+    }
+
+    if let Some(dir) = &args.write_index {
+        let summary = index::write_index(dir, args.force)?;
+        events::line(format!(
+            "Wrote {} ({} tensors from {} shards, total_size {})",
+            summary.path.display(),
+            summary.tensors,
+            summary.shards,
+            summary.total_size
+        ));
+        return Ok(());
+    }
+
+    if let Some(path) = &args.dump_header {
+        let header = raw_header::RawHeader::read(path)?;
+        for line in header.summary() {
+            eprintln!("{line}");
+        }
+        for line in raw_header::pretty_print(&header.json) {
+            events::line(raw_header::line_text(&line));
+        }
+        return Ok(());
+    }
+
+    if !args.remote_extract.is_empty() {
+        let [url] = args.paths.as_slice() else {
+            anyhow::bail!("--remote-extract takes exactly one URL");
+        };
+        let dir = args.out.clone().unwrap_or_else(|| PathBuf::from("."));
+        let extracted = remote::extract(
+            &url.to_string_lossy(),
+            &args.remote_extract,
+            &dir,
+            events::progress().as_mut(),
+        )?;
+        if extracted.is_empty() {
+            eprintln!("No tensor matches the --remote-extract patterns");
+            events::exit(3);
+        }
+        for extracted in extracted {
+            events::line(format!(
+                "{} ({} bytes) and {}",
+                extracted.data.display(),
+                extracted.bytes,
+                extracted.sidecar.display()
+            ));
+        }
+        return Ok(());
+    }
+    if let Some(url) = args
+        .paths
+        .iter()
+        .find(|path| remote::is_url(&path.to_string_lossy()))
+    {
+        anyhow::bail!(
+            "Remote models can't be explored; use --remote-extract NAME to download single tensors of {}",
+            url.display()
         );
-        std::process::exit(1);
     }
 
-    let files = collect_safetensors_files(&args.paths, args.recursive)?;
+    // Command-line flags win over the config file
+    let config = config::load(args.config.as_deref());
+
+    if args.paths.is_empty() && args.diff.is_none() {
+        // Started bare in a terminal: offer the recently opened models, or a file browser
+        let capabilities = Capabilities::detect();
+        let interactive = !args.json_events && capabilities.not_interactive.is_none();
+        let recent_shown = config.recent.limit().is_some() && !recent::load().is_empty();
+        let theme = args
+            .theme
+            .or(config.view.theme)
+            .unwrap_or_else(|| capabilities.default_theme());
+        if !interactive {
+            eprintln!(
+                "Error: Please specify one or more SafeTensors or GGUF files or directories to explore."
+            );
+            eprintln!(
+                "Usage: safetensors-explorer <file1.safetensors> [file2.gguf] [directory] [*.safetensors] ..."
+            );
+            events::exit(1);
+        }
+        match explorer::choose_at_startup(theme, recent_shown)? {
+            Some(paths) => args.paths = paths,
+            None => return Ok(()),
+        }
+    }
+    utils::set_si_units(args.si || config.cli.si_units.unwrap_or(false));
+    let walk = WalkOptions {
+        recursive: args.recursive || config.cli.recursive.unwrap_or(false),
+        hidden: args.hidden,
+        follow_symlinks: args.follow_symlinks,
+        ignore: args.ignore.clone(),
+        prefer: args.prefer,
+        // Every model of the folder is a row of its own
+        one_copy: !args.batch_summary && !args.aggregate,
+    };
+    let min_size = args
+        .min_size
+        .as_deref()
+        .and_then(|size| utils::parse_size(size).ok())
+        .or_else(|| {
+            config
+                .view
+                .min_size
+                .as_deref()
+                .and_then(|size| utils::parse_size(size).ok())
+        });
+
+    // A broken name map is reported before anything is loaded
+    let names = match &args.name_map {
+        Some(path) => Some(name_map::NameMap::load(path)?),
+        None => None,
+    };
+
+    if let Some(sides) = &args.diff {
+        let status = run_diff(&sides[0], &sides[1], &walk, &args, names.as_ref())?;
+        for warning in names.iter().flat_map(|n| n.unused_warnings()) {
+            events::warn(&warning);
+        }
+        if status != diff::EXIT_IDENTICAL {
+            events::exit(status);
+        }
+        return Ok(());
+    }
+
+    let files = collect_safetensors_files(&args.paths, &walk)?;
 
     if files.is_empty() {
         eprintln!("Error: No SafeTensors or GGUF files found in the specified paths.");
-        std::process::exit(1);
+        events::exit(1);
     }
 
-    let mut explorer = Explorer::new(files);
-    explorer.run()
+    if args.params {
+        // Warnings stay quiet so the output is exactly the answer
+        let mut model = load_files(&files)?;
+        apply_filter(&mut model, &args);
+        if args.by_dtype {
+            for (dtype, params) in model.parameters_by_dtype() {
+                events::line(format!(
+                    "{dtype} {params} ({})",
+                    utils::format_parameters(params)
+                ));
+            }
+        } else {
+            events::line(format!(
+                "{} ({})",
+                model.total_parameters,
+                utils::format_parameters(model.total_parameters)
+            ));
+        }
+        return Ok(());
+    }
+
+    if !args.get.is_empty() || args.get_regex.is_some() {
+        let model = load_for_report(&files)?;
+        // Embedded metadata comes before sidecar entries, so it wins on duplicate keys
+        let mut missing = false;
+        for key in &args.get {
+            match summary::metadata_entry(&model.metadata, key) {
+                Some(entry) => events::line(summary::metadata_raw_text(entry)),
+                None => {
+                    eprintln!("Key not found: {key}");
+                    missing = true;
+                }
+            }
+        }
+        if let Some(pattern) = &args.get_regex {
+            let matches: Vec<_> = model
+                .metadata
+                .iter()
+                .filter(|m| pattern.is_match(&m.name))
+                .collect();
+            for entry in &matches {
+                let value = summary::metadata_raw_text(entry)
+                    .replace('\\', "\\\\")
+                    .replace('\n', "\\n")
+                    .replace('\t', "\\t");
+                events::line(format!("{}\t{value}", entry.name));
+            }
+            if matches.is_empty() {
+                eprintln!("No metadata key matches {pattern}");
+                missing = true;
+            }
+        }
+        if missing {
+            events::exit(3);
+        }
+        return Ok(());
+    }
+
+    if !args.peek.is_empty() {
+        let model = load_for_report(&files)?;
+        let mut missing = false;
+        for spec in &args.peek {
+            let (name, index) = peek::parse_spec(spec)?;
+            let Some(tensor) = model.tensors.iter().find(|t| t.name == name) else {
+                eprintln!("Tensor not found: {name}");
+                missing = true;
+                continue;
+            };
+            let element = peek::peek(tensor, &index).with_context(|| format!("--peek {spec}"))?;
+            events::line(format!("{}\t{}", element.label(tensor), element.value));
+        }
+        if missing {
+            events::exit(3);
+        }
+        return Ok(());
+    }
+
+    if !args.extract_raw.is_empty() {
+        let model = load_for_report(&files)?;
+        let dir = args.out.clone().unwrap_or_else(|| PathBuf::from("."));
+        let selected: Vec<_> = model
+            .tensors
+            .iter()
+            .filter(|t| args.extract_raw.iter().any(|p| p.matches(&t.name)))
+            .collect();
+        if selected.is_empty() {
+            eprintln!("No tensor matches the --extract-raw patterns");
+            events::exit(3);
+        }
+        for tensor in selected {
+            let extracted = extract::extract_raw(tensor, &dir)?;
+            events::line(format!(
+                "{} ({} bytes) and {}",
+                extracted.data.display(),
+                extracted.bytes,
+                extracted.sidecar.display()
+            ));
+        }
+        return Ok(());
+    }
+
+    if args.scan_nan {
+        let model = load_for_report(&files)?;
+        let result = scan::scan_nan(&model.tensors, events::progress().as_mut())?;
+        emit(&result, &args)?;
+        if !result.findings.is_empty() {
+            events::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.scan_degenerate {
+        let model = load_for_report(&files)?;
+        let result = scan::scan_degenerate(&model.tensors, events::progress().as_mut())?;
+        emit(&result, &args)?;
+        if !result.findings.is_empty() {
+            events::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.sparsity_report {
+        let model = load_for_report(&files)?;
+        let result = scan::scan_sparsity(&model.tensors, events::progress().as_mut())?;
+        emit(&result.report(&model.tensors), &args)?;
+        return Ok(());
+    }
+
+    if args.find_duplicates {
+        let model = load_for_report(&files)?;
+        let result = scan::find_duplicates(
+            &model.tensors,
+            scan::DUPLICATE_MIN_BYTES,
+            events::progress().as_mut(),
+        )?;
+        emit(&result, &args)?;
+        return Ok(());
+    }
+
+    if args.summary {
+        let model = load_for_report(&files)?;
+        emit(summary::build_summary(&model, &files).as_slice(), &args)?;
+        return Ok(());
+    }
+
+    if args.batch_summary {
+        let mut report = batch::BatchReport::default();
+        for group in batch::group_models(&files) {
+            // One model at a time, dropped before the next is loaded
+            match load_files(&group.files) {
+                Ok(model) => {
+                    for warning in &model.warnings {
+                        events::warn(warning);
+                    }
+                    report.push(batch::batch_row(&group.name, &model));
+                }
+                Err(e) => report.failed.push(batch::BatchFailure {
+                    name: group.name,
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+        emit(&report, &args)?;
+        if !report.failed.is_empty() {
+            events::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.aggregate {
+        let mut report = batch::InventoryReport::default();
+        for group in batch::group_models(&files) {
+            // Only the rows outlive each model; a model that fails to load is counted
+            match load_files(&group.files) {
+                Ok(model) => {
+                    for warning in &model.warnings {
+                        events::warn(warning);
+                    }
+                    report.add(&group.name, &model);
+                }
+                Err(e) => report.fail(&group.name, group.files.len(), format!("{e:#}")),
+            }
+        }
+        report.finish();
+        emit(&report, &args)?;
+        if !report.errors.is_empty() {
+            events::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.bench {
+        let report = bench::run_bench(&files, args.bench_iterations)?;
+        emit(&report, &args)?;
+        return Ok(());
+    }
+
+    if args.check {
+        // Not load_for_report: the split check's problems are findings of the report
+        let model = load_files(&files)?;
+        for warning in &model.warnings {
+            events::warn(warning);
+        }
+        let report = check::run_checks(&model);
+        emit(&report, &args)?;
+        if report.worst() == Some(check::Severity::Error) {
+            events::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(Chart::Layers) = args.chart {
+        let model = load_for_report(&files)?;
+        let layers = summary::layer_sizes(&model.tensors);
+        if layers.is_empty() {
+            eprintln!("No numbered layers found");
+            events::exit(1);
+        }
+        let width = crossterm::terminal::size().map_or(80, |(width, _)| width as usize);
+        for line in summary::layer_chart_lines(&layers, width.saturating_sub(1)) {
+            events::line(line);
+        }
+        return Ok(());
+    }
+
+    if args.cost_estimate {
+        let model = load_for_report(&files)?;
+        emit(&cost::estimate_model(&model, args.batch, args.ctx), &args)?;
+        return Ok(());
+    }
+
+    if let Some(user_path) = &args.count_tokens {
+        let model = load_for_report(&files)?;
+        let counter = prompt::PromptCounter::from_metadata(&model.metadata)?;
+        let system = match &args.system {
+            Some(path) => read_message(path)?,
+            None => String::new(),
+        };
+        let usage = counter.count(&system, &read_message(user_path)?);
+        emit(&usage, &args)?;
+        if !usage.fits() {
+            events::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(n) = args.top {
+        let mut model = load_for_report(&files)?;
+        // Shares stay relative to the whole model when --filter narrows the list
+        let model_bytes = model.tensors.iter().map(|t| t.size_bytes).sum();
+        apply_filter(&mut model, &args);
+        let min_size = min_size.unwrap_or(0);
+        let shown: Vec<_> = model
+            .tensors
+            .into_iter()
+            .filter(|t| t.size_bytes >= min_size)
+            .collect();
+        let (header, rows) =
+            summary::top_tensor_table(&summary::largest_tensors(&shown, n), model_bytes);
+        events::line(header);
+        for row in rows {
+            events::line(row);
+        }
+        return Ok(());
+    }
+
+    if let Some(offset) = &args.offset_of {
+        let offset = utils::parse_offset(offset).map_err(anyhow::Error::msg)?;
+        let model = load_for_report(&files)?;
+        let maps = offsets::offset_maps(&model);
+        emit(&offsets::offset_report(&maps, offset), &args)?;
+        return Ok(());
+    }
+
+    if let Some(n) = args.padding_report {
+        let model = load_for_report(&files)?;
+        if model.gguf_layouts.is_empty() {
+            anyhow::bail!("--padding-report needs at least one GGUF file");
+        }
+        for (i, (path, layout)) in model.gguf_layouts.iter().enumerate() {
+            if i > 0 {
+                events::line("");
+            }
+            for line in summary::padding_report_lines(path, layout, n) {
+                events::line(line);
+            }
+        }
+        return Ok(());
+    }
+
+    if args.format.is_some() || args.output.is_some() {
+        let mut model = load_for_report(&files)?;
+        apply_filter(&mut model, &args);
+        emit(&Listing(export::rows_from_tensors(&model.tensors)), &args)?;
+        return Ok(());
+    }
+
+    if args.json_events {
+        let model = load_for_report(&files)?;
+        events::loaded(&model);
+        return Ok(());
+    }
+
+    let capabilities = Capabilities::detect();
+    if let Some(reason) = &capabilities.not_interactive
+        && !args.force_tui
+    {
+        eprintln!(
+            "Not opening the explorer: {reason}. Printing the tensor listing instead (--force-tui opens it anyway)."
+        );
+        let mut model = load_for_report(&files)?;
+        apply_filter(&mut model, &args);
+        emit(&Listing(export::rows_from_tensors(&model.tensors)), &args)?;
+        return Ok(());
+    }
+
+    let mut options = ViewOptions {
+        group_by: args.group_by.or(config.view.group_by).unwrap_or_default(),
+        sort: args.sort.or(config.view.sort).unwrap_or_default(),
+        min_size: min_size.unwrap_or(0),
+        exact: false,
+        storage_order: config.view.gguf_storage_order.unwrap_or(false),
+        sample_threshold: config
+            .view
+            .stats_sample_threshold
+            .as_deref()
+            .and_then(|size| utils::parse_size(size).ok())
+            .unwrap_or(scan::DEFAULT_SAMPLE_THRESHOLD),
+        ascii: args.ascii || config.view.ascii_icons.unwrap_or(!capabilities.emoji),
+        theme: args
+            .theme
+            .or(config.view.theme)
+            .unwrap_or_else(|| capabilities.default_theme()),
+        cost_batch: args.batch.max(1),
+        cost_context: args.ctx,
+        limits: TreeLimits::new(
+            config
+                .view
+                .max_tree_depth
+                .unwrap_or(TreeLimits::default().max_depth),
+            config
+                .view
+                .max_top_level_groups
+                .unwrap_or(TreeLimits::default().max_top_level),
+        ),
+    };
+    let keymap = Keymap::new(
+        config
+            .keys
+            .iter()
+            .map(|(action, key)| (action, key.get_ref())),
+    );
+    // A saved session beats the config file but not flags given on this command line
+    let session_path = if args.no_session {
+        None
+    } else {
+        session::session_path(&files)
+    };
+    let saved = session_path.as_deref().and_then(session::load);
+    if let Some(saved) = &saved {
+        if args.group_by.is_none() {
+            options.group_by = saved.group_by;
+        }
+        if args.sort.is_none() {
+            options.sort = saved.sort;
+        }
+        if args.min_size.is_none() {
+            options.min_size = saved.min_size;
+        }
+        options.exact = saved.exact;
+        options.storage_order = saved.storage_order;
+    }
+
+    // A broken preset is reported before the terminal is taken over
+    let preset = args.preset.as_deref().map(preset::load).transpose()?;
+    let mut explorer = Explorer::new(files, options, keymap);
+    if let Some(paths) = preset {
+        explorer.set_preset(paths);
+    }
+    if let Some(path) = session_path {
+        explorer.set_session(path, saved);
+    }
+    explorer.set_recent(args.paths.clone(), config.recent.limit());
+    if let Some(names) = names {
+        explorer.set_name_map(names);
+    }
+    if args.watch {
+        explorer.set_watch();
+    }
+    let result = explorer.run();
+    for warning in explorer.name_map().iter().flat_map(|n| n.unused_warnings()) {
+        events::warn(&warning);
+    }
+    result
+}
+
+/// Drop the tensors that `--filter` excludes
+fn apply_filter(model: &mut ModelDescription, args: &Args) {
+    if let Some(pattern) = &args.filter {
+        model.retain_tensors(|t| pattern.matches(&t.name));
+    }
+}
+
+/// Load for the one-shot report modes, which stop at the first unreadable file
+fn load_for_report(files: &[PathBuf]) -> Result<ModelDescription> {
+    let model = load_files(files)?;
+    for warning in &model.warnings {
+        events::warn(warning);
+    }
+    // A split GGUF with parts missing or repeated would give misleading totals
+    for finding in check::split_check(&model) {
+        if finding.severity > check::Severity::Info {
+            events::warn(&finding.message);
+        }
+    }
+    Ok(model)
+}
+
+/// Load the files without printing their warnings; under `--json-events` each file is
+/// reported as it is read
+fn load_files(files: &[PathBuf]) -> Result<ModelDescription> {
+    if events::enabled() {
+        ModelDescription::load_observed(files, &mut events::file_observer(files.len()))
+    } else {
+        model::load_model(files)
+    }
+}
+
+/// A message for `--count-tokens`, from a file or `-` for stdin
+fn read_message(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut message = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut message)
+            .context("Failed to read the message from stdin")?;
+        return Ok(message);
+    }
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Load both sides of `--diff`, print the comparison and return the exit status.
+/// A GGUF side facing a non-GGUF side is compared as a conversion. With `--name-map`, other
+/// pairs of sides are compared by their translated names.
+fn run_diff(
+    a: &Path,
+    b: &Path,
+    walk: &WalkOptions,
+    args: &Args,
+    names: Option<&name_map::NameMap>,
+) -> Result<i32> {
+    let load = |path: &Path| -> Result<(bool, ModelDescription)> {
+        let files = collect_safetensors_files(&[path.to_path_buf()], walk)?;
+        if files.is_empty() {
+            anyhow::bail!("No model files found in {}", path.display());
+        }
+        let is_gguf = files
+            .iter()
+            .all(|f| f.extension().and_then(|s| s.to_str()) == Some("gguf"));
+        let mut model = load_for_report(&files)?;
+        model.retain_tensors(|t| !args.ignore_tensor.iter().any(|p| p.matches(&t.name)));
+        Ok((is_gguf, model))
+    };
+    match (load(a)?, load(b)?) {
+        ((true, gguf), (false, hf)) | ((false, hf), (true, gguf)) => {
+            let architecture = summary::metadata_string(&gguf.metadata, "general.architecture")
+                .unwrap_or_else(|| "unknown".to_string());
+            let builtin;
+            let names = match names {
+                Some(names) => names,
+                None => {
+                    builtin = name_map::NameMap::builtin();
+                    &builtin
+                }
+            };
+            let report = diff::diff_checkpoints(names, &architecture, &gguf.tensors, &hf.tensors);
+            if !report.known_architecture {
+                events::warn(&format!(
+                    "No tensor name table for architecture '{architecture}'; falling back to fuzzy name matching"
+                ));
+            }
+            emit(&report, args)?;
+            Ok(report.exit_code())
+        }
+        ((_, mut a), (_, mut b)) => {
+            if let Some(names) = names {
+                names.rename_tensors(&mut a);
+                names.rename_tensors(&mut b);
+            }
+            let metadata =
+                (!args.ignore_metadata).then_some((a.metadata.as_slice(), b.metadata.as_slice()));
+            let report = diff::diff_models(&a.tensors, &b.tensors, metadata);
+            emit(&report, args)?;
+            Ok(report.exit_code())
+        }
+    }
 }
 
-fn collect_safetensors_files(paths: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>> {
+fn collect_safetensors_files(paths: &[PathBuf], walk: &WalkOptions) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     for path in paths {
-        // Try to expand as glob pattern
-        let expanded_paths: Vec<PathBuf> = match glob::glob(&path.to_string_lossy()) {
-            Ok(paths) => paths.filter_map(Result::ok).collect(),
-            Err(_) => vec![path.clone()], // Not a valid glob, treat as literal path
-        };
+        let expanded_paths = expand_path_argument(path);
+
+        log::debug!(
+            "Argument {} expands to {} path(s)",
+            path.display(),
+            expanded_paths.len()
+        );
 
         // Process each expanded path
         for expanded_path in expanded_paths {
             if !expanded_path.exists() {
-                eprintln!("Warning: Path does not exist: {}", expanded_path.display());
+                log::warn!("Path does not exist: {}", expanded_path.display());
+                events::warn(&format!("Path does not exist: {}", expanded_path.display()));
                 continue;
             }
 
             if expanded_path.is_file() {
-                let ext = expanded_path.extension().and_then(|s| s.to_str());
-                if ext == Some("safetensors") || ext == Some("gguf") {
+                let ext = utils::model_extension(&expanded_path);
+                if ext.is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext)) {
+                    log::info!("Found file {}", expanded_path.display());
                     files.push(expanded_path.clone());
                 } else {
-                    eprintln!(
-                        "Warning: Skipping unsupported file: {}",
+                    log::warn!("Skipping unsupported file: {}", expanded_path.display());
+                    events::warn(&format!(
+                        "Skipping unsupported file: {}",
                         expanded_path.display()
-                    );
+                    ));
                 }
             } else if expanded_path.is_dir() {
                 // Check for SafeTensors index file first
-                let index_path = expanded_path.join("model.safetensors.index.json");
-                if index_path.exists() {
+                if let Some(index_path) = index::find_index(&expanded_path) {
+                    log::info!("Reading shard index {}", index_path.display());
                     let index_files = parse_safetensors_index(&index_path)?;
                     for file in index_files {
                         let full_path = expanded_path.join(file);
                         if full_path.exists() {
+                            log::info!("Found shard {}", full_path.display());
                             files.push(full_path);
+                        } else {
+                            log::warn!("Indexed shard is missing: {}", full_path.display());
                         }
                     }
                 } else {
                     // Fallback to directory scanning
-                    let patterns = if recursive {
-                        vec![
-                            format!("{}/**/*.safetensors", expanded_path.display()),
-                            format!("{}/**/*.gguf", expanded_path.display()),
-                        ]
-                    } else {
-                        vec![
-                            format!("{}/*.safetensors", expanded_path.display()),
-                            format!("{}/*.gguf", expanded_path.display()),
-                        ]
-                    };
-
-                    for pattern in patterns {
-                        for entry in glob::glob(&pattern).context("Failed to read glob pattern")? {
-                            match entry {
-                                Ok(file_path) => files.push(file_path),
-                                Err(e) => eprintln!("Warning: Error reading file: {e}"),
+                    let mut found = walk::find_files(&expanded_path, SUPPORTED_EXTENSIONS, walk);
+
+                    // HF repos often ship both formats; prefer safetensors over pickles
+                    let has_safetensors = found
+                        .iter()
+                        .any(|f| utils::model_extension(f) == Some("safetensors"));
+                    if has_safetensors {
+                        found.retain(|f| {
+                            let keep = !PYTORCH_EXTENSIONS
+                                .contains(&utils::model_extension(f).unwrap_or(""));
+                            if !keep {
+                                log::info!("Skipping {} in favor of safetensors", f.display());
                             }
-                        }
+                            keep
+                        });
                     }
+                    if walk.one_copy {
+                        found = keep_one_copy(found, walk.prefer)?;
+                    }
+                    for file in &found {
+                        log::info!("Found file {}", file.display());
+                    }
+                    files.extend(found);
                 }
             }
         }
     }
 
-    // Sort files for consistent ordering
+    // Sort files for consistent ordering; overlapping patterns may name a file twice
     files.sort();
+    files.dedup();
     Ok(files)
 }
 
+/// Keep one copy of each checkpoint among the files scanned from a directory, per
+/// subdirectory: the one `--prefer` names, the one picked at a prompt, or else the sharded
+/// one. The copies left out are reported with the reason they were taken for copies.
+fn keep_one_copy(files: Vec<PathBuf>, prefer: Option<Prefer>) -> Result<Vec<PathBuf>> {
+    let mut by_dir: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for file in &files {
+        if utils::model_extension(file) == Some("safetensors") {
+            let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+            by_dir.entry(dir).or_default().push(file.clone());
+        }
+    }
+    let interactive =
+        !events::enabled() && std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    let mut ignored = HashSet::new();
+    for (dir, dir_files) in by_dir {
+        let Some(copies) = copies::find_copies(&dir, &dir_files) else {
+            continue;
+        };
+        let (chosen, hint) = match prefer {
+            Some(prefer) => (copies.choose(prefer), ""),
+            None if interactive => (ask_copy(&copies)?, ""),
+            None => (
+                copies.choose(Prefer::Sharded),
+                " (--prefer consolidated|sharded, or name the files to load)",
+            ),
+        };
+        let others: Vec<String> = copies
+            .sets
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != chosen)
+            .map(|(_, set)| {
+                ignored.extend(set.files.iter().cloned());
+                set.label()
+            })
+            .collect();
+        events::warn(&format!(
+            "{} holds {} copies of the checkpoint ({}): loading {}, ignoring {}{hint}",
+            dir.display(),
+            copies.sets.len(),
+            copies.reason,
+            copies.sets[chosen].label(),
+            others.join(", ")
+        ));
+    }
+    Ok(files.into_iter().filter(|f| !ignored.contains(f)).collect())
+}
+
+/// Ask on the terminal which copy to load; Enter takes the sharded one
+fn ask_copy(copies: &Copies) -> Result<usize> {
+    let default = copies.choose(Prefer::Sharded);
+    eprintln!(
+        "{} holds {} copies of the checkpoint ({}):",
+        copies.dir.display(),
+        copies.sets.len(),
+        copies.reason
+    );
+    for line in copies.listing() {
+        eprintln!("{line}");
+    }
+    loop {
+        eprint!(
+            "Load which? [1-{}, Enter for {}]: ",
+            copies.sets.len(),
+            default + 1
+        );
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(default);
+        }
+        match answer.trim() {
+            "" => return Ok(default),
+            text => {
+                if let Ok(number) = text.parse::<usize>()
+                    && (1..=copies.sets.len()).contains(&number)
+                {
+                    return Ok(number - 1);
+                }
+            }
+        }
+    }
+}
+
+/// Expand braces, then glob wildcards, in one path argument. Parts that match nothing are
+/// reported, telling a missing directory apart from a pattern without matches.
+fn expand_path_argument(path: &Path) -> Vec<PathBuf> {
+    // An existing path is taken as is, even when its name holds `[`, `{` or `*`
+    if path.exists() {
+        return vec![path.to_path_buf()];
+    }
+    let text = path.to_string_lossy();
+    let patterns = match utils::expand_braces(&text) {
+        Ok(patterns) => patterns,
+        Err(e) => {
+            events::warn(&format!("Skipping {text}: {e}"));
+            return Vec::new();
+        }
+    };
+    log::debug!("Argument {text} brace-expands to {patterns:?}");
+
+    let mut expanded = Vec::new();
+    for pattern in patterns {
+        let literal = PathBuf::from(&pattern);
+        if !pattern.contains(['*', '?', '[']) {
+            if literal.exists() {
+                expanded.push(literal);
+            } else {
+                events::warn(&format!("Path does not exist: {pattern}"));
+            }
+            continue;
+        }
+        // The glob crate treats `\` as a separator on Windows and never as an escape, so
+        // backslash paths are safe to pass through
+        match glob::glob(&pattern) {
+            Ok(matches) => {
+                let before = expanded.len();
+                expanded.extend(matches.filter_map(Result::ok));
+                if expanded.len() > before {
+                    continue;
+                }
+                // Everything before the first wildcard is a plain directory path
+                let base: PathBuf = literal
+                    .components()
+                    .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+                    .collect();
+                if base.as_os_str().is_empty() || base.exists() {
+                    events::warn(&format!("Pattern matched no files: {pattern}"));
+                } else {
+                    events::warn(&format!(
+                        "Path does not exist: {} (in pattern {pattern})",
+                        base.display()
+                    ));
+                }
+            }
+            Err(e) => eprintln!("Warning: Invalid glob pattern {pattern}: {e}"),
+        }
+    }
+    expanded
+}
+
+/// Print a report in the `--format` format, or write it to `--output`
+fn emit(report: &(impl Report + ?Sized), args: &Args) -> Result<()> {
+    events::report(report);
+    let content = report::render(report, args.format.unwrap_or_default())?;
+    match &args.output {
+        Some(path) => {
+            fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+        }
+        None => {
+            events::text(&content);
+            Ok(())
+        }
+    }
+}
+
+fn parse_glob_pattern(pattern: &str) -> Result<glob::Pattern, String> {
+    glob::Pattern::new(pattern).map_err(|e| format!("invalid pattern: {e}"))
+}
+
 fn parse_safetensors_index(index_path: &PathBuf) -> Result<Vec<String>> {
     let content = fs::read_to_string(index_path)
         .with_context(|| format!("Failed to read index file: {}", index_path.display()))?;
@@ -0,0 +1,1275 @@
+use anyhow::{Context, Result, bail};
+use safetensors::tensor::Metadata;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::companion::{self, Companion, CompanionKind, GgufShape};
+use crate::dtype;
+use crate::gguf::{GGUFFile, GGUFLayout, GGUFValue, MetadataType, token_type_counts};
+use crate::hdf5;
+use crate::hf::{self, TokenizerStats};
+use crate::npy;
+use crate::onnx;
+use crate::pytorch;
+use crate::quant;
+use crate::scan::{NoProgress, Progress};
+use crate::summary;
+use crate::tree::{LoadFailure, MetadataInfo, TensorInfo, natural_sort_key};
+use crate::utils::{display_file_name, file_format, format_size, model_extension};
+use crate::zip::{METHOD_STORED, ZipArchive};
+
+pub use crate::quant::QuantSummary;
+
+/// Error context marking the byte offset where a parser gave up, so the explorer can
+/// point at it without parsing the message
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct AtOffset {
+    pub offset: u64,
+    pub what: String,
+}
+
+impl std::fmt::Display for AtOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (failed at offset {})", self.what, self.offset)
+    }
+}
+
+/// What one loaded file contributed, for the files panel
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct FileRecord {
+    pub path: PathBuf,
+    /// Size on disk in bytes
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch, when the filesystem has one
+    pub modified: Option<u64>,
+    pub format: &'static str,
+    /// Container version for formats that record one, e.g. `v3` for GGUF
+    pub version: Option<String>,
+    /// Tensors read from the file, before names shared with other files are deduplicated
+    pub tensors: usize,
+    pub metadata_keys: usize,
+    /// Time spent reading the header
+    pub parse_time: Duration,
+    /// Header bytes taken from the file, for the formats whose loader counts them
+    pub bytes_read: Option<u64>,
+    /// `parse_time` split into reading the header or metadata section and turning the
+    /// tensor records into tensors; None for formats not instrumented
+    pub header_time: Option<Duration>,
+    pub tensor_info_time: Option<Duration>,
+    /// When the file was read, in seconds since the Unix epoch; changes on every reload
+    pub loaded_at: u64,
+    /// Warnings raised while loading this file
+    pub warnings: Vec<String>,
+    /// Where the file sits in a split GGUF, from its `split.*` keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split: Option<GgufSplit>,
+    /// Set when tensor data runs past the end of the file, as in a download cut short
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incomplete: Option<Incomplete>,
+}
+
+/// How much of a truncated file arrived, going by where its tensors should end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Incomplete {
+    /// Size the file needs to hold all its tensors
+    pub expected_size: u64,
+    pub missing_bytes: u64,
+    /// Tensors whose data is all there
+    pub present_tensors: usize,
+    /// Tensors missing some or all of their data
+    pub cut_tensors: usize,
+}
+
+impl Incomplete {
+    /// None when every tensor of the file ends within `file_size`; fails when a tensor's
+    /// end overflows
+    fn of(tensors: &[TensorInfo], file_size: u64) -> Result<Option<Self>> {
+        let mut ends = Vec::with_capacity(tensors.len());
+        for t in tensors {
+            let Some(offset) = t.data_offset else {
+                continue;
+            };
+            let Some(end) = offset.checked_add(t.size_bytes as u64) else {
+                bail!(
+                    "Tensor {}'s {} bytes at offset {offset} end past the largest file offset",
+                    t.name,
+                    t.size_bytes
+                );
+            };
+            ends.push(end);
+        }
+        let Some(&expected_size) = ends.iter().max() else {
+            return Ok(None);
+        };
+        if expected_size <= file_size {
+            return Ok(None);
+        }
+        let cut_tensors = ends.iter().filter(|&&end| end > file_size).count();
+        Ok(Some(Incomplete {
+            expected_size,
+            missing_bytes: expected_size - file_size,
+            present_tensors: tensors.len() - cut_tensors,
+            cut_tensors,
+        }))
+    }
+}
+
+/// The `split.*` keys `llama-gguf-split` writes into every file of a split GGUF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct GgufSplit {
+    /// Zero-based part number (`split.no`); file names count from 1
+    pub no: u64,
+    pub count: u64,
+    /// Tensors across the whole set (`split.tensors.count`)
+    pub tensors_count: Option<u64>,
+}
+
+fn epoch_seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// Tensors listed under `file.padding_top_tensors`; `--padding-report` lists them all
+const LAYOUT_TOP_PADDING: usize = 5;
+
+/// Tensors and metadata merged from one or more model files
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct ModelDescription {
+    /// Every tensor, deduplicated by name and in natural name order
+    pub tensors: Vec<TensorInfo>,
+    /// Embedded metadata plus entries from sidecar files such as `config.json`
+    pub metadata: Vec<MetadataInfo>,
+    /// Non-fatal problems met while loading, e.g. an unreadable sidecar file
+    pub warnings: Vec<String>,
+    /// Parameter count, using the dequantized size of GPTQ/AWQ modules
+    pub total_parameters: usize,
+    /// Bytes of tensor data as stored
+    pub total_bytes: usize,
+    /// Parsed `config.json` found next to the safetensors files, if any
+    pub config: Option<serde_json::Value>,
+    /// GPTQ/AWQ settings when the checkpoint stores packed quantized weights
+    pub quantization: Option<QuantSummary>,
+    /// Files skipped by `load_partial` because they failed to parse
+    pub failures: Vec<LoadFailure>,
+    /// Data section layout of each GGUF file, for the padding report
+    pub gguf_layouts: Vec<(PathBuf, GGUFLayout)>,
+    /// GGUF files holding a vision projector (`clip` architecture), shown as their own
+    /// component next to the language model
+    pub mmproj_files: Vec<PathBuf>,
+    /// Vision projectors, draft models and adapters loaded with a base model, each kept as
+    /// a component of its own; empty when no file holds a base model
+    pub companions: Vec<Companion>,
+    /// Subdirectories the files came from, relative to the deepest directory holding them
+    /// all (e.g. `text_encoder_2` in a diffusers repo). Tensors of each are named with it
+    /// as a prefix so equal names in different models stay apart; empty when every file
+    /// sits in one directory.
+    pub components: Vec<String>,
+    /// One record per file that loaded, in load order
+    pub files: Vec<FileRecord>,
+    /// Tensors dropped when deduplicating by name, with the file the dropped copy came from
+    pub shadowed_tensors: Vec<(String, PathBuf)>,
+    /// Tensors of incomplete files whose data runs past the end of the file, kept out of
+    /// `tensors` and the totals
+    pub cut_off_tensors: Vec<TensorInfo>,
+    /// Files left unread because `load_cancellable` was cancelled before reaching them
+    pub not_loaded: Vec<PathBuf>,
+}
+
+/// What `ModelDescription::load_observed` reports for each file
+#[non_exhaustive]
+pub enum FileOutcome<'a> {
+    Parsed(&'a FileRecord),
+    Failed(&'a Path, &'a anyhow::Error),
+}
+
+/// Warning for a file whose tensor data runs past its end
+fn incomplete_warning(path: &Path, incomplete: &Incomplete) -> String {
+    format!(
+        "{} is incomplete: {} of {} arrived; {} of {} tensors present, the {} cut off are left out of the totals",
+        display_file_name(path),
+        format_size((incomplete.expected_size - incomplete.missing_bytes) as usize),
+        format_size(incomplete.expected_size as usize),
+        incomplete.present_tensors,
+        incomplete.present_tensors + incomplete.cut_tensors,
+        incomplete.cut_tensors
+    )
+}
+
+/// Read the headers of `paths` (model files, not directories) into one description, failing
+/// on the first file that cannot be parsed. Tensor data is never read.
+pub fn load_model<P: AsRef<Path>>(paths: &[P]) -> Result<ModelDescription> {
+    let files: Vec<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+    ModelDescription::load(&files)
+}
+
+impl ModelDescription {
+    /// Load every file, failing on the first one that cannot be parsed
+    pub fn load(files: &[PathBuf]) -> Result<Self> {
+        Self::load_with(files, false, &mut |_| {}, &mut NoProgress)
+    }
+
+    /// Like `load`, calling `on_file` as each file is read or fails to parse
+    pub fn load_observed(files: &[PathBuf], on_file: &mut dyn FnMut(FileOutcome)) -> Result<Self> {
+        Self::load_with(files, false, on_file, &mut NoProgress)
+    }
+
+    /// Load every file that parses, recording the others in `failures`; fails only when
+    /// no file could be loaded
+    pub fn load_partial(files: &[PathBuf]) -> Result<Self> {
+        Self::load_with(files, true, &mut |_| {}, &mut NoProgress)
+    }
+
+    /// Like `load_partial`, telling `progress` about each file before it is read, with the
+    /// bytes of the files done and of all files. Once `progress` returns false no further
+    /// file is opened: the ones read so far make up the model and the rest are listed in
+    /// `not_loaded`. Fails when cancelled before any file was read.
+    pub fn load_cancellable(files: &[PathBuf], progress: &mut dyn Progress) -> Result<Self> {
+        Self::load_with(files, true, &mut |_| {}, progress)
+    }
+
+    fn load_with(
+        files: &[PathBuf],
+        keep_going: bool,
+        on_file: &mut dyn FnMut(FileOutcome),
+        progress: &mut dyn Progress,
+    ) -> Result<Self> {
+        let mut model = ModelDescription {
+            tensors: Vec::new(),
+            metadata: Vec::new(),
+            warnings: Vec::new(),
+            total_parameters: 0,
+            total_bytes: 0,
+            config: None,
+            quantization: None,
+            failures: Vec::new(),
+            gguf_layouts: Vec::new(),
+            mmproj_files: Vec::new(),
+            companions: Vec::new(),
+            components: Vec::new(),
+            files: Vec::new(),
+            shadowed_tensors: Vec::new(),
+            cut_off_tensors: Vec::new(),
+            not_loaded: Vec::new(),
+        };
+
+        let prefixes = component_prefixes(files);
+        // Companion files found while loading, with the metadata each one added
+        let mut found: Vec<(PathBuf, CompanionKind, String, std::ops::Range<usize>)> = Vec::new();
+        let mut shapes: Vec<(GgufShape, std::ops::Range<usize>)> = Vec::new();
+        let sizes: Vec<u64> = files
+            .iter()
+            .map(|file| fs::metadata(file).map_or(0, |m| m.len()))
+            .collect();
+        let total: u64 = sizes.iter().sum();
+        let mut done = 0;
+        for (i, (file_path, prefix)) in files.iter().zip(&prefixes).enumerate() {
+            let label = format!(
+                "{} ({} of {} files)",
+                display_file_name(file_path),
+                i + 1,
+                files.len()
+            );
+            if !progress.update(&label, done, total) {
+                log::info!("Loading cancelled after {i} of {} files", files.len());
+                if model.files.is_empty() {
+                    anyhow::bail!("Loading cancelled before any file was read");
+                }
+                model.not_loaded = files[i..].to_vec();
+                break;
+            }
+            done += sizes[i];
+            let extension = model_extension(file_path);
+            let before = model.tensors.len();
+            let (metadata_before, warnings_before) = (model.metadata.len(), model.warnings.len());
+            let stat = fs::metadata(file_path).ok();
+            log::info!(
+                "Opening {} ({} bytes)",
+                file_path.display(),
+                stat.as_ref().map_or(0, |m| m.len())
+            );
+            // Loaders fill in the version; the counts are taken once the file is read
+            model.files.push(FileRecord {
+                path: file_path.clone(),
+                size: stat.as_ref().map_or(0, |m| m.len()),
+                modified: stat
+                    .as_ref()
+                    .and_then(|m| m.modified().ok())
+                    .and_then(epoch_seconds),
+                format: file_format(file_path),
+                version: None,
+                tensors: 0,
+                metadata_keys: 0,
+                parse_time: Duration::ZERO,
+                bytes_read: None,
+                header_time: None,
+                tensor_info_time: None,
+                loaded_at: epoch_seconds(SystemTime::now()).unwrap_or(0),
+                warnings: Vec::new(),
+                split: None,
+                incomplete: None,
+            });
+            let started = Instant::now();
+
+            let loaded = match extension {
+                Some("safetensors") => model.load_safetensors_file(file_path),
+                Some("gguf") => model.load_gguf_file(file_path),
+                Some("dduf") => model.load_dduf_file(file_path),
+                Some("zip") => model.load_zip_file(file_path),
+                Some("onnx") => model.load_onnx_file(file_path),
+                Some("h5" | "hdf5") => model.load_hdf5_file(file_path),
+                Some("npy") => npy::read_npy(file_path).map(|t| model.tensors.push(t)),
+                Some("npz") => npy::read_npz(file_path).map(|t| model.tensors.extend(t)),
+                Some("bin" | "pt" | "pth") => {
+                    model.load_pytorch_file(file_path);
+                    Ok(())
+                }
+                _ => {
+                    model.warn(format!("Unsupported file format: {}", file_path.display()));
+                    Ok(())
+                }
+            };
+            let size = stat.as_ref().map_or(0, |m| m.len());
+            let loaded = loaded.and_then(|()| Incomplete::of(&model.tensors[before..], size));
+            let incomplete = match loaded {
+                Ok(incomplete) => incomplete,
+                Err(e) => {
+                    on_file(FileOutcome::Failed(file_path, &e));
+                    model.files.pop();
+                    // Whatever the file gave before failing isn't part of the model
+                    model.tensors.truncate(before);
+                    model.metadata.truncate(metadata_before);
+                    log::error!("Failed to load {}: {e:#}", file_path.display());
+                    if !keep_going {
+                        return Err(e);
+                    }
+                    if model.failures.len() + 1 == files.len() {
+                        return Err(if files.len() > 1 {
+                            e.context(format!("None of the {} files could be loaded", files.len()))
+                        } else {
+                            e
+                        });
+                    }
+                    model.failures.push(LoadFailure {
+                        file: file_path.clone(),
+                        chain: e.chain().map(ToString::to_string).collect(),
+                        offset: e.downcast_ref::<AtOffset>().map(|at| at.offset),
+                    });
+                    continue;
+                }
+            };
+            log::info!(
+                "Loaded {} tensors from {}",
+                model.tensors.len() - before,
+                file_path.display()
+            );
+            if let Some(record) = model.files.last_mut() {
+                record.incomplete = incomplete;
+                if let Some(incomplete) = &record.incomplete {
+                    let warning = incomplete_warning(&record.path, incomplete);
+                    model.warn(warning);
+                }
+            }
+            if let Some(record) = model.files.last_mut() {
+                record.parse_time = started.elapsed();
+                record.tensors = model.tensors.len() - before;
+                record.metadata_keys = model.metadata.len() - metadata_before;
+                record.warnings = model.warnings[warnings_before..].to_vec();
+                on_file(FileOutcome::Parsed(record));
+            }
+            let added = metadata_before..model.metadata.len();
+            let file_metadata = &model.metadata[added.clone()];
+            match companion::classify(file_path, file_metadata, &model.tensors[before..]) {
+                Some((kind, reason)) => found.push((file_path.clone(), kind, reason, added)),
+                None => shapes.extend(GgufShape::of(file_path, file_metadata).map(|s| (s, added))),
+            }
+            if !prefix.is_empty() {
+                for tensor in &mut model.tensors[before..] {
+                    tensor.name.insert_str(0, prefix);
+                }
+                let component = prefix.trim_end_matches('.');
+                if !model.components.iter().any(|c| c == component) {
+                    model.components.push(component.to_string());
+                }
+            }
+        }
+
+        // Drafts and adapters need a base model to be companions of; a projector is told
+        // apart even on its own
+        let all_shapes: Vec<GgufShape> = shapes.iter().map(|(shape, _)| shape.clone()).collect();
+        for (file, reason) in companion::find_drafts(&all_shapes) {
+            if let Some((_, added)) = shapes.iter().find(|(shape, _)| shape.file == file) {
+                found.push((file, CompanionKind::Draft, reason, added.clone()));
+            }
+        }
+        let has_base = model
+            .files
+            .iter()
+            .any(|record| record.tensors > 0 && !found.iter().any(|(f, ..)| *f == record.path));
+        for (file, kind, reason, added) in found {
+            if kind != CompanionKind::Projector && !has_base {
+                continue;
+            }
+            let companion = Companion { kind, file, reason };
+            // The projector loader labels its own metadata
+            if kind != CompanionKind::Projector {
+                let source = companion.source();
+                for entry in &mut model.metadata[added] {
+                    let file_info = entry
+                        .source
+                        .as_deref()
+                        .is_some_and(|s| s.starts_with(summary::FILE_INFO_PREFIX));
+                    if !file_info {
+                        entry.source = Some(source.clone());
+                    }
+                }
+            }
+            model.companions.push(companion);
+        }
+
+        // Deduplicate tensors by name; a companion's tensors only shadow each other
+        let companion_files: HashSet<PathBuf> =
+            model.companions.iter().map(|c| c.file.clone()).collect();
+        let mut seen_names = HashSet::new();
+        let mut shadowed = Vec::new();
+        model.tensors.retain(|tensor| {
+            let owner = companion_files
+                .get(&tensor.source_file)
+                .map(|file| file.as_path());
+            let first = seen_names.insert((owner, tensor.name.clone()));
+            if !first {
+                shadowed.push((tensor.name.clone(), tensor.source_file.clone()));
+            }
+            first
+        });
+        model.shadowed_tensors = shadowed;
+
+        // Tensors an incomplete file doesn't hold in full can't be read or counted
+        let incomplete: Vec<(PathBuf, u64)> = model
+            .files
+            .iter()
+            .filter(|record| record.incomplete.is_some())
+            .map(|record| (record.path.clone(), record.size))
+            .collect();
+        if !incomplete.is_empty() {
+            let (cut, kept) =
+                std::mem::take(&mut model.tensors)
+                    .into_iter()
+                    .partition(|tensor: &TensorInfo| {
+                        incomplete.iter().any(|(path, size)| {
+                            tensor.source_file == *path
+                                && tensor
+                                    .data_offset
+                                    .is_some_and(|start| start + tensor.size_bytes as u64 > *size)
+                        })
+                    });
+            model.tensors = kept;
+            model.cut_off_tensors = cut;
+        }
+
+        model.tensors.sort_by_key(|a| natural_sort_key(&a.name));
+
+        if let Some(dir) = Self::checkpoint_dir(files) {
+            model.config = model.load_json_sidecar(&dir, "config.json");
+            model.load_json_sidecar(&dir, "generation_config.json");
+            match hf::read_tokenizer_stats(&dir) {
+                Ok(Some(stats)) => model.add_tokenizer_metadata(&stats),
+                Ok(None) => {}
+                Err(e) => model.warn(format!("{e:#}")),
+            }
+        }
+
+        if let Some(mismatch) = summary::projector_mismatch(&model) {
+            model.warn(mismatch);
+        }
+        for mismatch in companion::companion_mismatches(&model) {
+            model.warn(mismatch);
+        }
+        let iq_tensors = summary::iq_tensor_count(&model.tensors);
+        if iq_tensors > 0 && !summary::has_imatrix(&model.metadata) {
+            model.warn(format!(
+                "{iq_tensors} IQ-quantized tensors but no quantize.imatrix.* metadata; \
+                 IQ quants made without an importance matrix are usually low quality"
+            ));
+        }
+
+        model.quantization = quant::annotate(&mut model.tensors, model.config.as_ref());
+        model.total_parameters = model.tensors.iter().map(quant::logical_parameters).sum();
+        model.total_bytes = model.tensors.iter().map(|t| t.size_bytes).sum();
+        Ok(model)
+    }
+
+    /// Keep only the tensors `keep` accepts, updating the parameter and byte totals
+    pub fn retain_tensors(&mut self, keep: impl FnMut(&TensorInfo) -> bool) {
+        self.tensors.retain(keep);
+        self.total_parameters = self.tensors.iter().map(quant::logical_parameters).sum();
+        self.total_bytes = self.tensors.iter().map(|t| t.size_bytes).sum();
+    }
+
+    /// Parameters stored in each dtype, largest first (ties by dtype name); dtypes that
+    /// only hold quantization companions such as scales are left out
+    pub fn parameters_by_dtype(&self) -> Vec<(String, usize)> {
+        let mut by_dtype: Vec<(String, usize)> = Vec::new();
+        for tensor in &self.tensors {
+            let params = quant::logical_parameters(tensor);
+            match by_dtype
+                .iter_mut()
+                .find(|(dtype, _)| *dtype == tensor.dtype)
+            {
+                Some((_, total)) => *total += params,
+                None => by_dtype.push((tensor.dtype.clone(), params)),
+            }
+        }
+        by_dtype.retain(|(_, params)| *params > 0);
+        by_dtype.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        by_dtype
+    }
+
+    /// Container version of the file being loaded, for its files panel record
+    fn set_file_version(&mut self, version: String) {
+        if let Some(record) = self.files.last_mut() {
+            record.version = Some(version);
+        }
+    }
+
+    /// Add one header's byte count and phase times to the record of the file being loaded;
+    /// a DDUF archive adds one per embedded safetensors file
+    fn record_phases(&mut self, bytes: u64, header: Duration, tensor_info: Duration) {
+        if let Some(record) = self.files.last_mut() {
+            *record.bytes_read.get_or_insert(0) += bytes;
+            *record.header_time.get_or_insert(Duration::ZERO) += header;
+            *record.tensor_info_time.get_or_insert(Duration::ZERO) += tensor_info;
+        }
+    }
+
+    /// Record a non-fatal problem; the caller decides whether and how to show it
+    fn warn(&mut self, message: String) {
+        log::warn!("{message}");
+        self.warnings.push(message);
+    }
+
+    /// Directory holding the first HF-style weights file, where sidecar files live
+    fn checkpoint_dir(files: &[PathBuf]) -> Option<PathBuf> {
+        let first = files.iter().find(|f| {
+            matches!(
+                model_extension(f),
+                Some("safetensors" | "bin" | "pt" | "pth")
+            )
+        })?;
+        first.parent().map(Path::to_path_buf)
+    }
+
+    /// Parse a JSON sidecar file and add its keys as metadata labeled with the file name
+    fn load_json_sidecar(&mut self, dir: &Path, file_name: &str) -> Option<serde_json::Value> {
+        let path = dir.join(file_name);
+        let content = fs::read_to_string(&path).ok()?;
+        let json: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(json) => json,
+            Err(e) => {
+                self.warn(format!("Failed to parse {}: {e}", path.display()));
+                return None;
+            }
+        };
+
+        let mut entries = Vec::new();
+        flatten_json("", &json, &mut entries);
+        for (key, value) in entries {
+            self.push_sidecar_entry(file_name, key, value);
+        }
+        Some(json)
+    }
+
+    fn push_sidecar_entry(&mut self, source: &str, key: String, value: GGUFValue) {
+        self.metadata.push(MetadataInfo {
+            name: key,
+            value: value.to_string(),
+            value_type: value.type_name(),
+            raw_value: Some(Arc::new(value)),
+            source: Some(source.to_string()),
+        });
+    }
+
+    /// Add a "Tokenizer" metadata group and cross-check the vocab against the embedding
+    fn add_tokenizer_metadata(&mut self, stats: &TokenizerStats) {
+        const SOURCE: &str = "Tokenizer";
+        let mut entries = vec![
+            (
+                "tokenizer.vocab_size",
+                GGUFValue::U64(stats.vocab_size as u64),
+            ),
+            (
+                "tokenizer.base_vocab",
+                GGUFValue::U64(stats.base_vocab as u64),
+            ),
+            (
+                "tokenizer.added_tokens",
+                GGUFValue::U64(stats.added_tokens as u64),
+            ),
+            (
+                "tokenizer.special_token_count",
+                GGUFValue::U64(stats.special_tokens.len() as u64),
+            ),
+            (
+                "tokenizer.special_tokens",
+                GGUFValue::Array(
+                    MetadataType::String,
+                    stats
+                        .special_tokens
+                        .iter()
+                        .map(|t| GGUFValue::String(t.clone()))
+                        .collect(),
+                ),
+            ),
+            (
+                "tokenizer.has_chat_template",
+                GGUFValue::Bool(stats.has_chat_template),
+            ),
+        ];
+        if let Some(model_type) = &stats.model_type {
+            entries.push((
+                "tokenizer.model_type",
+                GGUFValue::String(model_type.clone()),
+            ));
+            if model_type == "BPE" {
+                entries.push(("tokenizer.merges", GGUFValue::U64(stats.merges as u64)));
+            }
+        }
+        for (key, value) in entries {
+            self.push_sidecar_entry(SOURCE, key.to_string(), value);
+        }
+        for (key, token) in &stats.named_tokens {
+            self.push_sidecar_entry(
+                SOURCE,
+                format!("tokenizer.{key}"),
+                GGUFValue::String(token.clone()),
+            );
+        }
+
+        if let Some(embedding_rows) = summary::infer_architecture(&self.tensors).vocab_size {
+            let check = if embedding_rows == stats.vocab_size {
+                format!("ok: tokenizer vocab matches embedding rows ({embedding_rows})")
+            } else {
+                format!(
+                    "⚠ mismatch: tokenizer vocab {} vs embedding rows {} ({:+})",
+                    stats.vocab_size,
+                    embedding_rows,
+                    embedding_rows as i64 - stats.vocab_size as i64
+                )
+            };
+            self.push_sidecar_entry(
+                SOURCE,
+                "tokenizer.embedding_check".to_string(),
+                GGUFValue::String(check),
+            );
+        }
+    }
+
+    fn load_safetensors_file(&mut self, file_path: &Path) -> Result<()> {
+        let mut file = File::open(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        self.load_safetensors_header(&mut file, file_path, size, Some(0), "", None)
+    }
+
+    /// Read a safetensors header from `reader`, which holds `size` bytes when known and whose
+    /// first byte sits at `base_offset` in `file_path` (None when compressed inside an
+    /// archive); tensor names get `prefix` and `__metadata__` entries are labeled `source`
+    fn load_safetensors_header(
+        &mut self,
+        reader: &mut dyn Read,
+        file_path: &Path,
+        size: Option<u64>,
+        base_offset: Option<u64>,
+        prefix: &str,
+        source: Option<&str>,
+    ) -> Result<()> {
+        let started = Instant::now();
+        let (metadata, header_len) = read_safetensors_header(reader, file_path, size)?;
+        let data_start = base_offset.map(|base| base + 8 + header_len);
+        log::debug!(
+            "safetensors data for {} starts at offset {data_start:?}",
+            file_path.display()
+        );
+
+        // Check if there's a __metadata__ key in the header
+        if let Some(metadata_value) = metadata.metadata() {
+            // Parse the metadata as key-value pairs
+            for (key, value) in metadata_value {
+                let entry = crate::sd_metadata::safetensors_entry(key, value, source);
+                log::debug!(
+                    "safetensors __metadata__ key={key} type={}",
+                    entry.value_type
+                );
+                self.metadata.push(entry);
+            }
+        }
+
+        let header_time = started.elapsed();
+        let started = Instant::now();
+        for (name, tensor) in metadata.tensors() {
+            let shape = tensor.shape.clone();
+            let num_elements = shape.iter().product::<usize>();
+            let dtype = dtype::safetensors_name(tensor.dtype);
+            let (start, end) = tensor.data_offsets;
+            log::debug!(
+                "safetensors tensor name={prefix}{name} dtype={dtype} shape={shape:?} data_offsets=[{start}, {end})"
+            );
+
+            let data_offset = match data_start {
+                Some(data_start) => {
+                    Some(data_start.checked_add(start as u64).with_context(|| {
+                        format!(
+                            "Tensor {name}'s data offset {start} is past the largest file offset"
+                        )
+                    })?)
+                }
+                None => None,
+            };
+            self.tensors.push(TensorInfo {
+                name: format!("{prefix}{name}"),
+                dtype,
+                shape,
+                size_bytes: end - start,
+                num_elements,
+                source_file: file_path.to_path_buf(),
+                data_offset,
+                quant: None,
+                notes: if data_start.is_some() {
+                    Vec::new()
+                } else {
+                    vec!["Compressed inside archive; extract it first".to_string()]
+                },
+                storage_shape: None,
+            });
+        }
+        self.record_phases(8 + header_len, header_time, started.elapsed());
+
+        Ok(())
+    }
+
+    /// DDUF pipelines: each component folder's safetensors are read in place inside the
+    /// archive and nested under the folder name; config JSON files become metadata groups
+    fn load_dduf_file(&mut self, file_path: &Path) -> Result<()> {
+        self.load_archive(file_path, "", true)
+    }
+
+    /// Zip archives of a checkpoint, read like DDUF but nested under the archive's name.
+    /// Deflated safetensors members only have their header decompressed, so their tensors
+    /// have no data offset.
+    fn load_zip_file(&mut self, file_path: &Path) -> Result<()> {
+        // Dots would split the archive's group in the tree
+        let root = file_path
+            .file_stem()
+            .map(|stem| format!("{}.", stem.to_string_lossy().replace('.', "_")))
+            .unwrap_or_default();
+        self.load_archive(file_path, &root, false)
+    }
+
+    /// Load the safetensors and config JSON members of a zip-based archive, naming tensors
+    /// with `root` and the member's folder. `stored_only` skips compressed members, as
+    /// the DDUF spec doesn't allow them.
+    fn load_archive(&mut self, file_path: &Path, root: &str, stored_only: bool) -> Result<()> {
+        let archive = ZipArchive::open(file_path)?;
+        let mut members = 0;
+        for entry in &archive.entries {
+            let prefix = match entry.name.rsplit_once('/') {
+                Some((dir, _)) => format!("{root}{}.", dir.replace('/', ".")),
+                None => root.to_string(),
+            };
+            if entry.name.ends_with(".safetensors") {
+                let stored = entry.compression == METHOD_STORED;
+                if !stored && stored_only {
+                    self.warn(format!(
+                        "Skipping compressed member {} in {}",
+                        entry.name,
+                        file_path.display()
+                    ));
+                    continue;
+                }
+                let offset = if stored {
+                    Some(archive.data_offset(entry)?)
+                } else {
+                    None
+                };
+                self.load_safetensors_header(
+                    &mut archive.reader(entry)?,
+                    file_path,
+                    Some(entry.uncompressed_size),
+                    offset,
+                    &prefix,
+                    Some(&entry.name),
+                )
+                .with_context(|| {
+                    format!(
+                        "Failed to read {} in {}",
+                        entry.name,
+                        file_format(file_path)
+                    )
+                })?;
+                members += 1;
+            } else if entry.name.ends_with("config.json") || entry.name == "model_index.json" {
+                let json: serde_json::Value = match serde_json::from_slice(&archive.read(entry)?) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        self.warn(format!(
+                            "Failed to parse {} in {}: {e}",
+                            entry.name,
+                            file_path.display()
+                        ));
+                        continue;
+                    }
+                };
+                let mut entries = Vec::new();
+                flatten_json("", &json, &mut entries);
+                for (key, value) in entries {
+                    self.push_sidecar_entry(&entry.name, key, value);
+                }
+                // The checkpoint's own config, for the architecture and quantization summaries
+                let file_name = entry.name.rsplit('/').next();
+                if file_name == Some("config.json") && self.config.is_none() {
+                    self.config = Some(json);
+                }
+            }
+        }
+        if members == 0 && !stored_only {
+            self.warn(format!(
+                "No .safetensors members in {}",
+                file_path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// PyTorch checkpoints are best-effort: anything unreadable is reported and skipped
+    fn load_pytorch_file(&mut self, file_path: &Path) {
+        let checkpoint = match pytorch::read_checkpoint(file_path) {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => {
+                self.warn(format!("Skipping PyTorch checkpoint: {e:#}"));
+                return;
+            }
+        };
+        if checkpoint.tensors.is_empty() || checkpoint.unknown_objects > 0 {
+            self.warn(format!(
+                "{} could only be partly summarized ({} tensors, {} unknown objects)",
+                file_path.display(),
+                checkpoint.tensors.len(),
+                checkpoint.unknown_objects
+            ));
+        }
+
+        let source = file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        for (key, value) in checkpoint.entries {
+            self.metadata.push(MetadataInfo {
+                name: key,
+                value,
+                value_type: "pickle".to_string(),
+                raw_value: None,
+                source: source.clone(),
+            });
+        }
+        self.tensors.extend(checkpoint.tensors);
+    }
+
+    fn load_onnx_file(&mut self, file_path: &Path) -> Result<()> {
+        let onnx = onnx::read_onnx(file_path)?;
+        if let Some((_, version)) = onnx
+            .metadata
+            .iter()
+            .find(|(key, _)| key == "onnx.ir_version")
+        {
+            self.set_file_version(format!("IR v{version}"));
+        }
+        for (key, value) in onnx.metadata {
+            self.metadata.push(MetadataInfo {
+                name: key,
+                value: value.to_string(),
+                value_type: value.type_name(),
+                raw_value: Some(Arc::new(value)),
+                source: None,
+            });
+        }
+        self.tensors.extend(onnx.tensors);
+        Ok(())
+    }
+
+    /// HDF5 attributes are grouped under the file name, like pickle entries
+    fn load_hdf5_file(&mut self, file_path: &Path) -> Result<()> {
+        let contents = hdf5::read_hdf5(file_path)?;
+        for warning in &contents.warnings {
+            self.warn(format!("{}: {warning}", file_path.display()));
+        }
+        let source = file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        for (key, value) in contents.metadata {
+            self.metadata.push(MetadataInfo {
+                name: key,
+                value: value.to_string(),
+                value_type: value.type_name(),
+                raw_value: Some(Arc::new(value)),
+                source: source.clone(),
+            });
+        }
+        self.tensors.extend(contents.tensors);
+        Ok(())
+    }
+
+    fn load_gguf_file(&mut self, file_path: &Path) -> Result<()> {
+        let context = || format!("Failed to parse GGUF file: {}", file_path.display());
+        let started = Instant::now();
+        let mut gguf = GGUFFile::open(file_path).with_context(context)?;
+        self.set_file_version(format!("v{}", gguf.header.version));
+
+        // Load metadata
+        let first_entry = self.metadata.len();
+        for entry in gguf.metadata() {
+            let (key, value) = entry.with_context(context)?;
+            // Token types are only readable once decoded into a count per category
+            let shown = match &value {
+                GGUFValue::Array(_, types) if key == "tokenizer.ggml.token_type" => {
+                    summary::token_type_summary(&token_type_counts(types))
+                }
+                _ => value.to_string(),
+            };
+            self.metadata.push(MetadataInfo {
+                name: key,
+                value: shown,
+                value_type: value.type_name(),
+                raw_value: Some(Arc::new(value)),
+                source: None,
+            });
+        }
+        // A vision projector keeps its metadata apart so its general.* keys do not mix
+        // with those of the language model loaded next to it
+        if summary::is_mmproj(file_path, &self.metadata[first_entry..]) {
+            let source = summary::mmproj_source(file_path);
+            for entry in &mut self.metadata[first_entry..] {
+                entry.source = Some(source.clone());
+            }
+            self.mmproj_files.push(file_path.to_path_buf());
+        }
+        let split_key = |key: &str| {
+            self.metadata[first_entry..]
+                .iter()
+                .find(|entry| entry.name == key)
+                .and_then(|entry| entry.raw_value.as_ref()?.as_i64())
+                .and_then(|v| u64::try_from(v).ok())
+        };
+        let split = split_key("split.no")
+            .zip(split_key("split.count"))
+            .map(|(no, count)| GgufSplit {
+                no,
+                count,
+                tensors_count: split_key("split.tensors.count"),
+            });
+        if let Some(record) = self.files.last_mut() {
+            record.split = split;
+        }
+
+        let header_time = started.elapsed();
+
+        // Load tensors; their offsets are relative until the data section start is known
+        let started = Instant::now();
+        let first = self.tensors.len();
+        let mut records = Vec::new();
+        for tensor in gguf.tensors() {
+            let tensor = tensor.with_context(context)?;
+            let storage_shape: Vec<usize> = tensor.dimensions.iter().map(|&d| d as usize).collect();
+            let shape: Vec<usize> = storage_shape.iter().rev().copied().collect();
+            let dtype = tensor.tensor_type.to_string();
+
+            // Exact from the block layout; estimated only for types without a known one.
+            // Records whose counts overflow were rejected as they were read.
+            let num_elements = tensor.num_elements().unwrap_or_default() as usize;
+            let size_bytes = tensor.stored_size().map_or(0, |(size, _)| size as usize);
+
+            records.push(tensor.clone());
+            self.tensors.push(TensorInfo {
+                name: tensor.name,
+                dtype,
+                shape,
+                size_bytes,
+                num_elements,
+                source_file: file_path.to_path_buf(),
+                data_offset: Some(tensor.offset),
+                quant: None,
+                notes: Vec::new(),
+                storage_shape: Some(storage_shape),
+            });
+        }
+        if let (Some(data_offset), Some(info_end)) = (gguf.data_offset(), gguf.tensor_info_end()) {
+            for tensor in &mut self.tensors[first..] {
+                if let Some(offset) = tensor.data_offset {
+                    let absolute = offset.checked_add(data_offset).with_context(|| {
+                        format!(
+                            "Tensor {}'s data offset {offset} is past the largest file offset",
+                            tensor.name
+                        )
+                    });
+                    tensor.data_offset = Some(absolute.with_context(context)?);
+                }
+            }
+            let file_size = fs::metadata(file_path).map_or(0, |m| m.len());
+            let layout =
+                GGUFLayout::new(gguf.alignment(), info_end, data_offset, file_size, &records)
+                    .with_context(context)?;
+            self.add_layout_metadata(file_path, &layout);
+            self.gguf_layouts.push((file_path.to_path_buf(), layout));
+        }
+        let tensor_info_time = started.elapsed();
+        self.record_phases(
+            gguf.tensor_info_end().unwrap_or(0),
+            header_time,
+            tensor_info_time,
+        );
+
+        Ok(())
+    }
+
+    /// Add a "File info" metadata group with the padding overhead of a GGUF file, and warn
+    /// about gaps and overlaps its alignment cannot explain
+    fn add_layout_metadata(&mut self, file_path: &Path, layout: &GGUFLayout) {
+        let file_name = file_path.file_name().map_or_else(
+            || file_path.display().to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        );
+        let source = summary::file_info_source(file_path);
+        let overhead = format!(
+            "{} ({:.1}%){}",
+            format_size(layout.padding_bytes() as usize),
+            layout.padding_percent(),
+            if layout.estimated > 0 {
+                format!(", approximate: {} tensor sizes estimated", layout.estimated)
+            } else {
+                String::new()
+            }
+        );
+        let top: Vec<GGUFValue> = layout
+            .largest_padding()
+            .into_iter()
+            .take(LAYOUT_TOP_PADDING)
+            .filter(|(_, padding)| *padding > 0)
+            .map(|(name, padding)| GGUFValue::String(format!("{name}: {padding} B")))
+            .collect();
+        let entries = [
+            ("file.alignment", GGUFValue::U64(layout.alignment)),
+            ("file.data_offset", GGUFValue::U64(layout.data_offset)),
+            ("file.size", GGUFValue::U64(layout.file_size)),
+            ("file.padding_overhead", GGUFValue::String(overhead)),
+            (
+                "file.padding_top_tensors",
+                GGUFValue::Array(MetadataType::String, top),
+            ),
+        ];
+        for (key, value) in entries {
+            self.push_sidecar_entry(&source, key.to_string(), value);
+        }
+        for problem in layout.problems() {
+            self.warn(format!("{file_name}: {problem}"));
+        }
+    }
+}
+
+/// Largest JSON header accepted, the limit of the safetensors format
+pub(crate) const MAX_HEADER_BYTES: u64 = 100 << 20;
+
+/// Parse a safetensors header, returning it with the length of its JSON part. `size` is the
+/// length of the input when known, which the header must fit in.
+pub fn read_safetensors_header(
+    reader: &mut dyn Read,
+    file_path: &Path,
+    size: Option<u64>,
+) -> Result<(Metadata, u64)> {
+    // Only the header is read; tensor data stays on disk until a scan needs it
+    let mut len_bytes = [0u8; 8];
+    reader
+        .read_exact(&mut len_bytes)
+        .context(AtOffset {
+            offset: 0,
+            what: "Truncated header length".to_string(),
+        })
+        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+    let header_len = u64::from_le_bytes(len_bytes);
+    log::info!(
+        "safetensors header: {} bytes of JSON at offset 8",
+        header_len
+    );
+    // A garbage length prefix would otherwise ask for an allocation of up to 16 EiB
+    if header_len > MAX_HEADER_BYTES {
+        bail!(
+            "{} is not a safetensors file: its header would be {header_len} bytes, over the format's {} limit",
+            file_path.display(),
+            format_size(MAX_HEADER_BYTES as usize)
+        );
+    }
+    if let Some(size) = size
+        && 8 + header_len > size
+    {
+        bail!(
+            "{} is truncated or not a safetensors file: its header would be {header_len} bytes, but the file is {size} bytes",
+            file_path.display()
+        );
+    }
+
+    let mut header = vec![0u8; header_len as usize];
+    reader
+        .read_exact(&mut header)
+        .context(AtOffset {
+            offset: 8,
+            what: format!("Truncated {header_len}-byte JSON header"),
+        })
+        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+
+    let metadata: Metadata = serde_json::from_slice(&header)
+        .map_err(|e| {
+            // serde_json reports line/column; the byte offset is what a hex dump needs
+            let offset = 8 + json_error_offset(&header, &e) as u64;
+            anyhow::Error::new(e).context(AtOffset {
+                offset,
+                what: "Invalid JSON header".to_string(),
+            })
+        })
+        .with_context(|| format!("Failed to parse SafeTensors file: {}", file_path.display()))?;
+    validate_tensors(&metadata)
+        .with_context(|| format!("Failed to parse SafeTensors file: {}", file_path.display()))?;
+    Ok((metadata, header_len))
+}
+
+/// The checks `SafeTensors::deserialize` makes on the header, which parsing the `Metadata`
+/// alone skips: tensors' data follows one another without gaps and each holds exactly its
+/// shape's bytes. Without them a corrupt shard would overflow the size and element counts.
+fn validate_tensors(metadata: &Metadata) -> Result<()> {
+    let mut tensors: Vec<_> = metadata.tensors().into_iter().collect();
+    tensors.sort_by_key(|(_, tensor)| tensor.data_offsets);
+    let mut next = 0;
+    for (name, tensor) in tensors {
+        let (start, end) = tensor.data_offsets;
+        if start != next {
+            bail!("Tensor {name} starts at data offset {start}, expected {next}");
+        }
+        let Some(size) = end.checked_sub(start) else {
+            bail!("Tensor {name} has data offsets [{start}, {end}) that end before they start");
+        };
+        let expected = tensor
+            .shape
+            .iter()
+            .try_fold(1usize, |product, &dim| product.checked_mul(dim))
+            .and_then(|elements| elements.checked_mul(tensor.dtype.size()));
+        let Some(expected) = expected else {
+            bail!(
+                "Tensor {name} has shape {:?}, too large to count its bytes",
+                tensor.shape
+            );
+        };
+        if size != expected {
+            bail!(
+                "Tensor {name} holds {size} bytes, but {:?} {:?} takes {expected}",
+                tensor.dtype,
+                tensor.shape
+            );
+        }
+        next = end;
+    }
+    Ok(())
+}
+
+/// Byte offset within `json` of the line/column a serde_json error points at
+fn json_error_offset(json: &[u8], error: &serde_json::Error) -> usize {
+    let line_start: usize = json
+        .split(|&b| b == b'\n')
+        .take(error.line().saturating_sub(1))
+        .map(|line| line.len() + 1)
+        .sum();
+    (line_start + error.column().saturating_sub(1)).min(json.len())
+}
+
+/// Tensor name prefix for each file: its directory relative to the deepest directory
+/// holding all of the files, dotted (`unet.`, `text_encoder.`); empty for files in that
+/// directory itself, and for all files when they share one directory
+fn component_prefixes(files: &[PathBuf]) -> Vec<String> {
+    let dirs: Vec<&Path> = files
+        .iter()
+        .map(|f| f.parent().unwrap_or(Path::new("")))
+        .collect();
+    let Some(first) = dirs.first() else {
+        return Vec::new();
+    };
+    if dirs.iter().all(|dir| dir == first) {
+        return vec![String::new(); files.len()];
+    }
+    let mut root = first.to_path_buf();
+    while !dirs.iter().all(|dir| dir.starts_with(&root)) {
+        if !root.pop() {
+            break;
+        }
+    }
+    dirs.iter()
+        .map(|dir| {
+            let relative = dir.strip_prefix(&root).unwrap_or(dir);
+            relative
+                .components()
+                .map(|c| format!("{}.", c.as_os_str().to_string_lossy()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Flatten nested JSON objects into dotted keys; arrays stay as single values
+fn flatten_json(prefix: &str, value: &serde_json::Value, entries: &mut Vec<(String, GGUFValue)>) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_json(&key, child, entries);
+            }
+        }
+        _ => entries.push((prefix.to_string(), json_to_value(value))),
+    }
+}
+
+fn json_to_value(value: &serde_json::Value) -> GGUFValue {
+    match value {
+        serde_json::Value::Bool(b) => GGUFValue::Bool(*b),
+        serde_json::Value::Number(n) => {
+            if let Some(v) = n.as_u64() {
+                GGUFValue::U64(v)
+            } else if let Some(v) = n.as_i64() {
+                GGUFValue::I64(v)
+            } else {
+                GGUFValue::F64(n.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        serde_json::Value::String(s) => GGUFValue::String(s.clone()),
+        serde_json::Value::Array(items) => {
+            let items: Vec<GGUFValue> = items.iter().map(json_to_value).collect();
+            let item_type = match items.first() {
+                Some(GGUFValue::Bool(_)) => MetadataType::Bool,
+                Some(GGUFValue::U64(_)) => MetadataType::U64,
+                Some(GGUFValue::I64(_)) => MetadataType::I64,
+                Some(GGUFValue::F64(_)) => MetadataType::F64,
+                _ => MetadataType::String,
+            };
+            GGUFValue::Array(item_type, items)
+        }
+        // null and nested objects inside arrays are kept as their JSON text
+        other => GGUFValue::String(other.to_string()),
+    }
+}
@@ -0,0 +1,244 @@
+//! Tables translating tensor names between conventions, such as llama.cpp's `blk.0.attn_q`
+//! and Hugging Face's `model.layers.0.self_attn.q_proj`. Each table is a list of regex
+//! pattern/replacement rules; the built-in tables ship in the same JSON format a
+//! `--name-map` file uses, so a user file can extend or override them:
+//!
+//! ```json
+//! {
+//!   "rules": [{ "pattern": "^transformer\\.h\\.(\\d+)\\.", "replacement": "model.layers.${1}." }],
+//!   "architectures": {
+//!     "llama": [{ "pattern": "^blk\\.(\\d+)\\.attn_qkv\\.(\\w+)$", "replacement": "..." }]
+//!   }
+//! }
+//! ```
+//!
+//! `rules` apply to every architecture, `architectures` only to models declaring that
+//! architecture. The first rule whose pattern matches a name translates it: a user file's
+//! rules for the architecture come first, then its `rules`, then the built-in tables.
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::model::ModelDescription;
+use crate::summary::metadata_string;
+
+/// The llama, Qwen and Gemma tables between llama.cpp and Hugging Face names
+const BUILTIN_TABLES: &str = include_str!("name_maps.json");
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    rules: Vec<RuleEntry>,
+    #[serde(default)]
+    architectures: BTreeMap<String, Vec<RuleEntry>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleEntry {
+    pattern: String,
+    replacement: String,
+}
+
+/// One pattern/replacement pair, with how many names it has translated
+#[derive(Debug)]
+pub struct NameRule {
+    /// Architecture the rule is limited to; None applies it to all
+    pub architecture: Option<String>,
+    pub pattern: Regex,
+    pub replacement: String,
+    /// Where the rule was written, e.g. `architectures.llama[3]`
+    pub location: String,
+    /// From a `--name-map` file rather than the built-in tables
+    pub user: bool,
+    hits: AtomicUsize,
+}
+
+/// Every name table in effect, in the order rules are tried
+#[derive(Debug)]
+pub struct NameMap {
+    rules: Vec<NameRule>,
+    /// The `--name-map` file, if one was given
+    pub file: Option<PathBuf>,
+    /// Architectures names were translated for, whose rules are expected to match
+    translated: Mutex<BTreeSet<String>>,
+}
+
+/// Reject a replacement referring to a capture group the pattern doesn't have. The regex
+/// crate reads `$1_x` as a group named `1_x`, which silently expands to nothing.
+fn check_replacement(pattern: &Regex, replacement: &str) -> Result<()> {
+    let reference = Regex::new(r"\$(?:\$|\{([^}]*)\}|([0-9A-Za-z_]+))").expect("valid regex");
+    for caps in reference.captures_iter(replacement) {
+        let Some(group) = caps.get(1).or_else(|| caps.get(2)) else {
+            continue;
+        };
+        let group = group.as_str();
+        let exists = match group.parse::<usize>() {
+            Ok(index) => index < pattern.captures_len(),
+            Err(_) => pattern.capture_names().flatten().any(|name| name == group),
+        };
+        if !exists {
+            bail!(
+                "replacement \"{replacement}\" refers to group \"{group}\", which the pattern doesn't have{}",
+                if group.starts_with(|c: char| c.is_ascii_digit()) {
+                    " (write ${1} when a name character follows)"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+fn parse_rules(content: &str, user: bool) -> Result<Vec<NameRule>> {
+    let file: RuleFile = serde_json::from_str(content)
+        .context("expected {\"rules\": [...], \"architectures\": {\"<name>\": [...]}} with {\"pattern\", \"replacement\"} entries")?;
+    let sections = file
+        .architectures
+        .into_iter()
+        .map(|(architecture, rules)| (Some(architecture), rules))
+        .chain(std::iter::once((None, file.rules)));
+    let mut rules = Vec::new();
+    for (architecture, entries) in sections {
+        for (index, entry) in entries.into_iter().enumerate() {
+            let location = match &architecture {
+                Some(architecture) => format!("architectures.{architecture}[{index}]"),
+                None => format!("rules[{index}]"),
+            };
+            let pattern = Regex::new(&entry.pattern)
+                .with_context(|| format!("{location}: invalid pattern \"{}\"", entry.pattern))?;
+            check_replacement(&pattern, &entry.replacement)
+                .with_context(|| format!("{location}: invalid replacement"))?;
+            rules.push(NameRule {
+                architecture: architecture.clone(),
+                pattern,
+                replacement: entry.replacement,
+                location,
+                user,
+                hits: AtomicUsize::new(0),
+            });
+        }
+    }
+    Ok(rules)
+}
+
+impl NameMap {
+    /// The built-in tables alone
+    pub fn builtin() -> Self {
+        Self {
+            rules: parse_rules(BUILTIN_TABLES, false).expect("built-in name tables are valid"),
+            file: None,
+            translated: Mutex::default(),
+        }
+    }
+
+    /// The rules of a `--name-map` file ahead of the built-in tables
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read name map {}", path.display()))?;
+        let mut user = parse_rules(&content, true)
+            .with_context(|| format!("Invalid name map {}", path.display()))?;
+        // Rules for one architecture beat the ones for all
+        user.sort_by_key(|rule| rule.architecture.is_none());
+        let mut map = Self::builtin();
+        user.append(&mut map.rules);
+        map.rules = user;
+        map.file = Some(path.to_path_buf());
+        Ok(map)
+    }
+
+    /// True when some table is written for `architecture`; names of other architectures
+    /// are only translated by the rules for all
+    pub fn covers(&self, architecture: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.architecture.as_deref() == Some(architecture))
+    }
+
+    /// `name` translated by the first rule that matches it, if any
+    pub fn translate(&self, architecture: &str, name: &str) -> Option<String> {
+        if let Ok(mut translated) = self.translated.lock()
+            && !translated.contains(architecture)
+        {
+            translated.insert(architecture.to_string());
+        }
+        let rule = self.rules.iter().find(|rule| {
+            rule.architecture
+                .as_deref()
+                .is_none_or(|a| a == architecture)
+                && rule.pattern.is_match(name)
+        })?;
+        rule.hits.fetch_add(1, Ordering::Relaxed);
+        Some(rule.pattern.replace(name, &rule.replacement).into_owned())
+    }
+
+    /// Rename every tensor of `model` that a rule matches
+    pub fn rename_tensors(&self, model: &mut ModelDescription) {
+        let architecture = architecture_of(model).unwrap_or_default();
+        for tensor in &mut model.tensors {
+            if let Some(name) = self.translate(&architecture, &tensor.name) {
+                tensor.name = name;
+            }
+        }
+    }
+
+    /// Rules of the `--name-map` file that never matched a name; rules for architectures
+    /// no model declared weren't tried, so they don't count
+    pub fn unused_rules(&self) -> Vec<&NameRule> {
+        let translated = self
+            .translated
+            .lock()
+            .map(|t| t.clone())
+            .unwrap_or_default();
+        self.rules
+            .iter()
+            .filter(|rule| {
+                rule.user
+                    && rule.hits.load(Ordering::Relaxed) == 0
+                    && rule
+                        .architecture
+                        .as_ref()
+                        .is_none_or(|a| translated.contains(a))
+            })
+            .collect()
+    }
+
+    /// One warning per unused rule of the `--name-map` file, for the end of the run
+    pub fn unused_warnings(&self) -> Vec<String> {
+        let file = self
+            .file
+            .as_deref()
+            .map_or_else(String::new, |f| f.display().to_string());
+        self.unused_rules()
+            .into_iter()
+            .map(|rule| {
+                format!(
+                    "{file}: {} pattern \"{}\" never matched a tensor name",
+                    rule.location,
+                    rule.pattern.as_str()
+                )
+            })
+            .collect()
+    }
+}
+
+/// Architecture a model declares: `general.architecture` in GGUF metadata, else
+/// `model_type` in config.json
+pub fn architecture_of(model: &ModelDescription) -> Option<String> {
+    metadata_string(&model.metadata, "general.architecture").or_else(|| {
+        model
+            .config
+            .as_ref()
+            .and_then(|config| config.get("model_type"))
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+    })
+}
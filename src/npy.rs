@@ -0,0 +1,300 @@
+use anyhow::{Context, Result, bail};
+use std::{fs::File, io::Read, path::Path};
+
+use crate::tree::TensorInfo;
+use crate::zip::{METHOD_STORED, ZipArchive};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Note on arrays stored column-major, which readers of the data go by
+pub(crate) const FORTRAN_ORDER_NOTE: &str = "Fortran (column-major) order";
+
+/// Parsed `.npy` header
+struct NpyHeader {
+    descr: String,
+    fortran_order: bool,
+    shape: Vec<usize>,
+    /// Bytes before the array data: magic, version, length field and header dict
+    data_start: u64,
+}
+
+/// A Python literal as found in the npy header dict
+#[derive(Debug)]
+enum Literal {
+    Str(String),
+    Int(usize),
+    Bool(bool),
+    Tuple(Vec<Literal>),
+    /// Lists and anything else (e.g. structured dtype descriptions)
+    Other,
+}
+
+struct LiteralParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl LiteralParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            other => bail!("Expected '{expected}' in npy header, found {other:?}"),
+        }
+    }
+
+    fn value(&mut self) -> Result<Literal> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('\'' | '"') => {
+                let quote = self.chars.next().unwrap();
+                let text: String = self.chars.by_ref().take_while(|&c| c != quote).collect();
+                Ok(Literal::Str(text))
+            }
+            Some('(' | '[') => {
+                let close = if self.chars.next() == Some('(') {
+                    ')'
+                } else {
+                    ']'
+                };
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.chars.next_if_eq(&close).is_some() {
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    self.chars.next_if_eq(&',');
+                }
+                Ok(if close == ')' {
+                    Literal::Tuple(items)
+                } else {
+                    Literal::Other
+                })
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                }
+                // Python 2 era files may write longs as `3L`
+                self.chars.next_if_eq(&'L');
+                Ok(Literal::Int(digits.parse()?))
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "True" => Ok(Literal::Bool(true)),
+                    "False" => Ok(Literal::Bool(false)),
+                    "" => bail!("Unexpected character in npy header"),
+                    _ => Ok(Literal::Other),
+                }
+            }
+            None => bail!("Unexpected end of npy header"),
+        }
+    }
+
+    fn dict(&mut self) -> Result<Vec<(String, Literal)>> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.chars.next_if_eq(&'}').is_some() {
+                return Ok(entries);
+            }
+            let Literal::Str(key) = self.value()? else {
+                bail!("npy header keys must be strings");
+            };
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            self.chars.next_if_eq(&',');
+        }
+    }
+}
+
+fn read_header(reader: &mut dyn Read) -> Result<NpyHeader> {
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != NPY_MAGIC {
+        bail!("Not an npy array (bad magic)");
+    }
+    let major = preamble[6];
+    let (header_len, len_size) = if major == 1 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        (u16::from_le_bytes(len) as usize, 2)
+    } else {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        (u32::from_le_bytes(len) as usize, 4)
+    };
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let mut parser = LiteralParser {
+        chars: header.chars().peekable(),
+    };
+    let mut descr = None;
+    let mut fortran_order = false;
+    let mut shape = None;
+    for (key, value) in parser.dict()? {
+        match (key.as_str(), value) {
+            ("descr", Literal::Str(s)) => descr = Some(s),
+            ("descr", _) => descr = Some("structured".to_string()),
+            ("fortran_order", Literal::Bool(b)) => fortran_order = b,
+            ("shape", Literal::Tuple(dims)) => {
+                shape = Some(
+                    dims.into_iter()
+                        .map(|d| match d {
+                            Literal::Int(d) => Ok(d),
+                            other => Err(anyhow::anyhow!("Bad npy shape entry {other:?}")),
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                )
+            }
+            _ => {}
+        }
+    }
+
+    Ok(NpyHeader {
+        descr: descr.context("npy header has no 'descr'")?,
+        fortran_order,
+        shape: shape.context("npy header has no 'shape'")?,
+        data_start: (8 + len_size + header_len) as u64,
+    })
+}
+
+/// Friendly dtype name and element size for a numpy type string such as `<f4`
+fn numpy_dtype(descr: &str) -> (String, usize) {
+    let (order, code) = match descr.chars().next() {
+        Some('<' | '>' | '|' | '=') => descr.split_at(1),
+        _ => ("", descr),
+    };
+    let size: usize = code.get(1..).and_then(|s| s.parse().ok()).unwrap_or(0);
+    let name = match (code.chars().next(), size) {
+        (Some('f'), 2) => "F16".to_string(),
+        (Some('f'), 4) => "F32".to_string(),
+        (Some('f'), 8) => "F64".to_string(),
+        (Some('i'), n) => format!("I{}", n * 8),
+        (Some('u'), n) => format!("U{}", n * 8),
+        (Some('b'), 1) => "BOOL".to_string(),
+        (Some('c'), n) => format!("C{}", n * 8),
+        // ml_dtypes' bfloat16 is stored as an opaque 2-byte void type
+        (Some('V'), 2) => "V2 (bfloat16?)".to_string(),
+        _ => return (descr.to_string(), size),
+    };
+    // Big-endian data keeps a suffix so float scans don't misread it
+    if order == ">" && size > 1 {
+        (format!("{name}_BE"), size)
+    } else {
+        (name, size)
+    }
+}
+
+/// The array `header` describes, in an `.npy` of `available` bytes; fails when its shape
+/// overflows or its data runs past the end
+fn tensor_from_header(
+    name: String,
+    header: &NpyHeader,
+    path: &Path,
+    data_offset: Option<u64>,
+    available: u64,
+) -> Result<TensorInfo> {
+    let (dtype, elem_size) = numpy_dtype(&header.descr);
+    let counts = header
+        .shape
+        .iter()
+        .try_fold(1usize, |product, &dim| product.checked_mul(dim))
+        .and_then(|num_elements| Some((num_elements, num_elements.checked_mul(elem_size)?)));
+    let Some((num_elements, size_bytes)) = counts else {
+        bail!(
+            "Array {name} has shape {:?}, too large to count its bytes",
+            header.shape
+        );
+    };
+    if header
+        .data_start
+        .checked_add(size_bytes as u64)
+        .is_none_or(|end| end > available)
+    {
+        bail!(
+            "Array {name} needs {size_bytes} bytes after its {}-byte header, but the file is {available} bytes",
+            header.data_start
+        );
+    }
+    let mut notes = Vec::new();
+    if header.fortran_order {
+        notes.push(FORTRAN_ORDER_NOTE.to_string());
+    }
+    if dtype != header.descr {
+        notes.push(format!("NumPy dtype {}", header.descr));
+    }
+    Ok(TensorInfo {
+        name,
+        dtype,
+        shape: header.shape.clone(),
+        size_bytes,
+        num_elements,
+        source_file: path.to_path_buf(),
+        data_offset,
+        quant: None,
+        notes,
+        storage_shape: None,
+    })
+}
+
+/// Read the single array stored in an `.npy` file, named after the file stem
+pub fn read_npy(path: &Path) -> Result<TensorInfo> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let header = read_header(&mut file)
+        .with_context(|| format!("Failed to parse npy file: {}", path.display()))?;
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let size = file.metadata()?.len();
+    tensor_from_header(name, &header, path, Some(header.data_start), size)
+        .with_context(|| format!("Failed to parse npy file: {}", path.display()))
+}
+
+/// Read the headers of every `.npy` member of an `.npz` archive
+pub fn read_npz(path: &Path) -> Result<Vec<TensorInfo>> {
+    let archive = ZipArchive::open(path)?;
+    let mut tensors = Vec::new();
+    for entry in &archive.entries {
+        let Some(name) = entry.name.strip_suffix(".npy") else {
+            continue;
+        };
+        let header = read_header(&mut archive.reader(entry)?)
+            .with_context(|| format!("Failed to parse {} in {}", entry.name, path.display()))?;
+        // Members written by np.savez_compressed are deflated and can't be streamed in place
+        let data_offset = if entry.compression == METHOD_STORED {
+            Some(archive.data_offset(entry)? + header.data_start)
+        } else {
+            None
+        };
+        let mut tensor = tensor_from_header(
+            name.to_string(),
+            &header,
+            path,
+            data_offset,
+            entry.uncompressed_size,
+        )
+        .with_context(|| format!("Failed to parse {} in {}", entry.name, path.display()))?;
+        if data_offset.is_none() {
+            tensor.notes.push("Compressed in archive".to_string());
+        }
+        tensors.push(tensor);
+    }
+    Ok(tensors)
+}
@@ -0,0 +1,347 @@
+//! Byte maps of the loaded files: which region (header, tensor, padding) each byte range
+//! belongs to, and which tensor a given offset falls in. Used by the offset view and
+//! `--offset-of` to tell which tensors a truncated download is missing.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::model::ModelDescription;
+use crate::report::Report;
+use crate::utils::{display_file_name, format_size, format_thousands};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionKind {
+    /// Everything before the tensor data: length prefix and JSON header, GGUF metadata and
+    /// tensor records, archive entries
+    Header,
+    Tensor,
+    /// Bytes between the header and a tensor or between two tensors, such as alignment
+    Padding,
+    /// Bytes after the last tensor
+    Trailing,
+}
+
+impl RegionKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            RegionKind::Header => "header",
+            RegionKind::Tensor => "tensor",
+            RegionKind::Padding => "padding",
+            RegionKind::Trailing => "trailing",
+        }
+    }
+}
+
+/// A byte range `[start, end)` of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct Region {
+    pub kind: RegionKind,
+    /// Tensor name, or what the header or padding holds
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Region {
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// `tensor blk.0.attn_q.weight`, or what a header or padding region holds
+    pub fn describe(&self) -> String {
+        match self.kind {
+            RegionKind::Tensor => format!("tensor {}", self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+/// The regions of one file in offset order
+#[derive(Debug, Clone)]
+pub struct OffsetMap {
+    pub path: PathBuf,
+    pub file_size: u64,
+    pub regions: Vec<Region>,
+}
+
+/// Where an offset falls within a file, and what a file cut off there would be missing
+#[derive(Debug, Clone)]
+pub struct Location<'a> {
+    /// None past the end of the file's regions
+    pub region: Option<&'a Region>,
+    /// Bytes of the region before the offset
+    pub present: u64,
+    /// Tensors starting at or after the offset, and their bytes
+    pub later_tensors: usize,
+    pub later_bytes: u64,
+}
+
+impl OffsetMap {
+    pub fn tensor_count(&self) -> usize {
+        self.regions
+            .iter()
+            .filter(|r| r.kind == RegionKind::Tensor)
+            .count()
+    }
+
+    /// Tensors that extend past the end of the file, i.e. were cut off
+    pub fn truncated(&self) -> impl Iterator<Item = &Region> {
+        self.regions
+            .iter()
+            .filter(|r| r.kind == RegionKind::Tensor && r.end > self.file_size)
+    }
+
+    /// Index of the region holding `offset`
+    pub fn region_index(&self, offset: u64) -> Option<usize> {
+        self.regions
+            .iter()
+            .position(|r| r.start <= offset && offset < r.end)
+    }
+
+    pub fn locate(&self, offset: u64) -> Location<'_> {
+        let region = self.region_index(offset).map(|i| &self.regions[i]);
+        let later = self
+            .regions
+            .iter()
+            .filter(|r| r.kind == RegionKind::Tensor && r.start >= offset);
+        Location {
+            region,
+            present: region.map_or(0, |r| offset - r.start),
+            later_tensors: later.clone().count(),
+            later_bytes: later.map(Region::len).sum(),
+        }
+    }
+}
+
+/// Offset maps of every loaded file with tensor offsets, in load order, including the
+/// tensors an incomplete file cuts off. GGUF files split their header at the end of the
+/// tensor records; safetensors headers end after the length prefix and JSON; other formats
+/// count everything before the first tensor.
+pub fn offset_maps(model: &ModelDescription) -> Vec<OffsetMap> {
+    model
+        .files
+        .iter()
+        .filter_map(|record| {
+            let mut tensors: Vec<(u64, u64, &str)> = model
+                .tensors
+                .iter()
+                .chain(&model.cut_off_tensors)
+                .filter(|t| t.source_file == record.path)
+                .filter_map(|t| {
+                    let start = t.data_offset?;
+                    Some((start, start + t.size_bytes as u64, t.name.as_str()))
+                })
+                .collect();
+            if tensors.is_empty() {
+                return None;
+            }
+            tensors.sort();
+            let layout = model
+                .gguf_layouts
+                .iter()
+                .find(|(path, _)| *path == record.path)
+                .map(|(_, layout)| layout);
+            let mut regions = Vec::new();
+            let mut push = |kind, name: &str, start, end| {
+                if end > start {
+                    regions.push(Region {
+                        kind,
+                        name: name.to_string(),
+                        start,
+                        end,
+                    });
+                }
+            };
+            let first = tensors[0].0;
+            let mut cursor = match (layout, record.bytes_read) {
+                (Some(layout), _) => {
+                    push(
+                        RegionKind::Header,
+                        "metadata and tensor records",
+                        0,
+                        layout.tensor_info_end,
+                    );
+                    push(
+                        RegionKind::Padding,
+                        "alignment before the data section",
+                        layout.tensor_info_end,
+                        layout.data_offset,
+                    );
+                    layout.data_offset
+                }
+                (None, Some(header)) if record.format == "SafeTensors" => {
+                    push(
+                        RegionKind::Header,
+                        "length prefix and JSON header",
+                        0,
+                        header,
+                    );
+                    header
+                }
+                _ => {
+                    push(RegionKind::Header, "header", 0, first);
+                    first
+                }
+            };
+            for (start, end, name) in tensors {
+                push(RegionKind::Padding, "padding", cursor, start);
+                push(RegionKind::Tensor, name, start, end);
+                cursor = cursor.max(end);
+            }
+            push(
+                RegionKind::Trailing,
+                "after the last tensor",
+                cursor,
+                record.size,
+            );
+            Some(OffsetMap {
+                path: record.path.clone(),
+                file_size: record.size,
+                regions,
+            })
+        })
+        .collect()
+}
+
+/// `--offset-of`: the region holding one offset in each file
+#[derive(Debug, Clone, Serialize)]
+pub struct OffsetReport {
+    pub offset: u64,
+    pub files: Vec<OffsetHit>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OffsetHit {
+    pub path: PathBuf,
+    pub file_size: u64,
+    /// None when the offset is past the end of the file
+    pub region: Option<Region>,
+    pub present_bytes: u64,
+    pub later_tensors: usize,
+    pub later_bytes: u64,
+}
+
+pub fn offset_report(maps: &[OffsetMap], offset: u64) -> OffsetReport {
+    let files = maps
+        .iter()
+        .map(|map| {
+            let location = map.locate(offset);
+            OffsetHit {
+                path: map.path.clone(),
+                file_size: map.file_size,
+                region: location.region.cloned(),
+                present_bytes: location.present,
+                later_tensors: location.later_tensors,
+                later_bytes: location.later_bytes,
+            }
+        })
+        .collect();
+    OffsetReport { offset, files }
+}
+
+/// One line on what holds `offset` in a file, e.g. `tensor blk.3.attn_q.weight
+/// [1,024..5,120): 2,048 of 4,096 bytes (50.0%) before the offset`
+pub fn describe_location(path: &Path, offset: u64, location: &Location, file_size: u64) -> String {
+    let Some(region) = location.region else {
+        return format!(
+            "{}: offset {} is past the end of the file ({} bytes)",
+            display_file_name(path),
+            format_thousands(offset as usize),
+            format_thousands(file_size as usize)
+        );
+    };
+    let mut line = format!(
+        "{}: {} [{}..{}): {} of {} bytes ({:.1}%) before the offset",
+        display_file_name(path),
+        region.describe(),
+        format_thousands(region.start as usize),
+        format_thousands(region.end as usize),
+        format_thousands(location.present as usize),
+        format_thousands(region.len() as usize),
+        location.present as f64 / region.len().max(1) as f64 * 100.0
+    );
+    if location.later_tensors > 0 {
+        line.push_str(&format!(
+            "; {} later tensor{} ({}) {} after it",
+            format_thousands(location.later_tensors),
+            if location.later_tensors == 1 { "" } else { "s" },
+            format_size(location.later_bytes as usize),
+            if location.later_tensors == 1 {
+                "starts"
+            } else {
+                "start"
+            }
+        ));
+    }
+    line
+}
+
+impl Report for OffsetReport {
+    fn text_lines(&self) -> Vec<String> {
+        if self.files.is_empty() {
+            return vec!["No file records tensor offsets".to_string()];
+        }
+        self.files
+            .iter()
+            .map(|hit| {
+                let location = Location {
+                    region: hit.region.as_ref(),
+                    present: hit.present_bytes,
+                    later_tensors: hit.later_tensors,
+                    later_bytes: hit.later_bytes,
+                };
+                describe_location(&hit.path, self.offset, &location, hit.file_size)
+            })
+            .collect()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &[
+            "file",
+            "region",
+            "name",
+            "start",
+            "end",
+            "present_bytes",
+            "later_tensors",
+            "later_bytes",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.files
+            .iter()
+            .map(|hit| {
+                let (kind, name, start, end) = match &hit.region {
+                    Some(r) => (
+                        r.kind.label().to_string(),
+                        r.name.clone(),
+                        r.start.to_string(),
+                        r.end.to_string(),
+                    ),
+                    None => (
+                        "past_end".to_string(),
+                        String::new(),
+                        String::new(),
+                        String::new(),
+                    ),
+                };
+                vec![
+                    hit.path.display().to_string(),
+                    kind,
+                    name,
+                    start,
+                    end,
+                    hit.present_bytes.to_string(),
+                    hit.later_tensors.to_string(),
+                    hit.later_bytes.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
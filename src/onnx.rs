@@ -0,0 +1,372 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use crate::gguf::GGUFValue;
+use crate::tree::TensorInfo;
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+/// Strings longer than this (doc strings, embedded blobs) are skipped rather than read
+const MAX_STRING_LEN: u64 = 64 * 1024;
+
+/// Initializers and model-level metadata of an ONNX file
+pub struct OnnxModel {
+    pub tensors: Vec<TensorInfo>,
+    pub metadata: Vec<(String, GGUFValue)>,
+}
+
+/// Minimal protobuf wire-format reader that seeks over payloads it doesn't need,
+/// so raw tensor data in multi-gigabyte models is never read
+struct ProtoReader {
+    inner: BufReader<File>,
+    pos: u64,
+    file_len: u64,
+}
+
+impl ProtoReader {
+    /// Offset where `len` bytes starting at the current position end, which must be within
+    /// the file
+    fn end_of(&self, len: u64) -> Result<u64> {
+        match self.pos.checked_add(len) {
+            Some(end) if end <= self.file_len => Ok(end),
+            _ => bail!(
+                "{len}-byte field at offset {} runs past the end of the {}-byte file",
+                self.pos,
+                self.file_len
+            ),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        self.inner.read_exact(&mut buf)?;
+        self.pos += 1;
+        Ok(buf[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("Malformed protobuf varint at offset {}", self.pos)
+    }
+
+    /// Next field number and wire type, or None at the end of the enclosing message
+    fn key(&mut self, end: u64) -> Result<Option<(u64, u8)>> {
+        if self.pos >= end {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        Ok(Some((key >> 3, (key & 7) as u8)))
+    }
+
+    fn skip_bytes(&mut self, len: u64) -> Result<()> {
+        let end = self.end_of(len)?;
+        self.inner.seek_relative(len as i64)?;
+        self.pos = end;
+        Ok(())
+    }
+
+    fn skip(&mut self, wire_type: u8) -> Result<()> {
+        match wire_type {
+            WIRE_VARINT => {
+                self.varint()?;
+            }
+            WIRE_FIXED64 => self.skip_bytes(8)?,
+            WIRE_LEN => {
+                let len = self.varint()?;
+                self.skip_bytes(len)?;
+            }
+            WIRE_FIXED32 => self.skip_bytes(4)?,
+            other => bail!(
+                "Unsupported protobuf wire type {other} at offset {}",
+                self.pos
+            ),
+        }
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.varint()?;
+        if len > MAX_STRING_LEN {
+            self.skip_bytes(len)?;
+            return Ok(format!("<{len} bytes>"));
+        }
+        let mut buf = vec![0u8; len as usize];
+        self.inner.read_exact(&mut buf)?;
+        self.pos += len;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Start of a nested message; returns its end offset
+    fn message_end(&mut self) -> Result<u64> {
+        let len = self.varint()?;
+        self.end_of(len)
+    }
+
+    /// Integers of a repeated int64 field, which may or may not be packed
+    fn repeated_varint(&mut self, wire_type: u8, out: &mut Vec<i64>) -> Result<()> {
+        if wire_type == WIRE_LEN {
+            let end = self.message_end()?;
+            while self.pos < end {
+                out.push(self.varint()? as i64);
+            }
+        } else {
+            out.push(self.varint()? as i64);
+        }
+        Ok(())
+    }
+
+    /// `StringStringEntryProto { key = 1, value = 2 }`
+    fn string_pair(&mut self) -> Result<(String, String)> {
+        let end = self.message_end()?;
+        let (mut key, mut value) = (String::new(), String::new());
+        while let Some((field, wire_type)) = self.key(end)? {
+            match (field, wire_type) {
+                (1, WIRE_LEN) => key = self.string()?,
+                (2, WIRE_LEN) => value = self.string()?,
+                _ => self.skip(wire_type)?,
+            }
+        }
+        Ok((key, value))
+    }
+}
+
+/// Name and element size of a `TensorProto.DataType`, using the safetensors dtype names
+fn onnx_dtype(data_type: i64) -> (String, f32) {
+    let (name, size) = match data_type {
+        1 => ("F32", 4.0),
+        2 => ("U8", 1.0),
+        3 => ("I8", 1.0),
+        4 => ("U16", 2.0),
+        5 => ("I16", 2.0),
+        6 => ("I32", 4.0),
+        7 => ("I64", 8.0),
+        8 => ("STRING", 0.0),
+        9 => ("BOOL", 1.0),
+        10 => ("F16", 2.0),
+        11 => ("F64", 8.0),
+        12 => ("U32", 4.0),
+        13 => ("U64", 8.0),
+        14 => ("C64", 8.0),
+        15 => ("C128", 16.0),
+        16 => ("BF16", 2.0),
+        17 => ("F8_E4M3", 1.0),
+        18 => ("F8_E4M3FNUZ", 1.0),
+        19 => ("F8_E5M2", 1.0),
+        20 => ("F8_E5M2FNUZ", 1.0),
+        21 => ("U4", 0.5),
+        22 => ("I4", 0.5),
+        23 => ("F4_E2M1", 0.5),
+        other => return (format!("onnx_type_{other}"), 0.0),
+    };
+    (name.to_string(), size)
+}
+
+struct Initializer {
+    name: String,
+    dims: Vec<i64>,
+    data_type: i64,
+    /// Offset and length of an inline `raw_data` payload
+    raw_data: Option<(u64, u64)>,
+    external: Vec<(String, String)>,
+}
+
+fn read_initializer(reader: &mut ProtoReader) -> Result<Initializer> {
+    let end = reader.message_end()?;
+    let mut init = Initializer {
+        name: String::new(),
+        dims: Vec::new(),
+        data_type: 0,
+        raw_data: None,
+        external: Vec::new(),
+    };
+    while let Some((field, wire_type)) = reader.key(end)? {
+        match (field, wire_type) {
+            (1, _) => reader.repeated_varint(wire_type, &mut init.dims)?,
+            (2, WIRE_VARINT) => init.data_type = reader.varint()? as i64,
+            (8, WIRE_LEN) => init.name = reader.string()?,
+            (9, WIRE_LEN) => {
+                let len = reader.varint()?;
+                init.raw_data = Some((reader.pos, len));
+                reader.skip_bytes(len)?;
+            }
+            (13, WIRE_LEN) => init.external.push(reader.string_pair()?),
+            _ => reader.skip(wire_type)?,
+        }
+    }
+    Ok(init)
+}
+
+fn initializer_tensor(init: Initializer, path: &Path) -> Result<TensorInfo> {
+    let (dtype, elem_size) = onnx_dtype(init.data_type);
+    let shape: Vec<usize> = init.dims.iter().map(|&d| d.max(0) as usize).collect();
+    let Some(num_elements) = shape
+        .iter()
+        .try_fold(1usize, |product, &dim| product.checked_mul(dim))
+    else {
+        bail!(
+            "Initializer {} has dims {:?}, too large to count its elements",
+            init.name,
+            init.dims
+        );
+    };
+    let mut size_bytes = (num_elements as f64 * elem_size as f64).ceil() as usize;
+    let mut source_file = path.to_path_buf();
+    let mut data_offset = init.raw_data.map(|(offset, _)| offset);
+    let mut notes = Vec::new();
+
+    if let Some((_, len)) = init.raw_data {
+        size_bytes = len as usize;
+    } else if !init.external.is_empty() {
+        let get = |key: &str| {
+            init.external
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        let location = get("location").unwrap_or_default();
+        let offset = get("offset").and_then(|o| o.parse().ok()).unwrap_or(0u64);
+        if let Some(length) = get("length").and_then(|l| l.parse::<usize>().ok()) {
+            if offset.checked_add(length as u64).is_none() {
+                bail!(
+                    "Initializer {}'s {length} bytes of external data at offset {offset} end past the largest file offset",
+                    init.name
+                );
+            }
+            size_bytes = length;
+        }
+        // External paths are relative to the directory holding the .onnx file
+        source_file = path.parent().unwrap_or(Path::new("")).join(location);
+        data_offset = Some(offset);
+        notes.push(format!("External data: {location} @ offset {offset}"));
+    } else if num_elements > 0 {
+        // Values stored in typed repeated fields (float_data, int64_data, ...) are not mapped
+        notes.push("Data stored in typed protobuf fields".to_string());
+    }
+
+    Ok(TensorInfo {
+        name: init.name,
+        dtype,
+        shape,
+        size_bytes,
+        num_elements,
+        source_file,
+        data_offset,
+        quant: None,
+        notes,
+        storage_shape: None,
+    })
+}
+
+/// `GraphProto`: only the name and initializers are read
+fn read_graph(reader: &mut ProtoReader, path: &Path, model: &mut OnnxModel) -> Result<()> {
+    let end = reader.message_end()?;
+    while let Some((field, wire_type)) = reader.key(end)? {
+        match (field, wire_type) {
+            (2, WIRE_LEN) => model.metadata.push((
+                "onnx.graph_name".to_string(),
+                GGUFValue::String(reader.string()?),
+            )),
+            (5, WIRE_LEN) => {
+                let init = read_initializer(reader)?;
+                model.tensors.push(initializer_tensor(init, path)?);
+            }
+            _ => reader.skip(wire_type)?,
+        }
+    }
+    Ok(())
+}
+
+/// `OperatorSetIdProto { domain = 1, version = 2 }`
+fn read_opset(reader: &mut ProtoReader) -> Result<(String, i64)> {
+    let end = reader.message_end()?;
+    let (mut domain, mut version) = (String::new(), 0);
+    while let Some((field, wire_type)) = reader.key(end)? {
+        match (field, wire_type) {
+            (1, WIRE_LEN) => domain = reader.string()?,
+            (2, WIRE_VARINT) => version = reader.varint()? as i64,
+            _ => reader.skip(wire_type)?,
+        }
+    }
+    if domain.is_empty() {
+        domain = "ai.onnx".to_string();
+    }
+    Ok((domain, version))
+}
+
+/// `ModelProto`, the top-level message of the file
+fn read_model(
+    reader: &mut ProtoReader,
+    path: &Path,
+    end: u64,
+    model: &mut OnnxModel,
+) -> Result<()> {
+    while let Some((field, wire_type)) = reader.key(end)? {
+        match (field, wire_type) {
+            (1, WIRE_VARINT) => model.metadata.push((
+                "onnx.ir_version".to_string(),
+                GGUFValue::I64(reader.varint()? as i64),
+            )),
+            (2..=4 | 6, WIRE_LEN) => {
+                let key = match field {
+                    2 => "onnx.producer_name",
+                    3 => "onnx.producer_version",
+                    4 => "onnx.domain",
+                    _ => "onnx.doc_string",
+                };
+                model
+                    .metadata
+                    .push((key.to_string(), GGUFValue::String(reader.string()?)));
+            }
+            (5, WIRE_VARINT) => model.metadata.push((
+                "onnx.model_version".to_string(),
+                GGUFValue::I64(reader.varint()? as i64),
+            )),
+            (7, WIRE_LEN) => read_graph(reader, path, model)?,
+            (8, WIRE_LEN) => {
+                let (domain, version) = read_opset(reader)?;
+                model
+                    .metadata
+                    .push((format!("onnx.opset.{domain}"), GGUFValue::I64(version)));
+            }
+            (14, WIRE_LEN) => {
+                let (key, value) = reader.string_pair()?;
+                model.metadata.push((key, GGUFValue::String(value)));
+            }
+            _ => reader.skip(wire_type)?,
+        }
+    }
+    Ok(())
+}
+
+/// List graph initializers and model metadata of an ONNX model without loading weight data
+pub fn read_onnx(path: &Path) -> Result<OnnxModel> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let file_len = file.metadata()?.len();
+    let mut reader = ProtoReader {
+        inner: BufReader::new(file),
+        pos: 0,
+        file_len,
+    };
+    let mut model = OnnxModel {
+        tensors: Vec::new(),
+        metadata: Vec::new(),
+    };
+    read_model(&mut reader, path, file_len, &mut model)
+        .with_context(|| format!("Failed to parse ONNX file: {}", path.display()))?;
+    Ok(model)
+}
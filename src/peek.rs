@@ -0,0 +1,378 @@
+//! One element of a tensor read straight from disk, for questions like "what is
+//! `embed_tokens[151645][0]`". The byte offset comes from the shape, the element size and
+//! the row-major layout, and only the element's bytes are read; for GGUF quantized types,
+//! the block holding the element is read and only that element dequantized.
+
+use anyhow::{Context, Result, bail};
+
+use crate::dtype::{self, DtypeKind};
+use crate::gguf::GGMLType;
+use crate::npy::FORTRAN_ORDER_NOTE;
+use crate::scan::{f16_to_f32, read_tensor_range};
+use crate::tree::TensorInfo;
+use crate::utils::{display_file_name, format_shape, format_thousands};
+
+/// An element read from a tensor, with where it was found
+#[derive(Debug, Clone)]
+pub struct Peek {
+    /// Index of the element in each dimension
+    pub coords: Vec<usize>,
+    /// Index of the element in the tensor's data
+    pub flat: usize,
+    /// File offset and length of the bytes read
+    pub offset: u64,
+    pub len: usize,
+    pub value: String,
+    /// The element's bits in hex, most significant byte first, for fixed-width dtypes
+    pub raw: Option<String>,
+    /// Block holding the element and its position in it, for quantized types
+    pub block: Option<(usize, usize)>,
+}
+
+/// Index of a `--peek` spec or the detail view prompt: `151645,0`, `[151645][0]` or
+/// `151645 0`. Negative indices count from the end, as in Python.
+pub fn parse_index(text: &str) -> Result<Vec<i64>> {
+    text.split(|c: char| matches!(c, ',' | '[' | ']') || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.replace('_', "")
+                .parse::<i64>()
+                .with_context(|| format!("\"{part}\" is not an index"))
+        })
+        .collect()
+}
+
+/// Tensor name and index of `name[151645,0]`
+pub fn parse_spec(spec: &str) -> Result<(&str, Vec<i64>)> {
+    let Some((name, index)) = spec.split_once('[') else {
+        bail!("expected TENSOR[INDEX], e.g. model.embed_tokens.weight[151645,0]; got \"{spec}\"");
+    };
+    if !index.trim_end().ends_with(']') {
+        bail!("\"{spec}\" is missing the closing ]");
+    }
+    Ok((name.trim(), parse_index(&format!("[{index}"))?))
+}
+
+/// Position of an index counted from the end when negative, if within `len`
+fn wrap(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+/// Index in each dimension and flat index of the element `index` names: one index per
+/// dimension, or a single flat index
+pub fn resolve(shape: &[usize], index: &[i64]) -> Result<(Vec<usize>, usize)> {
+    let elements: usize = shape.iter().product();
+    if index.len() == shape.len() {
+        let mut coords = Vec::with_capacity(shape.len());
+        for (dim, (&i, &size)) in index.iter().zip(shape).enumerate() {
+            let Some(position) = wrap(i, size) else {
+                bail!(
+                    "Index {i} is out of range for dimension {dim} of shape {}: valid indices are 0 to {} (or -{size} to -1)",
+                    format_shape(shape),
+                    size as i64 - 1
+                );
+            };
+            coords.push(position);
+        }
+        let flat = coords
+            .iter()
+            .zip(shape)
+            .fold(0, |flat, (&i, &size)| flat * size + i);
+        return Ok((coords, flat));
+    }
+    if let [i] = index {
+        let Some(flat) = wrap(*i, elements) else {
+            bail!(
+                "Flat index {i} is out of range: the tensor has {} elements, valid indices are 0 to {}",
+                format_thousands(elements),
+                format_thousands(elements.saturating_sub(1))
+            );
+        };
+        let mut coords = vec![0; shape.len()];
+        let mut rest = flat;
+        for (coord, &size) in coords.iter_mut().zip(shape).rev() {
+            *coord = rest % size;
+            rest /= size;
+        }
+        return Ok((coords, flat));
+    }
+    bail!(
+        "Shape {} has {} dimensions but {} indices were given; give one per dimension or a single flat index",
+        format_shape(shape),
+        shape.len(),
+        index.len()
+    )
+}
+
+/// Where the element at `coords` is stored, counted in elements: its row-major flat index,
+/// or the column-major one for Fortran-ordered NumPy arrays
+fn storage_index(tensor: &TensorInfo, coords: &[usize], flat: usize) -> usize {
+    if !tensor.notes.iter().any(|note| note == FORTRAN_ORDER_NOTE) {
+        return flat;
+    }
+    coords
+        .iter()
+        .zip(&tensor.shape)
+        .rev()
+        .fold(0, |index, (&i, &size)| index * size + i)
+}
+
+/// Decode an IEEE-style float of `exponent` and `mantissa` bits. `finite` is the
+/// OCP FP8 E4M3 convention, which has no infinities and one NaN per sign.
+fn decode_minifloat(bits: u64, exponent: u32, mantissa: u32, finite: bool) -> f64 {
+    let sign = if (bits >> (exponent + mantissa)) & 1 == 1 {
+        -1.0
+    } else {
+        1.0
+    };
+    let e = ((bits >> mantissa) & ((1 << exponent) - 1)) as i32;
+    let m = (bits & ((1 << mantissa) - 1)) as f64;
+    let max_e = (1 << exponent) - 1;
+    let bias = (1 << (exponent - 1)) - 1;
+    let scale = (1u64 << mantissa) as f64;
+    if finite && e == max_e && m as u64 == (1 << mantissa) - 1 {
+        return f64::NAN;
+    }
+    if !finite && e == max_e {
+        return if m == 0.0 {
+            sign * f64::INFINITY
+        } else {
+            f64::NAN
+        };
+    }
+    if e == 0 {
+        sign * m / scale * 2f64.powi(1 - bias)
+    } else {
+        sign * (1.0 + m / scale) * 2f64.powi(e - bias)
+    }
+}
+
+/// A fixed-width element as text, from its little-endian bytes
+fn decode_fixed(name: &str, bytes: &[u8]) -> Result<String> {
+    let Some(info) = dtype::dtype_info(name) else {
+        bail!("Reading single values of dtype {name} is not supported");
+    };
+    let mut padded = [0u8; 8];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    let bits = u64::from_le_bytes(padded);
+    Ok(match (name, info.kind) {
+        (_, DtypeKind::Bool) => (bits != 0).to_string(),
+        (_, DtypeKind::Unsigned) => bits.to_string(),
+        // Sign-extend from the element's width
+        (_, DtypeKind::Signed) => {
+            let shift = 64 - info.bits;
+            (((bits << shift) as i64) >> shift).to_string()
+        }
+        ("F16", _) => f16_to_f32(bits as u16).to_string(),
+        ("BF16", _) => f32::from_bits((bits as u32) << 16).to_string(),
+        ("F32", _) => f32::from_bits(bits as u32).to_string(),
+        ("F64", _) => f64::from_bits(bits).to_string(),
+        (_, DtypeKind::Float { exponent, mantissa }) => {
+            (decode_minifloat(bits, exponent, mantissa, name == "F8_E4M3") as f32).to_string()
+        }
+    })
+}
+
+fn half(bytes: &[u8], at: usize) -> f32 {
+    f16_to_f32(u16::from_le_bytes([bytes[at], bytes[at + 1]]))
+}
+
+/// Scale and minimum of sub-block `j` of a Q4_K or Q5_K block, packed 6 bits each in
+/// `scales` (ggml's `get_scale_min_k4`)
+fn scale_min_k4(j: usize, scales: &[u8]) -> (f32, f32) {
+    if j < 4 {
+        ((scales[j] & 63) as f32, (scales[j + 4] & 63) as f32)
+    } else {
+        (
+            ((scales[j + 4] & 0xF) | ((scales[j - 4] >> 6) << 4)) as f32,
+            ((scales[j + 4] >> 4) | ((scales[j] >> 6) << 4)) as f32,
+        )
+    }
+}
+
+/// The non-uniform 4-bit levels of IQ4_NL
+const IQ4_NL_LEVELS: [i8; 16] = [
+    -127, -104, -83, -65, -49, -35, -22, -10, 1, 13, 25, 38, 53, 69, 89, 113,
+];
+
+/// Element `i` of one quantization block, as ggml's `dequantize_row_*` computes it; None for
+/// types whose layout isn't decoded here
+fn dequantize(ty: GGMLType, b: &[u8], i: usize) -> Option<f32> {
+    // The 32-element formats keep element j in the low nibble of byte j and element j + 16
+    // in the high nibble
+    let nibble = |qs: &[u8]| -> u8 { if i < 16 { qs[i] & 0xF } else { qs[i - 16] >> 4 } };
+    Some(match ty {
+        GGMLType::Q4_0 => half(b, 0) * (nibble(&b[2..]) as f32 - 8.0),
+        GGMLType::Q4_1 => half(b, 0) * nibble(&b[4..]) as f32 + half(b, 2),
+        GGMLType::Q5_0 | GGMLType::Q5_1 => {
+            let (qh, qs) = if ty == GGMLType::Q5_0 {
+                (&b[2..6], &b[6..])
+            } else {
+                (&b[4..8], &b[8..])
+            };
+            let qh = u32::from_le_bytes(qh.try_into().ok()?);
+            let q = (nibble(qs) | ((((qh >> i) & 1) as u8) << 4)) as f32;
+            if ty == GGMLType::Q5_0 {
+                half(b, 0) * (q - 16.0)
+            } else {
+                half(b, 0) * q + half(b, 2)
+            }
+        }
+        GGMLType::Q8_0 | GGMLType::Q8_1 => {
+            let qs = if ty == GGMLType::Q8_0 { 2 } else { 4 };
+            half(b, 0) * b[qs + i] as i8 as f32
+        }
+        GGMLType::IQ4_NL => half(b, 0) * IQ4_NL_LEVELS[nibble(&b[2..]) as usize] as f32,
+        GGMLType::Q2_K => {
+            let (scales, qs) = (&b[..16], &b[16..80]);
+            let (d, dmin) = (half(b, 80), half(b, 82));
+            let (n, r) = (i / 128, i % 128);
+            let (j, l) = (r / 32, r % 32);
+            let scale = scales[n * 8 + j * 2 + l / 16];
+            let q = (qs[n * 32 + l] >> (2 * j)) & 3;
+            d * (scale & 0xF) as f32 * q as f32 - dmin * (scale >> 4) as f32
+        }
+        GGMLType::Q3_K => {
+            let (hmask, qs, packed) = (&b[..32], &b[32..96], &b[96..108]);
+            let d = half(b, 108);
+            let (n, r) = (i / 128, i % 128);
+            let (j, l) = (r / 32, r % 32);
+            let is = n * 8 + j * 2 + l / 16;
+            // 16 six-bit scales: low 4 bits from the first 8 bytes, high 2 from the last 4
+            let low = if is < 8 {
+                packed[is] & 0xF
+            } else {
+                packed[is - 8] >> 4
+            };
+            let high = (packed[8 + is % 4] >> (2 * (is / 4))) & 3;
+            let scale = (low | (high << 4)) as i32 - 32;
+            let q = ((qs[n * 32 + l] >> (2 * j)) & 3) as i32;
+            let q = if hmask[l] & (1 << (n * 4 + j)) != 0 {
+                q
+            } else {
+                q - 4
+            };
+            d * scale as f32 * q as f32
+        }
+        GGMLType::Q4_K | GGMLType::Q5_K => {
+            let (d, dmin) = (half(b, 0), half(b, 2));
+            let scales = &b[4..16];
+            let (c, r) = (i / 64, i % 64);
+            let (sub, l) = (r / 32, r % 32);
+            let (scale, min) = scale_min_k4(2 * c + sub, scales);
+            let q = if ty == GGMLType::Q4_K {
+                let byte = b[16 + c * 32 + l];
+                if sub == 0 { byte & 0xF } else { byte >> 4 }
+            } else {
+                let (qh, qs) = (&b[16..48], &b[48..176]);
+                let byte = qs[c * 32 + l];
+                let low = if sub == 0 { byte & 0xF } else { byte >> 4 };
+                let high = (qh[l] >> (2 * c + sub)) & 1;
+                low | (high << 4)
+            };
+            d * scale * q as f32 - dmin * min
+        }
+        GGMLType::Q6_K => {
+            let (ql, qh, scales) = (&b[..128], &b[128..192], &b[192..208]);
+            let d = half(b, 208);
+            let (n, r) = (i / 128, i % 128);
+            let (part, l) = (r / 32, r % 32);
+            let low = ql[n * 64 + l + 32 * (part % 2)];
+            let low = if part < 2 { low & 0xF } else { low >> 4 };
+            let high = (qh[n * 32 + l] >> (2 * part)) & 3;
+            let q = (low | (high << 4)) as i32 - 32;
+            let scale = scales[n * 8 + l / 16 + 2 * part] as i8;
+            d * scale as f32 * q as f32
+        }
+        _ => return None,
+    })
+}
+
+/// Read the element `index` names from disk
+pub fn peek(tensor: &TensorInfo, index: &[i64]) -> Result<Peek> {
+    let (coords, flat) = resolve(&tensor.shape, index)?;
+    let stored = storage_index(tensor, &coords, flat);
+    let base = tensor.data_location()?;
+
+    // NumPy's big-endian dtypes are the canonical ones with their bytes reversed
+    let (name, big_endian) = match tensor.dtype.strip_suffix("_BE") {
+        Some(name) => (name, true),
+        None => (tensor.dtype.as_str(), false),
+    };
+    if let Some(size) = dtype::dtype_info(name).map(|info| info.bits as usize / 8) {
+        let mut bytes = read_tensor_range(tensor, stored * size, size)?;
+        if big_endian {
+            bytes.reverse();
+        }
+        return Ok(Peek {
+            coords,
+            flat,
+            offset: base + (stored * size) as u64,
+            len: size,
+            value: decode_fixed(name, &bytes)?,
+            raw: Some(bytes.iter().rev().map(|b| format!("{b:02x}")).collect()),
+            block: None,
+        });
+    }
+
+    let Some(ty) = GGMLType::from_name(&tensor.dtype) else {
+        bail!(
+            "Reading single values of dtype {} is not supported",
+            tensor.dtype
+        );
+    };
+    let Some((block_elements, block_bytes)) = ty.block_layout() else {
+        bail!("The block layout of {ty} is unknown");
+    };
+    let (block_elements, block_bytes) = (block_elements as usize, block_bytes as usize);
+    let (block, position) = (stored / block_elements, stored % block_elements);
+    let bytes = read_tensor_range(tensor, block * block_bytes, block_bytes)?;
+    let Some(value) = dequantize(ty, &bytes, position) else {
+        bail!(
+            "Dequantizing {ty} is not supported; the {ty} block holding the element is {block_bytes} bytes at offset {}",
+            base + (block * block_bytes) as u64
+        );
+    };
+    Ok(Peek {
+        coords,
+        flat,
+        offset: base + (block * block_bytes) as u64,
+        len: block_bytes,
+        value: value.to_string(),
+        raw: None,
+        block: Some((block, position)),
+    })
+}
+
+impl Peek {
+    /// `name[151645, 0]`
+    pub fn label(&self, tensor: &TensorInfo) -> String {
+        let coords: Vec<String> = self.coords.iter().map(usize::to_string).collect();
+        format!("{}[{}]", tensor.name, coords.join(", "))
+    }
+
+    /// The value and where it was read, for the detail view
+    pub fn lines(&self, tensor: &TensorInfo) -> Vec<String> {
+        let mut lines = vec![format!("{} = {}", self.label(tensor), self.value)];
+        let file = display_file_name(&tensor.source_file);
+        lines.push(match (&self.raw, self.block) {
+            (Some(raw), _) => format!(
+                "Flat index {}, {} bytes 0x{raw} at offset {} of {file}",
+                format_thousands(self.flat),
+                self.len,
+                format_thousands(self.offset as usize)
+            ),
+            (None, Some((block, position))) => format!(
+                "Flat index {}, element {position} of {} block {}: {} bytes at offset {} of {file}",
+                format_thousands(self.flat),
+                tensor.dtype,
+                format_thousands(block),
+                self.len,
+                format_thousands(self.offset as usize)
+            ),
+            (None, None) => String::new(),
+        });
+        lines
+    }
+}
@@ -0,0 +1,393 @@
+use anyhow::{Result, bail};
+
+/// A value produced by the restricted pickle machine. Calls are recorded, never executed.
+#[derive(Debug, Clone)]
+pub enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    /// Byte strings are only kept by length
+    Bytes(usize),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    /// `module.name` reference pushed by GLOBAL / STACK_GLOBAL
+    Global(String, String),
+    /// Argument of a BINPERSID opcode; torch uses it to reference tensor storages
+    PersistentId(Box<Value>),
+    /// `callable(*args)` from REDUCE or NEWOBJ
+    Call(Box<Value>, Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(v) => Some(*v),
+            Value::Bool(b) => Some(*b as i64),
+            _ => None,
+        }
+    }
+
+    pub fn items(&self) -> Option<&[Value]> {
+        match self {
+            Value::Tuple(items) | Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Name of the callable for globals and recorded calls, e.g. `collections.OrderedDict`
+    pub fn callable_name(&self) -> Option<String> {
+        match self {
+            Value::Global(module, name) => Some(format!("{module}.{name}")),
+            Value::Call(callable, _) => callable.callable_name(),
+            _ => None,
+        }
+    }
+}
+
+/// Calls that are safe to evaluate because they only build plain containers
+fn evaluate_call(callable: Value, args: Vec<Value>) -> Value {
+    match callable.callable_name().as_deref() {
+        Some("collections.OrderedDict" | "builtins.dict" | "__builtin__.dict") => {
+            match args.into_iter().next() {
+                Some(Value::List(pairs)) => Value::Dict(
+                    pairs
+                        .into_iter()
+                        .filter_map(|pair| match pair {
+                            Value::Tuple(mut kv) if kv.len() == 2 => {
+                                let value = kv.pop().unwrap();
+                                Some((kv.pop().unwrap(), value))
+                            }
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+                _ => Value::Dict(Vec::new()),
+            }
+        }
+        _ => Value::Call(Box::new(callable), args),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.pos + len > self.data.len() {
+            bail!("Unexpected end of pickle data");
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn string(&mut self, len: usize) -> Result<String> {
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn line(&mut self) -> Result<String> {
+        let end = self.data[self.pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| anyhow::anyhow!("Unterminated pickle text argument"))?;
+        let line = self.string(end)?;
+        self.pos += 1;
+        Ok(line)
+    }
+}
+
+/// Evaluate a pickle stream without importing or calling anything.
+///
+/// Only the opcodes needed to rebuild containers, scalars and call records are supported;
+/// anything else (e.g. the text-based protocol 0 instructions) is reported as an error.
+pub fn parse(data: &[u8]) -> Result<Value> {
+    let mut reader = Reader { data, pos: 0 };
+    let mut stack: Vec<Value> = Vec::new();
+    let mut marks: Vec<usize> = Vec::new();
+    let mut memo: Vec<Option<Value>> = Vec::new();
+
+    fn pop(stack: &mut Vec<Value>) -> Result<Value> {
+        stack
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Pickle stack underflow"))
+    }
+    fn pop_mark(stack: &mut Vec<Value>, marks: &mut Vec<usize>) -> Result<Vec<Value>> {
+        let mark = marks
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Pickle MARK missing"))?;
+        if mark > stack.len() {
+            bail!("Pickle stack underflow");
+        }
+        Ok(stack.split_off(mark))
+    }
+    fn memo_put(memo: &mut Vec<Option<Value>>, idx: usize, value: Value) {
+        if memo.len() <= idx {
+            memo.resize(idx + 1, None);
+        }
+        memo[idx] = Some(value);
+    }
+    fn memo_get(memo: &[Option<Value>], idx: usize) -> Result<Value> {
+        memo.get(idx)
+            .cloned()
+            .flatten()
+            .ok_or_else(|| anyhow::anyhow!("Pickle memo key {idx} missing"))
+    }
+    fn set_items(target: &mut Value, items: Vec<Value>) {
+        if let Value::Dict(entries) = target {
+            let mut items = items.into_iter();
+            while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                entries.push((key, value));
+            }
+        }
+    }
+
+    loop {
+        let opcode = reader.u8()?;
+        match opcode {
+            // PROTO
+            0x80 => {
+                reader.u8()?;
+            }
+            // FRAME
+            0x95 => {
+                reader.u64()?;
+            }
+            // STOP
+            b'.' => return pop(&mut stack),
+            // MARK
+            b'(' => marks.push(stack.len()),
+            // POP
+            b'0' => {
+                pop(&mut stack)?;
+            }
+            // POP_MARK
+            b'1' => {
+                pop_mark(&mut stack, &mut marks)?;
+            }
+            // DUP
+            b'2' => {
+                let top = stack.last().cloned().unwrap_or(Value::None);
+                stack.push(top);
+            }
+            // NONE, NEWTRUE, NEWFALSE
+            b'N' => stack.push(Value::None),
+            0x88 => stack.push(Value::Bool(true)),
+            0x89 => stack.push(Value::Bool(false)),
+            // BININT, BININT1, BININT2
+            b'J' => stack.push(Value::Int(reader.u32()? as i32 as i64)),
+            b'K' => stack.push(Value::Int(reader.u8()? as i64)),
+            b'M' => stack.push(Value::Int(reader.u16()? as i64)),
+            // LONG1, LONG4: little-endian two's complement
+            0x8a | 0x8b => {
+                let len = if opcode == 0x8a {
+                    reader.u8()? as usize
+                } else {
+                    reader.u32()? as usize
+                };
+                let bytes = reader.take(len)?;
+                let mut value: i64 = 0;
+                for (i, &b) in bytes.iter().take(8).enumerate() {
+                    value |= (b as i64) << (8 * i);
+                }
+                if (1..8).contains(&len) && bytes[len - 1] & 0x80 != 0 {
+                    value -= 1i64 << (8 * len);
+                }
+                stack.push(Value::Int(value));
+            }
+            // BINFLOAT (big-endian)
+            b'G' => stack.push(Value::Float(f64::from_be_bytes(
+                reader.take(8)?.try_into()?,
+            ))),
+            // SHORT_BINUNICODE, BINUNICODE, BINUNICODE8, SHORT_BINSTRING, BINSTRING
+            0x8c | b'U' => {
+                let len = reader.u8()? as usize;
+                stack.push(Value::Str(reader.string(len)?));
+            }
+            b'X' | b'T' => {
+                let len = reader.u32()? as usize;
+                stack.push(Value::Str(reader.string(len)?));
+            }
+            0x8d => {
+                let len = reader.u64()? as usize;
+                stack.push(Value::Str(reader.string(len)?));
+            }
+            // SHORT_BINBYTES, BINBYTES, BINBYTES8, BYTEARRAY8
+            b'C' => {
+                let len = reader.u8()? as usize;
+                reader.take(len)?;
+                stack.push(Value::Bytes(len));
+            }
+            b'B' => {
+                let len = reader.u32()? as usize;
+                reader.take(len)?;
+                stack.push(Value::Bytes(len));
+            }
+            0x8e | 0x96 => {
+                let len = reader.u64()? as usize;
+                reader.take(len)?;
+                stack.push(Value::Bytes(len));
+            }
+            // EMPTY_TUPLE, TUPLE, TUPLE1..3
+            b')' => stack.push(Value::Tuple(Vec::new())),
+            b't' => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                stack.push(Value::Tuple(items));
+            }
+            0x85..=0x87 => {
+                let count = (opcode - 0x84) as usize;
+                if stack.len() < count {
+                    bail!("Pickle stack underflow");
+                }
+                let items = stack.split_off(stack.len() - count);
+                stack.push(Value::Tuple(items));
+            }
+            // EMPTY_LIST, LIST, APPEND, APPENDS
+            b']' => stack.push(Value::List(Vec::new())),
+            b'l' => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                stack.push(Value::List(items));
+            }
+            b'a' => {
+                let item = pop(&mut stack)?;
+                if let Some(Value::List(list)) = stack.last_mut() {
+                    list.push(item);
+                }
+            }
+            b'e' => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                if let Some(Value::List(list)) = stack.last_mut() {
+                    list.extend(items);
+                }
+            }
+            // EMPTY_DICT, DICT, SETITEM, SETITEMS
+            b'}' => stack.push(Value::Dict(Vec::new())),
+            b'd' => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                let mut dict = Value::Dict(Vec::new());
+                set_items(&mut dict, items);
+                stack.push(dict);
+            }
+            b's' => {
+                let value = pop(&mut stack)?;
+                let key = pop(&mut stack)?;
+                if let Some(target) = stack.last_mut() {
+                    set_items(target, vec![key, value]);
+                }
+            }
+            b'u' => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                if let Some(target) = stack.last_mut() {
+                    set_items(target, items);
+                }
+            }
+            // EMPTY_SET, ADDITEMS, FROZENSET: kept as lists
+            0x8f => stack.push(Value::List(Vec::new())),
+            0x90 => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                if let Some(Value::List(list)) = stack.last_mut() {
+                    list.extend(items);
+                }
+            }
+            0x91 => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                stack.push(Value::List(items));
+            }
+            // GLOBAL, STACK_GLOBAL
+            b'c' => {
+                let module = reader.line()?;
+                let name = reader.line()?;
+                stack.push(Value::Global(module, name));
+            }
+            0x93 => {
+                let name = pop(&mut stack)?;
+                let module = pop(&mut stack)?;
+                match (module, name) {
+                    (Value::Str(module), Value::Str(name)) => {
+                        stack.push(Value::Global(module, name))
+                    }
+                    _ => bail!("STACK_GLOBAL expects two strings"),
+                }
+            }
+            // REDUCE, NEWOBJ, NEWOBJ_EX
+            b'R' | 0x81 => {
+                let args = pop(&mut stack)?;
+                let callable = pop(&mut stack)?;
+                let args = match args {
+                    Value::Tuple(items) => items,
+                    other => vec![other],
+                };
+                stack.push(evaluate_call(callable, args));
+            }
+            0x92 => {
+                pop(&mut stack)?;
+                let args = pop(&mut stack)?;
+                let callable = pop(&mut stack)?;
+                let args = args.items().map(<[Value]>::to_vec).unwrap_or_default();
+                stack.push(evaluate_call(callable, args));
+            }
+            // BUILD: instance state (e.g. a state_dict's `_metadata`) is irrelevant for listing
+            b'b' => {
+                pop(&mut stack)?;
+            }
+            // BINPERSID
+            b'Q' => {
+                let pid = pop(&mut stack)?;
+                stack.push(Value::PersistentId(Box::new(pid)));
+            }
+            // BINPUT, LONG_BINPUT, MEMOIZE
+            b'q' => {
+                let idx = reader.u8()? as usize;
+                memo_put(&mut memo, idx, stack.last().cloned().unwrap_or(Value::None));
+            }
+            b'r' => {
+                let idx = reader.u32()? as usize;
+                memo_put(&mut memo, idx, stack.last().cloned().unwrap_or(Value::None));
+            }
+            0x94 => {
+                let idx = memo.len();
+                memo_put(&mut memo, idx, stack.last().cloned().unwrap_or(Value::None));
+            }
+            // BINGET, LONG_BINGET
+            b'h' => {
+                let idx = reader.u8()? as usize;
+                stack.push(memo_get(&memo, idx)?);
+            }
+            b'j' => {
+                let idx = reader.u32()? as usize;
+                stack.push(memo_get(&memo, idx)?);
+            }
+            other => bail!(
+                "Unsupported pickle opcode 0x{other:02x} at offset {}",
+                reader.pos - 1
+            ),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Expansion presets: the expanded groups saved to a JSON file as a list of name paths
+//! (`[["model", "embed_tokens"], ["lm_head"]]`) and applied to any model, so reviews and
+//! screenshots start from the same view.
+
+use anyhow::{Context, Result};
+use std::{collections::HashSet, fs, path::Path};
+
+/// Write the name paths of the expanded groups to `path`, sorted so preset files diff cleanly
+pub fn save(path: &Path, expanded: &HashSet<Vec<String>>) -> Result<usize> {
+    let mut paths: Vec<&Vec<String>> = expanded.iter().collect();
+    paths.sort();
+    // One group per line, easier to edit by hand than fully pretty-printed JSON
+    let lines = paths
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    let content = match lines.as_slice() {
+        [] => "[]\n".to_string(),
+        lines => format!("[\n  {}\n]\n", lines.join(",\n  ")),
+    };
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(paths.len())
+}
+
+/// The group paths a preset file lists, with the groups above each one so that every
+/// listed group is shown
+pub fn load(path: &Path) -> Result<HashSet<Vec<String>>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read preset {}", path.display()))?;
+    let listed: Vec<Vec<String>> = serde_json::from_str(&content).with_context(|| {
+        format!(
+            "Failed to parse preset {}: expected a JSON list of group paths such as [[\"model\", \"layers\", \"0\"]]",
+            path.display()
+        )
+    })?;
+    let mut paths = HashSet::new();
+    for group in listed {
+        for depth in 1..=group.len() {
+            paths.insert(group[..depth].to_vec());
+        }
+    }
+    Ok(paths)
+}
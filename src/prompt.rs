@@ -0,0 +1,169 @@
+//! Will this prompt fit: a system and user message put through the GGUF's chat template,
+//! counted with its embedded tokenizer and set against the context length.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::report::Report;
+use crate::summary::{metadata_string, rope_from_gguf};
+use crate::template::{Template, Value};
+use crate::tokenizer::BpeTokenizer;
+use crate::tree::MetadataInfo;
+use crate::utils::format_thousands;
+
+/// The tokenizer, chat template and context length of one model, read once and reused for
+/// every prompt counted against it
+#[derive(Debug)]
+pub struct PromptCounter {
+    tokenizer: BpeTokenizer,
+    /// The parsed `tokenizer.chat_template`, or why there isn't one
+    template: Result<Template, String>,
+    context_length: Option<usize>,
+}
+
+/// Token counts of one prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextUsage {
+    /// e.g. `BPE, qwen2 pre-tokenizer, 151,936 tokens`
+    pub tokenizer: String,
+    pub template_applied: bool,
+    /// Why the chat template wasn't applied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_note: Option<String>,
+    pub system_tokens: usize,
+    pub user_tokens: usize,
+    /// Role markers and other text the template adds, BOS included
+    pub template_tokens: usize,
+    pub prompt_tokens: usize,
+    pub context_length: Option<usize>,
+}
+
+impl ContextUsage {
+    /// Whether the prompt fits; true when the context length is unknown
+    pub fn fits(&self) -> bool {
+        self.context_length
+            .is_none_or(|context| self.prompt_tokens <= context)
+    }
+}
+
+impl PromptCounter {
+    /// Fails, naming the reason, when the metadata has no tokenizer this can count with
+    pub fn from_metadata(metadata: &[MetadataInfo]) -> Result<Self> {
+        let tokenizer = BpeTokenizer::from_metadata(metadata)?;
+        let template = match metadata_string(metadata, "tokenizer.chat_template") {
+            Some(source) => Template::parse(&source).map_err(|e| format!("unsupported ({e})")),
+            None => Err("the file has none".to_string()),
+        };
+        Ok(PromptCounter {
+            tokenizer,
+            template,
+            context_length: rope_from_gguf(metadata).and_then(|rope| rope.context_length),
+        })
+    }
+
+    /// The messages rendered as the model would see them, with the generation prompt
+    fn render(&self, system: &str, user: &str) -> Result<String, String> {
+        let template = self.template.as_ref().map_err(Clone::clone)?;
+        let message = |role: &str, content: &str| {
+            Value::map([
+                ("role", Value::from(role)),
+                ("content", Value::from(content)),
+            ])
+        };
+        let mut messages = Vec::new();
+        if !system.is_empty() {
+            messages.push(message("system", system));
+        }
+        messages.push(message("user", user));
+        let token = |token: &Option<String>| Value::from(token.as_deref().unwrap_or_default());
+        template
+            .render(&[
+                ("messages", Value::list(messages)),
+                ("add_generation_prompt", Value::from(true)),
+                ("bos_token", token(&self.tokenizer.bos_token)),
+                ("eos_token", token(&self.tokenizer.eos_token)),
+            ])
+            .map_err(|e| format!("rendering failed ({e})"))
+    }
+
+    pub fn count(&self, system: &str, user: &str) -> ContextUsage {
+        let system_tokens = self.tokenizer.count(system);
+        let user_tokens = self.tokenizer.count(user);
+        let (prompt_tokens, template_note) = match self.render(system, user) {
+            Ok(prompt) => (self.tokenizer.count_prompt(&prompt), None),
+            // Without the template, the messages back to back
+            Err(reason) => (
+                self.tokenizer.count_prompt(&format!("{system}{user}")),
+                Some(reason),
+            ),
+        };
+        ContextUsage {
+            tokenizer: format!(
+                "BPE, {} pre-tokenizer, {} tokens",
+                self.tokenizer.pre,
+                format_thousands(self.tokenizer.vocab_size)
+            ),
+            template_applied: template_note.is_none(),
+            template_note,
+            system_tokens,
+            user_tokens,
+            template_tokens: prompt_tokens.saturating_sub(system_tokens + user_tokens),
+            prompt_tokens,
+            context_length: self.context_length,
+        }
+    }
+}
+
+impl Report for ContextUsage {
+    fn text_lines(&self) -> Vec<String> {
+        let tokens = |count: usize| format!("{} tokens", format_thousands(count));
+        let mut lines = vec![format!("Tokenizer:       {}", self.tokenizer)];
+        lines.push(match &self.template_note {
+            None => "Chat template:   applied, with the generation prompt".to_string(),
+            Some(reason) => {
+                format!("Chat template:   not applied, {reason}; messages counted alone")
+            }
+        });
+        lines.push(String::new());
+        lines.push(format!("System message:  {}", tokens(self.system_tokens)));
+        lines.push(format!("User message:    {}", tokens(self.user_tokens)));
+        lines.push(format!("Template:        {}", tokens(self.template_tokens)));
+        lines.push(format!("Prompt:          {}", tokens(self.prompt_tokens)));
+        match self.context_length {
+            Some(context) => {
+                lines.push(format!("Context length:  {}", tokens(context)));
+                let share = self.prompt_tokens as f64 / context.max(1) as f64 * 100.0;
+                lines.push(if self.fits() {
+                    format!(
+                        "Usage:           {share:.1}%, fits with {} to spare",
+                        tokens(context - self.prompt_tokens)
+                    )
+                } else {
+                    format!(
+                        "Usage:           {share:.1}%, over the context by {}",
+                        tokens(self.prompt_tokens - context)
+                    )
+                });
+            }
+            None => lines.push("Context length:  unknown (no <arch>.context_length)".to_string()),
+        }
+        lines
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["part", "tokens"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let mut rows = vec![
+            vec!["system".to_string(), self.system_tokens.to_string()],
+            vec!["user".to_string(), self.user_tokens.to_string()],
+            vec!["template".to_string(), self.template_tokens.to_string()],
+            vec!["prompt".to_string(), self.prompt_tokens.to_string()],
+        ];
+        if let Some(context) = self.context_length {
+            rows.push(vec!["context".to_string(), context.to_string()]);
+        }
+        rows
+    }
+}
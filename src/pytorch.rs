@@ -0,0 +1,291 @@
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use crate::pickle::{self, Value};
+use crate::scan::element_size;
+use crate::tree::TensorInfo;
+use crate::zip::{self, METHOD_STORED, ZipArchive};
+
+/// Tensor inventory recovered from a `torch.save` zip checkpoint
+pub struct PytorchCheckpoint {
+    pub tensors: Vec<TensorInfo>,
+    /// Non-tensor leaves such as `epoch` or unsummarizable objects, as (key, description)
+    pub entries: Vec<(String, String)>,
+    /// Number of objects that could not be interpreted
+    pub unknown_objects: usize,
+}
+
+/// Map a torch storage class such as `FloatStorage` to the safetensors dtype name
+fn storage_dtype(storage: &str) -> Option<&'static str> {
+    Some(match storage {
+        "FloatStorage" => "F32",
+        "HalfStorage" => "F16",
+        "BFloat16Storage" => "BF16",
+        "DoubleStorage" => "F64",
+        "LongStorage" => "I64",
+        "IntStorage" => "I32",
+        "ShortStorage" => "I16",
+        "CharStorage" => "I8",
+        "ByteStorage" => "U8",
+        "BoolStorage" => "BOOL",
+        "Float8_e4m3fnStorage" => "F8_E4M3",
+        "Float8_e5m2Storage" => "F8_E5M2",
+        _ => return None,
+    })
+}
+
+/// Map a `torch.<dtype>` global (used by newer UntypedStorage checkpoints) to a dtype name
+fn torch_dtype(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "float32" | "float" => "F32",
+        "float16" | "half" => "F16",
+        "bfloat16" => "BF16",
+        "float64" | "double" => "F64",
+        "int64" | "long" => "I64",
+        "int32" | "int" => "I32",
+        "int16" | "short" => "I16",
+        "int8" => "I8",
+        "uint8" => "U8",
+        "bool" => "BOOL",
+        _ => return None,
+    })
+}
+
+/// Storage reference from `persistent_load(('storage', type, key, location, numel))`
+struct StorageRef {
+    dtype: &'static str,
+    key: String,
+}
+
+fn storage_ref(pid: &Value) -> Option<StorageRef> {
+    let items = pid.items()?;
+    if items.first()?.as_str()? != "storage" {
+        return None;
+    }
+    let dtype = match items.get(1)? {
+        Value::Global(module, name) if module == "torch" => {
+            storage_dtype(name).or_else(|| torch_dtype(name))?
+        }
+        _ => return None,
+    };
+    let key = match items.get(2)? {
+        Value::Str(key) => key.clone(),
+        Value::Int(key) => key.to_string(),
+        _ => return None,
+    };
+    Some(StorageRef { dtype, key })
+}
+
+/// Storage, storage offset, shape and stride of `_rebuild_tensor_v2`'s arguments
+fn tensor_args(args: &[Value]) -> Option<(StorageRef, usize, Vec<usize>, Vec<usize>)> {
+    let Value::PersistentId(pid) = args.first()? else {
+        return None;
+    };
+    let storage = storage_ref(pid)?;
+    let storage_offset = args.get(1)?.as_int()? as usize;
+    let shape: Vec<usize> = args
+        .get(2)?
+        .items()?
+        .iter()
+        .map(|d| d.as_int().map(|d| d as usize))
+        .collect::<Option<_>>()?;
+    let stride: Vec<usize> = args
+        .get(3)
+        .and_then(|s| s.items())
+        .map(|s| {
+            s.iter()
+                .filter_map(|d| d.as_int())
+                .map(|d| d as usize)
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((storage, storage_offset, shape, stride))
+}
+
+struct Walker<'a> {
+    archive: &'a ZipArchive,
+    /// Directory prefix of the archive members, e.g. `archive/`
+    prefix: String,
+    path: &'a Path,
+    checkpoint: PytorchCheckpoint,
+}
+
+impl Walker<'_> {
+    /// `torch._utils._rebuild_tensor_v2(storage, offset, size, stride, ...)` and friends;
+    /// None when the arguments aren't a tensor's, an error when their sizes don't add up
+    fn tensor(&self, name: &str, args: &[Value]) -> Result<Option<TensorInfo>> {
+        let Some((storage, storage_offset, shape, stride)) = tensor_args(args) else {
+            return Ok(None);
+        };
+        let elem_size = element_size(storage.dtype).unwrap_or(1);
+        let counts = shape
+            .iter()
+            .try_fold(1usize, |product, &dim| product.checked_mul(dim))
+            .and_then(|num_elements| Some((num_elements, num_elements.checked_mul(elem_size)?)));
+        let Some((num_elements, size_bytes)) = counts else {
+            bail!("shape {shape:?} is too large to count its bytes");
+        };
+        let Some(start) = storage_offset.checked_mul(elem_size) else {
+            bail!("storage offset {storage_offset} is past the largest file offset");
+        };
+
+        // Only row-major views of uncompressed storages can be streamed straight from the file
+        let mut contiguous_stride = vec![1usize; shape.len()];
+        for i in (0..shape.len().saturating_sub(1)).rev() {
+            // Only an empty tensor's trailing dimensions can overflow; any stride suits it
+            contiguous_stride[i] = contiguous_stride[i + 1].saturating_mul(shape[i + 1]);
+        }
+        let mut notes = Vec::new();
+        let contiguous = stride == contiguous_stride || num_elements <= 1;
+        if !contiguous {
+            notes.push(format!("Non-contiguous view (stride {stride:?})"));
+        }
+        let entry = self
+            .archive
+            .find(&format!("{}data/{}", self.prefix, storage.key));
+        if let Some(entry) = entry
+            && contiguous
+            && start
+                .checked_add(size_bytes)
+                .is_none_or(|end| end as u64 > entry.uncompressed_size)
+        {
+            bail!(
+                "{size_bytes} bytes at offset {start} run past the {}-byte storage {}",
+                entry.uncompressed_size,
+                entry.name
+            );
+        }
+        let data_offset = entry
+            .filter(|entry| entry.compression == METHOD_STORED)
+            .filter(|_| contiguous)
+            .and_then(|entry| self.archive.data_offset(entry).ok())
+            .map(|offset| offset + start as u64);
+
+        Ok(Some(TensorInfo {
+            name: name.to_string(),
+            dtype: storage.dtype.to_string(),
+            shape,
+            size_bytes,
+            num_elements,
+            source_file: self.path.to_path_buf(),
+            data_offset,
+            quant: None,
+            notes,
+            storage_shape: None,
+        }))
+    }
+
+    fn walk(&mut self, name: &str, value: &Value) {
+        let child_name = |key: &str| {
+            if name.is_empty() {
+                key.to_string()
+            } else {
+                format!("{name}.{key}")
+            }
+        };
+        match value {
+            Value::Dict(entries) => {
+                for (key, child) in entries {
+                    let key = match key {
+                        Value::Str(s) => s.clone(),
+                        Value::Int(i) => i.to_string(),
+                        other => format!("{other:?}"),
+                    };
+                    self.walk(&child_name(&key), child);
+                }
+            }
+            Value::List(items) | Value::Tuple(items)
+                if items
+                    .iter()
+                    .any(|v| matches!(v, Value::Dict(_) | Value::Call(..))) =>
+            {
+                for (i, child) in items.iter().enumerate() {
+                    self.walk(&child_name(&i.to_string()), child);
+                }
+            }
+            Value::Call(callable, args) => {
+                let callable_name = callable.callable_name().unwrap_or_default();
+                match callable_name.as_str() {
+                    "torch._utils._rebuild_tensor_v2" | "torch._utils._rebuild_tensor" => {
+                        match self.tensor(name, args) {
+                            Ok(Some(tensor)) => self.checkpoint.tensors.push(tensor),
+                            Ok(None) => self.unknown(name, &callable_name),
+                            Err(e) => {
+                                self.checkpoint.unknown_objects += 1;
+                                self.checkpoint
+                                    .entries
+                                    .push((name.to_string(), format!("invalid tensor: {e}")));
+                            }
+                        }
+                    }
+                    // nn.Parameter wraps the tensor as its first argument
+                    "torch._utils._rebuild_parameter"
+                    | "torch._utils._rebuild_parameter_with_state" => match args.first() {
+                        Some(inner) => self.walk(name, inner),
+                        None => self.unknown(name, &callable_name),
+                    },
+                    _ => self.unknown(name, &callable_name),
+                }
+            }
+            Value::None => {}
+            other => {
+                let description = match other {
+                    Value::Bool(b) => b.to_string(),
+                    Value::Int(i) => i.to_string(),
+                    Value::Float(f) => f.to_string(),
+                    Value::Str(s) => format!("\"{s}\""),
+                    Value::Bytes(len) => format!("<{len} bytes>"),
+                    Value::List(items) | Value::Tuple(items) => format!("<{} items>", items.len()),
+                    _ => "unknown object".to_string(),
+                };
+                self.checkpoint
+                    .entries
+                    .push((name.to_string(), description));
+            }
+        }
+    }
+
+    fn unknown(&mut self, name: &str, callable: &str) {
+        self.checkpoint.unknown_objects += 1;
+        let description = if callable.is_empty() {
+            "unknown object".to_string()
+        } else {
+            format!("unknown object ({callable})")
+        };
+        self.checkpoint
+            .entries
+            .push((name.to_string(), description));
+    }
+}
+
+/// List the tensors of a `torch.save` checkpoint (.bin/.pt/.pth) without unpickling it
+pub fn read_checkpoint(path: &Path) -> Result<PytorchCheckpoint> {
+    if !zip::is_zip(path) {
+        bail!(
+            "{} is a legacy (pre-1.6, non-zip) PyTorch checkpoint, which is not supported",
+            path.display()
+        );
+    }
+    let archive = ZipArchive::open(path)?;
+    let pickle_entry = archive
+        .entries
+        .iter()
+        .find(|e| e.name == "data.pkl" || e.name.ends_with("/data.pkl"))
+        .with_context(|| format!("No data.pkl in {}", path.display()))?;
+    let prefix = pickle_entry.name.trim_end_matches("data.pkl").to_string();
+    let root = pickle::parse(&archive.read(pickle_entry)?)
+        .with_context(|| format!("Failed to read {} in {}", pickle_entry.name, path.display()))?;
+
+    let mut walker = Walker {
+        archive: &archive,
+        prefix,
+        path,
+        checkpoint: PytorchCheckpoint {
+            tensors: Vec::new(),
+            entries: Vec::new(),
+            unknown_objects: 0,
+        },
+    };
+    walker.walk("", &root);
+    Ok(walker.checkpoint)
+}
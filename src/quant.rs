@@ -0,0 +1,336 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::tree::TensorInfo;
+use crate::utils::format_shape;
+
+/// Suffixes of the packed tensors GPTQ and AWQ store in place of a linear layer's `weight`
+const QUANT_SUFFIXES: [&str; 4] = ["qweight", "qzeros", "scales", "g_idx"];
+
+/// Bit widths MLX quantizes to; the packed `weight` is U32 whatever the width
+const MLX_BITS: [usize; 6] = [2, 3, 4, 5, 6, 8];
+/// Group sizes MLX supports, its default first
+const MLX_GROUP_SIZES: [usize; 3] = [64, 32, 128];
+
+/// Weight-only quantization of one linear layer, attached to each of its packed tensors
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuantInfo {
+    /// Scheme name, e.g. "GPTQ", "AWQ" or "MLX"
+    pub scheme: String,
+    pub bits: usize,
+    pub group_size: Option<usize>,
+    /// Shape of the dequantized weight, `(out_features, in_features)`, after any expert
+    /// dimension of an MLX `switch_mlp`
+    pub logical_shape: Vec<usize>,
+    /// Bytes of all packed tensors of the module together
+    pub module_bytes: usize,
+}
+
+impl QuantInfo {
+    pub fn logical_elements(&self) -> usize {
+        self.logical_shape.iter().product()
+    }
+
+    /// Whether `name` is the module's packed weight (`qweight`, or `weight` for MLX), which
+    /// stands for the whole module in parameter counts
+    pub fn is_packed_weight(&self, name: &str) -> bool {
+        name.ends_with(if self.scheme == "MLX" {
+            ".weight"
+        } else {
+            ".qweight"
+        })
+    }
+
+    /// The companions counted in `bits_per_weight`
+    fn companions(&self) -> &'static str {
+        if self.scheme == "MLX" {
+            "scales, biases"
+        } else {
+            "zeros, scales, g_idx"
+        }
+    }
+
+    /// Storage bits per logical weight, counting zeros, scales and g_idx (scales and biases
+    /// for MLX)
+    pub fn bits_per_weight(&self) -> f64 {
+        match self.logical_elements() {
+            0 => 0.0,
+            n => (self.module_bytes * 8) as f64 / n as f64,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!(
+            "{} {}-bit → {}",
+            self.scheme,
+            self.bits,
+            format_shape(&self.logical_shape)
+        )
+    }
+
+    pub fn detail_lines(&self, tensor: &TensorInfo) -> Vec<String> {
+        let mut lines = vec![
+            format!("Quantization: {} {}-bit", self.scheme, self.bits),
+            format!("Packed storage shape: {}", format_shape(&tensor.shape)),
+            format!(
+                "Logical weight shape: {}",
+                format_shape(&self.logical_shape)
+            ),
+        ];
+        if let Some(group_size) = self.group_size {
+            lines.push(format!("Group size: {group_size}"));
+        }
+        lines.push(format!(
+            "Effective bits/weight: {:.2} (incl. {})",
+            self.bits_per_weight(),
+            self.companions()
+        ));
+        lines
+    }
+}
+
+/// Model-wide quantization settings, from `quantization_config` or guessed from tensor names
+#[derive(Debug, Clone, Serialize)]
+pub struct QuantSummary {
+    pub scheme: String,
+    pub bits: Option<usize>,
+    pub group_size: Option<usize>,
+    pub modules: usize,
+    pub bits_per_weight: f64,
+}
+
+fn quant_suffix(name: &str) -> Option<(&str, &str)> {
+    let (module, suffix) = name.rsplit_once('.')?;
+    QUANT_SUFFIXES.contains(&suffix).then_some((module, suffix))
+}
+
+fn shape_2d(tensor: &TensorInfo) -> Option<(usize, usize)> {
+    match tensor.shape[..] {
+        [rows, cols] => Some((rows, cols)),
+        _ => None,
+    }
+}
+
+/// Detect GPTQ/AWQ and MLX packed modules, attach `QuantInfo` to their tensors and
+/// summarize the scheme
+pub fn annotate(
+    tensors: &mut [TensorInfo],
+    config: Option<&serde_json::Value>,
+) -> Option<QuantSummary> {
+    let mut annotations = gptq_annotations(tensors, config);
+    let (mlx, mlx_bits, mlx_group_size) = mlx_annotations(tensors, config);
+    annotations.extend(mlx);
+    if annotations.is_empty() {
+        return None;
+    }
+    for (idx, info) in annotations {
+        tensors[idx].quant = Some(info);
+    }
+
+    let weights: Vec<&QuantInfo> = tensors
+        .iter()
+        .filter_map(|t| t.quant.as_ref().filter(|q| q.is_packed_weight(&t.name)))
+        .collect();
+    let logical: usize = weights.iter().map(|q| q.logical_elements()).sum();
+    let stored: usize = weights.iter().map(|q| q.module_bytes).sum();
+    let (config_bits, config_group_size) = if weights[0].scheme == "MLX" {
+        (mlx_bits, mlx_group_size)
+    } else {
+        gptq_settings(config)
+    };
+    Some(QuantSummary {
+        scheme: weights[0].scheme.clone(),
+        bits: config_bits.or(Some(weights[0].bits)),
+        group_size: config_group_size.or(weights[0].group_size),
+        modules: weights.len(),
+        bits_per_weight: (stored * 8) as f64 / logical.max(1) as f64,
+    })
+}
+
+/// `bits` and `group_size` from a GPTQ/AWQ `quantization_config`
+fn gptq_settings(config: Option<&serde_json::Value>) -> (Option<usize>, Option<usize>) {
+    let quant_config = config.and_then(|c| c.get("quantization_config"));
+    let bits = quant_config
+        .and_then(|q| q.get("bits").or_else(|| q.get("w_bit")))
+        .and_then(|b| b.as_u64())
+        .map(|b| b as usize);
+    let group_size = quant_config
+        .and_then(|q| q.get("group_size").or_else(|| q.get("q_group_size")))
+        .and_then(|g| g.as_i64())
+        .and_then(|g| usize::try_from(g).ok());
+    (bits, group_size)
+}
+
+/// GPTQ/AWQ modules: `qweight`, `qzeros` and `scales`, plus `g_idx` for GPTQ
+fn gptq_annotations(
+    tensors: &[TensorInfo],
+    config: Option<&serde_json::Value>,
+) -> Vec<(usize, QuantInfo)> {
+    let quant_config = config.and_then(|c| c.get("quantization_config"));
+    let config_method = quant_config
+        .and_then(|q| q.get("quant_method"))
+        .and_then(|m| m.as_str())
+        .map(str::to_uppercase);
+    let (config_bits, config_group_size) = gptq_settings(config);
+
+    let mut modules: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+    for (idx, tensor) in tensors.iter().enumerate() {
+        if let Some((module, suffix)) = quant_suffix(&tensor.name) {
+            modules.entry(module).or_default().insert(suffix, idx);
+        }
+    }
+
+    let mut annotations = Vec::new();
+    for parts in modules.values() {
+        let (Some(&qweight), Some(&qzeros), Some(&scales)) = (
+            parts.get("qweight"),
+            parts.get("qzeros"),
+            parts.get("scales"),
+        ) else {
+            continue;
+        };
+        let (Some((q_rows, q_cols)), Some((_, z_cols)), Some((s_rows, out_features))) = (
+            shape_2d(&tensors[qweight]),
+            shape_2d(&tensors[qzeros]),
+            shape_2d(&tensors[scales]),
+        ) else {
+            continue;
+        };
+
+        // qzeros packs one zero point per output column into int32s, revealing the bit width
+        let bits = config_bits
+            .or_else(|| (out_features > 0).then(|| 32 * z_cols / out_features))
+            .filter(|&b| b > 0 && b <= 8);
+        let Some(bits) = bits else {
+            continue;
+        };
+        let pack = 32 / bits;
+        // GPTQ packs along the input dim (qweight is [in/pack, out]); AWQ along the output dim
+        let in_features = if q_cols == out_features {
+            q_rows * pack
+        } else {
+            q_rows
+        };
+
+        let scheme = config_method.clone().unwrap_or_else(|| {
+            if parts.contains_key("g_idx") {
+                "GPTQ".to_string()
+            } else {
+                "AWQ".to_string()
+            }
+        });
+        let group_size = config_group_size.or((s_rows > 0).then(|| in_features / s_rows));
+        let module_bytes = parts.values().map(|&idx| tensors[idx].size_bytes).sum();
+
+        let info = QuantInfo {
+            scheme,
+            bits,
+            group_size,
+            logical_shape: vec![out_features, in_features],
+            module_bytes,
+        };
+        annotations.extend(parts.values().map(|&idx| (idx, info.clone())));
+    }
+    annotations
+}
+
+/// `bits` and `group_size` from an MLX `quantization` object; a module path key holds
+/// the settings of a module quantized differently
+fn mlx_settings(quantization: Option<&serde_json::Value>) -> (Option<usize>, Option<usize>) {
+    let get = |key: &str| {
+        quantization
+            .and_then(|q| q.get(key))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+    };
+    (get("bits"), get("group_size"))
+}
+
+/// MLX modules: a U32 `weight` packing `bits`-wide values along the input dimension, with
+/// one F16/BF16 `scales` and `biases` entry per group of inputs. The bit width and group
+/// size come from `quantization` in config.json; without it they are worked out from the
+/// shapes, trying the default group size of 64 first. Returns the model-wide settings too.
+fn mlx_annotations(
+    tensors: &[TensorInfo],
+    config: Option<&serde_json::Value>,
+) -> (Vec<(usize, QuantInfo)>, Option<usize>, Option<usize>) {
+    let quantization = config.and_then(|c| c.get("quantization"));
+    let (config_bits, config_group_size) = mlx_settings(quantization);
+    let by_name: HashMap<&str, usize> = tensors
+        .iter()
+        .enumerate()
+        .map(|(idx, tensor)| (tensor.name.as_str(), idx))
+        .collect();
+
+    let mut annotations = Vec::new();
+    for tensor in tensors {
+        let Some(module) = tensor.name.strip_suffix(".scales") else {
+            continue;
+        };
+        let (Some(&weight), Some(&scales), Some(&biases)) = (
+            by_name.get(format!("{module}.weight").as_str()),
+            by_name.get(tensor.name.as_str()),
+            by_name.get(format!("{module}.biases").as_str()),
+        ) else {
+            continue;
+        };
+        let (Some(&packed), Some(&groups)) =
+            (tensors[weight].shape.last(), tensors[scales].shape.last())
+        else {
+            continue;
+        };
+        if tensors[weight].dtype != "U32" || groups == 0 {
+            continue;
+        }
+
+        let (module_bits, module_group_size) =
+            match mlx_settings(quantization.and_then(|q| q.get(module))) {
+                (None, None) => (config_bits, config_group_size),
+                settings => settings,
+            };
+        // Bits times inputs fills the packed words exactly
+        let fits = |bits: usize, group_size: usize| bits * groups * group_size == packed * 32;
+        let settings = module_bits
+            .zip(module_group_size)
+            .filter(|&(bits, group_size)| fits(bits, group_size))
+            .or_else(|| {
+                module_group_size
+                    .into_iter()
+                    .chain(MLX_GROUP_SIZES)
+                    .find_map(|group_size| {
+                        MLX_BITS
+                            .into_iter()
+                            .find(|&bits| fits(bits, group_size))
+                            .map(|bits| (bits, group_size))
+                    })
+            });
+        let Some((bits, group_size)) = settings else {
+            continue;
+        };
+
+        let mut logical_shape = tensors[weight].shape.clone();
+        if let Some(last) = logical_shape.last_mut() {
+            *last = groups * group_size;
+        }
+        let parts = [weight, scales, biases];
+        let info = QuantInfo {
+            scheme: "MLX".to_string(),
+            bits,
+            group_size: Some(group_size),
+            logical_shape,
+            module_bytes: parts.iter().map(|&idx| tensors[idx].size_bytes).sum(),
+        };
+        annotations.extend(parts.map(|idx| (idx, info.clone())));
+    }
+    (annotations, config_bits, config_group_size)
+}
+
+/// Parameters a tensor contributes to the model: the dequantized weight for `qweight` (MLX
+/// `weight`), nothing for the other packed companions, and the stored elements otherwise
+pub fn logical_parameters(tensor: &TensorInfo) -> usize {
+    match &tensor.quant {
+        Some(info) if info.is_packed_weight(&tensor.name) => info.logical_elements(),
+        Some(_) => 0,
+        None => tensor.num_elements,
+    }
+}
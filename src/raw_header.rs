@@ -0,0 +1,292 @@
+//! The literal JSON header of a safetensors file, for debugging the program that wrote it.
+//! The text is re-indented token by token rather than parsed into maps, so key order, exact
+//! dtype strings and where `__metadata__` sits are shown as written.
+
+use anyhow::{Context, Result, bail};
+use serde::de::IgnoredAny;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::utils::format_size;
+
+/// Header of one safetensors file as stored
+#[derive(Debug, Clone)]
+pub struct RawHeader {
+    pub path: PathBuf,
+    /// Length of the JSON part declared by the first 8 bytes
+    pub header_len: u64,
+    /// The JSON text, without the padding after it
+    pub json: String,
+    /// Bytes after the JSON value within the declared length, normally spaces that align
+    /// the data to 8 bytes
+    pub padding: u64,
+    /// Whether the padding is all spaces, as the format asks
+    pub padding_is_spaces: bool,
+    /// File offset where the tensor data begins
+    pub data_start: u64,
+    /// Why the JSON doesn't parse, if it doesn't
+    pub error: Option<String>,
+}
+
+impl RawHeader {
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+        let file_size = file.metadata()?.len();
+        let mut len_bytes = [0u8; 8];
+        file.read_exact(&mut len_bytes)
+            .with_context(|| format!("Truncated header length in {}", path.display()))?;
+        let header_len = u64::from_le_bytes(len_bytes);
+        if header_len > file_size.saturating_sub(8) {
+            bail!(
+                "{} declares a {header_len}-byte header but holds only {file_size} bytes",
+                path.display()
+            );
+        }
+        let mut header = vec![0u8; header_len as usize];
+        file.read_exact(&mut header)
+            .with_context(|| format!("Failed to read the header of {}", path.display()))?;
+
+        // The end of the first JSON value is where the padding starts
+        let mut values = serde_json::Deserializer::from_slice(&header).into_iter::<IgnoredAny>();
+        let (end, error) = match values.next() {
+            Some(Ok(_)) => (values.byte_offset(), None),
+            Some(Err(e)) => (trimmed_len(&header), Some(e.to_string())),
+            None => (0, Some("The header is empty".to_string())),
+        };
+        let padding = &header[end..];
+        Ok(Self {
+            path: path.to_path_buf(),
+            header_len,
+            json: String::from_utf8_lossy(&header[..end]).into_owned(),
+            padding: padding.len() as u64,
+            padding_is_spaces: padding.iter().all(|&b| b == b' '),
+            data_start: 8 + header_len,
+            error,
+        })
+    }
+
+    /// Header length, padding and data offset, and the parse error if any
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "Header length: {} bytes ({}) of JSON at offset 8",
+                self.header_len,
+                format_size(self.header_len as usize)
+            ),
+            format!(
+                "Padding: {} byte{} after the JSON{}",
+                self.padding,
+                if self.padding == 1 { "" } else { "s" },
+                if self.padding_is_spaces {
+                    ""
+                } else {
+                    ", not all spaces"
+                }
+            ),
+            format!(
+                "Tensor data starts at offset {}{}",
+                self.data_start,
+                if self.data_start.is_multiple_of(8) {
+                    ""
+                } else {
+                    " (not 8-byte aligned)"
+                }
+            ),
+        ];
+        if let Some(error) = &self.error {
+            lines.push(format!("Invalid JSON: {error}"));
+        }
+        lines
+    }
+}
+
+/// Length of `bytes` without trailing whitespace and NULs
+fn trimmed_len(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace() && *b != 0)
+        .map_or(0, |last| last + 1)
+}
+
+/// Kind of a piece of pretty-printed JSON, for coloring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonToken {
+    Key,
+    String,
+    Number,
+    /// `true`, `false`, `null`, or anything else outside quotes
+    Literal,
+    Punctuation,
+    /// Indentation and the space after `:` and `,`
+    Space,
+}
+
+/// One line of pretty-printed JSON
+pub type JsonLine = Vec<(JsonToken, String)>;
+
+/// Plain text of a pretty-printed line
+pub fn line_text(line: &JsonLine) -> String {
+    line.iter().map(|(_, text)| text.as_str()).collect()
+}
+
+/// What an open bracket started
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Object,
+    Array,
+    /// An array of scalars such as a shape, kept on one line
+    InlineArray,
+}
+
+/// Whether the array opening at `bytes[start]` holds no objects or arrays
+fn is_scalar_array(bytes: &[u8], start: usize) -> bool {
+    let mut in_string = false;
+    let mut escaped = false;
+    for &b in &bytes[start + 1..] {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => return false,
+            b']' => return true,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Re-indent `json` two spaces per level, keeping every key and value as written and in
+/// order. Arrays of scalars stay on one line. Text that isn't valid JSON is laid out as far
+/// as it goes, since a broken header is what this is for.
+pub fn pretty_print(json: &str) -> Vec<JsonLine> {
+    let bytes = json.as_bytes();
+    let mut lines: Vec<JsonLine> = Vec::new();
+    let mut line: JsonLine = Vec::new();
+    let mut stack: Vec<Container> = Vec::new();
+    let mut expect_key = false;
+
+    let new_line = |lines: &mut Vec<JsonLine>, line: &mut JsonLine, depth: usize| {
+        lines.push(std::mem::take(line));
+        if depth > 0 {
+            line.push((JsonToken::Space, "  ".repeat(depth)));
+        }
+    };
+    // Index of the next byte that isn't whitespace
+    let skip_space = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            _ if b.is_ascii_whitespace() => i += 1,
+            b'"' => {
+                let start = i;
+                let mut escaped = false;
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => {
+                            i += 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                let kind = if expect_key {
+                    JsonToken::Key
+                } else {
+                    JsonToken::String
+                };
+                line.push((kind, json[start..i].to_string()));
+            }
+            b'{' | b'[' => {
+                let close = if b == b'{' { b'}' } else { b']' };
+                let next = skip_space(i + 1);
+                if bytes.get(next) == Some(&close) {
+                    line.push((
+                        JsonToken::Punctuation,
+                        format!("{}{}", b as char, close as char),
+                    ));
+                    i = next + 1;
+                    continue;
+                }
+                line.push((JsonToken::Punctuation, (b as char).to_string()));
+                let container = match b {
+                    b'{' => Container::Object,
+                    _ if is_scalar_array(bytes, i) => Container::InlineArray,
+                    _ => Container::Array,
+                };
+                stack.push(container);
+                expect_key = container == Container::Object;
+                if container != Container::InlineArray {
+                    new_line(&mut lines, &mut line, stack.len());
+                }
+                i += 1;
+            }
+            b'}' | b']' => {
+                if stack.pop().is_some_and(|c| c != Container::InlineArray) {
+                    new_line(&mut lines, &mut line, stack.len());
+                }
+                line.push((JsonToken::Punctuation, (b as char).to_string()));
+                expect_key = false;
+                i += 1;
+            }
+            b',' => {
+                line.push((JsonToken::Punctuation, ",".to_string()));
+                match stack.last() {
+                    Some(Container::InlineArray) => {
+                        line.push((JsonToken::Space, " ".to_string()));
+                    }
+                    top => {
+                        expect_key = top == Some(&Container::Object);
+                        new_line(&mut lines, &mut line, stack.len());
+                    }
+                }
+                i += 1;
+            }
+            b':' => {
+                line.push((JsonToken::Punctuation, ":".to_string()));
+                line.push((JsonToken::Space, " ".to_string()));
+                expect_key = false;
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < bytes.len()
+                    && !bytes[i].is_ascii_whitespace()
+                    && !matches!(bytes[i], b',' | b':' | b'{' | b'}' | b'[' | b']' | b'"')
+                {
+                    i += 1;
+                }
+                let text = &json[start..i];
+                let kind = if text.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+                    JsonToken::Number
+                } else {
+                    JsonToken::Literal
+                };
+                line.push((kind, text.to_string()));
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Path sets remembered when the config file doesn't say
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Paths the explorer was opened on, as given on the command line but made absolute
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentEntry {
+    pub paths: Vec<PathBuf>,
+    /// When the paths were last opened, in seconds since the Unix epoch
+    pub opened: u64,
+}
+
+impl RecentEntry {
+    /// Whether every path still exists; missing entries are greyed out and can be pruned
+    pub fn exists(&self) -> bool {
+        self.paths.iter().all(|path| path.exists())
+    }
+
+    /// The paths separated by commas, with the home directory shortened to `~`
+    pub fn label(&self) -> String {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        self.paths
+            .iter()
+            .map(
+                |path| match home.as_deref().map(|home| path.strip_prefix(home)) {
+                    Some(Ok(rest)) => Path::new("~").join(rest).display().to_string(),
+                    _ => path.display().to_string(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `$XDG_CACHE_HOME/safetensors-explorer/recent.json`, falling back to `~/.cache`
+fn recent_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("safetensors-explorer").join("recent.json"))
+}
+
+/// The remembered path sets, most recent first; a missing or corrupt file gives none
+pub fn load() -> Vec<RecentEntry> {
+    recent_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(entries: &[RecentEntry]) -> Result<()> {
+    let Some(path) = recent_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let content = serde_json::to_string_pretty(entries)?;
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Put `paths` at the top of the list, keeping at most `limit` entries
+pub fn record(paths: &[PathBuf], limit: usize) -> Result<()> {
+    let paths: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+        .collect();
+    let mut entries = load();
+    entries.retain(|entry| entry.paths != paths);
+    entries.insert(
+        0,
+        RecentEntry {
+            paths,
+            opened: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        },
+    );
+    entries.truncate(limit);
+    save(&entries)
+}
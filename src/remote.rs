@@ -0,0 +1,226 @@
+//! Single tensors of a safetensors file on a web server or the Hugging Face Hub, fetched
+//! without downloading the file: the header and each tensor's bytes come from HTTP Range
+//! requests made with `curl`. A transfer that drops is resumed from the last byte received.
+
+use anyhow::{Context, Result, bail};
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use crate::dtype;
+use crate::extract::{Extracted, RawDescription, file_stem, write_sidecar};
+use crate::model::{MAX_HEADER_BYTES, read_safetensors_header};
+use crate::scan::{NoProgress, Progress};
+use crate::tree::natural_sort_key;
+
+/// Attempts in a row that may fail without receiving a byte before a range is given up
+const MAX_ATTEMPTS: u32 = 5;
+/// Bytes read from curl between progress updates
+const CHUNK_SIZE: usize = 1 << 20;
+/// curl exit code for an HTTP error status (404, 401, ...), which a retry won't fix
+const CURL_HTTP_ERROR: i32 = 22;
+
+/// Whether a path argument is an http(s) URL rather than a local path
+pub fn is_url(arg: &str) -> bool {
+    arg.starts_with("https://") || arg.starts_with("http://")
+}
+
+/// URL serving the file itself: Hub `blob` pages, as shown in the browser, become `resolve`
+/// URLs; other URLs are kept
+pub fn resolve_url(url: &str) -> String {
+    match url.split_once("huggingface.co/") {
+        Some((scheme, path)) if path.contains("/blob/") => format!(
+            "{scheme}huggingface.co/{}",
+            path.replacen("/blob/", "/resolve/", 1)
+        ),
+        _ => url.to_string(),
+    }
+}
+
+/// Start curl writing bytes `start..end` of `url` to its stdout. Redirects are followed
+/// since the Hub serves files from a CDN; `HF_TOKEN` is sent for gated repos, through
+/// stdin so it stays out of the process list.
+fn spawn_range(url: &str, start: u64, end: u64) -> Result<Child> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--header", "@-", "--range"])
+        .arg(format!("{start}-{}", end - 1))
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run curl, which fetches remote files")?;
+    let headers = std::env::var("HF_TOKEN")
+        .map(|token| format!("Authorization: Bearer {token}\n"))
+        .unwrap_or_default();
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(headers.as_bytes())?;
+    }
+    Ok(child)
+}
+
+/// Write bytes `start..end` of `url` to `out`, starting curl again from the first missing
+/// byte when a transfer stops short. Ok(false) when `progress` cancelled.
+fn fetch_range(
+    url: &str,
+    start: u64,
+    end: u64,
+    out: &mut dyn Write,
+    label: &str,
+    progress: &mut dyn Progress,
+) -> Result<bool> {
+    let total = end - start;
+    let mut received = 0u64;
+    let mut failed_attempts = 0;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    while received < total {
+        let mut child = spawn_range(url, start + received, end)?;
+        let mut stdout = child.stdout.take().context("curl has no stdout")?;
+        let received_before = received;
+        let read = loop {
+            match stdout.read(&mut buffer) {
+                Ok(0) => break Ok(()),
+                Ok(n) => {
+                    if received + n as u64 > total {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        bail!(
+                            "{url} ignores Range requests: it sent more than the {total} bytes asked for"
+                        );
+                    }
+                    out.write_all(&buffer[..n])?;
+                    received += n as u64;
+                    if !progress.update(label, received, total) {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return Ok(false);
+                    }
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        let status = child.wait()?;
+        if received == total {
+            break;
+        }
+        if status.success() && read.is_ok() {
+            bail!(
+                "{url} ends at byte {}, before the end of {label}",
+                start + received
+            );
+        }
+
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        let reason = match read {
+            Err(e) => e.to_string(),
+            Ok(()) if !stderr.trim().is_empty() => stderr.trim().to_string(),
+            Ok(()) => format!("connection closed after {} of {total} bytes", received),
+        };
+        if status.code() == Some(CURL_HTTP_ERROR) {
+            bail!("Failed to fetch {url}: {reason}");
+        }
+        failed_attempts = if received > received_before {
+            1
+        } else {
+            failed_attempts + 1
+        };
+        if failed_attempts >= MAX_ATTEMPTS {
+            bail!("Failed to fetch {url} after {MAX_ATTEMPTS} attempts: {reason}");
+        }
+        let wait = Duration::from_secs(1 << (failed_attempts - 1));
+        log::warn!(
+            "Fetching {label} from {url} stopped at {received} of {total} bytes ({reason}); resuming in {}s",
+            wait.as_secs()
+        );
+        std::thread::sleep(wait);
+    }
+    Ok(true)
+}
+
+/// Download the tensors whose names match one of `patterns` from the safetensors file at
+/// `url` into `<dir>/<name>.bin`, each with a `<name>.json` sidecar as written by
+/// `--extract-raw`, whose source is the URL. Only the header and those tensors' bytes are
+/// fetched; an empty result means nothing matched.
+pub fn extract(
+    url: &str,
+    patterns: &[glob::Pattern],
+    dir: &Path,
+    progress: &mut dyn Progress,
+) -> Result<Vec<Extracted>> {
+    let url = resolve_url(url);
+    let file_name = url.split(['?', '#']).next().unwrap_or(&url);
+    if !file_name.ends_with(".safetensors") {
+        bail!("Only safetensors files can be read remotely: {url}");
+    }
+
+    let mut header = Vec::new();
+    fetch_range(&url, 0, 8, &mut header, "header", &mut NoProgress)?;
+    let header_len = u64::from_le_bytes(header[..8].try_into()?);
+    if header_len > MAX_HEADER_BYTES {
+        bail!("{url} is not a safetensors file: its header would be {header_len} bytes");
+    }
+    fetch_range(
+        &url,
+        8,
+        8 + header_len,
+        &mut header,
+        "header",
+        &mut NoProgress,
+    )?;
+    let (metadata, header_len) =
+        read_safetensors_header(&mut Cursor::new(header), Path::new(&url), None)?;
+    let data_start = 8 + header_len;
+
+    let mut selected: Vec<_> = metadata
+        .tensors()
+        .into_iter()
+        .filter(|(name, _)| patterns.iter().any(|p| p.matches(name)))
+        .collect();
+    selected.sort_by_cached_key(|(name, _)| natural_sort_key(name));
+    if !selected.is_empty() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let mut extracted = Vec::new();
+    for (name, tensor) in selected {
+        let (start, end) = tensor.data_offsets;
+        let (start, end) = (data_start + start as u64, data_start + end as u64);
+        let stem = file_stem(&name);
+        let data = dir.join(format!("{stem}.bin"));
+        let sidecar = dir.join(format!("{stem}.json"));
+
+        let mut out = BufWriter::new(
+            File::create(&data).with_context(|| format!("Failed to create {}", data.display()))?,
+        );
+        if !fetch_range(&url, start, end, &mut out, &name, progress)? {
+            bail!("Download of {name} cancelled");
+        }
+        out.flush()
+            .with_context(|| format!("Failed to write {}", data.display()))?;
+
+        let description = RawDescription {
+            name: name.clone(),
+            dtype: dtype::safetensors_name(tensor.dtype),
+            ggml_type: None,
+            shape: tensor.shape.clone(),
+            storage_shape: None,
+            byte_length: (end - start) as usize,
+            source_file: PathBuf::from(&url),
+            source_offset: start,
+            source_end: end,
+        };
+        write_sidecar(&sidecar, &description)?;
+        extracted.push(Extracted {
+            data,
+            sidecar,
+            bytes: (end - start) as usize,
+        });
+    }
+    Ok(extracted)
+}
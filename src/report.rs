@@ -0,0 +1,307 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use std::str::FromStr;
+
+use crate::check::Finding;
+use crate::utils::display_width;
+
+/// Output formats accepted by `--format`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    /// The human-readable report
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    Csv,
+    Markdown,
+}
+
+impl Format {
+    pub const ALL: [(&'static str, Format); 5] = [
+        ("table", Format::Table),
+        ("json", Format::Json),
+        ("yaml", Format::Yaml),
+        ("csv", Format::Csv),
+        ("markdown", Format::Markdown),
+    ];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Table => "txt",
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|&(_, format)| format)
+            .ok_or_else(|| {
+                let valid: Vec<&str> = Format::ALL.iter().map(|(known, _)| *known).collect();
+                format!(
+                    "unknown format '{name}' (valid formats: {})",
+                    valid.join(", ")
+                )
+            })
+    }
+}
+
+/// A non-interactive report. JSON and YAML serialize the report itself; CSV and
+/// Markdown flatten it into `columns` and `rows`; `table` prints `text_lines`.
+pub trait Report: Serialize {
+    /// Human-readable lines, as printed without `--format`
+    fn text_lines(&self) -> Vec<String>;
+
+    /// Column names of the flat form
+    fn columns(&self) -> &'static [&'static str];
+
+    /// One row per record, with a cell for every column
+    fn rows(&self) -> Vec<Vec<String>>;
+
+    /// Problems or differences the report found, which `--json-events` sends one event
+    /// each; none for reports that only describe
+    fn findings(&self) -> Vec<Finding> {
+        Vec::new()
+    }
+}
+
+pub fn render(report: &(impl Report + ?Sized), format: Format) -> Result<String> {
+    Ok(match format {
+        Format::Table => report
+            .text_lines()
+            .into_iter()
+            .map(|line| line + "\n")
+            .collect(),
+        Format::Json => serde_json::to_string_pretty(report)? + "\n",
+        Format::Yaml => to_yaml(report)?,
+        Format::Csv => {
+            let mut out = report.columns().join(",") + "\n";
+            for row in report.rows() {
+                let cells: Vec<String> = row.iter().map(|c| csv_field(c)).collect();
+                out.push_str(&cells.join(","));
+                out.push('\n');
+            }
+            out
+        }
+        Format::Markdown => {
+            let columns = report.columns();
+            let mut out = format!("| {} |\n", columns.join(" | "));
+            out.push_str(&format!("|{}\n", "---|".repeat(columns.len())));
+            for row in report.rows() {
+                let cells: Vec<String> = row.iter().map(|c| markdown_cell(c)).collect();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+            out
+        }
+    })
+}
+
+/// Rows padded into left-aligned columns under a header, for plain-text listings
+pub fn aligned_lines(columns: &[&str], rows: &[Vec<String>]) -> Vec<String> {
+    let mut widths: Vec<usize> = columns.iter().map(|c| display_width(c)).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        let padded: Vec<String> = cells
+            .zip(&widths)
+            .map(|(cell, &width)| {
+                format!(
+                    "{cell}{}",
+                    " ".repeat(width.saturating_sub(display_width(cell)))
+                )
+            })
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+    let mut lines = vec![line(&mut columns.iter().copied())];
+    lines.extend(
+        rows.iter()
+            .map(|row| line(&mut row.iter().map(String::as_str))),
+    );
+    lines
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// A serialized value with object keys kept in field order (`serde_json::Value` sorts them)
+enum Node {
+    Scalar(String),
+    Seq(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "any JSON value")
+            }
+            fn visit_unit<E>(self) -> Result<Node, E> {
+                Ok(Node::Scalar("null".to_string()))
+            }
+            fn visit_bool<E>(self, v: bool) -> Result<Node, E> {
+                Ok(Node::Scalar(v.to_string()))
+            }
+            fn visit_i64<E>(self, v: i64) -> Result<Node, E> {
+                Ok(Node::Scalar(v.to_string()))
+            }
+            fn visit_u64<E>(self, v: u64) -> Result<Node, E> {
+                Ok(Node::Scalar(v.to_string()))
+            }
+            fn visit_f64<E>(self, v: f64) -> Result<Node, E> {
+                let number = serde_json::Number::from_f64(v);
+                Ok(Node::Scalar(
+                    number.map_or(".nan".to_string(), |n| n.to_string()),
+                ))
+            }
+            fn visit_str<E>(self, v: &str) -> Result<Node, E> {
+                Ok(Node::Scalar(yaml_string(v)))
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(Node::Seq(items))
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry::<String, Node>()? {
+                    entries.push(entry);
+                }
+                Ok(Node::Map(entries))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+/// Serialize a value as block-style YAML, keeping struct fields in declaration order
+pub fn to_yaml(value: &(impl Serialize + ?Sized)) -> Result<String> {
+    let node: Node = serde_json::from_str(&serde_json::to_string(value)?)?;
+    let mut out = String::new();
+    write_yaml(&node, 0, &mut out);
+    if out.is_empty() {
+        // A top-level empty collection
+        out = match node {
+            Node::Map(_) => "{}\n".to_string(),
+            _ => "[]\n".to_string(),
+        };
+    }
+    Ok(out)
+}
+
+/// Write `node` as lines indented by `indent`; scalars are written bare with a newline
+fn write_yaml(node: &Node, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match node {
+        Node::Scalar(scalar) => {
+            out.push_str(&pad);
+            out.push_str(scalar);
+            out.push('\n');
+        }
+        Node::Map(entries) => {
+            for (key, value) in entries {
+                out.push_str(&format!("{pad}{}:", yaml_string(key)));
+                match value {
+                    Node::Map(inner) if !inner.is_empty() => {
+                        out.push('\n');
+                        write_yaml(value, indent + 2, out);
+                    }
+                    Node::Seq(items) if !items.is_empty() => {
+                        out.push('\n');
+                        write_yaml(value, indent + 2, out);
+                    }
+                    _ => {
+                        out.push(' ');
+                        out.push_str(&inline_scalar(value));
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        Node::Seq(items) => {
+            for item in items {
+                // Render the item one level deeper, then turn its first indent into the dash
+                let mut nested = String::new();
+                match item {
+                    Node::Map(inner) if !inner.is_empty() => {
+                        write_yaml(item, indent + 2, &mut nested)
+                    }
+                    Node::Seq(inner) if !inner.is_empty() => {
+                        write_yaml(item, indent + 2, &mut nested)
+                    }
+                    _ => nested = format!("{}{}\n", " ".repeat(indent + 2), inline_scalar(item)),
+                }
+                out.push_str(&pad);
+                out.push_str("- ");
+                out.push_str(&nested[indent + 2..]);
+            }
+        }
+    }
+}
+
+/// A scalar, or an empty collection in flow style
+fn inline_scalar(node: &Node) -> String {
+    match node {
+        Node::Scalar(scalar) => scalar.clone(),
+        Node::Seq(_) => "[]".to_string(),
+        Node::Map(_) => "{}".to_string(),
+    }
+}
+
+/// A string left plain when YAML would read it back unchanged, double-quoted otherwise
+fn yaml_string(s: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "null", "~", "true", "false", "yes", "no", "y", "n", "on", "off",
+    ];
+    let plain = !s.is_empty()
+        && s.trim() == s
+        && !s.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.ends_with(':')
+        && !s.chars().any(char::is_control)
+        && !RESERVED.iter().any(|word| word.eq_ignore_ascii_case(s))
+        // Anything number-like (1e3, 0x1f, 1_000, .inf) stays quoted
+        && !s.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '+')
+        && s.parse::<f64>().is_err();
+    if plain {
+        s.to_string()
+    } else {
+        // A JSON string literal is also a valid YAML double-quoted scalar
+        Value::String(s.to_string()).to_string()
+    }
+}
@@ -0,0 +1,138 @@
+//! What each tensor does in the network, from its name alone. Covers the HF (`q_proj`,
+//! `mlp.down_proj`, `ln_f`) and llama.cpp GGUF (`attn_q`, `ffn_down`, `output_norm`)
+//! naming conventions; names matching none of the patterns are `Other` rather than a guess.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TensorRole {
+    /// Token, position and token type embedding tables
+    Embedding,
+    /// Query, key, value and output projections
+    Attention,
+    /// Feed-forward projections, including expert weights and MoE routers
+    Mlp,
+    /// Layer and RMS norm weights and biases
+    Norm,
+    /// Biases of the linear layers
+    Bias,
+    /// The output head projecting to the vocabulary
+    LmHead,
+    /// Precomputed buffers saved with the weights, such as rotary frequencies and masks
+    Buffer,
+    Other,
+}
+
+impl TensorRole {
+    pub const ALL: [TensorRole; 8] = [
+        TensorRole::Embedding,
+        TensorRole::Attention,
+        TensorRole::Mlp,
+        TensorRole::Norm,
+        TensorRole::Bias,
+        TensorRole::LmHead,
+        TensorRole::Buffer,
+        TensorRole::Other,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TensorRole::Embedding => "Embeddings",
+            TensorRole::Attention => "Attention",
+            TensorRole::Mlp => "MLP",
+            TensorRole::Norm => "Norms",
+            TensorRole::Bias => "Biases",
+            TensorRole::LmHead => "LM head",
+            TensorRole::Buffer => "Rotary/buffers",
+            TensorRole::Other => "Other",
+        }
+    }
+}
+
+/// Name segments of attention blocks and their projections
+const ATTENTION_SEGMENTS: &[&str] = &[
+    "self_attn",
+    "self_attention",
+    "attention",
+    "cross_attn",
+    "encoder_attn",
+    "q_proj",
+    "k_proj",
+    "v_proj",
+    "o_proj",
+    "qkv_proj",
+    "c_attn",
+    "query_key_value",
+    "wqkv",
+];
+
+/// Name segments of feed-forward blocks and their projections
+const MLP_SEGMENTS: &[&str] = &[
+    "mlp",
+    "feed_forward",
+    "ffn",
+    "block_sparse_moe",
+    "experts",
+    "gate_proj",
+    "up_proj",
+    "gate_up_proj",
+    "down_proj",
+    "fc1",
+    "fc2",
+    "c_fc",
+    "dense_h_to_4h",
+    "dense_4h_to_h",
+    "intermediate",
+];
+
+/// The role of the tensor called `name`. Checked most specific first: output heads and
+/// buffers by full name, then norms (their biases included) before biases, so that
+/// `self_attn.q_norm.weight` is a norm and `mlp.fc1.bias` a bias.
+pub fn classify(name: &str) -> TensorRole {
+    let lower = name.to_ascii_lowercase();
+    let segments: Vec<&str> = lower.split('.').collect();
+    let last = segments.last().copied().unwrap_or_default();
+    let has = |wanted: &[&str]| segments.iter().any(|s| wanted.contains(s));
+
+    if lower == "output.weight"
+        || lower == "output.bias"
+        || segments.contains(&"lm_head")
+        || segments.contains(&"embed_out")
+    {
+        return TensorRole::LmHead;
+    }
+    // GPT-2 checkpoints carry the causal mask as `attn.bias` and `attn.masked_bias`
+    if matches!(last, "inv_freq" | "position_ids" | "masked_bias")
+        || segments.contains(&"rope_freqs")
+        || lower.ends_with(".attn.bias")
+    {
+        return TensorRole::Buffer;
+    }
+    if segments
+        .iter()
+        .any(|s| s.contains("norm") || *s == "ln" || s.starts_with("ln_"))
+    {
+        return TensorRole::Norm;
+    }
+    if last == "bias" {
+        return TensorRole::Bias;
+    }
+    if segments.iter().any(|s| {
+        s.contains("embed")
+            || s.starts_with("token_embd")
+            || s.starts_with("position_embd")
+            || matches!(*s, "wte" | "wpe")
+    }) {
+        return TensorRole::Embedding;
+    }
+    // GGUF names the projection in one segment (`attn_q`, `ffn_gate_exps`); diffusion
+    // U-Nets number their attention blocks (`attn1`, `attn2`)
+    if has(ATTENTION_SEGMENTS) || segments.iter().any(|s| s.starts_with("attn")) {
+        return TensorRole::Attention;
+    }
+    if has(MLP_SEGMENTS) || segments.iter().any(|s| s.starts_with("ffn_")) {
+        return TensorRole::Mlp;
+    }
+    TensorRole::Other
+}
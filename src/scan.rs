@@ -0,0 +1,1267 @@
+use anyhow::{Context, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    time::Duration,
+};
+
+use serde::Serialize;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::check::{Finding, Severity};
+use crate::report::Report;
+use crate::tree::TensorInfo;
+use crate::utils::{format_shape, format_size, progress_bar};
+use crate::work::{self, Job};
+
+/// Bytes read from disk per chunk when streaming tensor data.
+/// A multiple of every element size so values never straddle chunks.
+pub const CHUNK_SIZE: usize = 1 << 20;
+
+/// Floating point encodings that can be decoded for value analysis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatType {
+    F16,
+    BF16,
+    F32,
+    F64,
+}
+
+impl FloatType {
+    pub fn from_dtype(dtype: &str) -> Option<Self> {
+        match dtype {
+            "F16" => Some(FloatType::F16),
+            "BF16" => Some(FloatType::BF16),
+            "F32" => Some(FloatType::F32),
+            "F64" => Some(FloatType::F64),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            FloatType::F16 | FloatType::BF16 => 2,
+            FloatType::F32 => 4,
+            FloatType::F64 => 8,
+        }
+    }
+
+    /// Decode one little-endian element; `bytes` must be exactly `size()` long
+    pub fn decode(&self, bytes: &[u8]) -> f64 {
+        match self {
+            FloatType::F16 => f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])) as f64,
+            FloatType::BF16 => {
+                f32::from_bits((u16::from_le_bytes([bytes[0], bytes[1]]) as u32) << 16) as f64
+            }
+            FloatType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FloatType::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits >> 15) as u32) << 31;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+
+    let bits32 = match (exponent, mantissa) {
+        (0, 0) => sign,
+        (0, _) => {
+            // Subnormal: renormalize into the f32 exponent range
+            let shift = mantissa.leading_zeros() - 21;
+            let mantissa = (mantissa << shift) & 0x3ff;
+            sign | ((113 - shift) << 23) | (mantissa << 13)
+        }
+        (0x1f, _) => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits32)
+}
+
+/// Size in bytes of one element for dtypes with a fixed per-element width
+pub fn element_size(dtype: &str) -> Option<usize> {
+    crate::dtype::dtype_info(dtype).map(|info| info.bits as usize / 8)
+}
+
+/// What one worker of a parallel scan has done so far
+#[derive(Debug, Clone, Default)]
+pub struct WorkerStatus {
+    pub bytes: u64,
+    /// Tensor the worker is reading, if it has started one
+    pub current: Option<String>,
+}
+
+/// Snapshot of a parallel scan, passed to [`Progress::update_workers`]
+pub struct ScanStatus<'a> {
+    pub done: u64,
+    pub total: u64,
+    pub elapsed: Duration,
+    pub workers: &'a [WorkerStatus],
+}
+
+impl ScanStatus<'_> {
+    /// Overall bytes per second
+    pub fn throughput(&self) -> f64 {
+        self.done as f64 / self.elapsed.as_secs_f64().max(1e-3)
+    }
+
+    /// Time left at the current overall throughput; None until anything has been read
+    pub fn eta(&self) -> Option<Duration> {
+        (self.done > 0).then(|| {
+            Duration::from_secs_f64(self.total.saturating_sub(self.done) as f64 / self.throughput())
+        })
+    }
+}
+
+/// Receives progress updates from long-running scans
+pub trait Progress {
+    /// Called after each chunk; returning false cancels the scan
+    fn update(&mut self, label: &str, done: u64, total: u64) -> bool;
+
+    /// Called by parallel scans with per-worker detail; by default a plain update
+    fn update_workers(&mut self, status: &ScanStatus) -> bool {
+        let label = status
+            .workers
+            .iter()
+            .find_map(|w| w.current.as_deref())
+            .unwrap_or("");
+        self.update(label, status.done, status.total)
+    }
+}
+
+/// Progress sink for callers that don't report progress
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn update(&mut self, _label: &str, _done: u64, _total: u64) -> bool {
+        true
+    }
+}
+
+/// Progress bar rendered on stderr for non-interactive scans
+#[derive(Default)]
+pub struct StderrProgress {
+    last_percent: Option<u64>,
+}
+
+impl Progress for StderrProgress {
+    fn update(&mut self, _label: &str, done: u64, total: u64) -> bool {
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        if self.last_percent != Some(percent) {
+            self.last_percent = Some(percent);
+            eprint!("\r{} {percent:>3}%", progress_bar(done, total, 40));
+            if done >= total {
+                eprintln!();
+            }
+            let _ = io::stderr().flush();
+        }
+        true
+    }
+}
+
+/// Stream a tensor's bytes from its source file, calling `on_chunk` for each chunk.
+/// Returns Ok(false) if `on_chunk` asked to stop early.
+pub fn stream_tensor(tensor: &TensorInfo, mut on_chunk: impl FnMut(&[u8]) -> bool) -> Result<bool> {
+    let offset = tensor.data_location()?;
+
+    let mut file = File::open(&tensor.source_file)
+        .with_context(|| format!("Failed to open file: {}", tensor.source_file.display()))?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut remaining = tensor.size_bytes;
+    let mut buffer = vec![0u8; CHUNK_SIZE.min(remaining)];
+    while remaining > 0 {
+        let len = CHUNK_SIZE.min(remaining);
+        file.read_exact(&mut buffer[..len])
+            .with_context(|| format!("Failed to read data of tensor {}", tensor.name))?;
+        remaining -= len;
+        if !on_chunk(&buffer[..len]) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Read `len` bytes starting `start` bytes into a tensor's data
+pub fn read_tensor_range(tensor: &TensorInfo, start: usize, len: usize) -> Result<Vec<u8>> {
+    let offset = tensor.data_location()?;
+
+    let mut file = File::open(&tensor.source_file)
+        .with_context(|| format!("Failed to open file: {}", tensor.source_file.display()))?;
+    file.seek(SeekFrom::Start(offset + start as u64))?;
+
+    let mut buffer = vec![0u8; len];
+    file.read_exact(&mut buffer)
+        .with_context(|| format!("Failed to read data of tensor {}", tensor.name))?;
+    Ok(buffer)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NanFinding {
+    pub name: String,
+    pub shape: Vec<usize>,
+    pub nan_count: u64,
+    pub pos_inf_count: u64,
+    pub neg_inf_count: u64,
+    pub first_index: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct NanScan {
+    pub findings: Vec<NanFinding>,
+    pub scanned: usize,
+    pub skipped: usize,
+    pub cancelled: bool,
+}
+
+impl NanFinding {
+    /// `blk.0.attn_q.weight (4096, 4096): 3 NaN, 0 +Inf, 0 -Inf, first at index 17`
+    pub fn describe(&self) -> String {
+        format!(
+            "{} {}: {} NaN, {} +Inf, {} -Inf, first at index {}",
+            self.name,
+            format_shape(&self.shape),
+            self.nan_count,
+            self.pos_inf_count,
+            self.neg_inf_count,
+            self.first_index
+        )
+    }
+}
+
+impl NanScan {
+    /// What was scanned, without the findings
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "NaN/Inf scan: {} of {} float tensors affected{}",
+            self.findings.len(),
+            self.scanned,
+            if self.cancelled { " (cancelled)" } else { "" }
+        )];
+        if self.skipped > 0 {
+            lines.push(format!(
+                "Skipped {} non-float tensors (quantized, integer, or not stored contiguously)",
+                self.skipped
+            ));
+        }
+        lines
+    }
+
+    pub fn report_lines(&self) -> Vec<String> {
+        let mut lines = self.summary_lines();
+        if !self.findings.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(self.findings.iter().map(NanFinding::describe));
+        lines
+    }
+}
+
+impl Report for NanScan {
+    fn text_lines(&self) -> Vec<String> {
+        self.report_lines()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["name", "shape", "nan", "pos_inf", "neg_inf", "first_index"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.findings
+            .iter()
+            .map(|finding| {
+                vec![
+                    finding.name.clone(),
+                    format_shape(&finding.shape),
+                    finding.nan_count.to_string(),
+                    finding.pos_inf_count.to_string(),
+                    finding.neg_inf_count.to_string(),
+                    finding.first_index.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    /// One error per affected tensor
+    fn findings(&self) -> Vec<Finding> {
+        self.findings
+            .iter()
+            .map(|f| Finding {
+                check: "nan",
+                severity: Severity::Error,
+                message: f.describe(),
+                related_node_path: Some(f.name.clone()),
+                values: BTreeMap::from([
+                    ("nan_count", f.nan_count),
+                    ("pos_inf_count", f.pos_inf_count),
+                    ("neg_inf_count", f.neg_inf_count),
+                    ("first_index", f.first_index),
+                ]),
+            })
+            .collect()
+    }
+}
+
+/// Float type of a tensor whose data can be streamed from disk
+fn streamable_float(tensor: &TensorInfo) -> Option<FloatType> {
+    tensor.data_offset?;
+    FloatType::from_dtype(&tensor.dtype)
+}
+
+/// NaN and ±Inf counts over one byte range of a tensor
+struct NonFiniteCounts {
+    float_type: FloatType,
+    nan: u64,
+    pos_inf: u64,
+    neg_inf: u64,
+    /// Element index of the first non-finite value in the range
+    first: Option<u64>,
+    /// Element index of the next value to decode
+    index: u64,
+}
+
+impl NonFiniteCounts {
+    fn feed(&mut self, chunk: &[u8]) {
+        for bytes in chunk.chunks_exact(self.float_type.size()) {
+            let value = self.float_type.decode(bytes);
+            if !value.is_finite() {
+                self.first.get_or_insert(self.index);
+                if value.is_nan() {
+                    self.nan += 1;
+                } else if value > 0.0 {
+                    self.pos_inf += 1;
+                } else {
+                    self.neg_inf += 1;
+                }
+            }
+            self.index += 1;
+        }
+    }
+}
+
+/// Count NaN and ±Inf values in every float tensor, spread over worker threads
+pub fn scan_nan(tensors: &[TensorInfo], progress: &mut dyn Progress) -> Result<NanScan> {
+    let mut scan = NanScan::default();
+    let float_tensors: Vec<&TensorInfo> = tensors
+        .iter()
+        .filter(|t| streamable_float(t).is_some())
+        .collect();
+    scan.skipped = tensors.len() - float_tensors.len();
+
+    let jobs = work::split_jobs(&float_tensors);
+    let run = work::run_jobs(
+        &jobs,
+        work::worker_count(),
+        progress,
+        |job| {
+            let float_type = streamable_float(job.tensor).expect("float tensors only");
+            NonFiniteCounts {
+                float_type,
+                nan: 0,
+                pos_inf: 0,
+                neg_inf: 0,
+                first: None,
+                index: (job.start / float_type.size()) as u64,
+            }
+        },
+        NonFiniteCounts::feed,
+    )?;
+    scan.cancelled = run.cancelled;
+
+    for (tensor_index, tensor) in float_tensors.iter().enumerate() {
+        let ranges = tensor_results(&jobs, &run.results, tensor_index);
+        let mut finding = NanFinding {
+            name: tensor.name.clone(),
+            shape: tensor.shape.clone(),
+            nan_count: 0,
+            pos_inf_count: 0,
+            neg_inf_count: 0,
+            first_index: 0,
+        };
+        let mut first = None;
+        let mut complete = true;
+        for counts in ranges {
+            let Some(counts) = counts else {
+                complete = false;
+                continue;
+            };
+            finding.nan_count += counts.nan;
+            finding.pos_inf_count += counts.pos_inf;
+            finding.neg_inf_count += counts.neg_inf;
+            first = first.or(counts.first);
+        }
+        if let Some(first) = first {
+            finding.first_index = first;
+            scan.findings.push(finding);
+        }
+        if complete {
+            scan.scanned += 1;
+        }
+    }
+
+    Ok(scan)
+}
+
+/// Results of the jobs covering one tensor, in byte order
+fn tensor_results<'a, A>(
+    jobs: &[Job],
+    results: &'a [Option<A>],
+    tensor_index: usize,
+) -> impl Iterator<Item = Option<&'a A>> {
+    let start = jobs.partition_point(|job| job.tensor_index < tensor_index);
+    let end = jobs.partition_point(|job| job.tensor_index <= tensor_index);
+    results[start..end].iter().map(Option::as_ref)
+}
+
+/// Bytes read from each sampled window when pre-checking for degenerate tensors
+const SAMPLE_WINDOW: usize = 64 * 1024;
+const SAMPLE_WINDOWS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Degeneracy {
+    AllZero,
+    Constant(String),
+}
+
+impl std::fmt::Display for Degeneracy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Degeneracy::AllZero => write!(f, "all zeros"),
+            Degeneracy::Constant(value) => write!(f, "constant value {value}"),
+        }
+    }
+}
+
+/// Tracks whether the bytes fed so far are all zero or one repeated element
+struct DegeneracyTracker {
+    elem_size: Option<usize>,
+    float_type: Option<FloatType>,
+    all_zero: bool,
+    constant: bool,
+    first: Option<Vec<u8>>,
+}
+
+impl DegeneracyTracker {
+    fn new(dtype: &str) -> Self {
+        let elem_size = element_size(dtype);
+        Self {
+            elem_size,
+            float_type: FloatType::from_dtype(dtype),
+            all_zero: true,
+            constant: elem_size.is_some(),
+            first: None,
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        if self.all_zero && chunk.iter().any(|&b| b != 0) {
+            self.all_zero = false;
+        }
+        if !self.constant {
+            return;
+        }
+        let Some(elem_size) = self.elem_size else {
+            return;
+        };
+        for bytes in chunk.chunks_exact(elem_size) {
+            let first = self.first.get_or_insert_with(|| bytes.to_vec());
+            let equal = match self.float_type {
+                // Compare decoded values so that 0.0 and -0.0 count as the same constant
+                Some(float_type) => float_type.decode(bytes) == float_type.decode(first),
+                None => bytes == first.as_slice(),
+            };
+            if !equal {
+                self.constant = false;
+                return;
+            }
+        }
+    }
+
+    fn is_degenerate(&self) -> bool {
+        self.all_zero || self.constant
+    }
+
+    fn result(&self) -> Option<Degeneracy> {
+        if self.all_zero {
+            Some(Degeneracy::AllZero)
+        } else if self.constant {
+            let first = self.first.as_deref()?;
+            let value = match self.float_type {
+                Some(float_type) => float_type.decode(first).to_string(),
+                None => format!(
+                    "0x{}",
+                    first
+                        .iter()
+                        .rev()
+                        .map(|b| format!("{b:02x}"))
+                        .collect::<String>()
+                ),
+            };
+            Some(Degeneracy::Constant(value))
+        } else {
+            None
+        }
+    }
+}
+
+/// Check a tensor for all-zero or constant contents by reading every byte
+pub fn check_degenerate(
+    tensor: &TensorInfo,
+    progress: &mut dyn Progress,
+) -> Result<Option<Degeneracy>> {
+    if tensor.num_elements <= 1 {
+        return Ok(None);
+    }
+    let mut tracker = DegeneracyTracker::new(&tensor.dtype);
+    let total = tensor.size_bytes as u64;
+    let mut done = 0u64;
+    let completed = stream_tensor(tensor, |chunk| {
+        tracker.feed(chunk);
+        done += chunk.len() as u64;
+        tracker.is_degenerate() && progress.update(&tensor.name, done, total)
+    })?;
+    if !completed && tracker.is_degenerate() {
+        // Cancelled before the whole tensor was seen
+        return Ok(None);
+    }
+    Ok(tracker.result())
+}
+
+/// Cheap pre-check reading a few evenly spaced windows of the tensor
+fn sample_looks_degenerate(tensor: &TensorInfo) -> Result<bool> {
+    if tensor.size_bytes <= SAMPLE_WINDOW * SAMPLE_WINDOWS {
+        return Ok(true);
+    }
+    let align = element_size(&tensor.dtype).unwrap_or(1);
+    let mut tracker = DegeneracyTracker::new(&tensor.dtype);
+    let stride = (tensor.size_bytes - SAMPLE_WINDOW) / (SAMPLE_WINDOWS - 1);
+    for i in 0..SAMPLE_WINDOWS {
+        let start = (i * stride) / align * align;
+        tracker.feed(&read_tensor_range(tensor, start, SAMPLE_WINDOW)?);
+        if !tracker.is_degenerate() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DegenerateFinding {
+    pub name: String,
+    pub dtype: String,
+    pub shape: Vec<usize>,
+    pub kind: Degeneracy,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DegenerateScan {
+    pub findings: Vec<DegenerateFinding>,
+    pub scanned: usize,
+    pub cancelled: bool,
+}
+
+impl DegenerateFinding {
+    /// `model.norm.weight [F32, (4096,)]: constant value 1`
+    pub fn describe(&self) -> String {
+        format!(
+            "{} [{}, {}]: {}",
+            self.name,
+            self.dtype,
+            format_shape(&self.shape),
+            self.kind
+        )
+    }
+}
+
+impl DegenerateScan {
+    /// What was scanned, without the findings
+    pub fn summary_lines(&self) -> Vec<String> {
+        vec![format!(
+            "Degenerate tensor scan: {} of {} tensors are all-zero or constant{}",
+            self.findings.len(),
+            self.scanned,
+            if self.cancelled { " (cancelled)" } else { "" }
+        )]
+    }
+
+    pub fn report_lines(&self) -> Vec<String> {
+        let mut lines = self.summary_lines();
+        if !self.findings.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(self.findings.iter().map(DegenerateFinding::describe));
+        lines
+    }
+}
+
+impl Report for DegenerateScan {
+    fn text_lines(&self) -> Vec<String> {
+        self.report_lines()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["name", "dtype", "shape", "kind"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.findings
+            .iter()
+            .map(|finding| {
+                vec![
+                    finding.name.clone(),
+                    finding.dtype.clone(),
+                    format_shape(&finding.shape),
+                    finding.kind.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    /// One warning per all-zero or constant tensor; norms and biases are sometimes
+    /// legitimately constant
+    fn findings(&self) -> Vec<Finding> {
+        self.findings
+            .iter()
+            .map(|f| Finding {
+                check: "degenerate",
+                severity: Severity::Warning,
+                message: f.describe(),
+                related_node_path: Some(f.name.clone()),
+                values: BTreeMap::new(),
+            })
+            .collect()
+    }
+}
+
+/// Find all-zero and constant tensors, sampling first so healthy tensors stay cheap
+pub fn scan_degenerate(
+    tensors: &[TensorInfo],
+    progress: &mut dyn Progress,
+) -> Result<DegenerateScan> {
+    let mut scan = DegenerateScan::default();
+    let total = tensors.len() as u64;
+
+    for (i, tensor) in tensors.iter().enumerate() {
+        if !progress.update(&tensor.name, i as u64, total) {
+            scan.cancelled = true;
+            break;
+        }
+        scan.scanned += 1;
+        if tensor.num_elements <= 1
+            || tensor.data_offset.is_none()
+            || !sample_looks_degenerate(tensor)?
+        {
+            continue;
+        }
+        if let Some(kind) = check_degenerate(tensor, &mut NoProgress)? {
+            scan.findings.push(DegenerateFinding {
+                name: tensor.name.clone(),
+                dtype: tensor.dtype.clone(),
+                shape: tensor.shape.clone(),
+                kind,
+            });
+        }
+    }
+    if !scan.cancelled {
+        progress.update("", total, total);
+    }
+
+    Ok(scan)
+}
+
+pub const HISTOGRAM_BUCKETS: usize = 20;
+
+/// Values kept for bucketing; beyond this every k-th value is used for the bars
+const HISTOGRAM_MAX_VALUES: usize = 4 * 1024 * 1024;
+
+/// Chunks read by a sampled statistics pass, and the bytes in each
+pub const SAMPLE_CHUNKS: usize = 1000;
+pub const SAMPLE_CHUNK_SIZE: usize = 4096;
+
+/// Tensors larger than this get sampled statistics unless the config says otherwise
+pub const DEFAULT_SAMPLE_THRESHOLD: usize = 512 << 20;
+
+/// How much of a tensor a statistics pass reads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsMode {
+    /// Every element
+    Full,
+    /// `SAMPLE_CHUNKS` evenly spaced chunks of `SAMPLE_CHUNK_SIZE` bytes
+    Sampled,
+}
+
+/// Stream `chunks` evenly spaced windows of `chunk_size` bytes from a tensor's data.
+/// Windows start on element boundaries and never overlap, so no value is split across
+/// two of them. Falls back to [`stream_tensor`] when the windows would cover the tensor.
+pub fn stream_tensor_sampled(
+    tensor: &TensorInfo,
+    chunks: usize,
+    chunk_size: usize,
+    mut on_chunk: impl FnMut(&[u8]) -> bool,
+) -> Result<bool> {
+    let align = element_size(&tensor.dtype).unwrap_or(1);
+    let chunk_size = (chunk_size / align * align).max(align);
+    if chunks < 2 || tensor.size_bytes <= chunks * chunk_size {
+        return stream_tensor(tensor, on_chunk);
+    }
+    let offset = tensor.data_location()?;
+
+    let mut file = File::open(&tensor.source_file)
+        .with_context(|| format!("Failed to open file: {}", tensor.source_file.display()))?;
+    let stride = (tensor.size_bytes - chunk_size) / (chunks - 1);
+    let mut buffer = vec![0u8; chunk_size];
+    for i in 0..chunks {
+        let start = (i * stride) / align * align;
+        file.seek(SeekFrom::Start(offset + start as u64))?;
+        file.read_exact(&mut buffer)
+            .with_context(|| format!("Failed to read data of tensor {}", tensor.name))?;
+        if !on_chunk(&buffer) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Bytes a pass in `mode` reads from `tensor`
+pub fn stats_bytes(tensor: &TensorInfo, mode: StatsMode) -> usize {
+    match mode {
+        StatsMode::Full => tensor.size_bytes,
+        StatsMode::Sampled => tensor.size_bytes.min(SAMPLE_CHUNKS * SAMPLE_CHUNK_SIZE),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub buckets: Vec<u64>,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std: f64,
+    /// Finite values included in the statistics
+    pub count: u64,
+    pub zero_count: u64,
+    pub non_finite_count: u64,
+    /// The bars use every `sample_stride`-th value; 1 means all of them
+    pub sample_stride: usize,
+    /// Bytes read when the statistics are estimated from a sample; None after a full pass
+    pub sampled_bytes: Option<usize>,
+}
+
+impl Histogram {
+    /// NaN and ±Inf values as a fraction of all values seen
+    pub fn non_finite_rate(&self) -> f64 {
+        let seen = self.count + self.non_finite_count;
+        if seen > 0 {
+            self.non_finite_count as f64 / seen as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Running statistics over decoded values, fed the same way by full and sampled passes
+struct ValueStats {
+    float_type: FloatType,
+    sample_stride: usize,
+    index: usize,
+    values: Vec<f32>,
+    count: u64,
+    zero_count: u64,
+    non_finite_count: u64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    /// Sum of squared deviations from the mean (Welford)
+    m2: f64,
+}
+
+impl ValueStats {
+    fn new(float_type: FloatType, expected_values: usize) -> Self {
+        let sample_stride = expected_values.div_ceil(HISTOGRAM_MAX_VALUES).max(1);
+        Self {
+            float_type,
+            sample_stride,
+            index: 0,
+            values: Vec::with_capacity(expected_values.div_ceil(sample_stride)),
+            count: 0,
+            zero_count: 0,
+            non_finite_count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    /// Decode a chunk; its length must be a whole number of elements
+    fn feed(&mut self, chunk: &[u8]) {
+        for bytes in chunk.chunks_exact(self.float_type.size()) {
+            let value = self.float_type.decode(bytes);
+            if !value.is_finite() {
+                self.non_finite_count += 1;
+                continue;
+            }
+            self.count += 1;
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+            if value == 0.0 {
+                self.zero_count += 1;
+            }
+            let delta = value - self.mean;
+            self.mean += delta / self.count as f64;
+            self.m2 += delta * (value - self.mean);
+            if self.index.is_multiple_of(self.sample_stride) {
+                self.values.push(value as f32);
+            }
+            self.index += 1;
+        }
+    }
+
+    fn finish(self, sampled_bytes: Option<usize>) -> Histogram {
+        let mut buckets = vec![0u64; HISTOGRAM_BUCKETS];
+        let range = self.max - self.min;
+        for &value in &self.values {
+            let bucket = if range > 0.0 {
+                (((value as f64 - self.min) / range) * HISTOGRAM_BUCKETS as f64) as usize
+            } else {
+                0
+            };
+            buckets[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+        }
+        let has_values = self.count > 0;
+        Histogram {
+            buckets,
+            min: if has_values { self.min } else { 0.0 },
+            max: if has_values { self.max } else { 0.0 },
+            mean: self.mean,
+            std: if has_values {
+                (self.m2 / self.count as f64).sqrt()
+            } else {
+                0.0
+            },
+            count: self.count,
+            zero_count: self.zero_count,
+            non_finite_count: self.non_finite_count,
+            sample_stride: self.sample_stride,
+            sampled_bytes,
+        }
+    }
+}
+
+/// Build a value histogram for a float tensor, reading all of it or only a sample.
+/// Returns Ok(None) when the scan is cancelled.
+pub fn compute_histogram(
+    tensor: &TensorInfo,
+    mode: StatsMode,
+    progress: &mut dyn Progress,
+) -> Result<Option<Histogram>> {
+    let float_type = FloatType::from_dtype(&tensor.dtype)
+        .ok_or_else(|| anyhow::anyhow!("Histograms are unsupported for dtype {}", tensor.dtype))?;
+    let total = stats_bytes(tensor, mode);
+    let mut stats = ValueStats::new(float_type, total / float_type.size());
+    let mut done = 0u64;
+
+    let mut feed = |chunk: &[u8]| {
+        stats.feed(chunk);
+        done += chunk.len() as u64;
+        progress.update(&tensor.name, done, total as u64)
+    };
+    let completed = match mode {
+        StatsMode::Full => stream_tensor(tensor, &mut feed)?,
+        StatsMode::Sampled => {
+            stream_tensor_sampled(tensor, SAMPLE_CHUNKS, SAMPLE_CHUNK_SIZE, &mut feed)?
+        }
+    };
+    if !completed {
+        return Ok(None);
+    }
+    let sampled_bytes = (total < tensor.size_bytes).then_some(total);
+    Ok(Some(stats.finish(sampled_bytes)))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SparsityStats {
+    pub zero_fraction: f64,
+    pub l2_norm: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct SparsityScan {
+    /// Stats keyed by tensor name, for every float tensor that was scanned
+    pub stats: Vec<(String, SparsityStats)>,
+    pub cancelled: bool,
+}
+
+impl SparsityScan {
+    /// Stats joined with each tensor's dtype and shape, most sparse first
+    pub fn report(&self, tensors: &[TensorInfo]) -> SparsityReport {
+        let by_name: HashMap<&str, &TensorInfo> =
+            tensors.iter().map(|t| (t.name.as_str(), t)).collect();
+        let mut entries: Vec<SparsityEntry> = self
+            .stats
+            .iter()
+            .filter_map(|(name, stats)| {
+                let tensor = by_name.get(name.as_str())?;
+                Some(SparsityEntry {
+                    name: tensor.name.clone(),
+                    dtype: tensor.dtype.clone(),
+                    shape: tensor.shape.clone(),
+                    zero_fraction: stats.zero_fraction,
+                    l2_norm: stats.l2_norm,
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.zero_fraction
+                .total_cmp(&a.zero_fraction)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        SparsityReport {
+            entries,
+            cancelled: self.cancelled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SparsityEntry {
+    pub name: String,
+    pub dtype: String,
+    pub shape: Vec<usize>,
+    pub zero_fraction: f64,
+    pub l2_norm: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SparsityReport {
+    pub entries: Vec<SparsityEntry>,
+    pub cancelled: bool,
+}
+
+impl Report for SparsityReport {
+    fn text_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{:>8}  {:>12}  tensor{}",
+            "zeros",
+            "L2 norm",
+            if self.cancelled { " (cancelled)" } else { "" }
+        )];
+        for entry in &self.entries {
+            lines.push(format!(
+                "{:>7.2}%  {:>12.4}  {} [{}, {}]",
+                entry.zero_fraction * 100.0,
+                entry.l2_norm,
+                entry.name,
+                entry.dtype,
+                format_shape(&entry.shape)
+            ));
+        }
+        lines
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["name", "dtype", "shape", "zero_fraction", "l2_norm"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.name.clone(),
+                    entry.dtype.clone(),
+                    format_shape(&entry.shape),
+                    entry.zero_fraction.to_string(),
+                    entry.l2_norm.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Compute the exact-zero fraction and L2 norm of every float tensor
+pub fn scan_sparsity(tensors: &[TensorInfo], progress: &mut dyn Progress) -> Result<SparsityScan> {
+    let mut scan = SparsityScan::default();
+    let float_tensors: Vec<&TensorInfo> = tensors
+        .iter()
+        .filter(|t| streamable_float(t).is_some())
+        .collect();
+
+    let jobs = work::split_jobs(&float_tensors);
+    let run = work::run_jobs(
+        &jobs,
+        work::worker_count(),
+        progress,
+        |job| {
+            let float_type = streamable_float(job.tensor).expect("float tensors only");
+            (float_type, 0u64, 0.0f64)
+        },
+        |(float_type, zeros, sum_squares), chunk| {
+            for bytes in chunk.chunks_exact(float_type.size()) {
+                let value = float_type.decode(bytes);
+                if value == 0.0 {
+                    *zeros += 1;
+                }
+                *sum_squares += value * value;
+            }
+        },
+    )?;
+    scan.cancelled = run.cancelled;
+
+    for (tensor_index, tensor) in float_tensors.iter().enumerate() {
+        let Some(ranges) =
+            tensor_results(&jobs, &run.results, tensor_index).collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let zeros: u64 = ranges.iter().map(|(_, zeros, _)| zeros).sum();
+        let sum_squares: f64 = ranges.iter().map(|(_, _, sum)| sum).sum();
+        let zero_fraction = if tensor.num_elements > 0 {
+            zeros as f64 / tensor.num_elements as f64
+        } else {
+            0.0
+        };
+        scan.stats.push((
+            tensor.name.clone(),
+            SparsityStats {
+                zero_fraction,
+                l2_norm: sum_squares.sqrt(),
+            },
+        ));
+    }
+
+    Ok(scan)
+}
+
+/// Tensors smaller than this are ignored by duplicate detection
+pub const DUPLICATE_MIN_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub id: usize,
+    pub size_bytes: usize,
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DuplicateScan {
+    pub groups: Vec<DuplicateGroup>,
+    pub cancelled: bool,
+}
+
+impl DuplicateScan {
+    pub fn wasted_bytes(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|g| g.size_bytes * (g.names.len() - 1))
+            .sum()
+    }
+
+    /// Map from tensor name to the id of its duplicate group
+    pub fn group_ids(&self) -> HashMap<String, usize> {
+        self.groups
+            .iter()
+            .flat_map(|g| g.names.iter().map(|name| (name.clone(), g.id)))
+            .collect()
+    }
+
+    pub fn report_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Duplicate tensor data: {} groups, {} wasted{}",
+            self.groups.len(),
+            format_size(self.wasted_bytes()),
+            if self.cancelled { " (cancelled)" } else { "" }
+        )];
+        for group in &self.groups {
+            lines.push(String::new());
+            lines.push(format!(
+                "#{}: {} identical copies of {} ({} wasted)",
+                group.id,
+                group.names.len(),
+                format_size(group.size_bytes),
+                format_size(group.size_bytes * (group.names.len() - 1))
+            ));
+            for name in &group.names {
+                lines.push(format!("  {name}"));
+            }
+        }
+        lines
+    }
+}
+
+impl Report for DuplicateScan {
+    fn text_lines(&self) -> Vec<String> {
+        self.report_lines()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["group", "size_bytes", "name"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.groups
+            .iter()
+            .flat_map(|group| {
+                group.names.iter().map(|name| {
+                    vec![
+                        group.id.to_string(),
+                        group.size_bytes.to_string(),
+                        name.clone(),
+                    ]
+                })
+            })
+            .collect()
+    }
+
+    /// One warning per copy after the first of each group
+    fn findings(&self) -> Vec<Finding> {
+        self.groups
+            .iter()
+            .flat_map(|group| {
+                let (first, copies) = group.names.split_first()?;
+                Some(copies.iter().map(move |name| Finding {
+                    check: "duplicate",
+                    severity: Severity::Warning,
+                    message: format!(
+                        "{name} holds the same {} as {first}",
+                        format_size(group.size_bytes)
+                    ),
+                    related_node_path: Some(name.clone()),
+                    values: BTreeMap::from([
+                        ("group", group.id as u64),
+                        ("size_bytes", group.size_bytes as u64),
+                    ]),
+                }))
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+/// Hash every tensor in parallel. A tensor split into several ranges hashes the
+/// digests of its ranges, which is stable because equal sizes split identically.
+/// Returns None for tensors not fully hashed before a cancel.
+fn hash_tensors(
+    tensors: &[&TensorInfo],
+    progress: &mut dyn Progress,
+) -> Result<(Vec<Option<u64>>, bool)> {
+    let jobs = work::split_jobs(tensors);
+    let run = work::run_jobs(
+        &jobs,
+        work::worker_count(),
+        progress,
+        |_| Xxh3::new(),
+        |hasher, chunk| hasher.update(chunk),
+    )?;
+    let hashes = (0..tensors.len())
+        .map(|tensor_index| {
+            let digests: Vec<u64> = tensor_results(&jobs, &run.results, tensor_index)
+                .map(|hasher| hasher.map(Xxh3::digest))
+                .collect::<Option<_>>()?;
+            Some(match digests.as_slice() {
+                [digest] => *digest,
+                _ => {
+                    let mut hasher = Xxh3::new();
+                    for digest in &digests {
+                        hasher.update(&digest.to_le_bytes());
+                    }
+                    hasher.digest()
+                }
+            })
+        })
+        .collect();
+    Ok((hashes, run.cancelled))
+}
+
+/// Compare two equally sized tensors byte for byte
+fn tensors_identical(a: &TensorInfo, b: &TensorInfo) -> Result<bool> {
+    let mut offset = 0;
+    while offset < a.size_bytes {
+        let len = CHUNK_SIZE.min(a.size_bytes - offset);
+        if read_tensor_range(a, offset, len)? != read_tensor_range(b, offset, len)? {
+            return Ok(false);
+        }
+        offset += len;
+    }
+    Ok(true)
+}
+
+/// Find tensors whose data is a bitwise-identical copy of another tensor.
+/// Tensors that alias the same bytes in the same file are not counted as copies.
+pub fn find_duplicates(
+    tensors: &[TensorInfo],
+    min_size: usize,
+    progress: &mut dyn Progress,
+) -> Result<DuplicateScan> {
+    let mut scan = DuplicateScan::default();
+
+    // Only tensors sharing a byte length can possibly be identical
+    let mut by_size: HashMap<usize, Vec<&TensorInfo>> = HashMap::new();
+    let mut seen_locations = std::collections::HashSet::new();
+    for tensor in tensors {
+        if tensor.size_bytes >= min_size
+            && tensor.data_offset.is_some()
+            && seen_locations.insert((&tensor.source_file, tensor.data_offset))
+        {
+            by_size.entry(tensor.size_bytes).or_default().push(tensor);
+        }
+    }
+    let mut candidates: Vec<&TensorInfo> = by_size
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .flatten()
+        .collect();
+    candidates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let (hashes, cancelled) = hash_tensors(&candidates, progress)?;
+    scan.cancelled = cancelled;
+    let mut by_hash: HashMap<(usize, u64), Vec<&TensorInfo>> = HashMap::new();
+    for (tensor, hash) in candidates.into_iter().zip(hashes) {
+        if let Some(hash) = hash {
+            by_hash
+                .entry((tensor.size_bytes, hash))
+                .or_default()
+                .push(tensor);
+        }
+    }
+
+    // Confirm hash matches with a byte comparison against each group's first member
+    let mut groups: Vec<Vec<&TensorInfo>> = Vec::new();
+    for members in by_hash.into_values().filter(|m| m.len() > 1) {
+        let mut confirmed: Vec<Vec<&TensorInfo>> = Vec::new();
+        for tensor in members {
+            let mut placed = false;
+            for group in confirmed.iter_mut() {
+                if tensors_identical(group[0], tensor)? {
+                    group.push(tensor);
+                    placed = true;
+                    break;
+                }
+            }
+            if !placed {
+                confirmed.push(vec![tensor]);
+            }
+        }
+        groups.extend(confirmed.into_iter().filter(|g| g.len() > 1));
+    }
+
+    groups.sort_by(|a, b| {
+        let wasted = |g: &Vec<&TensorInfo>| g[0].size_bytes * (g.len() - 1);
+        wasted(b)
+            .cmp(&wasted(a))
+            .then_with(|| a[0].name.cmp(&b[0].name))
+    });
+    scan.groups = groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, group)| DuplicateGroup {
+            id: i + 1,
+            size_bytes: group[0].size_bytes,
+            names: group.iter().map(|t| t.name.clone()).collect(),
+        })
+        .collect();
+
+    Ok(scan)
+}
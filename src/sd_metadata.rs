@@ -0,0 +1,180 @@
+//! Metadata conventions of Stable Diffusion checkpoints and LoRAs: the `ss_*` training keys
+//! kohya-ss sd-scripts writes, the `modelspec.*` keys of the Stability AI model spec, and
+//! thumbnails embedded as base64. They are recognized by key and value, whatever the file
+//! is called.
+
+use std::sync::Arc;
+
+use crate::gguf::GGUFValue;
+use crate::tree::MetadataInfo;
+use crate::utils::{format_size, format_timestamp};
+
+/// Metadata group the `ss_*` keys are shown under
+pub const TRAINING_SOURCE: &str = "Training";
+
+/// Training keys holding Unix timestamps
+const TIMESTAMP_KEYS: &[&str] = &["ss_training_started_at", "ss_training_finished_at"];
+
+/// Training keys worth a line in the model summary, with their labels
+pub const TRAINING_SUMMARY_KEYS: &[(&str, &str)] = &[
+    ("ss_network_module", "Network module"),
+    ("ss_network_dim", "Network dim (rank)"),
+    ("ss_network_alpha", "Network alpha"),
+    ("ss_base_model_version", "Base model"),
+    ("ss_sd_model_name", "Trained on"),
+    ("ss_optimizer", "Optimizer"),
+    ("ss_learning_rate", "Learning rate"),
+    ("ss_unet_lr", "U-Net learning rate"),
+    ("ss_text_encoder_lr", "Text encoder learning rate"),
+    ("ss_num_epochs", "Epochs"),
+    ("ss_max_train_steps", "Steps"),
+    ("ss_num_train_images", "Training images"),
+    ("ss_resolution", "Resolution"),
+    ("ss_mixed_precision", "Mixed precision"),
+    ("ss_training_started_at", "Started"),
+    ("ss_training_finished_at", "Finished"),
+];
+
+/// Bare base64 values shorter than this are never taken for an image
+const MIN_BARE_IMAGE_LEN: usize = 1024;
+
+/// An image stored in a metadata value as base64, bare or as a `data:` URI
+#[derive(Debug, Clone)]
+pub struct EmbeddedImage {
+    /// e.g. `JPEG`
+    pub format: String,
+    /// File extension to export it with, e.g. `jpg`
+    pub extension: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The image a metadata value holds: a `data:image/...;base64,` URI, or a long bare base64
+/// value whose decoded bytes start with a PNG, JPEG, GIF or WebP signature
+pub fn embedded_image(value: &str) -> Option<EmbeddedImage> {
+    let (payload, subtype) = match value.strip_prefix("data:image/") {
+        Some(rest) => {
+            let (subtype, payload) = rest.split_once(";base64,")?;
+            (payload, Some(subtype))
+        }
+        None if value.len() >= MIN_BARE_IMAGE_LEN => (value, None),
+        None => return None,
+    };
+    let bytes = decode_base64(payload)?;
+    let (format, extension) = match (image_signature(&bytes), subtype) {
+        (Some((format, extension)), _) => (format.to_string(), extension.to_string()),
+        // A data URI names its type even when the signature is one not listed here
+        (None, Some(subtype)) => (subtype.to_uppercase(), subtype.to_string()),
+        (None, None) => return None,
+    };
+    Some(EmbeddedImage {
+        format,
+        extension,
+        bytes,
+    })
+}
+
+fn image_signature(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some(("PNG", "png")),
+        [0xFF, 0xD8, 0xFF, ..] => Some(("JPEG", "jpg")),
+        [b'G', b'I', b'F', b'8', ..] => Some(("GIF", "gif")),
+        [b'R', b'I', b'F', b'F', _, _, _, _, rest @ ..] if rest.starts_with(b"WEBP") => {
+            Some(("WebP", "webp"))
+        }
+        _ => None,
+    }
+}
+
+/// Standard or URL-safe base64, padding optional and line breaks ignored; None on any
+/// other character
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// A safetensors `__metadata__` entry. Images are summarized by format and decoded size,
+/// keeping the base64 text for `--get` and export; `ss_*` values are decoded from the
+/// strings sd-scripts writes and grouped under [`TRAINING_SOURCE`] (archive members keep
+/// their own group); everything else stays a plain string.
+pub fn safetensors_entry(key: &str, value: &str, source: Option<&str>) -> MetadataInfo {
+    let entry =
+        |shown: String, value_type: &str, raw_value: Option<GGUFValue>, source| MetadataInfo {
+            name: key.to_string(),
+            value: shown,
+            value_type: value_type.to_string(),
+            raw_value: raw_value.map(Arc::new),
+            source,
+        };
+    let source = source.map(str::to_string);
+    if let Some(image) = embedded_image(value) {
+        return entry(
+            format!(
+                "{} image, {} (base64)",
+                image.format,
+                format_size(image.bytes.len())
+            ),
+            "image",
+            Some(GGUFValue::String(value.to_string())),
+            source,
+        );
+    }
+    if !key.starts_with("ss_") {
+        return entry(value.to_string(), "string", None, source);
+    }
+
+    let source = source.or_else(|| Some(TRAINING_SOURCE.to_string()));
+    let number = !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'-' | b'+' | b'e' | b'E'));
+    match value {
+        "True" | "False" => {
+            let flag = GGUFValue::Bool(value == "True");
+            entry(flag.to_string(), "bool", Some(flag), source)
+        }
+        "None" => entry(value.to_string(), "null", None, source),
+        _ if number && TIMESTAMP_KEYS.contains(&key) && value.parse::<f64>().is_ok() => {
+            let seconds = value.parse::<f64>().unwrap_or_default();
+            entry(
+                format_timestamp(seconds.max(0.0) as u64),
+                "timestamp",
+                Some(GGUFValue::F64(seconds)),
+                source,
+            )
+        }
+        _ if number && let Ok(integer) = value.parse::<i64>() => {
+            let integer = GGUFValue::I64(integer);
+            entry(integer.to_string(), "i64", Some(integer), source)
+        }
+        _ if number && let Ok(float) = value.parse::<f64>() => {
+            let float = GGUFValue::F64(float);
+            entry(float.to_string(), "f64", Some(float), source)
+        }
+        // Tag frequencies, dataset dirs and bucket info; the explorer opens them as trees
+        _ if value.starts_with(['{', '['])
+            && serde_json::from_str::<serde_json::Value>(value).is_ok() =>
+        {
+            entry(value.to_string(), "json", None, source)
+        }
+        _ => entry(value.to_string(), "string", None, source),
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::tree::{GroupBy, SortOrder};
+
+/// Where the explorer was left for one model, restored the next time it is opened
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Name paths of the expanded groups
+    pub expanded: Vec<Vec<String>>,
+    /// Name path of the selected row
+    pub selected: Vec<String>,
+    pub group_by: GroupBy,
+    pub sort: SortOrder,
+    pub min_size: usize,
+    pub exact: bool,
+    pub storage_order: bool,
+    /// Files left out of the totals with Space in the per-file view
+    pub excluded: Vec<PathBuf>,
+    /// Groups sorted apart from the rest of the tree with `s`, by name path
+    pub sort_overrides: Vec<(Vec<String>, SortOrder)>,
+}
+
+/// `$XDG_CACHE_HOME/safetensors-explorer/sessions`, falling back to `~/.cache`
+fn sessions_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("safetensors-explorer").join("sessions"))
+}
+
+/// Session file for a set of model files, keyed by a hash of their resolved paths
+pub fn session_path(files: &[PathBuf]) -> Option<PathBuf> {
+    let mut resolved: Vec<String> = files
+        .iter()
+        .map(|f| {
+            fs::canonicalize(f)
+                .unwrap_or_else(|_| f.clone())
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    resolved.sort();
+    let hash = xxh3_64(resolved.join("\n").as_bytes());
+    Some(sessions_dir()?.join(format!("{hash:016x}.json")))
+}
+
+/// The saved session, if there is a readable one; a missing or corrupt file is not an error
+pub fn load(path: &Path) -> Option<Session> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save(path: &Path, session: &Session) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let content = serde_json::to_string(session)?;
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
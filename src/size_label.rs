@@ -0,0 +1,320 @@
+//! Size labels: the rounded parameter count of a model (`≈ 7.6B`), the conventional class
+//! it falls in, and whether the `7B`/`70B`/`8x7B` a file is labelled with agrees.
+
+use std::path::Path;
+
+use crate::model::ModelDescription;
+use crate::summary::{self, metadata_string, metadata_usize};
+use crate::tree::{MetadataInfo, TensorInfo};
+use crate::utils::format_parameters;
+
+/// Conventional size classes in billions of parameters. Models are labelled loosely (an
+/// "8B" has 8.0B, a "7B" anywhere from 6.7B to 8.5B), so neighbouring labels share a class
+/// and only labels more than one class apart count as a disagreement.
+pub const BUCKETS: &[(&str, f64)] = &[
+    ("100M", 0.1),
+    ("300M", 0.3),
+    ("0.5B", 0.5),
+    ("1B", 1.0),
+    ("1.5B", 1.5),
+    ("3B", 3.0),
+    ("7B", 7.0),
+    ("13B", 13.0),
+    ("20B", 20.0),
+    ("34B", 34.0),
+    ("70B", 70.0),
+    ("120B", 120.0),
+    ("180B", 180.0),
+    ("405B", 405.0),
+    ("671B", 671.0),
+    ("1T", 1000.0),
+];
+
+/// Index into [`BUCKETS`] of the class closest to `parameters` (on a log scale)
+pub fn bucket_index(parameters: f64) -> usize {
+    let billions = (parameters / 1e9).max(f64::MIN_POSITIVE);
+    BUCKETS
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            let distance = |size: f64| (billions / size).ln().abs();
+            distance(a.1).total_cmp(&distance(b.1))
+        })
+        .map_or(0, |(index, _)| index)
+}
+
+/// How many classes apart two parameter counts are
+pub fn buckets_apart(a: f64, b: f64) -> usize {
+    bucket_index(a).abs_diff(bucket_index(b))
+}
+
+/// Rounded parameter count, e.g. `≈ 7.6B`
+pub fn approx_label(parameters: usize) -> String {
+    format!("≈ {}", format_parameters(parameters))
+}
+
+/// Parameters a size token stands for: `7b`, `1.5B`, `360m`, or `8x7b` for eight experts
+/// of 7B each (56B, an upper bound since the experts share attention); None otherwise
+pub fn parse_size_token(token: &str) -> Option<f64> {
+    let token = token.to_ascii_lowercase();
+    let (count, size) = match token.split_once('x') {
+        Some((count, size)) => (count.parse::<u32>().ok()?, size),
+        None => (1, token.as_str()),
+    };
+    let multiplier = match size.chars().last()? {
+        'b' => 1e9,
+        'm' => 1e6,
+        't' => 1e12,
+        _ => return None,
+    };
+    let number = &size[..size.len() - 1];
+    if !number.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let value: f64 = number.parse().ok()?;
+    (value > 0.0 && count > 0).then_some(value * multiplier * count as f64)
+}
+
+/// The first size token of a name such as `general.size_label` (`8x7B`, `30B-A3B`) or a
+/// file name (`Meta-Llama-3-70B-Instruct.Q4_K_M`), as written and as a parameter count
+pub fn find_size_token(name: &str) -> Option<(String, f64)> {
+    name.split(['-', '_', ' ']).find_map(|token| {
+        // Keep `1.5B` whole, but split `8B.Q4` at the dot
+        std::iter::once(token)
+            .chain(token.split('.'))
+            .find_map(|part| parse_size_token(part).map(|value| (part.to_string(), value)))
+    })
+}
+
+/// A size the files claim, and where the claim comes from
+#[derive(Debug, Clone)]
+pub struct DeclaredSize {
+    /// `general.size_label`, `file name` or `directory name`
+    pub source: &'static str,
+    pub label: String,
+    pub parameters: f64,
+}
+
+/// Computed size of the language model and the sizes its files claim
+#[derive(Debug, Clone)]
+pub struct SizeCheck {
+    pub total: usize,
+    /// Parameters used per token, for mixture-of-experts models with expert counts recorded
+    pub active: Option<usize>,
+    pub declared: Vec<DeclaredSize>,
+}
+
+impl SizeCheck {
+    /// `≈ 46.7B`, or `≈ 46.7B total, ≈ 12.9B active` for a mixture of experts
+    pub fn label(&self) -> String {
+        match self.active {
+            Some(active) => format!(
+                "{} total, {} active",
+                approx_label(self.total),
+                approx_label(active)
+            ),
+            None => approx_label(self.total),
+        }
+    }
+
+    /// Claimed sizes more than one class away from the computed total
+    pub fn disagreements(&self) -> impl Iterator<Item = &DeclaredSize> {
+        self.declared
+            .iter()
+            .filter(|d| buckets_apart(d.parameters, self.total as f64) > 1)
+    }
+
+    /// One line on the claimed sizes, e.g. `general.size_label 8B agrees`; None when
+    /// nothing claims a size
+    pub fn verdict(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .declared
+            .iter()
+            .map(|d| {
+                let agrees = buckets_apart(d.parameters, self.total as f64) <= 1;
+                format!(
+                    "{} {} {}",
+                    d.source,
+                    d.label,
+                    if agrees { "agrees" } else { "disagrees ⚠" }
+                )
+            })
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// Expert count and experts used per token, from GGUF metadata or `config.json`
+pub fn expert_counts(
+    metadata: &[MetadataInfo],
+    config: Option<&serde_json::Value>,
+) -> Option<(usize, usize)> {
+    let from_gguf = metadata_string(metadata, "general.architecture").and_then(|arch| {
+        let get = |suffix: &str| metadata_usize(metadata, &format!("{arch}.{suffix}"));
+        get("expert_count").zip(get("expert_used_count"))
+    });
+    let from_config = || {
+        let config = config?;
+        let text = config.get("text_config").unwrap_or(config);
+        let get = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| text.get(key).and_then(|v| v.as_u64()))
+                .map(|v| v as usize)
+        };
+        get(&["num_local_experts", "num_experts", "n_routed_experts"])
+            .zip(get(&["num_experts_per_tok"]))
+    };
+    from_gguf
+        .or_else(from_config)
+        .filter(|&(count, used)| count > 1 && used > 0 && used < count)
+}
+
+/// Routed expert weights: `...experts.3.w1` in HF checkpoints, `ffn_up_exps` in GGUF;
+/// shared experts run for every token and don't count
+pub fn is_expert_tensor(name: &str) -> bool {
+    name.contains(".experts.") || name.contains("_exps.")
+}
+
+/// Parameters used per token when `used` of `count` experts run
+pub fn active_parameters(tensors: &[TensorInfo], count: usize, used: usize) -> usize {
+    let (experts, rest): (Vec<&TensorInfo>, Vec<&TensorInfo>) =
+        tensors.iter().partition(|t| is_expert_tensor(&t.name));
+    let params = |tensors: Vec<&TensorInfo>| -> usize {
+        tensors
+            .into_iter()
+            .map(crate::quant::logical_parameters)
+            .sum()
+    };
+    params(rest) + params(experts) * used / count
+}
+
+/// Computed size of the language model and the sizes claimed by `general.size_label`, the
+/// first file's name and its directory's name; None without language model tensors
+pub fn size_check(model: &ModelDescription) -> Option<SizeCheck> {
+    let (tensors, metadata) = summary::language_model_parts(model);
+    let total = summary::total_parameters(&tensors);
+    if total == 0 {
+        return None;
+    }
+    let active = expert_counts(&metadata, model.config.as_ref())
+        .map(|(count, used)| active_parameters(&tensors, count, used))
+        .filter(|&active| active < total);
+
+    let mut declared = Vec::new();
+    if let Some((label, parameters)) =
+        metadata_string(&metadata, "general.size_label").and_then(|l| find_size_token(&l))
+    {
+        declared.push(DeclaredSize {
+            source: "general.size_label",
+            label,
+            parameters,
+        });
+    }
+    // The name of a draft or projector file says nothing about the base model
+    let first = model.files.iter().map(|f| f.path.as_path()).find(|path| {
+        !model.mmproj_files.iter().any(|file| file == path)
+            && !model.companions.iter().any(|c| c.file == *path)
+    });
+    let token_in = |name: Option<&std::ffi::OsStr>| find_size_token(&name?.to_string_lossy());
+    // Shards are named `model-00001-of-00004`; the size is in the directory name then
+    let from_name = token_in(first.and_then(Path::file_stem))
+        .map(|found| ("file name", found))
+        .or_else(|| {
+            let resolved = first?.canonicalize().ok()?;
+            token_in(resolved.parent().and_then(Path::file_name))
+                .map(|found| ("directory name", found))
+        });
+    if let Some((source, (label, parameters))) = from_name {
+        declared.push(DeclaredSize {
+            source,
+            label,
+            parameters,
+        });
+    }
+    Some(SizeCheck {
+        total,
+        active,
+        declared,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbouring_classes_agree() {
+        let class = |parameters: f64| BUCKETS[bucket_index(parameters)].0;
+        assert_eq!(class(8.03e9), "7B");
+        assert_eq!(class(6.7e9), "7B");
+        // The boundary between 7B and 13B is their geometric mean, about 9.54B
+        assert_eq!(class(9.5e9), "7B");
+        assert_eq!(class(9.6e9), "13B");
+        assert_eq!(class(70.6e9), "70B");
+        assert_eq!(class(1.0), "100M");
+        assert_eq!(class(5e12), "1T");
+
+        assert_eq!(buckets_apart(8e9, 7e9), 0);
+        assert_eq!(buckets_apart(9.6e9, 7e9), 1);
+        assert_eq!(buckets_apart(7e9, 70e9), 4);
+        assert_eq!(buckets_apart(70e9, 7e9), 4);
+    }
+
+    #[test]
+    fn size_tokens() {
+        assert_eq!(parse_size_token("8x7B"), Some(56e9));
+        assert_eq!(parse_size_token("1.5B"), Some(1.5e9));
+        assert_eq!(parse_size_token("360m"), Some(360e6));
+        assert_eq!(parse_size_token("Q4_K_M"), None);
+        assert_eq!(parse_size_token("M"), None);
+        assert_eq!(parse_size_token("0B"), None);
+
+        assert_eq!(find_size_token("30B-A3B"), Some(("30B".to_string(), 30e9)));
+        assert_eq!(find_size_token("8x7B"), Some(("8x7B".to_string(), 56e9)));
+        assert_eq!(
+            find_size_token("Qwen2.5-1.5B-Instruct"),
+            Some(("1.5B".to_string(), 1.5e9))
+        );
+        assert_eq!(
+            find_size_token("Meta-Llama-3-70B-Instruct.Q4_K_M"),
+            Some(("70B".to_string(), 70e9))
+        );
+        assert_eq!(
+            find_size_token("llama-8B.Q4_K_M"),
+            Some(("8B".to_string(), 8e9))
+        );
+        assert_eq!(find_size_token("Q4_K_M"), None);
+    }
+
+    #[test]
+    fn active_parameters_count_the_routed_experts_used() {
+        let tensor = |name: &str, elements: usize| {
+            TensorInfo::new(
+                name,
+                "F32",
+                vec![elements],
+                elements * 4,
+                "model.safetensors",
+            )
+        };
+        let mut tensors = vec![
+            tensor("model.embed_tokens.weight", 60),
+            tensor("model.layers.0.self_attn.q_proj.weight", 40),
+            tensor("model.layers.0.mlp.shared_expert.up_proj.weight", 20),
+        ];
+        tensors.extend((0..4).map(|i| {
+            tensor(
+                &format!("model.layers.0.mlp.experts.{i}.up_proj.weight"),
+                50,
+            )
+        }));
+        // 120 always run, and 2 of the 4 experts' 200
+        assert_eq!(active_parameters(&tensors, 4, 2), 220);
+
+        let gguf = [
+            tensor("blk.0.attn_q.weight", 40),
+            tensor("blk.0.ffn_up_exps.weight", 400),
+        ];
+        assert_eq!(active_parameters(&gguf, 8, 2), 140);
+    }
+}
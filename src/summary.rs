@@ -0,0 +1,1361 @@
+use crate::gguf::{GGUFLayout, GGUFValue, merge_stats, token_type_counts};
+use crate::model::ModelDescription;
+use crate::report::Report;
+use crate::role::{TensorRole, classify};
+use crate::tree::{
+    DtypeShare, MetadataInfo, TensorInfo, TreeBuilder, TreeLimits, TreeNode, composition_label,
+    dtype_composition,
+};
+use crate::utils::{
+    display_width, fit_to_width, format_parameters, format_shape, format_size, format_thousands,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// One titled block of the model summary panel
+#[derive(Serialize)]
+pub struct SummarySection {
+    pub title: String,
+    #[serde(serialize_with = "serialize_rows")]
+    pub rows: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+struct SummaryRow<'a> {
+    label: &'a str,
+    value: &'a str,
+}
+
+fn serialize_rows<S: serde::Serializer>(
+    rows: &[(String, String)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        rows.iter()
+            .map(|(label, value)| SummaryRow { label, value }),
+    )
+}
+
+impl SummarySection {
+    fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn row(&mut self, label: &str, value: impl Into<String>) {
+        self.rows.push((label.to_string(), value.into()));
+    }
+}
+
+pub fn render_sections(sections: &[SummarySection]) -> Vec<String> {
+    let label_width = sections
+        .iter()
+        .flat_map(|s| s.rows.iter().map(|(label, _)| label.chars().count()))
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for section in sections {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("[{}]", section.title));
+        for (label, value) in &section.rows {
+            lines.push(format!("  {label:<label_width$}  {value}"));
+        }
+    }
+    lines
+}
+
+impl Report for [SummarySection] {
+    fn text_lines(&self) -> Vec<String> {
+        render_sections(self)
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["section", "label", "value"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.iter()
+            .flat_map(|section| {
+                section
+                    .rows
+                    .iter()
+                    .map(|(label, value)| vec![section.title.clone(), label.clone(), value.clone()])
+            })
+            .collect()
+    }
+}
+
+/// Architecture hyperparameters, either declared by metadata or inferred from tensors
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchitectureParams {
+    pub family: Option<String>,
+    pub layers: Option<usize>,
+    pub hidden_size: Option<usize>,
+    pub attention_heads: Option<usize>,
+    pub kv_heads: Option<usize>,
+    pub head_dim: Option<usize>,
+    pub vocab_size: Option<usize>,
+    pub intermediate_size: Option<usize>,
+}
+
+/// Index of the layer a tensor belongs to, e.g. 3 for `model.layers.3.mlp.up_proj.weight`
+pub fn layer_index(name: &str) -> Option<usize> {
+    layer_prefix(name).map(|(index, _)| index)
+}
+
+/// Token and positional embedding tables and output heads, left out of non-embedding
+/// parameter counts. Matched by well-known names, so unusual layouts can slip through.
+pub fn is_embedding(name: &str) -> bool {
+    const EXACT: &[&str] = &[
+        "token_embd.weight",
+        "position_embd.weight",
+        "output.weight",
+        "lm_head.weight",
+    ];
+    const SUFFIXES: &[&str] = &[
+        "embed_tokens.weight",
+        "embed_positions.weight",
+        "word_embeddings.weight",
+        "position_embeddings.weight",
+        "embed_in.weight",
+        "embed_out.weight",
+        "wte.weight",
+        "wpe.weight",
+        ".lm_head.weight",
+    ];
+    EXACT.contains(&name) || SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Parameters of `tensors`, using the dequantized size of GPTQ/AWQ modules
+pub fn total_parameters(tensors: &[TensorInfo]) -> usize {
+    tensors.iter().map(crate::quant::logical_parameters).sum()
+}
+
+/// Parameters held by the tensors [`is_embedding`] picks out
+pub fn embedding_parameters(tensors: &[TensorInfo]) -> usize {
+    tensors
+        .iter()
+        .filter(|t| is_embedding(&t.name))
+        .map(crate::quant::logical_parameters)
+        .sum()
+}
+
+/// Name parts that come right before a layer index (`model.layers.3`, `blk.3`)
+const LAYER_WORDS: &[&str] = &["layers", "layer", "h", "blk", "blocks"];
+
+/// Name prefix of the layer group a tensor belongs to, e.g. `model.layers.3`
+pub fn layer_prefix(name: &str) -> Option<(usize, String)> {
+    let parts: Vec<&str> = name.split('.').collect();
+    parts.windows(2).enumerate().find_map(|(i, pair)| {
+        if !LAYER_WORDS.contains(&pair[0]) {
+            return None;
+        }
+        let index = pair[1].parse().ok()?;
+        Some((index, parts[..i + 2].join(".")))
+    })
+}
+
+/// A tensor name split around its layer index, e.g. `model.layers`, 3 and
+/// `mlp.up_proj.weight`; None outside numbered layers
+pub fn split_layer_name(name: &str) -> Option<(String, usize, String)> {
+    let parts: Vec<&str> = name.split('.').collect();
+    parts.windows(2).enumerate().find_map(|(i, pair)| {
+        if !LAYER_WORDS.contains(&pair[0]) || i + 2 >= parts.len() {
+            return None;
+        }
+        let index = pair[1].parse().ok()?;
+        Some((parts[..=i].join("."), index, parts[i + 2..].join(".")))
+    })
+}
+
+/// Total size of one transformer layer
+pub struct LayerSize {
+    pub index: usize,
+    /// Group path of the layer in the prefix tree, from its first tensor
+    pub prefix: String,
+    pub tensors: usize,
+    pub bytes: usize,
+}
+
+/// Per-layer totals ordered by layer index
+pub fn layer_sizes(tensors: &[TensorInfo]) -> Vec<LayerSize> {
+    let mut layers: Vec<LayerSize> = Vec::new();
+    for tensor in tensors {
+        let Some((index, prefix)) = layer_prefix(&tensor.name) else {
+            continue;
+        };
+        match layers.iter_mut().find(|l| l.index == index) {
+            Some(layer) => {
+                layer.tensors += 1;
+                layer.bytes += tensor.size_bytes;
+            }
+            None => layers.push(LayerSize {
+                index,
+                prefix,
+                tensors: 1,
+                bytes: tensor.size_bytes,
+            }),
+        }
+    }
+    layers.sort_by_key(|l| l.index);
+    layers
+}
+
+/// One bar per layer scaled so the largest layer fills `width` columns
+pub fn layer_chart_lines(layers: &[LayerSize], width: usize) -> Vec<String> {
+    let max_bytes = layers.iter().map(|l| l.bytes).max().unwrap_or(0);
+    let index_width = layers
+        .iter()
+        .map(|l| l.index.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let sizes: Vec<String> = layers.iter().map(|l| format_size(l.bytes)).collect();
+    let size_width = sizes.iter().map(|s| s.len()).max().unwrap_or(0);
+    // "L<index> " before the bar, " <size>" after it
+    let bar_width = width
+        .saturating_sub(index_width + 2 + size_width + 1)
+        .max(10);
+
+    layers
+        .iter()
+        .zip(&sizes)
+        .map(|(layer, size)| {
+            let filled = if max_bytes == 0 {
+                0
+            } else {
+                // Any non-empty layer gets at least one block so it doesn't look missing
+                ((layer.bytes as f64 / max_bytes as f64 * bar_width as f64).round() as usize)
+                    .max(usize::from(layer.bytes > 0))
+            };
+            format!(
+                "L{:<index_width$} {}{} {:>size_width$}",
+                layer.index,
+                "█".repeat(filled),
+                " ".repeat(bar_width - filled),
+                size
+            )
+        })
+        .collect()
+}
+
+fn find_tensor<'a>(tensors: &'a [TensorInfo], suffixes: &[&str]) -> Option<&'a TensorInfo> {
+    tensors
+        .iter()
+        .find(|t| suffixes.iter().any(|suffix| t.name.ends_with(suffix)))
+}
+
+/// The token embedding table, found by its usual names
+pub fn token_embedding(tensors: &[TensorInfo]) -> Option<&TensorInfo> {
+    find_tensor(
+        tensors,
+        &[
+            "embed_tokens.weight",
+            "token_embd.weight",
+            "wte.weight",
+            "embed_in.weight",
+            "word_embeddings.weight",
+        ],
+    )
+}
+
+/// The output head (`lm_head.weight`, `output.weight`); None when the model ties it to the
+/// token embedding
+pub fn output_head(tensors: &[TensorInfo]) -> Option<&TensorInfo> {
+    tensors
+        .iter()
+        .find(|t| t.name == "output.weight" || t.name.ends_with("lm_head.weight"))
+}
+
+/// Guess the architecture from characteristic tensor names and shapes
+pub fn infer_architecture(tensors: &[TensorInfo]) -> ArchitectureParams {
+    let has = |pattern: &str| tensors.iter().any(|t| t.name.contains(pattern));
+    let ends = |suffix: &str| tensors.iter().any(|t| t.name.ends_with(suffix));
+
+    let family = if has("blk.") && has("attn_q") {
+        Some("llama.cpp layout")
+    } else if has("gpt_neox.") {
+        Some("GPT-NeoX")
+    } else if has(".attention.self.query") {
+        Some("BERT")
+    } else if has("self_attn.qkv_proj") {
+        Some("Phi-3")
+    } else if has("mlp.fc1") && has("self_attn.dense") {
+        Some("Phi")
+    } else if has("attn.c_attn") {
+        Some("GPT-2")
+    } else if has("pre_feedforward_layernorm") {
+        Some("Gemma 2/3")
+    } else if ends("self_attn.q_proj.bias") {
+        Some("Qwen2")
+    } else if has("self_attn.q_proj") {
+        Some("Llama/Mistral")
+    } else {
+        None
+    };
+
+    let layers = tensors
+        .iter()
+        .filter_map(|t| layer_index(&t.name))
+        .max()
+        .map(|max| max + 1);
+
+    // An MLX-quantized embedding packs several columns into each U32
+    let embedding = token_embedding(tensors).map(|t| t.logical_shape().to_vec());
+    let (vocab_size, hidden_size) = match embedding.as_deref() {
+        Some([vocab, hidden]) => (Some(*vocab), Some(*hidden)),
+        _ => (None, None),
+    };
+
+    let out_dim =
+        |suffixes: &[&str]| find_tensor(tensors, suffixes).and_then(|t| t.shape.first().copied());
+    let q_dim = out_dim(&[
+        "q_proj.weight",
+        "attn_q.weight",
+        ".attention.self.query.weight",
+    ]);
+    let k_dim = out_dim(&[
+        "k_proj.weight",
+        "attn_k.weight",
+        ".attention.self.key.weight",
+    ]);
+    let head_dim = out_dim(&["q_norm.weight", "attn_q_norm.weight"]);
+    let intermediate_size = out_dim(&[
+        "gate_proj.weight",
+        "up_proj.weight",
+        "ffn_up.weight",
+        "mlp.fc1.weight",
+        "dense_h_to_4h.weight",
+        "intermediate.dense.weight",
+    ])
+    .or_else(|| out_dim(&["gate_up_proj.weight"]).map(|d| d / 2));
+
+    ArchitectureParams {
+        family: family.map(str::to_string),
+        layers,
+        hidden_size,
+        attention_heads: q_dim.zip(head_dim).map(|(q, h)| q / h),
+        kv_heads: k_dim.zip(head_dim).map(|(k, h)| k / h),
+        head_dim,
+        vocab_size,
+        intermediate_size,
+    }
+}
+
+/// Hyperparameters declared by an HF `config.json`
+pub fn declared_from_config(config: &serde_json::Value) -> ArchitectureParams {
+    // Multimodal configs nest the language model under `text_config`
+    let text = config.get("text_config").unwrap_or(config);
+    let get = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| text.get(key).and_then(|v| v.as_u64()))
+            .map(|v| v as usize)
+    };
+
+    let family = config
+        .get("architectures")
+        .and_then(|a| a.get(0))
+        .and_then(|a| a.as_str())
+        .or_else(|| config.get("model_type").and_then(|m| m.as_str()))
+        .map(str::to_string);
+    let hidden_size = get(&["hidden_size", "n_embd", "d_model"]);
+    let attention_heads = get(&["num_attention_heads", "n_head"]);
+
+    ArchitectureParams {
+        family,
+        layers: get(&["num_hidden_layers", "n_layer", "num_layers"]),
+        hidden_size,
+        attention_heads,
+        kv_heads: get(&["num_key_value_heads"]).or(attention_heads),
+        head_dim: get(&["head_dim"])
+            .or_else(|| hidden_size.zip(attention_heads).map(|(h, n)| h / n)),
+        vocab_size: get(&["vocab_size"]),
+        intermediate_size: get(&["intermediate_size", "n_inner", "ffn_dim"]),
+    }
+}
+
+pub fn metadata_entry<'a>(metadata: &'a [MetadataInfo], key: &str) -> Option<&'a MetadataInfo> {
+    metadata.iter().find(|m| m.name == key)
+}
+
+/// Token type histogram as one line, e.g. "151,643 normal, 293 control, 256 byte"
+pub fn token_type_summary(counts: &[(&str, usize)]) -> String {
+    counts
+        .iter()
+        .map(|(name, count)| format!("{} {name}", format_thousands(*count)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Numeric metadata value, accepting both typed GGUF values and plain strings
+pub fn metadata_usize(metadata: &[MetadataInfo], key: &str) -> Option<usize> {
+    let entry = metadata_entry(metadata, key)?;
+    match entry.raw_value.as_deref() {
+        Some(GGUFValue::U8(v)) => Some(*v as usize),
+        Some(GGUFValue::U16(v)) => Some(*v as usize),
+        Some(GGUFValue::U32(v)) => Some(*v as usize),
+        Some(GGUFValue::U64(v)) => Some(*v as usize),
+        Some(GGUFValue::I32(v)) => usize::try_from(*v).ok(),
+        Some(GGUFValue::I64(v)) => usize::try_from(*v).ok(),
+        _ => entry.value.trim_matches('"').parse().ok(),
+    }
+}
+
+/// Floating-point metadata value, accepting integers and plain strings too
+pub fn metadata_f64(metadata: &[MetadataInfo], key: &str) -> Option<f64> {
+    let entry = metadata_entry(metadata, key)?;
+    match entry.raw_value.as_deref() {
+        Some(GGUFValue::F32(v)) => Some(*v as f64),
+        Some(GGUFValue::F64(v)) => Some(*v),
+        _ => metadata_usize(metadata, key)
+            .map(|v| v as f64)
+            .or_else(|| entry.value.trim_matches('"').parse().ok()),
+    }
+}
+
+pub fn metadata_string(metadata: &[MetadataInfo], key: &str) -> Option<String> {
+    let entry = metadata_entry(metadata, key)?;
+    match entry.raw_value.as_deref() {
+        Some(GGUFValue::String(s)) => Some(s.clone()),
+        _ => Some(entry.value.clone()),
+    }
+}
+
+/// Value as printed by `--get`: strings unquoted, arrays as compact JSON, scalars as-is
+pub fn metadata_raw_text(entry: &MetadataInfo) -> String {
+    match entry.raw_value.as_deref() {
+        Some(GGUFValue::String(s)) => s.clone(),
+        Some(array @ GGUFValue::Array(..)) => array.to_json().to_string(),
+        Some(value) => value.to_string(),
+        None => entry.value.clone(),
+    }
+}
+
+pub fn metadata_array_len(metadata: &[MetadataInfo], key: &str) -> Option<usize> {
+    match metadata_entry(metadata, key)?.raw_value.as_deref() {
+        Some(GGUFValue::Array(_, items)) => Some(items.len()),
+        _ => None,
+    }
+}
+
+/// Hyperparameters declared in GGUF metadata under the `<arch>.` key prefix
+pub fn declared_from_gguf(metadata: &[MetadataInfo]) -> Option<ArchitectureParams> {
+    let arch = metadata_string(metadata, "general.architecture")?;
+    let get = |suffix: &str| metadata_usize(metadata, &format!("{arch}.{suffix}"));
+
+    let hidden_size = get("embedding_length");
+    let attention_heads = get("attention.head_count");
+    Some(ArchitectureParams {
+        family: Some(arch.clone()),
+        layers: get("block_count"),
+        hidden_size,
+        attention_heads,
+        kv_heads: get("attention.head_count_kv").or(attention_heads),
+        head_dim: get("attention.key_length")
+            .or_else(|| hidden_size.zip(attention_heads).map(|(h, n)| h / n)),
+        vocab_size: get("vocab_size")
+            .or_else(|| metadata_array_len(metadata, "tokenizer.ggml.tokens")),
+        intermediate_size: get("feed_forward_length"),
+    })
+}
+
+/// Rotary embedding and context settings as declared; None where nothing was recorded
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RopeParams {
+    pub freq_base: Option<f64>,
+    /// Scaling scheme, e.g. `linear` or `yarn`
+    pub scaling: Option<String>,
+    pub factor: Option<f64>,
+    /// Context length the model was trained with before scaling
+    pub original_context: Option<usize>,
+    pub context_length: Option<usize>,
+}
+
+/// Rope settings from GGUF `<arch>.rope.*` and `<arch>.context_length` keys
+pub fn rope_from_gguf(metadata: &[MetadataInfo]) -> Option<RopeParams> {
+    let arch = metadata_string(metadata, "general.architecture")?;
+    let key = |suffix: &str| format!("{arch}.{suffix}");
+    Some(RopeParams {
+        freq_base: metadata_f64(metadata, &key("rope.freq_base")),
+        scaling: metadata_string(metadata, &key("rope.scaling.type")),
+        factor: metadata_f64(metadata, &key("rope.scaling.factor")),
+        original_context: metadata_usize(metadata, &key("rope.scaling.original_context_length")),
+        context_length: metadata_usize(metadata, &key("context_length")),
+    })
+}
+
+/// Rope settings from `rope_theta`, `rope_scaling` and `max_position_embeddings` in an HF
+/// `config.json`
+pub fn rope_from_config(config: &serde_json::Value) -> RopeParams {
+    let text = config.get("text_config").unwrap_or(config);
+    let scaling = text.get("rope_scaling").filter(|v| v.is_object());
+    let scaling_field = |key: &str| scaling.and_then(|s| s.get(key));
+    RopeParams {
+        freq_base: text.get("rope_theta").and_then(|v| v.as_f64()),
+        scaling: scaling_field("rope_type")
+            .or_else(|| scaling_field("type"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        factor: scaling_field("factor").and_then(|v| v.as_f64()),
+        original_context: scaling_field("original_max_position_embeddings")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize),
+        context_length: text
+            .get("max_position_embeddings")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize),
+    }
+}
+
+/// Base frequency llama.cpp assumes when `rope.freq_base` is missing
+const DEFAULT_ROPE_FREQ_BASE: f64 = 10000.0;
+
+/// Scaling scheme, base frequency and context lengths, with defaults for missing keys
+fn rope_section(params: &RopeParams) -> SummarySection {
+    let mut section = SummarySection::new("RoPE and context");
+    let default = |value: String| format!("{value} (default)");
+
+    let scaling = params.scaling.as_deref().unwrap_or("none");
+    let scaled = scaling != "none";
+    section.row(
+        "Scaling",
+        match &params.scaling {
+            Some(scaling) => scaling.clone(),
+            None => default("none".to_string()),
+        },
+    );
+    section.row(
+        "Base frequency",
+        match params.freq_base {
+            Some(base) => base.to_string(),
+            None => default(DEFAULT_ROPE_FREQ_BASE.to_string()),
+        },
+    );
+    let factor = params.factor.unwrap_or(1.0);
+    section.row(
+        "Scaling factor",
+        match params.factor {
+            Some(factor) => factor.to_string(),
+            None => default("1".to_string()),
+        },
+    );
+
+    let original = params.original_context.or(params.context_length);
+    let extended = original
+        .filter(|_| scaled && factor > 1.0)
+        .map(|original| (original as f64 * factor) as usize);
+    let effective = params.context_length.max(extended).or(original);
+    let tokens = |n: Option<usize>| n.map_or("unknown".to_string(), format_thousands);
+    section.row(
+        "Original context",
+        match params.original_context {
+            Some(original) => format_thousands(original),
+            None => default(tokens(original)),
+        },
+    );
+    section.row("Context length", tokens(params.context_length));
+    section.row(
+        "Effective context",
+        match extended {
+            Some(extended) if Some(extended) == effective => format!(
+                "{} ({} × {factor})",
+                format_thousands(extended),
+                tokens(original)
+            ),
+            _ => tokens(effective),
+        },
+    );
+    section
+}
+
+fn architecture_section(
+    declared: Option<(&str, ArchitectureParams)>,
+    inferred: &ArchitectureParams,
+) -> SummarySection {
+    let mut section = SummarySection::new("Architecture");
+    let source = declared.as_ref().map(|(source, _)| *source);
+    let declared = declared.map(|(_, params)| params).unwrap_or_default();
+
+    let family_value = match (&declared.family, &inferred.family) {
+        (Some(d), Some(i)) => format!("{d} (inferred layout: {i})"),
+        (Some(d), None) => d.clone(),
+        (None, Some(i)) => format!("{i} (inferred)"),
+        (None, None) => "unknown".to_string(),
+    };
+    section.row("Family", family_value);
+    if let Some(source) = source {
+        section.row("Declared by", source);
+    }
+
+    let fields = [
+        ("Layers", declared.layers, inferred.layers),
+        ("Hidden size", declared.hidden_size, inferred.hidden_size),
+        (
+            "Attention heads",
+            declared.attention_heads,
+            inferred.attention_heads,
+        ),
+        ("KV heads", declared.kv_heads, inferred.kv_heads),
+        ("Head dim", declared.head_dim, inferred.head_dim),
+        ("Vocab size", declared.vocab_size, inferred.vocab_size),
+        (
+            "Intermediate size",
+            declared.intermediate_size,
+            inferred.intermediate_size,
+        ),
+    ];
+    for (label, declared_value, inferred_value) in fields {
+        let value = match (declared_value, inferred_value) {
+            (Some(d), Some(i)) if d == i => format!("{d}"),
+            (Some(d), Some(i)) => format!("{d} declared, {i} inferred ⚠ mismatch"),
+            (Some(d), None) => format!("{d} declared"),
+            (None, Some(i)) => format!("{i} inferred"),
+            (None, None) => continue,
+        };
+        section.row(label, value);
+    }
+    section
+}
+
+pub fn build_summary(
+    model: &ModelDescription,
+    files: &[std::path::PathBuf],
+) -> Vec<SummarySection> {
+    let mut overview = SummarySection::new("Overview");
+    overview.row("Files", files.len().to_string());
+    overview.row("Tensors", model.tensors.len().to_string());
+    overview.row(
+        "Parameters",
+        format!(
+            "{} ({})",
+            format_parameters(model.total_parameters),
+            model.total_parameters
+        ),
+    );
+    if let Some(check) = crate::size_label::size_check(model) {
+        let value = match check.verdict() {
+            Some(verdict) => format!("{}; {verdict}", check.label()),
+            None => check.label(),
+        };
+        overview.row("Size", value);
+    }
+    let embedding = embedding_parameters(&model.tensors);
+    let non_embedding = model.total_parameters.saturating_sub(embedding);
+    overview.row(
+        "Non-embedding parameters",
+        format!(
+            "{} ({non_embedding}), excluding {} in embeddings and output heads",
+            format_parameters(non_embedding),
+            format_parameters(embedding)
+        ),
+    );
+    overview.row(
+        "Tensor data",
+        format_size(model.tensors.iter().map(|t| t.size_bytes).sum()),
+    );
+    overview.row("Metadata keys", model.metadata.len().to_string());
+    if !model.gguf_layouts.is_empty() {
+        let padding: u64 = model
+            .gguf_layouts
+            .iter()
+            .map(|(_, l)| l.padding_bytes())
+            .sum();
+        let file_bytes: u64 = model.gguf_layouts.iter().map(|(_, l)| l.file_size).sum();
+        overview.row(
+            "Padding overhead",
+            format!(
+                "{} ({:.1}%)",
+                format_size(padding as usize),
+                padding as f64 / file_bytes.max(1) as f64 * 100.0
+            ),
+        );
+    }
+    if let Some(quant) = &model.quantization {
+        let settings: Vec<String> = [
+            quant.bits.map(|b| format!("{b}-bit")),
+            quant.group_size.map(|g| format!("group {g}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let settings = match settings.as_slice() {
+            [] => String::new(),
+            settings => format!(" ({})", settings.join(", ")),
+        };
+        overview.row(
+            "Quantization",
+            format!("{} quantized{settings}", quant.scheme),
+        );
+        overview.row(
+            "Quantized modules",
+            format!(
+                "{} ({:.2} effective bits/weight)",
+                quant.modules, quant.bits_per_weight
+            ),
+        );
+    }
+
+    // Companions get sections of their own; the rest describes the language model
+    let (tensors, metadata) = language_model_parts(model);
+    let (vision_tensors, vision_metadata) = vision_parts(model);
+    if !model.companions.is_empty() && !tensors.is_empty() {
+        let base: usize = tensors.iter().map(|t| t.size_bytes).sum();
+        let mut total = base;
+        let mut parts = vec![format!(
+            "{} {}",
+            crate::companion::base_label(&model.companions).to_lowercase(),
+            format_size(base)
+        )];
+        for companion in &model.companions {
+            let bytes: usize = crate::companion::companion_tensors(model, companion)
+                .map(|t| t.size_bytes)
+                .sum();
+            total += bytes;
+            parts.push(format!(
+                "{} {}",
+                crate::companion::component_label(&model.companions, companion).to_lowercase(),
+                format_size(bytes)
+            ));
+        }
+        overview.row(
+            "Components",
+            format!("{} = {}", parts.join(" + "), format_size(total)),
+        );
+    }
+
+    let mut sections = vec![overview];
+    if !model.components.is_empty() {
+        sections.push(components_section(model));
+    }
+    if !model.companions.is_empty() && !tensors.is_empty() {
+        sections.push(companions_section(model));
+    }
+    if !tensors.is_empty() {
+        let inferred = infer_architecture(&tensors);
+        let declared = declared_from_gguf(&metadata)
+            .map(|params| ("GGUF metadata", params))
+            .or_else(|| {
+                model
+                    .config
+                    .as_ref()
+                    .map(|config| ("config.json", declared_from_config(config)))
+            });
+        let mut architecture = architecture_section(declared, &inferred);
+        if let Some(finding) = crate::check::vocab_check(model) {
+            architecture.row(
+                "Vocab check",
+                format!("{}: {}", finding.severity.label(), finding.message),
+            );
+        }
+        sections.push(architecture);
+        let rope =
+            rope_from_gguf(&metadata).or_else(|| model.config.as_ref().map(rope_from_config));
+        sections.extend(rope.as_ref().map(rope_section));
+    }
+    sections.extend(vision_section(&vision_metadata, &vision_tensors));
+    sections.extend(tokenizer_section(&metadata));
+    sections.extend(imatrix_section(&metadata, &tensors));
+    sections.extend(modelspec_section(&metadata));
+    sections.extend(training_section(&metadata));
+    if !model.tensors.is_empty() {
+        sections.push(role_section(&model.tensors));
+    }
+    sections.push(composition_section(&model.tensors));
+    sections
+}
+
+/// A GGUF file holds a vision projector when its architecture is `clip` or its name follows
+/// the `mmproj-*.gguf` convention
+pub fn is_mmproj(path: &Path, metadata: &[MetadataInfo]) -> bool {
+    metadata_string(metadata, "general.architecture").as_deref() == Some("clip")
+        || path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().to_lowercase().starts_with("mmproj"))
+}
+
+fn file_label(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Metadata group of a vision projector file
+pub fn mmproj_source(path: &Path) -> String {
+    format!("Vision projector: {}", file_label(path))
+}
+
+/// Prefix of the metadata groups computed from a file's layout rather than read from it
+pub const FILE_INFO_PREFIX: &str = "File info: ";
+
+/// Metadata group with the data section layout of a GGUF file
+pub fn file_info_source(path: &Path) -> String {
+    format!("{FILE_INFO_PREFIX}{}", file_label(path))
+}
+
+/// True when a metadata entry was read from (or computed for) one of `mmproj_files`
+pub fn metadata_in_mmproj(mmproj_files: &[PathBuf], entry: &MetadataInfo) -> bool {
+    entry.source.as_deref().is_some_and(|source| {
+        mmproj_files
+            .iter()
+            .any(|file| source == mmproj_source(file) || source == file_info_source(file))
+    })
+}
+
+/// Tensors and metadata of the language model, without any vision projector, draft model
+/// or adapter loaded with it
+pub fn language_model_parts(model: &ModelDescription) -> (Vec<TensorInfo>, Vec<MetadataInfo>) {
+    let tensors = model
+        .tensors
+        .iter()
+        .filter(|t| {
+            !model.mmproj_files.contains(&t.source_file)
+                && crate::companion::companion_of(&model.companions, t).is_none()
+        })
+        .cloned()
+        .collect();
+    let metadata = model
+        .metadata
+        .iter()
+        .filter(|m| {
+            !metadata_in_mmproj(&model.mmproj_files, m)
+                && !crate::companion::metadata_in_companion(&model.companions, m)
+        })
+        .cloned()
+        .collect();
+    (tensors, metadata)
+}
+
+/// Tensors and metadata of the vision projector files
+fn vision_parts(model: &ModelDescription) -> (Vec<TensorInfo>, Vec<MetadataInfo>) {
+    let tensors = model
+        .tensors
+        .iter()
+        .filter(|t| model.mmproj_files.contains(&t.source_file))
+        .cloned()
+        .collect();
+    let metadata = model
+        .metadata
+        .iter()
+        .filter(|m| metadata_in_mmproj(&model.mmproj_files, m))
+        .cloned()
+        .collect();
+    (tensors, metadata)
+}
+
+/// Width of the embeddings the projector hands to the language model: the declared
+/// `clip.vision.projection_dim`, else the output rows of the last `mm.N.weight`
+fn projector_output_dim(metadata: &[MetadataInfo], tensors: &[TensorInfo]) -> Option<usize> {
+    metadata_usize(metadata, "clip.vision.projection_dim").or_else(|| {
+        tensors
+            .iter()
+            .filter_map(|t| {
+                let index: usize = t
+                    .name
+                    .strip_prefix("mm.")?
+                    .strip_suffix(".weight")?
+                    .parse()
+                    .ok()?;
+                Some((index, t))
+            })
+            .max_by_key(|(index, _)| *index)
+            .and_then(|(_, t)| t.shape.first().copied())
+    })
+}
+
+/// A warning when the vision projector's output width differs from the embedding width of
+/// the language model loaded with it
+pub fn projector_mismatch(model: &ModelDescription) -> Option<String> {
+    let (tensors, metadata) = language_model_parts(model);
+    if model.mmproj_files.is_empty() || tensors.is_empty() {
+        return None;
+    }
+    let (vision_tensors, vision_metadata) = vision_parts(model);
+    let projected = projector_output_dim(&vision_metadata, &vision_tensors)?;
+    let hidden = declared_from_gguf(&metadata)
+        .and_then(|params| params.hidden_size)
+        .or_else(|| infer_architecture(&tensors).hidden_size)?;
+    (projected != hidden).then(|| {
+        format!(
+            "Vision projector outputs {projected}-dim embeddings but the language model's embedding dim is {hidden}; the mmproj likely belongs to another model"
+        )
+    })
+}
+
+/// Vision encoder and projector settings from `clip.*` keys; None without an mmproj file
+fn vision_section(metadata: &[MetadataInfo], tensors: &[TensorInfo]) -> Option<SummarySection> {
+    if metadata.is_empty() && tensors.is_empty() {
+        return None;
+    }
+    let mut section = SummarySection::new("Vision projector");
+    let keys = [
+        ("Projector type", "clip.projector_type"),
+        ("Vision embedding", "clip.vision.embedding_length"),
+        ("Vision layers", "clip.vision.block_count"),
+        ("Attention heads", "clip.vision.attention.head_count"),
+        ("Patch size", "clip.vision.patch_size"),
+        ("Image size", "clip.vision.image_size"),
+    ];
+    for (label, key) in keys {
+        if let Some(value) = metadata_string(metadata, key) {
+            section.row(label, value);
+        }
+    }
+    if let Some(dim) = projector_output_dim(metadata, tensors) {
+        section.row("Projection dim", dim.to_string());
+    }
+    section.row(
+        "Size",
+        format!(
+            "{} in {} tensors",
+            format_size(tensors.iter().map(|t| t.size_bytes).sum()),
+            tensors.len()
+        ),
+    );
+    Some(section)
+}
+
+/// Keys llama-quantize writes when quantizing with an importance matrix, with their labels
+const IMATRIX_KEYS: &[(&str, &str)] = &[
+    ("quantize.imatrix.file", "File"),
+    ("quantize.imatrix.dataset", "Dataset"),
+    ("quantize.imatrix.entries_count", "Entries"),
+    ("quantize.imatrix.chunks_count", "Chunks"),
+];
+
+/// True when the GGUF metadata records an importance matrix
+pub fn has_imatrix(metadata: &[MetadataInfo]) -> bool {
+    metadata
+        .iter()
+        .any(|m| m.name.starts_with("quantize.imatrix."))
+}
+
+/// Tensors stored in one of the IQ (importance-quant) types
+pub fn iq_tensor_count(tensors: &[TensorInfo]) -> usize {
+    tensors.iter().filter(|t| t.dtype.starts_with("IQ")).count()
+}
+
+/// Stability AI model spec (`modelspec.*`) keys, labelled by their suffix; None without them
+fn modelspec_section(metadata: &[MetadataInfo]) -> Option<SummarySection> {
+    let mut section = SummarySection::new("Model spec");
+    let mut fields: Vec<(&str, &MetadataInfo)> = metadata
+        .iter()
+        .filter_map(|entry| Some((entry.name.strip_prefix("modelspec.")?, entry)))
+        .collect();
+    // The spec version first, then the rest by name
+    fields.sort_by_key(|(field, _)| (*field != "sai_model_spec", *field));
+    for (field, entry) in fields {
+        let label = match field {
+            "sai_model_spec" => "Spec version".to_string(),
+            field => {
+                let words = field.replace('_', " ");
+                let mut chars = words.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            }
+        };
+        section.row(&label, entry.value.clone());
+    }
+    (!section.rows.is_empty()).then_some(section)
+}
+
+/// The headline kohya-ss training settings of a LoRA or fine-tune; None without `ss_*` keys
+fn training_section(metadata: &[MetadataInfo]) -> Option<SummarySection> {
+    let mut section = SummarySection::new("Training");
+    for (key, label) in crate::sd_metadata::TRAINING_SUMMARY_KEYS {
+        if let Some(entry) = metadata_entry(metadata, key)
+            && entry.value_type != "null"
+        {
+            section.row(label, entry.value.clone());
+        }
+    }
+    (!section.rows.is_empty()).then_some(section)
+}
+
+/// Importance matrix provenance; None unless the model has imatrix keys or IQ tensors
+fn imatrix_section(metadata: &[MetadataInfo], tensors: &[TensorInfo]) -> Option<SummarySection> {
+    let mut section = SummarySection::new("Imatrix");
+    for (key, label) in IMATRIX_KEYS {
+        if let Some(value) = metadata_string(metadata, key) {
+            section.row(label, value);
+        }
+    }
+    // Keys newer than this list still show up, under their own name
+    for entry in metadata
+        .iter()
+        .filter(|m| m.name.starts_with("quantize.imatrix."))
+        .filter(|m| !IMATRIX_KEYS.iter().any(|(key, _)| *key == m.name))
+    {
+        section.row(
+            &entry.name["quantize.imatrix.".len()..],
+            entry.value.clone(),
+        );
+    }
+    if section.rows.is_empty() {
+        let iq = iq_tensor_count(tensors);
+        if iq == 0 {
+            return None;
+        }
+        section.row(
+            "None recorded",
+            format!(
+                "{iq} IQ tensors quantized without an importance matrix are usually low quality"
+            ),
+        );
+    }
+    Some(section)
+}
+
+/// Tokenizer facts from GGUF `tokenizer.ggml.*` keys or the HF "Tokenizer" group; None
+/// when the model carries neither
+fn tokenizer_section(metadata: &[MetadataInfo]) -> Option<SummarySection> {
+    let mut section = SummarySection::new("Tokenizer");
+    if let Some(model) = metadata_string(metadata, "tokenizer.ggml.model") {
+        section.row("Model", model);
+    } else if let Some(model) = metadata_string(metadata, "tokenizer.model_type") {
+        section.row("Model", model);
+    }
+    let vocab = metadata_array_len(metadata, "tokenizer.ggml.tokens")
+        .or_else(|| metadata_usize(metadata, "tokenizer.vocab_size"));
+    if let Some(vocab) = vocab {
+        section.row("Vocab size", format_thousands(vocab));
+    }
+    if let Some(merges) = metadata_array_len(metadata, "tokenizer.ggml.merges")
+        .or_else(|| metadata_usize(metadata, "tokenizer.merges"))
+    {
+        section.row("Merges", format_thousands(merges));
+    }
+    if let Some(GGUFValue::Array(_, rules)) = metadata_entry(metadata, "tokenizer.ggml.merges")
+        .and_then(|entry| entry.raw_value.as_deref())
+    {
+        let stats = merge_stats(rules);
+        if let (Some(longest), Some(shortest)) = (stats.longest, stats.shortest) {
+            section.row(
+                "Merge results",
+                format!(
+                    "longest {longest:?} ({} chars), shortest {shortest:?} ({} chars)",
+                    longest.chars().count(),
+                    shortest.chars().count()
+                ),
+            );
+        }
+    }
+    if let Some(GGUFValue::Array(_, types)) = metadata_entry(metadata, "tokenizer.ggml.token_type")
+        .and_then(|entry| entry.raw_value.as_deref())
+    {
+        section.row("Token types", token_type_summary(&token_type_counts(types)));
+    }
+    if let Some(added) = metadata_usize(metadata, "tokenizer.added_tokens") {
+        section.row("Added tokens", format_thousands(added));
+    }
+    if section.rows.is_empty() {
+        None
+    } else {
+        Some(section)
+    }
+}
+
+/// Parameters and bytes of each model subdirectory; tensors outside them are counted
+/// under "(top level)"
+fn components_section(model: &ModelDescription) -> SummarySection {
+    let mut section = SummarySection::new("Components");
+    let mut totals: Vec<(&str, usize, usize)> = model
+        .components
+        .iter()
+        .map(|name| (name.as_str(), 0, 0))
+        .collect();
+    let mut loose = (0, 0);
+    for tensor in &model.tensors {
+        let params = crate::quant::logical_parameters(tensor);
+        // Nested components share a prefix; the longest one is where the tensor lives
+        let component = totals
+            .iter_mut()
+            .filter(|(name, ..)| {
+                tensor
+                    .name
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .max_by_key(|(name, ..)| name.len());
+        match component {
+            Some((_, p, b)) => {
+                *p += params;
+                *b += tensor.size_bytes;
+            }
+            None => {
+                loose.0 += params;
+                loose.1 += tensor.size_bytes;
+            }
+        }
+    }
+    if loose.1 > 0 {
+        totals.push(("(top level)", loose.0, loose.1));
+    }
+    for (name, params, bytes) in totals {
+        section.row(
+            name,
+            format!(
+                "{} parameters, {}",
+                format_parameters(params),
+                format_size(bytes)
+            ),
+        );
+    }
+    section
+}
+
+/// One row per companion file: what it is taken for and why, its totals, and how it fits
+/// the base model
+fn companions_section(model: &ModelDescription) -> SummarySection {
+    use crate::companion;
+    let mut section = SummarySection::new("Companions");
+    for c in &model.companions {
+        let tensors: Vec<TensorInfo> = companion::companion_tensors(model, c).cloned().collect();
+        let mut value = format!(
+            "{} ({}); {} tensors, {} parameters, {}",
+            file_label(&c.file),
+            c.reason,
+            tensors.len(),
+            format_parameters(total_parameters(&tensors)),
+            format_size(tensors.iter().map(|t| t.size_bytes).sum())
+        );
+        if let Some((_, fit)) = companion::companion_fit(model, c) {
+            value.push_str(&format!("; {fit}"));
+        }
+        section.row(&companion::component_label(&model.companions, c), value);
+    }
+    section
+}
+
+/// Dtype mix of every top-level group of the prefix tree
+/// Parameters and bytes of each tensor role with their shares of the model, roles in a
+/// fixed order and those without tensors left out
+fn role_section(tensors: &[TensorInfo]) -> SummarySection {
+    let mut section = SummarySection::new("By role");
+    let mut totals = [(0usize, 0usize); TensorRole::ALL.len()];
+    for tensor in tensors {
+        // `ALL` lists the roles in declaration order
+        let slot = classify(&tensor.name) as usize;
+        totals[slot].0 += crate::quant::logical_parameters(tensor);
+        totals[slot].1 += tensor.size_bytes;
+    }
+    let all_parameters: usize = totals.iter().map(|(p, _)| p).sum();
+    let all_bytes: usize = totals.iter().map(|(_, b)| b).sum();
+    let share = |part: usize, whole: usize| part as f64 / whole.max(1) as f64 * 100.0;
+    for (role, (parameters, bytes)) in TensorRole::ALL.iter().zip(totals) {
+        if parameters == 0 && bytes == 0 {
+            continue;
+        }
+        section.row(
+            role.label(),
+            format!(
+                "{} params ({:.1}%), {} ({:.1}%)",
+                format_parameters(parameters),
+                share(parameters, all_parameters),
+                format_size(bytes),
+                share(bytes, all_bytes)
+            ),
+        );
+    }
+    section
+}
+
+fn composition_section(tensors: &[TensorInfo]) -> SummarySection {
+    let mut section = SummarySection::new("Dtype composition");
+    let mut loose = Vec::new();
+    for node in TreeBuilder::build_tree(tensors, TreeLimits::default()) {
+        match &node {
+            TreeNode::Group {
+                name, total_size, ..
+            } => {
+                let shares = dtype_composition(node.tensors());
+                section.row(
+                    name,
+                    format!(
+                        "{} ({})",
+                        composition_label(&shares, usize::MAX),
+                        format_size(*total_size)
+                    ),
+                );
+            }
+            TreeNode::Tensor { info } => loose.push(info.clone()),
+            TreeNode::Metadata { .. } | TreeNode::Failed { .. } => {}
+        }
+    }
+    if !loose.is_empty() {
+        let shares = dtype_composition(&loose);
+        let total = loose.iter().map(|t| t.size_bytes).sum();
+        section.row(
+            "(top level)",
+            format!(
+                "{} ({})",
+                composition_label(&shares, usize::MAX),
+                format_size(total)
+            ),
+        );
+    }
+    section
+}
+
+/// Lines of the group info view: one row per dtype with tensor count, bytes and share
+pub fn composition_lines(shares: &[DtypeShare]) -> Vec<String> {
+    let total: usize = shares.iter().map(|s| s.bytes).sum();
+    let dtype_width = shares
+        .iter()
+        .map(|s| display_width(&s.dtype))
+        .chain(["Dtype".len()])
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![format!(
+        "{}  {:>8}  {:>12}  {:>7}",
+        fit_to_width("Dtype", dtype_width, false),
+        "Tensors",
+        "Size",
+        "Share"
+    )];
+    for share in shares {
+        let percent = if total == 0 {
+            0.0
+        } else {
+            share.bytes as f64 / total as f64 * 100.0
+        };
+        lines.push(format!(
+            "{}  {:>8}  {:>12}  {:>6.2}%",
+            fit_to_width(&share.dtype, dtype_width, false),
+            share.tensors,
+            format_size(share.bytes),
+            percent
+        ));
+    }
+    lines
+}
+
+/// How many tensors the `T` overlay lists
+pub const TOP_TENSORS: usize = 20;
+
+/// The `n` largest tensors, ties ordered by name so the list is deterministic
+pub fn largest_tensors(tensors: &[TensorInfo], n: usize) -> Vec<&TensorInfo> {
+    let mut sorted: Vec<&TensorInfo> = tensors.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    sorted.truncate(n);
+    sorted
+}
+
+/// Header and one row per tensor, with the running share of `model_bytes` in the last column
+pub fn top_tensor_table(top: &[&TensorInfo], model_bytes: usize) -> (String, Vec<String>) {
+    let cells: Vec<[String; 4]> = top
+        .iter()
+        .map(|t| {
+            [
+                t.name.clone(),
+                t.dtype.clone(),
+                format_shape(&t.shape),
+                format_size(t.size_bytes),
+            ]
+        })
+        .collect();
+    let titles = ["Name", "Dtype", "Shape", "Size"];
+    let widths: Vec<usize> = (0..titles.len())
+        .map(|col| {
+            cells
+                .iter()
+                .map(|row| display_width(&row[col]))
+                .chain([titles[col].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let rank_width = top.len().to_string().len();
+
+    let header = format!(
+        "{:>rank_width$}  {}  {}  {}  {}  {:>7}",
+        "#",
+        fit_to_width(titles[0], widths[0], false),
+        fit_to_width(titles[1], widths[1], false),
+        fit_to_width(titles[2], widths[2], false),
+        fit_to_width(titles[3], widths[3], true),
+        "Cum. %"
+    );
+    let mut cumulative = 0;
+    let rows = top
+        .iter()
+        .zip(&cells)
+        .enumerate()
+        .map(|(i, (tensor, row))| {
+            cumulative += tensor.size_bytes;
+            let share = if model_bytes == 0 {
+                0.0
+            } else {
+                cumulative as f64 / model_bytes as f64 * 100.0
+            };
+            format!(
+                "{:>rank_width$}  {}  {}  {}  {}  {:>6.2}%",
+                i + 1,
+                fit_to_width(&row[0], widths[0], false),
+                fit_to_width(&row[1], widths[1], false),
+                fit_to_width(&row[2], widths[2], false),
+                fit_to_width(&row[3], widths[3], true),
+                share
+            )
+        })
+        .collect();
+    (header, rows)
+}
+
+/// `--padding-report` for one GGUF file: the overhead line, then the `n` tensors followed
+/// by the most padding with their offsets in the data section
+pub fn padding_report_lines(path: &Path, layout: &GGUFLayout, n: usize) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{}: padding overhead: {} ({:.2}%), alignment {}, data at offset {}",
+        path.display(),
+        format_size(layout.padding_bytes() as usize),
+        layout.padding_percent(),
+        layout.alignment,
+        layout.data_offset
+    )];
+    if layout.estimated > 0 {
+        lines.push(format!(
+            "  approximate: {} tensors have no exact block size",
+            layout.estimated
+        ));
+    }
+    let top: Vec<(&str, i64)> = layout
+        .largest_padding()
+        .into_iter()
+        .filter(|(_, padding)| *padding != 0)
+        .take(n)
+        .collect();
+    if top.is_empty() {
+        lines.push("  no tensor is followed by padding".to_string());
+        return lines;
+    }
+    let name_width = top
+        .iter()
+        .map(|(name, _)| display_width(name))
+        .chain(["Tensor".len()])
+        .max()
+        .unwrap_or(0);
+    lines.push(format!(
+        "  {}  {:>10}",
+        fit_to_width("Tensor", name_width, false),
+        "Padding"
+    ));
+    for (name, padding) in top {
+        lines.push(format!(
+            "  {}  {:>8} B",
+            fit_to_width(name, name_width, false),
+            padding
+        ));
+    }
+    lines
+}
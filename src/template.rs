@@ -0,0 +1,1846 @@
+//! A Jinja subset, enough to render the chat templates embedded in GGUF files and HF
+//! tokenizer configs: `{{ }}` output, `if`/`for`/`set`/`macro` blocks, filters, tests,
+//! string and dict methods, `namespace()` and `raise_exception()`. Blocks are trimmed as
+//! HF's `apply_chat_template` does (`trim_blocks` and `lstrip_blocks`). Anything outside
+//! the subset is an error naming the construct, never silently wrong output.
+
+use anyhow::{Result, anyhow, bail};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A template value; maps keep their insertion order, as Python dicts do
+#[derive(Debug, Clone)]
+pub enum Value {
+    Undefined,
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Rc<Vec<Value>>),
+    Map(Rc<Vec<(String, Value)>>),
+    /// `namespace()`, the one value whose attributes can be assigned
+    Namespace(Rc<RefCell<Vec<(String, Value)>>>),
+    Macro(Rc<Macro>),
+}
+
+#[derive(Debug)]
+pub struct Macro {
+    params: Vec<(String, Option<Expr>)>,
+    body: Vec<Node>,
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl Value {
+    pub fn map<K: Into<String>>(entries: impl IntoIterator<Item = (K, Value)>) -> Self {
+        Value::Map(Rc::new(
+            entries.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        ))
+    }
+
+    pub fn list(items: impl IntoIterator<Item = Value>) -> Self {
+        Value::List(Rc::new(items.into_iter().collect()))
+    }
+
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Undefined | Value::None => false,
+            Value::Bool(b) => *b,
+            Value::Int(n) => *n != 0,
+            Value::Float(x) => *x != 0.0,
+            Value::Str(s) => !s.is_empty(),
+            Value::List(items) => !items.is_empty(),
+            Value::Map(entries) => !entries.is_empty(),
+            Value::Namespace(_) | Value::Macro(_) => true,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Undefined => "undefined",
+            Value::None => "none",
+            Value::Bool(_) => "boolean",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "dict",
+            Value::Namespace(_) => "namespace",
+            Value::Macro(_) => "macro",
+        }
+    }
+
+    /// Attribute or key lookup; missing ones are undefined
+    fn get(&self, key: &str) -> Value {
+        let find = |entries: &[(String, Value)]| {
+            entries
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(Value::Undefined, |(_, v)| v.clone())
+        };
+        match self {
+            Value::Map(entries) => find(entries),
+            Value::Namespace(entries) => find(&entries.borrow()),
+            _ => Value::Undefined,
+        }
+    }
+
+    /// As `str()` renders it
+    fn render(&self) -> String {
+        match self {
+            Value::Undefined => String::new(),
+            Value::Str(s) => s.clone(),
+            _ => self.repr(),
+        }
+    }
+
+    /// As Python's `repr()`, for values printed inside lists and dicts
+    fn repr(&self) -> String {
+        match self {
+            Value::Undefined => String::new(),
+            Value::None => "None".to_string(),
+            Value::Bool(true) => "True".to_string(),
+            Value::Bool(false) => "False".to_string(),
+            Value::Int(n) => n.to_string(),
+            Value::Float(x) if x.fract() == 0.0 && x.is_finite() => format!("{x:.1}"),
+            Value::Float(x) => x.to_string(),
+            Value::Str(s) => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'")),
+            Value::List(items) => format!(
+                "[{}]",
+                items.iter().map(Value::repr).collect::<Vec<_>>().join(", ")
+            ),
+            Value::Map(entries) => Self::repr_entries(entries),
+            Value::Namespace(entries) => Self::repr_entries(&entries.borrow()),
+            Value::Macro(_) => "<macro>".to_string(),
+        }
+    }
+
+    fn repr_entries(entries: &[(String, Value)]) -> String {
+        let items: Vec<String> = entries
+            .iter()
+            .map(|(k, v)| format!("'{k}': {}", v.repr()))
+            .collect();
+        format!("{{{}}}", items.join(", "))
+    }
+
+    /// `tojson` output: `json.dumps` with its default separators, non-ASCII kept
+    fn to_json(&self, indent: Option<usize>, depth: usize) -> String {
+        let (open, close, separator) = match indent {
+            Some(width) => (
+                format!("\n{}", " ".repeat(width * (depth + 1))),
+                format!("\n{}", " ".repeat(width * depth)),
+                format!(",\n{}", " ".repeat(width * (depth + 1))),
+            ),
+            None => (String::new(), String::new(), ", ".to_string()),
+        };
+        match self {
+            Value::Undefined | Value::None => "null".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Int(n) => n.to_string(),
+            Value::Float(x) => Value::Float(*x).repr(),
+            Value::Str(s) => serde_json::Value::from(s.as_str()).to_string(),
+            Value::List(items) if items.is_empty() => "[]".to_string(),
+            Value::List(items) => {
+                let items: Vec<String> =
+                    items.iter().map(|v| v.to_json(indent, depth + 1)).collect();
+                format!("[{open}{}{close}]", items.join(&separator))
+            }
+            Value::Map(entries) if entries.is_empty() => "{}".to_string(),
+            Value::Map(entries) => {
+                let items: Vec<String> = entries
+                    .iter()
+                    .map(|(k, v)| {
+                        format!(
+                            "{}: {}",
+                            serde_json::Value::from(k.as_str()),
+                            v.to_json(indent, depth + 1)
+                        )
+                    })
+                    .collect();
+                format!("{{{open}{}{close}}}", items.join(&separator))
+            }
+            Value::Namespace(_) | Value::Macro(_) => "null".to_string(),
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(x) => Some(*x),
+            Value::Bool(b) => Some(f64::from(u8::from(*b))),
+            _ => None,
+        }
+    }
+
+    /// Items to iterate over: list items, dict keys or string characters
+    fn items(&self) -> Result<Vec<Value>> {
+        match self {
+            Value::List(items) => Ok(items.to_vec()),
+            Value::Map(entries) => Ok(entries.iter().map(|(k, _)| Value::from(&**k)).collect()),
+            Value::Str(s) => Ok(s.chars().map(|c| Value::Str(c.to_string())).collect()),
+            Value::Undefined | Value::None => Ok(Vec::new()),
+            other => bail!("cannot iterate over a {}", other.type_name()),
+        }
+    }
+
+    fn length(&self) -> Result<usize> {
+        match self {
+            Value::Str(s) => Ok(s.chars().count()),
+            Value::List(items) => Ok(items.len()),
+            Value::Map(entries) => Ok(entries.len()),
+            Value::Undefined => Ok(0),
+            other => bail!("a {} has no length", other.type_name()),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Undefined, Value::Undefined) | (Value::None, Value::None) => true,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (a, b) => match (a.as_number(), b.as_number()) {
+                (Some(x), Some(y)) => x == y,
+                _ => false,
+            },
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------
+// Lexing: template source into text, `{{ }}` and `{% %}` segments
+
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    Output(String),
+    Statement(String),
+}
+
+/// Index of `close` in `source`, skipping quoted strings and braces of dict literals
+fn find_close(source: &str, close: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0usize;
+    let mut chars = source.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some(_) if c == '\\' => {
+                chars.next();
+            }
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if depth == 0 && source[i..].starts_with(close) => return Some(i),
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '{' => depth += 1,
+            None if c == '}' => depth = depth.saturating_sub(1),
+            None => {}
+        }
+    }
+    None
+}
+
+fn segments(source: &str) -> Result<Vec<Segment>> {
+    // Jinja drops a single trailing newline
+    let source = source.strip_suffix('\n').unwrap_or(source);
+    let mut segments = Vec::new();
+    let mut rest = source;
+    // What the previous tag asked of the text after it
+    let (mut strip_next, mut trim_newline) = (false, false);
+    loop {
+        let next = ["{{", "{%", "{#"]
+            .iter()
+            .filter_map(|open| rest.find(open))
+            .min();
+        let mut text = &rest[..next.unwrap_or(rest.len())];
+        if strip_next {
+            text = text.trim_start();
+        } else if trim_newline {
+            text = text
+                .strip_prefix("\r\n")
+                .or_else(|| text.strip_prefix('\n'))
+                .unwrap_or(text);
+        }
+        let Some(start) = next else {
+            segments.push(Segment::Text(text.to_string()));
+            break;
+        };
+        let kind = rest.as_bytes()[start + 1];
+        let inner_start = start + 2;
+        let marker = rest[inner_start..].chars().next();
+        if marker == Some('-') {
+            text = text.trim_end();
+        } else if kind != b'{' && marker != Some('+') {
+            // lstrip_blocks: spaces and tabs before a tag that starts its line
+            let before = &source[..source.len() - rest.len() + start];
+            let indent = before.rsplit('\n').next().unwrap_or_default();
+            if indent.len() <= text.len() && indent.trim_matches([' ', '\t']).is_empty() {
+                text = &text[..text.len() - indent.len()];
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text.to_string()));
+        }
+
+        let close = match kind {
+            b'{' => "}}",
+            b'%' => "%}",
+            _ => "#}",
+        };
+        let body = &rest[inner_start..];
+        let end = if kind == b'#' {
+            body.find(close)
+        } else {
+            find_close(body, close)
+        }
+        .ok_or_else(|| anyhow!("unclosed {}", &rest[start..start + 2]))?;
+        let mut inner = &body[..end];
+        inner = inner.strip_prefix(['-', '+']).unwrap_or(inner);
+        strip_next = inner.ends_with('-');
+        let keep_newline = inner.ends_with('+');
+        inner = inner.strip_suffix(['-', '+']).unwrap_or(inner);
+        trim_newline = kind != b'{' && !keep_newline;
+        match kind {
+            b'{' => segments.push(Segment::Output(inner.trim().to_string())),
+            b'%' => segments.push(Segment::Statement(inner.trim().to_string())),
+            _ => {}
+        }
+        rest = &body[end + close.len()..];
+    }
+    Ok(segments)
+}
+
+// ---------------------------------------------------------------------------------------
+// Expressions
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Op(&'static str),
+}
+
+/// Most bytes a string repetition, and items a `range()`, may produce; without a limit
+/// template input could ask for any amount of memory
+const MAX_GENERATED: usize = 1 << 20;
+
+const OPERATORS: [&str; 27] = [
+    "**", "//", "==", "!=", "<=", ">=", "(", ")", "[", "]", "{", "}", ",", ":", ".", "|", "~", "+",
+    "-", "*", "/", "%", "<", ">", "=", "!", ";",
+];
+
+fn tokens(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let is_float = i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit();
+            if is_float {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(if is_float {
+                Token::Float(text.parse()?)
+            } else {
+                Token::Int(text.parse()?)
+            });
+        } else if c == '\'' || c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                let Some(&ch) = chars.get(i) else {
+                    bail!("unterminated string in `{source}`");
+                };
+                i += 1;
+                match ch {
+                    '\\' => {
+                        let escaped = chars.get(i).copied().unwrap_or('\\');
+                        i += 1;
+                        value.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            'r' => '\r',
+                            other => other,
+                        });
+                    }
+                    _ if ch == c => break,
+                    _ => value.push(ch),
+                }
+            }
+            tokens.push(Token::Str(value));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| anyhow!("unexpected `{c}` in `{source}`"))?;
+            tokens.push(Token::Op(op));
+            i += op.chars().count();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Name(String),
+    List(Vec<Expr>),
+    Dict(Vec<(Expr, Expr)>),
+    Attr(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, [Option<Box<Expr>>; 3]),
+    Call(Box<Expr>, Vec<Expr>, Vec<(String, Expr)>),
+    Filter(Box<Expr>, String, Vec<Expr>, Vec<(String, Expr)>),
+    Test(Box<Expr>, String, Vec<Expr>, bool),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    /// `a if cond else b`
+    Conditional(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+}
+
+/// Positional and keyword arguments of a call or filter
+type Arguments = (Vec<Expr>, Vec<(String, Expr)>);
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self> {
+        Ok(Parser {
+            tokens: tokens(source)?,
+            pos: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn at_op(&self, op: &str) -> bool {
+        matches!(self.peek(), Some(Token::Op(o)) if *o == op)
+    }
+
+    fn at_name(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(n)) if n == name)
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        let found = self.at_op(op);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_name(&mut self, name: &str) -> bool {
+        let found = self.at_name(name);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<()> {
+        if self.eat_op(op) {
+            Ok(())
+        } else {
+            bail!("expected `{op}`, found {:?}", self.peek())
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Name(name)) => {
+                self.pos += 1;
+                Ok(name.clone())
+            }
+            other => bail!("expected a name, found {other:?}"),
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn finish(&self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => bail!("unexpected {token:?}"),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        let value = self.or()?;
+        if self.eat_name("if") {
+            let condition = self.or()?;
+            let otherwise = if self.eat_name("else") {
+                Some(Box::new(self.expression()?))
+            } else {
+                None
+            };
+            return Ok(Expr::Conditional(
+                Box::new(value),
+                Box::new(condition),
+                otherwise,
+            ));
+        }
+        Ok(value)
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut left = self.and()?;
+        while self.eat_name("or") {
+            left = Expr::Binary("or", Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut left = self.not()?;
+        while self.eat_name("and") {
+            left = Expr::Binary("and", Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat_name("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Expr> {
+        let mut left = self.sum()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(op @ ("==" | "!=" | "<" | ">" | "<=" | ">="))) => *op,
+                Some(Token::Name(n)) if n == "in" => "in",
+                Some(Token::Name(n))
+                    if n == "not"
+                        && matches!(self.tokens.get(self.pos + 1), Some(Token::Name(m)) if m == "in") =>
+                {
+                    self.pos += 1;
+                    "not in"
+                }
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.sum()?));
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut left = self.concat()?;
+        loop {
+            let op = if self.eat_op("+") {
+                "+"
+            } else if self.eat_op("-") {
+                "-"
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.concat()?));
+        }
+    }
+
+    fn concat(&mut self) -> Result<Expr> {
+        let mut left = self.product()?;
+        while self.eat_op("~") {
+            left = Expr::Binary("~", Box::new(left), Box::new(self.product()?));
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(op @ ("*" | "/" | "//" | "%"))) => *op,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat_op("-") {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        let primary = self.primary()?;
+        let value = self.postfix(primary)?;
+        self.filters(value)
+    }
+
+    fn arguments(&mut self) -> Result<Arguments> {
+        let (mut args, mut kwargs) = (Vec::new(), Vec::new());
+        while !self.eat_op(")") {
+            let keyword = matches!(self.peek(), Some(Token::Name(_)))
+                && matches!(self.tokens.get(self.pos + 1), Some(Token::Op("=")));
+            if keyword {
+                let name = self.name()?;
+                self.pos += 1;
+                kwargs.push((name, self.expression()?));
+            } else {
+                args.push(self.expression()?);
+            }
+            if !self.eat_op(",") {
+                self.expect_op(")")?;
+                break;
+            }
+        }
+        Ok((args, kwargs))
+    }
+
+    fn postfix(&mut self, mut value: Expr) -> Result<Expr> {
+        loop {
+            if self.eat_op(".") {
+                value = Expr::Attr(Box::new(value), self.name()?);
+            } else if self.eat_op("[") {
+                let mut parts: [Option<Box<Expr>>; 3] = [None, None, None];
+                let mut colons = 0;
+                loop {
+                    if self.eat_op("]") {
+                        break;
+                    }
+                    if self.eat_op(":") {
+                        colons += 1;
+                        if colons > 2 {
+                            bail!("too many `:` in a slice");
+                        }
+                        continue;
+                    }
+                    parts[colons] = Some(Box::new(self.expression()?));
+                }
+                value = match (colons, parts) {
+                    (0, [Some(index), ..]) => Expr::Index(Box::new(value), index),
+                    (0, _) => bail!("empty subscript"),
+                    (_, parts) => Expr::Slice(Box::new(value), parts),
+                };
+            } else if self.eat_op("(") {
+                let (args, kwargs) = self.arguments()?;
+                value = Expr::Call(Box::new(value), args, kwargs);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn filters(&mut self, mut value: Expr) -> Result<Expr> {
+        loop {
+            if self.eat_op("|") {
+                let name = self.name()?;
+                let (args, kwargs) = if self.eat_op("(") {
+                    self.arguments()?
+                } else {
+                    (Vec::new(), Vec::new())
+                };
+                value = Expr::Filter(Box::new(value), name, args, kwargs);
+            } else if self.eat_name("is") {
+                let negated = self.eat_name("not");
+                let name = self.name()?;
+                let args = if self.eat_op("(") {
+                    self.arguments()?.0
+                } else if matches!(
+                    self.peek(),
+                    Some(Token::Str(_) | Token::Int(_) | Token::Float(_))
+                ) {
+                    vec![self.primary()?]
+                } else {
+                    Vec::new()
+                };
+                value = Expr::Test(Box::new(value), name, args, negated);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow!("unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(match token {
+            Token::Str(mut s) => {
+                // Adjacent string literals are joined, as in Python
+                while let Some(Token::Str(next)) = self.peek() {
+                    s.push_str(next);
+                    self.pos += 1;
+                }
+                Expr::Literal(Value::Str(s))
+            }
+            Token::Int(n) => Expr::Literal(Value::Int(n)),
+            Token::Float(x) => Expr::Literal(Value::Float(x)),
+            Token::Name(name) => match name.as_str() {
+                "true" | "True" => Expr::Literal(Value::Bool(true)),
+                "false" | "False" => Expr::Literal(Value::Bool(false)),
+                "none" | "None" => Expr::Literal(Value::None),
+                _ => Expr::Name(name),
+            },
+            Token::Op("(") => {
+                let inner = self.expression()?;
+                if self.at_op(",") {
+                    // A tuple, treated as a list
+                    let mut items = vec![inner];
+                    while self.eat_op(",") && !self.at_op(")") {
+                        items.push(self.expression()?);
+                    }
+                    self.expect_op(")")?;
+                    Expr::List(items)
+                } else {
+                    self.expect_op(")")?;
+                    inner
+                }
+            }
+            Token::Op("[") => {
+                let mut items = Vec::new();
+                while !self.eat_op("]") {
+                    items.push(self.expression()?);
+                    if !self.eat_op(",") {
+                        self.expect_op("]")?;
+                        break;
+                    }
+                }
+                Expr::List(items)
+            }
+            Token::Op("{") => {
+                let mut entries = Vec::new();
+                while !self.eat_op("}") {
+                    let key = self.expression()?;
+                    self.expect_op(":")?;
+                    entries.push((key, self.expression()?));
+                    if !self.eat_op(",") {
+                        self.expect_op("}")?;
+                        break;
+                    }
+                }
+                Expr::Dict(entries)
+            }
+            other => bail!("unexpected {other:?}"),
+        })
+    }
+}
+
+fn parse_expression(source: &str) -> Result<Expr> {
+    let mut parser = Parser::new(source)?;
+    let expr = parser.expression()?;
+    parser.finish()?;
+    Ok(expr)
+}
+
+// ---------------------------------------------------------------------------------------
+// Statements
+
+#[derive(Debug, Clone)]
+enum Target {
+    Names(Vec<String>),
+    /// `ns.attr`
+    Attr(String, String),
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Output(Expr),
+    If(Vec<(Expr, Vec<Node>)>, Vec<Node>),
+    For {
+        vars: Vec<String>,
+        iterable: Expr,
+        condition: Option<Expr>,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Set(Target, Expr),
+    /// `{% set name %}...{% endset %}`
+    SetBlock(String, Vec<Node>),
+    Macro(String, Rc<Macro>),
+}
+
+/// Parse segments up to one of `ends`, returning the nodes and the end tag met
+fn parse_nodes(
+    segments: &mut std::vec::IntoIter<Segment>,
+    ends: &[&str],
+) -> Result<(Vec<Node>, Option<String>)> {
+    let mut nodes = Vec::new();
+    while let Some(segment) = segments.next() {
+        let statement = match segment {
+            Segment::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            }
+            Segment::Output(source) => {
+                nodes.push(Node::Output(parse_expression(&source)?));
+                continue;
+            }
+            Segment::Statement(statement) => statement,
+        };
+        let keyword = statement.split_whitespace().next().unwrap_or_default();
+        if ends.contains(&keyword) {
+            return Ok((nodes, Some(statement)));
+        }
+        let rest = statement[keyword.len()..].trim();
+        match keyword {
+            "if" => {
+                let mut branches = Vec::new();
+                let mut condition = parse_expression(rest)?;
+                let otherwise = loop {
+                    let (body, end) = parse_nodes(segments, &["elif", "else", "endif"])?;
+                    branches.push((condition, body));
+                    let end = end.ok_or_else(|| anyhow!("missing endif"))?;
+                    match end.split_whitespace().next() {
+                        Some("elif") => condition = parse_expression(end[4..].trim())?,
+                        Some("else") => {
+                            let (body, end) = parse_nodes(segments, &["endif"])?;
+                            end.ok_or_else(|| anyhow!("missing endif"))?;
+                            break body;
+                        }
+                        _ => break Vec::new(),
+                    }
+                };
+                nodes.push(Node::If(branches, otherwise));
+            }
+            "for" => {
+                let mut parser = Parser::new(rest)?;
+                let mut vars = vec![parser.name()?];
+                while parser.eat_op(",") {
+                    vars.push(parser.name()?);
+                }
+                if !parser.eat_name("in") {
+                    bail!("expected `in` in `for {rest}`");
+                }
+                // `if` here filters the loop rather than starting a conditional expression
+                let iterable = parser.or()?;
+                let iterable = parser.postfix(iterable)?;
+                let condition = if parser.eat_name("if") {
+                    Some(parser.expression()?)
+                } else {
+                    None
+                };
+                if parser.eat_name("recursive") {
+                    bail!("recursive loops are not supported");
+                }
+                parser.finish()?;
+                let (body, end) = parse_nodes(segments, &["else", "endfor"])?;
+                let end = end.ok_or_else(|| anyhow!("missing endfor"))?;
+                let otherwise = if end == "else" {
+                    let (body, end) = parse_nodes(segments, &["endfor"])?;
+                    end.ok_or_else(|| anyhow!("missing endfor"))?;
+                    body
+                } else {
+                    Vec::new()
+                };
+                nodes.push(Node::For {
+                    vars,
+                    iterable,
+                    condition,
+                    body,
+                    otherwise,
+                });
+            }
+            "set" => {
+                let mut parser = Parser::new(rest)?;
+                let first = parser.name()?;
+                let target = if parser.eat_op(".") {
+                    Target::Attr(first, parser.name()?)
+                } else {
+                    let mut names = vec![first];
+                    while parser.eat_op(",") {
+                        names.push(parser.name()?);
+                    }
+                    Target::Names(names)
+                };
+                if parser.done() {
+                    let Target::Names(names) = &target else {
+                        bail!("block set needs a single name");
+                    };
+                    let [name] = names.as_slice() else {
+                        bail!("block set needs a single name");
+                    };
+                    let (body, end) = parse_nodes(segments, &["endset"])?;
+                    end.ok_or_else(|| anyhow!("missing endset"))?;
+                    nodes.push(Node::SetBlock(name.clone(), body));
+                    continue;
+                }
+                parser.expect_op("=")?;
+                let value = parser.expression()?;
+                parser.finish()?;
+                nodes.push(Node::Set(target, value));
+            }
+            "macro" => {
+                let mut parser = Parser::new(rest)?;
+                let name = parser.name()?;
+                parser.expect_op("(")?;
+                let mut params = Vec::new();
+                while !parser.eat_op(")") {
+                    let param = parser.name()?;
+                    let default = if parser.eat_op("=") {
+                        Some(parser.expression()?)
+                    } else {
+                        None
+                    };
+                    params.push((param, default));
+                    if !parser.eat_op(",") {
+                        parser.expect_op(")")?;
+                        break;
+                    }
+                }
+                parser.finish()?;
+                let (body, end) = parse_nodes(segments, &["endmacro"])?;
+                end.ok_or_else(|| anyhow!("missing endmacro"))?;
+                nodes.push(Node::Macro(name, Rc::new(Macro { params, body })));
+            }
+            // HF's marker for assistant text in training masks; renders its body as is
+            "generation" | "endgeneration" => {}
+            other => bail!("unsupported tag `{{% {other} %}}`"),
+        }
+    }
+    Ok((nodes, None))
+}
+
+/// A parsed template, ready to render with different variables
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut segments = segments(source)?.into_iter();
+        let (nodes, end) = parse_nodes(&mut segments, &[])?;
+        if let Some(end) = end {
+            bail!("unexpected `{{% {end} %}}`");
+        }
+        Ok(Template { nodes })
+    }
+
+    pub fn render(&self, variables: &[(&str, Value)]) -> Result<String> {
+        let globals = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        let mut env = Env {
+            scopes: vec![globals],
+        };
+        let mut out = String::new();
+        env.run(&self.nodes, &mut out)?;
+        Ok(out)
+    }
+}
+
+// ---------------------------------------------------------------------------------------
+// Evaluation
+
+struct Env {
+    scopes: Vec<HashMap<String, Value>>,
+}
+
+/// Python-style index into a sequence of `len` items, negative from the end
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+/// Positions selected by a Python slice over `len` items
+fn slice_positions(len: usize, start: Option<i64>, stop: Option<i64>, step: i64) -> Vec<usize> {
+    let len = len as i64;
+    let clamp = |bound: i64, low: i64, high: i64| {
+        let bound = if bound < 0 { bound + len } else { bound };
+        bound.clamp(low, high)
+    };
+    let mut positions = Vec::new();
+    if step > 0 {
+        let (mut i, stop) = (
+            start.map_or(0, |s| clamp(s, 0, len)),
+            stop.map_or(len, |s| clamp(s, 0, len)),
+        );
+        while i < stop {
+            positions.push(i as usize);
+            i += step;
+        }
+    } else {
+        let (mut i, stop) = (
+            start.map_or(len - 1, |s| clamp(s, -1, len - 1)),
+            stop.map_or(-1, |s| clamp(s, -1, len - 1)),
+        );
+        while i > stop {
+            positions.push(i as usize);
+            i += step;
+        }
+    }
+    positions
+}
+
+fn strip_chars<'a>(s: &'a str, chars: Option<&Value>, start: bool, end: bool) -> &'a str {
+    let set: Option<Vec<char>> = match chars {
+        Some(Value::Str(chars)) => Some(chars.chars().collect()),
+        _ => None,
+    };
+    let matches = |c: char| match &set {
+        Some(set) => set.contains(&c),
+        None => c.is_whitespace(),
+    };
+    let s = if start {
+        s.trim_start_matches(matches)
+    } else {
+        s
+    };
+    if end { s.trim_end_matches(matches) } else { s }
+}
+
+/// `strftime` for the handful of fields chat templates put in their date line
+fn strftime_now(format: &str) -> String {
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    // "YYYY-MM-DD HH:MM:SS UTC"
+    let stamp = crate::utils::format_timestamp(seconds);
+    let field = |range: std::ops::Range<usize>| stamp[range].to_string();
+    let month: usize = stamp[5..7].parse().unwrap_or(1);
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&field(0..4)),
+            Some('m') => out.push_str(&field(5..7)),
+            Some('d') => out.push_str(&field(8..10)),
+            Some('H') => out.push_str(&field(11..13)),
+            Some('M') => out.push_str(&field(14..16)),
+            Some('S') => out.push_str(&field(17..19)),
+            Some('B') => out.push_str(MONTHS[month - 1]),
+            Some('b') => out.push_str(&MONTHS[month - 1][..3]),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+impl Env {
+    fn lookup(&self, name: &str) -> Value {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .unwrap_or(Value::Undefined)
+    }
+
+    fn assign(&mut self, name: &str, value: Value) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), value);
+        }
+    }
+
+    fn run(&mut self, nodes: &[Node], out: &mut String) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Output(expr) => out.push_str(&self.eval(expr)?.render()),
+                Node::If(branches, otherwise) => {
+                    let mut taken = false;
+                    for (condition, body) in branches {
+                        if self.eval(condition)?.truthy() {
+                            self.run(body, out)?;
+                            taken = true;
+                            break;
+                        }
+                    }
+                    if !taken {
+                        self.run(otherwise, out)?;
+                    }
+                }
+                Node::For {
+                    vars,
+                    iterable,
+                    condition,
+                    body,
+                    otherwise,
+                } => self.run_for(vars, iterable, condition.as_ref(), body, otherwise, out)?,
+                Node::Set(Target::Names(names), expr) => {
+                    let value = self.eval(expr)?;
+                    if let [name] = names.as_slice() {
+                        self.assign(name, value);
+                    } else {
+                        let items = value.items()?;
+                        if items.len() != names.len() {
+                            bail!("cannot unpack {} values into {}", items.len(), names.len());
+                        }
+                        for (name, item) in names.iter().zip(items) {
+                            self.assign(name, item);
+                        }
+                    }
+                }
+                Node::Set(Target::Attr(object, attr), expr) => {
+                    let value = self.eval(expr)?;
+                    let Value::Namespace(entries) = self.lookup(object) else {
+                        bail!("cannot set attributes of `{object}`, which is not a namespace");
+                    };
+                    let mut entries = entries.borrow_mut();
+                    match entries.iter_mut().find(|(k, _)| k == attr) {
+                        Some((_, slot)) => *slot = value,
+                        None => entries.push((attr.clone(), value)),
+                    }
+                }
+                Node::SetBlock(name, body) => {
+                    let mut captured = String::new();
+                    self.run(body, &mut captured)?;
+                    self.assign(name, Value::Str(captured));
+                }
+                Node::Macro(name, definition) => {
+                    self.assign(name, Value::Macro(definition.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run_for(
+        &mut self,
+        vars: &[String],
+        iterable: &Expr,
+        condition: Option<&Expr>,
+        body: &[Node],
+        otherwise: &[Node],
+        out: &mut String,
+    ) -> Result<()> {
+        let value = self.eval(iterable)?;
+        let items: Vec<Value> = match (&value, vars.len()) {
+            // `for key, value in dict.items()` arrives as a list of pairs; a bare dict with
+            // two names iterates its items too
+            (Value::Map(entries), 2) => entries
+                .iter()
+                .map(|(k, v)| Value::list([Value::from(&**k), v.clone()]))
+                .collect(),
+            _ => value.items()?,
+        };
+        self.scopes.push(HashMap::new());
+        let bind = |env: &mut Env, item: &Value| -> Result<()> {
+            if let [name] = vars {
+                env.assign(name, item.clone());
+                return Ok(());
+            }
+            let parts = item.items()?;
+            if parts.len() != vars.len() {
+                bail!("cannot unpack {} values into {}", parts.len(), vars.len());
+            }
+            for (name, part) in vars.iter().zip(parts) {
+                env.assign(name, part);
+            }
+            Ok(())
+        };
+        let mut kept = Vec::new();
+        for item in items {
+            if let Some(condition) = condition {
+                bind(self, &item)?;
+                if !self.eval(condition)?.truthy() {
+                    continue;
+                }
+            }
+            kept.push(item);
+        }
+        let length = kept.len();
+        for (index, item) in kept.iter().enumerate() {
+            bind(self, item)?;
+            let previous = index
+                .checked_sub(1)
+                .map_or(Value::Undefined, |i| kept[i].clone());
+            let next = kept.get(index + 1).cloned().unwrap_or(Value::Undefined);
+            self.assign(
+                "loop",
+                Value::map([
+                    ("index", Value::Int(index as i64 + 1)),
+                    ("index0", Value::Int(index as i64)),
+                    ("revindex", Value::Int((length - index) as i64)),
+                    ("revindex0", Value::Int((length - index - 1) as i64)),
+                    ("first", Value::Bool(index == 0)),
+                    ("last", Value::Bool(index + 1 == length)),
+                    ("length", Value::Int(length as i64)),
+                    ("previtem", previous),
+                    ("nextitem", next),
+                ]),
+            );
+            self.run(body, out)?;
+        }
+        self.scopes.pop();
+        if length == 0 {
+            self.run(otherwise, out)?;
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        Ok(match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Name(name) => self.lookup(name),
+            Expr::List(items) => Value::List(Rc::new(
+                items
+                    .iter()
+                    .map(|item| self.eval(item))
+                    .collect::<Result<_>>()?,
+            )),
+            Expr::Dict(entries) => {
+                let mut map = Vec::new();
+                for (key, value) in entries {
+                    let key = self.eval(key)?.render();
+                    let value = self.eval(value)?;
+                    map.retain(|(k, _): &(String, Value)| *k != key);
+                    map.push((key, value));
+                }
+                Value::Map(Rc::new(map))
+            }
+            Expr::Attr(object, attr) => self.eval(object)?.get(attr),
+            Expr::Index(object, index) => {
+                let object = self.eval(object)?;
+                let index = self.eval(index)?;
+                match (&object, &index) {
+                    (Value::List(items), Value::Int(i)) => resolve_index(*i, items.len())
+                        .map_or(Value::Undefined, |i| items[i].clone()),
+                    (Value::Str(s), Value::Int(i)) => {
+                        let chars: Vec<char> = s.chars().collect();
+                        resolve_index(*i, chars.len())
+                            .map_or(Value::Undefined, |i| Value::Str(chars[i].to_string()))
+                    }
+                    (_, Value::Str(key)) => object.get(key),
+                    _ => Value::Undefined,
+                }
+            }
+            Expr::Slice(object, [start, stop, step]) => {
+                let object = self.eval(object)?;
+                let mut bound = |part: &Option<Box<Expr>>| -> Result<Option<i64>> {
+                    match part {
+                        None => Ok(None),
+                        Some(expr) => match self.eval(expr)? {
+                            Value::Int(n) => Ok(Some(n)),
+                            Value::None => Ok(None),
+                            other => {
+                                bail!("slice bounds must be integers, not {}", other.type_name())
+                            }
+                        },
+                    }
+                };
+                let (start, stop, step) = (bound(start)?, bound(stop)?, bound(step)?.unwrap_or(1));
+                if step == 0 {
+                    bail!("slice step cannot be zero");
+                }
+                match &object {
+                    Value::List(items) => Value::list(
+                        slice_positions(items.len(), start, stop, step)
+                            .into_iter()
+                            .map(|i| items[i].clone()),
+                    ),
+                    Value::Str(s) => {
+                        let chars: Vec<char> = s.chars().collect();
+                        Value::Str(
+                            slice_positions(chars.len(), start, stop, step)
+                                .into_iter()
+                                .map(|i| chars[i])
+                                .collect(),
+                        )
+                    }
+                    _ => Value::Undefined,
+                }
+            }
+            Expr::Call(callee, args, kwargs) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                let kwargs = kwargs
+                    .iter()
+                    .map(|(name, arg)| Ok((name.clone(), self.eval(arg)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                match &**callee {
+                    Expr::Attr(object, method) => {
+                        let object = self.eval(object)?;
+                        call_method(&object, method, &args)?
+                    }
+                    Expr::Name(name) => match self.lookup(name) {
+                        Value::Macro(definition) => self.call_macro(&definition, args, kwargs)?,
+                        _ => call_function(name, &args, kwargs)?,
+                    },
+                    _ => bail!("only functions and methods can be called"),
+                }
+            }
+            Expr::Filter(value, name, args, kwargs) => {
+                let value = self.eval(value)?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                let kwargs = kwargs
+                    .iter()
+                    .map(|(name, arg)| Ok((name.clone(), self.eval(arg)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                apply_filter(value, name, &args, &kwargs)?
+            }
+            Expr::Test(value, name, args, negated) => {
+                let value = self.eval(value)?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                Value::Bool(apply_test(&value, name, &args)? != *negated)
+            }
+            Expr::Not(value) => Value::Bool(!self.eval(value)?.truthy()),
+            Expr::Negate(value) => match self.eval(value)? {
+                Value::Int(n) => match n.checked_neg() {
+                    Some(n) => Value::Int(n),
+                    None => bail!("integer overflow in `-{n}`"),
+                },
+                Value::Float(x) => Value::Float(-x),
+                other => bail!("cannot negate a {}", other.type_name()),
+            },
+            Expr::Binary("and", left, right) => {
+                let left = self.eval(left)?;
+                if left.truthy() {
+                    self.eval(right)?
+                } else {
+                    left
+                }
+            }
+            Expr::Binary("or", left, right) => {
+                let left = self.eval(left)?;
+                if left.truthy() {
+                    left
+                } else {
+                    self.eval(right)?
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                binary(op, &left, &right)?
+            }
+            Expr::Conditional(value, condition, otherwise) => {
+                if self.eval(condition)?.truthy() {
+                    self.eval(value)?
+                } else {
+                    match otherwise {
+                        Some(otherwise) => self.eval(otherwise)?,
+                        None => Value::Undefined,
+                    }
+                }
+            }
+        })
+    }
+
+    fn call_macro(
+        &mut self,
+        definition: &Macro,
+        args: Vec<Value>,
+        kwargs: Vec<(String, Value)>,
+    ) -> Result<Value> {
+        let mut scope = HashMap::new();
+        let mut args = args.into_iter();
+        for (param, default) in &definition.params {
+            let value = match (args.next(), kwargs.iter().find(|(k, _)| k == param)) {
+                (Some(value), _) => value,
+                (None, Some((_, value))) => value.clone(),
+                (None, None) => match default {
+                    Some(default) => self.eval(default)?,
+                    None => Value::Undefined,
+                },
+            };
+            scope.insert(param.clone(), value);
+        }
+        self.scopes.push(scope);
+        let mut out = String::new();
+        let result = self.run(&definition.body, &mut out);
+        self.scopes.pop();
+        result?;
+        Ok(Value::Str(out))
+    }
+}
+
+fn binary(op: &str, left: &Value, right: &Value) -> Result<Value> {
+    Ok(match op {
+        "==" => Value::Bool(left == right),
+        "!=" => Value::Bool(left != right),
+        "in" | "not in" => {
+            let found = match right {
+                Value::Str(haystack) => haystack.contains(&left.render()),
+                Value::List(items) => items.contains(left),
+                Value::Map(entries) => entries.iter().any(|(k, _)| Value::from(&**k) == *left),
+                Value::Undefined | Value::None => false,
+                other => bail!(
+                    "`in` needs a string, list or dict, not {}",
+                    other.type_name()
+                ),
+            };
+            Value::Bool(found == (op == "in"))
+        }
+        "<" | ">" | "<=" | ">=" => {
+            let ordering = match (left, right) {
+                (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+                _ => match (left.as_number(), right.as_number()) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b),
+                    _ => bail!(
+                        "cannot compare {} with {}",
+                        left.type_name(),
+                        right.type_name()
+                    ),
+                },
+            };
+            let ordering = ordering.ok_or_else(|| anyhow!("cannot compare NaN"))?;
+            Value::Bool(match op {
+                "<" => ordering.is_lt(),
+                ">" => ordering.is_gt(),
+                "<=" => ordering.is_le(),
+                _ => ordering.is_ge(),
+            })
+        }
+        "~" => Value::Str(left.render() + &right.render()),
+        "+" => match (left, right) {
+            (Value::Str(a), Value::Str(b)) => Value::Str(format!("{a}{b}")),
+            (Value::List(a), Value::List(b)) => Value::list(a.iter().chain(b.iter()).cloned()),
+            (Value::Int(a), Value::Int(b)) => integer(op, *a, *b, a.checked_add(*b))?,
+            _ => arithmetic(op, left, right)?,
+        },
+        "-" | "*" | "/" | "//" | "%" => match (op, left, right) {
+            ("-", Value::Int(a), Value::Int(b)) => integer(op, *a, *b, a.checked_sub(*b))?,
+            ("*", Value::Int(a), Value::Int(b)) => integer(op, *a, *b, a.checked_mul(*b))?,
+            ("*", Value::Str(s), Value::Int(n)) => {
+                let times = (*n).max(0) as usize;
+                if s.len().saturating_mul(times) > MAX_GENERATED {
+                    bail!(
+                        "`*` would repeat a {}-byte string {times} times, over the {MAX_GENERATED}-byte limit",
+                        s.len()
+                    );
+                }
+                Value::Str(s.repeat(times))
+            }
+            ("//", Value::Int(a), Value::Int(b)) if *b != 0 => {
+                integer(op, *a, *b, a.checked_div_euclid(*b))?
+            }
+            ("%", Value::Int(a), Value::Int(b)) if *b != 0 => {
+                integer(op, *a, *b, a.checked_rem_euclid(*b))?
+            }
+            _ => arithmetic(op, left, right)?,
+        },
+        other => bail!("unsupported operator `{other}`"),
+    })
+}
+
+/// Result of integer `a op b`, None when it overflowed
+fn integer(op: &str, a: i64, b: i64, result: Option<i64>) -> Result<Value> {
+    match result {
+        Some(n) => Ok(Value::Int(n)),
+        None => bail!("integer overflow in `{a} {op} {b}`"),
+    }
+}
+
+fn arithmetic(op: &str, left: &Value, right: &Value) -> Result<Value> {
+    let (Some(a), Some(b)) = (left.as_number(), right.as_number()) else {
+        bail!(
+            "cannot apply `{op}` to {} and {}",
+            left.type_name(),
+            right.type_name()
+        );
+    };
+    Ok(Value::Float(match op {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" => a / b,
+        "//" => (a / b).floor(),
+        _ => a.rem_euclid(b),
+    }))
+}
+
+fn string_arg(args: &[Value], index: usize) -> Option<String> {
+    match args.get(index) {
+        Some(Value::Str(s)) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn call_method(object: &Value, method: &str, args: &[Value]) -> Result<Value> {
+    Ok(match (object, method) {
+        (Value::Str(s), "strip") => Value::from(strip_chars(s, args.first(), true, true)),
+        (Value::Str(s), "lstrip") => Value::from(strip_chars(s, args.first(), true, false)),
+        (Value::Str(s), "rstrip") => Value::from(strip_chars(s, args.first(), false, true)),
+        (Value::Str(s), "upper") => Value::Str(s.to_uppercase()),
+        (Value::Str(s), "lower") => Value::Str(s.to_lowercase()),
+        (Value::Str(s), "title") => Value::Str(title_case(s)),
+        (Value::Str(s), "capitalize") => Value::Str(capitalize(s)),
+        (Value::Str(s), "startswith" | "endswith") => {
+            let prefixes = match args.first() {
+                Some(Value::List(items)) => items.iter().map(Value::render).collect(),
+                Some(other) => vec![other.render()],
+                None => bail!("{method} needs an argument"),
+            };
+            Value::Bool(prefixes.iter().any(|p| {
+                if method == "startswith" {
+                    s.starts_with(p.as_str())
+                } else {
+                    s.ends_with(p.as_str())
+                }
+            }))
+        }
+        (Value::Str(s), "replace") => {
+            let (Some(from), Some(to)) = (string_arg(args, 0), string_arg(args, 1)) else {
+                bail!("replace needs two strings");
+            };
+            Value::Str(s.replace(&from, &to))
+        }
+        (Value::Str(s), "find") => {
+            let needle = string_arg(args, 0).unwrap_or_default();
+            Value::Int(
+                s.find(&needle)
+                    .map_or(-1, |byte| s[..byte].chars().count() as i64),
+            )
+        }
+        (Value::Str(s), "split") => {
+            let limit = match args.get(1) {
+                Some(Value::Int(n)) if *n >= 0 => Some(*n as usize),
+                _ => None,
+            };
+            let parts: Vec<Value> = match (string_arg(args, 0), limit) {
+                (Some(sep), Some(n)) => s.splitn(n + 1, sep.as_str()).map(Value::from).collect(),
+                (Some(sep), None) => s.split(sep.as_str()).map(Value::from).collect(),
+                (None, _) => s.split_whitespace().map(Value::from).collect(),
+            };
+            Value::list(parts)
+        }
+        (Value::Map(entries), "items") => Value::list(
+            entries
+                .iter()
+                .map(|(k, v)| Value::list([Value::from(&**k), v.clone()])),
+        ),
+        (Value::Map(entries), "keys") => {
+            Value::list(entries.iter().map(|(k, _)| Value::from(&**k)))
+        }
+        (Value::Map(entries), "values") => Value::list(entries.iter().map(|(_, v)| v.clone())),
+        (Value::Map(_) | Value::Namespace(_), "get") => {
+            let key = args.first().map(Value::render).unwrap_or_default();
+            match object.get(&key) {
+                Value::Undefined => args.get(1).cloned().unwrap_or(Value::None),
+                value => value,
+            }
+        }
+        (Value::Undefined, _) => bail!("cannot call {method}() on an undefined value"),
+        _ => bail!("unsupported method {}.{method}()", object.type_name()),
+    })
+}
+
+fn title_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut word_start = true;
+    for c in s.chars() {
+        if word_start {
+            out.extend(c.to_uppercase());
+        } else {
+            out.extend(c.to_lowercase());
+        }
+        word_start = !c.is_alphanumeric();
+    }
+    out
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+fn call_function(name: &str, args: &[Value], kwargs: Vec<(String, Value)>) -> Result<Value> {
+    Ok(match name {
+        "raise_exception" => bail!(
+            "the template raised an exception: {}",
+            args.first().map(Value::render).unwrap_or_default()
+        ),
+        "namespace" | "dict" => {
+            let mut entries = match args.first() {
+                Some(Value::Map(entries)) => entries.to_vec(),
+                _ => Vec::new(),
+            };
+            entries.extend(kwargs);
+            if name == "namespace" {
+                Value::Namespace(Rc::new(RefCell::new(entries)))
+            } else {
+                Value::Map(Rc::new(entries))
+            }
+        }
+        "range" => {
+            let ints: Vec<i64> = args
+                .iter()
+                .map(|arg| match arg {
+                    Value::Int(n) => Ok(*n),
+                    other => bail!("range() needs integers, not {}", other.type_name()),
+                })
+                .collect::<Result<_>>()?;
+            let (start, stop, step) = match ints[..] {
+                [stop] => (0, stop, 1),
+                [start, stop] => (start, stop, 1),
+                [start, stop, step] if step != 0 => (start, stop, step),
+                _ => bail!("range() takes one to three integers"),
+            };
+            let mut items = Vec::new();
+            let mut i = Some(start);
+            while let Some(n) = i
+                && ((step > 0 && n < stop) || (step < 0 && n > stop))
+            {
+                if items.len() == MAX_GENERATED {
+                    bail!("range({start}, {stop}, {step}) has over {MAX_GENERATED} items");
+                }
+                items.push(Value::Int(n));
+                i = n.checked_add(step);
+            }
+            Value::list(items)
+        }
+        "strftime_now" => Value::Str(strftime_now(&string_arg(args, 0).unwrap_or_default())),
+        other => bail!("unsupported function {other}()"),
+    })
+}
+
+fn apply_filter(
+    value: Value,
+    name: &str,
+    args: &[Value],
+    kwargs: &[(String, Value)],
+) -> Result<Value> {
+    let kwarg = |key: &str| {
+        kwargs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    };
+    Ok(match name {
+        "trim" => Value::from(strip_chars(&value.render(), args.first(), true, true)),
+        "length" | "count" => Value::Int(value.length()? as i64),
+        "string" => Value::Str(value.render()),
+        "upper" => Value::Str(value.render().to_uppercase()),
+        "lower" => Value::Str(value.render().to_lowercase()),
+        "title" => Value::Str(title_case(&value.render())),
+        "capitalize" => Value::Str(capitalize(&value.render())),
+        "safe" | "e" | "escape" => value,
+        "tojson" => {
+            let indent = match kwarg("indent").or_else(|| args.first().cloned()) {
+                Some(Value::Int(n)) if n > 0 => Some(n as usize),
+                _ => None,
+            };
+            Value::Str(value.to_json(indent, 0))
+        }
+        "int" => match &value {
+            Value::Int(_) => value,
+            Value::Float(x) => Value::Int(*x as i64),
+            Value::Str(s) => Value::Int(s.trim().parse().unwrap_or(0)),
+            Value::Bool(b) => Value::Int(i64::from(*b)),
+            _ => Value::Int(0),
+        },
+        "float" => Value::Float(value.as_number().unwrap_or(0.0)),
+        "abs" => match value {
+            Value::Int(n) => Value::Int(n.abs()),
+            Value::Float(x) => Value::Float(x.abs()),
+            other => bail!("abs needs a number, not {}", other.type_name()),
+        },
+        "first" => value
+            .items()?
+            .into_iter()
+            .next()
+            .unwrap_or(Value::Undefined),
+        "last" => value.items()?.pop().unwrap_or(Value::Undefined),
+        "list" => Value::list(value.items()?),
+        "reverse" => match &value {
+            Value::Str(s) => Value::Str(s.chars().rev().collect()),
+            _ => Value::list(value.items()?.into_iter().rev()),
+        },
+        "join" => {
+            let separator = args.first().map(Value::render).unwrap_or_default();
+            let items: Vec<String> = value.items()?.iter().map(Value::render).collect();
+            Value::Str(items.join(&separator))
+        }
+        "default" | "d" => {
+            let boolean = args.get(1).is_some_and(Value::truthy);
+            let missing = matches!(value, Value::Undefined) || (boolean && !value.truthy());
+            if missing {
+                args.first().cloned().unwrap_or(Value::from(""))
+            } else {
+                value
+            }
+        }
+        "items" => call_method(&value, "items", &[])?,
+        "replace" => call_method(&Value::Str(value.render()), "replace", args)?,
+        "map" => {
+            let Some(attribute) = kwarg("attribute") else {
+                bail!("map is only supported with attribute=");
+            };
+            let attribute = attribute.render();
+            Value::list(value.items()?.iter().map(|item| item.get(&attribute)))
+        }
+        "selectattr" | "rejectattr" => {
+            let attribute = args.first().map(Value::render).unwrap_or_default();
+            let test = args.get(1).map(Value::render);
+            let mut kept = Vec::new();
+            for item in value.items()? {
+                let field = item.get(&attribute);
+                let passed = match &test {
+                    Some(test) => apply_test(&field, test, &args[2..])?,
+                    None => field.truthy(),
+                };
+                if passed == (name == "selectattr") {
+                    kept.push(item);
+                }
+            }
+            Value::list(kept)
+        }
+        other => bail!("unsupported filter `{other}`"),
+    })
+}
+
+fn apply_test(value: &Value, name: &str, args: &[Value]) -> Result<bool> {
+    Ok(match name {
+        "defined" => !matches!(value, Value::Undefined),
+        "undefined" => matches!(value, Value::Undefined),
+        "none" => matches!(value, Value::None),
+        "string" => matches!(value, Value::Str(_)),
+        "number" => matches!(value, Value::Int(_) | Value::Float(_)),
+        "integer" => matches!(value, Value::Int(_)),
+        "float" => matches!(value, Value::Float(_)),
+        "boolean" => matches!(value, Value::Bool(_)),
+        "true" => matches!(value, Value::Bool(true)),
+        "false" => matches!(value, Value::Bool(false)),
+        "mapping" => matches!(value, Value::Map(_) | Value::Namespace(_)),
+        "sequence" | "iterable" => {
+            matches!(value, Value::List(_) | Value::Str(_) | Value::Map(_))
+        }
+        "callable" => matches!(value, Value::Macro(_)),
+        "equalto" | "eq" | "==" | "sameas" => args.first().is_some_and(|other| value == other),
+        "ne" | "!=" => args.first().is_none_or(|other| value != other),
+        "in" => match args.first() {
+            Some(container) => binary("in", value, container)?.truthy(),
+            None => false,
+        },
+        "odd" | "even" => match value {
+            Value::Int(n) => (n.rem_euclid(2) == 1) == (name == "odd"),
+            _ => false,
+        },
+        other => bail!("unsupported test `{other}`"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str) -> Result<String> {
+        Template::parse(source)?.render(&[])
+    }
+
+    #[test]
+    fn renders_a_chat_template() {
+        let template = Template::parse(
+            "{% for message in messages %}{{ '<|im_start|>' + message['role'] + '\n' + message['content'] | trim + '<|im_end|>\n' }}{% endfor %}{% if add_generation_prompt %}{{ '<|im_start|>assistant\n' }}{% endif %}",
+        )
+        .unwrap();
+        let message = Value::map([("role", "user".into()), ("content", " Hi \n".into())]);
+        let prompt = template
+            .render(&[
+                ("messages", Value::list([message])),
+                ("add_generation_prompt", true.into()),
+            ])
+            .unwrap();
+        assert_eq!(
+            prompt,
+            "<|im_start|>user\nHi<|im_end|>\n<|im_start|>assistant\n"
+        );
+    }
+
+    #[test]
+    fn integer_overflow_is_an_error() {
+        assert_eq!(render("{{ 2 + 3 * 4 - -1 }}").unwrap(), "15");
+        for (source, construct) in [
+            ("{{ 9223372036854775807 + 1 }}", "`9223372036854775807 + 1`"),
+            (
+                "{{ -9223372036854775807 - 2 }}",
+                "`-9223372036854775807 - 2`",
+            ),
+            ("{{ 4294967296 * 4294967296 }}", "`4294967296 * 4294967296`"),
+        ] {
+            let error = render(source).unwrap_err().to_string();
+            assert!(error.contains(construct), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn generated_lengths_are_capped() {
+        assert_eq!(render("{{ '-' * 3 }}").unwrap(), "---");
+        let error = render("{{ 'ab' * 4294967296 }}").unwrap_err().to_string();
+        assert!(error.contains("`*` would repeat"), "{error}");
+        assert_eq!(render("{{ range(3) | length }}").unwrap(), "3");
+        let error = render("{{ range(9223372036854775807) | length }}")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("range(0, 9223372036854775807, 1)"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! Token counting with the vocabulary embedded in a GGUF file. Byte-level BPE only
+//! (`tokenizer.ggml.model = gpt2`, as used by Llama 3, Qwen 2 and GPT-2 style models),
+//! with the pre-tokenizer picked by `tokenizer.ggml.pre`. SentencePiece, WordPiece and
+//! Unigram vocabularies, and pre-tokenizers not listed here, are refused by name rather
+//! than counted wrongly.
+
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::gguf::GGUFValue;
+use crate::summary::{metadata_entry, metadata_string, metadata_usize};
+use crate::tree::MetadataInfo;
+
+/// `token_type` values of tokens matched as a whole before pre-tokenizing
+const CONTROL: i64 = 3;
+const USER_DEFINED: i64 = 4;
+
+/// Pre-tokenizer regexes, from llama.cpp, with the trailing `\s+(?!\S)|\s+` written as
+/// `\s+`: the regex crate has no lookahead, so `pieces` gives back the last character of a
+/// whitespace run that precedes text instead
+const GPT2: &str = r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+";
+const LLAMA3: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+";
+const QWEN2: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+";
+
+/// The pre-tokenizer regex for a `tokenizer.ggml.pre` value
+fn pre_tokenizer(pre: &str) -> Option<&'static str> {
+    match pre {
+        "default" | "gpt-2" | "gpt2" => Some(GPT2),
+        "llama3" | "llama-bpe" | "llama-v3" | "smaug-bpe" | "dbrx" => Some(LLAMA3),
+        "qwen2" | "deepseek-r1-qwen" => Some(QWEN2),
+        _ => None,
+    }
+}
+
+/// What a non-BPE `tokenizer.ggml.model` is, for the refusal
+fn tokenizer_kind(model: &str) -> &'static str {
+    match model {
+        "llama" => "SentencePiece",
+        "t5" => "Unigram",
+        "bert" => "WordPiece",
+        "rwkv" => "RWKV",
+        _ => "unrecognized",
+    }
+}
+
+/// GPT-2's reversible map from bytes to printable characters, in which BPE vocabularies
+/// spell their tokens
+fn byte_chars() -> [char; 256] {
+    let mut chars = ['\0'; 256];
+    let mut shifted = 0;
+    for byte in 0..=255u8 {
+        let printable = matches!(byte, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+        chars[byte as usize] = if printable {
+            char::from(byte)
+        } else {
+            shifted += 1;
+            char::from_u32(255 + shifted).unwrap_or('\0')
+        };
+    }
+    chars
+}
+
+fn string_array(metadata: &[MetadataInfo], key: &str) -> Option<Vec<String>> {
+    match metadata_entry(metadata, key)?.raw_value.as_deref() {
+        Some(GGUFValue::Array(_, items)) => Some(
+            items
+                .iter()
+                .map(|item| match item {
+                    GGUFValue::String(s) => s.clone(),
+                    _ => String::new(),
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn int_array(metadata: &[MetadataInfo], key: &str) -> Option<Vec<i64>> {
+    match metadata_entry(metadata, key)?.raw_value.as_deref() {
+        Some(GGUFValue::Array(_, items)) => Some(
+            items
+                .iter()
+                .map(|item| item.as_i64().unwrap_or(0))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// A byte-level BPE tokenizer read from GGUF metadata
+#[derive(Debug)]
+pub struct BpeTokenizer {
+    /// `tokenizer.ggml.pre`
+    pub pre: String,
+    pub vocab_size: usize,
+    pub bos_token: Option<String>,
+    pub eos_token: Option<String>,
+    /// Whether a BOS token starts every prompt (`tokenizer.ggml.add_bos_token`)
+    pub add_bos: bool,
+    vocab: HashMap<String, u32>,
+    /// Rank and result of each merge, by the pair it merges
+    merges: HashMap<(u32, u32), (usize, u32)>,
+    /// Control and user-defined tokens, longest first; None when there are none
+    specials: Option<Regex>,
+    splitter: Regex,
+    /// Whether whitespace ending in a newline is its own piece (the Llama 3 family)
+    newline_pieces: bool,
+    byte_chars: [char; 256],
+}
+
+impl BpeTokenizer {
+    pub fn from_metadata(metadata: &[MetadataInfo]) -> Result<Self> {
+        let Some(model) = metadata_string(metadata, "tokenizer.ggml.model") else {
+            bail!("No tokenizer is embedded (no tokenizer.ggml.model)");
+        };
+        if model != "gpt2" {
+            bail!(
+                "The {} tokenizer of this file (tokenizer.ggml.model = {model}) isn't supported; only byte-level BPE (gpt2) tokenizers can be counted",
+                tokenizer_kind(&model)
+            );
+        }
+        let pre = metadata_string(metadata, "tokenizer.ggml.pre").unwrap_or("default".into());
+        let Some(pattern) = pre_tokenizer(&pre) else {
+            bail!(
+                "The BPE pre-tokenizer '{pre}' isn't supported; counts need one of default, llama-bpe or qwen2"
+            );
+        };
+        let Some(tokens) = string_array(metadata, "tokenizer.ggml.tokens") else {
+            bail!("The tokenizer has no vocabulary (tokenizer.ggml.tokens)");
+        };
+        let Some(merge_list) = string_array(metadata, "tokenizer.ggml.merges") else {
+            bail!("The BPE tokenizer has no merges (tokenizer.ggml.merges)");
+        };
+        let types = int_array(metadata, "tokenizer.ggml.token_type").unwrap_or_default();
+
+        let vocab: HashMap<String, u32> = tokens
+            .iter()
+            .enumerate()
+            .map(|(id, token)| (token.clone(), id as u32))
+            .collect();
+        let mut merges = HashMap::with_capacity(merge_list.len());
+        for (rank, merge) in merge_list.iter().enumerate() {
+            let Some((left, right)) = merge.split_once(' ') else {
+                continue;
+            };
+            let ids = (
+                vocab.get(left),
+                vocab.get(right),
+                vocab.get(&format!("{left}{right}")),
+            );
+            if let (Some(&left), Some(&right), Some(&merged)) = ids {
+                merges.entry((left, right)).or_insert((rank, merged));
+            }
+        }
+
+        let mut specials: Vec<&str> = tokens
+            .iter()
+            .zip(&types)
+            .filter(|(token, kind)| matches!(**kind, CONTROL | USER_DEFINED) && !token.is_empty())
+            .map(|(token, _)| token.as_str())
+            .collect();
+        specials.sort_by_key(|token| std::cmp::Reverse(token.len()));
+        let specials = if specials.is_empty() {
+            None
+        } else {
+            let escaped: Vec<String> = specials.iter().map(|s| regex::escape(s)).collect();
+            Some(Regex::new(&escaped.join("|"))?)
+        };
+
+        let token =
+            |key: &str| metadata_usize(metadata, key).and_then(|id| tokens.get(id).cloned());
+        let add_bos = matches!(
+            metadata_entry(metadata, "tokenizer.ggml.add_bos_token")
+                .and_then(|entry| entry.raw_value.as_deref()),
+            Some(GGUFValue::Bool(true))
+        );
+        Ok(BpeTokenizer {
+            newline_pieces: pattern != GPT2,
+            splitter: Regex::new(pattern)?,
+            pre,
+            vocab_size: tokens.len(),
+            bos_token: token("tokenizer.ggml.bos_token_id"),
+            eos_token: token("tokenizer.ggml.eos_token_id"),
+            add_bos,
+            vocab,
+            merges,
+            specials,
+            byte_chars: byte_chars(),
+        })
+    }
+
+    /// Pre-tokenized pieces of `text`, which holds no special tokens
+    fn pieces<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        while let Some(found) = self.splitter.find_at(text, start) {
+            let mut end = found.end();
+            let piece = found.as_str();
+            // `\s+(?!\S)`: a whitespace run followed by text leaves its last character to
+            // the text's piece, unless the run is a single character or ends a line
+            let run_before_text = piece.chars().all(char::is_whitespace)
+                && text[end..].starts_with(|c: char| !c.is_whitespace())
+                && !(self.newline_pieces && piece.ends_with(['\r', '\n']));
+            if run_before_text && piece.chars().count() > 1 {
+                end -= piece.chars().next_back().map_or(0, char::len_utf8);
+            }
+            pieces.push(&text[found.start()..end]);
+            start = end;
+        }
+        pieces
+    }
+
+    /// Tokens of one pre-tokenized piece after applying the merges
+    fn merged_len(&self, piece: &str) -> usize {
+        let spelled: String = piece
+            .bytes()
+            .map(|byte| self.byte_chars[byte as usize])
+            .collect();
+        if self.vocab.contains_key(&spelled) {
+            return 1;
+        }
+        // Characters missing from the vocabulary stay single tokens
+        let mut symbols: Vec<u32> = spelled
+            .chars()
+            .map(|c| {
+                let mut buffer = [0; 4];
+                self.vocab
+                    .get(c.encode_utf8(&mut buffer) as &str)
+                    .copied()
+                    .unwrap_or(u32::MAX)
+            })
+            .collect();
+        loop {
+            let best = symbols
+                .windows(2)
+                .enumerate()
+                .filter_map(|(i, pair)| {
+                    self.merges
+                        .get(&(pair[0], pair[1]))
+                        .map(|&(rank, merged)| (rank, i, merged))
+                })
+                .min();
+            let Some((_, i, merged)) = best else {
+                return symbols.len();
+            };
+            symbols[i] = merged;
+            symbols.remove(i + 1);
+        }
+    }
+
+    /// Tokens in `text`, with special tokens matched whole; no BOS is added
+    pub fn count(&self, text: &str) -> usize {
+        let mut count = 0;
+        let plain = |segment: &str| -> usize {
+            self.pieces(segment)
+                .into_iter()
+                .map(|piece| self.merged_len(piece))
+                .sum()
+        };
+        let Some(specials) = &self.specials else {
+            return plain(text);
+        };
+        let mut start = 0;
+        for special in specials.find_iter(text) {
+            count += plain(&text[start..special.start()]) + 1;
+            start = special.end();
+        }
+        count + plain(&text[start..])
+    }
+
+    /// Tokens of a whole prompt: `count` plus the BOS token when the model adds one and the
+    /// text doesn't already start with it
+    pub fn count_prompt(&self, text: &str) -> usize {
+        let bos = match &self.bos_token {
+            Some(bos) if self.add_bos && !text.starts_with(bos.as_str()) => 1,
+            _ => 0,
+        };
+        bos + self.count(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gguf::MetadataType;
+    use std::sync::Arc;
+
+    fn entry(name: &str, value: GGUFValue) -> MetadataInfo {
+        MetadataInfo {
+            name: name.to_string(),
+            value: value.to_string(),
+            value_type: value.type_name(),
+            raw_value: Some(Arc::new(value)),
+            source: None,
+        }
+    }
+
+    fn strings(items: &[&str]) -> GGUFValue {
+        let items = items.iter().map(|s| GGUFValue::String(s.to_string()));
+        GGUFValue::Array(MetadataType::String, items.collect())
+    }
+
+    fn tokenizer(pre: &str, tokens: &[&str], merges: &[&str]) -> BpeTokenizer {
+        BpeTokenizer::from_metadata(&[
+            entry("tokenizer.ggml.model", GGUFValue::String("gpt2".into())),
+            entry("tokenizer.ggml.pre", GGUFValue::String(pre.into())),
+            entry("tokenizer.ggml.tokens", strings(tokens)),
+            entry("tokenizer.ggml.merges", strings(merges)),
+        ])
+        .unwrap()
+    }
+
+    /// A text and its pieces under the llama3, qwen2 and gpt2 pre-tokenizers
+    type Case<'a> = (&'a str, &'a [&'a str], &'a [&'a str], &'a [&'a str]);
+
+    /// The splits of llama.cpp's regexes, whose `\s+(?!\S)` needs lookahead
+    #[test]
+    fn pieces_emulate_the_whitespace_lookahead() {
+        let cases: &[Case] = &[
+            (
+                "Hello  world",
+                &["Hello", " ", " world"],
+                &["Hello", " ", " world"],
+                &["Hello", " ", " world"],
+            ),
+            (
+                "a   b\n\n  c",
+                &["a", "  ", " b", "\n\n", " ", " c"],
+                &["a", "  ", " b", "\n\n", " ", " c"],
+                &["a", "  ", " b", "\n\n ", " c"],
+            ),
+            (
+                "x \n y",
+                &["x", " \n", " y"],
+                &["x", " \n", " y"],
+                &["x", " \n", " y"],
+            ),
+            ("end   ", &["end", "   "], &["end", "   "], &["end", "   "]),
+            (
+                "12345 it's\t\tok",
+                &["123", "45", " it", "'s", "\t", "\tok"],
+                &["1", "2", "3", "4", "5", " it", "'s", "\t", "\tok"],
+                &["12345", " it", "'s", "\t", "\t", "ok"],
+            ),
+            ("  lead", &[" ", " lead"], &[" ", " lead"], &[" ", " lead"]),
+        ];
+        let llama3 = tokenizer("llama-bpe", &[], &[]);
+        let qwen2 = tokenizer("qwen2", &[], &[]);
+        let gpt2 = tokenizer("gpt2", &[], &[]);
+        for (text, llama3_pieces, qwen2_pieces, gpt2_pieces) in cases {
+            assert_eq!(llama3.pieces(text), *llama3_pieces, "llama3 {text:?}");
+            assert_eq!(qwen2.pieces(text), *qwen2_pieces, "qwen2 {text:?}");
+            assert_eq!(gpt2.pieces(text), *gpt2_pieces, "gpt2 {text:?}");
+        }
+    }
+
+    #[test]
+    fn merges_apply_lowest_rank_first() {
+        // `ab` outranks `bc`, so `abc` becomes `ab` `c`; with no merge for those it stays two
+        let tokens = ["a", "b", "c", "ab", "bc"];
+        let bpe = tokenizer("gpt2", &tokens, &["a b", "b c"]);
+        assert_eq!(bpe.count("abc"), 2);
+        assert_eq!(bpe.count("bc"), 1);
+        // A character missing from the vocabulary is a token of its own
+        assert_eq!(bpe.count("abz"), 2);
+    }
+}
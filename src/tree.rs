@@ -1,22 +1,202 @@
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 
-#[derive(Debug, Clone)]
+use crate::gguf::GGUFValue;
+pub use crate::quant::QuantInfo;
+use crate::utils::model_extension;
+
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct TensorInfo {
     pub name: String,
     pub dtype: String,
+    /// Row-major (HF) order, whatever order the file stores the dimensions in
     pub shape: Vec<usize>,
+    /// Dimensions as the file lists them, when that differs from `shape` (GGUF ne order,
+    /// fastest-varying first)
+    pub storage_shape: Option<Vec<usize>>,
     pub size_bytes: usize,
     pub num_elements: usize,
+    pub source_file: PathBuf,
+    /// Absolute offset of the tensor data within `source_file`, when known
+    pub data_offset: Option<u64>,
+    /// Set on the packed tensors of a GPTQ/AWQ quantized module
+    pub quant: Option<QuantInfo>,
+    /// Format-specific remarks shown in the detail view (e.g. storage order)
+    pub notes: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+impl TensorInfo {
+    /// A tensor with no storage shape, data offset, quantization or notes; set those fields
+    /// afterwards when known
+    pub fn new(
+        name: impl Into<String>,
+        dtype: impl Into<String>,
+        shape: Vec<usize>,
+        size_bytes: usize,
+        source_file: impl Into<PathBuf>,
+    ) -> Self {
+        TensorInfo {
+            name: name.into(),
+            dtype: dtype.into(),
+            num_elements: shape.iter().product(),
+            shape,
+            storage_shape: None,
+            size_bytes,
+            source_file: source_file.into(),
+            data_offset: None,
+            quant: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Shape for the tree: the storage order when asked for and the file has one
+    pub fn display_shape(&self, storage_order: bool) -> &[usize] {
+        match &self.storage_shape {
+            Some(storage) if storage_order => storage,
+            _ => &self.shape,
+        }
+    }
+
+    /// Shape of the weight a quantized module's packed tensor stands for; the stored shape
+    /// for everything else
+    pub fn logical_shape(&self) -> &[usize] {
+        match &self.quant {
+            Some(quant) if quant.is_packed_weight(&self.name) => &quant.logical_shape,
+            _ => &self.shape,
+        }
+    }
+
+    /// Offset of the tensor's data in `source_file`, or why there is none: members
+    /// compressed inside a zip or npz archive can't be read in place
+    pub fn data_location(&self) -> anyhow::Result<u64> {
+        if let Some(offset) = self.data_offset {
+            return Ok(offset);
+        }
+        if matches!(model_extension(&self.source_file), Some("zip" | "npz")) {
+            anyhow::bail!(
+                "Tensor {} is compressed inside {}; extract it first",
+                self.name,
+                self.source_file.display()
+            );
+        }
+        anyhow::bail!("Data location unknown for tensor {}", self.name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct MetadataInfo {
     pub name: String,
     pub value: String,
     pub value_type: String,
+    /// Structured value for entries read from typed sources such as GGUF
+    pub raw_value: Option<Arc<GGUFValue>>,
+    /// Sidecar file the entry came from (e.g. `config.json`); None when embedded in the model
+    pub source: Option<String>,
 }
 
+/// Characters of context kept on each side of a match in a value excerpt
+const EXCERPT_CONTEXT: usize = 16;
+
+/// Where a search query occurs inside a metadata value
 #[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ValueMatch {
+    /// Position of the matching element when the value is an array
+    pub element: Option<usize>,
+    /// Text around the match, with `…` where it was cut
+    pub excerpt: String,
+    /// Byte range of the match within `excerpt`
+    pub range: Range<usize>,
+}
+
+impl MetadataInfo {
+    /// First case-insensitive occurrence of `query` in the value. Arrays are searched
+    /// element by element, stopping at the first hit, so huge vocab arrays are never
+    /// rendered as a whole.
+    pub fn find_in_value(&self, query: &str) -> Option<ValueMatch> {
+        if query.is_empty() {
+            return None;
+        }
+        let (element, (excerpt, range)) = match self.raw_value.as_deref() {
+            Some(GGUFValue::Array(_, items)) => {
+                items.iter().enumerate().find_map(|(i, item)| {
+                    let text = match item {
+                        GGUFValue::String(s) => excerpt_around(s, query),
+                        GGUFValue::Array(..) => excerpt_around(&item.to_json().to_string(), query),
+                        scalar => excerpt_around(&scalar.to_string(), query),
+                    };
+                    text.map(|found| (Some(i), found))
+                })?
+            }
+            Some(GGUFValue::String(s)) => (None, excerpt_around(s, query)?),
+            _ => (None, excerpt_around(&self.value, query)?),
+        };
+        Some(ValueMatch {
+            element,
+            excerpt,
+            range,
+        })
+    }
+}
+
+/// Excerpt of `text` around the first ASCII-case-insensitive occurrence of `query`,
+/// on one line, with the byte range of the occurrence in the excerpt
+fn excerpt_around(text: &str, query: &str) -> Option<(String, Range<usize>)> {
+    // ASCII lowercasing keeps byte offsets, so they carry over to `text`
+    let start = text
+        .to_ascii_lowercase()
+        .find(&query.to_ascii_lowercase())?;
+    let end = start + query.len();
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(EXCERPT_CONTEXT - 1)
+        .map_or(0, |(i, _)| i);
+    let to = text[end..]
+        .char_indices()
+        .nth(EXCERPT_CONTEXT)
+        .map_or(text.len(), |(i, _)| end + i);
+
+    let mut excerpt = String::new();
+    if from > 0 {
+        excerpt.push('…');
+    }
+    let range = excerpt.len() + start - from..excerpt.len() + end - from;
+    // Single-byte replacements, so the range stays valid
+    excerpt.push_str(&text[from..to].replace(['\n', '\r', '\t'], " "));
+    if to < text.len() {
+        excerpt.push('…');
+    }
+    Some((excerpt, range))
+}
+
+/// A file that could not be parsed, kept so the rest of the model can still be explored
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct LoadFailure {
+    pub file: PathBuf,
+    /// The error and each of its causes, outermost first
+    pub chain: Vec<String>,
+    /// Byte offset in the file where parsing stopped, when the parser knows it
+    pub offset: Option<u64>,
+}
+
+impl LoadFailure {
+    pub fn file_name(&self) -> String {
+        self.file.file_name().map_or_else(
+            || self.file.to_string_lossy().into_owned(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub enum TreeNode {
     Group {
         name: String,
@@ -24,6 +204,12 @@ pub enum TreeNode {
         expanded: bool,
         tensor_count: usize,
         total_size: usize,
+        /// Subtree aggregates, computed on first use; clones of the node share them
+        #[serde(skip)]
+        stats: Arc<OnceLock<GroupStats>>,
+        /// Order of this group's children when it differs from the tree's, set with `s`
+        #[serde(skip)]
+        sort: Option<SortOrder>,
     },
     Tensor {
         info: TensorInfo,
@@ -31,6 +217,11 @@ pub enum TreeNode {
     Metadata {
         info: MetadataInfo,
     },
+    /// Top-level placeholder for a file that failed to load, named after the file
+    Failed {
+        name: String,
+        info: LoadFailure,
+    },
 }
 
 impl TreeNode {
@@ -39,8 +230,130 @@ impl TreeNode {
             TreeNode::Group { name, .. } => name,
             TreeNode::Tensor { info } => &info.name,
             TreeNode::Metadata { info } => &info.name,
+            TreeNode::Failed { name, .. } => name,
+        }
+    }
+
+    /// Tensors still present below this node after small tensors were pruned
+    pub fn visible_tensor_count(&self) -> usize {
+        match self {
+            TreeNode::Group { children, .. } => {
+                children.iter().map(TreeNode::visible_tensor_count).sum()
+            }
+            TreeNode::Tensor { .. } => 1,
+            TreeNode::Metadata { .. } | TreeNode::Failed { .. } => 0,
+        }
+    }
+
+    /// Tensors at or below this node that are still in the tree
+    pub fn tensors(&self) -> Vec<&TensorInfo> {
+        match self {
+            TreeNode::Group { children, .. } => {
+                children.iter().flat_map(TreeNode::tensors).collect()
+            }
+            TreeNode::Tensor { info } => vec![info],
+            TreeNode::Metadata { .. } | TreeNode::Failed { .. } => Vec::new(),
+        }
+    }
+
+    /// Aggregates over the group's subtree, computed on the first call and cached on the node
+    pub fn group_stats(&self) -> Option<&GroupStats> {
+        match self {
+            TreeNode::Group { stats, .. } => {
+                Some(stats.get_or_init(|| TreeBuilder::subtree_stats(self)))
+            }
+            _ => None,
         }
     }
+
+    /// Quantization of the module this group holds, when its children are GPTQ/AWQ or MLX
+    /// packed tensors
+    pub fn group_quant(&self) -> Option<&QuantInfo> {
+        let TreeNode::Group { children, .. } = self else {
+            return None;
+        };
+        children.iter().find_map(|child| match child {
+            TreeNode::Tensor { info } => info
+                .quant
+                .as_ref()
+                .filter(|quant| quant.is_packed_weight(&info.name)),
+            _ => None,
+        })
+    }
+}
+
+/// Aggregates over everything below a group, shown by the group info popup
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct GroupStats {
+    pub tensors: usize,
+    pub parameters: usize,
+    pub bytes: usize,
+    pub composition: Vec<DtypeShare>,
+    /// Name and size in bytes of the largest and the smallest tensor
+    pub largest: Option<(String, usize)>,
+    pub smallest: Option<(String, usize)>,
+    /// Distinct shapes with their tensor counts, most common first
+    pub shapes: Vec<(Vec<usize>, usize)>,
+    /// Lowest and highest layer index among the tensors
+    pub layers: Option<(usize, usize)>,
+    pub metadata_keys: usize,
+    /// Key with the longest value and that value's length in bytes
+    pub largest_value: Option<(String, usize)>,
+}
+
+/// Bytes held by one dtype within a group
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DtypeShare {
+    pub dtype: String,
+    pub tensors: usize,
+    pub bytes: usize,
+}
+
+/// Per-dtype byte totals, largest first (ties by dtype name)
+pub fn dtype_composition<'a>(tensors: impl IntoIterator<Item = &'a TensorInfo>) -> Vec<DtypeShare> {
+    let mut shares: Vec<DtypeShare> = Vec::new();
+    for tensor in tensors {
+        match shares.iter_mut().find(|s| s.dtype == tensor.dtype) {
+            Some(share) => {
+                share.tensors += 1;
+                share.bytes += tensor.size_bytes;
+            }
+            None => shares.push(DtypeShare {
+                dtype: tensor.dtype.clone(),
+                tensors: 1,
+                bytes: tensor.size_bytes,
+            }),
+        }
+    }
+    shares.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.dtype.cmp(&b.dtype)));
+    shares
+}
+
+/// Compact breakdown such as `Q4_K 78%, Q6_K 18%, F32 4%`, listing at most `max` dtypes;
+/// a single dtype is shown without a percentage
+pub fn composition_label(shares: &[DtypeShare], max: usize) -> String {
+    if let [only] = shares {
+        return only.dtype.clone();
+    }
+    let total: usize = shares.iter().map(|s| s.bytes).sum();
+    let mut parts: Vec<String> = shares
+        .iter()
+        .take(max)
+        .map(|share| {
+            let percent = if total == 0 {
+                0.0
+            } else {
+                share.bytes as f64 / total as f64 * 100.0
+            };
+            format!("{} {percent:.0}%", share.dtype)
+        })
+        .collect();
+    if shares.len() > max {
+        parts.push(format!("+{} more", shares.len() - max));
+    }
+    parts.join(", ")
 }
 
 pub fn natural_sort_key(name: &str) -> Vec<NaturalSortItem> {
@@ -83,42 +396,235 @@ pub fn natural_sort_key(name: &str) -> Vec<NaturalSortItem> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum NaturalSortItem {
     Text(String),
     Number(u32),
 }
 
+/// How tensors are grouped into the top level of the tree
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum GroupBy {
+    /// Nested by dot-separated name prefix
+    #[default]
+    Prefix,
+    /// One group per dtype, tensors listed by full name, largest first
+    Dtype,
+    /// One group per shape, tensors listed by full name, largest first
+    Shape,
+    /// One group per source file, with the prefix tree underneath
+    File,
+}
+
+impl GroupBy {
+    pub fn next(self) -> Self {
+        match self {
+            GroupBy::Prefix => GroupBy::Dtype,
+            GroupBy::Dtype => GroupBy::Shape,
+            GroupBy::Shape => GroupBy::File,
+            GroupBy::File => GroupBy::Prefix,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupBy::Prefix => "prefix",
+            GroupBy::Dtype => "dtype",
+            GroupBy::Shape => "shape",
+            GroupBy::File => "file",
+        }
+    }
+
+    /// Whether tensors appear under flat groups and need their full name shown
+    pub fn flat(self) -> bool {
+        matches!(self, GroupBy::Dtype | GroupBy::Shape)
+    }
+}
+
+/// Order of the entries within each group
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum SortOrder {
+    /// Natural name order, so `layers.2` comes before `layers.10`
+    #[default]
+    Name,
+    /// Largest first; metadata groups stay on top
+    Size,
+    /// Most parameters first; metadata groups stay on top
+    Params,
+}
+
+impl SortOrder {
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Name => "name",
+            SortOrder::Size => "size",
+            SortOrder::Params => "params",
+        }
+    }
+}
+
+/// Name of the group holding the tensors nested deeper than [`TreeLimits::max_depth`]
+pub const DEEP_GROUP_NAME: &str = "…";
+
+/// Safeguards for generated names (flattened dict keys, exported graphs) that would
+/// otherwise build trees too deep or too wide to navigate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TreeLimits {
+    /// Levels of prefix groups; the tensors below the last level are listed by full name
+    /// under one `…` group
+    pub max_depth: usize,
+    /// Distinct top-level prefixes above which the tensors are listed flat instead
+    pub max_top_level: usize,
+}
+
+impl Default for TreeLimits {
+    fn default() -> Self {
+        TreeLimits {
+            max_depth: 10,
+            max_top_level: 1000,
+        }
+    }
+}
+
+impl TreeLimits {
+    pub fn new(max_depth: usize, max_top_level: usize) -> Self {
+        TreeLimits {
+            max_depth,
+            max_top_level,
+        }
+    }
+
+    /// Whether `tensors` have too many top-level prefixes to group
+    pub fn too_wide(&self, tensors: &[TensorInfo]) -> bool {
+        let mut prefixes = HashSet::new();
+        tensors
+            .iter()
+            .filter_map(|t| t.name.split_once('.').map(|(first, _)| first))
+            .any(|prefix| prefixes.insert(prefix) && prefixes.len() > self.max_top_level)
+    }
+}
+
 pub struct TreeBuilder;
 
 impl TreeBuilder {
-    pub fn build_tree_mixed(tensors: &[TensorInfo], metadata: &[MetadataInfo]) -> Vec<TreeNode> {
+    pub fn build_tree_mixed(
+        tensors: &[TensorInfo],
+        metadata: &[MetadataInfo],
+        group_by: GroupBy,
+        limits: TreeLimits,
+    ) -> Vec<TreeNode> {
         let mut tree = Vec::new();
 
-        // Add metadata as a separate group
-        if !metadata.is_empty() {
+        // Add metadata as separate groups: embedded metadata first, then one per sidecar file
+        let mut sources: Vec<Option<&str>> = Vec::new();
+        for meta in metadata {
+            if !sources.contains(&meta.source.as_deref()) {
+                sources.push(meta.source.as_deref());
+            }
+        }
+        sources.sort();
+
+        for source in sources {
             let mut metadata_children = Vec::new();
-            for meta in metadata {
+            for meta in metadata.iter().filter(|m| m.source.as_deref() == source) {
                 metadata_children.push(TreeNode::Metadata { info: meta.clone() });
             }
             metadata_children.sort_by_key(|a| natural_sort_key(a.name()));
 
             tree.push(TreeNode::Group {
-                name: "🔧 Metadata".to_string(),
+                name: format!("🔧 {}", source.unwrap_or("Metadata")),
                 children: metadata_children,
                 expanded: false,
                 tensor_count: 0,
                 total_size: 0,
+                stats: Default::default(),
+                sort: None,
             });
         }
 
         // Build tensor tree
-        let tensor_tree = Self::build_tree(tensors);
+        let tensor_tree = Self::build_tree_by(tensors, group_by, limits);
         tree.extend(tensor_tree);
 
         tree
     }
 
-    pub fn build_tree(tensors: &[TensorInfo]) -> Vec<TreeNode> {
+    pub fn build_tree_by(
+        tensors: &[TensorInfo],
+        group_by: GroupBy,
+        limits: TreeLimits,
+    ) -> Vec<TreeNode> {
+        let key: fn(&TensorInfo) -> String = match group_by {
+            GroupBy::Prefix => return Self::build_tree(tensors, limits),
+            GroupBy::Dtype => |t: &TensorInfo| t.dtype.clone(),
+            GroupBy::Shape => |t: &TensorInfo| crate::utils::format_shape(&t.shape),
+            GroupBy::File => |t: &TensorInfo| crate::utils::display_file_name(&t.source_file),
+        };
+
+        let mut groups: HashMap<String, Vec<TensorInfo>> = HashMap::new();
+        for tensor in tensors {
+            groups.entry(key(tensor)).or_default().push(tensor.clone());
+        }
+
+        let mut tree: Vec<TreeNode> = groups
+            .into_iter()
+            .map(|(name, mut tensors)| {
+                let tensor_count = tensors.len();
+                let total_size = tensors.iter().map(|t| t.size_bytes).sum();
+                let children = if group_by == GroupBy::File {
+                    Self::build_tree(&tensors, limits)
+                } else {
+                    tensors.sort_by(|a, b| {
+                        b.size_bytes
+                            .cmp(&a.size_bytes)
+                            .then_with(|| natural_sort_key(&a.name).cmp(&natural_sort_key(&b.name)))
+                    });
+                    tensors
+                        .into_iter()
+                        .map(|info| TreeNode::Tensor { info })
+                        .collect()
+                };
+                TreeNode::Group {
+                    name,
+                    children,
+                    expanded: false,
+                    tensor_count,
+                    total_size,
+                    stats: Default::default(),
+                    sort: None,
+                }
+            })
+            .collect();
+
+        // Files keep their natural order; dtype and shape groups put the heaviest first
+        if group_by == GroupBy::File {
+            tree.sort_by_key(|a| natural_sort_key(a.name()));
+        } else {
+            tree.sort_by_key(|node| match node {
+                TreeNode::Group { total_size, .. } => std::cmp::Reverse(*total_size),
+                _ => std::cmp::Reverse(0),
+            });
+        }
+        tree
+    }
+
+    /// Nest `tensors` by dot-separated name prefix. A chain of groups with one subgroup each
+    /// becomes one group (`vision_tower.vision_model`); past `limits.max_depth` levels the
+    /// rest of a group's tensors are listed under `…`, and with more than
+    /// `limits.max_top_level` top-level prefixes the tensors are listed flat.
+    pub fn build_tree(tensors: &[TensorInfo], limits: TreeLimits) -> Vec<TreeNode> {
+        if limits.too_wide(tensors) {
+            return Self::flat_tensors(tensors);
+        }
         let mut root_map: HashMap<String, Vec<TensorInfo>> = HashMap::new();
 
         for tensor in tensors {
@@ -141,19 +647,16 @@ impl TreeBuilder {
                     tree.push(TreeNode::Tensor { info: tensor });
                 }
             } else {
-                tensors.sort_by(|a, b| natural_sort_key(&a.name).cmp(&natural_sort_key(&b.name)));
-                let tensor_count = tensors.len();
-                let total_size = tensors.iter().map(|t| t.size_bytes).sum();
-
-                let children = Self::build_subtree(&tensors, &prefix);
-
-                tree.push(TreeNode::Group {
-                    name: prefix,
-                    children,
-                    expanded: true,
-                    tensor_count,
-                    total_size,
-                });
+                tensors.sort_by_key(|a| natural_sort_key(&a.name));
+                let (name, full_prefix) = Self::collapse_chain(&tensors, prefix.clone(), prefix);
+                tree.push(Self::prefix_group(
+                    name,
+                    &tensors,
+                    &full_prefix,
+                    1,
+                    limits,
+                    true,
+                ));
             }
         }
 
@@ -161,7 +664,12 @@ impl TreeBuilder {
         tree
     }
 
-    fn build_subtree(tensors: &[TensorInfo], prefix: &str) -> Vec<TreeNode> {
+    fn build_subtree(
+        tensors: &[TensorInfo],
+        prefix: &str,
+        level: usize,
+        limits: TreeLimits,
+    ) -> Vec<TreeNode> {
         let mut groups: HashMap<String, Vec<TensorInfo>> = HashMap::new();
         let mut direct_tensors = Vec::new();
 
@@ -186,25 +694,149 @@ impl TreeBuilder {
             result.push(TreeNode::Tensor { info: tensor });
         }
 
-        for (group_name, group_tensors) in groups {
-            let tensor_count = group_tensors.len();
-            let total_size = group_tensors.iter().map(|t| t.size_bytes).sum();
-            let full_prefix = format!("{prefix}.{group_name}");
-            let children = Self::build_subtree(&group_tensors, &full_prefix);
-
+        if level >= limits.max_depth && !groups.is_empty() {
+            let deeper: Vec<TensorInfo> = groups.into_values().flatten().collect();
+            result.sort_by_key(|a| natural_sort_key(a.name()));
             result.push(TreeNode::Group {
-                name: group_name,
-                children,
+                name: DEEP_GROUP_NAME.to_string(),
+                tensor_count: deeper.len(),
+                total_size: deeper.iter().map(|t| t.size_bytes).sum(),
+                children: Self::flat_tensors(&deeper),
                 expanded: false,
-                tensor_count,
-                total_size,
+                stats: Default::default(),
+                sort: None,
             });
+            return result;
+        }
+
+        for (group_name, group_tensors) in groups {
+            let (name, full_prefix) = Self::collapse_chain(
+                &group_tensors,
+                group_name.clone(),
+                format!("{prefix}.{group_name}"),
+            );
+            result.push(Self::prefix_group(
+                name,
+                &group_tensors,
+                &full_prefix,
+                level + 1,
+                limits,
+                false,
+            ));
         }
 
         result.sort_by_key(|a| natural_sort_key(a.name()));
         result
     }
 
+    fn prefix_group(
+        name: String,
+        tensors: &[TensorInfo],
+        prefix: &str,
+        level: usize,
+        limits: TreeLimits,
+        expanded: bool,
+    ) -> TreeNode {
+        TreeNode::Group {
+            name,
+            children: Self::build_subtree(tensors, prefix, level, limits),
+            expanded,
+            tensor_count: tensors.len(),
+            total_size: tensors.iter().map(|t| t.size_bytes).sum(),
+            stats: Default::default(),
+            sort: None,
+        }
+    }
+
+    /// Extend a group's name and prefix while all its tensors continue with the same
+    /// segment, so that single-child chains show as one group
+    fn collapse_chain(
+        tensors: &[TensorInfo],
+        mut name: String,
+        mut prefix: String,
+    ) -> (String, String) {
+        loop {
+            let dotted = format!("{prefix}.");
+            let mut next = tensors.iter().map(|tensor| {
+                tensor
+                    .name
+                    .strip_prefix(&dotted)
+                    .and_then(|remaining| remaining.split_once('.'))
+                    .map(|(segment, _)| segment)
+            });
+            let Some(Some(first)) = next.next() else {
+                return (name, prefix);
+            };
+            if !next.all(|segment| segment == Some(first)) {
+                return (name, prefix);
+            }
+            name = format!("{name}.{first}");
+            prefix = format!("{prefix}.{first}");
+        }
+    }
+
+    /// Tensors as a flat list in natural name order
+    fn flat_tensors(tensors: &[TensorInfo]) -> Vec<TreeNode> {
+        let mut tensors = tensors.to_vec();
+        tensors.sort_by_cached_key(|a| natural_sort_key(&a.name));
+        tensors
+            .into_iter()
+            .map(|info| TreeNode::Tensor { info })
+            .collect()
+    }
+
+    /// Metadata entries at or below `node`
+    pub fn subtree_metadata(node: &TreeNode) -> Vec<&MetadataInfo> {
+        match node {
+            TreeNode::Group { children, .. } => {
+                children.iter().flat_map(Self::subtree_metadata).collect()
+            }
+            TreeNode::Metadata { info } => vec![info],
+            TreeNode::Tensor { .. } | TreeNode::Failed { .. } => Vec::new(),
+        }
+    }
+
+    /// Aggregate the tensors and metadata still in the tree below `node`
+    pub fn subtree_stats(node: &TreeNode) -> GroupStats {
+        let tensors = node.tensors();
+        let mut shapes: Vec<(Vec<usize>, usize)> = Vec::new();
+        for tensor in &tensors {
+            match shapes.iter_mut().find(|(shape, _)| *shape == tensor.shape) {
+                Some((_, count)) => *count += 1,
+                None => shapes.push((tensor.shape.clone(), 1)),
+            }
+        }
+        shapes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let layers = tensors
+            .iter()
+            .filter_map(|t| crate::summary::layer_index(&t.name))
+            .fold(None, |range, index| match range {
+                None => Some((index, index)),
+                Some((low, high)) => Some((index.min(low), index.max(high))),
+            });
+        let by_size = |t: &&TensorInfo| (t.name.clone(), t.size_bytes);
+
+        let metadata = Self::subtree_metadata(node);
+        let largest_value = metadata
+            .iter()
+            .map(|m| (m.name.clone(), crate::summary::metadata_raw_text(m).len()))
+            .max_by_key(|(_, len)| *len);
+
+        GroupStats {
+            tensors: tensors.len(),
+            parameters: tensors.iter().map(|t| t.num_elements).sum(),
+            bytes: tensors.iter().map(|t| t.size_bytes).sum(),
+            composition: dtype_composition(tensors.iter().copied()),
+            largest: tensors.iter().max_by_key(|t| t.size_bytes).map(by_size),
+            smallest: tensors.iter().min_by_key(|t| t.size_bytes).map(by_size),
+            shapes,
+            layers,
+            metadata_keys: metadata.len(),
+            largest_value,
+        }
+    }
+
     pub fn flatten_tree(tree: &[TreeNode]) -> Vec<(TreeNode, usize)> {
         let mut flattened = Vec::new();
         for node in tree {
@@ -227,6 +859,316 @@ impl TreeBuilder {
         }
     }
 
+    /// Re-sort the tree by `order`, except the children of the groups whose name paths
+    /// `overrides` gives an order of their own. The name order keeps the order the tree was
+    /// built in; the other orders keep metadata ahead of tensors and break ties by name.
+    pub fn apply_sort(
+        nodes: &mut [TreeNode],
+        order: SortOrder,
+        overrides: &HashMap<Vec<String>, SortOrder>,
+    ) {
+        let level = (order != SortOrder::Name).then_some(order);
+        Self::sort_level(nodes, level, order, &mut Vec::new(), overrides);
+    }
+
+    fn sort_level(
+        nodes: &mut [TreeNode],
+        level: Option<SortOrder>,
+        order: SortOrder,
+        path: &mut Vec<String>,
+        overrides: &HashMap<Vec<String>, SortOrder>,
+    ) {
+        if let Some(level) = level {
+            Self::sort_nodes(nodes, level);
+        }
+        for node in nodes {
+            if let TreeNode::Group {
+                name,
+                children,
+                sort,
+                ..
+            } = node
+            {
+                path.push(name.clone());
+                *sort = overrides.get(path).copied();
+                let level = sort.or((order != SortOrder::Name).then_some(order));
+                Self::sort_level(children, level, order, path, overrides);
+                path.pop();
+            }
+        }
+    }
+
+    fn sort_nodes(nodes: &mut [TreeNode], order: SortOrder) {
+        nodes.sort_by_cached_key(|node| {
+            let weight = match (order, node) {
+                (SortOrder::Name, _)
+                | (
+                    _,
+                    TreeNode::Group {
+                        tensor_count: 0, ..
+                    }
+                    | TreeNode::Metadata { .. }
+                    | TreeNode::Failed { .. },
+                ) => None,
+                (SortOrder::Size, TreeNode::Group { total_size, .. }) => Some(*total_size),
+                (SortOrder::Size, TreeNode::Tensor { info }) => Some(info.size_bytes),
+                (SortOrder::Params, node) => Some(
+                    node.tensors()
+                        .into_iter()
+                        .map(crate::quant::logical_parameters)
+                        .sum(),
+                ),
+            };
+            (
+                weight.is_some(),
+                std::cmp::Reverse(weight.unwrap_or(0)),
+                natural_sort_key(node.name()),
+            )
+        });
+    }
+
+    /// Remove tensors smaller than `min_size` and any group left empty; the remaining
+    /// groups keep counting the removed tensors in `tensor_count` and `total_size`
+    pub fn prune_small(nodes: &mut Vec<TreeNode>, min_size: usize) {
+        nodes.retain_mut(|node| match node {
+            TreeNode::Tensor { info } => info.size_bytes >= min_size,
+            TreeNode::Group { children, .. } => {
+                Self::prune_small(children, min_size);
+                !children.is_empty()
+            }
+            TreeNode::Metadata { .. } | TreeNode::Failed { .. } => true,
+        });
+    }
+
+    /// Expand every group on the path to the named tensor; false when it isn't in the tree
+    pub fn reveal_tensor(nodes: &mut [TreeNode], tensor_name: &str) -> bool {
+        nodes.iter_mut().any(|node| match node {
+            TreeNode::Tensor { info } => info.name == tensor_name,
+            TreeNode::Group {
+                children, expanded, ..
+            } => {
+                let found = Self::reveal_tensor(children, tensor_name);
+                *expanded |= found;
+                found
+            }
+            TreeNode::Metadata { .. } | TreeNode::Failed { .. } => false,
+        })
+    }
+
+    /// Expand the groups above the group at `path` (names from the root); false when it isn't
+    /// in the tree
+    pub fn reveal_group(nodes: &mut [TreeNode], path: &[String]) -> bool {
+        let Some((first, rest)) = path.split_first() else {
+            return false;
+        };
+        nodes.iter_mut().any(|node| match node {
+            TreeNode::Group {
+                name,
+                children,
+                expanded,
+                ..
+            } if name == first => {
+                if rest.is_empty() {
+                    return true;
+                }
+                let found = Self::reveal_group(children, rest);
+                *expanded |= found;
+                found
+            }
+            _ => false,
+        })
+    }
+
+    /// Expand every group on `path` (names from the root) so the node at its end is shown;
+    /// false when it isn't in the tree
+    pub fn reveal_path(nodes: &mut [TreeNode], path: &[String]) -> bool {
+        let Some((first, rest)) = path.split_first() else {
+            return false;
+        };
+        nodes.iter_mut().any(|node| {
+            if node.name() != first {
+                return false;
+            }
+            match node {
+                _ if rest.is_empty() => true,
+                TreeNode::Group {
+                    children, expanded, ..
+                } => {
+                    let found = Self::reveal_path(children, rest);
+                    *expanded |= found;
+                    found
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /// Every node with its name path, in tree order, including those in collapsed groups
+    pub fn all_paths(nodes: &[TreeNode]) -> Vec<(Vec<String>, &TreeNode)> {
+        let mut paths = Vec::new();
+        Self::collect_paths(nodes, &mut Vec::new(), &mut paths);
+        paths
+    }
+
+    fn collect_paths<'a>(
+        nodes: &'a [TreeNode],
+        path: &mut Vec<String>,
+        paths: &mut Vec<(Vec<String>, &'a TreeNode)>,
+    ) {
+        for node in nodes {
+            path.push(node.name().to_string());
+            paths.push((path.clone(), node));
+            if let TreeNode::Group { children, .. } = node {
+                Self::collect_paths(children, path, paths);
+            }
+            path.pop();
+        }
+    }
+
+    /// Name paths of the expanded groups, so expansion survives rebuilding the tree
+    pub fn expanded_paths(nodes: &[TreeNode]) -> HashSet<Vec<String>> {
+        let mut paths = HashSet::new();
+        Self::collect_expanded(nodes, &mut Vec::new(), &mut paths);
+        paths
+    }
+
+    fn collect_expanded(
+        nodes: &[TreeNode],
+        path: &mut Vec<String>,
+        paths: &mut HashSet<Vec<String>>,
+    ) {
+        for node in nodes {
+            if let TreeNode::Group {
+                name,
+                children,
+                expanded,
+                ..
+            } = node
+            {
+                path.push(name.clone());
+                if *expanded {
+                    paths.insert(path.clone());
+                }
+                Self::collect_expanded(children, path, paths);
+                path.pop();
+            }
+        }
+    }
+
+    /// Expand exactly the groups whose name paths are in `paths`
+    pub fn restore_expanded(nodes: &mut [TreeNode], paths: &HashSet<Vec<String>>) {
+        Self::apply_expanded(nodes, &mut Vec::new(), paths);
+    }
+
+    fn apply_expanded(
+        nodes: &mut [TreeNode],
+        path: &mut Vec<String>,
+        paths: &HashSet<Vec<String>>,
+    ) {
+        for node in nodes {
+            if let TreeNode::Group {
+                name,
+                children,
+                expanded,
+                ..
+            } = node
+            {
+                path.push(name.clone());
+                *expanded = paths.contains(path);
+                Self::apply_expanded(children, path, paths);
+                path.pop();
+            }
+        }
+    }
+
+    /// Show `levels` levels of groups: groups less than `levels - 1` deep are expanded and
+    /// all deeper ones collapsed, so `0` and `1` both leave only the top level
+    pub fn set_depth(nodes: &mut [TreeNode], levels: usize) {
+        for node in nodes {
+            if let TreeNode::Group {
+                children, expanded, ..
+            } = node
+            {
+                *expanded = levels > 1;
+                Self::set_depth(children, levels.saturating_sub(1));
+            }
+        }
+    }
+
+    /// Name path of the row at `idx` of a flattened tree, rebuilt from the row depths
+    pub fn node_path(flattened: &[(TreeNode, usize)], idx: usize) -> Vec<String> {
+        let mut path = Vec::new();
+        for (node, depth) in flattened.iter().take(idx + 1) {
+            path.truncate(*depth);
+            path.push(node.name().to_string());
+        }
+        path
+    }
+
+    /// Row of a flattened tree with the given name path
+    pub fn find_path(flattened: &[(TreeNode, usize)], target: &[String]) -> Option<usize> {
+        let mut path: Vec<&str> = Vec::new();
+        flattened.iter().position(|(node, depth)| {
+            path.truncate(*depth);
+            path.push(node.name());
+            path.iter().copied().eq(target.iter().map(String::as_str))
+        })
+    }
+
+    /// A JSON document as a tree under one expanded root named `name`: objects and arrays
+    /// become groups (array elements named `[0]`, `[1]`, ...) and everything else, empty
+    /// objects and arrays included, a metadata leaf holding its JSON text
+    pub fn build_json_tree(name: &str, value: &serde_json::Value) -> Vec<TreeNode> {
+        let mut root = Self::json_node(name.to_string(), value);
+        if let TreeNode::Group { expanded, .. } = &mut root {
+            *expanded = true;
+        }
+        vec![root]
+    }
+
+    fn json_node(name: String, value: &serde_json::Value) -> TreeNode {
+        let children: Vec<TreeNode> = match value {
+            serde_json::Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| Self::json_node(key.clone(), value))
+                .collect(),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, value)| Self::json_node(format!("[{i}]"), value))
+                .collect(),
+            _ => Vec::new(),
+        };
+        if children.is_empty() {
+            let value_type = match value {
+                serde_json::Value::Null => "null",
+                serde_json::Value::Bool(_) => "bool",
+                serde_json::Value::Number(_) => "number",
+                serde_json::Value::String(_) => "string",
+                serde_json::Value::Array(_) => "array",
+                serde_json::Value::Object(_) => "object",
+            };
+            return TreeNode::Metadata {
+                info: MetadataInfo {
+                    name,
+                    value: value.to_string(),
+                    value_type: value_type.to_string(),
+                    raw_value: None,
+                    source: None,
+                },
+            };
+        }
+        TreeNode::Group {
+            name,
+            children,
+            expanded: false,
+            tensor_count: 0,
+            total_size: 0,
+            stats: Default::default(),
+            sort: None,
+        }
+    }
+
     pub fn toggle_node_by_index(target_idx: usize, nodes: &mut [TreeNode]) -> bool {
         let mut current_idx = 0;
         Self::toggle_node_by_index_recursive(target_idx, nodes, &mut current_idx)
@@ -263,3 +1205,25 @@ impl TreeBuilder {
         false
     }
 }
+
+/// The part of a JSON document at a name path of [`TreeBuilder::build_json_tree`], whose
+/// first element names the root
+pub fn json_at_path<'a>(
+    value: &'a serde_json::Value,
+    path: &[String],
+) -> Option<&'a serde_json::Value> {
+    path.iter()
+        .skip(1)
+        .try_fold(value, |value, name| match value {
+            serde_json::Value::Array(items) => {
+                let index = name
+                    .strip_prefix('[')?
+                    .strip_suffix(']')?
+                    .parse::<usize>()
+                    .ok()?;
+                items.get(index)
+            }
+            serde_json::Value::Object(map) => map.get(name),
+            _ => None,
+        })
+}
@@ -0,0 +1,258 @@
+//! Squarified treemap of one level of the tensor tree, blocks sized by bytes, for showing
+//! what a model is made of at a glance. Blocks too small to read on a terminal collapse into
+//! one "other" block, which opens as a level of its own.
+
+use safetensors_explorer::tree::{DtypeShare, TreeNode, dtype_composition};
+
+use crate::watch;
+
+/// Blocks given fewer terminal cells than this go into the "other" block
+pub const MIN_BLOCK_CELLS: f64 = 24.0;
+
+/// Terminal cells are about twice as tall as wide; layouts are computed in square units
+const CELL_ASPECT: f64 = 2.0;
+
+/// Cells of a block on screen, relative to the top left of the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    fn center(&self) -> (f64, f64) {
+        (
+            self.x as f64 + self.width as f64 / 2.0,
+            (self.y as f64 + self.height as f64 / 2.0) * CELL_ASPECT,
+        )
+    }
+}
+
+/// A level of the map: the nodes it lays out and the block selected
+#[derive(Debug)]
+pub struct Level<'a> {
+    /// Breadcrumb entry: the group's name, or "other"
+    pub title: String,
+    pub nodes: Vec<&'a TreeNode>,
+    /// Name of the group the nodes are in, for shortening tensor names
+    pub parent: Option<&'a str>,
+    pub selected: usize,
+}
+
+/// One block of the map: a group or tensor, or the nodes collapsed into "other"
+#[derive(Debug)]
+pub struct Block<'a> {
+    pub nodes: Vec<&'a TreeNode>,
+    pub bytes: usize,
+    pub tensors: usize,
+    /// Bytes per dtype under the block, largest first
+    pub dtypes: Vec<DtypeShare>,
+    pub rect: Rect,
+}
+
+impl Block<'_> {
+    pub fn is_other(&self) -> bool {
+        self.nodes.len() > 1
+    }
+
+    /// Dtype holding most of the block's bytes, which picks its color; None for "other"
+    pub fn dtype(&self) -> Option<&str> {
+        if self.is_other() {
+            return None;
+        }
+        self.dtypes.first().map(|share| share.dtype.as_str())
+    }
+
+    /// The node's name, shortened for tensors under a group named after their prefix as in
+    /// the tree; `other (12)` for collapsed nodes
+    pub fn label(&self, parent: Option<&str>) -> String {
+        match self.nodes.as_slice() {
+            [TreeNode::Tensor { info }] => match (parent, info.name.rsplit_once('.')) {
+                (Some(group), Some((prefix, last)))
+                    if prefix == group || prefix.ends_with(&format!(".{group}")) =>
+                {
+                    last.to_string()
+                }
+                _ => info.name.clone(),
+            },
+            [node] => node.name().to_string(),
+            nodes => format!("other ({})", nodes.len()),
+        }
+    }
+}
+
+fn node_bytes(node: &TreeNode) -> usize {
+    match node {
+        TreeNode::Group { total_size, .. } => *total_size,
+        TreeNode::Tensor { info } => info.size_bytes,
+        _ => 0,
+    }
+}
+
+/// Groups and tensors of a tree level that hold data, largest first; the tensors the last
+/// reload removed aren't part of the model any more
+pub fn level_nodes(nodes: &[TreeNode]) -> Vec<&TreeNode> {
+    let mut level: Vec<&TreeNode> = nodes
+        .iter()
+        .filter(|node| node_bytes(node) > 0)
+        .filter(
+            |node| !matches!(node, TreeNode::Group { name, .. } if name == watch::REMOVED_GROUP),
+        )
+        .collect();
+    level.sort_by_key(|node| std::cmp::Reverse(node_bytes(node)));
+    level
+}
+
+/// Worst aspect ratio of a row of areas laid along a side of length `side`
+fn worst_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let (min, max) = row
+        .iter()
+        .fold((f64::MAX, 0f64), |(min, max), &a| (min.min(a), max.max(a)));
+    let side = side * side;
+    (side * max / (sum * sum)).max(sum * sum / (side * min))
+}
+
+/// Rectangles (x, y, width, height) for `areas`, which sum to `width * height`, in the order
+/// given (Bruls, Huizing and van Wijk's squarified layout; squarest for largest first)
+fn squarify(areas: &[f64], width: f64, height: f64) -> Vec<(f64, f64, f64, f64)> {
+    let mut rects = Vec::with_capacity(areas.len());
+    let (mut x, mut y, mut width, mut height) = (0.0, 0.0, width, height);
+    let mut rest = areas;
+    while !rest.is_empty() {
+        let side = width.min(height);
+        let mut count = 1;
+        while count < rest.len()
+            && worst_ratio(&rest[..=count], side) <= worst_ratio(&rest[..count], side)
+        {
+            count += 1;
+        }
+        let (row, remaining) = rest.split_at(count);
+        let sum: f64 = row.iter().sum();
+        if width >= height {
+            // A column along the left edge
+            let column = if height > 0.0 { sum / height } else { 0.0 };
+            let mut top = y;
+            for area in row {
+                let h = if column > 0.0 { area / column } else { 0.0 };
+                rects.push((x, top, column, h));
+                top += h;
+            }
+            x += column;
+            width -= column;
+        } else {
+            // A row along the top edge
+            let band = if width > 0.0 { sum / width } else { 0.0 };
+            let mut left = x;
+            for area in row {
+                let w = if band > 0.0 { area / band } else { 0.0 };
+                rects.push((left, y, w, band));
+                left += w;
+            }
+            y += band;
+            height -= band;
+        }
+        rest = remaining;
+    }
+    rects
+}
+
+/// Lay out `nodes` (largest first, as [`level_nodes`] returns them) on a map of `width` by
+/// `height` cells, largest block first. Nodes that would get fewer than [`MIN_BLOCK_CELLS`]
+/// cells share one "other" block, drawn at that size at least.
+pub fn layout<'a>(nodes: &[&'a TreeNode], width: u16, height: u16) -> Vec<Block<'a>> {
+    let total: usize = nodes.iter().map(|node| node_bytes(node)).sum();
+    if total == 0 || width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let cells = width as f64 * height as f64;
+    let fitting = nodes
+        .iter()
+        .take_while(|node| node_bytes(node) as f64 / total as f64 * cells >= MIN_BLOCK_CELLS)
+        .count();
+    // When no node is large enough, as in a flat list of thousands of equal tensors, the
+    // largest get half the map at the minimum size and "other" pages through the rest
+    let mut shown = if fitting > 0 {
+        fitting
+    } else {
+        ((cells / MIN_BLOCK_CELLS) as usize / 2).clamp(1, nodes.len())
+    };
+    // "other" standing for a single node would just hide its name
+    if nodes.len() == shown + 1 {
+        shown += 1;
+    }
+
+    let block = |nodes: Vec<&'a TreeNode>| {
+        let tensors: Vec<_> = nodes.iter().flat_map(|node| node.tensors()).collect();
+        Block {
+            bytes: nodes.iter().map(|node| node_bytes(node)).sum(),
+            tensors: tensors.len(),
+            dtypes: dtype_composition(tensors),
+            nodes,
+            rect: Rect::default(),
+        }
+    };
+    let mut blocks: Vec<Block> = nodes[..shown]
+        .iter()
+        .map(|node| block(vec![node]))
+        .collect();
+    if shown < nodes.len() {
+        blocks.push(block(nodes[shown..].to_vec()));
+    }
+
+    // Every block keeps the minimum area, so the last one stays visible however small; the
+    // others give up the difference in proportion
+    let (map_width, map_height) = (width as f64, height as f64 * CELL_ASPECT);
+    let minimum = MIN_BLOCK_CELLS * CELL_ASPECT;
+    let areas: Vec<f64> = blocks
+        .iter()
+        .map(|block| (block.bytes as f64 / total as f64 * map_width * map_height).max(minimum))
+        .collect();
+    let scale = map_width * map_height / areas.iter().sum::<f64>();
+    let mut order: Vec<(f64, Block)> = areas
+        .into_iter()
+        .map(|area| area * scale)
+        .zip(blocks)
+        .collect();
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let (areas, mut blocks): (Vec<f64>, Vec<Block>) = order.into_iter().unzip();
+    // Rounding both edges of every rectangle keeps neighbours flush, without gaps or overlap
+    for (block, (x, y, w, h)) in blocks
+        .iter_mut()
+        .zip(squarify(&areas, map_width, map_height))
+    {
+        let (y, h) = (y / CELL_ASPECT, h / CELL_ASPECT);
+        let (left, top) = (x.round() as u16, y.round() as u16);
+        let (right, bottom) = (
+            ((x + w).round() as u16).min(width),
+            ((y + h).round() as u16).min(height),
+        );
+        block.rect = Rect {
+            x: left,
+            y: top,
+            width: right.saturating_sub(left),
+            height: bottom.saturating_sub(top),
+        };
+    }
+    blocks
+}
+
+/// The block to select when moving from `from` in direction (`dx`, `dy`): the nearest one
+/// whose center lies that way, preferring blocks in line with it
+pub fn neighbor(blocks: &[Block], from: usize, dx: i32, dy: i32) -> Option<usize> {
+    let (fx, fy) = blocks.get(from)?.rect.center();
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(index, block)| *index != from && block.rect.width > 0 && block.rect.height > 0)
+        .filter_map(|(index, block)| {
+            let (cx, cy) = block.rect.center();
+            let along = (cx - fx) * dx as f64 + (cy - fy) * dy as f64;
+            let across = (cx - fx) * dy as f64 + (cy - fy) * dx as f64;
+            (along > 0.0).then_some((index, along + 2.0 * across.abs()))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
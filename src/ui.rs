@@ -1,24 +1,628 @@
 use anyhow::Result;
 use crossterm::{
     cursor, execute,
-    style::{Color, ResetColor, SetForegroundColor},
+    style::{Attribute, Color, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
     terminal::{self, ClearType},
 };
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
 
-use crate::tree::{MetadataInfo, TensorInfo, TreeNode};
-use crate::utils::{format_parameters, format_shape, format_size};
+use crate::browser::BrowserEntry;
+use crate::check::{Finding, Severity};
+use crate::dtype;
+use crate::filter::{FilterEntry, TypeFilter};
+use crate::gguf::{GGMLType, GGUFValue, MergeStats, merge_pieces, token_type_name};
+use crate::keymap::Keymap;
+use crate::model::FileRecord;
+use crate::offsets::{OffsetMap, RegionKind};
+use crate::raw_header::{JsonLine, JsonToken};
+use crate::recent::RecentEntry;
+use crate::role::classify;
+use crate::scan::{Histogram, SAMPLE_CHUNK_SIZE, ScanStatus, SparsityStats};
+use crate::summary;
+use crate::tree::{
+    GroupBy, LoadFailure, MetadataInfo, TensorInfo, TreeBuilder, TreeNode, ValueMatch,
+    composition_label, dtype_composition,
+};
+use crate::treemap::{Block, Level};
+use crate::utils::{
+    display_file_name, display_parameters, display_size, display_width, file_format, fit_to_width,
+    format_duration, format_parameters, format_shape, format_size, format_thousands,
+    format_timestamp, model_extension, progress_bar, truncate_start_to_width, truncate_to_width,
+    wrap_to_width,
+};
+use crate::watch::{self, Change, ReloadChanges};
+
+/// Dtype and category checkboxes next to the tree
+pub struct FilterPane<'a> {
+    pub entries: &'a [(FilterEntry, usize)],
+    pub filter: &'a TypeFilter,
+    pub selected: usize,
+    /// Keys go to the pane rather than the tree
+    pub focused: bool,
+}
+
+/// Columns the filter pane takes, border included
+const FILTER_PANE_WIDTH: usize = 32;
 
 pub struct DrawConfig<'a> {
     pub tree: &'a [(TreeNode, usize)],
     pub current_file: &'a str,
-    pub file_idx: usize,
+    /// Dotted path of the group holding the selected row, or the file a top-level row is from
+    pub context: &'a str,
+    /// Files loaded into the session
     pub total_files: usize,
     pub total_parameters: usize,
+    /// Embedding parameters to leave out of the footer count; None counts everything
+    pub embedding_parameters: Option<usize>,
     pub selected_idx: usize,
     pub scroll_offset: usize,
     pub search_mode: bool,
     pub search_query: &'a str,
+    /// The search skips metadata values
+    pub search_names_only: bool,
+    /// Per row, where the search query was found in a metadata value; empty outside search
+    pub value_matches: &'a [Option<ValueMatch>],
+    /// The search jumps between matches in the tree instead of filtering it
+    pub search_jump: bool,
+    /// Names of the tensors and metadata entries matched by a jump search, highlighted
+    pub jump_matches: &'a HashSet<String>,
+    /// Match selected with `n`/`N`, and how many there are
+    pub jump_index: Option<usize>,
+    pub jump_count: usize,
+    /// Cached sparsity stats, present when the sparsity column is enabled
+    pub sparsity: Option<&'a HashMap<String, SparsityStats>>,
+    /// Duplicate group id per tensor name, from the last duplicate scan
+    pub duplicates: &'a HashMap<String, usize>,
+    /// Names of the tensors pinned for comparison, in panel order
+    pub pinned: &'a [String],
+    /// What the last reload changed, marked and colored in the tree
+    pub changes: &'a ReloadChanges,
+    /// Tensors show their full name when grouped by dtype or shape
+    pub group_by: GroupBy,
+    /// Show exact byte and parameter counts instead of rounded ones
+    pub exact: bool,
+    /// Show GGUF shapes in storage (ne) order instead of logical order
+    pub storage_order: bool,
+    /// Size floor in bytes (0 when off) and how many tensors it hides
+    pub min_size: usize,
+    pub hidden_tensors: usize,
+    /// Files that failed to parse and are shown as error rows
+    pub failed_files: usize,
+    /// Files left unread by a cancelled load
+    pub not_loaded_files: usize,
+    /// Files whose tensor data runs past their end, e.g. downloads cut short
+    pub incomplete_files: usize,
+    /// Model subdirectories whose totals the footer adds up
+    pub components: usize,
+    /// Parameters of the base model and of each companion loaded with it
+    pub component_parameters: &'a [(String, usize)],
+    /// Name of the file the tree is limited to, from the files panel
+    pub file_filter: Option<String>,
+    /// Files left out of the totals; their rows are greyed out
+    pub excluded: &'a [PathBuf],
+    /// Files counted, files loaded and bytes counted, while some are excluded
+    pub counted_files: Option<(usize, usize, usize)>,
+    /// Rounded parameter count (`≈ 7.6B`), with a warning when the size label disagrees
+    pub size_label: Option<&'a str>,
+    /// Checked entries of the filter pane, summarized in the header
+    pub type_filter: Option<String>,
+    /// The filter pane, drawn to the right of the tree while open
+    pub filter_pane: Option<FilterPane<'a>>,
+    /// Notification shown in place of the footer stats (e.g. after an export)
+    pub status: Option<&'a Status>,
+    /// Plain ASCII markers instead of emoji icons
+    pub ascii: bool,
+    pub theme: Theme,
+    /// Current key bindings, for the hint line
+    pub keys: &'a Keymap,
+}
+
+/// Colors for the selection highlight and status messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Black on white selection, bright status colors
+    #[default]
+    Dark,
+    /// White on blue selection, darker status colors for light backgrounds
+    Light,
+    /// No colors: reverse video for the selection
+    Mono,
+}
+
+impl Theme {
+    fn highlight(self, stdout: &mut io::Stdout) -> Result<()> {
+        match self {
+            Theme::Dark => execute!(
+                stdout,
+                SetForegroundColor(Color::Black),
+                SetBackgroundColor(Color::White)
+            )?,
+            Theme::Light => execute!(
+                stdout,
+                SetForegroundColor(Color::White),
+                SetBackgroundColor(Color::DarkBlue)
+            )?,
+            Theme::Mono => execute!(stdout, SetAttribute(Attribute::Reverse))?,
+        }
+        Ok(())
+    }
+
+    /// Draw attention to a value, e.g. a field that differs between pinned tensors
+    fn emphasis(self, stdout: &mut io::Stdout) -> Result<()> {
+        match StatusLevel::Warning.color(self) {
+            Some(color) => execute!(stdout, SetForegroundColor(color))?,
+            None => execute!(stdout, SetAttribute(Attribute::Bold))?,
+        }
+        Ok(())
+    }
+
+    /// Fade a row that is shown but not counted
+    fn dim(self, stdout: &mut io::Stdout) -> Result<()> {
+        match self {
+            Theme::Dark => execute!(stdout, SetForegroundColor(Color::DarkGrey))?,
+            Theme::Light => execute!(stdout, SetForegroundColor(Color::Grey))?,
+            Theme::Mono => execute!(stdout, SetAttribute(Attribute::Dim))?,
+        }
+        Ok(())
+    }
+
+    /// Color a status message by its level
+    fn status(self, level: StatusLevel, stdout: &mut io::Stdout) -> Result<()> {
+        if let Some(color) = level.color(self) {
+            execute!(stdout, SetForegroundColor(color))?;
+        }
+        Ok(())
+    }
+
+    /// Mark the part of a metadata value a search matched, until [`Theme::end_match`]
+    fn search_match(self, stdout: &mut io::Stdout) -> Result<()> {
+        execute!(
+            stdout,
+            SetAttribute(Attribute::Bold),
+            SetAttribute(Attribute::Underlined)
+        )?;
+        Ok(())
+    }
+
+    fn end_match(self, stdout: &mut io::Stdout) -> Result<()> {
+        execute!(
+            stdout,
+            SetAttribute(Attribute::NormalIntensity),
+            SetAttribute(Attribute::NoUnderline)
+        )?;
+        Ok(())
+    }
+
+    /// Color a piece of pretty-printed JSON by its kind; Mono only sets keys in bold
+    fn json(self, token: JsonToken, stdout: &mut io::Stdout) -> Result<()> {
+        let color = match (self, token) {
+            (Theme::Mono, JsonToken::Key) => {
+                execute!(stdout, SetAttribute(Attribute::Bold))?;
+                return Ok(());
+            }
+            (Theme::Mono, _) | (_, JsonToken::Punctuation | JsonToken::Space) => return Ok(()),
+            (_, JsonToken::Key) => StatusLevel::Info.color(self),
+            (_, JsonToken::String) => StatusLevel::Success.color(self),
+            (_, JsonToken::Number) => StatusLevel::Warning.color(self),
+            (Theme::Dark, JsonToken::Literal) => Some(Color::Magenta),
+            (Theme::Light, JsonToken::Literal) => Some(Color::DarkMagenta),
+        };
+        if let Some(color) = color {
+            execute!(stdout, SetForegroundColor(color))?;
+        }
+        Ok(())
+    }
+
+    /// Color of a dtype in the treemap, by how many bits it spends per element: blue for
+    /// 32 and up, cyan for 16, green for 8, yellow for 5 and 6, magenta for 4, red below;
+    /// grey for integer-packed and unknown types
+    fn dtype_color(self, dtype: &str) -> Option<Color> {
+        if self == Theme::Mono {
+            return None;
+        }
+        let name = dtype.strip_suffix("_BE").unwrap_or(dtype);
+        let bits = match (dtype::dtype_info(name), GGMLType::from_name(name)) {
+            (Some(info), _) => Some(info.bits as f64),
+            (None, Some(ty)) => ty
+                .block_layout()
+                .map(|(elements, bytes)| bytes as f64 * 8.0 / elements as f64),
+            (None, None) => None,
+        };
+        Some(match bits {
+            Some(bits) if bits >= 32.0 => Color::Blue,
+            Some(bits) if bits >= 16.0 => Color::Cyan,
+            Some(bits) if bits >= 8.0 => Color::Green,
+            Some(bits) if bits >= 5.0 => Color::Yellow,
+            Some(bits) if bits >= 4.0 => Color::Magenta,
+            Some(_) => Color::Red,
+            None => Color::DarkGrey,
+        })
+    }
+
+    /// Fill of a treemap block: its dtype's color, grey for "other", the selection highlight
+    /// when selected. Mono reverses every block but the selected one.
+    fn treemap_block(
+        self,
+        dtype: Option<&str>,
+        selected: bool,
+        stdout: &mut io::Stdout,
+    ) -> Result<()> {
+        match (self, selected) {
+            (Theme::Mono, true) => execute!(stdout, SetAttribute(Attribute::Bold))?,
+            (Theme::Mono, false) => execute!(stdout, SetAttribute(Attribute::Reverse))?,
+            (_, true) => self.highlight(stdout)?,
+            (_, false) => {
+                let color = dtype
+                    .and_then(|dtype| self.dtype_color(dtype))
+                    .unwrap_or(Color::DarkGrey);
+                let text = match color {
+                    Color::Blue | Color::DarkGrey | Color::Red | Color::Magenta => Color::White,
+                    _ => Color::Black,
+                };
+                execute!(stdout, SetForegroundColor(text), SetBackgroundColor(color))?
+            }
+        }
+        Ok(())
+    }
+
+    fn reset(stdout: &mut io::Stdout) -> Result<()> {
+        execute!(stdout, ResetColor, SetAttribute(Attribute::Reset))?;
+        Ok(())
+    }
+}
+
+/// Markers drawn in front of tree rows. The emoji set sticks to characters that measure
+/// the same in every terminal: two columns for the pictographs, one for the arrows (`▼` and
+/// `▶` are two columns wide in CJK locales, and `🏷️` needs a variation selector).
+struct Icons {
+    expanded: &'static str,
+    collapsed: &'static str,
+    group: &'static str,
+    tensor: &'static str,
+    metadata: &'static str,
+    failed: &'static str,
+    pinned: &'static str,
+}
+
+const EMOJI_ICONS: Icons = Icons {
+    expanded: "▾",
+    collapsed: "▸",
+    group: "📁 ",
+    tensor: "📄 ",
+    metadata: "🔖 ",
+    failed: "❌ ",
+    pinned: "📌",
+};
+
+const ASCII_ICONS: Icons = Icons {
+    expanded: "v",
+    collapsed: ">",
+    group: "",
+    tensor: "- ",
+    metadata: "# ",
+    failed: "! ",
+    pinned: "pin",
+};
+
+/// How long a non-error status stays up when no key is pressed
+const STATUS_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Severity of a status message, which picks its color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl StatusLevel {
+    fn color(self, theme: Theme) -> Option<Color> {
+        match (theme, self) {
+            (Theme::Mono, _) => None,
+            (Theme::Dark, StatusLevel::Info) => Some(Color::Cyan),
+            (Theme::Dark, StatusLevel::Success) => Some(Color::Green),
+            (Theme::Dark, StatusLevel::Warning) => Some(Color::Yellow),
+            (Theme::Dark, StatusLevel::Error) => Some(Color::Red),
+            (Theme::Light, StatusLevel::Info) => Some(Color::DarkCyan),
+            (Theme::Light, StatusLevel::Success) => Some(Color::DarkGreen),
+            (Theme::Light, StatusLevel::Warning) => Some(Color::DarkYellow),
+            (Theme::Light, StatusLevel::Error) => Some(Color::DarkRed),
+        }
+    }
+}
+
+/// A transient notification; cleared by the next key press, or by the timeout unless it is an error
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub level: StatusLevel,
+    pub message: String,
+    shown_at: Instant,
+}
+
+impl Status {
+    pub fn new(level: StatusLevel, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            // Multi-line errors are folded onto the single footer row
+            message: message.into().replace('\n', " "),
+            shown_at: Instant::now(),
+        }
+    }
+
+    /// Time left before the message clears itself; None for errors, which wait for a key
+    pub fn remaining(&self) -> Option<Duration> {
+        (self.level != StatusLevel::Error)
+            .then(|| STATUS_TIMEOUT.saturating_sub(self.shown_at.elapsed()))
+    }
+}
+
+/// Lines under the findings list for the selected finding's full message
+const REPORT_DETAIL_LINES: usize = 3;
+
+/// Scrollable list of findings from an analysis (layer audit, NaN scan, ...), one row each,
+/// colored by severity. Analyses only produce [`Finding`]s; the explorer moves the selection,
+/// jumps to the related tensor on Enter and exports the list on `e`.
+pub struct ReportView {
+    pub title: String,
+    /// What was analyzed, shown above the findings
+    pub intro: Vec<String>,
+    pub findings: Vec<Finding>,
+    pub selected: usize,
+    scroll_offset: usize,
+    /// Outcome of the last export, shown above the footer
+    pub note: Option<String>,
+}
+
+impl ReportView {
+    pub fn new(title: impl Into<String>, intro: Vec<String>, findings: Vec<Finding>) -> Self {
+        ReportView {
+            title: title.into(),
+            intro,
+            findings,
+            selected: 0,
+            scroll_offset: 0,
+            note: None,
+        }
+    }
+
+    pub fn selected_finding(&self) -> Option<&Finding> {
+        self.findings.get(self.selected)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.findings.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// `2 errors, 1 warning, 4 ok`, most severe first, leaving out severities with none
+    pub fn severity_counts(&self) -> String {
+        let parts: Vec<String> = [Severity::Error, Severity::Warning, Severity::Info]
+            .into_iter()
+            .filter_map(|severity| {
+                let count = self
+                    .findings
+                    .iter()
+                    .filter(|f| f.severity == severity)
+                    .count();
+                (count > 0).then(|| match severity {
+                    Severity::Info => format!("{count} ok"),
+                    _ => format!(
+                        "{count} {}{}",
+                        severity.label(),
+                        if count == 1 { "" } else { "s" }
+                    ),
+                })
+            })
+            .collect();
+        if parts.is_empty() {
+            "no findings".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    pub fn draw(&mut self, theme: Theme) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = (terminal_width as usize).saturating_sub(1);
+        let intro_height = self.intro.len() + usize::from(!self.intro.is_empty());
+        let available_height = (terminal_height as usize)
+            .saturating_sub(4 + REPORT_DETAIL_LINES + intro_height)
+            .max(1);
+        if self.selected >= self.scroll_offset + available_height {
+            self.scroll_offset = self.selected + 1 - available_height;
+        } else {
+            self.scroll_offset = self.scroll_offset.min(self.selected);
+        }
+
+        writeln!(stdout, "{}\r", truncate_to_width(&self.title, width))?;
+        writeln!(
+            stdout,
+            "{}\r",
+            "=".repeat(display_width(&self.title).min(width))
+        )?;
+        for line in &self.intro {
+            writeln!(stdout, "{}\r", truncate_to_width(line, width))?;
+        }
+        if !self.intro.is_empty() {
+            writeln!(stdout, "\r")?;
+        }
+        if self.findings.is_empty() {
+            writeln!(stdout, "Nothing found\r")?;
+        }
+        for (idx, finding) in self
+            .findings
+            .iter()
+            .enumerate()
+            .skip(self.scroll_offset)
+            .take(available_height)
+        {
+            let row = format!(
+                "{:<8}{}: {}",
+                finding.severity.label(),
+                finding.check,
+                finding.message
+            );
+            let row = truncate_to_width(&row, width);
+            if idx == self.selected {
+                theme.highlight(&mut stdout)?;
+            } else {
+                theme.status(severity_level(finding.severity), &mut stdout)?;
+            }
+            writeln!(stdout, "{row}\r")?;
+            Theme::reset(&mut stdout)?;
+        }
+
+        // The selected finding in full, since rows are cut at the terminal width
+        let detail = self.note.clone().or_else(|| {
+            self.selected_finding().map(|f| match &f.related_node_path {
+                Some(path) => format!("{} → {path}", f.message),
+                None => f.message.clone(),
+            })
+        });
+        if let Some(detail) = detail {
+            let lines = wrap_to_width(&detail, width);
+            let shown = lines.len().min(REPORT_DETAIL_LINES);
+            execute!(
+                stdout,
+                cursor::MoveTo(0, terminal_height.saturating_sub(1 + shown as u16))
+            )?;
+            for line in lines.iter().take(shown) {
+                writeln!(stdout, "{line}\r")?;
+            }
+        }
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        let hint = format!(
+            "{} | {}/{} | ↑/↓ to select, Enter to show the tensor, e to export JSON, q/Esc to return",
+            self.severity_counts(),
+            (self.selected + 1).min(self.findings.len()),
+            self.findings.len()
+        );
+        write!(stdout, "{}", truncate_to_width(&hint, width))?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Status color for a finding's severity: passed checks show as successes
+fn severity_level(severity: Severity) -> StatusLevel {
+    match severity {
+        Severity::Info => StatusLevel::Success,
+        Severity::Warning => StatusLevel::Warning,
+        Severity::Error => StatusLevel::Error,
+    }
+}
+
+/// Narrowest column of the side-by-side comparison before it switches to stacked blocks
+const MIN_COMPARE_COLUMN: usize = 24;
+
+/// One field of the comparison panel with its value for every pinned tensor
+struct CompareRow {
+    label: &'static str,
+    values: Vec<String>,
+    /// Set when the tensors disagree on a field known for all of them
+    differs: bool,
+}
+
+fn comparison_rows(tensors: &[(&TensorInfo, Option<&Histogram>)], exact: bool) -> Vec<CompareRow> {
+    const LABELS: [&str; 8] = [
+        "Name", "Dtype", "Shape", "Size", "Elements", "Min", "Max", "Mean",
+    ];
+    let columns: Vec<[String; 8]> = tensors
+        .iter()
+        .map(|&(tensor, histogram)| {
+            // Statistics estimated from a sample are marked with `~`
+            let stat = |value: f64| match histogram {
+                Some(h) if h.sampled_bytes.is_some() => format!("~{value:.6}"),
+                Some(_) => format!("{value:.6}"),
+                None => "-".to_string(),
+            };
+            let (min, max, mean) = histogram.map_or((0.0, 0.0, 0.0), |h| (h.min, h.max, h.mean));
+            [
+                tensor.name.clone(),
+                tensor.dtype.clone(),
+                format_shape(&tensor.shape),
+                display_size(tensor.size_bytes, exact),
+                display_parameters(tensor.num_elements, exact),
+                stat(min),
+                stat(max),
+                stat(mean),
+            ]
+        })
+        .collect();
+    LABELS
+        .iter()
+        .enumerate()
+        .map(|(field, &label)| {
+            let values: Vec<String> = columns.iter().map(|c| c[field].clone()).collect();
+            let differs = label != "Name"
+                && !values.iter().any(|v| v == "-")
+                && values.iter().any(|v| *v != values[0]);
+            CompareRow {
+                label,
+                values,
+                differs,
+            }
+        })
+        .collect()
+}
+
+/// Tensor rows are `name | dtype | shape | size`, with the size flush against the right edge
+const DTYPE_WIDTH: usize = 8;
+const MAX_SHAPE_WIDTH: usize = 24;
+/// Below this many columns for the name, the shape column is dropped
+const MIN_NAME_WIDTH: usize = 24;
+
+/// Column widths for one frame, derived from the terminal width and the rows in the tree
+struct RowLayout {
+    width: usize,
+    /// `None` when the terminal is too narrow for a shape column
+    shape_width: Option<usize>,
+    size_width: usize,
+}
+
+impl RowLayout {
+    fn new(config: &DrawConfig, terminal_width: usize) -> Self {
+        // Leave the last column empty so full-width rows never wrap
+        let width = terminal_width.saturating_sub(1);
+        let (mut shape_width, mut size_width) = (0, 0);
+        for (node, _) in config.tree {
+            if let TreeNode::Tensor { info } = node {
+                shape_width = shape_width.max(display_width(&format_shape(
+                    info.display_shape(config.storage_order),
+                )));
+                size_width =
+                    size_width.max(display_width(&display_size(info.size_bytes, config.exact)));
+            }
+        }
+        let shape_width = shape_width.min(MAX_SHAPE_WIDTH);
+        let with_shape = 1 + DTYPE_WIDTH + 1 + shape_width + 1 + size_width;
+        RowLayout {
+            width,
+            shape_width: (width >= MIN_NAME_WIDTH + with_shape).then_some(shape_width),
+            size_width,
+        }
+    }
+
+    /// Columns left for the name part of a tensor row
+    fn name_width(&self) -> usize {
+        let shape = self.shape_width.map_or(0, |w| w + 1);
+        self.width
+            .saturating_sub(1 + DTYPE_WIDTH + 1 + shape + self.size_width)
+    }
 }
 
 pub struct UI;
@@ -32,48 +636,84 @@ impl UI {
             cursor::MoveTo(0, 0)
         )?;
 
-        let (_, terminal_height) = terminal::size()?;
-        let header_height = 3;
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let full_width = (terminal_width as usize).saturating_sub(1);
+        let pane_width = match config.filter_pane {
+            Some(_) => FILTER_PANE_WIDTH.min(terminal_width as usize / 2),
+            None => 0,
+        };
+        let layout = RowLayout::new(config, terminal_width as usize - pane_width);
+        let header_height = 4;
         let footer_height = 2;
         let available_height =
             (terminal_height as usize).saturating_sub(header_height + footer_height);
 
         // Header
-        writeln!(
-            stdout,
-            "SafeTensors Explorer - {} ({}/{})\r",
+        let mut title = format!(
+            "SafeTensors Explorer - {} ({} file{})",
             config.current_file,
-            config.file_idx + 1,
-            config.total_files
-        )?;
+            config.total_files,
+            if config.total_files == 1 { "" } else { "s" }
+        );
+        if let Some(size) = config.size_label {
+            title.push_str(&format!(" | {size}"));
+        }
+        if let Some(filter) = &config.type_filter {
+            title.push_str(&format!(" | Filter: {filter}"));
+        }
+        writeln!(stdout, "{}\r", truncate_to_width(&title, full_width))?;
         if config.search_mode {
-            writeln!(
-                stdout,
-                "SEARCH MODE: {} | Type to search, Enter/Esc to exit search\r",
-                if config.search_query.is_empty() {
-                    "_"
-                } else {
-                    config.search_query
-                }
-            )?;
+            let (scope, scope_toggle) = if config.search_names_only {
+                ("names", "also search metadata values")
+            } else {
+                ("names and values", "search names only")
+            };
+            let (mode, mode_toggle) = if config.search_jump {
+                ("jump", "filter instead")
+            } else {
+                ("filter", "jump to matches instead")
+            };
+            let query = if config.search_query.is_empty() {
+                "_"
+            } else {
+                config.search_query
+            };
+            let line = format!(
+                "SEARCH MODE ({mode}, {scope}): {query} | Type to search, Tab: {scope_toggle}, Ctrl+F: {mode_toggle}, Enter/Esc to exit search"
+            );
+            writeln!(stdout, "{}\r", truncate_to_width(&line, full_width))?;
         } else {
+            let key = |action| config.keys.key_for(action).unwrap_or('-');
             writeln!(
                 stdout,
-                "Use ↑/↓ to navigate, Enter/Space to expand/collapse, / to search, q to quit\r"
+                "Use ↑/↓ to navigate, Enter/Space to expand/collapse, {} to search, {} for help, {} to quit\r",
+                key("search"),
+                key("help"),
+                key("quit")
             )?;
         }
         writeln!(stdout, "{}\r", "=".repeat(80))?;
+        // Keep the innermost path segments when the context doesn't fit
+        writeln!(
+            stdout,
+            "{}\r",
+            truncate_start_to_width(config.context, full_width)
+        )?;
 
-        // Calculate scroll offset
+        // Calculate scroll offset; a terminal only as tall as the header and footer has no
+        // rows left for the tree, and the loop below draws none
         let new_scroll_offset = if config.selected_idx >= config.scroll_offset + available_height {
-            config.selected_idx.saturating_sub(available_height - 1)
+            config
+                .selected_idx
+                .saturating_sub(available_height.saturating_sub(1))
         } else if config.selected_idx < config.scroll_offset {
             config.selected_idx
         } else {
             config.scroll_offset
         };
 
-        // Draw tree
+        // Draw tree, tracking the names of the groups above each row
+        let mut ancestors = TreeBuilder::node_path(config.tree, new_scroll_offset);
         for (actual_index, (node, depth)) in config
             .tree
             .iter()
@@ -83,34 +723,203 @@ impl UI {
         {
             let is_selected = actual_index == config.selected_idx;
 
+            let is_jump_match = matches!(node, TreeNode::Tensor { .. } | TreeNode::Metadata { .. })
+                && config.jump_matches.contains(node.name());
+            let is_excluded = match node {
+                TreeNode::Tensor { info } => config.excluded.contains(&info.source_file),
+                TreeNode::Group { name, .. } if *depth == 0 && config.group_by == GroupBy::File => {
+                    config
+                        .excluded
+                        .iter()
+                        .any(|path| display_file_name(path) == *name)
+                }
+                _ => false,
+            };
+            // Green for new tensors, yellow for changed ones and the groups holding them, red
+            // and struck through for the ones the last reload lost
+            let change = match node {
+                TreeNode::Group { name, .. } if *depth == 0 && name == watch::REMOVED_GROUP => {
+                    Some(StatusLevel::Error)
+                }
+                TreeNode::Tensor { info } if config.changes.is_removed(&info.name) => {
+                    Some(StatusLevel::Error)
+                }
+                TreeNode::Tensor { info } => {
+                    config
+                        .changes
+                        .tensor(&info.name)
+                        .map(|change| match change {
+                            Change::New => StatusLevel::Success,
+                            Change::Changed { .. } => StatusLevel::Warning,
+                        })
+                }
+                TreeNode::Group { .. } if !config.changes.is_empty() => config
+                    .changes
+                    .group_delta(&node.tensors())
+                    .map(|_| StatusLevel::Warning),
+                _ => None,
+            };
             if is_selected {
-                execute!(
-                    stdout,
-                    SetForegroundColor(Color::Black),
-                    crossterm::style::SetBackgroundColor(Color::White)
-                )?;
+                config.theme.highlight(&mut stdout)?;
+            } else if is_jump_match {
+                config.theme.emphasis(&mut stdout)?;
+            } else if is_excluded {
+                config.theme.dim(&mut stdout)?;
+            } else if let Some(level) = change {
+                config.theme.status(level, &mut stdout)?;
+            }
+            if let TreeNode::Tensor { info } = node
+                && config.changes.is_removed(&info.name)
+            {
+                execute!(stdout, SetAttribute(Attribute::CrossedOut))?;
             }
 
-            Self::draw_node(node, *depth, &mut stdout)?;
+            let value_match = config
+                .value_matches
+                .get(actual_index)
+                .and_then(Option::as_ref);
+            ancestors.truncate(*depth);
+            let parent = ancestors.last().map(String::as_str);
+            Self::draw_node(
+                node,
+                *depth,
+                parent,
+                value_match,
+                config,
+                &layout,
+                &mut stdout,
+            )?;
+            ancestors.push(node.name().to_string());
 
-            if is_selected {
-                execute!(stdout, ResetColor)?;
+            if is_selected || is_jump_match || is_excluded || change.is_some() {
+                Theme::reset(&mut stdout)?;
             }
         }
+        if let Some(pane) = &config.filter_pane
+            && available_height > 0
+        {
+            Self::draw_filter_pane(
+                pane,
+                terminal_width as usize - pane_width,
+                pane_width,
+                header_height,
+                available_height,
+                config.theme,
+                &mut stdout,
+            )?;
+        }
 
-        // Footer
+        // Footer: a pending status message takes the row over from the stats
         execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
-        if config.search_mode && config.tree.is_empty() {
+        if let Some(status) = config.status {
+            let message = match status.level {
+                StatusLevel::Error => format!("{} | press any key", status.message),
+                _ => status.message.clone(),
+            };
+            config.theme.status(status.level, &mut stdout)?;
+            write!(stdout, "{}\r", truncate_to_width(&message, full_width))?;
+            Theme::reset(&mut stdout)?;
+        } else if config.search_mode && config.tree.is_empty() {
             writeln!(
                 stdout,
                 "No results found for \"{}\" | Press Esc to exit search\r",
                 config.search_query
             )?;
         } else {
+            let mut grouping = match config.group_by {
+                GroupBy::Prefix => String::new(),
+                other => format!(" | Grouped by {}", other.label()),
+            };
+            if config.min_size > 0 {
+                grouping.push_str(&format!(
+                    " | Min size {} (+{} small tensors hidden)",
+                    display_size(config.min_size, config.exact),
+                    config.hidden_tensors
+                ));
+            }
+            if config.jump_count > 0 {
+                grouping.push_str(&match config.jump_index {
+                    Some(index) => format!(
+                        " | Match {}/{} (n/N: next/previous, Esc clears)",
+                        index + 1,
+                        config.jump_count
+                    ),
+                    None => format!(" | {} matches", config.jump_count),
+                });
+            } else if config.search_mode && config.search_jump && !config.search_query.is_empty() {
+                grouping.push_str(" | No matches");
+            }
+            if config.components > 1 {
+                grouping.push_str(&format!(" | Sum of {} components", config.components));
+            }
+            if config.component_parameters.len() > 1 {
+                let parts: Vec<String> = config
+                    .component_parameters
+                    .iter()
+                    .map(|(name, params)| {
+                        format!("{name} {}", display_parameters(*params, config.exact))
+                    })
+                    .collect();
+                grouping.push_str(&format!(" | {}", parts.join(" + ")));
+            }
+            if let Some((counted, total, bytes)) = config.counted_files {
+                grouping.push_str(&format!(
+                    " | Totals over {counted} of {total} files ({})",
+                    display_size(bytes, config.exact)
+                ));
+            }
+            if let Some(file) = &config.file_filter {
+                grouping.push_str(&format!(" | Only tensors from {file}"));
+            }
+            if config.failed_files > 0 {
+                grouping.push_str(&format!(
+                    " | {} file{} failed to load",
+                    config.failed_files,
+                    if config.failed_files == 1 { "" } else { "s" }
+                ));
+            }
+            if config.not_loaded_files > 0 {
+                grouping.push_str(&format!(
+                    " | Load cancelled, {} file{} not loaded",
+                    config.not_loaded_files,
+                    if config.not_loaded_files == 1 {
+                        ""
+                    } else {
+                        "s"
+                    }
+                ));
+            }
+            if config.incomplete_files > 0 {
+                grouping.push_str(&format!(
+                    " | {}{} file{} incomplete, cut-off tensors not counted",
+                    if config.ascii { "" } else { "⏳ " },
+                    config.incomplete_files,
+                    if config.incomplete_files == 1 {
+                        ""
+                    } else {
+                        "s"
+                    }
+                ));
+            }
+            let parameters = match config.embedding_parameters {
+                Some(embedding) => format!(
+                    "Non-embedding Parameters: {} ({} embedding excluded)",
+                    display_parameters(
+                        config.total_parameters.saturating_sub(embedding),
+                        config.exact
+                    ),
+                    display_parameters(embedding, config.exact)
+                ),
+                None => format!(
+                    "Total Parameters: {}",
+                    display_parameters(config.total_parameters, config.exact)
+                ),
+            };
             writeln!(
                 stdout,
-                "Total Parameters: {} | Selected: {}/{} | Scroll: {} | Matches: {}\r",
-                format_parameters(config.total_parameters),
+                "{}{} | Selected: {}/{} | Scroll: {} | Matches: {}\r",
+                parameters,
+                grouping,
                 config.selected_idx + 1,
                 config.tree.len(),
                 new_scroll_offset,
@@ -122,8 +931,93 @@ impl UI {
         Ok(new_scroll_offset)
     }
 
-    fn draw_node(node: &TreeNode, depth: usize, stdout: &mut io::Stdout) -> Result<()> {
+    /// The filter pane in the columns from `left`, over the rows the tree uses
+    fn draw_filter_pane(
+        pane: &FilterPane,
+        left: usize,
+        width: usize,
+        top: usize,
+        height: usize,
+        theme: Theme,
+        stdout: &mut io::Stdout,
+    ) -> Result<()> {
+        let inner = width.saturating_sub(2);
+        let hints = ["Space: toggle  c: clear all", "Tab: focus tree  Esc: close"];
+        let list_height = height.saturating_sub(2 + hints.len());
+        let scroll = (pane.selected + 1).saturating_sub(list_height);
+        let mut lines = vec![(
+            fit_to_width(
+                if pane.focused {
+                    "Filter"
+                } else {
+                    "Filter (Tab to focus)"
+                },
+                inner,
+                false,
+            ),
+            false,
+        )];
+        for (idx, (entry, count)) in pane
+            .entries
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(list_height)
+        {
+            let label = match entry {
+                FilterEntry::Dtype(dtype) => dtype.as_str(),
+                FilterEntry::Category(category) => category.label(),
+            };
+            let mark = if pane.filter.is_checked(entry) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            let count = format_thousands(*count);
+            let label_width = inner.saturating_sub(4 + display_width(&count) + 1);
+            lines.push((
+                format!("{mark} {} {count}", fit_to_width(label, label_width, false)),
+                pane.focused && idx == pane.selected,
+            ));
+        }
+        while lines.len() < height.saturating_sub(hints.len()) {
+            lines.push((String::new(), false));
+        }
+        lines.extend(
+            hints
+                .iter()
+                .map(|hint| (fit_to_width(hint, inner, false), false)),
+        );
+
+        for (row, (line, selected)) in lines.iter().enumerate().take(height) {
+            execute!(stdout, cursor::MoveTo(left as u16, (top + row) as u16))?;
+            write!(stdout, "│ ")?;
+            if *selected {
+                theme.highlight(stdout)?;
+                write!(stdout, "{line}")?;
+                Theme::reset(stdout)?;
+            } else {
+                write!(stdout, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_node(
+        node: &TreeNode,
+        depth: usize,
+        parent: Option<&str>,
+        value_match: Option<&ValueMatch>,
+        config: &DrawConfig,
+        layout: &RowLayout,
+        stdout: &mut io::Stdout,
+    ) -> Result<()> {
         let indent = "  ".repeat(depth);
+        let icons = if config.ascii {
+            &ASCII_ICONS
+        } else {
+            &EMOJI_ICONS
+        };
 
         match node {
             TreeNode::Group {
@@ -131,53 +1025,188 @@ impl UI {
                 expanded,
                 tensor_count,
                 total_size,
+                sort,
                 ..
             } => {
-                let icon = if *expanded { "▼" } else { "▶" };
+                let icon = if *expanded {
+                    icons.expanded
+                } else {
+                    icons.collapsed
+                };
+                // Metadata groups are named "🔧 <source>" by the tree builder
+                let name = match name.strip_prefix("🔧 ") {
+                    Some(source) if config.ascii => format!("[{source}]"),
+                    _ => name.clone(),
+                };
+                let sort_label = match sort {
+                    Some(order) => format!(" [by {}]", order.label()),
+                    None => String::new(),
+                };
+                let quant_label = match node.group_quant() {
+                    Some(quant) => format!(" [{}]", quant.label()),
+                    None => String::new(),
+                };
+                // Counts and totals include tensors hidden by the size floor
+                let hidden = tensor_count - node.visible_tensor_count();
+                let hidden_label = if hidden > 0 {
+                    format!("; +{hidden} small tensors hidden")
+                } else {
+                    String::new()
+                };
+                // Dtype groups already are the breakdown
+                let composition = dtype_composition(node.tensors());
+                let composition_label =
+                    if composition.is_empty() || config.group_by == GroupBy::Dtype {
+                        String::new()
+                    } else {
+                        format!(" ({})", composition_label(&composition, 3))
+                    };
+                let label = format!(
+                    "{indent}{icon} {}{name}{composition_label}{quant_label}{sort_label}",
+                    icons.group
+                );
+                // What the last reload changed below the group
+                let change_label = match config.changes.group_delta(&node.tensors()) {
+                    Some((_, bytes)) if bytes != 0 => {
+                        format!(", {}", watch::signed_size(bytes, config.exact))
+                    }
+                    Some((count, _)) => format!(", {count} changed"),
+                    None => String::new(),
+                };
+                let info = format!(
+                    "({} tensors, {}{}{})",
+                    tensor_count,
+                    display_size(*total_size, config.exact),
+                    change_label,
+                    hidden_label
+                );
+                let label_width = layout.width.saturating_sub(display_width(&info) + 1);
                 writeln!(
                     stdout,
-                    "{}{} 📁 {} ({} tensors, {})\r",
-                    indent,
-                    icon,
-                    name,
-                    tensor_count,
-                    format_size(*total_size)
+                    "{} {}\r",
+                    fit_to_width(&label, label_width, false),
+                    info
                 )?;
             }
             TreeNode::Tensor { info } => {
-                // In search mode (depth 0), show full name; otherwise show short name
-                let display_name = if depth == 0 {
-                    &info.name
-                } else {
-                    info.name.split('.').next_back().unwrap_or(&info.name)
+                // Tensors directly under their name prefix show the last segment; the full name
+                // in search mode (depth 0), flat groupings, flat lists and `…` groups
+                let display_name = match (parent, info.name.rsplit_once('.')) {
+                    (Some(group), Some((prefix, last)))
+                        if !config.group_by.flat()
+                            && (prefix == group || prefix.ends_with(&format!(".{group}"))) =>
+                    {
+                        last
+                    }
+                    _ => &info.name,
+                };
+                let sparsity_column = match config.sparsity {
+                    Some(cache) => match cache.get(&info.name) {
+                        Some(stats) => format!(
+                            " zeros {:.1}% L2 {:.3}",
+                            stats.zero_fraction * 100.0,
+                            stats.l2_norm
+                        ),
+                        None => " zeros - L2 -".to_string(),
+                    },
+                    None => String::new(),
+                };
+                let duplicate_marker = match config.duplicates.get(&info.name) {
+                    Some(id) => format!(" ⧉ dup #{id}"),
+                    None => String::new(),
+                };
+                let pin_marker = match config.pinned.iter().position(|name| *name == info.name) {
+                    Some(slot) => format!(" {}{}", icons.pinned, slot + 1),
+                    None => String::new(),
+                };
+                let change_marker = match config.changes.tensor(&info.name) {
+                    Some(change) => format!(" {}", change.marker(config.exact)),
+                    None => String::new(),
+                };
+                let label = format!(
+                    "{indent}  {}{display_name}{sparsity_column}{duplicate_marker}{pin_marker}{change_marker}",
+                    icons.tensor
+                );
+                let shape_column = match layout.shape_width {
+                    Some(width) => format!(
+                        " {}",
+                        fit_to_width(
+                            &format_shape(info.display_shape(config.storage_order)),
+                            width,
+                            false
+                        )
+                    ),
+                    None => String::new(),
                 };
                 writeln!(
                     stdout,
-                    "{}  📄 {} [{}, {}, {}]\r",
-                    indent,
-                    display_name,
-                    info.dtype,
-                    format_shape(&info.shape),
-                    format_size(info.size_bytes)
+                    "{} {}{} {}\r",
+                    fit_to_width(&label, layout.name_width(), false),
+                    fit_to_width(&info.dtype, DTYPE_WIDTH, false),
+                    shape_column,
+                    fit_to_width(
+                        &display_size(info.size_bytes, config.exact),
+                        layout.size_width,
+                        true
+                    )
                 )?;
             }
-            TreeNode::Metadata { info } => {
-                let truncated_value = if info.value.len() > 50 {
-                    format!("{}...", &info.value[..47])
-                } else {
-                    info.value.clone()
+            // A search hit inside the value: show where it matched instead of the value's start
+            TreeNode::Metadata { info } if let Some(found) = value_match => {
+                let position = match found.element {
+                    Some(element) => format!("value[{element}]"),
+                    None => "value".to_string(),
                 };
-                writeln!(
-                    stdout,
-                    "{}  🏷️  {} [{}]: {}\r",
-                    indent, info.name, info.value_type, truncated_value
-                )?;
+                let head = format!(
+                    "{}  {}{} [{}] {position}: {}",
+                    indent,
+                    icons.metadata,
+                    info.name,
+                    info.value_type,
+                    &found.excerpt[..found.range.start]
+                );
+                let matched = &found.excerpt[found.range.clone()];
+                let room = layout.width.saturating_sub(display_width(&head));
+                if room < display_width(matched) {
+                    let line = format!("{head}{}", &found.excerpt[found.range.start..]);
+                    writeln!(stdout, "{}\r", truncate_to_width(&line, layout.width))?;
+                } else {
+                    write!(stdout, "{head}")?;
+                    config.theme.search_match(stdout)?;
+                    write!(stdout, "{matched}")?;
+                    config.theme.end_match(stdout)?;
+                    let rest = &found.excerpt[found.range.end..];
+                    let room = room - display_width(matched);
+                    writeln!(stdout, "{}\r", truncate_to_width(rest, room))?;
+                }
             }
+            TreeNode::Metadata { info } => {
+                let line = format!(
+                    "{}  {}{} [{}]: {}",
+                    indent,
+                    icons.metadata,
+                    info.name,
+                    info.value_type,
+                    truncate_to_width(&info.value, 50)
+                );
+                writeln!(stdout, "{}\r", truncate_to_width(&line, layout.width))?;
+            }
+            TreeNode::Failed { name, .. } => {
+                let label = format!("{indent}{}{name} — parse error", icons.failed);
+                writeln!(stdout, "{}\r", fit_to_width(&label, layout.width, false))?;
+            }
+            _ => writeln!(stdout, "\r")?,
         }
         Ok(())
     }
 
-    pub fn draw_tensor_detail(tensor: &TensorInfo) -> Result<()> {
+    /// `model_bytes` is the size of all loaded tensors, for the share-of-model line
+    pub fn draw_tensor_detail(
+        tensor: &TensorInfo,
+        model_bytes: usize,
+        analysis: &[String],
+        position: (usize, usize),
+    ) -> Result<()> {
         let mut stdout = io::stdout();
         execute!(
             stdout,
@@ -185,42 +1214,1317 @@ impl UI {
             cursor::MoveTo(0, 0)
         )?;
 
-        writeln!(stdout, "Tensor Details\r")?;
-        writeln!(stdout, "==============\r")?;
+        let (index, count) = position;
+        let title = format!("Tensor Details ({index} of {count} in view)");
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(&title)))?;
         writeln!(stdout, "Name: {}\r", tensor.name)?;
-        writeln!(stdout, "Data Type: {}\r", tensor.dtype)?;
-        writeln!(stdout, "Shape: {}\r", format_shape(&tensor.shape))?;
-        writeln!(stdout, "Size: {}\r", format_size(tensor.size_bytes))?;
-        writeln!(stdout, "\r")?;
-        writeln!(stdout, "Press any key to return...\r")?;
-
-        stdout.flush()?;
-        Ok(())
-    }
-
-    pub fn draw_metadata_detail(metadata: &MetadataInfo) -> Result<()> {
-        let mut stdout = io::stdout();
-        execute!(
+        match dtype::describe(&tensor.dtype) {
+            Some(description) => writeln!(stdout, "Data Type: {} ({description})\r", tensor.dtype)?,
+            None => writeln!(stdout, "Data Type: {}\r", tensor.dtype)?,
+        }
+        match &tensor.storage_shape {
+            Some(storage) => writeln!(
+                stdout,
+                "Shape: storage ne: {}, logical shape: {}\r",
+                format_shape(storage),
+                format_shape(&tensor.shape)
+            )?,
+            None => writeln!(stdout, "Shape: {}\r", format_shape(&tensor.shape))?,
+        }
+        // Both forms, so exact values are at hand whatever the tree shows
+        writeln!(
             stdout,
-            terminal::Clear(ClearType::All),
-            cursor::MoveTo(0, 0)
+            "Size: {} ({} bytes)\r",
+            format_size(tensor.size_bytes),
+            format_thousands(tensor.size_bytes)
         )?;
-
-        writeln!(stdout, "Metadata Details\r")?;
-        writeln!(stdout, "================\r")?;
-        writeln!(stdout, "Key: {}\r", metadata.name)?;
-        writeln!(stdout, "Type: {}\r", metadata.value_type)?;
-        writeln!(stdout, "Value:\r")?;
-
-        // Handle multi-line values or long values
-        let lines = metadata.value.lines();
-        for line in lines.take(20) {
-            // Limit to 20 lines
-            writeln!(stdout, "  {line}\r")?;
-        }
-
+        writeln!(
+            stdout,
+            "Elements: {} ({})\r",
+            format_parameters(tensor.num_elements),
+            format_thousands(tensor.num_elements)
+        )?;
+        if tensor.num_elements > 0 {
+            let bytes_per_element = tensor.size_bytes as f64 / tensor.num_elements as f64;
+            writeln!(
+                stdout,
+                "Bytes/element: {:.4} ({:.2} bits)\r",
+                bytes_per_element,
+                bytes_per_element * 8.0
+            )?;
+        }
+        writeln!(stdout, "Role: {}\r", classify(&tensor.name).label())?;
+        if model_bytes > 0 {
+            writeln!(
+                stdout,
+                "Share of model: {:.3}%\r",
+                tensor.size_bytes as f64 / model_bytes as f64 * 100.0
+            )?;
+        }
+        writeln!(
+            stdout,
+            "File: {}\r",
+            tensor
+                .source_file
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_else(|| tensor.source_file.to_string_lossy())
+        )?;
+        writeln!(stdout, "Format: {}\r", file_format(&tensor.source_file))?;
+        if let Some(offset) = tensor.data_offset {
+            writeln!(
+                stdout,
+                "Byte range: {}..{} ({:#x}..{:#x})\r",
+                format_thousands(offset as usize),
+                format_thousands(offset as usize + tensor.size_bytes),
+                offset,
+                offset as usize + tensor.size_bytes
+            )?;
+        }
+        for note in &tensor.notes {
+            writeln!(stdout, "Note: {note}\r")?;
+        }
+        if summary::is_embedding(&tensor.name) {
+            writeln!(
+                stdout,
+                "Embedding: yes, left out of non-embedding parameter counts\r"
+            )?;
+        }
+        if let Some(quant) = &tensor.quant {
+            for line in quant.detail_lines(tensor) {
+                writeln!(stdout, "{line}\r")?;
+            }
+        }
+        if !analysis.is_empty() {
+            writeln!(stdout, "\r")?;
+            for line in analysis {
+                writeln!(stdout, "{line}\r")?;
+            }
+        }
+        writeln!(stdout, "\r")?;
+        writeln!(
+            stdout,
+            "Z: check for all-zero/constant values | H: value histogram | F: histogram (full pass)\r"
+        )?;
+        writeln!(stdout, "V: read the value at an index\r")?;
+        writeln!(stdout, "←/→ or [/]: previous/next tensor in view\r")?;
+        writeln!(stdout, "Press any other key to return...\r")?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    pub fn draw_metadata_detail(metadata: &MetadataInfo, footer: &str) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        writeln!(stdout, "Metadata Details\r")?;
+        writeln!(stdout, "================\r")?;
+        writeln!(stdout, "Key: {}\r", metadata.name)?;
+        writeln!(stdout, "Type: {}\r", metadata.value_type)?;
+        writeln!(stdout, "Value:\r")?;
+
+        // Handle multi-line values or long values
+        let lines = metadata.value.lines();
+        for line in lines.take(20) {
+            // Limit to 20 lines
+            writeln!(stdout, "  {line}\r")?;
+        }
+        if metadata.name == "tokenizer.ggml.token_type"
+            && let Some(GGUFValue::Array(_, types)) = metadata.raw_value.as_deref()
+        {
+            writeln!(stdout, "First token types:\r")?;
+            for (id, value) in types.iter().enumerate().take(20) {
+                let name = value.as_i64().map_or("invalid", token_type_name);
+                writeln!(stdout, "  {id}: {name}\r")?;
+            }
+        }
+
+        writeln!(stdout, "\r")?;
+        writeln!(stdout, "{footer}\r")?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// A metadata value holding a JSON document, as a tree of its objects and arrays.
+    /// The selected value is shown in full above the footer unless there is a `note`.
+    /// Returns the clamped scroll offset.
+    pub fn draw_json_tree(
+        title: &str,
+        rows: &[(TreeNode, usize)],
+        selected_idx: usize,
+        scroll_offset: usize,
+        note: Option<&str>,
+        ascii: bool,
+        theme: Theme,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = (terminal_width as usize).saturating_sub(1);
+        let available_height = (terminal_height as usize)
+            .saturating_sub(4 + REPORT_DETAIL_LINES)
+            .max(1);
+        let scroll_offset = if selected_idx >= scroll_offset + available_height {
+            selected_idx + 1 - available_height
+        } else {
+            scroll_offset.min(selected_idx)
+        };
+        let icons = if ascii { &ASCII_ICONS } else { &EMOJI_ICONS };
+
+        writeln!(stdout, "{}\r", truncate_to_width(title, width))?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title).min(width)))?;
+        for (idx, (node, depth)) in rows
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(available_height)
+        {
+            let indent = "  ".repeat(*depth);
+            let row = match node {
+                TreeNode::Group {
+                    name,
+                    children,
+                    expanded,
+                    ..
+                } => {
+                    let icon = if *expanded {
+                        icons.expanded
+                    } else {
+                        icons.collapsed
+                    };
+                    // Arrays are the groups whose elements are named by index
+                    let plural = if children.len() == 1 { "" } else { "s" };
+                    let count = match children.first() {
+                        Some(first) if first.name().starts_with('[') => {
+                            format!("[{} item{plural}]", children.len())
+                        }
+                        _ => format!("{{{} key{plural}}}", children.len()),
+                    };
+                    format!("{indent}{icon} {}{name} {count}", icons.group)
+                }
+                TreeNode::Metadata { info } => {
+                    format!("{indent}  {}{}: {}", icons.metadata, info.name, info.value)
+                }
+                _ => continue,
+            };
+            let row = truncate_to_width(&row, width);
+            if idx == selected_idx {
+                theme.highlight(&mut stdout)?;
+                writeln!(stdout, "{row}\r")?;
+                Theme::reset(&mut stdout)?;
+            } else {
+                writeln!(stdout, "{row}\r")?;
+            }
+        }
+
+        // Long strings are cut in the rows; the selected one is shown in full
+        let detail = note
+            .map(str::to_string)
+            .or_else(|| match rows.get(selected_idx) {
+                Some((TreeNode::Metadata { info }, _)) => Some(info.value.clone()),
+                _ => None,
+            });
+        if let Some(detail) = detail {
+            let lines = wrap_to_width(&detail, width);
+            let shown = lines.len().min(REPORT_DETAIL_LINES);
+            execute!(
+                stdout,
+                cursor::MoveTo(0, terminal_height.saturating_sub(1 + shown as u16))
+            )?;
+            for line in lines.iter().take(shown) {
+                writeln!(stdout, "{line}\r")?;
+            }
+        }
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        let hint = format!(
+            "{}/{} | ↑/↓ to select, Enter to expand/collapse, y to copy, e to export JSON, q/Esc to return",
+            (selected_idx + 1).min(rows.len()),
+            rows.len()
+        );
+        write!(stdout, "{}", truncate_to_width(&hint, width))?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    pub fn draw_failure_detail(failure: &LoadFailure) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        writeln!(stdout, "Load Error\r")?;
+        writeln!(stdout, "==========\r")?;
+        writeln!(stdout, "File: {}\r", failure.file.display())?;
+        writeln!(stdout, "Format: {}\r", file_format(&failure.file))?;
+        if let Ok(meta) = std::fs::metadata(&failure.file) {
+            writeln!(
+                stdout,
+                "File size: {} bytes\r",
+                format_thousands(meta.len() as usize)
+            )?;
+        }
+        match failure.offset {
+            Some(offset) => writeln!(stdout, "Failed at byte offset: {offset} ({offset:#x})\r")?,
+            None => writeln!(stdout, "Failed at byte offset: unknown\r")?,
+        }
+        writeln!(stdout, "\r")?;
+        writeln!(stdout, "Error:\r")?;
+        for (i, cause) in failure.chain.iter().enumerate() {
+            writeln!(stdout, "  {i}: {cause}\r")?;
+        }
+
+        writeln!(stdout, "\r")?;
+        if model_extension(&failure.file) == Some("safetensors") {
+            writeln!(
+                stdout,
+                "Press h for the raw header, any other key to return...\r"
+            )?;
+        } else {
+            writeln!(stdout, "Press any key to return...\r")?;
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    pub fn draw_progress(title: &str, label: &str, done: u64, total: u64) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, _) = terminal::size()?;
+        let bar_width = (terminal_width as usize).saturating_sub(10).clamp(10, 70);
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title)))?;
+        writeln!(
+            stdout,
+            "{} {percent:>3}%\r",
+            progress_bar(done, total, bar_width)
+        )?;
+        writeln!(
+            stdout,
+            "{} / {}\r",
+            format_size(done as usize),
+            format_size(total as usize)
+        )?;
+        writeln!(stdout, "{label}\r")?;
+        writeln!(stdout, "\r")?;
+        writeln!(stdout, "Press Esc to cancel\r")?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Progress of a parallel scan: overall bar and ETA, then one line per worker
+    pub fn draw_worker_progress(title: &str, status: &ScanStatus) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, _) = terminal::size()?;
+        let bar_width = (terminal_width as usize).saturating_sub(10).clamp(10, 70);
+        let percent = (status.done * 100).checked_div(status.total).unwrap_or(100);
+        let seconds = status.elapsed.as_secs_f64().max(1e-3);
+
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title)))?;
+        writeln!(
+            stdout,
+            "{} {percent:>3}%\r",
+            progress_bar(status.done, status.total, bar_width)
+        )?;
+        writeln!(
+            stdout,
+            "{} / {} at {}/s, ETA {}\r",
+            format_size(status.done as usize),
+            format_size(status.total as usize),
+            format_size(status.throughput() as usize),
+            status
+                .eta()
+                .map_or("--".to_string(), |eta| format_duration(eta.as_secs()))
+        )?;
+        writeln!(stdout, "\r")?;
+        for (i, worker) in status.workers.iter().enumerate() {
+            let line = format!(
+                "worker {:<2} {:>10}/s  {}",
+                i + 1,
+                format_size((worker.bytes as f64 / seconds) as usize),
+                worker.current.as_deref().unwrap_or("idle")
+            );
+            writeln!(
+                stdout,
+                "{}\r",
+                truncate_to_width(&line, terminal_width as usize)
+            )?;
+        }
+        writeln!(stdout, "\r")?;
+        writeln!(stdout, "Press Esc to cancel\r")?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Draw a modal prompt; `input` is the text typed so far, or None when waiting for a single key
+    pub fn draw_prompt(title: &str, lines: &[&str], input: Option<&str>) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title)))?;
+        for line in lines {
+            writeln!(stdout, "{line}\r")?;
+        }
+        if let Some(input) = input {
+            writeln!(stdout, "> {input}_\r")?;
+            writeln!(stdout, "\r")?;
+            writeln!(
+                stdout,
+                "Enter to confirm, Tab to complete a path, Esc to cancel\r"
+            )?;
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Draw a list with one highlighted row under a fixed header line, returning the
+    /// scroll offset that keeps the selection visible
+    pub fn draw_picker(
+        title: &str,
+        header: &str,
+        rows: &[String],
+        selected_idx: usize,
+        scroll_offset: usize,
+        theme: Theme,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (_, terminal_height) = terminal::size()?;
+        let available_height = (terminal_height as usize).saturating_sub(5).max(1);
+        let scroll_offset = if selected_idx >= scroll_offset + available_height {
+            selected_idx + 1 - available_height
+        } else {
+            scroll_offset.min(selected_idx)
+        };
+
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title)))?;
+        writeln!(stdout, "{header}\r")?;
+        for (idx, row) in rows
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(available_height)
+        {
+            if idx == selected_idx {
+                theme.highlight(&mut stdout)?;
+                writeln!(stdout, "{row}\r")?;
+                Theme::reset(&mut stdout)?;
+            } else {
+                writeln!(stdout, "{row}\r")?;
+            }
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        write!(
+            stdout,
+            "{}/{} | ↑/↓ to select, Enter to jump to it in the tree, q/Esc to return",
+            (selected_idx + 1).min(rows.len()),
+            rows.len()
+        )?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// Recently opened path sets, newest first; entries whose paths are gone are dimmed.
+    /// `note` is the outcome of the last key, shown above the key hints.
+    pub fn draw_recent(
+        entries: &[RecentEntry],
+        selected_idx: usize,
+        scroll_offset: usize,
+        note: Option<&str>,
+        theme: Theme,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = terminal_width as usize;
+        let available_height = (terminal_height as usize).saturating_sub(6).max(1);
+        let scroll_offset = if selected_idx >= scroll_offset + available_height {
+            selected_idx + 1 - available_height
+        } else {
+            scroll_offset.min(selected_idx)
+        };
+
+        let title = "Recently Opened";
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title)))?;
+        if entries.is_empty() {
+            writeln!(stdout, "Nothing has been opened yet\r")?;
+        }
+        for (idx, entry) in entries
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(available_height)
+        {
+            let missing = if entry.exists() { "" } else { "  (missing)" };
+            let row = format!(
+                "{}  {}{missing}",
+                format_timestamp(entry.opened),
+                entry.label()
+            );
+            let row = fit_to_width(&row, width.saturating_sub(1), false);
+            if idx == selected_idx {
+                theme.highlight(&mut stdout)?;
+            } else if !missing.is_empty() {
+                theme.dim(&mut stdout)?;
+            }
+            writeln!(stdout, "{row}\r")?;
+            Theme::reset(&mut stdout)?;
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height.saturating_sub(2)))?;
+        if let Some(note) = note {
+            write!(
+                stdout,
+                "{}",
+                truncate_to_width(note, width.saturating_sub(1))
+            )?;
+        }
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        write!(
+            stdout,
+            "{}/{} | Enter to open, o to browse files, d to forget, x to prune missing entries, q/Esc to cancel",
+            (selected_idx + 1).min(entries.len()),
+            entries.len()
+        )?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// The file browser's listing of `dir`: directories with a trailing `/`, model files with
+    /// their size. `adding` offers `a` to add the selection to the loaded files.
+    pub fn draw_browser(
+        dir: &Path,
+        entries: &[BrowserEntry],
+        selected_idx: usize,
+        scroll_offset: usize,
+        note: Option<&str>,
+        adding: bool,
+        theme: Theme,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = terminal_width as usize;
+        let available_height = (terminal_height as usize).saturating_sub(6).max(1);
+        let scroll_offset = if selected_idx >= scroll_offset + available_height {
+            selected_idx + 1 - available_height
+        } else {
+            scroll_offset.min(selected_idx)
+        };
+
+        let title = format!("Open: {}", dir.display());
+        writeln!(
+            stdout,
+            "{}\r",
+            truncate_start_to_width(&title, width.saturating_sub(1))
+        )?;
+        writeln!(
+            stdout,
+            "{}\r",
+            "=".repeat(display_width(&title).min(width.saturating_sub(1)))
+        )?;
+        if entries.is_empty() {
+            writeln!(stdout, "No directories or model files here\r")?;
+        }
+        let size_width = 12;
+        for (idx, entry) in entries
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(available_height)
+        {
+            let row = match entry.size {
+                Some(size) => format!(
+                    "{} {:>size_width$}",
+                    fit_to_width(&entry.name, width.saturating_sub(size_width + 2), false),
+                    format_size(size as usize)
+                ),
+                None => fit_to_width(&format!("{}/", entry.name), width.saturating_sub(1), false),
+            };
+            if idx == selected_idx {
+                theme.highlight(&mut stdout)?;
+            } else if entry.is_dir() {
+                theme.emphasis(&mut stdout)?;
+            }
+            writeln!(stdout, "{row}\r")?;
+            Theme::reset(&mut stdout)?;
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height.saturating_sub(2)))?;
+        if let Some(note) = note {
+            write!(
+                stdout,
+                "{}",
+                truncate_to_width(note, width.saturating_sub(1))
+            )?;
+        }
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        let add = if adding { ", a to add" } else { "" };
+        let hints = format!(
+            "{}/{} | Enter to open{add}, → into directory, ←/Backspace up, . hidden files, q/Esc to cancel",
+            (selected_idx + 1).min(entries.len()),
+            entries.len()
+        );
+        write!(
+            stdout,
+            "{}",
+            truncate_to_width(&hints, width.saturating_sub(1))
+        )?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// Regions of one file in offset order, with the outcome of the last offset lookup
+    /// (`note`) above the key hints
+    pub fn draw_offsets(
+        map: &OffsetMap,
+        position: (usize, usize),
+        selected_idx: usize,
+        scroll_offset: usize,
+        note: Option<&str>,
+        theme: Theme,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = terminal_width as usize;
+        let available_height = (terminal_height as usize).saturating_sub(7).max(1);
+        let scroll_offset = if selected_idx >= scroll_offset + available_height {
+            selected_idx + 1 - available_height
+        } else {
+            scroll_offset.min(selected_idx)
+        };
+
+        let (file_idx, file_count) = position;
+        let mut title = format!("Offset Map: {}", display_file_name(&map.path));
+        if file_count > 1 {
+            title.push_str(&format!(" ({}/{file_count})", file_idx + 1));
+        }
+        writeln!(stdout, "{}\r", truncate_to_width(&title, width))?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(&title).min(width)))?;
+        let mut summary = format!(
+            "{} bytes | {} tensors",
+            format_thousands(map.file_size as usize),
+            format_thousands(map.tensor_count())
+        );
+        let truncated = map.truncated().count();
+        if truncated > 0 {
+            summary.push_str(&format!(
+                " | ⚠ {truncated} tensor{} extend past the end of the file",
+                if truncated == 1 { "" } else { "s" }
+            ));
+        }
+        writeln!(stdout, "{}\r", truncate_to_width(&summary, width))?;
+        writeln!(
+            stdout,
+            "{}\r",
+            truncate_to_width(
+                &format!(
+                    "{:>15} {:>15} {:>10}  {:<8} Name",
+                    "Start", "End", "Size", "Region"
+                ),
+                width
+            )
+        )?;
+        for (idx, region) in map
+            .regions
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(available_height)
+        {
+            let row = format!(
+                "{:>15} {:>15} {:>10}  {:<8} {}",
+                format_thousands(region.start as usize),
+                format_thousands(region.end as usize),
+                format_size(region.len() as usize),
+                region.kind.label(),
+                region.name
+            );
+            let row = truncate_to_width(&row, width);
+            if idx == selected_idx {
+                theme.highlight(&mut stdout)?;
+                writeln!(stdout, "{row}\r")?;
+                Theme::reset(&mut stdout)?;
+            } else if region.kind == RegionKind::Tensor && region.end > map.file_size {
+                theme.emphasis(&mut stdout)?;
+                writeln!(stdout, "{row}\r")?;
+                Theme::reset(&mut stdout)?;
+            } else {
+                writeln!(stdout, "{row}\r")?;
+            }
+        }
+
+        if let Some(note) = note {
+            execute!(stdout, cursor::MoveTo(0, terminal_height.saturating_sub(2)))?;
+            write!(stdout, "{}", truncate_to_width(note, width))?;
+        }
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        let mut hint = format!(
+            "{}/{} | ↑/↓ to select, g to locate a byte offset, Enter to show a tensor in the tree",
+            (selected_idx + 1).min(map.regions.len()),
+            map.regions.len()
+        );
+        if file_count > 1 {
+            hint.push_str(", ←/→ for other files");
+        }
+        hint.push_str(", q/Esc to return");
+        write!(stdout, "{}", truncate_to_width(&hint, width))?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// Loaded files, one row each, with the selected file's details below the list.
+    /// The full path is printed as is, wrapping rather than being cut.
+    pub fn draw_files(
+        records: &[FileRecord],
+        selected_idx: usize,
+        scroll_offset: usize,
+        filter: Option<&Path>,
+        exact: bool,
+        ascii: bool,
+        theme: Theme,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = terminal_width as usize;
+        let available_height = ((terminal_height as usize).saturating_sub(4) / 2).max(1);
+        let scroll_offset = if selected_idx >= scroll_offset + available_height {
+            selected_idx + 1 - available_height
+        } else {
+            scroll_offset.min(selected_idx)
+        };
+
+        let title = format!("Loaded Files ({})", records.len());
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(&title)))?;
+        let name_width = records
+            .iter()
+            .map(|r| display_width(&display_file_name(&r.path)))
+            .max()
+            .unwrap_or(0)
+            .min(width / 2);
+        let format_label = |record: &FileRecord| match &record.version {
+            Some(version) => format!("{} {version}", record.format),
+            None => record.format.to_string(),
+        };
+        for (idx, record) in records
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(available_height)
+        {
+            let mut row = format!(
+                "{}  {:<14} {:>10} {:>8} tensors {:>6} keys",
+                fit_to_width(&display_file_name(&record.path), name_width, false),
+                format_label(record),
+                display_size(record.size as usize, exact),
+                format_thousands(record.tensors),
+                format_thousands(record.metadata_keys)
+            );
+            if record.incomplete.is_some() {
+                row.push_str(if ascii {
+                    "  [incomplete]"
+                } else {
+                    "  ⏳ incomplete"
+                });
+            }
+            if filter == Some(record.path.as_path()) {
+                row.push_str("  [shown alone]");
+            }
+            let row = truncate_to_width(&row, width);
+            if idx == selected_idx {
+                theme.highlight(&mut stdout)?;
+                writeln!(stdout, "{row}\r")?;
+                Theme::reset(&mut stdout)?;
+            } else {
+                writeln!(stdout, "{row}\r")?;
+            }
+        }
+
+        if let Some(record) = records.get(selected_idx) {
+            writeln!(stdout, "\r")?;
+            writeln!(stdout, "Path: {}\r", record.path.display())?;
+            writeln!(
+                stdout,
+                "Size: {} ({} bytes)\r",
+                format_size(record.size as usize),
+                format_thousands(record.size as usize)
+            )?;
+            match record.modified {
+                Some(modified) => writeln!(stdout, "Modified: {}\r", format_timestamp(modified))?,
+                None => writeln!(stdout, "Modified: unknown\r")?,
+            }
+            writeln!(stdout, "Format: {}\r", format_label(record))?;
+            writeln!(
+                stdout,
+                "Tensors: {} | Metadata keys: {}\r",
+                format_thousands(record.tensors),
+                format_thousands(record.metadata_keys)
+            )?;
+            if let Some(incomplete) = &record.incomplete {
+                writeln!(
+                    stdout,
+                    "Incomplete: {} of {} bytes present, {} missing; {} of {} tensors complete\r",
+                    format_thousands(record.size as usize),
+                    format_thousands(incomplete.expected_size as usize),
+                    format_thousands(incomplete.missing_bytes as usize),
+                    format_thousands(incomplete.present_tensors),
+                    format_thousands(incomplete.present_tensors + incomplete.cut_tensors)
+                )?;
+            }
+            writeln!(
+                stdout,
+                "Parse time: {:.1} ms | Loaded at: {}\r",
+                record.parse_time.as_secs_f64() * 1000.0,
+                format_timestamp(record.loaded_at)
+            )?;
+            if record.warnings.is_empty() {
+                writeln!(stdout, "Warnings: none\r")?;
+            } else {
+                writeln!(stdout, "Warnings:\r")?;
+                for warning in &record.warnings {
+                    writeln!(
+                        stdout,
+                        "  {}\r",
+                        truncate_to_width(warning, width.saturating_sub(2))
+                    )?;
+                }
+            }
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        write!(
+            stdout,
+            "{}/{} | ↑/↓ to select, Enter to show its tensors, h for its raw header, q/Esc to return",
+            (selected_idx + 1).min(records.len()),
+            records.len()
+        )?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// Draw a scrollable list of report lines, returning the clamped scroll offset
+    pub fn draw_report(title: &str, lines: &[String], scroll_offset: usize) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (_, terminal_height) = terminal::size()?;
+        let available_height = (terminal_height as usize).saturating_sub(4);
+        let scroll_offset = scroll_offset.min(lines.len().saturating_sub(available_height));
+
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title)))?;
+        for line in lines.iter().skip(scroll_offset).take(available_height) {
+            writeln!(stdout, "{line}\r")?;
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        write!(
+            stdout,
+            "Lines {}-{} of {} | ↑/↓/PgUp/PgDn to scroll, q/Esc to return",
+            (scroll_offset + 1).min(lines.len()),
+            (scroll_offset + available_height).min(lines.len()),
+            lines.len()
+        )?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// A safetensors header as written: its length, padding and data offset, then the JSON
+    /// with keys, strings, numbers and literals colored, starting `column` columns into each
+    /// line. Returns the clamped scroll offset.
+    pub fn draw_raw_header(
+        title: &str,
+        summary: &[String],
+        lines: &[JsonLine],
+        scroll_offset: usize,
+        column: usize,
+        theme: Theme,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = (terminal_width as usize).saturating_sub(1);
+        let available_height = (terminal_height as usize)
+            .saturating_sub(5 + summary.len())
+            .max(1);
+        let scroll_offset = scroll_offset.min(lines.len().saturating_sub(available_height));
+
+        writeln!(stdout, "{}\r", truncate_to_width(title, width))?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(title).min(width)))?;
+        for line in summary {
+            writeln!(stdout, "{}\r", truncate_to_width(line, width))?;
+        }
         writeln!(stdout, "\r")?;
-        writeln!(stdout, "Press any key to return...\r")?;
+        for line in lines.iter().skip(scroll_offset).take(available_height) {
+            // Characters left of `column` are skipped, then as many as fit are drawn
+            let mut at = 0;
+            let mut used = 0;
+            'tokens: for (token, text) in line {
+                theme.json(*token, &mut stdout)?;
+                for c in text.chars() {
+                    let w = c.width().unwrap_or(0);
+                    if at < column {
+                        at += w;
+                        continue;
+                    }
+                    if used + w > width {
+                        break 'tokens;
+                    }
+                    write!(stdout, "{c}")?;
+                    used += w;
+                }
+                Theme::reset(&mut stdout)?;
+            }
+            Theme::reset(&mut stdout)?;
+            writeln!(stdout, "\r")?;
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        let hint = format!(
+            "Lines {}-{} of {} | ↑/↓/PgUp/PgDn to scroll, ←/→ for long lines, q/Esc to return",
+            (scroll_offset + 1).min(lines.len()),
+            (scroll_offset + available_height).min(lines.len()),
+            lines.len()
+        );
+        write!(stdout, "{}", truncate_to_width(&hint, width))?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// One page of `tokenizer.ggml.merges`, optionally only the rules at `matches`;
+    /// returns the clamped scroll offset
+    pub fn draw_merges(
+        rules: &[GGUFValue],
+        stats: &MergeStats,
+        filter: Option<(&str, &[usize])>,
+        scroll_offset: usize,
+    ) -> Result<usize> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = terminal_width as usize;
+        let available_height = (terminal_height as usize).saturating_sub(6);
+        let shown = filter.map_or(rules.len(), |(_, matches)| matches.len());
+        let scroll_offset = scroll_offset.min(shown.saturating_sub(available_height));
+
+        writeln!(stdout, "Tokenizer Merges\r")?;
+        writeln!(stdout, "================\r")?;
+        let mut summary = format!("Merges: {}", format_thousands(stats.count));
+        if let (Some(longest), Some(shortest)) = (&stats.longest, &stats.shortest) {
+            summary.push_str(&format!(
+                " | Longest result: {longest:?} ({} chars) | Shortest: {shortest:?} ({} chars)",
+                longest.chars().count(),
+                shortest.chars().count()
+            ));
+        }
+        writeln!(stdout, "{}\r", truncate_to_width(&summary, width))?;
+        match filter {
+            Some((piece, matches)) => writeln!(
+                stdout,
+                "Involving {piece:?}: {} merges\r",
+                format_thousands(matches.len())
+            )?,
+            None => writeln!(stdout, "\r")?,
+        }
+
+        for position in scroll_offset..(scroll_offset + available_height).min(shown) {
+            let rank = filter.map_or(position, |(_, matches)| matches[position]);
+            let line = match merge_pieces(&rules[rank]) {
+                Some((left, right)) => format!("{rank:>8}  {left} + {right} -> {left}{right}"),
+                None => format!("{rank:>8}  {} (not a merge rule)", rules[rank]),
+            };
+            writeln!(stdout, "{}\r", truncate_to_width(&line, width))?;
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        write!(
+            stdout,
+            "Merges {}-{} of {} | ↑/↓/PgUp/PgDn to scroll, / to find a piece, q/Esc to return",
+            (scroll_offset + 1).min(shown),
+            (scroll_offset + available_height).min(shown),
+            format_thousands(shown)
+        )?;
+
+        stdout.flush()?;
+        Ok(scroll_offset)
+    }
+
+    /// Pinned tensors side by side, one column each, with fields that differ emphasized.
+    /// Terminals too narrow for the columns get one block per tensor instead.
+    pub fn draw_comparison(
+        tensors: &[(&TensorInfo, Option<&Histogram>)],
+        selected: usize,
+        exact: bool,
+        theme: Theme,
+    ) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = (terminal_width as usize).saturating_sub(1);
+        let available_height = (terminal_height as usize).saturating_sub(4);
+
+        let title = format!("Pinned Tensors ({})", tensors.len());
+        writeln!(stdout, "{title}\r")?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(&title)))?;
+
+        let rows = comparison_rows(tensors, exact);
+        let label_width = rows.iter().map(|row| row.label.len()).max().unwrap_or(0) + 2;
+        let column_width = width.saturating_sub(label_width) / tensors.len().max(1);
+        let side_by_side = column_width >= MIN_COMPARE_COLUMN;
+
+        if side_by_side {
+            // Header: the slot numbers, with the selected one highlighted
+            write!(stdout, "{}", " ".repeat(label_width))?;
+            for slot in 0..tensors.len() {
+                let header = fit_to_width(&format!("[{}]", slot + 1), column_width - 1, false);
+                if slot == selected {
+                    theme.highlight(&mut stdout)?;
+                }
+                write!(stdout, "{header}")?;
+                Theme::reset(&mut stdout)?;
+                write!(stdout, " ")?;
+            }
+            writeln!(stdout, "\r")?;
+            for row in rows.iter().take(available_height.saturating_sub(1)) {
+                let cells: Vec<String> = row
+                    .values
+                    .iter()
+                    .map(|value| {
+                        // Long names keep their distinctive end
+                        let value = if row.label == "Name" {
+                            truncate_start_to_width(value, column_width - 1)
+                        } else {
+                            value.clone()
+                        };
+                        fit_to_width(&value, column_width - 1, false)
+                    })
+                    .collect();
+                let line = format!("{:<label_width$}{}", row.label, cells.join(" "));
+                if row.differs {
+                    theme.emphasis(&mut stdout)?;
+                }
+                write!(stdout, "{}", line.trim_end())?;
+                Theme::reset(&mut stdout)?;
+                writeln!(stdout, "\r")?;
+            }
+        } else {
+            // One block per tensor, scrolled so the selected block is on screen
+            let block_height = rows.len() + 1;
+            let blocks_shown = (available_height / block_height).max(1);
+            let first = selected.saturating_sub(blocks_shown - 1);
+            for slot in (first..tensors.len()).take(blocks_shown) {
+                let header =
+                    truncate_to_width(&format!("[{}] {}", slot + 1, rows[0].values[slot]), width);
+                if slot == selected {
+                    theme.highlight(&mut stdout)?;
+                }
+                write!(stdout, "{header}")?;
+                Theme::reset(&mut stdout)?;
+                writeln!(stdout, "\r")?;
+                for row in &rows[1..] {
+                    if row.differs {
+                        theme.emphasis(&mut stdout)?;
+                    }
+                    let line = format!("  {:<label_width$}{}", row.label, row.values[slot]);
+                    write!(stdout, "{}", truncate_to_width(&line, width))?;
+                    Theme::reset(&mut stdout)?;
+                    writeln!(stdout, "\r")?;
+                }
+                writeln!(stdout, "\r")?;
+            }
+        }
+
+        execute!(stdout, cursor::MoveTo(0, terminal_height - 1))?;
+        let keys = if side_by_side { "←/→" } else { "↑/↓" };
+        write!(
+            stdout,
+            "{}",
+            truncate_to_width(
+                &format!(
+                    "{keys} select | p/x unpin selected | differing fields highlighted | q/Esc return"
+                ),
+                width
+            )
+        )?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render a histogram as one block-character bar per bucket
+    pub fn histogram_lines(
+        histogram: &Histogram,
+        tensor_bytes: usize,
+        bar_width: usize,
+    ) -> Vec<String> {
+        const PARTIAL_BLOCKS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+        let mut lines = vec![format!(
+            "min {:.6}  max {:.6}  mean {:.6}  std {:.6}",
+            histogram.min, histogram.max, histogram.mean, histogram.std
+        )];
+        let zero_percent = if histogram.count > 0 {
+            histogram.zero_count as f64 / histogram.count as f64 * 100.0
+        } else {
+            0.0
+        };
+        lines.push(format!(
+            "exact zeros {:.2}%  non-finite {} ({:.4}%)",
+            zero_percent,
+            histogram.non_finite_count,
+            histogram.non_finite_rate() * 100.0
+        ));
+        if let Some(sampled) = histogram.sampled_bytes {
+            lines.push(format!(
+                "ESTIMATED from {} evenly spaced chunks ({} of {}, {:.3}% of the tensor); F: exact full pass",
+                sampled.div_ceil(SAMPLE_CHUNK_SIZE),
+                format_size(sampled),
+                format_size(tensor_bytes),
+                sampled as f64 / tensor_bytes.max(1) as f64 * 100.0
+            ));
+        }
+        if histogram.sample_stride > 1 {
+            lines.push(format!(
+                "Bars use every {} elements ({} values)",
+                histogram.sample_stride,
+                histogram.count.div_ceil(histogram.sample_stride as u64)
+            ));
+        }
+        lines.push(String::new());
+
+        let peak = histogram.buckets.iter().copied().max().unwrap_or(0).max(1);
+        let bucket_width = (histogram.max - histogram.min) / histogram.buckets.len() as f64;
+        for (i, &count) in histogram.buckets.iter().enumerate() {
+            let start = histogram.min + bucket_width * i as f64;
+            let eighths = (count as f64 / peak as f64 * (bar_width * 8) as f64).round() as usize;
+            let bar = format!("{}{}", "█".repeat(eighths / 8), PARTIAL_BLOCKS[eighths % 8]);
+            lines.push(format!("{start:>12.5} | {bar} {count}"));
+        }
+        lines
+    }
+
+    /// Rows of the terminal the treemap has, below its title and legend and above the
+    /// caption and key hints
+    pub fn treemap_area() -> Result<(u16, u16)> {
+        let (width, height) = terminal::size()?;
+        Ok((width, height.saturating_sub(5)))
+    }
+
+    /// One level of the treemap: blocks filled with their dtype's color and labeled with
+    /// name, size and share while they have room, the selected block's details in a caption
+    pub fn draw_treemap(
+        levels: &[Level],
+        blocks: &[Block],
+        model_bytes: usize,
+        exact: bool,
+        ascii: bool,
+        theme: Theme,
+    ) -> Result<()> {
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let width = terminal_width as usize;
+        let Some(level) = levels.last() else {
+            return Ok(());
+        };
+
+        let separator = if ascii { " > " } else { " › " };
+        let path: Vec<&str> = levels.iter().map(|level| level.title.as_str()).collect();
+        let title = format!("Treemap: {}", path.join(separator));
+        writeln!(stdout, "{}\r", truncate_to_width(&title, width))?;
+        writeln!(stdout, "{}\r", "=".repeat(display_width(&title).min(width)))?;
+
+        // Legend: the dtypes of this level, largest first
+        let level_bytes: usize = blocks.iter().map(|block| block.bytes).sum();
+        let mut dtypes: Vec<(&str, usize)> = Vec::new();
+        for share in blocks.iter().flat_map(|block| &block.dtypes) {
+            match dtypes.iter_mut().find(|(dtype, _)| *dtype == share.dtype) {
+                Some((_, bytes)) => *bytes += share.bytes,
+                None => dtypes.push((&share.dtype, share.bytes)),
+            }
+        }
+        dtypes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        write!(stdout, "{} ", display_size(level_bytes, exact))?;
+        let mut used = display_size(level_bytes, exact).len() + 1;
+        for (dtype, bytes) in dtypes {
+            let entry = format!(
+                " {dtype} {:.0}%",
+                bytes as f64 / level_bytes.max(1) as f64 * 100.0
+            );
+            let swatch = if theme == Theme::Mono { 0 } else { 2 };
+            if used + swatch + entry.len() > width {
+                break;
+            }
+            if let Some(color) = theme.dtype_color(dtype) {
+                execute!(stdout, SetForegroundColor(color))?;
+                write!(stdout, " {}", if ascii { "#" } else { "█" })?;
+                Theme::reset(&mut stdout)?;
+            }
+            write!(stdout, "{entry}")?;
+            used += swatch + entry.len();
+        }
+
+        if blocks.is_empty() {
+            execute!(stdout, cursor::MoveTo(0, 3))?;
+            write!(stdout, "Nothing here holds tensor data")?;
+        }
+        let percent = |bytes: usize, of: usize| bytes as f64 / of.max(1) as f64 * 100.0;
+        for (index, block) in blocks.iter().enumerate() {
+            let rect = block.rect;
+            // A column and row of background between blocks, where they have room for it
+            let inner_width = if rect.width >= 4 {
+                rect.width - 1
+            } else {
+                rect.width
+            };
+            let inner_height = if rect.height >= 3 {
+                rect.height - 1
+            } else {
+                rect.height
+            };
+            let lines = [
+                block.label(level.parent),
+                display_size(block.bytes, exact),
+                format!("{:.1}%", percent(block.bytes, level_bytes)),
+            ];
+            theme.treemap_block(block.dtype(), index == level.selected, &mut stdout)?;
+            for row in 0..inner_height {
+                let text = lines.get(row as usize).map_or("", String::as_str);
+                execute!(stdout, cursor::MoveTo(rect.x, 3 + rect.y + row))?;
+                write!(
+                    stdout,
+                    "{}",
+                    fit_to_width(text, inner_width as usize, false)
+                )?;
+            }
+            Theme::reset(&mut stdout)?;
+        }
+
+        if let Some(block) = blocks.get(level.selected) {
+            let mut caption = match block.nodes.as_slice() {
+                [TreeNode::Tensor { info }] => format!("{}: ", info.name),
+                [node] => format!("{}: ", node.name()),
+                nodes => format!("{} smaller items: ", format_thousands(nodes.len())),
+            };
+            caption.push_str(&format!(
+                "{}, {:.1}% of this level, {:.1}% of the model",
+                display_size(block.bytes, exact),
+                percent(block.bytes, level_bytes),
+                percent(block.bytes, model_bytes)
+            ));
+            match block.nodes.as_slice() {
+                [TreeNode::Tensor { info }] => {
+                    caption.push_str(&format!(" | {} {}", info.dtype, format_shape(&info.shape)))
+                }
+                _ => caption.push_str(&format!(
+                    " | {} tensor{} | {}",
+                    format_thousands(block.tensors),
+                    if block.tensors == 1 { "" } else { "s" },
+                    composition_label(&block.dtypes, 3)
+                )),
+            }
+            execute!(stdout, cursor::MoveTo(0, terminal_height.saturating_sub(2)))?;
+            write!(stdout, "{}", truncate_to_width(&caption, width))?;
+        }
+        execute!(stdout, cursor::MoveTo(0, terminal_height.saturating_sub(1)))?;
+        let hint = "←/↑/↓/→ or Tab to select, Enter to open a block (a tensor: show it in the tree), Esc/Backspace to go up, q to return";
+        write!(stdout, "{}", truncate_to_width(hint, width))?;
 
         stdout.flush()?;
         Ok(())
@@ -1,3 +1,43 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Suffixes download tools add to a file until it has fully arrived
+pub const DOWNLOAD_SUFFIXES: &[&str] = &["part", "download"];
+
+/// Extension of a model file, looking through a download suffix: `safetensors` for both
+/// `model.safetensors` and `model.safetensors.part`
+pub fn model_extension(path: &Path) -> Option<&str> {
+    let extension = path.extension()?.to_str()?;
+    if DOWNLOAD_SUFFIXES.contains(&extension) {
+        return Path::new(path.file_stem()?).extension()?.to_str();
+    }
+    Some(extension)
+}
+
+/// Human-readable container format of a model file, from its extension
+pub fn file_format(path: &Path) -> &'static str {
+    match model_extension(path) {
+        Some("safetensors") => "SafeTensors",
+        Some("gguf") => "GGUF",
+        Some("bin" | "pt" | "pth") => "PyTorch checkpoint (pickle)",
+        Some("npy") => "NumPy array (.npy)",
+        Some("npz") => "NumPy archive (.npz)",
+        Some("dduf") => "DDUF archive",
+        Some("zip") => "zip archive",
+        Some("onnx") => "ONNX",
+        Some("h5" | "hdf5") => "HDF5",
+        _ => "raw data file",
+    }
+}
+
+/// Last component of a path, or the whole path when it has none
+pub fn display_file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
 pub fn format_shape(shape: &[usize]) -> String {
     format!(
         "({})",
@@ -9,23 +49,88 @@ pub fn format_shape(shape: &[usize]) -> String {
     )
 }
 
+/// Set once at startup from `si_units` in the config file
+static SI_UNITS: AtomicBool = AtomicBool::new(false);
+
+/// Switch `format_size` to decimal (SI) units: kB, MB, GB, ...
+pub fn set_si_units(enabled: bool) {
+    SI_UNITS.store(enabled, Ordering::Relaxed);
+}
+
+/// Byte size in binary (IEC) units, or decimal ones after `set_si_units`; the single
+/// formatter used for every size the explorer shows
 pub fn format_size(bytes: usize) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+    format_size_in(bytes, SI_UNITS.load(Ordering::Relaxed))
+}
+
+fn format_size_in(bytes: usize, si: bool) -> String {
+    let (units, step): (&[&str], f64) = if si {
+        (&["B", "kB", "MB", "GB", "TB", "PB"], 1000.0)
+    } else {
+        (&["B", "KiB", "MiB", "GiB", "TiB", "PiB"], 1024.0)
+    };
     let mut size = bytes as f64;
     let mut unit_idx = 0;
 
-    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
-        size /= 1024.0;
+    // Compared as printed, so 1048575 bytes is 1.0 MiB rather than 1024.0 KiB
+    while (size * 10.0).round() / 10.0 >= step && unit_idx < units.len() - 1 {
+        size /= step;
         unit_idx += 1;
     }
 
     if unit_idx == 0 {
-        format!("{} {}", bytes, UNITS[unit_idx])
+        format!("{} {}", bytes, units[unit_idx])
     } else {
-        format!("{:.1} {}", size, UNITS[unit_idx])
+        format!("{:.1} {}", size, units[unit_idx])
+    }
+}
+
+/// Parse a byte offset: `1234567`, `1,234,567` (as printed), `0x12d687` or a size such
+/// as `4GiB`
+pub fn parse_offset(text: &str) -> Result<u64, String> {
+    let text = text.trim().replace(',', "");
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).map_err(|_| format!("invalid offset '{text}'"));
+    }
+    match text.parse::<u64>() {
+        Ok(offset) => Ok(offset),
+        Err(_) => parse_size(&text).map(|size| size as u64),
     }
 }
 
+/// Parse a size such as `1MiB`, `512 KB` or `100` (bytes). `KiB`/`MiB`/... are always
+/// binary; `KB`/`MB`/... follow the units `format_size` shows, binary unless `set_si_units`
+pub fn parse_size(text: &str) -> Result<usize, String> {
+    parse_size_in(text, SI_UNITS.load(Ordering::Relaxed))
+}
+
+fn parse_size_in(text: &str, si: bool) -> Result<usize, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{text}' (expected e.g. 1MB)"))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let (prefix, binary) = match unit.strip_suffix("IB") {
+        Some(prefix) => (prefix, true),
+        None => (unit.strip_suffix('B').unwrap_or(&unit), !si),
+    };
+    let power = match prefix {
+        "" if unit.is_empty() || unit == "B" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => return Err(format!("unknown size unit '{unit}'")),
+    };
+    let step: f64 = if binary { 1024.0 } else { 1000.0 };
+    Ok((number * step.powi(power)) as usize)
+}
+
 pub fn format_parameters(params: usize) -> String {
     if params < 1_000 {
         format!("{params}")
@@ -37,3 +142,458 @@ pub fn format_parameters(params: usize) -> String {
         format!("{:.1}B", params as f64 / 1_000_000_000.0)
     }
 }
+
+/// Integer with comma thousands separators, e.g. `7,615,616,512`
+pub fn format_thousands(value: usize) -> String {
+    let digits = value.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Byte size as shown in the tree: rounded with a unit, or exact when `exact` is set
+pub fn display_size(bytes: usize, exact: bool) -> String {
+    if exact {
+        format!("{} B", format_thousands(bytes))
+    } else {
+        format_size(bytes)
+    }
+}
+
+/// Parameter count as shown in the tree: rounded (`7.6B`), or exact when `exact` is set
+pub fn display_parameters(params: usize, exact: bool) -> String {
+    if exact {
+        format_thousands(params)
+    } else {
+        format_parameters(params)
+    }
+}
+
+/// Terminal columns taken by `text`; emoji and CJK characters count as two
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// `text` split where a cut can't change the width of what's left: each character keeps
+/// the combining marks, joiners and variation selectors after it, so `🏷️` (one column as a
+/// bare character, two with its selector) is never split from its selector
+fn width_clusters(text: &str) -> Vec<&str> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let mut joined = c == '\u{200d}';
+        while let Some(&(_, next)) = chars.peek() {
+            if !joined && next.width() != Some(0) {
+                break;
+            }
+            joined = next == '\u{200d}';
+            chars.next();
+        }
+        let end = chars.peek().map_or(text.len(), |&(i, _)| i);
+        clusters.push(&text[start..end]);
+        start = end;
+    }
+    clusters
+}
+
+/// Cut `text` to at most `width` columns, marking the cut with `…`
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for cluster in width_clusters(text) {
+        let w = cluster.width();
+        if used + w + 1 > width {
+            break;
+        }
+        out.push_str(cluster);
+        used += w;
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+/// Cut `text` to at most `width` columns from the front, keeping its end: `…q_proj.weight`
+pub fn truncate_start_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for cluster in width_clusters(text).into_iter().rev() {
+        let w = cluster.width();
+        if used + w + 1 > width {
+            break;
+        }
+        kept.push(cluster);
+        used += w;
+    }
+    let mut out = if width > 0 {
+        "…".to_string()
+    } else {
+        String::new()
+    };
+    out.extend(kept.into_iter().rev());
+    out
+}
+
+/// Break `text` into lines of at most `width` columns at spaces; words wider than a line
+/// are cut
+pub fn wrap_to_width(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        if !line.is_empty() && display_width(&line) + 1 + display_width(word) > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+        .into_iter()
+        .map(|line| truncate_to_width(&line, width))
+        .collect()
+}
+
+/// `text` fitted to exactly `width` columns: truncated, then padded on the right (or left)
+pub fn fit_to_width(text: &str, width: usize, align_right: bool) -> String {
+    let text = truncate_to_width(text, width);
+    let padding = " ".repeat(width.saturating_sub(text.width()));
+    if align_right {
+        padding + &text
+    } else {
+        text + &padding
+    }
+}
+
+/// Tab-complete the last component of a file system path; returns the extended input and,
+/// when it is still ambiguous, the candidate names
+pub fn complete_path(input: &str) -> (String, Vec<String>) {
+    let (dir, prefix) = match input.rfind('/') {
+        Some(pos) => input.split_at(pos + 1),
+        None => ("", input),
+    };
+    let Ok(entries) = std::fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return (input.to_string(), Vec::new());
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Hidden entries only complete once the user has typed the dot
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            Some(if is_dir { name + "/" } else { name })
+        })
+        .collect();
+    candidates.sort();
+
+    let Some(first) = candidates.first() else {
+        return (input.to_string(), Vec::new());
+    };
+    let common = candidates.iter().fold(first.as_str(), |common, name| {
+        let len = common
+            .char_indices()
+            .zip(name.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8());
+        &common[..len]
+    });
+    let completed = format!("{dir}{common}");
+    if candidates.len() == 1 {
+        (completed, Vec::new())
+    } else {
+        (completed, candidates)
+    }
+}
+
+/// Upper bound on the patterns one argument may expand to, so a typo like `{1..99999999}`
+/// fails instead of exhausting memory
+const MAX_BRACE_EXPANSIONS: usize = 100_000;
+
+/// Shell-style brace expansion: `a{b,c}d` gives `abd` and `acd`, `{01..03}` gives
+/// `01`, `02` and `03` (zero padding is kept), `{a..c}` gives `a`, `b` and `c`, and braces
+/// nest. Braces holding neither a comma nor a range are kept literally; unbalanced ones are
+/// an error. Backslashes are never escapes, so Windows paths pass through untouched.
+pub fn expand_braces(pattern: &str) -> Result<Vec<String>, String> {
+    let mut depth = 0usize;
+    for (i, c) in pattern.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Err(format!("unmatched }} at position {i}")),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err(format!("{depth} unclosed {{"));
+    }
+    let mut expanded = Vec::new();
+    expand_braces_into(pattern, 0, &mut expanded)?;
+    Ok(expanded)
+}
+
+fn expand_braces_into(pattern: &str, from: usize, out: &mut Vec<String>) -> Result<(), String> {
+    let Some((open, close)) = find_brace_pair(pattern, from) else {
+        if out.len() >= MAX_BRACE_EXPANSIONS {
+            return Err(format!(
+                "expands to more than {MAX_BRACE_EXPANSIONS} patterns"
+            ));
+        }
+        out.push(pattern.to_string());
+        return Ok(());
+    };
+    let (prefix, body, suffix) = (
+        &pattern[..open],
+        &pattern[open + 1..close],
+        &pattern[close + 1..],
+    );
+    let alternatives = match split_alternatives(body) {
+        Some(alternatives) => alternatives,
+        None => match expand_range(body) {
+            Some(range) => range?,
+            // Not an expansion: keep the braces and look further along
+            None => return expand_braces_into(pattern, close + 1, out),
+        },
+    };
+    for alternative in alternatives {
+        let candidate = format!("{prefix}{alternative}{suffix}");
+        // Resume after the prefix; the alternative itself may hold more braces
+        expand_braces_into(&candidate, open, out)?;
+    }
+    Ok(())
+}
+
+/// Byte positions of the first `{` at or after `from` and its matching `}`
+fn find_brace_pair(pattern: &str, from: usize) -> Option<(usize, usize)> {
+    let open = from + pattern[from..].find('{')?;
+    let mut depth = 0;
+    for (i, c) in pattern[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((open, open + i));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split `body` at its top-level commas; None when there are none
+fn split_alternatives(body: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in body.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(body[start..i].to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    parts.push(body[start..].to_string());
+    Some(parts)
+}
+
+/// `1..10`, `001..120` or `a..f`; None when `body` is not a range
+fn expand_range(body: &str) -> Option<Result<Vec<String>, String>> {
+    let (from, to) = body.split_once("..")?;
+    if let (Ok(start), Ok(end)) = (from.parse::<i64>(), to.parse::<i64>()) {
+        let count = start.abs_diff(end) as usize + 1;
+        if count > MAX_BRACE_EXPANSIONS {
+            return Some(Err(format!("range {{{body}}} has {count} values")));
+        }
+        // A leading zero on either end pads every value to the wider end
+        let padded = |s: &str| {
+            s.trim_start_matches('-').len() > 1 && s.trim_start_matches('-').starts_with('0')
+        };
+        let width = if padded(from) || padded(to) {
+            from.len().max(to.len())
+        } else {
+            0
+        };
+        let values: Vec<i64> = if start <= end {
+            (start..=end).collect()
+        } else {
+            (end..=start).rev().collect()
+        };
+        return Some(Ok(values
+            .into_iter()
+            .map(|v| format!("{v:0width$}"))
+            .collect()));
+    }
+    let (mut from_chars, mut to_chars) = (from.chars(), to.chars());
+    match (
+        from_chars.next(),
+        from_chars.next(),
+        to_chars.next(),
+        to_chars.next(),
+    ) {
+        (Some(start), None, Some(end), None)
+            if start.is_ascii_alphanumeric() && end.is_ascii_alphanumeric() =>
+        {
+            let values: Vec<char> = if start <= end {
+                (start..=end).collect()
+            } else {
+                (end..=start).rev().collect()
+            };
+            Some(Ok(values.into_iter().map(String::from).collect()))
+        }
+        _ => None,
+    }
+}
+
+pub fn progress_bar(done: u64, total: u64, width: usize) -> String {
+    let filled = if total == 0 {
+        width
+    } else {
+        ((done.min(total) as f64 / total as f64) * width as f64) as usize
+    };
+    format!("[{}{}]", "#".repeat(filled), " ".repeat(width - filled))
+}
+
+/// Short human duration such as `45s`, `3m05s` or `1h02m`
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// UTC date and time of a Unix timestamp, e.g. `2024-03-09 14:05:00 UTC`
+pub fn format_timestamp(seconds: u64) -> String {
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brace_ranges_keep_zero_padding() {
+        assert_eq!(
+            expand_braces("model-{00001..00004}-of-00004.safetensors").unwrap(),
+            [
+                "model-00001-of-00004.safetensors",
+                "model-00002-of-00004.safetensors",
+                "model-00003-of-00004.safetensors",
+                "model-00004-of-00004.safetensors",
+            ]
+        );
+        assert_eq!(expand_braces("{8..11}").unwrap(), ["8", "9", "10", "11"]);
+        assert_eq!(expand_braces("{3..1}").unwrap(), ["3", "2", "1"]);
+        assert_eq!(expand_braces("{a..c}").unwrap(), ["a", "b", "c"]);
+        assert!(expand_braces("{1..999999}").is_err());
+    }
+
+    #[test]
+    fn brace_alternatives_and_nesting() {
+        assert_eq!(
+            expand_braces("{llama,qwen}-*/**/model.gguf").unwrap(),
+            ["llama-*/**/model.gguf", "qwen-*/**/model.gguf"]
+        );
+        assert_eq!(
+            expand_braces("{a,b{1..2}}/{x,y}").unwrap(),
+            ["a/x", "a/y", "b1/x", "b1/y", "b2/x", "b2/y"]
+        );
+        assert_eq!(expand_braces("{,.bak}").unwrap(), ["", ".bak"]);
+        // Neither a comma nor a range: kept as written
+        assert_eq!(expand_braces("run{1}/{x}").unwrap(), ["run{1}/{x}"]);
+        assert_eq!(
+            expand_braces(r"C:\models\{a,b}.gguf").unwrap(),
+            [r"C:\models\a.gguf", r"C:\models\b.gguf"]
+        );
+    }
+
+    #[test]
+    fn unbalanced_braces_are_errors() {
+        assert_eq!(
+            expand_braces("{llama,qwen-*/model.gguf"),
+            Err("1 unclosed {".to_string())
+        );
+        assert_eq!(expand_braces("{a,{b,c}"), Err("1 unclosed {".to_string()));
+        assert_eq!(
+            expand_braces("llama,qwen}/model.gguf"),
+            Err("unmatched } at position 10".to_string())
+        );
+        assert!(expand_braces("}{a,b}").is_err());
+    }
+
+    #[test]
+    fn sizes_at_unit_boundaries() {
+        let cases = [
+            (1023, "1023 B", "1.0 kB"),
+            (1024, "1.0 KiB", "1.0 kB"),
+            (999_999, "976.6 KiB", "1.0 MB"),
+            (1_048_575, "1.0 MiB", "1.0 MB"),
+            (1 << 40, "1.0 TiB", "1.1 TB"),
+            ((1 << 40) - 1, "1.0 TiB", "1.1 TB"),
+        ];
+        for (bytes, binary, si) in cases {
+            assert_eq!(format_size_in(bytes, false), binary, "{bytes} bytes");
+            assert_eq!(format_size_in(bytes, true), si, "{bytes} bytes");
+        }
+        assert_eq!(format_size_in(999, true), "999 B");
+        assert_eq!(format_size_in(1 << 60, false), "1024.0 PiB");
+    }
+
+    #[test]
+    fn sizes_parse_in_the_display_units() {
+        assert_eq!(parse_size_in("1GB", false), Ok(1 << 30));
+        assert_eq!(parse_size_in("1GB", true), Ok(1_000_000_000));
+        assert_eq!(parse_size_in("1 gib", true), Ok(1 << 30));
+        assert_eq!(parse_size_in("512 kB", true), Ok(512_000));
+        assert_eq!(parse_size_in("1.5M", false), Ok(3 << 19));
+        assert_eq!(parse_size_in("1T", true), Ok(1_000_000_000_000));
+        assert_eq!(parse_size_in("100", true), Ok(100));
+        assert_eq!(parse_size_in("100B", false), Ok(100));
+        assert!(parse_size_in("1XB", false).is_err());
+        assert!(parse_size_in("1iB", false).is_err());
+        assert!(parse_size_in("MB", false).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::copies::Prefer;
+use crate::utils::{format_thousands, model_extension};
+
+/// Directory scan settings from `-r`, `--hidden`, `--follow-symlinks` and `--ignore`
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    pub recursive: bool,
+    /// Descend into directories whose name starts with a dot
+    pub hidden: bool,
+    /// Descend into symlinked directories; cycles are still cut
+    pub follow_symlinks: bool,
+    /// Matched against each entry's name and its path below the scanned directory
+    pub ignore: Vec<glob::Pattern>,
+    /// Load one copy of a checkpoint a directory holds several of, and which
+    pub one_copy: bool,
+    pub prefer: Option<Prefer>,
+}
+
+impl WalkOptions {
+    fn ignored(&self, root: &Path, path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.ignore
+            .iter()
+            .any(|pattern| pattern.matches(&name) || pattern.matches_path(relative))
+    }
+}
+
+/// Progress line on stderr, shown only once a scan has run for a while
+struct ScanProgress {
+    dirs: usize,
+    files: usize,
+    start: Instant,
+    last_shown: Option<Instant>,
+}
+
+impl ScanProgress {
+    const DELAY: Duration = Duration::from_secs(1);
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    fn new() -> Self {
+        Self {
+            dirs: 0,
+            files: 0,
+            start: Instant::now(),
+            last_shown: None,
+        }
+    }
+
+    fn line(&self) -> String {
+        format!(
+            "scanned {} dirs, found {} model files",
+            format_thousands(self.dirs),
+            format_thousands(self.files)
+        )
+    }
+
+    fn tick(&mut self) {
+        let due = match self.last_shown {
+            Some(last) => last.elapsed() >= Self::INTERVAL,
+            None => self.start.elapsed() >= Self::DELAY,
+        };
+        if due && io::stderr().is_terminal() {
+            eprint!("\r{}", self.line());
+            let _ = io::stderr().flush();
+            self.last_shown = Some(Instant::now());
+        }
+    }
+
+    fn finish(&self) {
+        if self.last_shown.is_some() {
+            eprintln!("\r{}", self.line());
+        }
+    }
+}
+
+/// Files below `root` with one of `extensions`, sorted by path. Unreadable directories
+/// are reported and skipped rather than ending the scan.
+pub fn find_files(root: &Path, extensions: &[&str], options: &WalkOptions) -> Vec<PathBuf> {
+    let mut progress = ScanProgress::new();
+    let mut found = Vec::new();
+    // Resolved directories already queued, so a symlink back up the tree is entered once
+    let mut visited = HashSet::new();
+    if options.follow_symlinks
+        && let Ok(real) = fs::canonicalize(root)
+    {
+        visited.insert(real);
+    }
+
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        progress.dirs += 1;
+        let mut entries: Vec<fs::DirEntry> = match fs::read_dir(&dir) {
+            Ok(entries) => entries.filter_map(Result::ok).collect(),
+            Err(e) => {
+                log::warn!("Cannot read directory {}: {e}", dir.display());
+                eprintln!("Warning: Cannot read directory {}: {e}", dir.display());
+                continue;
+            }
+        };
+        entries.sort_by_key(fs::DirEntry::file_name);
+
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let path = entry.path();
+            if options.ignored(root, &path) {
+                log::debug!("Ignoring {}", path.display());
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let is_link = file_type.is_symlink();
+            // A symlink counts as what it points to; dangling ones are skipped
+            let is_dir = if is_link {
+                match fs::metadata(&path) {
+                    Ok(meta) => meta.is_dir(),
+                    Err(_) => {
+                        log::debug!("Skipping dangling symlink {}", path.display());
+                        continue;
+                    }
+                }
+            } else {
+                file_type.is_dir()
+            };
+
+            if !is_dir {
+                let ext = model_extension(&path);
+                if ext.is_some_and(|ext| extensions.contains(&ext)) {
+                    found.push(path);
+                    progress.files += 1;
+                }
+                continue;
+            }
+            if !options.recursive {
+                continue;
+            }
+            if !options.hidden && entry.file_name().to_string_lossy().starts_with('.') {
+                log::debug!("Skipping hidden directory {}", path.display());
+                continue;
+            }
+            if is_link && !options.follow_symlinks {
+                log::debug!("Not following symlinked directory {}", path.display());
+                continue;
+            }
+            if options.follow_symlinks {
+                match fs::canonicalize(&path) {
+                    Ok(real) if !visited.insert(real.clone()) => {
+                        log::warn!(
+                            "Skipping {}: {} was already scanned (symlink cycle or second link)",
+                            path.display(),
+                            real.display()
+                        );
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("Cannot resolve {}: {e}", path.display());
+                        continue;
+                    }
+                }
+            }
+            subdirs.push(path);
+        }
+        // The stack pops the last entry first, so push in reverse to walk in name order
+        pending.extend(subdirs.into_iter().rev());
+        progress.tick();
+    }
+
+    progress.finish();
+    found.sort();
+    found
+}
@@ -0,0 +1,205 @@
+//! Following the loaded files while something rewrites them, such as a training run saving
+//! checkpoints. `--watch` reloads once a changed file has settled, and every reload of the
+//! same files records what changed since the load before, for markers in the tree.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use safetensors_explorer::diff;
+use safetensors_explorer::tree::TensorInfo;
+use safetensors_explorer::utils::display_size;
+
+/// How often `--watch` looks at the files
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Name of the top-level group listing the tensors the last reload lost
+pub const REMOVED_GROUP: &str = "Removed at the last reload";
+
+/// Length and modification time of a file; None while it can't be read
+type Stamp = Option<(u64, SystemTime)>;
+
+fn stamps(files: &[PathBuf]) -> Vec<Stamp> {
+    files
+        .iter()
+        .map(|path| {
+            let metadata = fs::metadata(path).ok()?;
+            Some((metadata.len(), metadata.modified().ok()?))
+        })
+        .collect()
+}
+
+/// The files as they were when last loaded, and a change seen at the previous poll
+#[derive(Debug, Default)]
+pub struct Watcher {
+    loaded: Vec<Stamp>,
+    pending: Option<Vec<Stamp>>,
+}
+
+impl Watcher {
+    /// Remember the files as they are about to be read
+    pub fn loading(&mut self, files: &[PathBuf]) {
+        self.loaded = stamps(files);
+        self.pending = None;
+    }
+
+    /// True when the files changed since they were loaded and have stayed the same since the
+    /// previous poll, so a file still being written isn't read halfway
+    pub fn poll(&mut self, files: &[PathBuf]) -> bool {
+        let now = stamps(files);
+        if now == self.loaded {
+            self.pending = None;
+            false
+        } else if self.pending.as_ref() == Some(&now) {
+            true
+        } else {
+            self.pending = Some(now);
+            false
+        }
+    }
+}
+
+/// What the last reload did to a tensor that is still loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    New,
+    /// Bytes gained (negative when lost), and which of `dtype` and `shape` differ
+    Changed {
+        bytes: i64,
+        fields: Vec<&'static str>,
+    },
+}
+
+/// Size change with its sign, e.g. `+120.0 MiB`
+pub fn signed_size(bytes: i64, exact: bool) -> String {
+    let sign = if bytes < 0 { '-' } else { '+' };
+    format!(
+        "{sign}{}",
+        display_size(bytes.unsigned_abs() as usize, exact)
+    )
+}
+
+impl Change {
+    /// Marker shown after the tensor's name
+    pub fn marker(&self, exact: bool) -> String {
+        match self {
+            Change::New => "new".to_string(),
+            Change::Changed { bytes, fields } => {
+                let mut parts = Vec::new();
+                if *bytes != 0 {
+                    parts.push(signed_size(*bytes, exact));
+                }
+                if !fields.is_empty() {
+                    parts.push(format!("{} changed", fields.join("/")));
+                }
+                parts.join(", ")
+            }
+        }
+    }
+}
+
+/// Tensors that differ between the last two loads of the same files; empty after a reload
+/// that changed nothing
+#[derive(Debug, Default)]
+pub struct ReloadChanges {
+    pub changed: HashMap<String, Change>,
+    /// Tensors gone since the load before, as they were
+    pub removed: Vec<TensorInfo>,
+    removed_names: HashSet<String>,
+    /// Bytes the files gained in all
+    bytes: i64,
+}
+
+impl ReloadChanges {
+    /// Compare two loads tensor by tensor, as `--diff` does, adding size changes
+    pub fn between(before: &[TensorInfo], after: &[TensorInfo]) -> Self {
+        let diff = diff::diff_models(before, after, None);
+        let previous: HashMap<&str, &TensorInfo> =
+            before.iter().map(|t| (t.name.as_str(), t)).collect();
+        let mut fields: HashMap<&str, Vec<&'static str>> = HashMap::new();
+        for change in &diff.changed {
+            fields.entry(&change.name).or_default().push(change.field);
+        }
+
+        let mut changed: HashMap<String, Change> = diff
+            .added
+            .iter()
+            .map(|name| (name.clone(), Change::New))
+            .collect();
+        for tensor in after {
+            let Some(old) = previous.get(tensor.name.as_str()) else {
+                continue;
+            };
+            let bytes = tensor.size_bytes as i64 - old.size_bytes as i64;
+            let fields = fields.remove(tensor.name.as_str()).unwrap_or_default();
+            if bytes != 0 || !fields.is_empty() {
+                changed.insert(tensor.name.clone(), Change::Changed { bytes, fields });
+            }
+        }
+        let removed_names: HashSet<String> = diff.removed.into_iter().collect();
+        let total =
+            |tensors: &[TensorInfo]| -> i64 { tensors.iter().map(|t| t.size_bytes as i64).sum() };
+        Self {
+            changed,
+            removed: before
+                .iter()
+                .filter(|t| removed_names.contains(&t.name))
+                .cloned()
+                .collect(),
+            removed_names,
+            bytes: total(after) - total(before),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn tensor(&self, name: &str) -> Option<&Change> {
+        self.changed.get(name)
+    }
+
+    /// Whether a row names a tensor the last reload lost
+    pub fn is_removed(&self, name: &str) -> bool {
+        self.removed_names.contains(name)
+    }
+
+    /// How many of `tensors` changed, and the bytes they gained together
+    pub fn group_delta(&self, tensors: &[&TensorInfo]) -> Option<(usize, i64)> {
+        let mut count = 0;
+        let mut bytes = 0;
+        for tensor in tensors {
+            match self.changed.get(&tensor.name) {
+                Some(Change::New) => bytes += tensor.size_bytes as i64,
+                Some(Change::Changed { bytes: delta, .. }) => bytes += delta,
+                None => continue,
+            }
+            count += 1;
+        }
+        (count > 0).then_some((count, bytes))
+    }
+
+    /// Counts for the status line, e.g. "3 changed, 1 new, 2 removed (+120.0 MiB)"
+    pub fn summary(&self, exact: bool) -> String {
+        if self.is_empty() {
+            return "nothing changed".to_string();
+        }
+        let new = self.changed.values().filter(|c| **c == Change::New).count();
+        let counts = [
+            (self.changed.len() - new, "changed"),
+            (new, "new"),
+            (self.removed.len(), "removed"),
+        ];
+        let mut summary = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, what)| format!("{count} {what}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.bytes != 0 {
+            summary.push_str(&format!(" ({})", signed_size(self.bytes, exact)));
+        }
+        summary
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::scan::{CHUNK_SIZE, Progress, ScanStatus, WorkerStatus};
+use crate::tree::TensorInfo;
+
+/// Bytes of one tensor handled by a single job. Ranges start at multiples of this,
+/// so tensors of equal size are always split the same way.
+pub const RANGE_SIZE: usize = 64 << 20;
+
+/// Upper bound on read buffer memory across all workers; each holds one `CHUNK_SIZE` buffer
+const MAX_READ_BUFFERS: usize = 64 << 20;
+
+/// How often the progress sink is polled while no worker reports anything
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A byte range of one tensor, processed by one worker
+#[derive(Debug)]
+pub struct Job<'a> {
+    pub tensor: &'a TensorInfo,
+    /// Position of the tensor in the slice passed to [`split_jobs`]
+    pub tensor_index: usize,
+    pub start: usize,
+    pub len: usize,
+}
+
+/// Split every tensor into jobs of at most `RANGE_SIZE` bytes, in tensor order
+pub fn split_jobs<'a>(tensors: &[&'a TensorInfo]) -> Vec<Job<'a>> {
+    let mut jobs = Vec::new();
+    for (tensor_index, &tensor) in tensors.iter().enumerate() {
+        let mut start = 0;
+        loop {
+            let len = RANGE_SIZE.min(tensor.size_bytes - start);
+            jobs.push(Job {
+                tensor,
+                tensor_index,
+                start,
+                len,
+            });
+            start += len;
+            if start >= tensor.size_bytes {
+                break;
+            }
+        }
+    }
+    jobs
+}
+
+/// Worker threads to use: one per core, limited by the read buffer budget
+pub fn worker_count() -> usize {
+    thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .clamp(1, MAX_READ_BUFFERS / CHUNK_SIZE)
+}
+
+/// Results of [`run_jobs`], indexed like the jobs; None for jobs that never finished
+pub struct JobResults<A> {
+    pub results: Vec<Option<A>>,
+    pub cancelled: bool,
+}
+
+enum Message<A> {
+    Started { worker: usize, job: usize },
+    Read { worker: usize, bytes: u64 },
+    Finished { job: usize, result: Result<A> },
+}
+
+/// Run `jobs` on `workers` threads pulling from a shared queue. Each job starts from
+/// `init(job)` and is fed its bytes in order, chunk by chunk; results come back through
+/// a channel so `progress` is only ever called from this thread.
+pub fn run_jobs<A: Send>(
+    jobs: &[Job],
+    workers: usize,
+    progress: &mut dyn Progress,
+    init: impl Fn(&Job) -> A + Sync,
+    feed: impl Fn(&mut A, &[u8]) + Sync,
+) -> Result<JobResults<A>> {
+    let workers = workers.clamp(1, jobs.len().max(1));
+    let total: u64 = jobs.iter().map(|job| job.len as u64).sum();
+    let next_job = AtomicUsize::new(0);
+    let cancel = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+
+    let mut results: Vec<Option<A>> = jobs.iter().map(|_| None).collect();
+    let mut status: Vec<WorkerStatus> = (0..workers).map(|_| WorkerStatus::default()).collect();
+    let mut done = 0u64;
+    let mut error = None;
+    let started = Instant::now();
+
+    thread::scope(|scope| {
+        for worker in 0..workers {
+            let tx = tx.clone();
+            let (next_job, cancel, init, feed) = (&next_job, &cancel, &init, &feed);
+            scope.spawn(move || {
+                let mut buffer = vec![0u8; CHUNK_SIZE];
+                while !cancel.load(Ordering::Relaxed) {
+                    let index = next_job.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(index) else {
+                        break;
+                    };
+                    let _ = tx.send(Message::Started { worker, job: index });
+                    let result = run_job(job, &mut buffer, cancel, init, feed, |bytes| {
+                        let _ = tx.send(Message::Read { worker, bytes });
+                    });
+                    // A job cut short by cancellation has no usable result
+                    if !cancel.load(Ordering::Relaxed) || result.is_err() {
+                        let _ = tx.send(Message::Finished { job: index, result });
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        loop {
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(Message::Started { worker, job }) => {
+                    status[worker].current = Some(jobs[job].tensor.name.clone());
+                }
+                Ok(Message::Read { worker, bytes }) => {
+                    status[worker].bytes += bytes;
+                    done += bytes;
+                }
+                Ok(Message::Finished { job, result }) => match result {
+                    Ok(value) => results[job] = Some(value),
+                    Err(e) => {
+                        error.get_or_insert(e);
+                        cancel.store(true, Ordering::Relaxed);
+                    }
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            let snapshot = ScanStatus {
+                done,
+                total,
+                elapsed: started.elapsed(),
+                workers: &status,
+            };
+            if !cancel.load(Ordering::Relaxed) && !progress.update_workers(&snapshot) {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+
+    if let Some(e) = error {
+        return Err(e);
+    }
+    Ok(JobResults {
+        results,
+        cancelled: cancel.load(Ordering::Relaxed),
+    })
+}
+
+fn run_job<A>(
+    job: &Job,
+    buffer: &mut [u8],
+    cancel: &AtomicBool,
+    init: &impl Fn(&Job) -> A,
+    feed: &impl Fn(&mut A, &[u8]),
+    mut on_read: impl FnMut(u64),
+) -> Result<A> {
+    let tensor = job.tensor;
+    let offset = tensor.data_location()?;
+    let mut file = File::open(&tensor.source_file)
+        .with_context(|| format!("Failed to open file: {}", tensor.source_file.display()))?;
+    file.seek(SeekFrom::Start(offset + job.start as u64))?;
+
+    let mut state = init(job);
+    let mut remaining = job.len;
+    while remaining > 0 && !cancel.load(Ordering::Relaxed) {
+        let len = buffer.len().min(remaining);
+        file.read_exact(&mut buffer[..len])
+            .with_context(|| format!("Failed to read data of tensor {}", tensor.name))?;
+        feed(&mut state, &buffer[..len]);
+        remaining -= len;
+        on_read(len as u64);
+    }
+    Ok(state)
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result, bail};
+use flate2::read::DeflateDecoder;
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_EOCD_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+/// EOCD record size plus the longest possible archive comment
+const EOCD_SEARCH_LEN: u64 = 22 + u16::MAX as u64;
+
+pub const METHOD_STORED: u16 = 0;
+pub const METHOD_DEFLATED: u16 = 8;
+
+/// One member of a zip archive, as listed in the central directory
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    pub name: String,
+    pub compression: u16,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    local_header_offset: u64,
+}
+
+/// Zip archive whose central directory has been read; member data stays on disk
+pub struct ZipArchive {
+    path: PathBuf,
+    pub entries: Vec<ZipEntry>,
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(buf: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap())
+}
+
+/// Whether the file starts with a zip local file header
+pub fn is_zip(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| u32::from_le_bytes(magic) == LOCAL_HEADER_SIGNATURE)
+}
+
+impl ZipArchive {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+        let file_len = file.metadata()?.len();
+
+        // The end-of-central-directory record sits at the end, before an optional comment
+        let tail_len = file_len.min(EOCD_SEARCH_LEN);
+        let mut tail = vec![0u8; tail_len as usize];
+        file.seek(SeekFrom::Start(file_len - tail_len))?;
+        file.read_exact(&mut tail)?;
+        let eocd = (0..tail.len().saturating_sub(21))
+            .rev()
+            .find(|&pos| u32_at(&tail, pos) == EOCD_SIGNATURE)
+            .with_context(|| format!("Not a zip archive: {}", path.display()))?;
+
+        let mut entry_count = u16_at(&tail, eocd + 10) as u64;
+        let mut directory_size = u32_at(&tail, eocd + 12) as u64;
+        let mut directory_offset = u32_at(&tail, eocd + 16) as u64;
+
+        // Archives over 4 GB (common for checkpoints) keep the real values in a ZIP64 record
+        if eocd >= 20 && u32_at(&tail, eocd - 20) == ZIP64_EOCD_LOCATOR_SIGNATURE {
+            let record_offset = u64_at(&tail, eocd - 20 + 8);
+            let mut record = [0u8; 56];
+            file.seek(SeekFrom::Start(record_offset))?;
+            file.read_exact(&mut record)?;
+            if u32_at(&record, 0) != ZIP64_EOCD_SIGNATURE {
+                bail!("Corrupt ZIP64 directory in {}", path.display());
+            }
+            entry_count = u64_at(&record, 32);
+            directory_size = u64_at(&record, 40);
+            directory_offset = u64_at(&record, 48);
+        }
+
+        // Sizes straight from the file would otherwise ask for allocations of up to 16 EiB
+        if directory_offset
+            .checked_add(directory_size)
+            .is_none_or(|end| end > file_len)
+        {
+            bail!(
+                "Corrupt zip directory in {}: {directory_size} bytes at offset {directory_offset} run past the end of the {file_len}-byte file",
+                path.display()
+            );
+        }
+        let mut directory = vec![0u8; directory_size as usize];
+        file.seek(SeekFrom::Start(directory_offset))?;
+        file.read_exact(&mut directory)
+            .with_context(|| format!("Failed to read zip directory: {}", path.display()))?;
+
+        // Each entry takes at least a 46-byte header
+        let mut entries = Vec::with_capacity(entry_count.min(directory_size / 46) as usize);
+        let mut pos = 0;
+        for _ in 0..entry_count {
+            if pos + 46 > directory.len() || u32_at(&directory, pos) != CENTRAL_HEADER_SIGNATURE {
+                bail!("Corrupt zip directory in {}", path.display());
+            }
+            let compression = u16_at(&directory, pos + 10);
+            let mut compressed_size = u32_at(&directory, pos + 20) as u64;
+            let mut uncompressed_size = u32_at(&directory, pos + 24) as u64;
+            let name_len = u16_at(&directory, pos + 28) as usize;
+            let extra_len = u16_at(&directory, pos + 30) as usize;
+            let comment_len = u16_at(&directory, pos + 32) as usize;
+            let mut local_header_offset = u32_at(&directory, pos + 42) as u64;
+
+            let name_start = pos + 46;
+            let extra_start = name_start + name_len;
+            let next = extra_start + extra_len + comment_len;
+            if next > directory.len() {
+                bail!("Corrupt zip directory in {}", path.display());
+            }
+            let name = String::from_utf8_lossy(&directory[name_start..extra_start]).into_owned();
+
+            // ZIP64 extra field: only the values saturated in the header are present, in order
+            let mut extra = &directory[extra_start..extra_start + extra_len];
+            while extra.len() >= 4 {
+                let id = u16_at(extra, 0);
+                let len = (u16_at(extra, 2) as usize).min(extra.len() - 4);
+                if id == ZIP64_EXTRA_ID {
+                    let mut values = extra[4..4 + len].chunks_exact(8).map(|c| u64_at(c, 0));
+                    for field in [
+                        &mut uncompressed_size,
+                        &mut compressed_size,
+                        &mut local_header_offset,
+                    ] {
+                        if *field == u32::MAX as u64
+                            && let Some(value) = values.next()
+                        {
+                            *field = value;
+                        }
+                    }
+                }
+                extra = &extra[4 + len..];
+            }
+
+            entries.push(ZipEntry {
+                name,
+                compression,
+                compressed_size,
+                uncompressed_size,
+                local_header_offset,
+            });
+            pos = next;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Absolute file offset of the member's (possibly compressed) data
+    pub fn data_offset(&self, entry: &ZipEntry) -> Result<u64> {
+        let mut file = File::open(&self.path)?;
+        let mut header = [0u8; 30];
+        file.seek(SeekFrom::Start(entry.local_header_offset))?;
+        file.read_exact(&mut header)?;
+        if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE {
+            bail!(
+                "Corrupt zip entry {} in {}",
+                entry.name,
+                self.path.display()
+            );
+        }
+        let name_len = u16_at(&header, 26) as u64;
+        let extra_len = u16_at(&header, 28) as u64;
+        Ok(entry.local_header_offset + 30 + name_len + extra_len)
+    }
+
+    /// Stream a member's uncompressed bytes
+    pub fn reader(&self, entry: &ZipEntry) -> Result<Box<dyn Read>> {
+        let offset = self.data_offset(entry)?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let raw = file.take(entry.compressed_size);
+        match entry.compression {
+            METHOD_STORED => Ok(Box::new(raw)),
+            METHOD_DEFLATED => Ok(Box::new(DeflateDecoder::new(raw))),
+            method => bail!(
+                "Unsupported compression method {method} for {} in {}",
+                entry.name,
+                self.path.display()
+            ),
+        }
+    }
+
+    /// Read a whole member into memory
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.uncompressed_size as usize);
+        self.reader(entry)?
+            .read_to_end(&mut data)
+            .with_context(|| {
+                format!("Failed to read {} from {}", entry.name, self.path.display())
+            })?;
+        Ok(data)
+    }
+}
@@ -0,0 +1,73 @@
+//! Column alignment by display width: tensor names with CJK characters and emoji take two
+//! columns per character, and cuts never split a character from its variation selector.
+
+use safetensors_explorer::utils::{
+    display_width, fit_to_width, truncate_start_to_width, truncate_to_width,
+};
+
+const NAMES: &[&str] = &[
+    "model.layers.0.self_attn.q_proj.weight",
+    "模型.层.0.注意力.权重",
+    "model.嵌入.weight",
+    "🏷️.tag.bias",
+];
+
+#[test]
+fn fitted_names_fill_exactly_the_column() {
+    for name in NAMES {
+        for width in [0, 1, 2, 3, 8, 15, 16, 40, 60] {
+            for align_right in [false, true] {
+                let fitted = fit_to_width(name, width, align_right);
+                assert_eq!(
+                    display_width(&fitted),
+                    width,
+                    "{name:?} fitted to {width} gave {fitted:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rows_with_cjk_names_line_up() {
+    let rows: Vec<String> = NAMES
+        .iter()
+        .map(|name| format!("{} F16", fit_to_width(name, 20, false)))
+        .collect();
+    for row in &rows {
+        assert_eq!(display_width(row), 24, "{row:?}");
+        assert!(row.ends_with(" F16"));
+    }
+}
+
+#[test]
+fn cuts_stay_within_the_width() {
+    for name in NAMES {
+        for width in 0..=display_width(name) + 1 {
+            let end = truncate_to_width(name, width);
+            let start = truncate_start_to_width(name, width);
+            assert!(display_width(&end) <= width, "{end:?} wider than {width}");
+            assert!(
+                display_width(&start) <= width,
+                "{start:?} wider than {width}"
+            );
+        }
+    }
+}
+
+#[test]
+fn cjk_names_cut_on_character_boundaries() {
+    // Five two-column characters don't fit in 9 columns: four and the ellipsis do
+    assert_eq!(truncate_to_width("模型权重矩阵", 9), "模型权重…");
+    assert_eq!(truncate_start_to_width("模型权重矩阵", 9), "…权重矩阵");
+    assert_eq!(fit_to_width("模型权重矩阵", 10, false), "模型权重… ");
+}
+
+#[test]
+fn variation_selectors_stay_with_their_character() {
+    let cut = truncate_to_width("🏷️🏷️🏷️", 5);
+    assert_eq!(cut, "🏷️🏷️…");
+    assert_eq!(display_width(&cut), 5);
+    let cut = truncate_start_to_width("a🏷️b", 3);
+    assert_eq!(cut, "…b");
+}
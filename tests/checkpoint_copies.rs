@@ -0,0 +1,148 @@
+//! Directories holding two copies of one checkpoint load one of them, while shards under
+//! other names (`model_part_0`) or listed by a non-standard index all load.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Tensor names listed for `dir`, and what was printed to stderr
+fn list(dir: &Path, extra: &[&str]) -> (Vec<String>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_safetensors_explorer"))
+        .arg(dir)
+        .args(["--format", "csv"])
+        .args(extra)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let names = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split(',').nth(1).map(str::to_string))
+        .collect();
+    (names, String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+/// Mistral layout: `consolidated.safetensors` next to HF shards of the same weights
+fn mistral_repo(test: &str) -> PathBuf {
+    let dir = common::fixture_dir("copies", test);
+    common::write_safetensors(
+        &dir.join("consolidated.safetensors"),
+        &[
+            ("tok_embeddings.weight", &[64, 16]),
+            ("output.weight", &[64, 16]),
+        ],
+        &[],
+    );
+    common::write_safetensors(
+        &dir.join("model-00001-of-00002.safetensors"),
+        &[("model.embed_tokens.weight", &[64, 16])],
+        &[],
+    );
+    common::write_safetensors(
+        &dir.join("model-00002-of-00002.safetensors"),
+        &[("lm_head.weight", &[64, 16])],
+        &[],
+    );
+    dir
+}
+
+#[test]
+fn copies_load_the_sharded_one_by_default() {
+    let dir = mistral_repo("default");
+    let (names, stderr) = list(&dir, &[]);
+    assert_eq!(names, ["lm_head.weight", "model.embed_tokens.weight"]);
+    assert!(stderr.contains("2 copies"), "{stderr}");
+    assert!(
+        stderr.contains("ignoring consolidated.safetensors"),
+        "{stderr}"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn prefer_picks_the_consolidated_copy() {
+    let dir = mistral_repo("prefer");
+    let (names, stderr) = list(&dir, &["--prefer", "consolidated"]);
+    assert_eq!(names, ["output.weight", "tok_embeddings.weight"]);
+    assert!(
+        stderr.contains("ignoring model*.safetensors (2 files)"),
+        "{stderr}"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn variants_with_the_same_names_are_copies() {
+    let dir = common::fixture_dir("copies", "variants");
+    common::write_safetensors(
+        &dir.join("diffusion_pytorch_model.safetensors"),
+        &[("conv_in.weight", &[8, 8])],
+        &[],
+    );
+    common::write_safetensors(
+        &dir.join("diffusion_pytorch_model.fp16.safetensors"),
+        &[("conv_in.weight", &[8, 8]), ("conv_out.weight", &[8, 8])],
+        &[],
+    );
+    let (names, stderr) = list(&dir, &[]);
+    assert_eq!(names, ["conv_in.weight"]);
+    assert!(stderr.contains("same tensor names"), "{stderr}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn part_named_shards_all_load() {
+    let dir = common::fixture_dir("copies", "parts");
+    common::write_safetensors(
+        &dir.join("model_part_0.safetensors"),
+        &[("a.weight", &[8, 8])],
+        &[],
+    );
+    common::write_safetensors(
+        &dir.join("model_part_1.safetensors"),
+        &[("b.weight", &[8, 8])],
+        &[],
+    );
+    let (names, stderr) = list(&dir, &[]);
+    assert_eq!(names, ["a.weight", "b.weight"]);
+    assert!(!stderr.contains("copies"), "{stderr}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn any_safetensors_index_lists_the_shards() {
+    let dir = common::fixture_dir("copies", "index");
+    common::write_safetensors(
+        &dir.join("consolidated-00001-of-00002.safetensors"),
+        &[("a.weight", &[8, 8])],
+        &[],
+    );
+    common::write_safetensors(
+        &dir.join("consolidated-00002-of-00002.safetensors"),
+        &[("b.weight", &[8, 8])],
+        &[],
+    );
+    common::write_safetensors(
+        &dir.join("stray.safetensors"),
+        &[("stray.weight", &[8, 8])],
+        &[],
+    );
+    let index = serde_json::json!({
+        "metadata": {},
+        "weight_map": {
+            "a.weight": "consolidated-00001-of-00002.safetensors",
+            "b.weight": "consolidated-00002-of-00002.safetensors",
+        },
+    });
+    fs::write(
+        dir.join("consolidated.safetensors.index.json"),
+        index.to_string(),
+    )
+    .unwrap();
+    let (names, _) = list(&dir, &[]);
+    assert_eq!(names, ["a.weight", "b.weight"]);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,145 @@
+//! Fixture helpers shared by the integration tests. Each test binary uses some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use safetensors_explorer::gguf::GGMLType;
+
+/// Write a safetensors file holding each tensor's dtype, shape and raw bytes, with
+/// `metadata` as its `__metadata__` when given
+pub fn write_safetensors_bytes(
+    path: &Path,
+    tensors: &[(&str, &str, &[usize], Vec<u8>)],
+    metadata: &[(&str, &str)],
+) {
+    let mut header = serde_json::Map::new();
+    if !metadata.is_empty() {
+        let entries = metadata
+            .iter()
+            .map(|(key, value)| (key.to_string(), serde_json::Value::from(*value)))
+            .collect();
+        header.insert("__metadata__".into(), serde_json::Value::Object(entries));
+    }
+    let mut data: Vec<u8> = Vec::new();
+    for (name, dtype, shape, bytes) in tensors {
+        header.insert(
+            name.to_string(),
+            serde_json::json!({
+                "dtype": dtype,
+                "shape": shape,
+                "data_offsets": [data.len(), data.len() + bytes.len()],
+            }),
+        );
+        data.extend(bytes);
+    }
+    let header = serde_json::to_vec(&serde_json::Value::Object(header)).unwrap();
+    let mut content = (header.len() as u64).to_le_bytes().to_vec();
+    content.extend(header);
+    content.extend(data);
+    fs::write(path, content).unwrap();
+}
+
+/// Write a safetensors file with zero-filled F32 tensors of the given shapes
+pub fn write_safetensors(path: &Path, tensors: &[(&str, &[usize])], metadata: &[(&str, &str)]) {
+    let tensors: Vec<_> = tensors
+        .iter()
+        .map(|&(name, shape)| {
+            (
+                name,
+                "F32",
+                shape,
+                vec![0; shape.iter().product::<usize>() * 4],
+            )
+        })
+        .collect();
+    write_safetensors_bytes(path, &tensors, metadata);
+}
+
+/// A fresh directory for one test's fixtures; `suite` keeps test binaries apart
+pub fn fixture_dir(suite: &str, test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "safetensors_explorer_{suite}_{test}_{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Metadata value of a GGUF fixture
+#[derive(Clone, Copy)]
+pub enum GgufValue<'a> {
+    U32(u32),
+    I32(i32),
+    Bool(bool),
+    Str(&'a str),
+    Strs(&'a [&'a str]),
+    I32s(&'a [i32]),
+}
+
+fn gguf_string(out: &mut Vec<u8>, text: &str) {
+    out.extend((text.len() as u64).to_le_bytes());
+    out.extend(text.as_bytes());
+}
+
+/// Write a GGUF v3 file with the given metadata and zero-filled tensors of a ggml type such
+/// as `F32` or `IQ4_XS`, whose dims are in ggml order (fastest-varying first)
+pub fn write_gguf(path: &Path, metadata: &[(&str, GgufValue)], tensors: &[(&str, &str, &[u64])]) {
+    let mut out = b"GGUF".to_vec();
+    out.extend(3u32.to_le_bytes());
+    out.extend((tensors.len() as u64).to_le_bytes());
+    out.extend((metadata.len() as u64).to_le_bytes());
+    for (key, value) in metadata {
+        gguf_string(&mut out, key);
+        match value {
+            GgufValue::U32(v) => {
+                out.extend(4u32.to_le_bytes());
+                out.extend(v.to_le_bytes());
+            }
+            GgufValue::I32(v) => {
+                out.extend(5u32.to_le_bytes());
+                out.extend(v.to_le_bytes());
+            }
+            GgufValue::Bool(v) => {
+                out.extend(7u32.to_le_bytes());
+                out.push(*v as u8);
+            }
+            GgufValue::Str(text) => {
+                out.extend(8u32.to_le_bytes());
+                gguf_string(&mut out, text);
+            }
+            GgufValue::Strs(items) => {
+                out.extend(9u32.to_le_bytes());
+                out.extend(8u32.to_le_bytes());
+                out.extend((items.len() as u64).to_le_bytes());
+                for item in *items {
+                    gguf_string(&mut out, item);
+                }
+            }
+            GgufValue::I32s(items) => {
+                out.extend(9u32.to_le_bytes());
+                out.extend(5u32.to_le_bytes());
+                out.extend((items.len() as u64).to_le_bytes());
+                for item in *items {
+                    out.extend(item.to_le_bytes());
+                }
+            }
+        }
+    }
+    let mut offset = 0u64;
+    for (name, ggml_type, dims) in tensors {
+        let ggml_type = GGMLType::from_name(ggml_type).unwrap();
+        gguf_string(&mut out, name);
+        out.extend((dims.len() as u32).to_le_bytes());
+        for dim in *dims {
+            out.extend(dim.to_le_bytes());
+        }
+        out.extend((ggml_type as u32).to_le_bytes());
+        out.extend(offset.to_le_bytes());
+        let bytes = ggml_type.tensor_bytes(dims.iter().product()).unwrap();
+        offset += bytes.next_multiple_of(32);
+    }
+    out.resize(out.len().next_multiple_of(32) + offset as usize, 0);
+    fs::write(path, out).unwrap();
+}
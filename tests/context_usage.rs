@@ -0,0 +1,130 @@
+//! Prompt token counts through the chat templates and BPE pre-tokenizers of Llama 3 and
+//! Qwen 2, against the counts llama.cpp gives with the full vocabularies. The fixtures
+//! carry only the tokens these prompts use, each of which is a single token in the real
+//! vocabulary, so the counts depend on the template and the pre-tokenizer alone.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::GgufValue;
+use safetensors_explorer::model::load_model;
+use safetensors_explorer::prompt::{ContextUsage, PromptCounter};
+
+/// `tokenizer.chat_template` of Meta-Llama-3-8B-Instruct
+const LLAMA3_TEMPLATE: &str = "{% set loop_messages = messages %}{% for message in loop_messages %}{% set content = '<|start_header_id|>' + message['role'] + '<|end_header_id|>\n\n'+ message['content'] | trim + '<|eot_id|>' %}{% if loop.index0 == 0 %}{% set content = bos_token + content %}{% endif %}{{ content }}{% endfor %}{% if add_generation_prompt %}{{ '<|start_header_id|>assistant<|end_header_id|>\n\n' }}{% endif %}";
+
+/// `tokenizer.chat_template` of Qwen2-7B-Instruct
+const QWEN2_TEMPLATE: &str = "{% for message in messages %}{% if loop.first and messages[0]['role'] != 'system' %}{{ '<|im_start|>system\nYou are a helpful assistant.<|im_end|>\n' }}{% endif %}{{'<|im_start|>' + message['role'] + '\n' + message['content'] + '<|im_end|>' + '\n'}}{% endfor %}{% if add_generation_prompt %}{{ '<|im_start|>assistant\n' }}{% endif %}";
+
+/// Words of the prompts, spelled as byte-level BPE vocabularies spell them (`Ġ` a space,
+/// `Ċ` a newline)
+const WORDS: &[&str] = &[
+    "Ġ",
+    "Ċ",
+    "ĊĊ",
+    "You",
+    "Ġare",
+    "Ġa",
+    "Ġhelpful",
+    "Ġassistant",
+    "Hello",
+    "Ġworld",
+    "system",
+    "user",
+    "assistant",
+];
+
+/// Write a GGUF holding a BPE tokenizer of the printable ASCII characters, `WORDS` and the
+/// control tokens `specials`, the first of which is BOS
+fn tokenizer_gguf(path: &Path, pre: &str, specials: &[&str], add_bos: bool, template: &str) {
+    let ascii: Vec<String> = ('!'..='~').map(String::from).collect();
+    let mut tokens: Vec<&str> = ascii.iter().map(String::as_str).collect();
+    tokens.extend(WORDS);
+    let bos = tokens.len() as u32;
+    tokens.extend(specials);
+    let types: Vec<i32> = (0..tokens.len())
+        .map(|id| if id < bos as usize { 1 } else { 3 })
+        .collect();
+    common::write_gguf(
+        path,
+        &[
+            ("general.architecture", GgufValue::Str("llama")),
+            ("llama.context_length", GgufValue::U32(8192)),
+            ("tokenizer.ggml.model", GgufValue::Str("gpt2")),
+            ("tokenizer.ggml.pre", GgufValue::Str(pre)),
+            ("tokenizer.ggml.tokens", GgufValue::Strs(&tokens)),
+            ("tokenizer.ggml.token_type", GgufValue::I32s(&types)),
+            ("tokenizer.ggml.merges", GgufValue::Strs(&[])),
+            ("tokenizer.ggml.bos_token_id", GgufValue::U32(bos)),
+            ("tokenizer.ggml.add_bos_token", GgufValue::Bool(add_bos)),
+            ("tokenizer.chat_template", GgufValue::Str(template)),
+        ],
+        &[],
+    );
+}
+
+fn usage(path: &Path, system: &str, user: &str) -> ContextUsage {
+    let model = load_model(&[path]).unwrap();
+    PromptCounter::from_metadata(&model.metadata)
+        .unwrap()
+        .count(system, user)
+}
+
+#[test]
+fn llama3_prompt_counts() {
+    let dir = common::fixture_dir("context_usage", "llama3");
+    let path = dir.join("llama3.gguf");
+    tokenizer_gguf(
+        &path,
+        "llama-bpe",
+        &[
+            "<|begin_of_text|>",
+            "<|start_header_id|>",
+            "<|end_header_id|>",
+            "<|eot_id|>",
+        ],
+        true,
+        LLAMA3_TEMPLATE,
+    );
+    let chat = usage(&path, "You are a helpful assistant.", "Hello");
+    // A run of spaces before a word leaves its last space to the word: `Hello`, ` `, ` world`
+    let spaced = usage(&path, "", "Hello  world");
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(chat.template_applied, "{:?}", chat.template_note);
+    assert_eq!(chat.system_tokens, 6);
+    assert_eq!(chat.user_tokens, 1);
+    // <|begin_of_text|>, three role headers of four tokens with their `\n\n` and two
+    // <|eot_id|>
+    assert_eq!(chat.prompt_tokens, 22);
+    assert_eq!(chat.template_tokens, 15);
+    assert_eq!(chat.context_length, Some(8192));
+    assert_eq!(spaced.user_tokens, 3);
+    assert_eq!(spaced.prompt_tokens, 3 + 10);
+}
+
+#[test]
+fn qwen2_prompt_counts() {
+    let dir = common::fixture_dir("context_usage", "qwen2");
+    let path = dir.join("qwen2.gguf");
+    tokenizer_gguf(
+        &path,
+        "qwen2",
+        &["<|endoftext|>", "<|im_start|>", "<|im_end|>"],
+        false,
+        QWEN2_TEMPLATE,
+    );
+    let chat = usage(&path, "You are a helpful assistant.", "Hello");
+    // Without a system message the template adds its default one, counted as template
+    let default_system = usage(&path, "", "Hello");
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(chat.template_applied, "{:?}", chat.template_note);
+    assert_eq!(chat.system_tokens, 6);
+    assert_eq!(chat.user_tokens, 1);
+    assert_eq!(chat.prompt_tokens, 20);
+    assert_eq!(default_system.prompt_tokens, 20);
+    assert_eq!(default_system.template_tokens, 19);
+}
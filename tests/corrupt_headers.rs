@@ -0,0 +1,236 @@
+//! Headers whose sizes, offsets or shapes don't add up are load failures naming what is
+//! wrong, never a panic or an allocation of whatever size the file claims.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use safetensors_explorer::model::ModelDescription;
+
+/// Write a safetensors file with `header` as its JSON and `data` after it, as given
+fn write_raw_safetensors(path: &Path, header: serde_json::Value, data: &[u8]) {
+    let header = serde_json::to_vec(&header).unwrap();
+    let mut content = (header.len() as u64).to_le_bytes().to_vec();
+    content.extend(header);
+    content.extend(data);
+    fs::write(path, content).unwrap();
+}
+
+/// Write a GGUF v3 file with no metadata and one F32 tensor record as given, followed by
+/// 32 bytes of data
+fn write_raw_gguf(path: &Path, dims: &[u64], offset: u64) {
+    let mut out = b"GGUF".to_vec();
+    out.extend(3u32.to_le_bytes());
+    out.extend(1u64.to_le_bytes());
+    out.extend(0u64.to_le_bytes());
+    out.extend(6u64.to_le_bytes());
+    out.extend(b"weight");
+    out.extend((dims.len() as u32).to_le_bytes());
+    for dim in dims {
+        out.extend(dim.to_le_bytes());
+    }
+    out.extend(0u32.to_le_bytes());
+    out.extend(offset.to_le_bytes());
+    out.resize(out.len().next_multiple_of(32) + 32, 0);
+    fs::write(path, out).unwrap();
+}
+
+/// Write a zip archive with no members whose ZIP64 end record claims a central directory of
+/// `directory_size` bytes holding `entries` entries
+fn write_zip64_claiming(path: &Path, entries: u64, directory_size: u64) {
+    let mut out = b"PK\x03\x04".to_vec();
+    out.extend([0; 4]);
+    let record_offset = out.len() as u64;
+    let mut record = [0u8; 56];
+    record[..4].copy_from_slice(&0x0606_4b50u32.to_le_bytes());
+    record[32..40].copy_from_slice(&entries.to_le_bytes());
+    record[40..48].copy_from_slice(&directory_size.to_le_bytes());
+    out.extend(record);
+    out.extend(0x0706_4b50u32.to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+    out.extend(record_offset.to_le_bytes());
+    out.extend(1u32.to_le_bytes());
+    out.extend(0x0605_4b50u32.to_le_bytes());
+    out.extend([0; 6]);
+    out.extend(u16::MAX.to_le_bytes());
+    out.extend(u32::MAX.to_le_bytes());
+    out.extend(u32::MAX.to_le_bytes());
+    out.extend([0; 2]);
+    fs::write(path, out).unwrap();
+}
+
+/// Write an `.npy` file with the given header dict and `data` after it
+fn write_raw_npy(path: &Path, dict: &str, data: &[u8]) {
+    let mut header = dict.to_string();
+    while !(10 + header.len() + 1).is_multiple_of(64) {
+        header.push(' ');
+    }
+    header.push('\n');
+    let mut out = b"\x93NUMPY\x01\x00".to_vec();
+    out.extend((header.len() as u16).to_le_bytes());
+    out.extend(header.as_bytes());
+    out.extend(data);
+    fs::write(path, out).unwrap();
+}
+
+fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Write an ONNX model whose graph holds one F32 initializer with the given dims and no data
+fn write_raw_onnx(path: &Path, dims: &[u64]) {
+    let mut initializer = Vec::new();
+    for &dim in dims {
+        initializer.push(1 << 3);
+        varint(&mut initializer, dim);
+    }
+    initializer.extend([2 << 3, 1, 8 << 3 | 2, 6]);
+    initializer.extend(b"weight");
+    let mut graph = vec![5 << 3 | 2];
+    varint(&mut graph, initializer.len() as u64);
+    graph.extend(initializer);
+    let mut model = vec![7 << 3 | 2];
+    varint(&mut model, graph.len() as u64);
+    model.extend(graph);
+    fs::write(path, model).unwrap();
+}
+
+/// Load `corrupt` next to a valid file, returning the error chain of its failure
+fn failure_of(dir: &Path, corrupt: PathBuf) -> String {
+    let good = dir.join("good.safetensors");
+    common::write_safetensors(&good, &[("lm_head.weight", &[2, 2])], &[]);
+    let model = ModelDescription::load_partial(&[good, corrupt.clone()]).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+    assert_eq!(model.tensors.len(), 1);
+    assert_eq!(model.failures.len(), 1);
+    assert_eq!(model.failures[0].file, corrupt);
+    model.failures[0].chain.join(": ")
+}
+
+#[test]
+fn safetensors_offsets_must_follow_on() {
+    let dir = common::fixture_dir("corrupt", "reversed");
+    let path = dir.join("model.safetensors");
+    write_raw_safetensors(
+        &path,
+        serde_json::json!({
+            "a": {"dtype": "F32", "shape": [1], "data_offsets": [0, 4]},
+            "b": {"dtype": "F32", "shape": [1], "data_offsets": [4, 0]},
+        }),
+        &[0; 8],
+    );
+    let chain = failure_of(&dir, path);
+    assert!(chain.contains("Tensor b"), "{chain}");
+}
+
+#[test]
+fn safetensors_shape_must_match_its_bytes() {
+    let dir = common::fixture_dir("corrupt", "shape");
+    let path = dir.join("model.safetensors");
+    write_raw_safetensors(
+        &path,
+        serde_json::json!({
+            "a": {"dtype": "F32", "shape": [4294967296u64, 4294967296u64, 16], "data_offsets": [0, 4]},
+        }),
+        &[0; 4],
+    );
+    let chain = failure_of(&dir, path);
+    assert!(chain.contains("Tensor a has shape"), "{chain}");
+
+    let dir = common::fixture_dir("corrupt", "size");
+    let path = dir.join("model.safetensors");
+    write_raw_safetensors(
+        &path,
+        serde_json::json!({
+            "a": {"dtype": "F32", "shape": [2], "data_offsets": [0, 4]},
+        }),
+        &[0; 4],
+    );
+    let chain = failure_of(&dir, path);
+    assert!(chain.contains("Tensor a holds 4 bytes"), "{chain}");
+}
+
+#[test]
+fn gguf_tensor_records_must_fit_in_a_file() {
+    let dir = common::fixture_dir("corrupt", "gguf_dims");
+    let path = dir.join("model.gguf");
+    write_raw_gguf(&path, &[4294967296, 4294967296, 16], 0);
+    let chain = failure_of(&dir, path);
+    assert!(
+        chain.contains("GGUF tensor info 0 starting at offset 24"),
+        "{chain}"
+    );
+    assert!(chain.contains("Tensor weight has dimensions"), "{chain}");
+
+    let dir = common::fixture_dir("corrupt", "gguf_offset");
+    let path = dir.join("model.gguf");
+    write_raw_gguf(&path, &[8], u64::MAX - 4);
+    let chain = failure_of(&dir, path);
+    assert!(
+        chain.contains("GGUF tensor info 0 starting at offset 24"),
+        "{chain}"
+    );
+    assert!(
+        chain.contains("end past the largest file offset"),
+        "{chain}"
+    );
+}
+
+#[test]
+fn zip_directory_must_lie_within_the_archive() {
+    let dir = common::fixture_dir("corrupt", "zip64");
+    let path = dir.join("model.zip");
+    write_zip64_claiming(&path, 1, 1 << 40);
+    let chain = failure_of(&dir, path);
+    assert!(
+        chain.contains("1099511627776 bytes at offset 0 run past the end"),
+        "{chain}"
+    );
+
+    // A directory that fits can't be made to reserve room for more entries than it holds
+    let dir = common::fixture_dir("corrupt", "zip64_entries");
+    let path = dir.join("model.zip");
+    write_zip64_claiming(&path, u64::MAX, 46);
+    let chain = failure_of(&dir, path);
+    assert!(chain.contains("Corrupt zip directory"), "{chain}");
+}
+
+#[test]
+fn npy_shape_must_fit_in_the_file() {
+    let dir = common::fixture_dir("corrupt", "npy_shape");
+    let path = dir.join("weight.npy");
+    write_raw_npy(
+        &path,
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (4294967296, 4294967296, 16), }",
+        &[0; 4],
+    );
+    let chain = failure_of(&dir, path);
+    assert!(chain.contains("Array weight has shape"), "{chain}");
+
+    let dir = common::fixture_dir("corrupt", "npy_size");
+    let path = dir.join("weight.npy");
+    write_raw_npy(
+        &path,
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (4, 4), }",
+        &[0; 32],
+    );
+    let chain = failure_of(&dir, path);
+    assert!(
+        chain.contains("Array weight needs 64 bytes after its"),
+        "{chain}"
+    );
+}
+
+#[test]
+fn onnx_dims_must_be_countable() {
+    let dir = common::fixture_dir("corrupt", "onnx");
+    let path = dir.join("model.onnx");
+    write_raw_onnx(&path, &[1 << 40, 1 << 40]);
+    let chain = failure_of(&dir, path);
+    assert!(chain.contains("Initializer weight has dims"), "{chain}");
+}
@@ -0,0 +1,143 @@
+//! Exit status contract of `--diff` on two safetensors checkpoints:
+//! 0 identical, 1 only metadata differs, 2 tensors differ. Names can be translated with
+//! `--name-map` before they are compared.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn diff(a: &Path, b: &Path, extra: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_safetensors_explorer"))
+        .arg("--diff")
+        .arg(a)
+        .arg(b)
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+const BASE: &[(&str, &[usize])] = &[
+    ("model.embed_tokens.weight", &[32, 8]),
+    ("lm_head.weight", &[32, 8]),
+];
+
+#[test]
+fn identical_checkpoints_exit_0() {
+    let dir = common::fixture_dir("diff", "identical");
+    let (a, b) = (dir.join("a.safetensors"), dir.join("b.safetensors"));
+    common::write_safetensors(&a, BASE, &[("format", "pt")]);
+    common::write_safetensors(&b, BASE, &[("format", "pt")]);
+
+    let output = diff(&a, &b, &["--format", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["identical_count"], 2);
+    assert_eq!(report["added"], serde_json::json!([]));
+    assert_eq!(report["removed"], serde_json::json!([]));
+    assert_eq!(report["changed"], serde_json::json!([]));
+}
+
+#[test]
+fn metadata_only_difference_exits_1_unless_ignored() {
+    let dir = common::fixture_dir("diff", "metadata");
+    let (a, b) = (dir.join("a.safetensors"), dir.join("b.safetensors"));
+    common::write_safetensors(&a, BASE, &[("format", "pt")]);
+    common::write_safetensors(&b, BASE, &[("format", "np")]);
+
+    let output = diff(&a, &b, &["--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report["changed"],
+        serde_json::json!([{"name": "format", "field": "metadata", "a": "pt", "b": "np"}])
+    );
+
+    let output = diff(&a, &b, &["--ignore-metadata"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn tensor_differences_exit_2() {
+    let dir = common::fixture_dir("diff", "tensors");
+    let (a, b) = (dir.join("a.safetensors"), dir.join("b.safetensors"));
+    common::write_safetensors(&a, BASE, &[]);
+    common::write_safetensors(
+        &b,
+        &[
+            ("model.embed_tokens.weight", &[64, 8]),
+            ("lm_head.weight", &[32, 8]),
+            ("model.norm.weight", &[8]),
+        ],
+        &[],
+    );
+
+    let output = diff(&a, &b, &["--format", "json"]);
+    assert_eq!(output.status.code(), Some(2));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["added"], serde_json::json!(["model.norm.weight"]));
+    assert_eq!(
+        report["changed"],
+        serde_json::json!([{
+            "name": "model.embed_tokens.weight",
+            "field": "shape",
+            "a": "(32, 8)",
+            "b": "(64, 8)",
+        }])
+    );
+    assert_eq!(report["identical_count"], 1);
+}
+
+#[test]
+fn ignored_tensors_are_left_out() {
+    let dir = common::fixture_dir("diff", "ignore");
+    let (a, b) = (dir.join("a.safetensors"), dir.join("b.safetensors"));
+    common::write_safetensors(&a, BASE, &[]);
+    common::write_safetensors(&b, &[("model.embed_tokens.weight", &[32, 8])], &[]);
+
+    assert_eq!(diff(&a, &b, &[]).status.code(), Some(2));
+    let output = diff(&a, &b, &["--ignore-tensor", "lm_head.*"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn name_map_translates_names_and_reports_unused_rules() {
+    let dir = common::fixture_dir("diff", "name_map");
+    let (a, b) = (dir.join("a.safetensors"), dir.join("b.safetensors"));
+    common::write_safetensors(&a, BASE, &[]);
+    common::write_safetensors(
+        &b,
+        &[
+            ("transformer.wte.weight", &[32, 8]),
+            ("lm_head.weight", &[32, 8]),
+        ],
+        &[],
+    );
+    let map = dir.join("map.json");
+    fs::write(
+        &map,
+        r#"{"rules": [
+            {"pattern": "^transformer\\.wte\\.", "replacement": "model.embed_tokens."},
+            {"pattern": "^transformer\\.ln_f\\.", "replacement": "model.norm."}
+        ]}"#,
+    )
+    .unwrap();
+
+    assert_eq!(diff(&a, &b, &[]).status.code(), Some(2));
+    let output = diff(&a, &b, &["--name-map", map.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("rules[1]"), "{stderr}");
+    assert!(!stderr.contains("rules[0]"), "{stderr}");
+
+    // A replacement naming a group the pattern lacks is refused up front
+    fs::write(
+        &map,
+        r#"{"rules": [{"pattern": "^h\\.(\\d+)", "replacement": "layers.$1_x"}]}"#,
+    )
+    .unwrap();
+    let output = diff(&a, &b, &["--name-map", map.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1_x"));
+}
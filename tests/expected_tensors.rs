@@ -0,0 +1,129 @@
+//! Tensor names of a GGUF checked against the set llama.cpp loads for its architecture:
+//! dropped biases and misnamed norms are reported per name with their layers, and either
+//! form of a fused projection meets the table.
+
+use safetensors_explorer::expected::{Gap, checklist};
+
+/// `blk.N.<role>` for every layer and role, after the names outside the layers
+fn names(global: &[&str], roles: &[&str], layers: usize) -> Vec<String> {
+    let mut names: Vec<String> = global.iter().map(|name| name.to_string()).collect();
+    for layer in 0..layers {
+        names.extend(roles.iter().map(|role| format!("blk.{layer}.{role}")));
+    }
+    names
+}
+
+fn gap_names(gaps: &[Gap]) -> Vec<(&str, &[usize])> {
+    gaps.iter()
+        .map(|gap| (gap.name.as_str(), gap.layers.as_slice()))
+        .collect()
+}
+
+#[test]
+fn dropped_biases_and_misnamed_norm() {
+    let names = names(
+        &["token_embd.weight", "output_norm.weight"],
+        &[
+            "attn_norm.weight",
+            "attn_q.weight",
+            "attn_k.weight",
+            "attn_v.weight",
+            "attn_output.weight",
+            "post_attention_norm.weight",
+            "ffn_gate.weight",
+            "ffn_up.weight",
+            "ffn_down.weight",
+        ],
+        2,
+    );
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let found = checklist("qwen2", 2, &names).expect("qwen2 has a table");
+    assert_eq!(
+        gap_names(&found.missing),
+        [
+            ("blk.*.attn_q.bias", &[0, 1][..]),
+            ("blk.*.attn_k.bias", &[0, 1][..]),
+            ("blk.*.attn_v.bias", &[0, 1][..]),
+            ("blk.*.ffn_norm.weight", &[0, 1][..]),
+        ]
+    );
+    assert_eq!(
+        gap_names(&found.unexpected),
+        [("blk.*.post_attention_norm.weight", &[0, 1][..])]
+    );
+    assert_eq!(
+        found.unexpected[0].example.as_deref(),
+        Some("blk.0.post_attention_norm.weight")
+    );
+}
+
+#[test]
+fn fused_or_separate_projections_and_extra_layers() {
+    let roles = [
+        "attn_norm.weight",
+        "attn_qkv.weight",
+        "attn_output.weight",
+        "ffn_norm.weight",
+        "ffn_up.weight",
+        "ffn_down.weight",
+    ];
+    let global = ["token_embd.weight", "output_norm.weight", "output.weight"];
+    let fused = names(&global, &roles, 3);
+    let fused: Vec<&str> = fused.iter().map(String::as_str).collect();
+    let found = checklist("phi3", 3, &fused).expect("phi3 has a table");
+    assert!(found.missing.is_empty() && found.unexpected.is_empty());
+    assert_eq!(found.required, 3 + 3 * 6);
+
+    // Separate projections meet the table as well; a layer past the block count doesn't
+    let mut split = names(&global, &roles, 2);
+    for layer in 0..2 {
+        split.retain(|name| *name != format!("blk.{layer}.attn_qkv.weight"));
+        for role in ["attn_q.weight", "attn_k.weight", "attn_v.weight"] {
+            split.push(format!("blk.{layer}.{role}"));
+        }
+    }
+    split.push("blk.2.attn_norm.weight".to_string());
+    let split: Vec<&str> = split.iter().map(String::as_str).collect();
+    let found = checklist("phi3", 2, &split).expect("phi3 has a table");
+    assert!(found.missing.is_empty());
+    assert_eq!(
+        gap_names(&found.unexpected),
+        [("blk.*.attn_norm.weight", &[2][..])]
+    );
+
+    assert!(checklist("mamba", 2, &split).is_none());
+}
+
+#[test]
+fn truncated_tensors_are_named_apart() {
+    let roles = [
+        "attn_norm.weight",
+        "attn_q.weight",
+        "attn_k.weight",
+        "attn_v.weight",
+        "attn_output.weight",
+        "ffn_norm.weight",
+        "ffn_gate.weight",
+        "ffn_up.weight",
+        "ffn_down.weight",
+        "post_attention_norm.weight",
+    ];
+    let names = names(&["token_embd.weight", "output_norm.weight"], &roles, 2);
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let found = checklist("llama", 2, &names).expect("llama has a table");
+    assert!(found.missing.is_empty());
+
+    // The file ends inside layer 1: its tensors are in the header but have no data
+    let cut_off = &names[2 + roles.len() + 7..];
+    assert_eq!(
+        gap_names(&found.expected_among(cut_off)),
+        [
+            ("blk.*.ffn_up.weight", &[1][..]),
+            ("blk.*.ffn_down.weight", &[1][..]),
+        ]
+    );
+    assert_eq!(
+        found.expected_among(&names[..1])[0].example.as_deref(),
+        Some("token_embd.weight")
+    );
+}
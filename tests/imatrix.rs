@@ -0,0 +1,117 @@
+//! The summary's Imatrix section, from the `quantize.imatrix.*` keys llama-quantize writes,
+//! and the warning for IQ quants made without an importance matrix.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::GgufValue;
+use safetensors_explorer::model::{ModelDescription, load_model};
+use safetensors_explorer::summary::build_summary;
+
+/// llama.cpp's `LLAMA_FTYPE_MOSTLY_IQ4_XS` and `LLAMA_FTYPE_MOSTLY_Q4_K_M`
+const FTYPE_IQ4_XS: u32 = 30;
+const FTYPE_Q4_K_M: u32 = 15;
+
+/// Keys `llama-quantize --imatrix` adds to the file, as it types them
+const IMATRIX_KEYS: &[(&str, GgufValue)] = &[
+    (
+        "quantize.imatrix.file",
+        GgufValue::Str("/models/imatrix/Meta-Llama-3.1-8B-Instruct.imatrix"),
+    ),
+    (
+        "quantize.imatrix.dataset",
+        GgufValue::Str("/training_dir/calibration_datav3.txt"),
+    ),
+    ("quantize.imatrix.entries_count", GgufValue::I32(224)),
+    ("quantize.imatrix.chunks_count", GgufValue::I32(125)),
+];
+
+fn quant(path: &Path, file_type: u32, ggml_type: &str, imatrix: &[(&str, GgufValue)]) {
+    let mut metadata = vec![
+        ("general.architecture", GgufValue::Str("llama")),
+        ("general.name", GgufValue::Str("Meta Llama 3.1 8B Instruct")),
+        ("general.file_type", GgufValue::U32(file_type)),
+        ("general.quantization_version", GgufValue::U32(2)),
+    ];
+    metadata.extend(imatrix);
+    common::write_gguf(
+        path,
+        &metadata,
+        &[
+            ("token_embd.weight", "Q6_K", &[256, 8]),
+            ("blk.0.attn_q.weight", ggml_type, &[256, 256]),
+            ("blk.0.ffn_down.weight", ggml_type, &[256, 256]),
+            ("blk.0.attn_norm.weight", "F32", &[256]),
+        ],
+    );
+}
+
+fn load(
+    test: &str,
+    file_type: u32,
+    ggml_type: &str,
+    imatrix: &[(&str, GgufValue)],
+) -> (ModelDescription, PathBuf) {
+    let dir = common::fixture_dir("imatrix", test);
+    let path = dir.join("model.gguf");
+    quant(&path, file_type, ggml_type, imatrix);
+    let model = load_model(&[&path]).unwrap();
+    (model, path)
+}
+
+/// Rows of the summary's Imatrix section, if it has one
+fn imatrix_rows(model: &ModelDescription, path: &Path) -> Option<Vec<(String, String)>> {
+    build_summary(model, &[path.to_path_buf()])
+        .into_iter()
+        .find(|section| section.title == "Imatrix")
+        .map(|section| section.rows)
+}
+
+fn warns_about_imatrix(model: &ModelDescription) -> bool {
+    model
+        .warnings
+        .iter()
+        .any(|warning| warning.contains("no quantize.imatrix.* metadata"))
+}
+
+#[test]
+fn imatrix_keys_become_labeled_rows() {
+    let (model, path) = load("keys", FTYPE_IQ4_XS, "IQ4_XS", IMATRIX_KEYS);
+    let rows = imatrix_rows(&model, &path);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    let rows = rows.expect("an Imatrix section");
+    let rows: Vec<(&str, &str)> = rows.iter().map(|(l, v)| (l.as_str(), v.as_str())).collect();
+    assert_eq!(
+        rows,
+        [
+            ("File", "/models/imatrix/Meta-Llama-3.1-8B-Instruct.imatrix"),
+            ("Dataset", "/training_dir/calibration_datav3.txt"),
+            ("Entries", "224"),
+            ("Chunks", "125"),
+        ]
+    );
+    assert!(!warns_about_imatrix(&model), "{:?}", model.warnings);
+}
+
+#[test]
+fn iq_quant_without_imatrix_warns() {
+    let (model, path) = load("missing", FTYPE_IQ4_XS, "IQ4_XS", &[]);
+    let rows = imatrix_rows(&model, &path);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    let rows = rows.expect("an Imatrix section");
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0, "None recorded");
+    assert!(rows[0].1.starts_with("2 IQ tensors"), "{}", rows[0].1);
+    assert!(warns_about_imatrix(&model), "{:?}", model.warnings);
+}
+
+#[test]
+fn k_quant_without_imatrix_is_quiet() {
+    let (model, path) = load("k_quant", FTYPE_Q4_K_M, "Q4_K", &[]);
+    let rows = imatrix_rows(&model, &path);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert_eq!(rows, None);
+    assert!(!warns_about_imatrix(&model), "{:?}", model.warnings);
+}
@@ -0,0 +1,78 @@
+//! Path arguments are brace-expanded before globbing, so alternatives combine with `*` and
+//! `**`; the parts that match nothing and unbalanced braces are reported on stderr.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use common::GgufValue;
+
+/// Tensor names listed for the path arguments, and what was printed to stderr
+fn list(args: &[String]) -> (Vec<String>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_safetensors_explorer"))
+        .args(args)
+        .args(["--format", "csv"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    let names = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split(',').nth(1).map(str::to_string))
+        .collect();
+    (names, String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+fn model(path: &Path, tensor: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    common::write_gguf(
+        path,
+        &[("general.architecture", GgufValue::Str("llama"))],
+        &[(tensor, "F32", &[8, 4])],
+    );
+}
+
+#[test]
+fn braces_combine_with_globstar() {
+    let dir = common::fixture_dir("patterns", "globstar");
+    model(&dir.join("llama-7b/run/final/model.gguf"), "llama.weight");
+    model(&dir.join("llama-7b/other.gguf"), "other.weight");
+    // `**` also matches no directory at all
+    model(&dir.join("qwen-1b/model.gguf"), "qwen.weight");
+
+    let pattern = format!("{}/{{llama,qwen,mistral}}-*/**/model.gguf", dir.display());
+    let (mut names, stderr) = list(&[pattern]);
+    names.sort();
+    // Files from several folders get their folder as a prefix
+    assert_eq!(
+        names,
+        ["llama-7b.run.final.llama.weight", "qwen-1b.qwen.weight"]
+    );
+    assert!(
+        stderr.contains(&format!(
+            "Pattern matched no files: {}/mistral-*/**/model.gguf",
+            dir.display()
+        )),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("llama-*"), "{stderr}");
+
+    let missing = format!("{}/{{gone,qwen-1b}}/model.gguf", dir.display());
+    let unbalanced = format!("{}/{{llama,qwen-*/model.gguf", dir.display());
+    let (names, stderr) = list(&[missing, unbalanced.clone()]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(names, ["qwen.weight"]);
+    assert!(
+        stderr.contains(&format!(
+            "Path does not exist: {}/gone/model.gguf",
+            dir.display()
+        )),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(&format!("Skipping {unbalanced}: 1 unclosed {{")),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,84 @@
+//! `--peek` reads single elements by multi-dimensional or flat index, decoding the dtype
+//! from the element's own bytes, and rejects indices outside the shape with its bounds.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn fixture(test: &str) -> PathBuf {
+    let path = common::fixture_dir("peek", test).join("model.safetensors");
+    let bf16: Vec<u8> = [0x3fc0u16, 0xc000, 0, 0, 0, 0x4040]
+        .iter()
+        .flat_map(|bits| bits.to_le_bytes())
+        .collect();
+    let i16: Vec<u8> = [7i16, -5].iter().flat_map(|v| v.to_le_bytes()).collect();
+    common::write_safetensors_bytes(
+        &path,
+        &[
+            ("embed", "BF16", &[2, 3], bf16),
+            ("scale", "F8_E4M3", &[4], vec![0x38, 0xc4, 0x01, 0x7f]),
+            ("ids", "I16", &[2], i16),
+        ],
+        &[],
+    );
+    path
+}
+
+fn peek(path: &Path, specs: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_safetensors_explorer"));
+    command.arg(path);
+    for spec in specs {
+        command.args(["--peek", spec]);
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn reads_elements_by_index() {
+    let path = fixture("index");
+    let output = peek(
+        &path,
+        &[
+            "embed[0,0]",
+            "embed[0][1]",
+            "embed[-1, -1]",
+            "embed[4]",
+            "scale[1]",
+            "scale[2]",
+            "scale[3]",
+            "ids[1]",
+        ],
+    );
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "embed[0, 0]\t1.5\n\
+         embed[0, 1]\t-2\n\
+         embed[1, 2]\t3\n\
+         embed[1, 1]\t0\n\
+         scale[1]\t-3\n\
+         scale[2]\t0.001953125\n\
+         scale[3]\tNaN\n\
+         ids[1]\t-5\n"
+    );
+}
+
+#[test]
+fn rejects_indices_outside_the_shape() {
+    let path = fixture("bounds");
+    let out_of_range = peek(&path, &["embed[2,0]"]);
+    let flat = peek(&path, &["embed[6]"]);
+    let missing = peek(&path, &["lm_head[0]"]);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    let stderr = String::from_utf8(out_of_range.stderr).unwrap();
+    assert!(!out_of_range.status.success());
+    assert!(stderr.contains("Index 2 is out of range for dimension 0 of shape (2, 3)"));
+    assert!(stderr.contains("valid indices are 0 to 1"));
+    let stderr = String::from_utf8(flat.stderr).unwrap();
+    assert!(stderr.contains("the tensor has 6 elements, valid indices are 0 to 5"));
+    assert_eq!(missing.status.code(), Some(3));
+}
@@ -0,0 +1,113 @@
+//! Tensor roles by name across the HF and llama.cpp conventions, and names no pattern
+//! covers falling through to `Other`.
+
+use safetensors_explorer::role::{TensorRole, classify};
+
+fn assert_roles(cases: &[(&str, TensorRole)]) {
+    for (name, role) in cases {
+        assert_eq!(classify(name), *role, "{name}");
+    }
+}
+
+#[test]
+fn hf_llama_names() {
+    assert_roles(&[
+        ("model.embed_tokens.weight", TensorRole::Embedding),
+        (
+            "model.layers.0.self_attn.q_proj.weight",
+            TensorRole::Attention,
+        ),
+        (
+            "model.layers.0.self_attn.o_proj.weight",
+            TensorRole::Attention,
+        ),
+        ("model.layers.0.self_attn.q_proj.bias", TensorRole::Bias),
+        ("model.layers.0.self_attn.q_norm.weight", TensorRole::Norm),
+        (
+            "model.layers.0.self_attn.rotary_emb.inv_freq",
+            TensorRole::Buffer,
+        ),
+        ("model.layers.0.mlp.gate_proj.weight", TensorRole::Mlp),
+        ("model.layers.0.mlp.down_proj.qweight", TensorRole::Mlp),
+        ("model.layers.0.input_layernorm.weight", TensorRole::Norm),
+        (
+            "model.layers.0.post_attention_layernorm.weight",
+            TensorRole::Norm,
+        ),
+        (
+            "model.layers.3.block_sparse_moe.gate.weight",
+            TensorRole::Mlp,
+        ),
+        (
+            "model.layers.3.block_sparse_moe.experts.7.w2.weight",
+            TensorRole::Mlp,
+        ),
+        ("model.norm.weight", TensorRole::Norm),
+        ("lm_head.weight", TensorRole::LmHead),
+    ]);
+}
+
+#[test]
+fn gguf_names() {
+    assert_roles(&[
+        ("token_embd.weight", TensorRole::Embedding),
+        ("blk.0.attn_q.weight", TensorRole::Attention),
+        ("blk.0.attn_qkv.bias", TensorRole::Bias),
+        ("blk.0.attn_output.weight", TensorRole::Attention),
+        ("blk.0.attn_norm.weight", TensorRole::Norm),
+        ("blk.0.ffn_norm.weight", TensorRole::Norm),
+        ("blk.0.ffn_gate_inp.weight", TensorRole::Mlp),
+        ("blk.0.ffn_down_exps.weight", TensorRole::Mlp),
+        ("rope_freqs.weight", TensorRole::Buffer),
+        ("output_norm.weight", TensorRole::Norm),
+        ("output.weight", TensorRole::LmHead),
+    ]);
+}
+
+#[test]
+fn gpt2_neox_and_bert_names() {
+    assert_roles(&[
+        ("transformer.wte.weight", TensorRole::Embedding),
+        ("transformer.wpe.weight", TensorRole::Embedding),
+        ("transformer.h.0.attn.c_attn.weight", TensorRole::Attention),
+        ("transformer.h.0.attn.c_attn.bias", TensorRole::Bias),
+        ("transformer.h.0.attn.bias", TensorRole::Buffer),
+        ("transformer.h.0.attn.masked_bias", TensorRole::Buffer),
+        ("transformer.h.0.mlp.c_fc.weight", TensorRole::Mlp),
+        ("transformer.h.0.ln_1.weight", TensorRole::Norm),
+        ("transformer.ln_f.bias", TensorRole::Norm),
+        (
+            "gpt_neox.layers.0.attention.query_key_value.weight",
+            TensorRole::Attention,
+        ),
+        (
+            "gpt_neox.layers.0.mlp.dense_h_to_4h.weight",
+            TensorRole::Mlp,
+        ),
+        ("embed_out.weight", TensorRole::LmHead),
+        (
+            "bert.embeddings.token_type_embeddings.weight",
+            TensorRole::Embedding,
+        ),
+        ("bert.embeddings.LayerNorm.weight", TensorRole::Norm),
+        (
+            "bert.encoder.layer.0.attention.self.query.weight",
+            TensorRole::Attention,
+        ),
+        (
+            "bert.encoder.layer.0.intermediate.dense.weight",
+            TensorRole::Mlp,
+        ),
+    ]);
+}
+
+#[test]
+fn unknown_names_are_other() {
+    assert_roles(&[
+        ("bert.pooler.dense.weight", TensorRole::Other),
+        ("classifier.weight", TensorRole::Other),
+        ("conv_in.weight", TensorRole::Other),
+        ("mystery", TensorRole::Other),
+        ("", TensorRole::Other),
+    ]);
+}
@@ -0,0 +1,123 @@
+//! Trees of generated names: single-child chains collapse into one group, nesting stops at
+//! the depth limit under a `…` group, and too many top-level prefixes give a flat list.
+
+use std::time::{Duration, Instant};
+
+use safetensors_explorer::tree::{DEEP_GROUP_NAME, TensorInfo, TreeBuilder, TreeLimits, TreeNode};
+
+fn tensor(name: &str) -> TensorInfo {
+    TensorInfo::new(name, "F32", vec![1], 4, "model.safetensors")
+}
+
+fn tensors(names: impl IntoIterator<Item = String>) -> Vec<TensorInfo> {
+    names.into_iter().map(|name| tensor(&name)).collect()
+}
+
+fn group_names(nodes: &[TreeNode]) -> Vec<&str> {
+    nodes
+        .iter()
+        .filter(|node| matches!(node, TreeNode::Group { .. }))
+        .map(TreeNode::name)
+        .collect()
+}
+
+fn children<'a>(nodes: &'a [TreeNode], name: &str) -> &'a [TreeNode] {
+    match nodes.iter().find(|node| node.name() == name) {
+        Some(TreeNode::Group { children, .. }) => children,
+        _ => panic!("no group {name} among {:?}", group_names(nodes)),
+    }
+}
+
+/// Levels of groups below `nodes`
+fn depth(nodes: &[TreeNode]) -> usize {
+    nodes
+        .iter()
+        .map(|node| match node {
+            TreeNode::Group { children, .. } => 1 + depth(children),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[test]
+fn single_child_chains_become_one_group() {
+    let tree = TreeBuilder::build_tree(
+        &tensors(
+            [
+                "model.vision_tower.vision_model.encoder.layers.0.q.weight",
+                "model.vision_tower.vision_model.encoder.layers.1.q.weight",
+                "model.vision_tower.vision_model.post_layernorm.weight",
+                "model.embed_tokens.weight",
+                "a.b.c.weight",
+            ]
+            .map(String::from),
+        ),
+        TreeLimits::default(),
+    );
+    assert_eq!(group_names(&tree), ["a.b.c", "model"]);
+    let model = children(&tree, "model");
+    assert_eq!(
+        group_names(model),
+        ["embed_tokens", "vision_tower.vision_model"]
+    );
+    let vision = children(model, "vision_tower.vision_model");
+    assert_eq!(group_names(vision), ["encoder.layers", "post_layernorm"]);
+    assert_eq!(
+        group_names(children(vision, "encoder.layers")),
+        ["0.q", "1.q"]
+    );
+}
+
+#[test]
+fn deep_names_stop_at_the_depth_limit() {
+    let names = (0..50_000).map(|i| {
+        format!(
+            "export.graph.block{}.sub{}.seq.{}.a.b.c.d.e.f.g.leaf{i}.weight",
+            i % 50,
+            i % 7,
+            i % 3
+        )
+    });
+    let limits = TreeLimits::new(4, TreeLimits::default().max_top_level);
+    let started = Instant::now();
+    let tree = TreeBuilder::build_tree(&tensors(names), limits);
+    let flattened = TreeBuilder::flatten_tree(&tree);
+    assert!(started.elapsed() < Duration::from_secs(30));
+
+    // export.graph / block0 / sub0.seq / 0.a.b.c.d.e.f.g, then the leaves under `…`
+    assert_eq!(depth(&tree), 5);
+    let deepest = children(
+        children(
+            children(children(&tree, "export.graph"), "block0"),
+            "sub0.seq",
+        ),
+        "0.a.b.c.d.e.f.g",
+    );
+    assert_eq!(group_names(deepest), [DEEP_GROUP_NAME]);
+    let listed = children(deepest, DEEP_GROUP_NAME);
+    assert!(
+        listed
+            .iter()
+            .all(|node| matches!(node, TreeNode::Tensor { .. }))
+    );
+    assert_eq!(
+        listed[0].name(),
+        "export.graph.block0.sub0.seq.0.a.b.c.d.e.f.g.leaf0.weight"
+    );
+    assert!(flattened.len() < 100);
+}
+
+#[test]
+fn too_many_top_level_prefixes_give_a_flat_list() {
+    let names = (0..50_000).map(|i| format!("key{i}.weight"));
+    let tree = TreeBuilder::build_tree(&tensors(names), TreeLimits::default());
+    assert_eq!(tree.len(), 50_000);
+    assert!(group_names(&tree).is_empty());
+    assert_eq!(tree[0].name(), "key0.weight");
+    assert_eq!(tree[10].name(), "key10.weight");
+
+    let names = (0..1000).map(|i| format!("key{i}.weight"));
+    let tree = TreeBuilder::build_tree(&tensors(names), TreeLimits::default());
+    assert_eq!(group_names(&tree).len(), 1000);
+}